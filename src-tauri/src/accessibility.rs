@@ -17,6 +17,30 @@ pub struct AccessibilityConfig {
     pub audio_cues: AudioCueSettings,
     pub alternative_text: bool,
     pub skip_links: bool,
+    #[serde(default)]
+    pub command_announcements: CommandAnnouncementSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandAnnouncementSettings {
+    pub enabled: bool,
+    /// Only commands running at least this long are announced
+    pub min_duration_ms: u64,
+    /// Placeholders: `{command}`, `{exit_code}`, `{duration}` (seconds)
+    pub message_template: String,
+    /// Minimum gap between two command announcements
+    pub throttle_ms: u64,
+}
+
+impl Default for CommandAnnouncementSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_duration_ms: 10_000,
+            message_template: "command {command} finished with exit code {exit_code}".to_string(),
+            throttle_ms: 2_000,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -80,6 +104,439 @@ pub enum ShortcutContext {
     Debug,
 }
 
+type Matrix3 = [[f64; 3]; 3];
+
+/// Linear RGB to LMS cone space (Fidaner, Lin & Ozguven daltonization).
+const RGB_TO_LMS: Matrix3 = [
+    [17.8824, 43.5161, 4.11935],
+    [3.45565, 27.1554, 3.86714],
+    [0.0299566, 0.184309, 1.46709],
+];
+
+const LMS_TO_RGB: Matrix3 = [
+    [0.0809444479, -0.130504409, 0.116721066],
+    [-0.0102485335, 0.0540193266, -0.113614708],
+    [-0.000365296938, -0.00412161469, 0.693511405],
+];
+
+const PROTANOPIA_LMS: Matrix3 = [[0.0, 2.02344, -2.52581], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+const DEUTERANOPIA_LMS: Matrix3 = [[1.0, 0.0, 0.0], [0.494207, 0.0, 1.24827], [0.0, 0.0, 1.0]];
+const TRITANOPIA_LMS: Matrix3 = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [-0.395913, 0.801109, 0.0]];
+
+/// Moves the information lost by a dichromat into channels they can still distinguish.
+const ERROR_SHIFT: Matrix3 = [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]];
+
+/// How much of the full dichromat simulation applies to the anomalous (weak) variants.
+const ANOMALY_SEVERITY: f64 = 0.6;
+
+fn apply_matrix(m: &Matrix3, v: [f64; 3]) -> [f64; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+fn srgb_to_linear(c: u8) -> f64 {
+    let c = c as f64 / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f64) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    (c * 255.0).round() as u8
+}
+
+/// How a color appears to someone with `color_blind_type`, in linear RGB.
+fn simulate_linear(rgb: [f64; 3], color_blind_type: &ColorBlindType) -> [f64; 3] {
+    let (matrix, severity) = match color_blind_type {
+        ColorBlindType::Protanopia => (&PROTANOPIA_LMS, 1.0),
+        ColorBlindType::Deuteranopia => (&DEUTERANOPIA_LMS, 1.0),
+        ColorBlindType::Tritanopia => (&TRITANOPIA_LMS, 1.0),
+        ColorBlindType::Protanomaly => (&PROTANOPIA_LMS, ANOMALY_SEVERITY),
+        ColorBlindType::Deuteranomaly => (&DEUTERANOPIA_LMS, ANOMALY_SEVERITY),
+        ColorBlindType::Tritanomaly => (&TRITANOPIA_LMS, ANOMALY_SEVERITY),
+        ColorBlindType::Monochromacy => {
+            let luminance = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+            return [luminance; 3];
+        }
+        ColorBlindType::None => return rgb,
+    };
+    let simulated = apply_matrix(&LMS_TO_RGB, apply_matrix(matrix, apply_matrix(&RGB_TO_LMS, rgb)));
+    [0, 1, 2].map(|i| rgb[i] + (simulated[i] - rgb[i]) * severity)
+}
+
+/// Daltonize a color: simulate the deficiency, then shift the lost difference into
+/// distinguishable channels. Monochromacy has no remaining hue channel to shift into,
+/// so colors are returned unchanged for it.
+pub fn daltonize_color(rgb: (u8, u8, u8), color_blind_type: &ColorBlindType) -> (u8, u8, u8) {
+    if matches!(color_blind_type, ColorBlindType::None | ColorBlindType::Monochromacy) {
+        return rgb;
+    }
+    let linear = [srgb_to_linear(rgb.0), srgb_to_linear(rgb.1), srgb_to_linear(rgb.2)];
+    let simulated = simulate_linear(linear, color_blind_type);
+    let error = [0, 1, 2].map(|i| linear[i] - simulated[i]);
+    let shift = apply_matrix(&ERROR_SHIFT, error);
+    (
+        linear_to_srgb(linear[0] + shift[0]),
+        linear_to_srgb(linear[1] + shift[1]),
+        linear_to_srgb(linear[2] + shift[2]),
+    )
+}
+
+// HTML audit
+
+struct AuditFinding {
+    rule_id: &'static str,
+    element_id: Option<String>,
+    message: String,
+    suggestion: String,
+}
+
+struct AuditElement {
+    tag: String,
+    label: String,
+    color: (u8, u8, u8),
+    background: (u8, u8, u8),
+    large_text: bool,
+    contrast_checked: bool,
+}
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+
+fn parse_attributes(source: &str) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    let chars: Vec<char> = source.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        while i < chars.len() && (chars[i].is_whitespace() || chars[i] == '/') {
+            i += 1;
+        }
+        let name_start = i;
+        while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '=' && chars[i] != '/' {
+            i += 1;
+        }
+        if name_start == i {
+            break;
+        }
+        let name: String = chars[name_start..i].iter().collect::<String>().to_lowercase();
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        let mut value = String::new();
+        if i < chars.len() && chars[i] == '=' {
+            i += 1;
+            while i < chars.len() && chars[i].is_whitespace() {
+                i += 1;
+            }
+            if i < chars.len() && (chars[i] == '"' || chars[i] == '\'') {
+                let quote = chars[i];
+                i += 1;
+                let value_start = i;
+                while i < chars.len() && chars[i] != quote {
+                    i += 1;
+                }
+                value = chars[value_start..i].iter().collect();
+                i += 1;
+            } else {
+                let value_start = i;
+                while i < chars.len() && !chars[i].is_whitespace() {
+                    i += 1;
+                }
+                value = chars[value_start..i].iter().collect();
+            }
+        }
+        attrs.insert(name, value);
+    }
+    attrs
+}
+
+/// Inline `style` declarations as lowercase property -> value.
+fn parse_style(style: &str) -> HashMap<String, String> {
+    style
+        .split(';')
+        .filter_map(|decl| {
+            let (prop, value) = decl.split_once(':')?;
+            Some((prop.trim().to_lowercase(), value.trim().to_lowercase()))
+        })
+        .collect()
+}
+
+fn parse_css_color(value: &str) -> Option<(u8, u8, u8)> {
+    let value = value.trim().trim_end_matches("!important").trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        let expand = |c: char| c.to_digit(16).map(|d| (d * 17) as u8);
+        return match hex.len() {
+            3 | 4 => {
+                let mut chars = hex.chars();
+                Some((expand(chars.next()?)?, expand(chars.next()?)?, expand(chars.next()?)?))
+            }
+            6 | 8 => Some((
+                u8::from_str_radix(&hex[0..2], 16).ok()?,
+                u8::from_str_radix(&hex[2..4], 16).ok()?,
+                u8::from_str_radix(&hex[4..6], 16).ok()?,
+            )),
+            _ => None,
+        };
+    }
+    if let Some(args) = value.strip_prefix("rgba(").or_else(|| value.strip_prefix("rgb(")) {
+        let parts: Vec<u8> = args
+            .trim_end_matches(')')
+            .split([',', ' ', '/'])
+            .filter(|p| !p.is_empty())
+            .take(3)
+            .filter_map(|p| p.trim().parse::<f64>().ok().map(|v| v.clamp(0.0, 255.0) as u8))
+            .collect();
+        return (parts.len() == 3).then(|| (parts[0], parts[1], parts[2]));
+    }
+    match value {
+        "black" => Some((0, 0, 0)),
+        "white" => Some((255, 255, 255)),
+        "red" => Some((255, 0, 0)),
+        "green" => Some((0, 128, 0)),
+        "lime" => Some((0, 255, 0)),
+        "blue" => Some((0, 0, 255)),
+        "yellow" => Some((255, 255, 0)),
+        "orange" => Some((255, 165, 0)),
+        "purple" => Some((128, 0, 128)),
+        "gray" | "grey" => Some((128, 128, 128)),
+        "silver" => Some((192, 192, 192)),
+        "lightgray" | "lightgrey" => Some((211, 211, 211)),
+        "darkgray" | "darkgrey" => Some((169, 169, 169)),
+        "navy" => Some((0, 0, 128)),
+        "teal" => Some((0, 128, 128)),
+        "maroon" => Some((128, 0, 0)),
+        _ => None,
+    }
+}
+
+fn relative_luminance((r, g, b): (u8, u8, u8)) -> f64 {
+    0.2126 * srgb_to_linear(r) + 0.7152 * srgb_to_linear(g) + 0.0722 * srgb_to_linear(b)
+}
+
+/// WCAG 2 contrast ratio between two colors, from 1:1 to 21:1.
+pub fn contrast_ratio(a: (u8, u8, u8), b: (u8, u8, u8)) -> f64 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+fn is_focusable(tag: &str, attrs: &HashMap<String, String>) -> bool {
+    matches!(tag, "button" | "input" | "select" | "textarea" | "summary")
+        || (tag == "a" && attrs.contains_key("href"))
+        || attrs.get("tabindex").is_some_and(|t| t.trim() != "-1")
+}
+
+/// Inline styles that remove the outline without providing another visible focus cue.
+fn removes_focus_indicator(style: &HashMap<String, String>) -> bool {
+    let outline_removed = style.get("outline").is_some_and(|v| v == "none" || v == "0" || v.starts_with("0 "))
+        || style.get("outline-style").is_some_and(|v| v == "none")
+        || style.get("outline-width").is_some_and(|v| v == "0" || v == "0px");
+    let replacement = style.get("box-shadow").is_some_and(|v| v != "none")
+        || style.keys().any(|k| k.starts_with("border") && style[k] != "none" && style[k] != "0");
+    outline_removed && !replacement
+}
+
+fn font_is_large(style: &HashMap<String, String>) -> Option<bool> {
+    let size = style.get("font-size")?;
+    let px: f64 = if let Some(pt) = size.strip_suffix("pt") {
+        pt.trim().parse::<f64>().ok()? * 4.0 / 3.0
+    } else {
+        size.trim_end_matches("px").trim().parse().ok()?
+    };
+    let bold = style.get("font-weight").is_some_and(|w| w == "bold" || w.parse::<u32>().is_ok_and(|n| n >= 700));
+    // WCAG large text: 18pt, or 14pt bold
+    Some(px >= 24.0 || (bold && px >= 18.66))
+}
+
+fn audit_html(html: &str) -> Vec<AuditFinding> {
+    let mut findings = Vec::new();
+    let mut stack: Vec<AuditElement> = Vec::new();
+    let mut tag_counts: HashMap<String, usize> = HashMap::new();
+    let mut last_heading: Option<(u32, String)> = None;
+    let mut saw_body = false;
+    let mut has_skip_link = false;
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            check_text_contrast(rest, &mut stack, &mut findings);
+            break;
+        };
+        check_text_contrast(&rest[..lt], &mut stack, &mut findings);
+        rest = &rest[lt..];
+
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map_or("", |end| &rest[end + 3..]);
+            continue;
+        }
+        let Some(gt) = rest.find('>') else { break };
+        let tag_source = &rest[1..gt];
+        rest = &rest[gt + 1..];
+
+        if tag_source.starts_with('!') || tag_source.starts_with('?') {
+            continue;
+        }
+        if let Some(closing) = tag_source.strip_prefix('/') {
+            let name = closing.trim().to_lowercase();
+            if let Some(pos) = stack.iter().rposition(|el| el.tag == name) {
+                stack.truncate(pos);
+            }
+            continue;
+        }
+
+        let name_end = tag_source.find(|c: char| c.is_whitespace() || c == '/').unwrap_or(tag_source.len());
+        let tag = tag_source[..name_end].to_lowercase();
+        if tag.is_empty() {
+            continue;
+        }
+        let attrs = parse_attributes(&tag_source[name_end..]);
+        let style = parse_style(attrs.get("style").map(|s| s.as_str()).unwrap_or(""));
+        let ordinal = {
+            let count = tag_counts.entry(tag.clone()).or_insert(0);
+            *count += 1;
+            *count
+        };
+        let label = attrs.get("id").filter(|id| !id.is_empty()).cloned().unwrap_or_else(|| format!("{}#{}", tag, ordinal));
+
+        if tag == "body" {
+            saw_body = true;
+        }
+        if tag == "a" && attrs.get("href").is_some_and(|href| href.starts_with('#') && href.len() > 1) {
+            has_skip_link = true;
+        }
+
+        if tag == "img" && !attrs.contains_key("alt") {
+            findings.push(AuditFinding {
+                rule_id: "missing_alt_text",
+                element_id: Some(label.clone()),
+                message: "Image is missing alt attribute".to_string(),
+                suggestion: "Add descriptive alt text, or alt=\"\" for decorative images".to_string(),
+            });
+        }
+
+        if let Some(level) = tag.strip_prefix('h').and_then(|n| n.parse::<u32>().ok()).filter(|n| (1..=6).contains(n)) {
+            if let Some((previous, ref previous_label)) = last_heading {
+                if level > previous + 1 {
+                    findings.push(AuditFinding {
+                        rule_id: "missing_heading_structure",
+                        element_id: Some(label.clone()),
+                        message: format!("Heading level jumps from h{} ({}) to h{}", previous, previous_label, level),
+                        suggestion: format!("Use h{} here or add the missing intermediate heading", previous + 1),
+                    });
+                }
+            }
+            last_heading = Some((level, label.clone()));
+        }
+
+        if is_focusable(&tag, &attrs) && removes_focus_indicator(&style) {
+            findings.push(AuditFinding {
+                rule_id: "missing_focus_indicator",
+                element_id: Some(label.clone()),
+                message: "Focusable element removes its outline without another focus style".to_string(),
+                suggestion: "Keep the outline or add a visible :focus style such as a box-shadow or border".to_string(),
+            });
+        }
+
+        let parent = stack.last();
+        let element = AuditElement {
+            color: style.get("color").and_then(|c| parse_css_color(c))
+                .unwrap_or_else(|| parent.map_or((0, 0, 0), |p| p.color)),
+            background: style.get("background-color").or_else(|| style.get("background"))
+                .and_then(|c| parse_css_color(c))
+                .unwrap_or_else(|| parent.map_or((255, 255, 255), |p| p.background)),
+            large_text: font_is_large(&style).unwrap_or_else(|| parent.is_some_and(|p| p.large_text)),
+            contrast_checked: false,
+            tag: tag.clone(),
+            label,
+        };
+
+        let self_closing = tag_source.trim_end().ends_with('/');
+        if VOID_ELEMENTS.contains(&tag.as_str()) || self_closing {
+            continue;
+        }
+        stack.push(element);
+
+        // Script and style bodies are not rendered text
+        if tag == "script" || tag == "style" {
+            let close = format!("</{}", tag);
+            let end = rest.to_lowercase().find(&close).unwrap_or(rest.len());
+            rest = &rest[end..];
+        }
+    }
+
+    if saw_body && !has_skip_link {
+        findings.push(AuditFinding {
+            rule_id: "missing_skip_links",
+            element_id: Some("body".to_string()),
+            message: "Page has no skip navigation link".to_string(),
+            suggestion: "Add a link such as <a href=\"#main\">Skip to content</a> at the start of the body".to_string(),
+        });
+    }
+
+    findings
+}
+
+/// Check contrast once for the element that directly contains a run of visible text.
+fn check_text_contrast(text: &str, stack: &mut [AuditElement], findings: &mut Vec<AuditFinding>) {
+    if text.trim().is_empty() {
+        return;
+    }
+    let Some(element) = stack.last_mut() else { return };
+    if element.contrast_checked || element.tag == "script" || element.tag == "style" {
+        return;
+    }
+    element.contrast_checked = true;
+    let ratio = contrast_ratio(element.color, element.background);
+    let required = if element.large_text { 3.0 } else { 4.5 };
+    if ratio < required {
+        findings.push(AuditFinding {
+            rule_id: "insufficient_color_contrast",
+            element_id: Some(element.label.clone()),
+            message: format!("Text has insufficient color contrast ratio ({:.1}:1)", ratio),
+            suggestion: format!("Increase contrast ratio to at least {}:1", required),
+        });
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShortcutExport {
+    pub version: u32,
+    pub shortcuts: Vec<KeyboardShortcut>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShortcutImportResult {
+    pub imported: usize,
+    /// Pairs of (incoming, existing-or-incoming) shortcuts that would share a key combo
+    pub conflicts: Vec<(KeyboardShortcut, KeyboardShortcut)>,
+    /// Ids of installed shortcuts that are not customizable and would have been changed
+    pub locked: Vec<String>,
+}
+
+/// Key combo in a comparable form: case-insensitive, modifiers in any order.
+fn normalize_keys(keys: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = keys.iter().map(|k| k.trim().to_lowercase()).collect();
+    if let Some(key) = normalized.pop() {
+        normalized.sort();
+        normalized.dedup();
+        normalized.push(key);
+    }
+    normalized
+}
+
+/// Two enabled shortcuts overlap when they share a key combo in the same context;
+/// global shortcuts overlap with every context.
+fn shortcuts_overlap(a: &KeyboardShortcut, b: &KeyboardShortcut) -> bool {
+    a.id != b.id
+        && a.enabled
+        && b.enabled
+        && (a.context == b.context || a.context == ShortcutContext::Global || b.context == ShortcutContext::Global)
+        && normalize_keys(&a.keys) == normalize_keys(&b.keys)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FocusManager {
     pub current_focus: Option<String>,
@@ -229,6 +686,7 @@ pub struct AccessibilityManager {
     focus_manager: Arc<Mutex<FocusManager>>,
     announcements: Arc<Mutex<Vec<ScreenReaderAnnouncement>>>,
     accessibility_rules: Arc<Mutex<Vec<AccessibilityRule>>>,
+    last_command_announcement: Arc<Mutex<Option<std::time::Instant>>>,
 }
 
 impl AccessibilityManager {
@@ -265,6 +723,7 @@ impl AccessibilityManager {
             },
             alternative_text: true,
             skip_links: true,
+            command_announcements: CommandAnnouncementSettings::default(),
         };
 
         let default_focus_manager = FocusManager {
@@ -292,6 +751,7 @@ impl AccessibilityManager {
             shortcuts: Arc::new(Mutex::new(Self::create_default_shortcuts())),
             focus_manager: Arc::new(Mutex::new(default_focus_manager)),
             announcements: Arc::new(Mutex::new(Vec::new())),
+            last_command_announcement: Arc::new(Mutex::new(None)),
             accessibility_rules: Arc::new(Mutex::new(Self::create_accessibility_rules())),
         }
     }
@@ -429,6 +889,20 @@ impl AccessibilityManager {
         *config = new_config;
     }
 
+    /// Apply the accessibility switches exposed in the settings file.
+    pub fn configure(&self, settings: &crate::settings::Settings) {
+        if settings.screen_reader_support != self.get_config().screen_reader_support {
+            if settings.screen_reader_support {
+                self.enable_screen_reader_support();
+            } else {
+                self.disable_screen_reader_support();
+            }
+        }
+        let mut config = self.config.lock().unwrap();
+        config.high_contrast_mode = settings.high_contrast_mode;
+        config.reduced_motion = settings.reduced_motion;
+    }
+
     pub fn enable_screen_reader_support(&self) {
         let mut config = self.config.lock().unwrap();
         config.screen_reader_support = true;
@@ -489,9 +963,7 @@ impl AccessibilityManager {
         
         // Check for conflicts
         for existing in shortcuts.values() {
-            if existing.keys == shortcut.keys && 
-               existing.context == shortcut.context &&
-               existing.enabled {
+            if shortcuts_overlap(existing, &shortcut) {
                 return Err(format!("Shortcut conflict with: {}", existing.name));
             }
         }
@@ -500,6 +972,68 @@ impl AccessibilityManager {
         Ok(())
     }
 
+    /// Every pair of enabled shortcuts bound to the same key combo in an overlapping context.
+    pub fn find_shortcut_conflicts(&self) -> Vec<(KeyboardShortcut, KeyboardShortcut)> {
+        let shortcuts = self.shortcuts.lock().unwrap();
+        let mut all: Vec<&KeyboardShortcut> = shortcuts.values().collect();
+        all.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut conflicts = Vec::new();
+        for (i, a) in all.iter().enumerate() {
+            for b in &all[i + 1..] {
+                if shortcuts_overlap(a, b) {
+                    conflicts.push(((*a).clone(), (*b).clone()));
+                }
+            }
+        }
+        conflicts
+    }
+
+    pub fn export_shortcuts(&self) -> Result<String, String> {
+        let shortcuts = self.shortcuts.lock().unwrap();
+        let mut list: Vec<KeyboardShortcut> = shortcuts.values().cloned().collect();
+        list.sort_by(|a, b| a.id.cmp(&b.id));
+        serde_json::to_string_pretty(&ShortcutExport { version: 1, shortcuts: list })
+            .map_err(|e| e.to_string())
+    }
+
+    /// Apply exported shortcuts. Nothing is written if any incoming binding conflicts with
+    /// an installed one or another incoming one, or would change a non-customizable shortcut.
+    pub fn import_shortcuts(&self, json_data: &str) -> Result<ShortcutImportResult, String> {
+        let export: ShortcutExport = serde_json::from_str(json_data)
+            .map_err(|e| format!("Invalid shortcut export: {}", e))?;
+        let mut shortcuts = self.shortcuts.lock().unwrap();
+
+        let locked: Vec<String> = export.shortcuts.iter()
+            .filter(|incoming| shortcuts.get(&incoming.id).is_some_and(|existing| {
+                !existing.customizable && normalize_keys(&existing.keys) != normalize_keys(&incoming.keys)
+            }))
+            .map(|incoming| incoming.id.clone())
+            .collect();
+
+        // Compare against the table as it would look after the import
+        let mut merged = shortcuts.clone();
+        for incoming in &export.shortcuts {
+            merged.insert(incoming.id.clone(), incoming.clone());
+        }
+        let mut conflicts = Vec::new();
+        for (i, incoming) in export.shortcuts.iter().enumerate() {
+            for other in merged.values() {
+                let already_reported = export.shortcuts[..i].iter().any(|earlier| earlier.id == other.id);
+                if !already_reported && shortcuts_overlap(incoming, other) {
+                    conflicts.push((incoming.clone(), other.clone()));
+                }
+            }
+        }
+
+        if !conflicts.is_empty() || !locked.is_empty() {
+            return Ok(ShortcutImportResult { imported: 0, conflicts, locked });
+        }
+        let imported = export.shortcuts.len();
+        *shortcuts = merged;
+        Ok(ShortcutImportResult { imported, conflicts, locked })
+    }
+
     // Focus Management
     pub fn set_focus(&self, element_id: &str) {
         let mut focus_manager = self.focus_manager.lock().unwrap();
@@ -577,6 +1111,40 @@ impl AccessibilityManager {
         }
     }
 
+    /// Announce a finished command for screen-reader users. Returns whether an announcement
+    /// was queued: short commands, disabled support and throttled bursts are skipped.
+    pub fn announce_command_finished(&self, command: &str, exit_code: Option<i32>, duration_ms: Option<u64>) -> bool {
+        let settings = {
+            let config = self.config.lock().unwrap();
+            if !config.screen_reader_support || !config.command_announcements.enabled {
+                return false;
+            }
+            config.command_announcements.clone()
+        };
+        let duration_ms = match duration_ms {
+            Some(duration) if duration >= settings.min_duration_ms => duration,
+            _ => return false,
+        };
+
+        {
+            let mut last = self.last_command_announcement.lock().unwrap();
+            let now = std::time::Instant::now();
+            if let Some(previous) = *last {
+                if now.duration_since(previous).as_millis() < settings.throttle_ms as u128 {
+                    return false;
+                }
+            }
+            *last = Some(now);
+        }
+
+        let message = settings.message_template
+            .replace("{command}", command.trim())
+            .replace("{exit_code}", &exit_code.map(|code| code.to_string()).unwrap_or_else(|| "unknown".to_string()))
+            .replace("{duration}", &format!("{:.1}", duration_ms as f64 / 1000.0));
+        self.announce(&message, AnnouncementPriority::Medium, false);
+        true
+    }
+
     pub fn get_pending_announcements(&self) -> Vec<ScreenReaderAnnouncement> {
         let mut announcements = self.announcements.lock().unwrap();
         let pending = announcements.clone();
@@ -588,86 +1156,59 @@ impl AccessibilityManager {
     pub fn set_color_blind_support(&self, color_blind_type: ColorBlindType) {
         let mut config = self.config.lock().unwrap();
         config.color_blind_support.enabled = color_blind_type != ColorBlindType::None;
-        config.color_blind_support.color_blind_type = color_blind_type.clone();
-        
-        // Set up color adjustments based on type
-        config.color_blind_support.color_adjustments = match color_blind_type {
-            ColorBlindType::Protanopia => {
-                // Red-blind: adjust red colors
-                [
-                    ("#ff0000".to_string(), "#0066cc".to_string()), // Red -> Blue
-                    ("#ff6600".to_string(), "#0099cc".to_string()), // Orange -> Light Blue
-                ].into_iter().collect()
-            },
-            ColorBlindType::Deuteranopia => {
-                // Green-blind: adjust green colors
-                [
-                    ("#00ff00".to_string(), "#ffff00".to_string()), // Green -> Yellow
-                    ("#009900".to_string(), "#cc6600".to_string()), // Dark Green -> Orange
-                ].into_iter().collect()
-            },
-            ColorBlindType::Tritanopia => {
-                // Blue-blind: adjust blue colors
-                [
-                    ("#0000ff".to_string(), "#ff00ff".to_string()), // Blue -> Magenta
-                    ("#0066cc".to_string(), "#cc0066".to_string()), // Light Blue -> Pink
-                ].into_iter().collect()
-            },
-            _ => HashMap::new(),
-        };
+        config.color_blind_support.color_blind_type = color_blind_type;
+        
+        // Adjustments are computed by `daltonize_color`; the map only holds explicit overrides
+        config.color_blind_support.color_adjustments.clear();
     }
 
+    /// Adjust a single `#rrggbb` color for the configured deficiency. Explicit overrides in
+    /// `color_adjustments` win; anything that is not a hex color is returned untouched.
     pub fn get_adjusted_color(&self, color: &str) -> String {
         let config = self.config.lock().unwrap();
         
-        if config.color_blind_support.enabled {
-            if let Some(adjusted) = config.color_blind_support.color_adjustments.get(color) {
-                return adjusted.clone();
+        if !config.color_blind_support.enabled {
+            return color.to_string();
+        }
+        if let Some(adjusted) = config.color_blind_support.color_adjustments.get(color) {
+            return adjusted.clone();
+        }
+
+        let hex = color.trim_start_matches('#');
+        if hex.len() != 6 && hex.len() != 8 {
+            return color.to_string();
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        match (channel(0), channel(2), channel(4)) {
+            (Some(r), Some(g), Some(b)) => {
+                let (r, g, b) = daltonize_color((r, g, b), &config.color_blind_support.color_blind_type);
+                format!("#{:02x}{:02x}{:02x}{}", r, g, b, &hex[6..])
             }
+            _ => color.to_string(),
         }
-        
-        color.to_string()
     }
 
     // Accessibility Auditing
+    /// Audit an HTML snapshot of the UI. Each finding names the offending element by its
+    /// `id` attribute, or by `tag#n` (the n-th such tag in document order) when it has none.
     pub fn run_accessibility_audit(&self, element_data: &str) -> Vec<AccessibilityAuditResult> {
         let rules = self.accessibility_rules.lock().unwrap();
-        let mut results = Vec::new();
-
-        // This would typically involve running JavaScript functions to check elements
-        // For this example, we'll create mock results
-        for rule in rules.iter() {
-            // Mock audit logic - in reality this would parse the element_data
-            // and run the appropriate checks
-            match rule.rule_id.as_str() {
-                "missing_alt_text" => {
-                    if element_data.contains("<img") && !element_data.contains("alt=") {
-                        results.push(AccessibilityAuditResult {
-                            rule_id: rule.rule_id.clone(),
-                            element_id: Some("image-1".to_string()),
-                            severity: rule.severity.clone(),
-                            message: "Image is missing alt attribute".to_string(),
-                            suggestion: Some("Add descriptive alt text to the image".to_string()),
-                            auto_fixable: rule.auto_fix.is_some(),
-                        });
-                    }
-                },
-                "insufficient_color_contrast" => {
-                    // Mock color contrast check
-                    results.push(AccessibilityAuditResult {
-                        rule_id: rule.rule_id.clone(),
-                        element_id: Some("text-1".to_string()),
-                        severity: rule.severity.clone(),
-                        message: "Text has insufficient color contrast ratio (2.1:1)".to_string(),
-                        suggestion: Some("Increase contrast ratio to at least 4.5:1".to_string()),
-                        auto_fixable: false,
-                    });
-                },
-                _ => {}
-            }
-        }
-
-        results
+        let findings = audit_html(element_data);
+
+        findings
+            .into_iter()
+            .filter_map(|finding| {
+                let rule = rules.iter().find(|rule| rule.rule_id == finding.rule_id)?;
+                Some(AccessibilityAuditResult {
+                    rule_id: rule.rule_id.clone(),
+                    element_id: finding.element_id,
+                    severity: rule.severity.clone(),
+                    message: finding.message,
+                    suggestion: Some(finding.suggestion),
+                    auto_fixable: rule.auto_fix.is_some(),
+                })
+            })
+            .collect()
     }
 
     // Utility Functions
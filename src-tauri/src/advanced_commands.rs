@@ -3,7 +3,8 @@ use crate::theme_manager::ThemeManager;
 use crate::network_manager::NetworkManager;
 use crate::dev_tools::DevToolsManager;
 use crate::accessibility::{AccessibilityManager, I18nManager};
-use crate::advanced_terminal::AdvancedTerminalManager;
+use crate::advanced_terminal::{AdvancedTerminalManager, PaneCwd};
+use crate::commands::TerminalManagerState;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -129,6 +130,16 @@ pub async fn export_theme(
     manager.export_theme(&theme_id)
 }
 
+#[tauri::command]
+pub async fn adjust_theme_for_colorblindness(
+    theme_manager: State<'_, Arc<Mutex<ThemeManager>>>,
+    theme_id: String,
+    color_blind_type: crate::accessibility::ColorBlindType,
+) -> Result<crate::theme_manager::Theme, String> {
+    let manager = theme_manager.lock().await;
+    manager.adjust_theme_for_colorblindness(&theme_id, color_blind_type)
+}
+
 #[tauri::command]
 pub async fn import_theme(
     theme_manager: State<'_, Arc<Mutex<ThemeManager>>>,
@@ -161,11 +172,21 @@ pub async fn connect_ssh(
     network_manager: State<'_, Arc<Mutex<NetworkManager>>>,
     connection_id: String,
     terminal_id: Option<String>,
-) -> Result<String, String> {
+) -> Result<String, crate::network_manager::SshError> {
     let manager = network_manager.lock().await;
     manager.connect_ssh(&connection_id, terminal_id).await
 }
 
+#[tauri::command]
+pub async fn trust_ssh_host_key(
+    network_manager: State<'_, Arc<Mutex<NetworkManager>>>,
+    connection_id: String,
+    fingerprint: String,
+) -> Result<(), crate::network_manager::SshError> {
+    let manager = network_manager.lock().await;
+    manager.trust_host_key(&connection_id, &fingerprint).await
+}
+
 #[tauri::command]
 pub async fn disconnect_ssh(
     network_manager: State<'_, Arc<Mutex<NetworkManager>>>,
@@ -175,6 +196,80 @@ pub async fn disconnect_ssh(
     manager.disconnect_ssh(&session_id)
 }
 
+#[tauri::command]
+pub async fn create_remote_port_forward(
+    network_manager: State<'_, Arc<Mutex<NetworkManager>>>,
+    session_id: String,
+    remote_port: u16,
+    local_host: String,
+    local_port: u16,
+) -> Result<String, String> {
+    let connection = network_manager.lock().await.remote_forward_connection(&session_id)?;
+    // Other network commands keep working while the tunnel comes up
+    let handle = NetworkManager::open_remote_tunnel(&connection, remote_port, &local_host, local_port).await?;
+    network_manager.lock().await.add_remote_port_forward(&session_id, &connection, remote_port, local_host, local_port, handle)
+}
+
+#[tauri::command]
+pub async fn list_port_forwards(
+    network_manager: State<'_, Arc<Mutex<NetworkManager>>>,
+    session_id: String,
+) -> Result<Vec<crate::network_manager::PortForward>, String> {
+    let manager = network_manager.lock().await;
+    manager.list_port_forwards(&session_id)
+}
+
+#[tauri::command]
+pub async fn close_port_forward(
+    network_manager: State<'_, Arc<Mutex<NetworkManager>>>,
+    forward_id: String,
+) -> Result<(), String> {
+    let manager = network_manager.lock().await;
+    manager.close_port_forward(&forward_id)
+}
+
+#[tauri::command]
+pub async fn sftp_list_directory(
+    network_manager: State<'_, Arc<Mutex<NetworkManager>>>,
+    session_id: String,
+    path: String,
+) -> Result<Vec<crate::filesystem_manager::FileSystemEntry>, crate::network_manager::SftpError> {
+    let connection = network_manager.lock().await.sftp_connection(&session_id)?;
+    NetworkManager::sftp_list_directory(&connection, &path).await
+}
+
+#[tauri::command]
+pub async fn sftp_download(
+    app: tauri::AppHandle,
+    network_manager: State<'_, Arc<Mutex<NetworkManager>>>,
+    session_id: String,
+    remote: String,
+    local: String,
+) -> Result<crate::filesystem_manager::FileOperation, crate::network_manager::SftpError> {
+    use tauri::Emitter;
+    let connection = network_manager.lock().await.sftp_connection(&session_id)?;
+    NetworkManager::sftp_download(&connection, &remote, &local, |operation| {
+        let _ = app.emit("file-operation-progress", operation);
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn sftp_upload(
+    app: tauri::AppHandle,
+    network_manager: State<'_, Arc<Mutex<NetworkManager>>>,
+    session_id: String,
+    local: String,
+    remote: String,
+) -> Result<crate::filesystem_manager::FileOperation, crate::network_manager::SftpError> {
+    use tauri::Emitter;
+    let connection = network_manager.lock().await.sftp_connection(&session_id)?;
+    NetworkManager::sftp_upload(&connection, &local, &remote, |operation| {
+        let _ = app.emit("file-operation-progress", operation);
+    })
+    .await
+}
+
 #[tauri::command]
 pub async fn scan_ports(
     network_manager: State<'_, Arc<Mutex<NetworkManager>>>,
@@ -185,6 +280,32 @@ pub async fn scan_ports(
     Ok(manager.scan_ports(&host, ports).await)
 }
 
+#[tauri::command]
+pub async fn discover_hosts(
+    network_manager: State<'_, Arc<Mutex<NetworkManager>>>,
+    network: String,
+) -> Result<Vec<crate::network_manager::HostDiscoveryResult>, String> {
+    let manager = network_manager.lock().await;
+    Ok(manager.discover_hosts(&network).await)
+}
+
+#[tauri::command]
+pub async fn ping_host(
+    network_manager: State<'_, Arc<Mutex<NetworkManager>>>,
+    host: String,
+    count: Option<u32>,
+    interval_ms: Option<u64>,
+    timeout_ms: Option<u64>,
+) -> Result<crate::network_manager::PingStats, String> {
+    let manager = network_manager.lock().await;
+    manager.ping(
+        &host,
+        count.unwrap_or(4),
+        std::time::Duration::from_millis(interval_ms.unwrap_or(1000)),
+        std::time::Duration::from_millis(timeout_ms.unwrap_or(1000)),
+    ).await
+}
+
 #[tauri::command]
 pub async fn get_network_stats(
     network_manager: State<'_, Arc<Mutex<NetworkManager>>>,
@@ -193,6 +314,15 @@ pub async fn get_network_stats(
     Ok(manager.get_network_stats())
 }
 
+/// Open sockets with their owning process where the OS reports one.
+#[tauri::command]
+pub async fn get_network_connections(
+    network_manager: State<'_, Arc<Mutex<NetworkManager>>>,
+) -> Result<Vec<crate::network_manager::NetworkConnection>, String> {
+    let manager = network_manager.lock().await;
+    manager.refresh_network_connections().await
+}
+
 // Developer Tools Commands
 #[tauri::command]
 pub async fn discover_git_repositories(
@@ -243,6 +373,103 @@ pub async fn git_pull(
     manager.git_pull(&repo_name).await
 }
 
+#[tauri::command]
+pub async fn stash_save(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    repo_name: String,
+    message: Option<String>,
+    include_untracked: Option<bool>,
+) -> Result<String, String> {
+    let manager = dev_tools_manager.lock().await;
+    manager.stash_save(&repo_name, message.as_deref(), include_untracked.unwrap_or(false)).await
+}
+
+#[tauri::command]
+pub async fn list_stashes(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    repo_name: String,
+) -> Result<Vec<crate::dev_tools::GitStash>, String> {
+    let manager = dev_tools_manager.lock().await;
+    manager.list_stashes(&repo_name).await
+}
+
+#[tauri::command]
+pub async fn stash_apply(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    repo_name: String,
+    index: usize,
+) -> Result<String, String> {
+    let manager = dev_tools_manager.lock().await;
+    manager.stash_apply(&repo_name, index).await
+}
+
+#[tauri::command]
+pub async fn stash_pop(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    repo_name: String,
+    index: usize,
+) -> Result<String, String> {
+    let manager = dev_tools_manager.lock().await;
+    manager.stash_pop(&repo_name, index).await
+}
+
+#[tauri::command]
+pub async fn stash_drop(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    repo_name: String,
+    index: usize,
+) -> Result<String, String> {
+    let manager = dev_tools_manager.lock().await;
+    manager.stash_drop(&repo_name, index).await
+}
+
+#[tauri::command]
+pub async fn get_git_diff(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    repo_name: String,
+    file: Option<String>,
+    staged: Option<bool>,
+) -> Result<Vec<crate::dev_tools::FileDiff>, String> {
+    let manager = dev_tools_manager.lock().await;
+    manager.get_git_diff(&repo_name, file.as_deref(), staged.unwrap_or(false)).await
+}
+
+#[tauri::command]
+pub async fn stage_hunk(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    repo_name: String,
+    file: String,
+    hunk: usize,
+    expected_header: Option<String>,
+) -> Result<String, String> {
+    let manager = dev_tools_manager.lock().await;
+    manager.stage_hunk(&repo_name, &file, hunk, expected_header.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn unstage_hunk(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    repo_name: String,
+    file: String,
+    hunk: usize,
+    expected_header: Option<String>,
+) -> Result<String, String> {
+    let manager = dev_tools_manager.lock().await;
+    manager.unstage_hunk(&repo_name, &file, hunk, expected_header.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn git_blame(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    repo_name: String,
+    file: String,
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+) -> Result<Vec<crate::dev_tools::BlameLine>, String> {
+    let manager = dev_tools_manager.lock().await;
+    manager.git_blame(&repo_name, &file, start_line, end_line).await
+}
+
 #[tauri::command]
 pub async fn run_build(
     dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
@@ -261,6 +488,130 @@ pub async fn run_tests(
     manager.run_tests(&config_name).await
 }
 
+#[tauri::command]
+pub async fn detect_project_type(path: String) -> Result<crate::dev_tools::ProjectInfo, String> {
+    crate::dev_tools::detect_project_type(std::path::Path::new(&path)).await
+}
+
+/// Build and test configurations suggested for the project at `path`, ready to add.
+#[tauri::command]
+pub async fn suggest_configs(
+    path: String,
+) -> Result<(Vec<crate::dev_tools::BuildConfiguration>, Vec<crate::dev_tools::TestConfiguration>), String> {
+    let info = crate::dev_tools::detect_project_type(std::path::Path::new(&path)).await?;
+    Ok((info.build_configs, info.test_configs))
+}
+
+#[tauri::command]
+pub async fn add_build_configuration(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    config: crate::dev_tools::BuildConfiguration,
+) -> Result<String, String> {
+    let manager = dev_tools_manager.lock().await;
+    manager.add_build_configuration(config)
+}
+
+#[tauri::command]
+pub async fn add_test_configuration(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    config: crate::dev_tools::TestConfiguration,
+) -> Result<String, String> {
+    let manager = dev_tools_manager.lock().await;
+    manager.add_test_configuration(config)
+}
+
+#[tauri::command]
+pub async fn register_debugger(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    debugger: crate::dev_tools::Debugger,
+) -> Result<String, String> {
+    let manager = dev_tools_manager.lock().await;
+    manager.register_debugger(debugger)
+}
+
+#[tauri::command]
+pub async fn get_debugger(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    debugger_id: String,
+) -> Result<Option<crate::dev_tools::Debugger>, String> {
+    let manager = dev_tools_manager.lock().await;
+    Ok(manager.get_debugger(&debugger_id))
+}
+
+// Debug commands work on a clone so a slow adapter doesn't hold the manager lock
+#[tauri::command]
+pub async fn debug_start(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    debugger_id: String,
+    program: String,
+    arguments: Option<Vec<String>>,
+    working_directory: Option<String>,
+    environment: Option<HashMap<String, String>>,
+    launch_options: Option<serde_json::Value>,
+) -> Result<crate::dev_tools::DebugSession, String> {
+    let manager = dev_tools_manager.lock().await.clone();
+    manager
+        .debug_start(
+            &debugger_id,
+            &program,
+            arguments.unwrap_or_default(),
+            working_directory,
+            environment.unwrap_or_default(),
+            launch_options,
+        )
+        .await
+}
+
+#[tauri::command]
+pub async fn debug_continue(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    debugger_id: String,
+) -> Result<(), String> {
+    let manager = dev_tools_manager.lock().await.clone();
+    manager.debug_continue(&debugger_id).await
+}
+
+#[tauri::command]
+pub async fn debug_step_over(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    debugger_id: String,
+) -> Result<(), String> {
+    let manager = dev_tools_manager.lock().await.clone();
+    manager.debug_step_over(&debugger_id).await
+}
+
+#[tauri::command]
+pub async fn debug_set_breakpoint(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    debugger_id: String,
+    file_path: String,
+    line: usize,
+    condition: Option<String>,
+) -> Result<crate::dev_tools::Breakpoint, String> {
+    let manager = dev_tools_manager.lock().await.clone();
+    manager.debug_set_breakpoint(&debugger_id, &file_path, line, condition).await
+}
+
+#[tauri::command]
+pub async fn debug_evaluate(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    debugger_id: String,
+    expression: String,
+    frame_id: Option<usize>,
+) -> Result<String, String> {
+    let manager = dev_tools_manager.lock().await.clone();
+    manager.debug_evaluate(&debugger_id, &expression, frame_id).await
+}
+
+#[tauri::command]
+pub async fn debug_stop(
+    dev_tools_manager: State<'_, Arc<Mutex<DevToolsManager>>>,
+    debugger_id: String,
+) -> Result<(), String> {
+    let manager = dev_tools_manager.lock().await.clone();
+    manager.debug_stop(&debugger_id).await
+}
+
 // Accessibility Commands
 #[tauri::command]
 pub async fn get_accessibility_config(
@@ -310,6 +661,15 @@ pub async fn announce(
     Ok(())
 }
 
+#[tauri::command]
+pub async fn run_accessibility_audit(
+    accessibility_manager: State<'_, Arc<Mutex<AccessibilityManager>>>,
+    element_data: String,
+) -> Result<Vec<crate::accessibility::AccessibilityAuditResult>, String> {
+    let manager = accessibility_manager.lock().await;
+    Ok(manager.run_accessibility_audit(&element_data))
+}
+
 #[tauri::command]
 pub async fn get_keyboard_shortcuts(
     accessibility_manager: State<'_, Arc<Mutex<AccessibilityManager>>>,
@@ -319,6 +679,31 @@ pub async fn get_keyboard_shortcuts(
     Ok(manager.get_shortcuts(context))
 }
 
+#[tauri::command]
+pub async fn find_shortcut_conflicts(
+    accessibility_manager: State<'_, Arc<Mutex<AccessibilityManager>>>,
+) -> Result<Vec<(crate::accessibility::KeyboardShortcut, crate::accessibility::KeyboardShortcut)>, String> {
+    let manager = accessibility_manager.lock().await;
+    Ok(manager.find_shortcut_conflicts())
+}
+
+#[tauri::command]
+pub async fn export_shortcuts(
+    accessibility_manager: State<'_, Arc<Mutex<AccessibilityManager>>>,
+) -> Result<String, String> {
+    let manager = accessibility_manager.lock().await;
+    manager.export_shortcuts()
+}
+
+#[tauri::command]
+pub async fn import_shortcuts(
+    accessibility_manager: State<'_, Arc<Mutex<AccessibilityManager>>>,
+    json_data: String,
+) -> Result<crate::accessibility::ShortcutImportResult, String> {
+    let manager = accessibility_manager.lock().await;
+    manager.import_shortcuts(&json_data)
+}
+
 // Internationalization Commands
 #[tauri::command]
 pub async fn get_i18n_config(
@@ -376,6 +761,27 @@ pub async fn get_terminal_session(
     Ok(manager.get_session(&session_id))
 }
 
+/// The session's command history, narrowed by `filter`.
+#[tauri::command]
+pub async fn get_rich_history(
+    terminal_manager: State<'_, Arc<Mutex<AdvancedTerminalManager>>>,
+    session_id: String,
+    filter: Option<crate::advanced_terminal::HistoryFilter>,
+) -> Result<Vec<crate::advanced_terminal::HistoryEntry>, String> {
+    let manager = terminal_manager.lock().await;
+    manager.get_rich_history(&session_id, &filter.unwrap_or_default())
+}
+
+/// The session's command lines without metadata, for callers that predate `get_rich_history`.
+#[tauri::command]
+pub async fn get_session_command_history(
+    terminal_manager: State<'_, Arc<Mutex<AdvancedTerminalManager>>>,
+    session_id: String,
+) -> Result<Vec<String>, String> {
+    let manager = terminal_manager.lock().await;
+    manager.get_command_history(&session_id)
+}
+
 #[tauri::command]
 pub async fn get_all_terminal_sessions(
     terminal_manager: State<'_, Arc<Mutex<AdvancedTerminalManager>>>,
@@ -384,15 +790,22 @@ pub async fn get_all_terminal_sessions(
     Ok(manager.get_all_sessions())
 }
 
+/// The new pane starts in the source pane's live shell cwd.
 #[tauri::command]
 pub async fn split_pane(
     terminal_manager: State<'_, Arc<Mutex<AdvancedTerminalManager>>>,
+    terminals: State<'_, TerminalManagerState>,
     session_id: String,
     pane_id: String,
     split_type: crate::advanced_terminal::SplitType,
     ratio: f32,
 ) -> Result<String, String> {
     let manager = terminal_manager.lock().await;
+    if let Some(terminal_id) = manager.pane_terminal_id(&session_id, &pane_id) {
+        if let Some(cwd) = terminals.lock().await.get_working_directory(&terminal_id) {
+            manager.update_terminal_working_directory(&terminal_id, PathBuf::from(cwd), None);
+        }
+    }
     manager.split_pane(&session_id, &pane_id, split_type, ratio)
 }
 
@@ -406,13 +819,82 @@ pub async fn close_pane(
     manager.close_pane(&session_id, &pane_id)
 }
 
+#[tauri::command]
+pub async fn apply_layout(
+    terminal_manager: State<'_, Arc<Mutex<AdvancedTerminalManager>>>,
+    session_id: String,
+    layout_type: crate::advanced_terminal::LayoutType,
+    minimum_pane_size: Option<crate::advanced_terminal::PaneSize>,
+) -> Result<Vec<crate::advanced_terminal::TerminalPane>, String> {
+    let manager = terminal_manager.lock().await;
+    manager.apply_layout(&session_id, layout_type, minimum_pane_size)
+}
+
+#[tauri::command]
+pub async fn attach_pane_terminal(
+    terminal_manager: State<'_, Arc<Mutex<AdvancedTerminalManager>>>,
+    session_id: String,
+    pane_id: String,
+    terminal_id: String,
+) -> Result<(), String> {
+    let manager = terminal_manager.lock().await;
+    manager.attach_pane_terminal(&session_id, &pane_id, &terminal_id)
+}
+
+#[tauri::command]
+pub async fn set_input_broadcast(
+    terminal_manager: State<'_, Arc<Mutex<AdvancedTerminalManager>>>,
+    session_id: String,
+    pane_ids: Vec<String>,
+) -> Result<(), String> {
+    let manager = terminal_manager.lock().await;
+    manager.set_input_broadcast(&session_id, pane_ids)
+}
+
+#[tauri::command]
+pub async fn clear_input_broadcast(
+    terminal_manager: State<'_, Arc<Mutex<AdvancedTerminalManager>>>,
+    session_id: String,
+) -> Result<(), String> {
+    let manager = terminal_manager.lock().await;
+    manager.clear_input_broadcast(&session_id)
+}
+
+/// The pane's shell cwd as last reported via OSC 7, or its recorded directory.
+#[tauri::command]
+pub async fn get_pane_cwd(
+    terminal_manager: State<'_, Arc<Mutex<AdvancedTerminalManager>>>,
+    terminals: State<'_, TerminalManagerState>,
+    session_id: String,
+    pane_id: String,
+) -> Result<PaneCwd, String> {
+    let pane = terminal_manager
+        .lock()
+        .await
+        .get_pane(&session_id, &pane_id)
+        .ok_or_else(|| format!("Pane {} not found in session {}", pane_id, session_id))?;
+    if let Some(terminal_id) = &pane.terminal_id {
+        if let Some(cwd) = terminals.lock().await.get_reported_working_directory(terminal_id) {
+            return Ok(PaneCwd { path: cwd.path, host: cwd.host, remote: cwd.remote });
+        }
+    }
+    Ok(PaneCwd { path: pane.working_directory.to_string_lossy().into_owned(), host: None, remote: false })
+}
+
+/// The new tab starts in the active pane's live shell cwd.
 #[tauri::command]
 pub async fn create_terminal_tab(
     terminal_manager: State<'_, Arc<Mutex<AdvancedTerminalManager>>>,
+    terminals: State<'_, TerminalManagerState>,
     session_id: String,
     title: Option<String>,
 ) -> Result<String, String> {
     let manager = terminal_manager.lock().await;
+    if let Some(terminal_id) = manager.active_pane_terminal_id(&session_id) {
+        if let Some(cwd) = terminals.lock().await.get_working_directory(&terminal_id) {
+            manager.update_terminal_working_directory(&terminal_id, PathBuf::from(cwd), None);
+        }
+    }
     manager.create_tab(&session_id, title)
 }
 
@@ -464,6 +946,94 @@ pub async fn get_session_templates(
     Ok(manager.get_templates())
 }
 
+// How long to wait for a prompt before sending a template command anyway
+const TEMPLATE_PROMPT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Create a session from a template and start a shell in each pane. With
+/// `run_initial_commands` (the default) each pane's commands are then typed in
+/// order; `wait_for_prompt` (also default) holds each one until the shell is
+/// back at a prompt, using shell integration when it's active.
+#[tauri::command]
+pub async fn apply_session_template(
+    terminal_manager: State<'_, Arc<Mutex<AdvancedTerminalManager>>>,
+    terminals: State<'_, TerminalManagerState>,
+    template_id: String,
+    session_name: Option<String>,
+    run_initial_commands: Option<bool>,
+    wait_for_prompt: Option<bool>,
+) -> Result<crate::advanced_terminal::AppliedTemplate, String> {
+    let manager = terminal_manager.lock().await;
+    let mut applied = manager.apply_template(&template_id, session_name)?;
+
+    for pane in &mut applied.panes {
+        let session = applied.session_id.clone();
+        let pane_info = manager.get_pane(&session, &pane.pane_id);
+        let size = pane_info
+            .map(|p| crate::pty::TerminalSize { cols: p.size.columns, rows: p.size.rows, pixel_width: 0, pixel_height: 0 })
+            .unwrap_or(crate::pty::TerminalSize { cols: 80, rows: 24, pixel_width: 0, pixel_height: 0 });
+        let options = crate::pty::ShellOptions {
+            cwd: Some(pane.working_directory.to_string_lossy().into_owned()),
+            env: applied.environment_variables.clone(),
+            ..Default::default()
+        };
+        let terminal_id = terminals.lock().await.create_terminal(size, &options).map_err(|e| e.to_string())?;
+        manager.attach_pane_terminal(&session, &pane.pane_id, &terminal_id)?;
+        pane.terminal_id = Some(terminal_id);
+    }
+    drop(manager);
+
+    if run_initial_commands.unwrap_or(true) {
+        let wait = wait_for_prompt.unwrap_or(true);
+        for pane in applied.panes.iter().filter(|pane| !pane.initial_commands.is_empty()) {
+            let Some(terminal_id) = pane.terminal_id.clone() else { continue };
+            let commands = pane.initial_commands.clone();
+            let terminals = terminals.inner().clone();
+            tauri::async_runtime::spawn(async move {
+                // With shell integration, the next command also waits for a new prompt block
+                let mut block_at_send: Option<Option<String>> = None;
+                for command in commands {
+                    if wait {
+                        let started = std::time::Instant::now();
+                        loop {
+                            let (ready, block) = {
+                                let terminals = terminals.lock().await;
+                                (terminals.is_ready_for_input(&terminal_id), terminals.last_block_id(&terminal_id))
+                            };
+                            let moved_on = match &block_at_send {
+                                Some(previous) if block.is_some() => block != *previous,
+                                _ => true,
+                            };
+                            if ready && moved_on {
+                                break;
+                            }
+                            if started.elapsed() > TEMPLATE_PROMPT_TIMEOUT {
+                                log::warn!("No prompt from terminal {} after {:?}; sending {:?} anyway", terminal_id, TEMPLATE_PROMPT_TIMEOUT, command);
+                                break;
+                            }
+                            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                        }
+                    }
+                    let written = {
+                        let terminals = terminals.lock().await;
+                        block_at_send = Some(terminals.last_block_id(&terminal_id));
+                        terminals.write_to_terminal(&terminal_id, &format!("{}\r", command)).map_err(|e| e.to_string())
+                    };
+                    if let Err(e) = written {
+                        log::warn!("Stopping template commands for terminal {}: {}", terminal_id, e);
+                        break;
+                    }
+                    if wait {
+                        // Without shell integration the prompt check is a line match, so let the command start first
+                        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                    }
+                }
+            });
+        }
+    }
+
+    Ok(applied)
+}
+
 #[tauri::command]
 pub async fn export_session(
     terminal_manager: State<'_, Arc<Mutex<AdvancedTerminalManager>>>,
@@ -13,7 +13,7 @@ pub struct TerminalSession {
     pub last_accessed: u64,
     pub working_directory: PathBuf,
     pub environment_variables: HashMap<String, String>,
-    pub command_history: Vec<String>,
+    pub command_history: Vec<HistoryEntry>,
     pub scrollback_buffer: Vec<String>,
     pub panes: Vec<TerminalPane>,
     pub active_pane_id: Option<String>,
@@ -22,6 +22,104 @@ pub struct TerminalSession {
     pub active_tab_index: usize,
     pub status: SessionStatus,
     pub metadata: SessionMetadata,
+    #[serde(default)]
+    pub input_broadcast: Vec<String>, // panes whose input is synchronized; empty when off
+}
+
+const MAX_SESSION_HISTORY: usize = 5000;
+
+/// A command run in one of the session's panes, as shell integration saw it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "StoredHistoryEntry")]
+pub struct HistoryEntry {
+    pub command: String,
+    pub timestamp: u64, // when it started
+    pub working_directory: Option<PathBuf>,
+    pub exit_code: Option<i32>,
+    pub duration_ms: Option<u64>,
+    pub pane_id: Option<String>,
+}
+
+// Sessions saved before history carried metadata stored bare command lines
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredHistoryEntry {
+    Plain(String),
+    Rich {
+        command: String,
+        timestamp: u64,
+        #[serde(default)]
+        working_directory: Option<PathBuf>,
+        #[serde(default)]
+        exit_code: Option<i32>,
+        #[serde(default)]
+        duration_ms: Option<u64>,
+        #[serde(default)]
+        pane_id: Option<String>,
+    },
+}
+
+impl From<StoredHistoryEntry> for HistoryEntry {
+    fn from(stored: StoredHistoryEntry) -> Self {
+        match stored {
+            StoredHistoryEntry::Plain(command) => HistoryEntry {
+                command,
+                timestamp: 0,
+                working_directory: None,
+                exit_code: None,
+                duration_ms: None,
+                pane_id: None,
+            },
+            StoredHistoryEntry::Rich { command, timestamp, working_directory, exit_code, duration_ms, pane_id } => {
+                HistoryEntry { command, timestamp, working_directory, exit_code, duration_ms, pane_id }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitStatusFilter {
+    Success,
+    Failure, // a non-zero exit code
+}
+
+/// Every field narrows the result; entries come back oldest first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryFilter {
+    pub since: Option<u64>, // inclusive, in seconds like `HistoryEntry::timestamp`
+    pub until: Option<u64>, // exclusive
+    pub exit_status: Option<ExitStatusFilter>,
+    pub directory: Option<PathBuf>,
+    pub include_subdirectories: bool,
+    pub query: Option<String>, // substring of the command line
+    pub limit: Option<usize>, // the most recent matches
+}
+
+impl HistoryFilter {
+    fn matches(&self, entry: &HistoryEntry) -> bool {
+        if self.since.is_some_and(|since| entry.timestamp < since) || self.until.is_some_and(|until| entry.timestamp >= until) {
+            return false;
+        }
+        let status_matches = match self.exit_status {
+            None => true,
+            Some(ExitStatusFilter::Success) => entry.exit_code == Some(0),
+            Some(ExitStatusFilter::Failure) => entry.exit_code.is_some_and(|code| code != 0),
+        };
+        if !status_matches {
+            return false;
+        }
+        if let Some(directory) = &self.directory {
+            let in_directory = entry.working_directory.as_ref().is_some_and(|cwd| {
+                cwd == directory || (self.include_subdirectories && cwd.starts_with(directory))
+            });
+            if !in_directory {
+                return false;
+            }
+        }
+        self.query.as_ref().is_none_or(|query| entry.command.contains(query.as_str()))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,6 +137,38 @@ pub struct TerminalPane {
     pub split_info: Option<SplitInfo>,
     pub created_at: u64,
     pub last_activity: u64,
+    #[serde(default)]
+    pub terminal_id: Option<String>, // PTY session backing the pane, once attached
+    #[serde(default)]
+    pub cwd_host: Option<String>, // set while the shell reports a cwd on another machine
+    #[serde(default)]
+    pub has_activity: bool, // output arrived while the pane wasn't focused
+    #[serde(default)]
+    pub has_bell: bool, // the bell rang while the pane wasn't focused
+}
+
+/// A pane's shell working directory; `host` is set when the shell reported it from another machine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaneCwd {
+    pub path: String,
+    pub host: Option<String>,
+    pub remote: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaneAlertKind {
+    Activity,
+    Bell,
+}
+
+/// A background pane that just started showing an activity or bell indicator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaneAlert {
+    pub kind: PaneAlertKind,
+    pub session_id: String,
+    pub pane_id: String,
+    pub terminal_id: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,6 +182,8 @@ pub struct TerminalTab {
     pub last_accessed: u64,
     pub is_pinned: bool,
     pub color: Option<String>,
+    #[serde(default)]
+    pub working_directory: Option<PathBuf>, // inherited from the pane that was active when the tab opened
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -117,9 +249,107 @@ pub enum LayoutType {
     ThreeColumn,
     ThreeRow,
     Grid,
+    EvenHorizontal, // side by side, equal widths
+    EvenVertical,   // stacked, equal heights
+    MainVertical,   // large pane on the left, the rest stacked on the right
+    MainHorizontal, // large pane on top, the rest side by side below
     Custom,
 }
 
+// Share of the window the main pane gets in the main-* layouts
+const MAIN_PANE_RATIO: f32 = 0.6;
+
+pub const DEFAULT_MINIMUM_PANE_SIZE: PaneSize = PaneSize { rows: 3, columns: 10 };
+
+// Split `total` cells into `parts` near-equal spans; earlier spans take the remainder
+fn distribute(total: u16, parts: usize) -> Vec<u16> {
+    let parts_u16 = parts as u16;
+    (0..parts_u16).map(|i| total / parts_u16 + u16::from(i < total % parts_u16)).collect()
+}
+
+// (offset, span) pairs for consecutive spans
+fn offsets(spans: &[u16]) -> Vec<(u16, u16)> {
+    let mut offset = 0;
+    spans
+        .iter()
+        .map(|&span| {
+            let start = offset;
+            offset += span;
+            (start, span)
+        })
+        .collect()
+}
+
+/// Cell rectangles (column, row, columns, rows) for `count` panes tiled over a
+/// `total`-sized window. Rows of a grid that come up short stretch their panes
+/// to the full width, so there are never empty cells.
+fn layout_cells(layout_type: &LayoutType, count: usize, total: &PaneSize) -> Result<Vec<(u16, u16, u16, u16)>, String> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let full_rows = || offsets(&distribute(total.rows, count)).into_iter().map(|(y, h)| (0, y, total.columns, h)).collect();
+    let full_columns = || offsets(&distribute(total.columns, count)).into_iter().map(|(x, w)| (x, 0, w, total.rows)).collect();
+    let cells = match layout_type {
+        LayoutType::EvenHorizontal | LayoutType::TwoColumn | LayoutType::ThreeColumn => full_columns(),
+        LayoutType::EvenVertical | LayoutType::TwoRow | LayoutType::ThreeRow => full_rows(),
+        LayoutType::Grid => {
+            let columns = (count as f64).sqrt().ceil() as usize;
+            let rows = count.div_ceil(columns);
+            let mut cells = Vec::with_capacity(count);
+            for (row, (y, h)) in offsets(&distribute(total.rows, rows)).into_iter().enumerate() {
+                let in_row = columns.min(count - row * columns);
+                cells.extend(offsets(&distribute(total.columns, in_row)).into_iter().map(|(x, w)| (x, y, w, h)));
+            }
+            cells
+        }
+        LayoutType::MainVertical | LayoutType::MainHorizontal if count == 1 => vec![(0, 0, total.columns, total.rows)],
+        LayoutType::MainVertical => {
+            let main = (total.columns as f32 * MAIN_PANE_RATIO).round() as u16;
+            let mut cells = vec![(0, 0, main, total.rows)];
+            cells.extend(offsets(&distribute(total.rows, count - 1)).into_iter().map(|(y, h)| (main, y, total.columns - main, h)));
+            cells
+        }
+        LayoutType::MainHorizontal => {
+            let main = (total.rows as f32 * MAIN_PANE_RATIO).round() as u16;
+            let mut cells = vec![(0, 0, total.columns, main)];
+            cells.extend(offsets(&distribute(total.columns, count - 1)).into_iter().map(|(x, w)| (x, main, w, total.rows - main)));
+            cells
+        }
+        LayoutType::Single if count == 1 => vec![(0, 0, total.columns, total.rows)],
+        LayoutType::Single | LayoutType::Custom => {
+            return Err(format!("{:?} layout can't be applied to {} panes", layout_type, count));
+        }
+    };
+    Ok(cells)
+}
+
+/// Balanced positions (as fractions of the window) and sizes for `count` panes.
+/// Fails if any pane would be smaller than `minimum`.
+pub fn compute_layout(
+    layout_type: &LayoutType,
+    count: usize,
+    total: &PaneSize,
+    minimum: &PaneSize,
+) -> Result<Vec<(PanePosition, PaneSize)>, String> {
+    let cells = layout_cells(layout_type, count, total)?;
+    if let Some((_, _, w, h)) = cells.iter().find(|(_, _, w, h)| *w < minimum.columns || *h < minimum.rows) {
+        return Err(format!(
+            "Not enough room for {} panes in a {:?} layout: a pane would be {}x{}, minimum is {}x{}",
+            count, layout_type, w, h, minimum.columns, minimum.rows
+        ));
+    }
+    let (columns, rows) = (total.columns.max(1) as f32, total.rows.max(1) as f32);
+    Ok(cells
+        .into_iter()
+        .map(|(x, y, w, h)| {
+            (
+                PanePosition { x: x as f32 / columns, y: y as f32 / rows, width: w as f32 / columns, height: h as f32 / rows },
+                PaneSize { rows: h, columns: w },
+            )
+        })
+        .collect())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Split {
     pub split_id: String,
@@ -163,6 +393,22 @@ pub struct SessionTemplate {
     pub tags: Vec<String>,
 }
 
+/// A pane created from a template, with what its shell should start with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplatePane {
+    pub pane_id: String,
+    pub working_directory: PathBuf,
+    pub initial_commands: Vec<String>,
+    pub terminal_id: Option<String>, // filled in once a PTY is attached
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedTemplate {
+    pub session_id: String,
+    pub environment_variables: HashMap<String, String>,
+    pub panes: Vec<TemplatePane>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TabGroup {
     pub group_id: String,
@@ -240,6 +486,7 @@ pub enum TerminalEventType {
     CommandExecuted,
     ProcessStarted,
     ProcessEnded,
+    InputBroadcastChanged,
 }
 
 pub struct AdvancedTerminalManager {
@@ -248,6 +495,7 @@ pub struct AdvancedTerminalManager {
     templates: Arc<Mutex<HashMap<String, SessionTemplate>>>,
     workspaces: Arc<Mutex<HashMap<String, WorkspaceConfig>>>,
     active_session_id: Arc<Mutex<Option<String>>>,
+    alert_on_bell_only: Arc<Mutex<bool>>, // plain output doesn't flag background panes
     event_history: Arc<Mutex<VecDeque<TerminalEvent>>>,
     event_sender: Arc<Mutex<Option<mpsc::UnboundedSender<TerminalEvent>>>>,
     next_session_id: Arc<Mutex<u64>>,
@@ -263,6 +511,7 @@ impl AdvancedTerminalManager {
             templates: Arc::new(Mutex::new(HashMap::new())),
             workspaces: Arc::new(Mutex::new(HashMap::new())),
             active_session_id: Arc::new(Mutex::new(None)),
+            alert_on_bell_only: Arc::new(Mutex::new(false)),
             event_history: Arc::new(Mutex::new(VecDeque::with_capacity(1000))),
             event_sender: Arc::new(Mutex::new(None)),
             next_session_id: Arc::new(Mutex::new(1)),
@@ -352,6 +601,10 @@ impl AdvancedTerminalManager {
             split_info: None,
             created_at: timestamp,
             last_activity: timestamp,
+            terminal_id: None,
+            cwd_host: None,
+            has_activity: false,
+            has_bell: false,
         };
 
         let default_tab = TerminalTab {
@@ -364,6 +617,7 @@ impl AdvancedTerminalManager {
             last_accessed: timestamp,
             is_pinned: false,
             color: None,
+            working_directory: None,
         };
 
         let layout = PaneLayout {
@@ -395,6 +649,7 @@ impl AdvancedTerminalManager {
                 git_branch: None,
                 custom_properties: HashMap::new(),
             },
+            input_broadcast: Vec::new(),
         };
 
         {
@@ -474,7 +729,7 @@ impl AdvancedTerminalManager {
             *active_session = Some(session_id.to_string());
         }
 
-        // Update session's last accessed time
+        // Update session's last accessed time; its focused pane is now seen
         {
             let mut sessions = self.sessions.lock().unwrap();
             if let Some(session) = sessions.get_mut(session_id) {
@@ -482,6 +737,11 @@ impl AdvancedTerminalManager {
                     .duration_since(UNIX_EPOCH)
                     .unwrap()
                     .as_secs();
+                let active_pane = session.active_pane_id.clone();
+                if let Some(pane) = session.panes.iter_mut().find(|p| Some(&p.pane_id) == active_pane.as_ref()) {
+                    pane.has_activity = false;
+                    pane.has_bell = false;
+                }
             }
         }
 
@@ -523,6 +783,10 @@ impl AdvancedTerminalManager {
             split_info: None,
             created_at: timestamp,
             last_activity: timestamp,
+            terminal_id: None,
+            cwd_host: None,
+            has_activity: false,
+            has_bell: false,
         };
 
         {
@@ -604,6 +868,10 @@ impl AdvancedTerminalManager {
                 }),
                 created_at: timestamp,
                 last_activity: timestamp,
+                terminal_id: None,
+                cwd_host: None,
+                has_activity: false,
+                has_bell: false,
             };
 
             session.panes.push(new_pane);
@@ -635,6 +903,62 @@ impl AdvancedTerminalManager {
         }
     }
 
+    /// Rearrange every pane of a session into `layout_type`, in focus order. The
+    /// window size is inferred from the current panes; split history is discarded
+    /// since the panes no longer form a binary split tree.
+    pub fn apply_layout(&self, session_id: &str, layout_type: LayoutType, minimum: Option<PaneSize>) -> Result<Vec<TerminalPane>, String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(session_id).ok_or_else(|| format!("Session {} not found", session_id))?;
+
+        let total = session
+            .panes
+            .iter()
+            .filter(|p| p.position.width > 0.0 && p.position.height > 0.0)
+            .map(|p| PaneSize {
+                rows: (p.size.rows as f32 / p.position.height).round() as u16,
+                columns: (p.size.columns as f32 / p.position.width).round() as u16,
+            })
+            .max_by_key(|size| size.rows as u32 * size.columns as u32)
+            .unwrap_or(PaneSize { rows: 24, columns: 80 });
+
+        let mut order: Vec<usize> = session
+            .layout
+            .focus_order
+            .iter()
+            .filter_map(|id| session.panes.iter().position(|p| &p.pane_id == id))
+            .collect();
+        for index in 0..session.panes.len() {
+            if !order.contains(&index) {
+                order.push(index);
+            }
+        }
+
+        let minimum = minimum.unwrap_or(DEFAULT_MINIMUM_PANE_SIZE);
+        let placements = compute_layout(&layout_type, order.len(), &total, &minimum)?;
+        for (index, (position, size)) in order.into_iter().zip(placements) {
+            let pane = &mut session.panes[index];
+            pane.position = position;
+            pane.size = size;
+            pane.split_info = None;
+        }
+        session.layout.splits.clear();
+        session.layout.layout_type = layout_type.clone();
+
+        self.emit_event(TerminalEvent {
+            event_type: TerminalEventType::PaneResized,
+            session_id: session_id.to_string(),
+            pane_id: None,
+            tab_id: None,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            data: [("layout_type".to_string(), serde_json::json!(layout_type))].into_iter().collect(),
+        });
+
+        Ok(session.panes.clone())
+    }
+
     fn calculate_split_layout(
         &self,
         original_pos: &PanePosition,
@@ -733,6 +1057,12 @@ impl AdvancedTerminalManager {
             // Remove from focus order
             session.layout.focus_order.retain(|id| id != pane_id);
 
+            // A broadcast group of one is just normal input
+            session.input_broadcast.retain(|id| id != pane_id);
+            if session.input_broadcast.len() < 2 {
+                session.input_broadcast.clear();
+            }
+
             // Remove related splits
             session.layout.splits.retain(|split| {
                 split.first_pane != pane_id && split.second_pane != pane_id
@@ -759,10 +1089,11 @@ impl AdvancedTerminalManager {
     pub fn focus_pane(&self, session_id: &str, pane_id: &str) -> Result<(), String> {
         let mut sessions = self.sessions.lock().unwrap();
         if let Some(session) = sessions.get_mut(session_id) {
-            // Verify pane exists
-            if !session.panes.iter().any(|p| p.pane_id == pane_id) {
+            let Some(pane) = session.panes.iter_mut().find(|p| p.pane_id == pane_id) else {
                 return Err(format!("Pane {} not found", pane_id));
-            }
+            };
+            pane.has_activity = false;
+            pane.has_bell = false;
 
             session.active_pane_id = Some(pane_id.to_string());
 
@@ -784,6 +1115,225 @@ impl AdvancedTerminalManager {
         }
     }
 
+    /// Link a pane to the PTY session that renders in it.
+    pub fn attach_pane_terminal(&self, session_id: &str, pane_id: &str, terminal_id: &str) -> Result<(), String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(session_id).ok_or_else(|| format!("Session {} not found", session_id))?;
+        let pane = session
+            .panes
+            .iter_mut()
+            .find(|p| p.pane_id == pane_id)
+            .ok_or_else(|| format!("Pane {} not found", pane_id))?;
+        pane.terminal_id = Some(terminal_id.to_string());
+        Ok(())
+    }
+
+    pub fn pane_terminal_id(&self, session_id: &str, pane_id: &str) -> Option<String> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions.get(session_id)?.panes.iter().find(|p| p.pane_id == pane_id)?.terminal_id.clone()
+    }
+
+    pub fn active_pane_terminal_id(&self, session_id: &str) -> Option<String> {
+        let sessions = self.sessions.lock().unwrap();
+        let session = sessions.get(session_id)?;
+        let active = session.active_pane_id.as_ref()?;
+        session.panes.iter().find(|p| &p.pane_id == active)?.terminal_id.clone()
+    }
+
+    /// Track the shell's cwd on whichever pane shows `terminal_id`. A remote cwd
+    /// only records the host, so the pane keeps its last local directory for splits.
+    pub fn update_terminal_working_directory(&self, terminal_id: &str, working_directory: PathBuf, remote_host: Option<String>) {
+        let mut sessions = self.sessions.lock().unwrap();
+        for session in sessions.values_mut() {
+            for pane in session.panes.iter_mut().filter(|p| p.terminal_id.as_deref() == Some(terminal_id)) {
+                if remote_host.is_none() {
+                    pane.working_directory = working_directory.clone();
+                }
+                pane.cwd_host = remote_host.clone();
+            }
+        }
+    }
+
+    /// Add a command that finished in `terminal_id` to the history of the
+    /// session showing it.
+    pub fn record_command(&self, terminal_id: &str, event: &crate::shell_hooks::CommandEvent) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let Some((session, pane_id)) = sessions.values_mut().find_map(|session| {
+            let pane_id = session.panes.iter().find(|p| p.terminal_id.as_deref() == Some(terminal_id))?.pane_id.clone();
+            Some((session, pane_id))
+        }) else {
+            return;
+        };
+        let entry = HistoryEntry {
+            command: event.command.trim().to_string(),
+            timestamp: event.timestamp / 1000,
+            working_directory: (!event.working_dir.is_empty()).then(|| PathBuf::from(&event.working_dir)),
+            exit_code: event.exit_code,
+            duration_ms: event.duration_ms,
+            pane_id: Some(pane_id.clone()),
+        };
+        session.command_history.push(entry);
+        if session.command_history.len() > MAX_SESSION_HISTORY {
+            let overflow = session.command_history.len() - MAX_SESSION_HISTORY;
+            session.command_history.drain(..overflow);
+        }
+        let session_id = session.session_id.clone();
+        drop(sessions);
+
+        let mut data = HashMap::new();
+        data.insert("command".to_string(), serde_json::Value::String(event.command.trim().to_string()));
+        data.insert("exit_code".to_string(), serde_json::json!(event.exit_code));
+        self.emit_event(TerminalEvent {
+            event_type: TerminalEventType::CommandExecuted,
+            session_id,
+            pane_id: Some(pane_id),
+            tab_id: None,
+            timestamp: event.timestamp / 1000,
+            data,
+        });
+    }
+
+    pub fn get_rich_history(&self, session_id: &str, filter: &HistoryFilter) -> Result<Vec<HistoryEntry>, String> {
+        let sessions = self.sessions.lock().unwrap();
+        let session = sessions.get(session_id).ok_or_else(|| format!("Session {} not found", session_id))?;
+        let mut entries: Vec<HistoryEntry> = session.command_history.iter().filter(|entry| filter.matches(entry)).cloned().collect();
+        if let Some(limit) = filter.limit {
+            entries.drain(..entries.len().saturating_sub(limit));
+        }
+        Ok(entries)
+    }
+
+    /// Just the command lines, oldest first.
+    pub fn get_command_history(&self, session_id: &str) -> Result<Vec<String>, String> {
+        let sessions = self.sessions.lock().unwrap();
+        let session = sessions.get(session_id).ok_or_else(|| format!("Session {} not found", session_id))?;
+        Ok(session.command_history.iter().map(|entry| entry.command.clone()).collect())
+    }
+
+    pub fn set_alert_on_bell_only(&self, bell_only: bool) {
+        *self.alert_on_bell_only.lock().unwrap() = bell_only;
+    }
+
+    /// Flag the background panes showing `terminal_id` after it printed output
+    /// (and rang the bell, if `bell`). Returns the indicators that just turned
+    /// on; a pane already flagged isn't reported again until it's focused.
+    pub fn note_terminal_output(&self, terminal_id: &str, bell: bool) -> Vec<PaneAlert> {
+        let bell_only = *self.alert_on_bell_only.lock().unwrap();
+        let active_session = self.active_session_id.lock().unwrap().clone();
+        let mut alerts = Vec::new();
+        let mut sessions = self.sessions.lock().unwrap();
+        for session in sessions.values_mut() {
+            let session_active = active_session.as_deref() == Some(session.session_id.as_str());
+            for pane in session.panes.iter_mut().filter(|p| p.terminal_id.as_deref() == Some(terminal_id)) {
+                if session_active && session.active_pane_id.as_deref() == Some(pane.pane_id.as_str()) {
+                    continue;
+                }
+                let mut raise = |kind| alerts.push(PaneAlert {
+                    kind,
+                    session_id: session.session_id.clone(),
+                    pane_id: pane.pane_id.clone(),
+                    terminal_id: terminal_id.to_string(),
+                });
+                if bell && !pane.has_bell {
+                    pane.has_bell = true;
+                    raise(PaneAlertKind::Bell);
+                }
+                if !bell_only && !pane.has_activity {
+                    pane.has_activity = true;
+                    raise(PaneAlertKind::Activity);
+                }
+            }
+        }
+        alerts
+    }
+
+    pub fn get_pane(&self, session_id: &str, pane_id: &str) -> Option<TerminalPane> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions.get(session_id)?.panes.iter().find(|p| p.pane_id == pane_id).cloned()
+    }
+
+    /// Synchronize input across `pane_ids`, like tmux's synchronize-panes: input
+    /// written to any of them goes to all of them.
+    pub fn set_input_broadcast(&self, session_id: &str, pane_ids: Vec<String>) -> Result<(), String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(session_id).ok_or_else(|| format!("Session {} not found", session_id))?;
+        let mut group: Vec<String> = Vec::new();
+        for pane_id in pane_ids {
+            let pane = session
+                .panes
+                .iter()
+                .find(|p| p.pane_id == pane_id)
+                .ok_or_else(|| format!("Pane {} not found", pane_id))?;
+            if pane.status == PaneStatus::Closed {
+                return Err(format!("Pane {} is closed", pane_id));
+            }
+            if pane.terminal_id.is_none() {
+                return Err(format!("Pane {} has no terminal attached", pane_id));
+            }
+            if !group.contains(&pane_id) {
+                group.push(pane_id);
+            }
+        }
+        if group.len() < 2 {
+            return Err("Broadcasting input needs at least two panes".to_string());
+        }
+        session.input_broadcast = group.clone();
+
+        self.emit_event(TerminalEvent {
+            event_type: TerminalEventType::InputBroadcastChanged,
+            session_id: session_id.to_string(),
+            pane_id: None,
+            tab_id: None,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            data: [("pane_ids".to_string(), serde_json::json!(group))].into_iter().collect(),
+        });
+        Ok(())
+    }
+
+    pub fn clear_input_broadcast(&self, session_id: &str) -> Result<(), String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(session_id).ok_or_else(|| format!("Session {} not found", session_id))?;
+        session.input_broadcast.clear();
+
+        self.emit_event(TerminalEvent {
+            event_type: TerminalEventType::InputBroadcastChanged,
+            session_id: session_id.to_string(),
+            pane_id: None,
+            tab_id: None,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            data: [("pane_ids".to_string(), serde_json::json!([]))].into_iter().collect(),
+        });
+        Ok(())
+    }
+
+    /// Terminals that input for `terminal_id` should reach: the whole broadcast
+    /// group when its pane is in one (closed panes excluded), otherwise just itself.
+    pub fn input_targets(&self, terminal_id: &str) -> Vec<String> {
+        let sessions = self.sessions.lock().unwrap();
+        for session in sessions.values() {
+            let in_group = session
+                .panes
+                .iter()
+                .any(|p| p.terminal_id.as_deref() == Some(terminal_id) && session.input_broadcast.contains(&p.pane_id));
+            if in_group {
+                return session
+                    .input_broadcast
+                    .iter()
+                    .filter_map(|id| session.panes.iter().find(|p| &p.pane_id == id))
+                    .filter(|p| p.status != PaneStatus::Closed)
+                    .filter_map(|p| p.terminal_id.clone())
+                    .collect();
+            }
+        }
+        vec![terminal_id.to_string()]
+    }
+
     // Tab Management
     pub fn create_tab(&self, session_id: &str, title: Option<String>) -> Result<String, String> {
         let tab_id = self.generate_tab_id();
@@ -792,7 +1342,7 @@ impl AdvancedTerminalManager {
             .unwrap()
             .as_secs();
 
-        let new_tab = TerminalTab {
+        let mut new_tab = TerminalTab {
             tab_id: tab_id.clone(),
             title: title.unwrap_or_else(|| "New Tab".to_string()),
             icon: None,
@@ -802,11 +1352,17 @@ impl AdvancedTerminalManager {
             last_accessed: timestamp,
             is_pinned: false,
             color: None,
+            working_directory: None,
         };
 
         {
             let mut sessions = self.sessions.lock().unwrap();
             if let Some(session) = sessions.get_mut(session_id) {
+                new_tab.working_directory = session
+                    .active_pane_id
+                    .as_ref()
+                    .and_then(|id| session.panes.iter().find(|p| &p.pane_id == id))
+                    .map(|p| p.working_directory.clone());
                 session.tabs.push(new_tab);
             } else {
                 return Err(format!("Session {} not found", session_id));
@@ -949,7 +1505,9 @@ impl AdvancedTerminalManager {
             pane.current_command = None;
             pane.process_id = None;
             pane.status = PaneStatus::Inactive;
+            pane.terminal_id = None;
         }
+        restored_session.input_broadcast.clear();
 
         {
             let mut sessions = self.sessions.lock().unwrap();
@@ -1039,7 +1597,10 @@ impl AdvancedTerminalManager {
         templates.values().cloned().collect()
     }
 
-    pub fn apply_template(&self, template_id: &str, session_name: Option<String>) -> Result<String, String> {
+    /// Create a session shaped like the template: one pane per template pane, in
+    /// the template's focus order, each with its working directory and initial
+    /// commands. Starting shells and running the commands is up to the caller.
+    pub fn apply_template(&self, template_id: &str, session_name: Option<String>) -> Result<AppliedTemplate, String> {
         let template = {
             let templates = self.templates.lock().unwrap();
             templates.get(template_id).cloned()
@@ -1048,24 +1609,48 @@ impl AdvancedTerminalManager {
 
         // Create new session based on template
         let session_id = self.create_session(session_name, Some(template_id.to_string()))?;
+        let first_pane = self
+            .get_session(&session_id)
+            .and_then(|session| session.active_pane_id)
+            .ok_or_else(|| format!("Session {} has no pane", session_id))?;
+
+        let mut template_panes = template.pane_layout.focus_order.clone();
+        if template_panes.is_empty() {
+            template_panes.push(template.pane_layout.root_pane.clone());
+        }
+        let mut panes = Vec::with_capacity(template_panes.len());
+        for (index, template_pane) in template_panes.iter().enumerate() {
+            let working_directory = template.working_directories.get(template_pane).cloned();
+            let pane_id = if index == 0 {
+                first_pane.clone()
+            } else {
+                self.create_pane(&session_id, working_directory.clone())?
+            };
+            panes.push(TemplatePane {
+                pane_id,
+                working_directory: working_directory.unwrap_or_else(|| std::env::current_dir().unwrap_or_default()),
+                initial_commands: template.initial_commands.get(template_pane).cloned().unwrap_or_default(),
+                terminal_id: None,
+            });
+        }
 
         // Apply template configuration
         {
             let mut sessions = self.sessions.lock().unwrap();
             if let Some(session) = sessions.get_mut(&session_id) {
-                session.environment_variables.extend(template.environment_variables);
+                session.environment_variables.extend(template.environment_variables.clone());
                 session.metadata.tags = template.tags;
-                
+
                 // Update pane working directories
                 for pane in &mut session.panes {
-                    if let Some(working_dir) = template.working_directories.get(&pane.pane_id) {
-                        pane.working_directory = working_dir.clone();
+                    if let Some(applied) = panes.iter().find(|applied| applied.pane_id == pane.pane_id) {
+                        pane.working_directory = applied.working_directory.clone();
                     }
                 }
             }
         }
 
-        Ok(session_id)
+        Ok(AppliedTemplate { session_id, environment_variables: template.environment_variables, panes })
     }
 
     // Utility Functions
@@ -1139,6 +1724,11 @@ impl AdvancedTerminalManager {
         let new_session_id = self.generate_session_id();
         let mut imported_session = session;
         imported_session.session_id = new_session_id.clone();
+        // PTY sessions from another run don't exist here
+        for pane in &mut imported_session.panes {
+            pane.terminal_id = None;
+        }
+        imported_session.input_broadcast.clear();
 
         {
             let mut sessions = self.sessions.lock().unwrap();
@@ -1148,3 +1738,51 @@ impl AdvancedTerminalManager {
         Ok(new_session_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(columns: u16, rows: u16) -> PaneSize {
+        PaneSize { rows, columns }
+    }
+
+    #[test]
+    fn three_panes_tile_as_two_over_one() {
+        let cells = layout_cells(&LayoutType::Grid, 3, &window(80, 24)).unwrap();
+        assert_eq!(cells, [(0, 0, 40, 12), (40, 0, 40, 12), (0, 12, 80, 12)]);
+
+        let placements = compute_layout(&LayoutType::Grid, 3, &window(80, 24), &DEFAULT_MINIMUM_PANE_SIZE).unwrap();
+        let fractions: Vec<_> = placements.iter().map(|(p, _)| (p.x, p.y, p.width, p.height)).collect();
+        assert_eq!(fractions, [(0.0, 0.0, 0.5, 0.5), (0.5, 0.0, 0.5, 0.5), (0.0, 0.5, 1.0, 0.5)]);
+    }
+
+    #[test]
+    fn five_panes_tile_as_three_over_two_with_remainders_going_first() {
+        let cells = layout_cells(&LayoutType::Grid, 5, &window(80, 25)).unwrap();
+        assert_eq!(cells, [(0, 0, 27, 13), (27, 0, 27, 13), (54, 0, 26, 13), (0, 13, 40, 12), (40, 13, 40, 12)]);
+
+        // Every cell of the window is covered exactly once
+        let area: u32 = cells.iter().map(|&(_, _, w, h)| w as u32 * h as u32).sum();
+        assert_eq!(area, 80 * 25);
+    }
+
+    #[test]
+    fn layouts_that_would_squeeze_a_pane_below_the_minimum_are_refused() {
+        let minimum = DEFAULT_MINIMUM_PANE_SIZE;
+        assert!(compute_layout(&LayoutType::Grid, 5, &window(30, 6), &minimum).is_ok());
+        // The second grid row gets 2 rows against a minimum of 3
+        let err = compute_layout(&LayoutType::Grid, 5, &window(30, 5), &minimum).unwrap_err();
+        assert!(err.contains("a pane would be 15x2, minimum is 10x3"), "{}", err);
+        assert!(compute_layout(&LayoutType::EvenHorizontal, 9, &window(80, 24), &minimum).is_err());
+        assert!(compute_layout(&LayoutType::Single, 2, &window(80, 24), &minimum).is_err());
+    }
+
+    #[test]
+    fn main_layouts_give_the_first_pane_the_larger_share() {
+        let cells = layout_cells(&LayoutType::MainVertical, 3, &window(100, 24)).unwrap();
+        assert_eq!(cells, [(0, 0, 60, 24), (60, 0, 40, 12), (60, 12, 40, 12)]);
+        let cells = layout_cells(&LayoutType::MainHorizontal, 3, &window(80, 30)).unwrap();
+        assert_eq!(cells, [(0, 0, 80, 18), (0, 18, 40, 12), (40, 18, 40, 12)]);
+    }
+}
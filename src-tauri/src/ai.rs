@@ -20,6 +20,196 @@ pub struct AiResponse {
     pub text: String,
 }
 
+// Output sent for explanation keeps its head and tail; the middle is elided
+const EXPLAIN_OUTPUT_HEAD_LINES: usize = 80;
+const EXPLAIN_OUTPUT_TAIL_LINES: usize = 80;
+
+/// Builds an `explain_output` request from one command block. Callers redact
+/// the command and output before handing them over.
+pub fn explain_output_request(command: &str, output: &str, exit_code: Option<i32>, context: AiContext) -> AiRequest {
+    let lines: Vec<&str> = output.lines().collect();
+    let output = if lines.len() > EXPLAIN_OUTPUT_HEAD_LINES + EXPLAIN_OUTPUT_TAIL_LINES {
+        format!(
+            "{}\n[... {} lines omitted ...]\n{}",
+            lines[..EXPLAIN_OUTPUT_HEAD_LINES].join("\n"),
+            lines.len() - EXPLAIN_OUTPUT_HEAD_LINES - EXPLAIN_OUTPUT_TAIL_LINES,
+            lines[lines.len() - EXPLAIN_OUTPUT_TAIL_LINES..].join("\n")
+        )
+    } else {
+        output.to_string()
+    };
+    let exit = exit_code.map(|code| code.to_string()).unwrap_or_else(|| "unknown".into());
+    AiRequest {
+        task: "explain_output".into(),
+        user_input: format!("Command: {}\nExit code: {}\nOutput:\n{}", command, exit, output),
+        context,
+    }
+}
+
+/// A generated command plus why it was suggested and how risky it is. Blocked
+/// commands are still returned so the UI can show them, but must never be run
+/// without the user's explicit action.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CheckedCommandSuggestion {
+    pub command: String,
+    pub rationale: String,
+    pub assessment: crate::security::CommandRiskAssessment,
+    pub blocked: bool,
+}
+
+/// Split a `generate_command_explained` response into command and rationale.
+/// Models are asked for JSON; fenced or `#`-commented replies are handled too.
+pub fn parse_command_with_rationale(text: &str) -> (String, String) {
+    #[derive(Deserialize)]
+    struct Explained { command: String, #[serde(default)] rationale: String }
+
+    let trimmed = text.trim().trim_start_matches("```json").trim_start_matches("```").trim_end_matches("```").trim();
+    if let Ok(explained) = serde_json::from_str::<Explained>(trimmed) {
+        return (explained.command.trim().to_string(), explained.rationale.trim().to_string());
+    }
+
+    if let Some(start) = text.find("```") {
+        let after = &text[start + 3..];
+        let body_start = after.find('\n').map(|i| i + 1).unwrap_or(0);
+        if let Some(end) = after[body_start..].find("```") {
+            let command = after[body_start..body_start + end].trim().to_string();
+            let rationale = format!("{} {}", &text[..start], &after[body_start + end + 3..]);
+            return (command, rationale.split_whitespace().collect::<Vec<_>>().join(" "));
+        }
+    }
+
+    let mut command = String::new();
+    let mut rationale = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        match line.strip_prefix('#') {
+            Some(comment) => rationale.push(comment.trim()),
+            None if command.is_empty() => command = line.to_string(),
+            None => rationale.push(line),
+        }
+    }
+    (command, rationale.join(" "))
+}
+
+const LOCAL_EMBEDDING_DIMENSIONS: usize = 256;
+
+/// Where history embeddings come from, chosen with `EMBEDDING_PROVIDER`
+/// ("local", "openai" or "none"). Without it, the OpenAI-compatible API is used
+/// when `AI_API_KEY` is set and the local hashing model otherwise. The API is
+/// only ever used when the `remote_history_embeddings` setting allows it.
+#[derive(Clone, Debug)]
+pub enum EmbeddingProvider {
+    /// Feature-hashed word and character-trigram vectors; no network, rough but useful
+    Local,
+    OpenAICompatible { base_url: String, api_key: String, model: String },
+    Disabled,
+}
+
+impl EmbeddingProvider {
+    pub fn from_env(allow_remote: bool) -> Self {
+        Self::from_vars(|name| std::env::var(name).ok(), allow_remote)
+    }
+
+    /// The provider for the variables `var` looks up, so the choice can be made
+    /// without touching the process environment.
+    fn from_vars(var: impl Fn(&str) -> Option<String>, allow_remote: bool) -> Self {
+        let api_key = if allow_remote { var("AI_API_KEY").unwrap_or_default() } else { String::new() };
+        let api = || EmbeddingProvider::OpenAICompatible {
+            base_url: var("AI_BASE_URL").unwrap_or_else(|| "https://api.openai.com/v1".into()),
+            api_key: api_key.clone(),
+            model: var("AI_EMBEDDING_MODEL").unwrap_or_else(|| "text-embedding-3-small".into()),
+        };
+        match var("EMBEDDING_PROVIDER").unwrap_or_default().to_lowercase().as_str() {
+            "none" | "off" => EmbeddingProvider::Disabled,
+            "local" => EmbeddingProvider::Local,
+            "openai" | "openai-compatible" if !api_key.is_empty() => api(),
+            "openai" | "openai-compatible" => EmbeddingProvider::Disabled,
+            _ if !api_key.is_empty() => api(),
+            _ => EmbeddingProvider::Local,
+        }
+    }
+
+    /// Identifies the vector space; an index built by another provider must be rebuilt.
+    pub fn id(&self) -> String {
+        match self {
+            EmbeddingProvider::Local => format!("local-fnv-{}", LOCAL_EMBEDDING_DIMENSIONS),
+            EmbeddingProvider::OpenAICompatible { base_url, model, .. } => format!("{}#{}", base_url, model),
+            EmbeddingProvider::Disabled => "disabled".into(),
+        }
+    }
+
+    pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        match self {
+            EmbeddingProvider::Local => Ok(texts.iter().map(|text| local_embedding(text)).collect()),
+            EmbeddingProvider::OpenAICompatible { base_url, api_key, model } => {
+                call_openai_embeddings(base_url, api_key, model, texts).await
+            }
+            EmbeddingProvider::Disabled => Err("Embeddings are disabled".into()),
+        }
+    }
+}
+
+// 64-bit FNV-1a. Local vectors are persisted, so the feature hash must not
+// change between builds the way std's DefaultHasher may.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+fn local_embedding(text: &str) -> Vec<f32> {
+    let mut vector = vec![0f32; LOCAL_EMBEDDING_DIMENSIONS];
+    let mut add = |feature: &str, weight: f32| {
+        let hash = fnv1a(feature.as_bytes());
+        let sign = if hash & 1 == 0 { 1.0 } else { -1.0 };
+        vector[(hash >> 1) as usize % LOCAL_EMBEDDING_DIMENSIONS] += sign * weight;
+    };
+    let lower = text.to_lowercase();
+    for word in lower.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+        add(word, 2.0);
+        let chars: Vec<char> = format!(" {} ", word).chars().collect();
+        for trigram in chars.windows(3) {
+            add(&trigram.iter().collect::<String>(), 1.0);
+        }
+    }
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+#[derive(Serialize)]
+struct OpenAiEmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct OpenAiEmbeddingResponse {
+    data: Vec<OpenAiEmbedding>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiEmbedding { embedding: Vec<f32>, index: usize }
+
+async fn call_openai_embeddings(base: &str, key: &str, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    let url = format!("{}/embeddings", base.trim_end_matches('/'));
+    let resp = reqwest::Client::new()
+        .post(url)
+        .bearer_auth(key)
+        .json(&OpenAiEmbeddingRequest { model, input: texts })
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("Embedding error: {}", resp.status()));
+    }
+    let mut parsed: OpenAiEmbeddingResponse = resp.json().await.map_err(|e| e.to_string())?;
+    if parsed.data.len() != texts.len() {
+        return Err("Embedding response size mismatch".into());
+    }
+    parsed.data.sort_by_key(|e| e.index);
+    Ok(parsed.data.into_iter().map(|e| e.embedding).collect())
+}
+
 pub struct AiClient {
     provider: AiProvider,
 }
@@ -68,6 +258,20 @@ fn mock_response(req: AiRequest) -> AiResponse {
             trim_error(&req.user_input),
             mock_fix_suggestion(&req.user_input)
         ),
+        "generate_command_explained" => serde_json::json!({
+            "command": mock_guess_command(&req.user_input),
+            "rationale": format!("Closest known command for \"{}\"", req.user_input),
+        })
+        .to_string(),
+        "explain_output" => {
+            let command = req.user_input.lines().next().unwrap_or_default().trim_start_matches("Command: ").to_string();
+            let output_lines = req.user_input.lines().skip(3).count();
+            format!("# What happened\n`{}` ran and printed {} line(s) of output.\n# Next steps\nConsider: {}",
+                command,
+                output_lines,
+                mock_next_step(&command)
+            )
+        }
         "suggest_next" => {
             let last = req.context.recent_commands.last().cloned().unwrap_or_else(|| "(no recent commands)".into());
             format!("# Next steps\nYou recently ran: {}\nConsider: {}",
@@ -130,6 +334,8 @@ async fn call_openai_compatible(base: &str, key: &str, model: &str, req: AiReque
         "generate_command" => "You are a helpful terminal AI. Respond with a single shell command and a short explanation if needed.",
         "explain_error" => "You explain terminal errors concisely and propose a fix.",
         "suggest_next" => "You propose next terminal commands based on context.",
+        "generate_command_explained" => "You are a helpful terminal AI. Respond only with JSON of the form {\"command\": \"<single shell command>\", \"rationale\": \"<one or two sentences on why this command fits>\"}.",
+        "explain_output" => "You explain a terminal command's output: what the command did, what the output means, and likely next steps. Be concise.",
         _ => "You are an assistant.",
     };
     let ctx = format!(
@@ -165,3 +371,42 @@ async fn call_openai_compatible(base: &str, key: &str, model: &str, req: AiReque
     let text = parsed.choices.get(0).map(|c| c.message.content.clone()).unwrap_or_default();
     Ok(AiResponse { text })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_embeddings_use_a_fixed_hash() {
+        // Published FNV-1a test vectors
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(local_embedding("resize image"), local_embedding("resize image"));
+    }
+
+    fn provider(vars: &[(&str, &str)], allow_remote: bool) -> EmbeddingProvider {
+        let vars: std::collections::HashMap<&str, &str> = vars.iter().cloned().collect();
+        EmbeddingProvider::from_vars(|name| vars.get(name).map(|value| value.to_string()), allow_remote)
+    }
+
+    #[test]
+    fn remote_embeddings_need_opt_in() {
+        let key = [("AI_API_KEY", "sk-test")];
+        assert!(matches!(provider(&key, false), EmbeddingProvider::Local));
+        match provider(&key, true) {
+            EmbeddingProvider::OpenAICompatible { base_url, api_key, model } => {
+                assert_eq!((base_url.as_str(), api_key.as_str(), model.as_str()), ("https://api.openai.com/v1", "sk-test", "text-embedding-3-small"));
+            }
+            other => panic!("expected the API, got {:?}", other),
+        }
+        assert!(matches!(provider(&[], true), EmbeddingProvider::Local));
+    }
+
+    #[test]
+    fn an_explicit_provider_wins_over_the_key() {
+        assert!(matches!(provider(&[("AI_API_KEY", "sk-test"), ("EMBEDDING_PROVIDER", "Local")], true), EmbeddingProvider::Local));
+        assert!(matches!(provider(&[("AI_API_KEY", "sk-test"), ("EMBEDDING_PROVIDER", "off")], true), EmbeddingProvider::Disabled));
+        // Asking for the API without being allowed to use it turns embeddings off rather than going local
+        assert!(matches!(provider(&[("AI_API_KEY", "sk-test"), ("EMBEDDING_PROVIDER", "openai")], false), EmbeddingProvider::Disabled));
+    }
+}
@@ -4,7 +4,7 @@ use crate::terminal_types::{TerminalCapabilities, ColorSupport};
 use base64::{Engine as _, engine::general_purpose};
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -25,29 +25,74 @@ impl Color {
     pub fn magenta() -> Self { Color::new(255, 0, 255) }
     pub fn cyan() -> Self { Color::new(0, 255, 255) }
     pub fn white() -> Self { Color::new(255, 255, 255) }
+
+    /// Entry `index` of the xterm 256-color palette. The first eight match the
+    /// basic SGR colors above.
+    pub fn indexed(index: u8) -> Self {
+        const BRIGHT: [(u8, u8, u8); 8] = [
+            (128, 128, 128), (255, 85, 85), (85, 255, 85), (255, 255, 85),
+            (85, 85, 255), (255, 85, 255), (85, 255, 255), (255, 255, 255),
+        ];
+        const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+        match index {
+            0..=7 => [Self::black, Self::red, Self::green, Self::yellow, Self::blue, Self::magenta, Self::cyan, Self::white][index as usize](),
+            8..=15 => {
+                let (r, g, b) = BRIGHT[index as usize - 8];
+                Color::new(r, g, b)
+            }
+            16..=231 => {
+                let cube = index - 16;
+                Color::new(LEVELS[(cube / 36) as usize], LEVELS[(cube / 6 % 6) as usize], LEVELS[(cube % 6) as usize])
+            }
+            _ => {
+                let level = 8 + (index - 232) * 10;
+                Color::new(level, level, level)
+            }
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnderlineStyle {
+    #[default]
+    None,
+    Single,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct CharAttributes {
     pub bold: bool,
+    pub dim: bool,
     pub italic: bool,
-    pub underline: bool,
+    pub underline: UnderlineStyle,
+    pub blink: bool,
     pub strikethrough: bool,
     pub reverse: bool,
+    pub hidden: bool,
     pub fg_color: Option<Color>,
     pub bg_color: Option<Color>,
+    pub underline_color: Option<Color>, // None draws the underline in the text color
 }
 
 impl Default for CharAttributes {
     fn default() -> Self {
         CharAttributes {
             bold: false,
+            dim: false,
             italic: false,
-            underline: false,
+            underline: UnderlineStyle::None,
+            blink: false,
             strikethrough: false,
             reverse: false,
+            hidden: false,
             fg_color: None,
             bg_color: None,
+            underline_color: None,
         }
     }
 }
@@ -72,6 +117,11 @@ pub enum AnsiCommand {
     CursorColumn(u16),
     CursorSave,
     CursorRestore,
+    LineFeed,     // \n or ESC D: down a line, scrolling the region at its bottom margin
+    ReverseIndex, // ESC M: up a line, scrolling the region at its top margin
+    Tab(u16),           // \t or CHT: forward this many tab stops
+    SetTabStop,         // HTS (ESC H) at the cursor column
+    ClearTabStop(bool), // TBC: all stops when true (\e[3g), else the cursor column's (\e[g)
     
     // Cursor styles
     SetCursorStyle(CursorStyle),
@@ -90,8 +140,8 @@ pub enum AnsiCommand {
     EnterAlternateScreen,
     ExitAlternateScreen,
     
-    // Text attributes
-    SetGraphicsMode(Vec<u8>),
+    // Text attributes: each SGR parameter with its colon-separated subparameters
+    SetGraphicsMode(Vec<Vec<u16>>),
     
     // Scrolling
     ScrollUp(u16),
@@ -130,6 +180,9 @@ pub enum AnsiCommand {
     
     // Hyperlinks
     SetHyperlink(String, String), // URL, text
+
+    // Current directory reported by the shell (OSC 7): path, host
+    SetWorkingDirectory(String, Option<String>),
     
     // Images
     DisplayImage(ImageData),
@@ -139,6 +192,17 @@ pub enum AnsiCommand {
     BeginSynchronizedUpdate,
     EndSynchronizedUpdate,
     
+    // Queries the terminal answers through the parser's response queue
+    DeviceStatusReport,            // \e[5n
+    CursorPositionReport,          // \e[6n
+    PrimaryDeviceAttributes,       // \e[c
+    SecondaryDeviceAttributes,     // \e[>c
+    RequestMode(u16, bool),        // DECRQM \e[?mode$p: mode, private (DEC) mode
+
+    // Resets
+    SoftReset, // DECSTR \e[!p: attributes, margins and modes, keeping the screen
+    FullReset, // RIS \ec: a soft reset that also clears the screen
+
     // Device Control Strings
     DeviceControlString(String),
     
@@ -146,6 +210,9 @@ pub enum AnsiCommand {
     Bell,
     VisualBell,
     
+    // Semantic prompt marks (OSC 133)
+    SemanticPromptMark(PromptMark),
+    
     // Unrecognized escape sequence
     Unknown(String),
 }
@@ -160,6 +227,15 @@ pub enum CursorStyle {
     BlinkingBar,
 }
 
+// OSC 133 A/B/C/D as emitted by shell integration scripts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PromptMark {
+    PromptStart,
+    CommandStart,
+    OutputStart,
+    CommandEnd(Option<i32>), // exit code
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MouseReportMode {
     X10,
@@ -195,6 +271,67 @@ pub struct AnsiParser {
     hyperlink_stack: Vec<HyperlinkParams>,
     in_synchronized_update: bool,
     osc_params: HashMap<String, String>,
+    responses: Vec<u8>, // replies to queries, waiting to be written back to the PTY
+    sampled_chars: usize,
+    garbage_chars: usize,
+    binary_detected: bool,
+    charsets: [Charset; 2], // designated into G0 and G1
+    shifted_out: bool,      // SO selected G1, SI goes back to G0
+}
+
+// Output is checked for binary garbage in spans of this many characters...
+const BINARY_SAMPLE_CHARS: usize = 512;
+// ...and a span counts as binary when at least this share of it is stray
+// control bytes or invalid UTF-8
+const BINARY_GARBAGE_PERCENT: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Charset {
+    Ascii,
+    DecSpecialGraphics, // \e(0, the line-drawing set
+}
+
+impl Charset {
+    fn translate(self, ch: char) -> char {
+        if self == Charset::Ascii {
+            return ch;
+        }
+        match ch {
+            '_' => '\u{a0}',
+            '`' => '◆',
+            'a' => '▒',
+            'b' => '␉',
+            'c' => '␌',
+            'd' => '␍',
+            'e' => '␊',
+            'f' => '°',
+            'g' => '±',
+            'h' => '␤',
+            'i' => '␋',
+            'j' => '┘',
+            'k' => '┐',
+            'l' => '┌',
+            'm' => '└',
+            'n' => '┼',
+            'o' => '⎺',
+            'p' => '⎻',
+            'q' => '─',
+            'r' => '⎼',
+            's' => '⎽',
+            't' => '├',
+            'u' => '┤',
+            'v' => '┴',
+            'w' => '┬',
+            'x' => '│',
+            'y' => '≤',
+            'z' => '≥',
+            '{' => 'π',
+            '|' => '≠',
+            '}' => '£',
+            '~' => '·',
+            _ => ch,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -225,18 +362,45 @@ impl AnsiParser {
             hyperlink_stack: Vec::new(),
             in_synchronized_update: false,
             osc_params: HashMap::new(),
+            responses: Vec::new(),
+            sampled_chars: 0,
+            garbage_chars: 0,
+            binary_detected: false,
+            charsets: [Charset::Ascii; 2],
+            shifted_out: false,
         }
     }
 
     pub fn parse(&mut self, input: &str) -> Vec<AnsiCommand> {
         let mut commands = Vec::new();
-        let mut chars = input.chars().peekable();
+        let mut chars = input.chars();
 
         while let Some(ch) = chars.next() {
+            self.sample_char(ch);
+            // An ESC inside a string sequence is held until the next character,
+            // which may come in the next read: `\\` makes it the terminator (ST),
+            // anything else aborts the string and the ESC starts a new sequence
+            if self.in_string_sequence() && self.buffer.ends_with('\x1b') {
+                self.buffer.pop();
+                if ch == '\\' {
+                    if let Some(command) = self.parse_escape_sequence(&self.buffer) {
+                        commands.push(command);
+                    }
+                    self.reset_escape_state();
+                    continue;
+                }
+                self.reset_escape_state();
+                self.in_escape = true;
+                self.buffer.push('\x1b');
+            }
             match ch {
+                '\x1b' if self.in_string_sequence() => {
+                    self.buffer.push(ch);
+                }
                 '\x1b' => {
-                    // Start of escape sequence
+                    // Start of escape sequence; one already in progress is abandoned
                     self.flush_buffer(&mut commands);
+                    self.buffer.clear();
                     self.in_escape = true;
                     self.escape_type = EscapeType::None;
                     self.buffer.push(ch);
@@ -276,29 +440,54 @@ impl AnsiParser {
                     self.escape_type = EscapeType::SS3;
                     self.buffer.push(ch);
                 }
-                '\x07' if self.in_escape && matches!(self.escape_type, EscapeType::OSC | EscapeType::DCS | EscapeType::PM | EscapeType::APC) => {
+                '\x07' if self.in_string_sequence() => {
                     // End of OSC/DCS/PM/APC sequence with BEL
                     if let Some(command) = self.parse_escape_sequence(&self.buffer) {
                         commands.push(command);
                     }
                     self.reset_escape_state();
                 }
-                '\x1b' if self.in_escape && chars.peek() == Some(&'\\') => {
-                    // End of OSC/DCS/PM/APC sequence with ESC \
-                    chars.next(); // consume the \\
+                '@'..='~' if self.in_escape && self.escape_type == EscapeType::CSI => {
+                    // End of CSI sequence
+                    self.buffer.push(ch);
                     if let Some(command) = self.parse_escape_sequence(&self.buffer) {
                         commands.push(command);
                     }
                     self.reset_escape_state();
                 }
-                'A'..='Z' | 'a'..='z' if self.in_escape && self.escape_type == EscapeType::CSI => {
-                    // End of CSI sequence
+                'D' | 'M' | 'H' if self.in_escape && self.escape_type == EscapeType::None && self.buffer == "\x1b" => {
+                    // Index, reverse index and horizontal tab set
+                    commands.push(match ch {
+                        'D' => AnsiCommand::LineFeed,
+                        'M' => AnsiCommand::ReverseIndex,
+                        _ => AnsiCommand::SetTabStop,
+                    });
+                    self.reset_escape_state();
+                }
+                ' '..='/' if self.in_escape && self.escape_type == EscapeType::None => {
+                    // Intermediate byte, as in the charset designation \e(B
                     self.buffer.push(ch);
-                    if let Some(command) = self.parse_escape_sequence(&self.buffer) {
-                        commands.push(command);
+                }
+                '0'..='~' if self.in_escape && self.escape_type == EscapeType::None => {
+                    // Final byte of a short escape; keypad modes aren't modelled
+                    let charset = if ch == '0' { Charset::DecSpecialGraphics } else { Charset::Ascii };
+                    match self.buffer.as_str() {
+                        "\x1b" => match ch {
+                            'c' => commands.push(AnsiCommand::FullReset),
+                            '7' => commands.push(AnsiCommand::CursorSave),
+                            '8' => commands.push(AnsiCommand::CursorRestore),
+                            _ => {}
+                        },
+                        "\x1b(" => self.charsets[0] = charset,
+                        "\x1b)" => self.charsets[1] = charset,
+                        _ => {}
                     }
                     self.reset_escape_state();
                 }
+                _ if self.in_escape && matches!(self.escape_type, EscapeType::SS2 | EscapeType::SS3) => {
+                    // A single shift covers just the next character
+                    self.reset_escape_state();
+                }
                 _ if self.in_escape => {
                     self.buffer.push(ch);
                 }
@@ -310,7 +499,7 @@ impl AnsiParser {
                 '\n' => {
                     // Line feed - move cursor down one line
                     self.flush_buffer(&mut commands);
-                    commands.push(AnsiCommand::CursorDown(1));
+                    commands.push(AnsiCommand::LineFeed);
                 }
                 '\x07' => {
                     // Bell character
@@ -320,15 +509,23 @@ impl AnsiParser {
                 '\t' => {
                     // Tab character
                     self.flush_buffer(&mut commands);
-                    commands.push(AnsiCommand::CursorRight(8)); // Simple tab implementation
+                    commands.push(AnsiCommand::Tab(1));
                 }
                 '\x08' => {
                     // Backspace
                     self.flush_buffer(&mut commands);
                     commands.push(AnsiCommand::CursorLeft(1));
                 }
+                '\x0e' | '\x0f' => {
+                    // Shift out to G1 / shift in to G0
+                    self.shifted_out = ch == '\x0e';
+                }
+                '\x00'..='\x1f' | '\x7f'..='\u{9f}' => {
+                    // Other control characters (NUL, C1 codes, ...) aren't printable
+                }
                 _ => {
-                    self.buffer.push(ch);
+                    let charset = self.charsets[self.shifted_out as usize];
+                    self.buffer.push(charset.translate(ch));
                 }
             }
         }
@@ -342,6 +539,42 @@ impl AnsiParser {
         commands
     }
 
+    // Characters inside escape sequences only count when they're garbage, so
+    // escape-heavy output from full-screen programs still reads as text
+    fn sample_char(&mut self, ch: char) {
+        let garbage = matches!(ch, '\x00'..='\x06' | '\x10'..='\x1a' | '\x1c'..='\x1f' | '\x7f'..='\u{9f}' | '\u{fffd}');
+        if garbage {
+            self.garbage_chars += 1;
+        } else if self.in_escape {
+            return;
+        }
+        self.sampled_chars += 1;
+        if self.sampled_chars >= BINARY_SAMPLE_CHARS {
+            if self.garbage_chars * 100 >= self.sampled_chars * BINARY_GARBAGE_PERCENT {
+                self.binary_detected = true;
+            }
+            self.sampled_chars = 0;
+            self.garbage_chars = 0;
+        }
+    }
+
+    /// Whether a span of binary-looking output has been seen since the last call.
+    pub fn take_binary_detected(&mut self) -> bool {
+        std::mem::take(&mut self.binary_detected)
+    }
+
+    /// Back to the initial attributes with no sequence in progress.
+    pub fn reset_state(&mut self) {
+        self.reset_escape_state();
+        self.current_attributes = CharAttributes::default();
+        self.saved_cursor = None;
+        self.hyperlink_stack.clear();
+        self.in_synchronized_update = false;
+        self.osc_params.clear();
+        self.charsets = [Charset::Ascii; 2];
+        self.shifted_out = false;
+    }
+
     fn flush_buffer(&mut self, commands: &mut Vec<AnsiCommand>) {
         if !self.buffer.is_empty() && !self.in_escape {
             commands.push(AnsiCommand::PrintText(self.buffer.clone()));
@@ -349,6 +582,10 @@ impl AnsiParser {
         }
     }
 
+    fn in_string_sequence(&self) -> bool {
+        self.in_escape && matches!(self.escape_type, EscapeType::OSC | EscapeType::DCS | EscapeType::PM | EscapeType::APC)
+    }
+
     fn reset_escape_state(&mut self) {
         self.buffer.clear();
         self.in_escape = false;
@@ -416,6 +653,14 @@ impl AnsiParser {
                 }
             }
             
+            // Tab stops
+            'I' => Some(AnsiCommand::Tab(params.first().copied().unwrap_or(1))),
+            'g' => match params.first().copied().unwrap_or(0) {
+                0 => Some(AnsiCommand::ClearTabStop(false)),
+                3 => Some(AnsiCommand::ClearTabStop(true)),
+                _ => Some(AnsiCommand::Unknown(seq.to_string())),
+            },
+
             // Text modification
             'L' => Some(AnsiCommand::InsertLines(params.get(0).copied().unwrap_or(1))),
             'M' => Some(AnsiCommand::DeleteLines(params.get(0).copied().unwrap_or(1))),
@@ -426,24 +671,28 @@ impl AnsiParser {
             'S' => Some(AnsiCommand::ScrollUp(params.get(0).copied().unwrap_or(1))),
             'T' => Some(AnsiCommand::ScrollDown(params.get(0).copied().unwrap_or(1))),
             'r' => {
-                let top = params.get(0).copied().unwrap_or(1);
-                let bottom = params.get(1).copied().unwrap_or(24);
+                // Positional, with 0 for an omitted margin: `\e[;10r` keeps the top
+                // at row 1, and a missing bottom means the last row
+                let mut margins = params_str.split(';').map(|p| p.parse::<u16>().unwrap_or(0));
+                let top = margins.next().unwrap_or(0);
+                let bottom = margins.next().unwrap_or(0);
                 Some(AnsiCommand::SetScrollRegion(top, bottom))
             }
             
             // Graphics mode
             'm' => {
-                let params: Vec<u8> = params_str
+                // Empty fields default to 0, so a bare `\e[m` resets like `\e[0m`
+                let params: Vec<Vec<u16>> = params_str
                     .split(';')
-                    .filter_map(|s| s.parse().ok())
+                    .map(|param| param.split(':').map(|sub| sub.parse().unwrap_or(0)).collect())
                     .collect();
                 Some(AnsiCommand::SetGraphicsMode(params))
             }
             
             // Cursor visibility and style
-            'q' if params_str.starts_with(" ") => {
-                // DECSCUSR - Set cursor style
-                match params.get(0).copied().unwrap_or(1) {
+            'q' if params_str.ends_with(' ') => {
+                // DECSCUSR - Set cursor style; the space is an intermediate byte
+                match params_str.trim_end().parse().unwrap_or(1) {
                     0 | 1 => Some(AnsiCommand::SetCursorStyle(CursorStyle::BlinkingBlock)),
                     2 => Some(AnsiCommand::SetCursorStyle(CursorStyle::Block)),
                     3 => Some(AnsiCommand::SetCursorStyle(CursorStyle::BlinkingUnderline)),
@@ -455,6 +704,8 @@ impl AnsiParser {
             }
             
             // Mouse reporting
+            'h' if params_str == "?9" => Some(AnsiCommand::EnableMouseReporting(MouseReportMode::X10)),
+            'l' if params_str == "?9" => Some(AnsiCommand::DisableMouseReporting(MouseReportMode::X10)),
             'h' if params_str == "?1000" => Some(AnsiCommand::EnableMouseReporting(MouseReportMode::Normal)),
             'l' if params_str == "?1000" => Some(AnsiCommand::DisableMouseReporting(MouseReportMode::Normal)),
             'h' if params_str == "?1002" => Some(AnsiCommand::EnableMouseReporting(MouseReportMode::Button)),
@@ -463,6 +714,8 @@ impl AnsiParser {
             'l' if params_str == "?1003" => Some(AnsiCommand::DisableMouseReporting(MouseReportMode::Any)),
             'h' if params_str == "?1006" => Some(AnsiCommand::EnableMouseReporting(MouseReportMode::SGR)),
             'l' if params_str == "?1006" => Some(AnsiCommand::DisableMouseReporting(MouseReportMode::SGR)),
+            'h' if params_str == "?1015" => Some(AnsiCommand::EnableMouseReporting(MouseReportMode::URXVT)),
+            'l' if params_str == "?1015" => Some(AnsiCommand::DisableMouseReporting(MouseReportMode::URXVT)),
             
             // Alternate screen
             'h' if params_str == "?1049" || params_str == "?47" => Some(AnsiCommand::EnterAlternateScreen),
@@ -480,6 +733,24 @@ impl AnsiParser {
             'h' if params_str == "?1004" => Some(AnsiCommand::EnableFocusEvents),
             'l' if params_str == "?1004" => Some(AnsiCommand::DisableFocusEvents),
             
+            // Status and attribute queries
+            'n' if params_str == "5" => Some(AnsiCommand::DeviceStatusReport),
+            'n' if params_str == "6" => Some(AnsiCommand::CursorPositionReport),
+            'c' if params_str.is_empty() || params_str == "0" => Some(AnsiCommand::PrimaryDeviceAttributes),
+            'c' if params_str == ">" || params_str == ">0" => Some(AnsiCommand::SecondaryDeviceAttributes),
+            'p' if params_str == "!" => Some(AnsiCommand::SoftReset),
+            'p' if params_str.ends_with('$') => {
+                let mode = params_str.trim_end_matches('$');
+                let (mode, private) = match mode.strip_prefix('?') {
+                    Some(mode) => (mode, true),
+                    None => (mode, false),
+                };
+                match mode.parse() {
+                    Ok(mode) => Some(AnsiCommand::RequestMode(mode, private)),
+                    Err(_) => Some(AnsiCommand::Unknown(seq.to_string())),
+                }
+            }
+
             // Synchronized updates
             'h' if params_str == "?2026" => Some(AnsiCommand::BeginSynchronizedUpdate),
             'l' if params_str == "?2026" => Some(AnsiCommand::EndSynchronizedUpdate),
@@ -508,6 +779,13 @@ impl AnsiParser {
                     let title = parts.get(1).unwrap_or(&"").to_string();
                     Some(AnsiCommand::SetIconTitle(title))
                 }
+                7 => {
+                    // Working directory as a file:// URL
+                    match parse_file_url(parts.get(1).unwrap_or(&"")) {
+                        Some((path, host)) => Some(AnsiCommand::SetWorkingDirectory(path, host)),
+                        None => Some(AnsiCommand::Unknown(seq.to_string())),
+                    }
+                }
                 8 => {
                     // Hyperlink
                     let hyperlink_parts: Vec<&str> = parts.get(1).unwrap_or(&"").splitn(2, ';').collect();
@@ -515,6 +793,20 @@ impl AnsiParser {
                     let text = "".to_string(); // Text will be in subsequent print commands
                     Some(AnsiCommand::SetHyperlink(url, text))
                 }
+                133 => {
+                    // Semantic prompt marks: A prompt, B command, C output, D[;exit]
+                    let mut fields = parts.get(1).unwrap_or(&"").split(';');
+                    match fields.next() {
+                        Some("A") => Some(AnsiCommand::SemanticPromptMark(PromptMark::PromptStart)),
+                        Some("B") => Some(AnsiCommand::SemanticPromptMark(PromptMark::CommandStart)),
+                        Some("C") => Some(AnsiCommand::SemanticPromptMark(PromptMark::OutputStart)),
+                        Some("D") => {
+                            let exit_code = fields.next().and_then(|c| c.trim().parse::<i32>().ok());
+                            Some(AnsiCommand::SemanticPromptMark(PromptMark::CommandEnd(exit_code)))
+                        }
+                        _ => Some(AnsiCommand::Unknown(seq.to_string())),
+                    }
+                }
                 1337 => {
                     // iTerm2 proprietary sequences
                     if let Some(data) = parts.get(1) {
@@ -559,50 +851,380 @@ impl AnsiParser {
         }
     }
 
-    pub fn apply_graphics_mode(&mut self, params: &[u8]) {
-        for &param in params {
-            match param {
+    pub fn apply_graphics_mode(&mut self, params: &[Vec<u16>]) {
+        let mut index = 0;
+        while index < params.len() {
+            let param = &params[index];
+            index += 1;
+            match param[0] {
                 0 => self.current_attributes = CharAttributes::default(),
                 1 => self.current_attributes.bold = true,
+                2 => self.current_attributes.dim = true,
                 3 => self.current_attributes.italic = true,
-                4 => self.current_attributes.underline = true,
+                4 => {
+                    self.current_attributes.underline = match param.get(1) {
+                        None | Some(1) => UnderlineStyle::Single,
+                        Some(0) => UnderlineStyle::None,
+                        Some(2) => UnderlineStyle::Double,
+                        Some(3) => UnderlineStyle::Curly,
+                        Some(4) => UnderlineStyle::Dotted,
+                        Some(5) => UnderlineStyle::Dashed,
+                        Some(_) => self.current_attributes.underline,
+                    }
+                }
+                5 | 6 => self.current_attributes.blink = true,
                 7 => self.current_attributes.reverse = true,
+                8 => self.current_attributes.hidden = true,
                 9 => self.current_attributes.strikethrough = true,
-                22 => self.current_attributes.bold = false,
+                21 => self.current_attributes.underline = UnderlineStyle::Double,
+                // Each of these clears only its own attribute; 22 covers both weights
+                22 => {
+                    self.current_attributes.bold = false;
+                    self.current_attributes.dim = false;
+                }
                 23 => self.current_attributes.italic = false,
-                24 => self.current_attributes.underline = false,
+                24 => self.current_attributes.underline = UnderlineStyle::None,
+                25 => self.current_attributes.blink = false,
                 27 => self.current_attributes.reverse = false,
+                28 => self.current_attributes.hidden = false,
                 29 => self.current_attributes.strikethrough = false,
-                30 => self.current_attributes.fg_color = Some(Color::black()),
-                31 => self.current_attributes.fg_color = Some(Color::red()),
-                32 => self.current_attributes.fg_color = Some(Color::green()),
-                33 => self.current_attributes.fg_color = Some(Color::yellow()),
-                34 => self.current_attributes.fg_color = Some(Color::blue()),
-                35 => self.current_attributes.fg_color = Some(Color::magenta()),
-                36 => self.current_attributes.fg_color = Some(Color::cyan()),
-                37 => self.current_attributes.fg_color = Some(Color::white()),
+                code @ 30..=37 => self.current_attributes.fg_color = Some(Color::indexed(code as u8 - 30)),
+                38 => self.current_attributes.fg_color = extended_color(param, params, &mut index),
                 39 => self.current_attributes.fg_color = None,
-                40 => self.current_attributes.bg_color = Some(Color::black()),
-                41 => self.current_attributes.bg_color = Some(Color::red()),
-                42 => self.current_attributes.bg_color = Some(Color::green()),
-                43 => self.current_attributes.bg_color = Some(Color::yellow()),
-                44 => self.current_attributes.bg_color = Some(Color::blue()),
-                45 => self.current_attributes.bg_color = Some(Color::magenta()),
-                46 => self.current_attributes.bg_color = Some(Color::cyan()),
-                47 => self.current_attributes.bg_color = Some(Color::white()),
+                code @ 40..=47 => self.current_attributes.bg_color = Some(Color::indexed(code as u8 - 40)),
+                48 => self.current_attributes.bg_color = extended_color(param, params, &mut index),
                 49 => self.current_attributes.bg_color = None,
+                58 => self.current_attributes.underline_color = extended_color(param, params, &mut index),
+                59 => self.current_attributes.underline_color = None,
                 _ => {} // Ignore unknown parameters
             }
         }
     }
 
+    /// Primary device attributes reply: a VT220-class terminal (62) with the
+    /// optional features our capabilities cover (4 sixel, 22 ANSI color).
+    pub fn device_attributes(&self) -> String {
+        let mut attributes = vec!["62"];
+        if self.capabilities.sixel_graphics {
+            attributes.push("4");
+        }
+        if self.capabilities.colors != ColorSupport::Monochrome {
+            attributes.push("22");
+        }
+        format!("\x1b[?{}c", attributes.join(";"))
+    }
+
+    /// Queue a reply for the program on the other end of the PTY.
+    pub fn respond(&mut self, reply: &str) {
+        self.responses.extend_from_slice(reply.as_bytes());
+    }
+
+    /// Replies queued since the last call, in the order the queries arrived.
+    pub fn take_responses(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.responses)
+    }
+
     pub fn current_attributes(&self) -> &CharAttributes {
         &self.current_attributes
     }
 }
 
+/// The color of a 38/48/58 parameter, in either the colon form (`38:2::r:g:b`,
+/// `38:2:r:g:b`, `38:5:n`) or the legacy semicolon form (`38;2;r;g;b`, `38;5;n`),
+/// which consumes the following parameters by advancing `next`.
+fn extended_color(param: &[u16], params: &[Vec<u16>], next: &mut usize) -> Option<Color> {
+    let channel = |value: Option<&u16>| value.map(|&v| v.min(255) as u8);
+    if param.len() > 1 {
+        return match param[1] {
+            // The color space id before r:g:b is optional
+            2 => {
+                let rgb = if param.len() >= 6 { &param[3..6] } else { &param[2..] };
+                Some(Color::new(channel(rgb.first())?, channel(rgb.get(1))?, channel(rgb.get(2))?))
+            }
+            5 => channel(param.get(2)).map(Color::indexed),
+            _ => None,
+        };
+    }
+    let value = |offset: usize| channel(params.get(*next + offset).and_then(|p| p.first()));
+    match value(0)? {
+        2 => {
+            let color = Color::new(value(1)?, value(2)?, value(3)?);
+            *next += 4;
+            Some(color)
+        }
+        5 => {
+            let color = Color::indexed(value(1)?);
+            *next += 2;
+            Some(color)
+        }
+        _ => None,
+    }
+}
+
+/// Path and host from an OSC 7 `file://host/path` URL. The host may be empty
+/// (`file:///path`) or left out entirely (`file:/path`). Paths are percent-decoded,
+/// keeping malformed escapes as-is, and Windows drive paths (`/C:/...`) lose the
+/// leading slash.
+fn parse_file_url(url: &str) -> Option<(String, Option<String>)> {
+    let url = url.trim();
+    let (host, path) = match url.strip_prefix("file://") {
+        Some(rest) => {
+            let slash = rest.find('/')?;
+            (&rest[..slash], &rest[slash..])
+        }
+        None => ("", url.strip_prefix("file:").filter(|path| path.starts_with('/'))?),
+    };
+
+    let raw = path.as_bytes();
+    let mut bytes = Vec::with_capacity(raw.len());
+    let mut i = 0;
+    while i < raw.len() {
+        let decoded = (raw[i] == b'%')
+            .then(|| raw.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match decoded {
+            Some(byte) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            None => {
+                bytes.push(raw[i]);
+                i += 1;
+            }
+        }
+    }
+    let path = String::from_utf8_lossy(&bytes).into_owned();
+    let is_drive = path.len() >= 3 && path.as_bytes()[2] == b':' && path.as_bytes()[1].is_ascii_alphabetic();
+    let path = if is_drive { path[1..].to_string() } else { path };
+    let host = (!host.is_empty()).then(|| host.to_string());
+    Some((path, host))
+}
+
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "rgba({}, {}, {}, {})", self.r, self.g, self.b, self.a)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // PTY reads decoded the way the reader thread does, parsed, and rendered
+    // with adjacent text runs joined: a read boundary may split plain text
+    // into several `PrintText`s
+    fn parse_chunks(chunks: &[&[u8]]) -> Vec<String> {
+        let mut decoder = encoding_rs::UTF_8.new_decoder_without_bom_handling();
+        let mut parser = AnsiParser::new();
+        let mut rendered: Vec<String> = Vec::new();
+        let mut text = String::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let data = crate::pty::decode_output(&mut decoder, chunk, i + 1 == chunks.len());
+            for command in parser.parse(&data) {
+                match command {
+                    AnsiCommand::PrintText(run) => text.push_str(&run),
+                    other => {
+                        if !text.is_empty() {
+                            rendered.push(format!("{:?}", AnsiCommand::PrintText(std::mem::take(&mut text))));
+                        }
+                        rendered.push(format!("{:?}", other));
+                    }
+                }
+            }
+        }
+        if !text.is_empty() {
+            rendered.push(format!("{:?}", AnsiCommand::PrintText(text)));
+        }
+        rendered
+    }
+
+    #[test]
+    fn a_stream_split_at_any_byte_parses_the_same() {
+        let stream = concat!(
+            "\x1b]0;build: ~/src\x1b\\",
+            "\x1b[1;31merror\x1b[0m: failed\r\n",
+            "\x1b]7;file://host/home/user\x07",
+            "\x1b]133;D;1\x1b\\",
+            "\x1b[2 q\x1b[?2004h\x1b[12;40H",
+            "\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\",
+            "é ✓ done\x1b[K",
+        );
+        let bytes = stream.as_bytes();
+        let whole = parse_chunks(&[bytes]);
+        let whole_screen = screen_after(&[bytes]);
+        assert!(whole.iter().all(|command| !command.starts_with("Unknown")), "{:?}", whole);
+        for split in 1..bytes.len() {
+            let (head, tail) = bytes.split_at(split);
+            assert_eq!(parse_chunks(&[head, tail]), whole, "split at byte {}", split);
+            assert_eq!(screen_after(&[head, tail]), whole_screen, "split at byte {}", split);
+        }
+        // One byte per read is the worst case
+        let single_bytes: Vec<&[u8]> = bytes.chunks(1).collect();
+        assert_eq!(parse_chunks(&single_bytes), whole);
+        assert_eq!(screen_after(&single_bytes), whole_screen);
+    }
+
+    // What a terminal fed these reads ends up showing: cells with their
+    // attributes, cursor, links, and the state the sequences switch on
+    fn screen_after(chunks: &[&[u8]]) -> String {
+        let size = crate::pty::TerminalSize { rows: 24, cols: 80, pixel_width: 0, pixel_height: 0 };
+        let mut term = crate::terminal::Terminal::new("split".into(), size, 1000, 1 << 20);
+        let mut decoder = encoding_rs::UTF_8.new_decoder_without_bom_handling();
+        for (i, chunk) in chunks.iter().enumerate() {
+            term.process_output(&crate::pty::decode_output(&mut decoder, chunk, i + 1 == chunks.len()));
+        }
+        format!(
+            "{:?}\n{:?}\n{:?}\n{:?}\n{:?} {}",
+            term.grid.rows, term.grid.cursor, term.hyperlinks, term.working_directory, term.cursor_style, term.bracketed_paste
+        )
+    }
+
+    #[test]
+    fn an_escape_inside_a_string_aborts_it_unless_it_is_the_terminator() {
+        let commands = parse_chunks(&[b"\x1b]0;half a title\x1b", b"[2J"]);
+        assert_eq!(commands, parse_chunks(&[b"\x1b[2J"]));
+    }
+
+    // Attributes after parsing `data` and applying its SGR sequences, as the terminal does
+    fn attributes_after(parser: &mut AnsiParser, data: &str) -> CharAttributes {
+        for command in parser.parse(data) {
+            if let AnsiCommand::SetGraphicsMode(params) = command {
+                parser.apply_graphics_mode(&params);
+            }
+        }
+        parser.current_attributes().clone()
+    }
+
+    #[test]
+    fn each_underline_style_is_parsed_from_its_subparameter() {
+        let styles = [
+            ("\x1b[4m", UnderlineStyle::Single),
+            ("\x1b[4:1m", UnderlineStyle::Single),
+            ("\x1b[4:2m", UnderlineStyle::Double),
+            ("\x1b[4:3m", UnderlineStyle::Curly),
+            ("\x1b[4:4m", UnderlineStyle::Dotted),
+            ("\x1b[4:5m", UnderlineStyle::Dashed),
+            ("\x1b[21m", UnderlineStyle::Double),
+        ];
+        for (sequence, style) in styles {
+            let mut parser = AnsiParser::new();
+            assert_eq!(attributes_after(&mut parser, sequence).underline, style, "{:?}", sequence);
+        }
+
+        let mut parser = AnsiParser::new();
+        attributes_after(&mut parser, "\x1b[1;4:3m");
+        assert_eq!(attributes_after(&mut parser, "\x1b[4:0m").underline, UnderlineStyle::None);
+        attributes_after(&mut parser, "\x1b[4:3m");
+        // An unknown style keeps the current one, and 24 turns any style off
+        assert_eq!(attributes_after(&mut parser, "\x1b[4:9m").underline, UnderlineStyle::Curly);
+        let attributes = attributes_after(&mut parser, "\x1b[24m");
+        assert_eq!(attributes.underline, UnderlineStyle::None);
+        assert!(attributes.bold);
+    }
+
+    #[test]
+    fn a_colored_curly_underline_keeps_the_text_color() {
+        let mut parser = AnsiParser::new();
+        let attributes = attributes_after(&mut parser, "\x1b[32;4:3;58:2::255:128:0m");
+        assert_eq!(attributes.underline, UnderlineStyle::Curly);
+        assert_eq!(attributes.underline_color, Some(Color::new(255, 128, 0)));
+        assert_eq!(attributes.fg_color, Some(Color::indexed(2)));
+
+        // The semicolon forms consume their arguments rather than reading them as SGR codes
+        let attributes = attributes_after(&mut parser, "\x1b[58;2;1;2;3m");
+        assert_eq!(attributes.underline_color, Some(Color::new(1, 2, 3)));
+        assert!(!attributes.dim && !attributes.italic);
+        assert_eq!(attributes_after(&mut parser, "\x1b[58;5;196m").underline_color, Some(Color::indexed(196)));
+        assert_eq!(attributes_after(&mut parser, "\x1b[58:2:10:20:30m").underline_color, Some(Color::new(10, 20, 30)));
+
+        let attributes = attributes_after(&mut parser, "\x1b[59m");
+        assert_eq!(attributes.underline_color, None);
+        assert_eq!(attributes.underline, UnderlineStyle::Curly);
+    }
+
+    #[test]
+    fn not_bold_keeps_the_red_foreground() {
+        let mut parser = AnsiParser::new();
+        let attributes = attributes_after(&mut parser, "\x1b[1;31m");
+        assert!(attributes.bold);
+        let attributes = attributes_after(&mut parser, "\x1b[22m");
+        assert!(!attributes.bold);
+        assert_eq!(attributes.fg_color, Some(Color::indexed(1)));
+    }
+
+    #[test]
+    fn each_disable_code_clears_only_its_own_attribute() {
+        let everything = "\x1b[1;3;4;5;7;8;9;31;44m";
+        type Cleared = fn(&CharAttributes) -> bool;
+        let cases: [(&str, Cleared); 9] = [
+            ("\x1b[22m", |a| !a.bold && !a.dim),
+            ("\x1b[23m", |a| !a.italic),
+            ("\x1b[24m", |a| a.underline == UnderlineStyle::None),
+            ("\x1b[25m", |a| !a.blink),
+            ("\x1b[27m", |a| !a.reverse),
+            ("\x1b[28m", |a| !a.hidden),
+            ("\x1b[29m", |a| !a.strikethrough),
+            ("\x1b[39m", |a| a.fg_color.is_none()),
+            ("\x1b[49m", |a| a.bg_color.is_none()),
+        ];
+        for (reset, cleared) in cases {
+            let mut parser = AnsiParser::new();
+            let before = attributes_after(&mut parser, everything);
+            let after = attributes_after(&mut parser, reset);
+            assert!(cleared(&after), "{:?} left {:?}", reset, after);
+
+            // Turning the cleared attribute back on restores exactly the earlier state
+            let restored = attributes_after(&mut parser, everything);
+            assert_eq!(restored, before, "{:?}", reset);
+            let changed = [
+                after.bold != before.bold,
+                after.italic != before.italic,
+                after.underline != before.underline,
+                after.blink != before.blink,
+                after.reverse != before.reverse,
+                after.hidden != before.hidden,
+                after.strikethrough != before.strikethrough,
+                after.fg_color != before.fg_color,
+                after.bg_color != before.bg_color,
+            ];
+            assert_eq!(changed.iter().filter(|c| **c).count(), 1, "{:?} changed {:?}", reset, after);
+        }
+
+        let mut parser = AnsiParser::new();
+        attributes_after(&mut parser, "\x1b[2m");
+        assert!(!attributes_after(&mut parser, "\x1b[22m").dim);
+        attributes_after(&mut parser, everything);
+        assert_eq!(attributes_after(&mut parser, "\x1b[0m"), CharAttributes::default());
+    }
+
+    fn printed(parser: &mut AnsiParser, data: &str) -> String {
+        parser
+            .parse(data)
+            .into_iter()
+            .filter_map(|command| match command {
+                AnsiCommand::PrintText(text) => Some(text),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dec_line_drawing_is_translated_until_ascii_is_selected_again() {
+        let mut parser = AnsiParser::new();
+        assert_eq!(printed(&mut parser, "\x1b(0lqk\x1b(B"), "┌─┐");
+        assert_eq!(printed(&mut parser, "lqk"), "lqk");
+        // `tree` style output: the charset stays selected across reads
+        assert_eq!(printed(&mut parser, "\x1b(0tq"), "├─");
+        assert_eq!(printed(&mut parser, "q x\r\nmqq\x1b(B src"), "─ │└── src");
+    }
+
+    #[test]
+    fn shift_out_and_in_switch_between_g1_and_g0() {
+        let mut parser = AnsiParser::new();
+        assert_eq!(printed(&mut parser, "\x1b)0qq\x0eqq\x0fqq"), "qq──qq");
+        // SO/SI only pick a slot; G0 is still ASCII
+        assert_eq!(printed(&mut parser, "\x0elj\x1b)Blj\x0f"), "┌┘lj");
+    }
+}
@@ -0,0 +1,274 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+    Gzip, // a single gzip-compressed file
+}
+
+/// Cumulative progress; `total_*` may be refined once the work is under way.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ArchiveProgress {
+    pub bytes: u64,
+    pub total_bytes: u64,
+    pub files: usize,
+    pub total_files: usize,
+}
+
+pub fn detect_format(path: &Path) -> Option<ArchiveFormat> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name.ends_with(".zip") {
+        Some(ArchiveFormat::Zip)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveFormat::TarGz)
+    } else if name.ends_with(".gz") {
+        Some(ArchiveFormat::Gzip)
+    } else {
+        None
+    }
+}
+
+struct SourceEntry {
+    path: PathBuf,
+    name: String, // '/'-separated path inside the archive
+    is_dir: bool,
+    size: u64,
+}
+
+// Every file and directory under `sources`, each named relative to its source's parent
+fn collect_sources(sources: &[String]) -> Result<Vec<SourceEntry>, String> {
+    let mut entries = Vec::new();
+    for source in sources {
+        let root = Path::new(source);
+        let base = root.file_name()
+            .ok_or_else(|| format!("Cannot archive {}", source))?
+            .to_string_lossy()
+            .to_string();
+        let mut pending = vec![(root.to_path_buf(), base)];
+        while let Some((path, name)) = pending.pop() {
+            let metadata = fs::metadata(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            if metadata.is_dir() {
+                let mut children: Vec<_> = fs::read_dir(&path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+                    .flatten()
+                    .collect();
+                children.sort_by_key(|entry| entry.file_name());
+                for child in children.into_iter().rev() {
+                    pending.push((child.path(), format!("{}/{}", name, child.file_name().to_string_lossy())));
+                }
+                entries.push(SourceEntry { path, name, is_dir: true, size: 0 });
+            } else {
+                entries.push(SourceEntry { path, name, is_dir: false, size: metadata.len() });
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn copy_with_progress(reader: &mut dyn Read, writer: &mut dyn Write, on_bytes: &mut dyn FnMut(u64)) -> io::Result<()> {
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(());
+        }
+        writer.write_all(&buffer[..read])?;
+        on_bytes(read as u64);
+    }
+}
+
+/// Create an archive at `destination` from `sources` (files or directories).
+pub fn create(sources: &[String], destination: &Path, format: ArchiveFormat, progress: &mut dyn FnMut(ArchiveProgress)) -> Result<(), String> {
+    let entries = collect_sources(sources)?;
+    let mut state = ArchiveProgress {
+        total_bytes: entries.iter().map(|e| e.size).sum(),
+        total_files: entries.iter().filter(|e| !e.is_dir).count(),
+        ..Default::default()
+    };
+    progress(state);
+    let file = File::create(destination).map_err(|e| format!("Failed to create {}: {}", destination.display(), e))?;
+    let io_error = |e: io::Error| format!("Failed to write {}: {}", destination.display(), e);
+
+    match format {
+        ArchiveFormat::Gzip => {
+            let [entry] = entries.as_slice() else {
+                return Err("A .gz file holds exactly one file; use .tar.gz or .zip for several".to_string());
+            };
+            if entry.is_dir {
+                return Err("A .gz file can't hold a directory; use .tar.gz or .zip".to_string());
+            }
+            let mut encoder = GzEncoder::new(file, Compression::default());
+            let mut input = File::open(&entry.path).map_err(|e| format!("Failed to read {}: {}", entry.path.display(), e))?;
+            copy_with_progress(&mut input, &mut encoder, &mut |n| {
+                state.bytes += n;
+                progress(state);
+            }).map_err(io_error)?;
+            encoder.finish().map_err(io_error)?;
+            state.files = 1;
+        }
+        ArchiveFormat::TarGz => {
+            let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+            for entry in &entries {
+                if entry.is_dir {
+                    builder.append_dir(&entry.name, &entry.path).map_err(io_error)?;
+                    continue;
+                }
+                builder.append_path_with_name(&entry.path, &entry.name).map_err(io_error)?;
+                state.bytes += entry.size;
+                state.files += 1;
+                progress(state);
+            }
+            builder.into_inner().and_then(|encoder| encoder.finish()).map_err(io_error)?;
+        }
+        ArchiveFormat::Zip => {
+            let mut writer = zip::ZipWriter::new(file);
+            let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+            for entry in &entries {
+                if entry.is_dir {
+                    writer.add_directory(format!("{}/", entry.name), options).map_err(|e| e.to_string())?;
+                    continue;
+                }
+                writer.start_file(entry.name.as_str(), options).map_err(|e| e.to_string())?;
+                let mut input = File::open(&entry.path).map_err(|e| format!("Failed to read {}: {}", entry.path.display(), e))?;
+                copy_with_progress(&mut input, &mut writer, &mut |n| {
+                    state.bytes += n;
+                    progress(state);
+                }).map_err(io_error)?;
+                state.files += 1;
+            }
+            writer.finish().map_err(|e| e.to_string())?;
+        }
+    }
+    progress(state);
+    Ok(())
+}
+
+// Lexically resolve `relative` under `root`, refusing anything that climbs out
+// of it or is absolute ("zip slip").
+fn contained_path(root: &Path, relative: &Path) -> Result<PathBuf, String> {
+    let mut resolved = PathBuf::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => {}
+            Component::ParentDir if resolved.pop() => {}
+            _ => return Err(format!("Refusing to extract {}: it points outside the destination", relative.display())),
+        }
+    }
+    Ok(root.join(resolved))
+}
+
+struct CountingReader<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buffer)?;
+        self.count.fetch_add(read as u64, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+/// Extract `archive` into the `destination` directory, creating it if needed.
+/// Existing files are overwritten; entries that would land outside
+/// `destination` abort the extraction.
+pub fn extract(archive: &Path, destination: &Path, format: ArchiveFormat, progress: &mut dyn FnMut(ArchiveProgress)) -> Result<(), String> {
+    fs::create_dir_all(destination).map_err(|e| format!("Failed to create {}: {}", destination.display(), e))?;
+    let file = File::open(archive).map_err(|e| format!("Failed to open {}: {}", archive.display(), e))?;
+    let archive_size = file.metadata().map(|m| m.len()).unwrap_or_default();
+    let io_error = |e: io::Error| format!("Failed to extract {}: {}", archive.display(), e);
+    let mut state = ArchiveProgress::default();
+
+    match format {
+        ArchiveFormat::Gzip => {
+            let name = archive.file_stem().ok_or("Archive has no file name")?;
+            let target = contained_path(destination, Path::new(name))?;
+            let count = Arc::new(AtomicU64::new(0));
+            let mut decoder = GzDecoder::new(CountingReader { inner: file, count: count.clone() });
+            let mut output = File::create(&target).map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+            state.total_bytes = archive_size;
+            state.total_files = 1;
+            copy_with_progress(&mut decoder, &mut output, &mut |_| {
+                state.bytes = count.load(Ordering::Relaxed);
+                progress(state);
+            }).map_err(io_error)?;
+            state.files = 1;
+        }
+        ArchiveFormat::TarGz => {
+            // Entry sizes aren't known up front, so progress follows the compressed input
+            let count = Arc::new(AtomicU64::new(0));
+            let mut tar = tar::Archive::new(GzDecoder::new(CountingReader { inner: file, count: count.clone() }));
+            state.total_bytes = archive_size;
+            for entry in tar.entries().map_err(io_error)? {
+                let mut entry = entry.map_err(io_error)?;
+                let path = entry.path().map_err(io_error)?.to_path_buf();
+                contained_path(destination, &path)?;
+                if let Some(link) = entry.link_name().map_err(io_error)? {
+                    // Symlinks resolve from the entry's directory, hard links from the
+                    // archive root; either way the target has to stay inside too
+                    let base = if entry.header().entry_type().is_hard_link() { Path::new("") } else { path.parent().unwrap_or(Path::new("")) };
+                    if link.is_absolute() || contained_path(destination, &base.join(&link)).is_err() {
+                        return Err(format!("Refusing to extract {}: its link points outside the destination", path.display()));
+                    }
+                }
+                // unpack_in applies tar's own traversal checks on top of ours
+                entry.unpack_in(destination).map_err(io_error)?;
+                if entry.header().entry_type().is_file() {
+                    state.files += 1;
+                }
+                state.bytes = count.load(Ordering::Relaxed);
+                progress(state);
+            }
+            state.total_files = state.files;
+        }
+        ArchiveFormat::Zip => {
+            let mut zip = zip::ZipArchive::new(file).map_err(|e| format!("Failed to read {}: {}", archive.display(), e))?;
+            for index in 0..zip.len() {
+                let entry = zip.by_index(index).map_err(|e| e.to_string())?;
+                state.total_bytes += entry.size();
+                if !entry.is_dir() {
+                    state.total_files += 1;
+                }
+            }
+            progress(state);
+            for index in 0..zip.len() {
+                let mut entry = zip.by_index(index).map_err(|e| e.to_string())?;
+                let name = entry.enclosed_name()
+                    .map(|name| name.to_path_buf())
+                    .ok_or_else(|| format!("Refusing to extract {}: it points outside the destination", entry.name()))?;
+                let target = contained_path(destination, &name)?;
+                if entry.is_dir() {
+                    fs::create_dir_all(&target).map_err(io_error)?;
+                    continue;
+                }
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent).map_err(io_error)?;
+                }
+                let mut output = File::create(&target).map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+                copy_with_progress(&mut entry, &mut output, &mut |n| {
+                    state.bytes += n;
+                    progress(state);
+                }).map_err(io_error)?;
+                #[cfg(unix)]
+                if let Some(mode) = entry.unix_mode() {
+                    use std::os::unix::fs::PermissionsExt;
+                    let _ = fs::set_permissions(&target, fs::Permissions::from_mode(mode & 0o777));
+                }
+                state.files += 1;
+            }
+        }
+    }
+    progress(state);
+    Ok(())
+}
@@ -1,24 +1,39 @@
-use crate::terminal::{TerminalManager, TerminalGrid};
-use crate::pty::TerminalSize;
+use crate::terminal::{TerminalManager, TerminalGrid, ClickTarget, Clickable, SelectedRange, SelectionMode, DEFAULT_FILE_REFERENCE_PATTERN};
+use crate::pty::{LargeOutputAction, ShellOptions, TerminalSize};
 use crate::shell_hooks::{Command, CommandSuggestion, PromptInfo};
-use crate::search::{ScrollMatch, ContextLine};
+use crate::search::{ScrollMatch, ScrollGrepResult, GrepOptions, ContextLine};
 use crate::ai::{AiClient, AiRequest};
 use crate::workflows;
-use crate::settings::{Settings, load_settings, save_settings};
+use crate::settings::{self, Settings, load_settings, save_settings};
 use crate::plugins;
 use crate::telemetry;
+use crate::structured_output;
+use crate::recording;
+use crate::theme_manager::ThemeManager;
+use crate::advanced_terminal::AdvancedTerminalManager;
+use crate::transcript::{TranscriptExport, TranscriptFormat};
+use crate::clipboard_manager::{ClipboardContentType, ClipboardSource, ClipboardState};
+use crate::notifications::CommandNotifier;
 use tauri::State;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
 pub type TerminalManagerState = Arc<Mutex<TerminalManager>>;
+pub type PluginHostState = Arc<Mutex<plugins::PluginHost>>;
 
+/// `working_dir` is the session's cwd; see `ShellOptions` for how it falls back when omitted.
 #[tauri::command]
 pub async fn create_terminal(
     cols: u16,
     rows: u16,
     shell: Option<String>,
     working_dir: Option<String>,
+    args: Option<Vec<String>>,
+    env: Option<HashMap<String, String>>,
+    login_shell: Option<bool>,
+    encoding: Option<String>,
+    shell_integration: Option<bool>,
     terminal_manager: State<'_, TerminalManagerState>,
 ) -> Result<String, String> {
     let size = TerminalSize {
@@ -27,24 +42,89 @@ pub async fn create_terminal(
         pixel_width: 0, // Will be calculated on frontend
         pixel_height: 0,
     };
+    let options = ShellOptions {
+        shell,
+        args: args.unwrap_or_default(),
+        cwd: working_dir,
+        env: env.unwrap_or_default(),
+        login_shell: login_shell.unwrap_or(false),
+        encoding,
+        no_shell_integration: !shell_integration.unwrap_or(true),
+    };
 
     terminal_manager
         .lock()
         .await
-        .create_terminal(size, shell, working_dir)
+        .create_terminal(size, &options)
         .map_err(|e| e.to_string())
 }
 
+// One-shot and hidden: never shows up among the terminal sessions
+#[tauri::command]
+pub async fn run_captured_command(
+    command: String,
+    args: Option<Vec<String>>,
+    cwd: Option<String>,
+    env: Option<HashMap<String, String>>,
+    timeout_ms: Option<u64>,
+) -> Result<crate::terminal::CapturedOutput, String> {
+    TerminalManager::run_captured(
+        &command,
+        &args.unwrap_or_default(),
+        cwd,
+        &env.unwrap_or_default(),
+        std::time::Duration::from_millis(timeout_ms.unwrap_or(30_000)),
+    )
+    .await
+}
+
+/// Input for a pane in a broadcast group is fanned out to every pane in it. Panes
+/// whose terminal has since closed are skipped; only the addressed one's errors surface.
 #[tauri::command]
 pub async fn write_to_terminal(
     terminal_id: String,
     data: String,
     terminal_manager: State<'_, TerminalManagerState>,
+    advanced_terminal: State<'_, Arc<Mutex<AdvancedTerminalManager>>>,
+) -> Result<(), String> {
+    let targets = advanced_terminal.lock().await.input_targets(&terminal_id);
+    let manager = terminal_manager.lock().await;
+    for target in targets.iter().filter(|target| **target != terminal_id) {
+        if let Err(e) = manager.write_to_terminal(target, &data) {
+            log::warn!("Skipping broadcast to terminal {}: {}", target, e);
+        }
+    }
+    manager.write_to_terminal(&terminal_id, &data).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn paste_to_terminal(
+    terminal_id: String,
+    text: String,
+    terminal_manager: State<'_, TerminalManagerState>,
 ) -> Result<(), String> {
+    let strip_trailing_newlines = load_settings().map(|s| s.strip_trailing_newlines_on_paste).unwrap_or(true);
+    terminal_manager
+        .lock()
+        .await
+        .paste_to_terminal(&terminal_id, &text, strip_trailing_newlines)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn send_mouse_event(
+    terminal_id: String,
+    button: crate::terminal::MouseButton,
+    action: crate::terminal::MouseAction,
+    row: u16,
+    col: u16,
+    modifiers: Option<crate::terminal::MouseModifiers>,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<bool, String> {
     terminal_manager
         .lock()
         .await
-        .write_to_terminal(&terminal_id, &data)
+        .send_mouse_event(&terminal_id, button, action, row, col, &modifiers.unwrap_or_default())
         .map_err(|e| e.to_string())
 }
 
@@ -92,6 +172,557 @@ pub async fn get_terminal_state(
         .get_terminal_state(&terminal_id))
 }
 
+#[tauri::command]
+pub async fn start_selection(
+    terminal_id: String,
+    row: u16,
+    col: u16,
+    mode: SelectionMode,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<(), String> {
+    terminal_manager.lock().await.start_selection(&terminal_id, row, col, mode)
+}
+
+#[tauri::command]
+pub async fn update_selection(
+    terminal_id: String,
+    row: u16,
+    col: u16,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<(), String> {
+    terminal_manager.lock().await.update_selection(&terminal_id, row, col)
+}
+
+#[tauri::command]
+pub async fn clear_selection(
+    terminal_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<(), String> {
+    terminal_manager.lock().await.clear_selection(&terminal_id);
+    Ok(())
+}
+
+fn selection_word_chars() -> String {
+    load_settings().map(|s| s.selection_word_chars).unwrap_or_else(|_| Settings::default().selection_word_chars)
+}
+
+#[tauri::command]
+pub async fn select_word_at(
+    terminal_id: String,
+    row: u16,
+    col: u16,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<Option<SelectedRange>, String> {
+    let word_chars = selection_word_chars();
+    terminal_manager.lock().await.select_at(&terminal_id, |terminal| terminal.select_word_at(row, col, &word_chars))
+}
+
+#[tauri::command]
+pub async fn select_line_at(
+    terminal_id: String,
+    row: u16,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<Option<SelectedRange>, String> {
+    terminal_manager.lock().await.select_at(&terminal_id, |terminal| terminal.select_line_at(row))
+}
+
+#[tauri::command]
+pub async fn select_semantic_at(
+    terminal_id: String,
+    row: u16,
+    col: u16,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<Option<SelectedRange>, String> {
+    let word_chars = selection_word_chars();
+    terminal_manager.lock().await.select_at(&terminal_id, |terminal| terminal.select_semantic_at(row, col, &word_chars))
+}
+
+fn file_reference_pattern() -> Result<regex::Regex, String> {
+    let configured = load_settings().map(|s| s.file_reference_pattern).unwrap_or_default();
+    let pattern = if configured.is_empty() { DEFAULT_FILE_REFERENCE_PATTERN } else { configured.as_str() };
+    regex::Regex::new(pattern).map_err(|e| format!("Invalid file reference pattern: {}", e))
+}
+
+/// URLs and file:line:column references on a screen row, for hover and click.
+#[tauri::command]
+pub async fn detect_clickable_regions(
+    terminal_id: String,
+    row: u16,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<Vec<Clickable>, String> {
+    let file_pattern = file_reference_pattern()?;
+    terminal_manager.lock().await.detect_clickable_regions(&terminal_id, row, &file_pattern)
+}
+
+/// What a clicked link may open. Only web links go to the browser; `file://`
+/// links are opened like file paths, and anything else (custom schemes,
+/// `ssh://`, strings with shell metacharacters dressed up as links) is refused.
+#[derive(Debug, PartialEq)]
+enum LinkTarget {
+    Web(String),
+    File(std::path::PathBuf),
+}
+
+fn link_target(url: &str) -> Result<LinkTarget, String> {
+    let lower = url.to_ascii_lowercase();
+    if lower.starts_with("http://") || lower.starts_with("https://") {
+        Ok(LinkTarget::Web(url.to_string()))
+    } else if lower.starts_with("www.") {
+        Ok(LinkTarget::Web(format!("https://{}", url)))
+    } else if lower.starts_with("file://") {
+        let path = &url["file://".len()..];
+        // file:///C:/x names the Windows path C:/x
+        let path = match path.strip_prefix('/') {
+            Some(rest) if cfg!(windows) && rest.get(1..2) == Some(":") => rest,
+            _ => path,
+        };
+        Ok(LinkTarget::File(std::path::PathBuf::from(path)))
+    } else {
+        Err(format!("Refusing to open {}: only http and https links can be opened", url))
+    }
+}
+
+/// Open with the platform's default handler. The opener plugin hands the
+/// target straight to ShellExecute/`open`/`xdg-open`, never through a shell.
+fn system_open(app: &tauri::AppHandle, target: &std::path::Path) -> Result<(), String> {
+    use tauri_plugin_opener::OpenerExt;
+    app.opener()
+        .open_path(target.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("Failed to open {}: {}", target.display(), e))
+}
+
+/// Open a clicked target: URLs in the browser, files in `editor_command` (or
+/// the default app). Relative paths resolve against the terminal's cwd.
+#[tauri::command]
+pub async fn open_clickable(
+    app: tauri::AppHandle,
+    target: ClickTarget,
+    terminal_id: Option<String>,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<(), String> {
+    let (path, line, column) = match target {
+        ClickTarget::Url { url } => match link_target(&url)? {
+            LinkTarget::Web(url) => {
+                use tauri_plugin_opener::OpenerExt;
+                return app.opener()
+                    .open_url(&url, None::<&str>)
+                    .map_err(|e| format!("Failed to open {}: {}", url, e));
+            }
+            LinkTarget::File(path) => (path.to_string_lossy().to_string(), None, None),
+        },
+        ClickTarget::File { path, line, column } => (path, line, column),
+    };
+    let expanded = match path.strip_prefix('~') {
+        Some(rest) => std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")).map(|home| format!("{}{}", home, rest)).unwrap_or_else(|_| path.clone()),
+        None => path.clone(),
+    };
+    let mut resolved = std::path::PathBuf::from(&expanded);
+    if resolved.is_relative() {
+        let cwd = match terminal_id {
+            Some(id) => terminal_manager.lock().await.get_working_directory(&id),
+            None => None,
+        };
+        if let Some(cwd) = cwd {
+            resolved = std::path::Path::new(&cwd).join(resolved);
+        }
+    }
+    if !resolved.exists() {
+        return Err(format!("{} does not exist", resolved.display()));
+    }
+    let resolved = resolved.to_string_lossy().to_string();
+
+    let editor = load_settings().map(|s| s.editor_command).unwrap_or_default();
+    if editor.trim().is_empty() {
+        return system_open(&app, std::path::Path::new(&resolved));
+    }
+    // Substitute per argument so paths with spaces stay one argument
+    let args: Vec<String> = editor
+        .split_whitespace()
+        .map(|arg| {
+            arg.replace("{path}", &resolved)
+                .replace("{line}", &line.unwrap_or(1).to_string())
+                .replace("{column}", &column.unwrap_or(1).to_string())
+        })
+        .collect();
+    let (program, rest) = args.split_first().ok_or("editor_command is empty")?;
+    std::process::Command::new(program)
+        .args(rest)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to start {}: {}", program, e))
+}
+
+#[tauri::command]
+pub async fn get_selection_text(
+    terminal_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<Option<String>, String> {
+    Ok(terminal_manager.lock().await.get_selection_text(&terminal_id))
+}
+
+/// Copy the terminal's current selection; returns the new clipboard entry id.
+#[tauri::command]
+pub async fn copy_terminal_selection(
+    terminal_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+    clipboard_manager: State<'_, ClipboardStateHandle>,
+) -> Result<String, String> {
+    let text = terminal_manager
+        .lock()
+        .await
+        .get_selection_text(&terminal_id)
+        .ok_or("Nothing is selected")?;
+    clipboard_manager
+        .lock()
+        .await
+        .add_to_clipboard(text, ClipboardContentType::PlainText, ClipboardSource::Selection)
+}
+
+// 0 removes the cap; backpressure from a slow consumer still applies
+#[tauri::command]
+pub async fn set_output_rate_limit(
+    max_bytes_per_sec: u64,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<(), String> {
+    // Saving publishes a settings change, but apply now rather than after the debounce
+    terminal_manager.lock().await.output_throttle().set_max_bytes_per_sec(max_bytes_per_sec);
+    let mut settings = load_settings()?;
+    settings.max_output_bytes_per_sec = max_bytes_per_sec;
+    save_settings(&settings)
+}
+
+/// Answer a `large-output-warning`: truncate keeps the command's output hidden,
+/// show_full reveals what was held back and lets the rest through.
+#[tauri::command]
+pub async fn resolve_large_output(
+    terminal_id: String,
+    action: LargeOutputAction,
+    app: tauri::AppHandle,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<(), String> {
+    use tauri::Emitter;
+    // Emit while holding the manager so newer output can't overtake the held output.
+    // The model already has it; only the frontend was kept waiting.
+    let manager = terminal_manager.lock().await;
+    if let Some(output) = manager.resolve_large_output(&terminal_id, action)? {
+        let _ = app.emit("terminal-output", &output);
+    }
+    Ok(())
+}
+
+/// Screen rows, cursor and modes changed since `since_version` (0 for a full
+/// snapshot); the returned `version` is the one to ask from next.
+#[tauri::command]
+pub async fn get_grid_diff(
+    terminal_id: String,
+    since_version: u64,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<crate::terminal::GridDiff, String> {
+    terminal_manager.lock().await.get_grid_diff(&terminal_id, since_version)
+}
+
+/// Screen rows written since the last call, for redrawing only those.
+#[tauri::command]
+pub async fn take_dirty_rows(
+    terminal_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<Vec<usize>, String> {
+    terminal_manager.lock().await.take_dirty_rows(&terminal_id)
+}
+
+/// Decode a terminal's output as `encoding` (a label such as `latin1` or
+/// `shift_jis`) instead of UTF-8; returns the encoding's canonical name.
+#[tauri::command]
+pub async fn set_session_encoding(
+    terminal_id: String,
+    encoding: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<String, String> {
+    let manager = terminal_manager.lock().await;
+    manager.set_session_encoding(&terminal_id, &encoding).map(str::to_string)
+}
+
+/// Restore a terminal to a known-good state without restarting its shell, for
+/// sessions broken by binary output (`binary-output-detected`) or a crashed TUI.
+#[tauri::command]
+pub async fn reset_terminal(
+    terminal_id: String,
+    app: tauri::AppHandle,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<(), String> {
+    use tauri::Emitter;
+    let manager = terminal_manager.lock().await;
+    let output = manager.reset_terminal(&terminal_id)?;
+    let _ = app.emit("terminal-output", &output);
+    let _ = app.emit("terminal-reset", serde_json::json!({ "terminal_id": terminal_id }));
+    Ok(())
+}
+
+/// Turn long-command completion notifications on or off for one session.
+#[tauri::command]
+pub async fn set_command_notifications(
+    terminal_id: String,
+    enabled: bool,
+    notifier: State<'_, Arc<Mutex<CommandNotifier>>>,
+) -> Result<(), String> {
+    notifier.lock().await.set_session_enabled(&terminal_id, enabled);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_scrollback_limits(
+    max_lines: usize,
+    max_bytes: usize,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<(), String> {
+    if max_lines == 0 || max_bytes == 0 {
+        return Err("Scrollback limits must be greater than zero".to_string());
+    }
+    terminal_manager.lock().await.set_scrollback_limits(max_lines, max_bytes);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_command_blocks(
+    terminal_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<Vec<crate::terminal::CommandBlock>, String> {
+    terminal_manager
+        .lock()
+        .await
+        .get_command_blocks(&terminal_id)
+        .ok_or_else(|| format!("Terminal {} not found", terminal_id))
+}
+
+/// How long the session's last command took, or has taken so far if it's still
+/// running; `None` before any command has run.
+#[tauri::command]
+pub async fn get_last_command_duration(
+    terminal_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<Option<crate::terminal::CommandDuration>, String> {
+    terminal_manager.lock().await.get_last_command_duration(&terminal_id)
+}
+
+#[tauri::command]
+pub async fn get_block_output(
+    block_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<String, String> {
+    terminal_manager
+        .lock()
+        .await
+        .get_block_output(&block_id)
+        .ok_or_else(|| format!("Command block {} not found", block_id))
+}
+
+pub type ClipboardStateHandle = Arc<Mutex<ClipboardState>>;
+
+/// Copy a block's plain-text output; returns the new clipboard entry id.
+#[tauri::command]
+pub async fn copy_block_output(
+    block_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+    clipboard_manager: State<'_, ClipboardStateHandle>,
+) -> Result<String, String> {
+    let output = terminal_manager
+        .lock()
+        .await
+        .get_copyable_block_output(&block_id)
+        .ok_or_else(|| format!("Command block {} not found", block_id))?;
+    clipboard_manager.lock().await.add_to_clipboard(output, ClipboardContentType::Output, ClipboardSource::Terminal)
+}
+
+#[tauri::command]
+pub async fn copy_last_output(
+    terminal_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+    clipboard_manager: State<'_, ClipboardStateHandle>,
+) -> Result<String, String> {
+    let (_, output) = terminal_manager
+        .lock()
+        .await
+        .get_last_command_output(&terminal_id)
+        .ok_or_else(|| format!("No command has run in terminal {}", terminal_id))?;
+    clipboard_manager.lock().await.add_to_clipboard(output, ClipboardContentType::Output, ClipboardSource::Terminal)
+}
+
+#[tauri::command]
+pub async fn copy_last_command(
+    terminal_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+    clipboard_manager: State<'_, ClipboardStateHandle>,
+) -> Result<String, String> {
+    let (command, _) = terminal_manager
+        .lock()
+        .await
+        .get_last_command_output(&terminal_id)
+        .ok_or_else(|| format!("No command has run in terminal {}", terminal_id))?;
+    clipboard_manager.lock().await.add_to_clipboard(command, ClipboardContentType::Command, ClipboardSource::Terminal)
+}
+
+/// Parse a block's output as JSON, JSON lines or a table, falling back to raw text.
+#[tauri::command]
+pub async fn parse_command_output(
+    block_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<structured_output::StructuredOutput, String> {
+    let output = terminal_manager
+        .lock()
+        .await
+        .get_block_output(&block_id)
+        .ok_or_else(|| format!("Command block {} not found", block_id))?;
+    Ok(structured_output::parse(&output))
+}
+
+#[tauri::command]
+pub async fn detect_output_format(
+    block_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<structured_output::OutputFormat, String> {
+    let output = terminal_manager
+        .lock()
+        .await
+        .get_block_output(&block_id)
+        .ok_or_else(|| format!("Command block {} not found", block_id))?;
+    Ok(structured_output::detect_format(&output))
+}
+
+/// A single command block, e.g. to pre-fill its command line for editing.
+#[tauri::command]
+pub async fn get_block(
+    block_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<crate::terminal::CommandBlock, String> {
+    terminal_manager
+        .lock()
+        .await
+        .get_block(&block_id)
+        .ok_or_else(|| format!("Command block {} not found", block_id))
+}
+
+/// Run a previous block's command again, or with `execute: false` put it on
+/// the prompt to edit first. Queued until the next prompt if a command is running.
+#[tauri::command]
+pub async fn rerun_block(
+    terminal_id: String,
+    block_id: String,
+    execute: Option<bool>,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<crate::terminal::RerunStatus, String> {
+    terminal_manager.lock().await.rerun_block(&terminal_id, &block_id, execute.unwrap_or(true))
+}
+
+#[tauri::command]
+pub async fn fold_block(
+    block_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<(), String> {
+    if terminal_manager.lock().await.set_block_folded(&block_id, true) {
+        Ok(())
+    } else {
+        Err(format!("Command block {} not found", block_id))
+    }
+}
+
+#[tauri::command]
+pub async fn unfold_block(
+    block_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<(), String> {
+    if terminal_manager.lock().await.set_block_folded(&block_id, false) {
+        Ok(())
+    } else {
+        Err(format!("Command block {} not found", block_id))
+    }
+}
+
+#[tauri::command]
+pub async fn get_visible_scrollback(
+    terminal_id: String,
+    limit: Option<usize>,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<Vec<crate::terminal::VisibleLine>, String> {
+    terminal_manager
+        .lock()
+        .await
+        .get_visible_scrollback(&terminal_id, limit)
+        .ok_or_else(|| format!("Terminal {} not found", terminal_id))
+}
+
+/// Number of scrollback rows, for sizing a virtualized scroll view.
+#[tauri::command]
+pub async fn get_scrollback_len(
+    terminal_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<usize, String> {
+    terminal_manager.lock().await.get_scrollback_len(&terminal_id)
+}
+
+/// Styled scrollback rows `start..start + count` (0 is the oldest retained
+/// row); a range past the end is clamped.
+#[tauri::command]
+pub async fn get_scrollback_rows(
+    terminal_id: String,
+    start: usize,
+    count: usize,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<Vec<crate::terminal::ScrollbackRow>, String> {
+    terminal_manager.lock().await.get_scrollback_rows(&terminal_id, start, count)
+}
+
+#[tauri::command]
+pub async fn get_accessible_terminal_snapshot(
+    terminal_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<crate::terminal::AccessibleSnapshot, String> {
+    terminal_manager
+        .lock()
+        .await
+        .get_accessible_snapshot(&terminal_id)
+        .ok_or_else(|| format!("Terminal {} not found", terminal_id))
+}
+
+pub type HistoryEmbeddingState = Arc<Mutex<crate::search::HistoryEmbeddingIndex>>;
+
+/// Closest history commands by meaning, falling back to fuzzy matching when the
+/// embedding provider is disabled or fails.
+#[tauri::command]
+pub async fn semantic_search_history(
+    query: String,
+    limit: Option<usize>,
+    terminal_manager: State<'_, TerminalManagerState>,
+    embedding_index: State<'_, HistoryEmbeddingState>,
+    security_manager: State<'_, Arc<Mutex<crate::security::SecurityManager>>>,
+) -> Result<Vec<crate::search::HistorySearchResult>, String> {
+    let limit = limit.unwrap_or(20);
+    let mut history = terminal_manager.lock().await.get_all_command_history();
+    // Secrets typed on the command line are masked before anything is embedded or saved
+    let query = {
+        let security = security_manager.lock().await;
+        for command in history.iter_mut() {
+            command.text = security.mask_sensitive_data(&command.text);
+        }
+        security.mask_sensitive_data(&query)
+    };
+    let allow_remote = load_settings().map(|s| s.remote_history_embeddings).unwrap_or(false);
+    let provider = crate::ai::EmbeddingProvider::from_env(allow_remote);
+
+    let mut index = embedding_index.lock().await;
+    let semantic = async {
+        index.update(&history, &provider).await?;
+        let query_vector = provider.embed(std::slice::from_ref(&query)).await?.pop().ok_or("Empty embedding response")?;
+        Ok::<_, String>(index.search(&query_vector, limit))
+    };
+    match semantic.await {
+        Ok(results) => Ok(results),
+        Err(e) => {
+            log::info!("Semantic history search unavailable, using fuzzy search: {}", e);
+            Ok(crate::search::fuzzy_search_history(&query, &history, limit))
+        }
+    }
+}
+
 // Shell integration commands
 #[tauri::command]
 pub async fn get_command_history(
@@ -127,10 +758,16 @@ pub async fn get_command_suggestions(
     partial_command: String,
     terminal_manager: State<'_, TerminalManagerState>,
 ) -> Result<Vec<CommandSuggestion>, String> {
+    // Project detection reads from disk, so it runs without holding the manager
+    let cwd = terminal_manager.lock().await.get_working_directory(&terminal_id);
+    let project = match &cwd {
+        Some(dir) => crate::dev_tools::detect_enclosing_project(std::path::Path::new(dir)).await,
+        None => None,
+    };
     Ok(terminal_manager
         .lock()
         .await
-        .get_command_suggestions(&terminal_id, &partial_command)
+        .get_command_suggestions(&terminal_id, &partial_command, cwd.as_deref().map(std::path::Path::new), project.as_ref())
         .unwrap_or_default())
 }
 
@@ -148,6 +785,20 @@ pub async fn handle_tab_completion(
         .unwrap_or_default())
 }
 
+#[tauri::command]
+pub async fn get_completion_items(
+    terminal_id: String,
+    current_line: String,
+    cursor_pos: usize,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<Vec<crate::completions::CompletionItem>, String> {
+    Ok(terminal_manager
+        .lock()
+        .await
+        .handle_tab_completion_items(&terminal_id, &current_line, cursor_pos)
+        .unwrap_or_default())
+}
+
 #[tauri::command]
 pub async fn is_at_prompt(
     terminal_id: String,
@@ -170,6 +821,23 @@ pub async fn get_current_prompt(
         .get_current_prompt(&terminal_id))
 }
 
+#[tauri::command]
+pub async fn record_exit_code(
+    terminal_id: String,
+    exit_code: i32,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<(), String> {
+    terminal_manager.lock().await.record_exit_code(&terminal_id, exit_code)
+}
+
+#[tauri::command]
+pub async fn get_last_exit_code(
+    terminal_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<Option<i32>, String> {
+    terminal_manager.lock().await.get_last_exit_code(&terminal_id)
+}
+
 #[tauri::command]
 pub async fn search_history(
     terminal_id: String,
@@ -199,20 +867,205 @@ pub async fn search_scrollback(
         .unwrap_or_default())
 }
 
+/// Write the session's scrollback to `path` as plain text, themed HTML or SGR-styled text.
+#[tauri::command]
+pub async fn export_scrollback(
+    terminal_id: String,
+    format: TranscriptFormat,
+    path: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+    theme_manager: State<'_, Arc<Mutex<ThemeManager>>>,
+) -> Result<TranscriptExport, String> {
+    let scheme = theme_manager.lock().await.get_current_theme().map(|theme| theme.color_scheme);
+    let (text, lines) = terminal_manager
+        .lock()
+        .await
+        .render_transcript(&terminal_id, format, scheme.as_ref())
+        .ok_or_else(|| format!("Terminal {} not found", terminal_id))?;
+    std::fs::write(&path, &text).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(TranscriptExport { path, format, lines, bytes: text.len() })
+}
+
+#[tauri::command]
+pub async fn start_recording(
+    terminal_id: String,
+    path: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<(), String> {
+    terminal_manager.lock().await.start_recording(&terminal_id, &path)
+}
+
+#[tauri::command]
+pub async fn stop_recording(
+    terminal_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<recording::RecordingSummary, String> {
+    terminal_manager
+        .lock()
+        .await
+        .stop_recording(&terminal_id)
+        .ok_or_else(|| format!("Terminal {} is not being recorded", terminal_id))
+}
+
+/// Replay a `.cast` file into a new display-only terminal at its recorded pace
+/// (scaled by `speed`). Output arrives as ordinary "terminal-output" events, size
+/// changes as "terminal-resized", and "recording-playback-finished" ends it.
+#[tauri::command]
+pub async fn play_recording(
+    app: tauri::AppHandle,
+    path: String,
+    speed: Option<f64>,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<recording::PlaybackInfo, String> {
+    use tauri::Emitter;
+    let speed = speed.unwrap_or(1.0);
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err(format!("Invalid playback speed {}", speed));
+    }
+    let (header, events) = recording::read_cast(&path)?;
+    let delays = recording::playback_delays(&header, &events, speed);
+    let size = TerminalSize { cols: header.width, rows: header.height, pixel_width: 0, pixel_height: 0 };
+    let (terminal_id, cancel) = terminal_manager.lock().await.create_playback_terminal(size);
+    let info = recording::PlaybackInfo {
+        terminal_id: terminal_id.clone(),
+        width: header.width,
+        height: header.height,
+        events: events.len(),
+        duration_secs: delays.iter().sum(),
+    };
+
+    let manager = terminal_manager.inner().clone();
+    tauri::async_runtime::spawn(async move {
+        for (recording::CastEvent(_, code, data), delay) in events.into_iter().zip(delays) {
+            if delay > 0.0 {
+                tokio::time::sleep(std::time::Duration::from_secs_f64(delay)).await;
+            }
+            if cancel.load(std::sync::atomic::Ordering::Relaxed) {
+                break;
+            }
+            match code.as_str() {
+                "o" => {
+                    manager.lock().await.feed_playback(&terminal_id, &data);
+                    let _ = app.emit("terminal-output", &crate::pty::TerminalOutput { session_id: terminal_id.clone(), data });
+                }
+                "r" => {
+                    if let Some(size) = recording::parse_resize(&data) {
+                        let _ = app.emit(
+                            "terminal-resized",
+                            serde_json::json!({ "terminal_id": terminal_id, "cols": size.cols, "rows": size.rows }),
+                        );
+                        manager.lock().await.resize_playback(&terminal_id, size);
+                    }
+                }
+                _ => {} // input and marker events aren't replayed
+            }
+        }
+        let cancelled = cancel.load(std::sync::atomic::Ordering::Relaxed);
+        manager.lock().await.finish_playback(&terminal_id);
+        let _ = app.emit(
+            "recording-playback-finished",
+            serde_json::json!({ "terminal_id": terminal_id, "cancelled": cancelled }),
+        );
+    });
+
+    Ok(info)
+}
+
+#[tauri::command]
+pub async fn stop_playback(
+    terminal_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<bool, String> {
+    Ok(terminal_manager.lock().await.stop_playback(&terminal_id))
+}
+
+/// `grep -C` over the scrollback: matches with surrounding lines, grouped into hunks.
+/// Options left out default to 3 lines of context either side and 500 matches.
+#[tauri::command]
+pub async fn search_scrollback_with_context(
+    terminal_id: String,
+    pattern: String,
+    options: Option<GrepOptions>,
+    terminal_manager: State<'_, TerminalManagerState>,
+) -> Result<ScrollGrepResult, String> {
+    let options = options.unwrap_or_default();
+    if options.use_regex {
+        regex::Regex::new(&pattern).map_err(|e| format!("Invalid regex: {}", e))?;
+    }
+    terminal_manager
+        .lock()
+        .await
+        .grep_scrollback(&terminal_id, &pattern, &options)
+        .ok_or_else(|| format!("Terminal {} not found", terminal_id))
+}
+
 // Settings endpoints
 #[tauri::command]
 pub async fn get_settings() -> Result<Settings, String> { load_settings() }
 #[tauri::command]
-pub async fn save_user_settings(settings: Settings) -> Result<(), String> { save_settings(&settings) }
+pub async fn save_user_settings(settings: serde_json::Value) -> Result<(), Vec<settings::SettingsFieldError>> {
+    let settings = Settings::from_json(settings)?;
+    save_settings(&settings).map_err(|e| vec![settings::SettingsFieldError { field: String::new(), message: e }])
+}
 
 // Plugins
 #[tauri::command]
 pub async fn list_plugins() -> Result<Vec<plugins::PluginManifest>, String> { Ok(plugins::list_plugins()) }
+#[tauri::command]
+pub async fn enable_plugin(name: String, plugin_host: State<'_, PluginHostState>) -> Result<(), String> {
+    plugin_host.lock().await.enable_plugin(&name)
+}
+#[tauri::command]
+pub async fn disable_plugin(name: String, plugin_host: State<'_, PluginHostState>) -> Result<(), String> {
+    plugin_host.lock().await.disable_plugin(&name)
+}
+#[tauri::command]
+pub async fn get_plugin_commands(plugin_host: State<'_, PluginHostState>) -> Result<Vec<plugins::PaletteCommand>, String> {
+    Ok(plugin_host.lock().await.get_plugin_commands())
+}
+#[tauri::command]
+pub async fn invoke_plugin_command(
+    plugin_id: String,
+    command_id: String,
+    args: Option<serde_json::Value>,
+    plugin_host: State<'_, PluginHostState>,
+) -> Result<plugins::PluginCommandResult, String> {
+    // Release the host lock before waiting on the plugin so other calls aren't blocked
+    let response = plugin_host
+        .lock()
+        .await
+        .invoke_plugin_command(&plugin_id, &command_id, args.unwrap_or(serde_json::Value::Null))?;
+    response.await.map_err(|_| format!("Plugin {} stopped before responding", plugin_id))?
+}
+#[tauri::command]
+pub async fn get_plugin_statuses(plugin_host: State<'_, PluginHostState>) -> Result<Vec<plugins::PluginStatus>, String> {
+    Ok(plugin_host.lock().await.get_statuses())
+}
 
 // Telemetry
 #[tauri::command]
 pub async fn record_event(kind: String, data: serde_json::Value) { telemetry::record(&kind, data); }
 
+#[tauri::command]
+pub async fn get_telemetry_summary() -> Result<telemetry::TelemetrySummary, String> {
+    Ok(telemetry::get_summary())
+}
+
+#[tauri::command]
+pub async fn upload_telemetry() -> Result<telemetry::TelemetryUploadResult, String> {
+    telemetry::upload_metrics().await
+}
+
+#[tauri::command]
+pub async fn list_crash_reports() -> Result<Vec<telemetry::CrashReportSummary>, String> {
+    Ok(telemetry::list_crash_reports())
+}
+
+#[tauri::command]
+pub async fn export_crash_report(id: String, destination: String) -> Result<(), String> {
+    telemetry::export_crash_report(&id, &destination)
+}
+
 // Workflow endpoints
 #[tauri::command]
 pub async fn list_workflows() -> Result<Vec<workflows::Workflow>, String> {
@@ -229,17 +1082,57 @@ pub async fn delete_workflow(id: String) -> Result<(), String> {
     workflows::delete(&id)
 }
 
+#[tauri::command]
+pub async fn import_workflow_yaml(yaml: String) -> Result<Vec<workflows::Workflow>, String> {
+    workflows::import_workflow_yaml(&yaml)
+}
+
+#[tauri::command]
+pub async fn import_workflows_from_directory(path: String) -> Result<workflows::WorkflowImportReport, String> {
+    workflows::import_workflows_from_directory(&path)
+}
+
 #[tauri::command]
 pub async fn preview_workflow_command(workflow_id: String, values: std::collections::HashMap<String, String>) -> Result<String, String> {
     let wf = workflows::get(&workflow_id)?;
+    let values = workflows::resolve_params(&wf, &values)?;
     Ok(workflows::render_command(&wf.command, &values))
 }
 
 #[tauri::command]
-pub async fn run_workflow(terminal_id: String, workflow_id: String, values: std::collections::HashMap<String, String>, terminal_manager: State<'_, TerminalManagerState>) -> Result<(), String> {
+pub async fn preview_workflow(workflow_id: String, values: std::collections::HashMap<String, String>) -> Result<Vec<workflows::WorkflowStepPreview>, String> {
     let wf = workflows::get(&workflow_id)?;
-    let cmd = workflows::render_command(&wf.command, &values) + "\r";
-    terminal_manager.lock().await.write_to_terminal(&terminal_id, &cmd).map_err(|e| e.to_string())
+    let values = workflows::resolve_params(&wf, &values)?;
+    Ok(workflows::preview_steps(&wf, &values))
+}
+
+#[tauri::command]
+pub async fn run_workflow(terminal_id: String, workflow_id: String, values: std::collections::HashMap<String, String>, terminal_manager: State<'_, TerminalManagerState>) -> Result<workflows::WorkflowRunReport, String> {
+    let wf = workflows::get(&workflow_id)?;
+    let values = workflows::resolve_params(&wf, &values)?;
+
+    // Single-command workflows are typed into the terminal as before
+    if wf.steps.is_empty() {
+        let cmd = workflows::render_command(&wf.command, &values);
+        terminal_manager.lock().await.write_to_terminal(&terminal_id, &(cmd.clone() + "\r")).map_err(|e| e.to_string())?;
+        return Ok(workflows::WorkflowRunReport {
+            workflow_id: wf.id,
+            success: true,
+            steps: vec![workflows::WorkflowStepResult {
+                index: 0,
+                name: None,
+                command: cmd,
+                status: workflows::StepStatus::SentToTerminal,
+                exit_code: None,
+                stdout: String::new(),
+                stderr: String::new(),
+                duration_ms: 0,
+            }],
+        });
+    }
+
+    let working_dir = terminal_manager.lock().await.get_current_prompt(&terminal_id).map(|p| p.working_dir);
+    Ok(workflows::run_steps(&wf, &values, working_dir).await)
 }
 
 // AI endpoints
@@ -259,6 +1152,36 @@ pub async fn ai_generate_command(
     client.generate(req).await.map(|r| r.text).map_err(|e| e)
 }
 
+/// Like `ai_generate_command`, but the suggestion comes back with the AI's
+/// rationale and a policy/risk assessment; nothing is executed.
+#[tauri::command]
+pub async fn ai_generate_command_checked(
+    terminal_id: Option<String>,
+    user_input: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+    security_manager: State<'_, Arc<Mutex<crate::security::SecurityManager>>>,
+) -> Result<crate::ai::CheckedCommandSuggestion, String> {
+    let ctx = if let Some(id) = &terminal_id {
+        terminal_manager.lock().await.gather_context(id).unwrap_or_else(|| crate::ai::AiContext { working_dir: None, prompt: None, recent_commands: vec![], tail_output: vec![] })
+    } else {
+        crate::ai::AiContext { working_dir: None, prompt: None, recent_commands: vec![], tail_output: vec![] }
+    };
+    let client = AiClient::from_env();
+    let req = AiRequest { task: "generate_command_explained".into(), user_input, context: ctx };
+    let text = client.generate(req).await?.text;
+    let (command, rationale) = crate::ai::parse_command_with_rationale(&text);
+    if command.is_empty() {
+        return Err("The AI response did not contain a command".to_string());
+    }
+    let assessment = security_manager.lock().await.assess_command(&command);
+    Ok(crate::ai::CheckedCommandSuggestion {
+        blocked: assessment.verdict == crate::security::CommandVerdict::Blocked,
+        command,
+        rationale,
+        assessment,
+    })
+}
+
 #[tauri::command]
 pub async fn ai_explain_error(
     terminal_id: Option<String>,
@@ -273,6 +1196,32 @@ pub async fn ai_explain_error(
     client.generate(req).await.map(|r| r.text).map_err(|e| e)
 }
 
+// Works on any finished block, successful or not
+#[tauri::command]
+pub async fn ai_explain_output(
+    block_id: String,
+    terminal_manager: State<'_, TerminalManagerState>,
+    security_manager: State<'_, Arc<Mutex<crate::security::SecurityManager>>>,
+) -> Result<String, String> {
+    let (block, output, ctx) = {
+        let manager = terminal_manager.lock().await;
+        let (block, output) = manager
+            .get_block_with_output(&block_id)
+            .ok_or_else(|| format!("Block {} not found", block_id))?;
+        let ctx = manager.gather_context(&block.terminal_id).unwrap_or_else(|| crate::ai::AiContext { working_dir: None, prompt: None, recent_commands: vec![], tail_output: vec![] });
+        (block, output, ctx)
+    };
+    // Only the block itself goes out, with secrets masked
+    let ctx = crate::ai::AiContext { recent_commands: vec![], tail_output: vec![], ..ctx };
+    let (command, output) = {
+        let security = security_manager.lock().await;
+        (security.mask_sensitive_data(&block.command), security.mask_sensitive_data(&output))
+    };
+    let client = AiClient::from_env();
+    let req = crate::ai::explain_output_request(&command, &output, block.exit_code, ctx);
+    client.generate(req).await.map(|r| r.text)
+}
+
 #[tauri::command]
 pub async fn ai_suggest_next(
     terminal_id: String,
@@ -283,3 +1232,18 @@ pub async fn ai_suggest_next(
     let req = AiRequest { task: "suggest_next".into(), user_input: String::new(), context: ctx };
     client.generate(req).await.map(|r| r.text).map_err(|e| e)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_web_and_file_links_are_opened() {
+        assert_eq!(link_target("https://example.com/a?b=1&c=2").unwrap(), LinkTarget::Web("https://example.com/a?b=1&c=2".into()));
+        assert_eq!(link_target("www.example.com").unwrap(), LinkTarget::Web("https://www.example.com".into()));
+        assert_eq!(link_target("file:///tmp/log.txt").unwrap(), LinkTarget::File("/tmp/log.txt".into()));
+        assert!(link_target("ftp://example.com/x").is_err());
+        assert!(link_target("ssh://host").is_err());
+        assert!(link_target("calc.exe&whoami").is_err());
+    }
+}
@@ -0,0 +1,575 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::{Path, PathBuf}, sync::{OnceLock, RwLock}};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CompletionKind {
+    File,
+    Directory,
+    Flag,
+    Command,
+    Subcommand,
+    GitRef,
+    Argument,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionItem {
+    pub kind: CompletionKind,
+    pub value: String, // text that replaces the word under the cursor
+    pub label: String,
+    pub description: Option<String>,
+}
+
+struct SubcommandSpec {
+    name: &'static str,
+    description: &'static str,
+    flags: &'static [(&'static str, &'static str)],
+    takes_ref: bool,
+}
+
+struct CommandSpec {
+    name: &'static str,
+    description: &'static str,
+    flags: &'static [(&'static str, &'static str)],
+    subcommands: &'static [SubcommandSpec],
+}
+
+const fn sub(
+    name: &'static str,
+    description: &'static str,
+    flags: &'static [(&'static str, &'static str)],
+    takes_ref: bool,
+) -> SubcommandSpec {
+    SubcommandSpec { name, description, flags, takes_ref }
+}
+
+static SPECS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "git",
+        description: "Distributed version control",
+        flags: &[("--version", "Print the git version"), ("--help", "Show help"), ("-C", "Run as if started in <path>")],
+        subcommands: &[
+            sub("status", "Show the working tree status", &[("-s", "Short format"), ("-b", "Show branch info")], false),
+            sub("add", "Add file contents to the index", &[("-A", "Stage all changes"), ("-p", "Interactively pick hunks"), ("-u", "Stage tracked files only")], false),
+            sub("commit", "Record changes to the repository", &[("-m", "Commit message"), ("-a", "Stage tracked changes first"), ("--amend", "Replace the last commit"), ("--no-verify", "Skip hooks")], false),
+            sub("push", "Update remote refs", &[("-u", "Set upstream"), ("--force-with-lease", "Force push if remote is unchanged"), ("--tags", "Push tags")], true),
+            sub("pull", "Fetch and integrate remote changes", &[("--rebase", "Rebase instead of merge"), ("--ff-only", "Only fast-forward")], true),
+            sub("fetch", "Download objects and refs", &[("--all", "Fetch all remotes"), ("--prune", "Remove deleted remote branches")], false),
+            sub("checkout", "Switch branches or restore files", &[("-b", "Create and switch to a new branch")], true),
+            sub("switch", "Switch branches", &[("-c", "Create and switch to a new branch")], true),
+            sub("branch", "List, create, or delete branches", &[("-d", "Delete a merged branch"), ("-D", "Force delete a branch"), ("-a", "List all branches"), ("-r", "List remote branches")], true),
+            sub("merge", "Join histories together", &[("--no-ff", "Always create a merge commit"), ("--squash", "Squash into one change"), ("--abort", "Abort the merge")], true),
+            sub("rebase", "Reapply commits on another base", &[("-i", "Interactive rebase"), ("--continue", "Continue after resolving"), ("--abort", "Abort the rebase")], true),
+            sub("log", "Show commit logs", &[("--oneline", "One line per commit"), ("--graph", "Draw the commit graph"), ("-n", "Limit number of commits")], true),
+            sub("diff", "Show changes", &[("--staged", "Diff staged changes"), ("--stat", "Show diffstat")], true),
+            sub("reset", "Reset current HEAD", &[("--soft", "Keep index and tree"), ("--mixed", "Keep tree"), ("--hard", "Discard all changes")], true),
+            sub("stash", "Stash away changes", &[("-u", "Include untracked files")], false),
+            sub("tag", "Create, list, or delete tags", &[("-a", "Annotated tag"), ("-d", "Delete a tag"), ("-l", "List tags")], true),
+            sub("clone", "Clone a repository", &[("--depth", "Shallow clone depth"), ("--branch", "Check out this branch")], false),
+            sub("remote", "Manage remotes", &[("-v", "Show URLs")], false),
+        ],
+    },
+    CommandSpec {
+        name: "docker",
+        description: "Container runtime",
+        flags: &[("--version", "Print the docker version"), ("--help", "Show help")],
+        subcommands: &[
+            sub("ps", "List containers", &[("-a", "Show all containers"), ("-q", "Only show IDs")], false),
+            sub("run", "Run a command in a new container", &[("-it", "Interactive with a TTY"), ("-d", "Run detached"), ("--rm", "Remove when it exits"), ("-p", "Publish a port"), ("-v", "Bind mount a volume"), ("-e", "Set an environment variable"), ("--name", "Container name")], false),
+            sub("build", "Build an image", &[("-t", "Name and tag"), ("-f", "Dockerfile path"), ("--no-cache", "Do not use cache")], false),
+            sub("exec", "Run a command in a running container", &[("-it", "Interactive with a TTY")], false),
+            sub("images", "List images", &[("-a", "Show all images")], false),
+            sub("pull", "Download an image", &[], false),
+            sub("push", "Upload an image", &[], false),
+            sub("logs", "Fetch container logs", &[("-f", "Follow output"), ("--tail", "Lines from the end")], false),
+            sub("stop", "Stop containers", &[], false),
+            sub("rm", "Remove containers", &[("-f", "Force removal")], false),
+            sub("rmi", "Remove images", &[("-f", "Force removal")], false),
+            sub("compose", "Manage multi-container apps", &[("-f", "Compose file"), ("-d", "Detached mode")], false),
+        ],
+    },
+    CommandSpec {
+        name: "cargo",
+        description: "Rust package manager",
+        flags: &[("--version", "Print the cargo version"), ("--help", "Show help")],
+        subcommands: &[
+            sub("build", "Compile the package", &[("--release", "Optimized build"), ("-p", "Package to build"), ("--workspace", "Build all members"), ("--all-features", "Enable all features")], false),
+            sub("run", "Run a binary", &[("--release", "Optimized build"), ("--bin", "Binary to run"), ("--example", "Example to run")], false),
+            sub("test", "Run tests", &[("--release", "Optimized build"), ("-p", "Package to test"), ("--workspace", "Test all members")], false),
+            sub("check", "Check for errors without building", &[("--all-targets", "Check tests, benches and examples"), ("--workspace", "Check all members")], false),
+            sub("clippy", "Run lints", &[("--all-targets", "Lint all targets"), ("--workspace", "Lint all members"), ("--fix", "Apply suggestions")], false),
+            sub("fmt", "Format the code", &[("--all", "Format all packages"), ("--check", "Only check formatting")], false),
+            sub("doc", "Build documentation", &[("--open", "Open in a browser"), ("--no-deps", "Skip dependencies")], false),
+            sub("new", "Create a new package", &[("--lib", "Library package"), ("--bin", "Binary package")], false),
+            sub("add", "Add a dependency", &[("--dev", "Dev dependency"), ("-F", "Features to enable")], false),
+            sub("update", "Update dependencies", &[("-p", "Package to update")], false),
+            sub("clean", "Remove build artifacts", &[], false),
+            sub("publish", "Upload to the registry", &[("--dry-run", "Do everything but upload")], false),
+        ],
+    },
+];
+
+/// Subcommand names and descriptions of a built-in spec (`git`, `cargo`, ...).
+pub fn subcommands(command: &str) -> Vec<(&'static str, &'static str)> {
+    SPECS
+        .iter()
+        .find(|spec| spec.name == command)
+        .map(|spec| spec.subcommands.iter().map(|sub| (sub.name, sub.description)).collect())
+        .unwrap_or_default()
+}
+
+const MAX_PATH_COMPLETIONS: usize = 200;
+
+// User-provided specs for custom CLIs, in a fig-like JSON shape:
+// {"name": "tool", "subcommands": [...], "options": [{"name": ["-e", "--env"], "args": {"generator": "enum", "values": [...]}}], "args": [{"generator": "file"}]}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionSpec {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub subcommands: Vec<CompletionSpec>,
+    #[serde(default)]
+    pub options: Vec<SpecOption>,
+    #[serde(default)]
+    pub args: Vec<SpecArg>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecOption {
+    pub name: OneOrMany,
+    pub description: Option<String>,
+    pub args: Option<SpecArg>, // value the option takes, if any
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn names(&self) -> Vec<&str> {
+        match self {
+            OneOrMany::One(name) => vec![name.as_str()],
+            OneOrMany::Many(names) => names.iter().map(|n| n.as_str()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecArg {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub generator: Option<ArgGenerator>,
+    pub values: Option<Vec<String>>, // for the enum generator
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArgGenerator {
+    File,
+    Directory,
+    Enum,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecLoadError {
+    pub path: String,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecLoadReport {
+    pub loaded: Vec<String>,
+    pub failed: Vec<SpecLoadError>,
+}
+
+fn completion_specs_dir() -> PathBuf {
+    expand_home("~").join(".warp-terminal").join("completions")
+}
+
+fn user_specs() -> &'static RwLock<HashMap<String, CompletionSpec>> {
+    static SPECS_REGISTRY: OnceLock<RwLock<HashMap<String, CompletionSpec>>> = OnceLock::new();
+    SPECS_REGISTRY.get_or_init(|| {
+        let registry = RwLock::new(HashMap::new());
+        let report = load_specs_into(&registry, &completion_specs_dir());
+        for failure in report.failed {
+            log::warn!("Invalid completion spec {}: {}", failure.path, failure.errors.join("; "));
+        }
+        registry
+    })
+}
+
+/// Parses and validates one spec file and makes it available to tab completion.
+pub fn load_completion_spec(path: &str) -> Result<String, SpecLoadError> {
+    let spec = parse_spec_file(Path::new(path))?;
+    let name = spec.name.clone();
+    user_specs().write().unwrap().insert(name.clone(), spec);
+    Ok(name)
+}
+
+/// Re-reads every `.json` spec from the completions directory.
+pub fn reload_completion_specs() -> SpecLoadReport {
+    let registry = user_specs();
+    registry.write().unwrap().clear();
+    load_specs_into(registry, &completion_specs_dir())
+}
+
+pub fn list_completion_specs() -> Vec<CompletionSpec> {
+    let mut specs: Vec<CompletionSpec> = user_specs().read().unwrap().values().cloned().collect();
+    specs.sort_by(|a, b| a.name.cmp(&b.name));
+    specs
+}
+
+fn load_specs_into(registry: &RwLock<HashMap<String, CompletionSpec>>, dir: &Path) -> SpecLoadReport {
+    let mut report = SpecLoadReport { loaded: Vec::new(), failed: Vec::new() };
+    let mut files: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().and_then(|x| x.to_str()) == Some("json"))
+            .collect(),
+        Err(_) => return report,
+    };
+    files.sort();
+
+    for file in files {
+        match parse_spec_file(&file) {
+            Ok(spec) => {
+                report.loaded.push(spec.name.clone());
+                registry.write().unwrap().insert(spec.name.clone(), spec);
+            }
+            Err(e) => report.failed.push(e),
+        }
+    }
+    report
+}
+
+fn parse_spec_file(path: &Path) -> Result<CompletionSpec, SpecLoadError> {
+    let fail = |errors: Vec<String>| SpecLoadError { path: path.to_string_lossy().to_string(), errors };
+    let data = fs::read_to_string(path).map_err(|e| fail(vec![e.to_string()]))?;
+    let spec: CompletionSpec = serde_json::from_str(&data).map_err(|e| fail(vec![e.to_string()]))?;
+
+    let mut errors = Vec::new();
+    validate_spec(&spec, &spec.name, &mut errors);
+    if errors.is_empty() { Ok(spec) } else { Err(fail(errors)) }
+}
+
+fn validate_spec(spec: &CompletionSpec, at: &str, errors: &mut Vec<String>) {
+    if spec.name.is_empty() || spec.name.contains(char::is_whitespace) {
+        errors.push(format!("{}: name must be a single non-empty word", at));
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    for sub in &spec.subcommands {
+        if !seen.insert(sub.name.as_str()) {
+            errors.push(format!("{}: duplicate subcommand '{}'", at, sub.name));
+        }
+        validate_spec(sub, &format!("{} {}", at, sub.name), errors);
+    }
+
+    for option in &spec.options {
+        let names = option.name.names();
+        if names.is_empty() {
+            errors.push(format!("{}: option without a name", at));
+        }
+        for name in &names {
+            if !name.starts_with('-') {
+                errors.push(format!("{}: option '{}' must start with '-'", at, name));
+            }
+        }
+        if let Some(arg) = &option.args {
+            validate_arg(arg, &format!("{} {}", at, names.first().unwrap_or(&"?")), errors);
+        }
+    }
+
+    for (i, arg) in spec.args.iter().enumerate() {
+        validate_arg(arg, &format!("{} args[{}]", at, i), errors);
+    }
+}
+
+fn validate_arg(arg: &SpecArg, at: &str, errors: &mut Vec<String>) {
+    let has_values = arg.values.as_ref().map(|v| !v.is_empty()).unwrap_or(false);
+    if arg.generator == Some(ArgGenerator::Enum) && !has_values {
+        errors.push(format!("{}: enum generator needs a non-empty 'values' list", at));
+    }
+    if has_values && arg.generator.is_some() && arg.generator != Some(ArgGenerator::Enum) {
+        errors.push(format!("{}: 'values' is only valid with the enum generator", at));
+    }
+}
+
+/// Completions from a user spec for `words[0]`, or `None` when no spec covers it.
+pub fn complete_from_user_spec(words: &[&str], partial: &str, working_dir: &str) -> Option<Vec<CompletionItem>> {
+    let specs = user_specs().read().unwrap();
+    let mut node = specs.get(*words.first()?)?;
+    let mut positional = 0;
+    let mut pending_arg: Option<&SpecArg> = None;
+
+    for word in &words[1..] {
+        if pending_arg.take().is_some() {
+            continue; // this word was the previous option's value
+        }
+        if word.starts_with('-') {
+            pending_arg = node
+                .options
+                .iter()
+                .find(|o| o.name.names().contains(word))
+                .and_then(|o| o.args.as_ref());
+        } else if let Some(sub) = node.subcommands.iter().find(|s| s.name == *word) {
+            node = sub;
+            positional = 0;
+        } else {
+            positional += 1;
+        }
+    }
+
+    if let Some(arg) = pending_arg {
+        return Some(complete_spec_arg(arg, partial, working_dir));
+    }
+
+    let mut items = Vec::new();
+    if partial.starts_with('-') {
+        for option in &node.options {
+            for name in option.name.names().into_iter().filter(|n| n.starts_with(partial)) {
+                items.push(CompletionItem {
+                    kind: CompletionKind::Flag,
+                    value: name.to_string(),
+                    label: name.to_string(),
+                    description: option.description.clone(),
+                });
+            }
+        }
+        return Some(items);
+    }
+
+    items.extend(node.subcommands.iter().filter(|s| s.name.starts_with(partial)).map(|s| CompletionItem {
+        kind: CompletionKind::Subcommand,
+        value: s.name.clone(),
+        label: s.name.clone(),
+        description: s.description.clone(),
+    }));
+    // Extra positionals reuse the last declared argument (e.g. a list of files)
+    if let Some(arg) = node.args.get(positional).or_else(|| node.args.last()) {
+        items.extend(complete_spec_arg(arg, partial, working_dir));
+    }
+    Some(items)
+}
+
+fn complete_spec_arg(arg: &SpecArg, partial: &str, working_dir: &str) -> Vec<CompletionItem> {
+    match arg.generator {
+        Some(ArgGenerator::File) => complete_path(partial, working_dir),
+        Some(ArgGenerator::Directory) => complete_path(partial, working_dir)
+            .into_iter()
+            .filter(|i| i.kind == CompletionKind::Directory)
+            .collect(),
+        Some(ArgGenerator::Enum) | None => arg
+            .values
+            .iter()
+            .flatten()
+            .filter(|v| v.starts_with(partial))
+            .map(|v| CompletionItem {
+                kind: CompletionKind::Argument,
+                value: v.clone(),
+                label: v.clone(),
+                description: arg.description.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// Top-level command names from user specs and the built-in table that start with `partial`.
+pub fn complete_command_name(partial: &str) -> Vec<CompletionItem> {
+    let mut items: Vec<CompletionItem> = user_specs()
+        .read()
+        .unwrap()
+        .values()
+        .filter(|spec| spec.name.starts_with(partial))
+        .map(|spec| CompletionItem {
+            kind: CompletionKind::Command,
+            value: spec.name.clone(),
+            label: spec.name.clone(),
+            description: spec.description.clone(),
+        })
+        .collect();
+    for spec in SPECS.iter().filter(|spec| spec.name.starts_with(partial)) {
+        if !items.iter().any(|i| i.value == spec.name) {
+            items.push(CompletionItem {
+                kind: CompletionKind::Command,
+                value: spec.name.to_string(),
+                label: spec.name.to_string(),
+                description: Some(spec.description.to_string()),
+            });
+        }
+    }
+    items
+}
+
+/// Completes the word being typed after a command. `words` are the complete
+/// words before it (the command first); `partial` may be empty.
+pub fn complete_arguments(words: &[&str], partial: &str, working_dir: &str) -> Vec<CompletionItem> {
+    // User specs take precedence over the built-in table
+    if let Some(items) = complete_from_user_spec(words, partial, working_dir) {
+        return items;
+    }
+
+    let spec = words.first().and_then(|cmd| SPECS.iter().find(|s| s.name == *cmd));
+    let spec = match spec {
+        Some(spec) => spec,
+        None => return complete_path(partial, working_dir),
+    };
+
+    let subcommand = words[1..]
+        .iter()
+        .find(|w| !w.starts_with('-'))
+        .and_then(|w| spec.subcommands.iter().find(|s| s.name == *w));
+
+    let mut items = Vec::new();
+    match subcommand {
+        None => {
+            if partial.starts_with('-') {
+                items.extend(flag_items(spec.flags, partial));
+            } else {
+                items.extend(spec.subcommands.iter().filter(|s| s.name.starts_with(partial)).map(|s| CompletionItem {
+                    kind: CompletionKind::Subcommand,
+                    value: s.name.to_string(),
+                    label: s.name.to_string(),
+                    description: Some(s.description.to_string()),
+                }));
+            }
+        }
+        Some(sub) => {
+            if partial.starts_with('-') {
+                items.extend(flag_items(sub.flags, partial));
+            } else {
+                if sub.takes_ref && spec.name == "git" {
+                    items.extend(git_refs(working_dir).into_iter().filter(|r| r.starts_with(partial)).map(|r| CompletionItem {
+                        kind: CompletionKind::GitRef,
+                        value: r.clone(),
+                        label: r,
+                        description: None,
+                    }));
+                }
+                items.extend(complete_path(partial, working_dir));
+            }
+        }
+    }
+    items
+}
+
+fn flag_items(flags: &[(&str, &str)], partial: &str) -> Vec<CompletionItem> {
+    flags
+        .iter()
+        .filter(|(flag, _)| flag.starts_with(partial))
+        .map(|(flag, description)| CompletionItem {
+            kind: CompletionKind::Flag,
+            value: flag.to_string(),
+            label: flag.to_string(),
+            description: Some(description.to_string()),
+        })
+        .collect()
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix('~') {
+        let home = if cfg!(windows) {
+            std::env::var("USERPROFILE").unwrap_or_else(|_| ".".into())
+        } else {
+            std::env::var("HOME").unwrap_or_else(|_| ".".into())
+        };
+        return PathBuf::from(home).join(rest.trim_start_matches(['/', '\\']));
+    }
+    PathBuf::from(path)
+}
+
+/// Files and directories matching `partial`, resolved against `working_dir`.
+/// Hidden entries are only offered when the partial name starts with a dot.
+pub fn complete_path(partial: &str, working_dir: &str) -> Vec<CompletionItem> {
+    let (dir_part, name_part) = match partial.rfind(['/', '\\']) {
+        Some(i) => (&partial[..=i], &partial[i + 1..]),
+        None => ("", partial),
+    };
+
+    let base = expand_home(working_dir);
+    let dir = if dir_part.is_empty() {
+        base
+    } else {
+        let d = expand_home(dir_part);
+        if d.is_absolute() { d } else { base.join(d) }
+    };
+
+    let mut items: Vec<CompletionItem> = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .flatten()
+            .filter_map(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                if !name.starts_with(name_part) || (name.starts_with('.') && !name_part.starts_with('.')) {
+                    return None;
+                }
+                let is_dir = e.path().is_dir();
+                let label = if is_dir { format!("{}/", name) } else { name };
+                Some(CompletionItem {
+                    kind: if is_dir { CompletionKind::Directory } else { CompletionKind::File },
+                    value: format!("{}{}", dir_part, label),
+                    label,
+                    description: None,
+                })
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    items.sort_by(|a, b| a.label.cmp(&b.label));
+    items.truncate(MAX_PATH_COMPLETIONS);
+    items
+}
+
+pub fn find_git_dir(start: &Path) -> Option<PathBuf> {
+    start.ancestors().map(|p| p.join(".git")).find(|p| p.is_dir())
+}
+
+/// Local branches, remote branches and tags, read straight from the .git directory.
+fn git_refs(working_dir: &str) -> Vec<String> {
+    let git_dir = match find_git_dir(&expand_home(working_dir)) {
+        Some(dir) => dir,
+        None => return Vec::new(),
+    };
+
+    let mut refs = Vec::new();
+    for (prefix, strip) in [("refs/heads", "refs/heads/"), ("refs/remotes", "refs/remotes/"), ("refs/tags", "refs/tags/")] {
+        collect_loose_refs(&git_dir, &git_dir.join(prefix), strip, &mut refs);
+    }
+    if let Ok(packed) = fs::read_to_string(git_dir.join("packed-refs")) {
+        for line in packed.lines().filter(|l| !l.starts_with('#') && !l.starts_with('^')) {
+            if let Some(name) = line.split_whitespace().nth(1) {
+                for strip in ["refs/heads/", "refs/remotes/", "refs/tags/"] {
+                    if let Some(short) = name.strip_prefix(strip) {
+                        refs.push(short.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    refs.retain(|r| !r.ends_with("/HEAD"));
+    refs.sort();
+    refs.dedup();
+    refs
+}
+
+fn collect_loose_refs(git_dir: &Path, dir: &Path, strip: &str, out: &mut Vec<String>) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for e in entries.flatten() {
+            let path = e.path();
+            if path.is_dir() {
+                collect_loose_refs(git_dir, &path, strip, out);
+            } else if let Ok(rel) = path.strip_prefix(git_dir) {
+                let name = rel.to_string_lossy().replace('\\', "/");
+                if let Some(short) = name.strip_prefix(strip) {
+                    out.push(short.to_string());
+                }
+            }
+        }
+    }
+}
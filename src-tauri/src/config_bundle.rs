@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::Arc;
+use tauri::State;
+use tokio::sync::Mutex;
+
+use crate::execution_context::{DirectoryBookmark, ExecutionContextState};
+use crate::network_manager::{NetworkManager, SshConnection};
+use crate::settings::{self, Settings};
+use crate::theme_manager::{Theme, ThemeManager};
+use crate::workflows::{self, Workflow};
+
+const CONFIG_BUNDLE_FORMAT: &str = "warp-terminal-config";
+pub const CONFIG_BUNDLE_VERSION: u32 = 1;
+
+/// Everything needed to carry a setup to another machine, in one JSON file.
+/// Sections are kept as raw JSON on import so a bad section fails on its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigBundle {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    pub includes_secrets: bool, // SSH passwords are stripped unless the export opted in
+    #[serde(default)]
+    pub settings: serde_json::Value, // includes keybindings
+    #[serde(default)]
+    pub themes: serde_json::Value,
+    #[serde(default)]
+    pub ssh_connections: serde_json::Value,
+    #[serde(default)]
+    pub workflows: serde_json::Value,
+    #[serde(default)]
+    pub bookmarks: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectionImportResult {
+    pub section: String,
+    pub imported: usize,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigImportReport {
+    pub version: u32,
+    pub merged: bool,
+    pub sections: Vec<SectionImportResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigExportSummary {
+    pub path: String,
+    pub includes_secrets: bool,
+    pub themes: usize,
+    pub ssh_connections: usize,
+    pub workflows: usize,
+    pub bookmarks: usize,
+}
+
+fn to_section<T: Serialize>(value: &T) -> Result<serde_json::Value, String> {
+    serde_json::to_value(value).map_err(|e| e.to_string())
+}
+
+pub fn export_bundle(
+    path: &str,
+    include_secrets: bool,
+    theme_manager: &ThemeManager,
+    network_manager: &NetworkManager,
+    context_state: &ExecutionContextState,
+) -> Result<ConfigExportSummary, String> {
+    let themes = theme_manager.get_custom_themes();
+    let mut ssh_connections = network_manager.get_ssh_connections();
+    if !include_secrets {
+        for connection in &mut ssh_connections {
+            connection.password = None;
+        }
+    }
+    let workflows = workflows::load_user_workflows()?;
+
+    let bundle = ConfigBundle {
+        format: CONFIG_BUNDLE_FORMAT.to_string(),
+        version: CONFIG_BUNDLE_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        includes_secrets: include_secrets,
+        settings: to_section(&settings::load_settings()?)?,
+        themes: to_section(&themes)?,
+        ssh_connections: to_section(&ssh_connections)?,
+        workflows: to_section(&workflows)?,
+        bookmarks: to_section(&context_state.bookmarks)?,
+    };
+    let json = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+    Ok(ConfigExportSummary {
+        path: path.to_string(),
+        includes_secrets: include_secrets,
+        themes: themes.len(),
+        ssh_connections: ssh_connections.len(),
+        workflows: workflows.len(),
+        bookmarks: context_state.bookmarks.len(),
+    })
+}
+
+fn read_bundle(path: &str) -> Result<ConfigBundle, String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let bundle: ConfigBundle = serde_json::from_str(&data).map_err(|e| format!("Not a configuration bundle: {}", e))?;
+    if bundle.format != CONFIG_BUNDLE_FORMAT {
+        return Err(format!("Not a configuration bundle (format \"{}\")", bundle.format));
+    }
+    if bundle.version == 0 || bundle.version > CONFIG_BUNDLE_VERSION {
+        return Err(format!(
+            "Unsupported bundle version {} (this build reads up to {})",
+            bundle.version, CONFIG_BUNDLE_VERSION
+        ));
+    }
+    Ok(bundle)
+}
+
+/// Run one section's restore, turning a missing section into an empty result.
+fn import_section<T, F>(name: &str, value: serde_json::Value, restore: F) -> SectionImportResult
+where
+    T: serde::de::DeserializeOwned,
+    F: FnOnce(T) -> Result<usize, Vec<String>>,
+{
+    let mut result = SectionImportResult { section: name.to_string(), imported: 0, errors: Vec::new() };
+    if value.is_null() {
+        return result;
+    }
+    match serde_json::from_value::<T>(value) {
+        Ok(items) => match restore(items) {
+            Ok(imported) => result.imported = imported,
+            Err(errors) => result.errors = errors,
+        },
+        Err(e) => result.errors.push(e.to_string()),
+    }
+    result
+}
+
+/// Restore a bundle. With `merge`, entries are added or updated by id (or name,
+/// for bookmarks) and settings keys in the bundle overlay the current ones;
+/// otherwise each present section replaces what's there.
+pub fn import_bundle(
+    path: &str,
+    merge: bool,
+    theme_manager: &ThemeManager,
+    network_manager: &NetworkManager,
+    context_state: &mut ExecutionContextState,
+) -> Result<ConfigImportReport, String> {
+    let bundle = read_bundle(path)?;
+    let mut sections = Vec::new();
+
+    sections.push(import_section("settings", bundle.settings, |incoming: serde_json::Map<String, serde_json::Value>| {
+        let mut object = if merge {
+            match serde_json::to_value(settings::load_settings().unwrap_or_default()) {
+                Ok(serde_json::Value::Object(current)) => current,
+                _ => serde_json::Map::new(),
+            }
+        } else {
+            serde_json::Map::new()
+        };
+        object.extend(incoming);
+        object.remove("version"); // saving stamps the current version
+        let settings = Settings::from_json(serde_json::Value::Object(object))
+            .map_err(|errors| errors.into_iter().map(|e| format!("{}: {}", e.field, e.message)).collect::<Vec<_>>())?;
+        settings::save_settings(&settings).map_err(|e| vec![e])?;
+        Ok(1)
+    }));
+
+    sections.push(import_section("themes", bundle.themes, |themes: Vec<Theme>| {
+        Ok(theme_manager.restore_custom_themes(themes, merge))
+    }));
+
+    sections.push(import_section("ssh_connections", bundle.ssh_connections, |connections: Vec<SshConnection>| {
+        Ok(network_manager.restore_ssh_connections(connections, merge))
+    }));
+
+    sections.push(import_section("workflows", bundle.workflows, |items: Vec<Workflow>| {
+        workflows::restore_user_workflows(items, merge).map_err(|e| vec![e])
+    }));
+
+    sections.push(import_section("bookmarks", bundle.bookmarks, |bookmarks: Vec<DirectoryBookmark>| {
+        context_state.restore_bookmarks(bookmarks, merge).map_err(|e| vec![e])
+    }));
+
+    Ok(ConfigImportReport { version: bundle.version, merged: merge, sections })
+}
+
+#[tauri::command]
+pub async fn export_config_bundle(
+    path: String,
+    include_secrets: Option<bool>,
+    theme_manager: State<'_, Arc<Mutex<ThemeManager>>>,
+    network_manager: State<'_, Arc<Mutex<NetworkManager>>>,
+    context_manager: State<'_, Arc<Mutex<ExecutionContextState>>>,
+) -> Result<ConfigExportSummary, String> {
+    let theme_manager = theme_manager.lock().await;
+    let network_manager = network_manager.lock().await;
+    let context_state = context_manager.lock().await;
+    export_bundle(&path, include_secrets.unwrap_or(false), &theme_manager, &network_manager, &context_state)
+}
+
+#[tauri::command]
+pub async fn import_config_bundle(
+    path: String,
+    merge: bool,
+    theme_manager: State<'_, Arc<Mutex<ThemeManager>>>,
+    network_manager: State<'_, Arc<Mutex<NetworkManager>>>,
+    context_manager: State<'_, Arc<Mutex<ExecutionContextState>>>,
+) -> Result<ConfigImportReport, String> {
+    let theme_manager = theme_manager.lock().await;
+    let network_manager = network_manager.lock().await;
+    let mut context_state = context_manager.lock().await;
+    import_bundle(&path, merge, &theme_manager, &network_manager, &mut context_state)
+}
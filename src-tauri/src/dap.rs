@@ -0,0 +1,214 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::dev_tools::DebugAdapter;
+
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const CONNECT_ATTEMPTS: u32 = 50;
+
+/// An event pushed by the adapter (`initialized`, `stopped`, `terminated`, ...).
+#[derive(Debug, Clone)]
+pub struct DapEvent {
+    pub event: String,
+    pub body: Value,
+}
+
+type Writer = Arc<tokio::sync::Mutex<Box<dyn AsyncWrite + Send + Unpin>>>;
+type PendingRequests = Arc<Mutex<HashMap<i64, oneshot::Sender<Value>>>>;
+
+/// Client side of the Debug Adapter Protocol: Content-Length framed JSON over the
+/// adapter's stdio, or over TCP when the adapter config names a port.
+pub struct DapClient {
+    writer: Writer,
+    seq: Arc<AtomicI64>,
+    pending: PendingRequests,
+    child: Mutex<Option<Child>>,
+}
+
+impl DapClient {
+    /// Launch (and/or connect to) the adapter. Events arrive on the returned
+    /// receiver, which closes when the adapter goes away.
+    pub async fn connect(adapter: &DebugAdapter, cwd: Option<&str>) -> Result<(Self, mpsc::UnboundedReceiver<DapEvent>), String> {
+        let mut child = None;
+        if let Some(program) = adapter.command.first() {
+            let mut cmd = Command::new(program);
+            cmd.args(&adapter.command[1..]).envs(&adapter.env).stderr(Stdio::null()).kill_on_drop(true);
+            if let Some(dir) = cwd {
+                cmd.current_dir(dir);
+            }
+            if adapter.port.is_none() {
+                cmd.stdin(Stdio::piped()).stdout(Stdio::piped());
+            }
+            child = Some(cmd.spawn().map_err(|e| format!("Failed to start debug adapter: {}", e))?);
+        }
+
+        let (reader, writer): (Box<dyn AsyncRead + Send + Unpin>, Box<dyn AsyncWrite + Send + Unpin>) = match adapter.port {
+            Some(port) => {
+                let host = adapter.host.clone().unwrap_or_else(|| "127.0.0.1".to_string());
+                let (read_half, write_half) = connect_with_retry(&host, port).await?.into_split();
+                (Box::new(read_half), Box::new(write_half))
+            }
+            None => {
+                let child = child.as_mut().ok_or("Debug adapter has no command to launch")?;
+                let stdout = child.stdout.take().ok_or("Debug adapter stdout unavailable")?;
+                let stdin = child.stdin.take().ok_or("Debug adapter stdin unavailable")?;
+                (Box::new(stdout), Box::new(stdin))
+            }
+        };
+
+        let writer: Writer = Arc::new(tokio::sync::Mutex::new(writer));
+        let seq = Arc::new(AtomicI64::new(1));
+        let pending: PendingRequests = Arc::default();
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        tokio::spawn(read_loop(BufReader::new(reader), pending.clone(), event_tx, writer.clone(), seq.clone()));
+
+        Ok((Self { writer, seq, pending, child: Mutex::new(child) }, event_rx))
+    }
+
+    /// Send a request without waiting, for requests whose response can arrive
+    /// late (adapters commonly answer `launch` only after `configurationDone`).
+    pub async fn send_request(&self, command: &str, arguments: Value) -> Result<oneshot::Receiver<Value>, String> {
+        let seq = self.seq.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(seq, tx);
+        let message = json!({ "seq": seq, "type": "request", "command": command, "arguments": arguments });
+        if let Err(e) = write_message(&self.writer, &message).await {
+            self.pending.lock().unwrap().remove(&seq);
+            return Err(e);
+        }
+        Ok(rx)
+    }
+
+    pub async fn wait_response(command: &str, rx: oneshot::Receiver<Value>) -> Result<Value, String> {
+        let response = tokio::time::timeout(REQUEST_TIMEOUT, rx)
+            .await
+            .map_err(|_| format!("Debug adapter did not answer {}", command))?
+            .map_err(|_| "Debug adapter exited".to_string())?;
+        response_body(command, response)
+    }
+
+    /// Send a request and return the body of its successful response.
+    pub async fn request(&self, command: &str, arguments: Value) -> Result<Value, String> {
+        let rx = self.send_request(command, arguments).await?;
+        Self::wait_response(command, rx).await
+    }
+
+    /// Ask the adapter to end the debuggee, then make sure the adapter is gone.
+    pub async fn shutdown(&self) {
+        let _ = tokio::time::timeout(Duration::from_secs(2), self.request("disconnect", json!({ "terminateDebuggee": true }))).await;
+        let child = self.child.lock().unwrap().take();
+        if let Some(mut child) = child {
+            let _ = child.kill().await;
+        }
+    }
+}
+
+pub fn response_body(command: &str, response: Value) -> Result<Value, String> {
+    if response["success"].as_bool() == Some(true) {
+        Ok(response["body"].clone())
+    } else {
+        Err(format!("{} failed: {}", command, response["message"].as_str().unwrap_or("unknown error")))
+    }
+}
+
+// Adapters started with a port need a moment before they accept connections
+async fn connect_with_retry(host: &str, port: u16) -> Result<TcpStream, String> {
+    let mut last_error = String::new();
+    for _ in 0..CONNECT_ATTEMPTS {
+        match TcpStream::connect((host, port)).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = e.to_string(),
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    Err(format!("Failed to connect to debug adapter at {}:{}: {}", host, port, last_error))
+}
+
+async fn write_message(writer: &Writer, message: &Value) -> Result<(), String> {
+    let body = serde_json::to_string(message).map_err(|e| e.to_string())?;
+    let mut writer = writer.lock().await;
+    writer
+        .write_all(format!("Content-Length: {}\r\n\r\n{}", body.len(), body).as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write to debug adapter: {}", e))?;
+    writer.flush().await.map_err(|e| format!("Failed to write to debug adapter: {}", e))
+}
+
+async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<Value>, String> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await.map_err(|e| e.to_string())? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let mut body = vec![0; length.unwrap_or_default()];
+    reader.read_exact(&mut body).await.map_err(|e| e.to_string())?;
+    serde_json::from_slice(&body).map(Some).map_err(|e| format!("Invalid DAP message: {}", e))
+}
+
+async fn read_loop<R: AsyncBufRead + Unpin>(
+    mut reader: R,
+    pending: PendingRequests,
+    events: mpsc::UnboundedSender<DapEvent>,
+    writer: Writer,
+    seq: Arc<AtomicI64>,
+) {
+    loop {
+        let message = match read_message(&mut reader).await {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                log::warn!("Debug adapter connection failed: {}", e);
+                break;
+            }
+        };
+        match message["type"].as_str() {
+            Some("response") => {
+                let sender = message["request_seq"].as_i64().and_then(|seq| pending.lock().unwrap().remove(&seq));
+                if let Some(sender) = sender {
+                    let _ = sender.send(message);
+                }
+            }
+            Some("event") => {
+                let event = message["event"].as_str().unwrap_or_default().to_string();
+                let _ = events.send(DapEvent { event, body: message["body"].clone() });
+            }
+            Some("request") => {
+                // Reverse requests (runInTerminal, startDebugging) aren't supported
+                let response = json!({
+                    "seq": seq.fetch_add(1, Ordering::SeqCst),
+                    "type": "response",
+                    "request_seq": message["seq"],
+                    "command": message["command"],
+                    "success": false,
+                    "message": "Not supported",
+                });
+                let _ = write_message(&writer, &response).await;
+            }
+            _ => {}
+        }
+    }
+    // Dropping the senders fails any request still waiting on a response
+    pending.lock().unwrap().clear();
+}
@@ -7,6 +7,8 @@ use tokio::process::Command;
 use tokio::sync::mpsc;
 use tokio::fs;
 
+use crate::dap::{DapClient, DapEvent};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GitRepository {
     pub path: PathBuf,
@@ -64,6 +66,43 @@ pub struct GitCommit {
     pub deletions: usize,
 }
 
+/// One `@@` hunk of a unified diff. `lines` keeps the ' ', '+', '-' and '\\'
+/// prefixes so the hunk can be turned back into a patch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffHunk {
+    pub index: usize,
+    pub header: String,
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDiff {
+    pub path: String,
+    pub old_path: Option<String>, // set for renames and deletions
+    pub header: Vec<String>,      // "diff --git" through "+++"
+    pub binary: bool,
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// One line of `git blame`. Lines changed in the working tree have `committed`
+/// false and the all-zero hash git reports for them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlameLine {
+    pub line_number: usize,
+    pub commit_hash: String,
+    pub short_hash: String,
+    pub author: String,
+    pub email: String,
+    pub timestamp: u64,
+    pub summary: String,
+    pub content: String,
+    pub committed: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GitBranch {
     pub name: String,
@@ -329,10 +368,23 @@ pub enum DevToolsEventType {
     TestsCompleted,
 }
 
+// Live adapter connection for a debugger; `thread_id` is the thread last reported stopped
+#[derive(Clone)]
+struct DebugConnection {
+    client: Arc<DapClient>,
+    thread_id: Option<i64>,
+}
+
+// (repo, file) -> (HEAD, lines)
+type BlameCache = HashMap<(PathBuf, String), (String, Vec<BlameLine>)>;
+
+#[derive(Clone)]
 pub struct DevToolsManager {
     git_repositories: Arc<Mutex<HashMap<String, GitRepository>>>,
     language_servers: Arc<Mutex<HashMap<String, LanguageServer>>>,
     debuggers: Arc<Mutex<HashMap<String, Debugger>>>,
+    debug_connections: Arc<Mutex<HashMap<String, DebugConnection>>>,
+    blame_cache: Arc<Mutex<BlameCache>>,
     project_templates: Arc<Mutex<HashMap<String, ProjectTemplate>>>,
     build_configs: Arc<Mutex<HashMap<String, BuildConfiguration>>>,
     test_configs: Arc<Mutex<HashMap<String, TestConfiguration>>>,
@@ -347,6 +399,8 @@ impl DevToolsManager {
             git_repositories: Arc::new(Mutex::new(HashMap::new())),
             language_servers: Arc::new(Mutex::new(HashMap::new())),
             debuggers: Arc::new(Mutex::new(HashMap::new())),
+            debug_connections: Arc::new(Mutex::new(HashMap::new())),
+            blame_cache: Arc::new(Mutex::new(HashMap::new())),
             project_templates: Arc::new(Mutex::new(HashMap::new())),
             build_configs: Arc::new(Mutex::new(HashMap::new())),
             test_configs: Arc::new(Mutex::new(HashMap::new())),
@@ -453,6 +507,13 @@ impl DevToolsManager {
         Ok(repository)
     }
 
+    /// Current branch and working-tree status of `path`, without registering it as a repository.
+    pub async fn git_working_tree(&self, path: &PathBuf) -> Result<(String, GitStatus), String> {
+        let branch = self.get_git_current_branch(path).await?;
+        let status = self.get_git_status(path).await?;
+        Ok((branch, status))
+    }
+
     async fn get_git_current_branch(&self, path: &PathBuf) -> Result<String, String> {
         let output = Command::new("git")
             .args(&["rev-parse", "--abbrev-ref", "HEAD"])
@@ -806,6 +867,258 @@ impl DevToolsManager {
         }
     }
 
+    /// Blame `file` (relative to the repository root) over the inclusive 1-based
+    /// line range. Results for files without working-tree changes are cached
+    /// against HEAD; dirty files are blamed fresh each time.
+    pub async fn git_blame(&self, repo_name: &str, file: &str, start_line: Option<usize>, end_line: Option<usize>) -> Result<Vec<BlameLine>, String> {
+        let repo_path = {
+            let repos = self.git_repositories.lock().unwrap();
+            repos.get(repo_name)
+                .map(|r| r.path.clone())
+                .ok_or_else(|| format!("Repository {} not found", repo_name))?
+        };
+
+        let head = Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .current_dir(&repo_path)
+            .output()
+            .await
+            .map_err(|e| format!("Failed to resolve HEAD: {}", e))?;
+        let head = String::from_utf8_lossy(&head.stdout).trim().to_string();
+
+        let dirty = Command::new("git")
+            .args(["status", "--porcelain=v1", "--", file])
+            .current_dir(&repo_path)
+            .output()
+            .await
+            .map(|output| !output.stdout.is_empty())
+            .unwrap_or(true);
+
+        let cache_key = (repo_path.clone(), file.to_string());
+        let cached = if dirty || head.is_empty() {
+            None
+        } else {
+            let cache = self.blame_cache.lock().unwrap();
+            cache.get(&cache_key).filter(|(commit, _)| *commit == head).map(|(_, lines)| lines.clone())
+        };
+
+        let lines = match cached {
+            Some(lines) => lines,
+            None => {
+                let output = Command::new("git")
+                    .args(["blame", "--porcelain", "--", file])
+                    .current_dir(&repo_path)
+                    .output()
+                    .await
+                    .map_err(|e| format!("Failed to run git blame: {}", e))?;
+
+                if !output.status.success() {
+                    let error = String::from_utf8_lossy(&output.stderr);
+                    return Err(format!("Blame failed: {}", error.trim()));
+                }
+
+                let lines = parse_blame_porcelain(&String::from_utf8_lossy(&output.stdout));
+                if !dirty && !head.is_empty() {
+                    let mut cache = self.blame_cache.lock().unwrap();
+                    cache.insert(cache_key, (head, lines.clone()));
+                }
+                lines
+            }
+        };
+
+        let start = start_line.unwrap_or(1).max(1);
+        let end = end_line.unwrap_or(usize::MAX);
+        if start > end {
+            return Err(format!("Invalid line range {}-{}", start, end));
+        }
+        Ok(lines.into_iter().filter(|line| line.line_number >= start && line.line_number <= end).collect())
+    }
+
+    // Git Diff and Hunk Staging
+    /// Working-tree changes (or the index when `staged`), optionally for one file.
+    pub async fn get_git_diff(&self, repo_name: &str, file: Option<&str>, staged: bool) -> Result<Vec<FileDiff>, String> {
+        let repo_path = self.git_repo_path(repo_name)?;
+        let mut args = vec!["diff", "--no-color", "--no-ext-diff", "-U3"];
+        if staged {
+            args.push("--cached");
+        }
+        args.push("--");
+        if let Some(file) = file {
+            args.push(file);
+        }
+        let output = Self::run_git(&repo_path, &args).await?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Failed to get diff: {}", error.trim()));
+        }
+
+        Ok(parse_unified_diff(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    // Build a one-hunk patch from the current diff and apply it to the index.
+    // `expected_header` guards against the diff having moved on since the UI read it.
+    async fn apply_hunk(&self, repo_name: &str, file: &str, hunk: usize, expected_header: Option<&str>, unstage: bool) -> Result<String, String> {
+        let repo_path = self.git_repo_path(repo_name)?;
+        let diffs = self.get_git_diff(repo_name, Some(file), unstage).await?;
+        let diff = diffs.into_iter().find(|d| d.path == file || d.old_path.as_deref() == Some(file))
+            .ok_or_else(|| format!("{} has no {} changes", file, if unstage { "staged" } else { "unstaged" }))?;
+        if diff.binary {
+            return Err(format!("{} is binary; stage the whole file instead", file));
+        }
+        let selected = diff.hunks.get(hunk)
+            .ok_or_else(|| format!("{} has no hunk {} ({} hunks)", file, hunk, diff.hunks.len()))?;
+        if let Some(expected) = expected_header {
+            if selected.header != expected {
+                return Err(format!("The diff for {} has changed; refresh and try again", file));
+            }
+        }
+
+        let mut patch = diff.header.join("\n");
+        patch.push('\n');
+        patch.push_str(&selected.header);
+        patch.push('\n');
+        for line in &selected.lines {
+            patch.push_str(line);
+            patch.push('\n');
+        }
+
+        let mut apply_args = vec!["apply", "--cached", "--whitespace=nowarn"];
+        if unstage {
+            apply_args.push("--reverse");
+        }
+        let mut check_args = apply_args.clone();
+        check_args.push("--check");
+
+        Self::run_git_with_input(&repo_path, &check_args, &patch).await
+            .map_err(|e| format!("Hunk does not apply cleanly to the index: {}", e))?;
+        Self::run_git_with_input(&repo_path, &apply_args, &patch).await
+            .map_err(|e| format!("Failed to {} hunk: {}", if unstage { "unstage" } else { "stage" }, e))?;
+
+        let _ = self.load_git_repository(&repo_path).await;
+        Ok(format!("{} hunk {} of {}", if unstage { "Unstaged" } else { "Staged" }, hunk, file))
+    }
+
+    async fn run_git_with_input(repo_path: &PathBuf, args: &[&str], input: &str) -> Result<(), String> {
+        use tokio::io::AsyncWriteExt;
+
+        let mut child = Command::new("git")
+            .args(args)
+            .current_dir(repo_path)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run git: {}", e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input.as_bytes()).await.map_err(|e| e.to_string())?;
+        }
+        let output = child.wait_with_output().await.map_err(|e| e.to_string())?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+        }
+    }
+
+    /// Stage hunk `hunk` of `file`'s unstaged diff, as numbered by `get_git_diff`.
+    pub async fn stage_hunk(&self, repo_name: &str, file: &str, hunk: usize, expected_header: Option<&str>) -> Result<String, String> {
+        self.apply_hunk(repo_name, file, hunk, expected_header, false).await
+    }
+
+    /// Unstage hunk `hunk` of `file`'s staged diff (`get_git_diff` with `staged`).
+    pub async fn unstage_hunk(&self, repo_name: &str, file: &str, hunk: usize, expected_header: Option<&str>) -> Result<String, String> {
+        self.apply_hunk(repo_name, file, hunk, expected_header, true).await
+    }
+
+    // Git Stash
+    fn git_repo_path(&self, repo_name: &str) -> Result<PathBuf, String> {
+        let repos = self.git_repositories.lock().unwrap();
+        repos.get(repo_name)
+            .map(|r| r.path.clone())
+            .ok_or_else(|| format!("Repository {} not found", repo_name))
+    }
+
+    async fn run_git(repo_path: &PathBuf, args: &[&str]) -> Result<std::process::Output, String> {
+        Command::new("git")
+            .args(args)
+            .current_dir(repo_path)
+            .output()
+            .await
+            .map_err(|e| format!("Failed to run git {}: {}", args.first().unwrap_or(&""), e))
+    }
+
+    pub async fn stash_save(&self, repo_name: &str, message: Option<&str>, include_untracked: bool) -> Result<String, String> {
+        let repo_path = self.git_repo_path(repo_name)?;
+
+        let mut args = vec!["stash", "push"];
+        if include_untracked {
+            args.push("--include-untracked");
+        }
+        if let Some(message) = message.filter(|m| !m.trim().is_empty()) {
+            args.extend(["-m", message]);
+        }
+        let output = Self::run_git(&repo_path, &args).await?;
+
+        if output.status.success() {
+            let _ = self.load_git_repository(&repo_path).await;
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        } else {
+            let error = String::from_utf8_lossy(&output.stderr);
+            Err(format!("Stash failed: {}", error.trim()))
+        }
+    }
+
+    pub async fn list_stashes(&self, repo_name: &str) -> Result<Vec<GitStash>, String> {
+        let repo_path = self.git_repo_path(repo_name)?;
+        let output = Self::run_git(&repo_path, &["stash", "list", "--format=%gd%x00%ct%x00%gs"]).await?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Failed to list stashes: {}", error.trim()));
+        }
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        Ok(output_str.lines().filter_map(parse_stash_line).collect())
+    }
+
+    async fn stash_ref_command(&self, repo_name: &str, action: &str, index: usize) -> Result<String, String> {
+        let repo_path = self.git_repo_path(repo_name)?;
+        let stash_ref = format!("stash@{{{}}}", index);
+        let output = Self::run_git(&repo_path, &["stash", action, &stash_ref]).await?;
+        // Refresh even on failure: a conflicted apply still changes the working tree
+        let _ = self.load_git_repository(&repo_path).await;
+
+        if output.status.success() {
+            return Ok(format!("Stash {} {}", stash_ref, match action { "apply" => "applied", "pop" => "popped", _ => "dropped" }));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if action != "drop" && (stdout.contains("CONFLICT") || stderr.contains("CONFLICT")) {
+            let conflicts = self.get_git_status(&repo_path).await.map(|s| s.conflicted).unwrap_or_default();
+            // A conflicted pop leaves the stash in place, so say so
+            let kept = if action == "pop" { " (the stash was kept)" } else { "" };
+            return Err(format!("{}: {} left conflicts in {}{}", STASH_CONFLICT_ERROR, stash_ref, conflicts.join(", "), kept));
+        }
+        Err(format!("Stash {} failed: {}", action, stderr.trim()))
+    }
+
+    /// Apply a stash, keeping it. Conflicts are reported with an error starting
+    /// with `STASH_CONFLICT_ERROR` so the UI can offer to resolve them.
+    pub async fn stash_apply(&self, repo_name: &str, index: usize) -> Result<String, String> {
+        self.stash_ref_command(repo_name, "apply", index).await
+    }
+
+    pub async fn stash_pop(&self, repo_name: &str, index: usize) -> Result<String, String> {
+        self.stash_ref_command(repo_name, "pop", index).await
+    }
+
+    pub async fn stash_drop(&self, repo_name: &str, index: usize) -> Result<String, String> {
+        self.stash_ref_command(repo_name, "drop", index).await
+    }
+
     // Language Server Protocol (LSP) Integration
     pub fn register_language_server(&self, language_server: LanguageServer) -> Result<String, String> {
         let server_id = language_server.id.clone();
@@ -1226,6 +1539,406 @@ impl DevToolsManager {
         result
     }
 
+    // Debug Adapter Protocol (DAP) Integration
+    pub fn register_debugger(&self, debugger: Debugger) -> Result<String, String> {
+        let debugger_id = debugger.id.clone();
+
+        {
+            let mut debuggers = self.debuggers.lock().unwrap();
+            debuggers.insert(debugger_id.clone(), debugger);
+        }
+
+        Ok(debugger_id)
+    }
+
+    fn update_debugger<T>(&self, debugger_id: &str, update: impl FnOnce(&mut Debugger) -> T) -> Result<T, String> {
+        let mut debuggers = self.debuggers.lock().unwrap();
+        let debugger = debuggers.get_mut(debugger_id)
+            .ok_or_else(|| format!("Debugger {} not found", debugger_id))?;
+        Ok(update(debugger))
+    }
+
+    fn debug_connection(&self, debugger_id: &str) -> Result<DebugConnection, String> {
+        let connections = self.debug_connections.lock().unwrap();
+        connections.get(debugger_id).cloned()
+            .ok_or_else(|| format!("Debugger {} has no active session", debugger_id))
+    }
+
+    /// Launch the debugger's adapter and run `program` under it: `initialize`,
+    /// `launch`, then once the adapter reports `initialized`, the registered
+    /// breakpoints and `configurationDone`. `launch_options` is merged into the
+    /// launch arguments for adapter-specific settings.
+    pub async fn debug_start(
+        &self,
+        debugger_id: &str,
+        program: &str,
+        arguments: Vec<String>,
+        working_directory: Option<String>,
+        environment: HashMap<String, String>,
+        launch_options: Option<serde_json::Value>,
+    ) -> Result<DebugSession, String> {
+        let debugger = {
+            let debuggers = self.debuggers.lock().unwrap();
+            debuggers.get(debugger_id).cloned()
+                .ok_or_else(|| format!("Debugger {} not found", debugger_id))?
+        };
+        if self.debug_connections.lock().unwrap().contains_key(debugger_id) {
+            return Err(format!("Debugger {} already has an active session", debugger_id));
+        }
+        self.update_debugger(debugger_id, |d| d.status = DebuggerStatus::Starting)?;
+
+        let working_directory = working_directory.unwrap_or_else(|| {
+            std::env::current_dir().map(|dir| dir.to_string_lossy().to_string()).unwrap_or_default()
+        });
+        let (client, mut events) = match DapClient::connect(&debugger.adapter, Some(&working_directory)).await {
+            Ok(connection) => connection,
+            Err(e) => {
+                let _ = self.update_debugger(debugger_id, |d| d.status = DebuggerStatus::Error);
+                return Err(e);
+            }
+        };
+        let client = Arc::new(client);
+
+        let mut launch = serde_json::json!({
+            "program": program,
+            "args": arguments,
+            "cwd": working_directory,
+            "env": environment,
+            "stopOnEntry": false,
+        });
+        if let (Some(launch), Some(serde_json::Value::Object(options))) = (launch.as_object_mut(), launch_options) {
+            launch.extend(options);
+        }
+
+        let handshake = async {
+            let capabilities = client.request("initialize", serde_json::json!({
+                "clientID": "warp-terminal",
+                "adapterID": debugger.adapter.adapter_type,
+                "linesStartAt1": true,
+                "columnsStartAt1": true,
+                "pathFormat": "path",
+                "supportsVariableType": true,
+            })).await?;
+
+            // Adapters commonly answer `launch` only after `configurationDone`
+            let mut launch_rx = client.send_request("launch", launch).await?;
+            let mut launched = false;
+            let deadline = tokio::time::sleep(crate::dap::REQUEST_TIMEOUT);
+            tokio::pin!(deadline);
+            loop {
+                tokio::select! {
+                    event = events.recv() => match event {
+                        Some(event) if event.event == "initialized" => break,
+                        Some(_) => {}
+                        None => return Err("Debug adapter exited during startup".to_string()),
+                    },
+                    response = &mut launch_rx, if !launched => {
+                        let response = response.map_err(|_| "Debug adapter exited during startup".to_string())?;
+                        crate::dap::response_body("launch", response)?;
+                        launched = true;
+                    }
+                    _ = &mut deadline => return Err("Debug adapter never reported initialized".to_string()),
+                }
+            }
+
+            let mut files: Vec<String> = debugger.breakpoints.iter().filter(|b| b.enabled).map(|b| b.file_path.clone()).collect();
+            files.sort();
+            files.dedup();
+            for file_path in files {
+                self.sync_breakpoints(debugger_id, &client, &file_path).await?;
+            }
+            if capabilities["supportsConfigurationDoneRequest"].as_bool().unwrap_or(true) {
+                client.request("configurationDone", serde_json::json!({})).await?;
+            }
+            if !launched {
+                DapClient::wait_response("launch", launch_rx).await?;
+            }
+            Ok::<(), String>(())
+        };
+
+        if let Err(e) = handshake.await {
+            client.shutdown().await;
+            let _ = self.update_debugger(debugger_id, |d| d.status = DebuggerStatus::Error);
+            return Err(e);
+        }
+
+        let session = DebugSession {
+            id: uuid::Uuid::new_v4().to_string(),
+            program: program.to_string(),
+            arguments,
+            working_directory,
+            environment,
+            started_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+        };
+        self.update_debugger(debugger_id, |d| {
+            d.status = DebuggerStatus::Running;
+            d.current_session = Some(session.clone());
+            d.call_stack.clear();
+            d.variables.clear();
+        })?;
+        self.debug_connections.lock().unwrap().insert(
+            debugger_id.to_string(),
+            DebugConnection { client: client.clone(), thread_id: None },
+        );
+
+        self.emit_event(DevToolsEvent {
+            event_type: DevToolsEventType::DebugSessionStarted,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            details: [
+                ("debugger_id".to_string(), serde_json::Value::String(debugger_id.to_string())),
+                ("session_id".to_string(), serde_json::Value::String(session.id.clone())),
+            ].into_iter().collect(),
+        });
+
+        let manager = self.clone();
+        let debugger_id = debugger_id.to_string();
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                if manager.handle_debug_event(&debugger_id, &client, event).await {
+                    break;
+                }
+            }
+            client.shutdown().await;
+            manager.end_debug_session(&debugger_id);
+        });
+
+        Ok(session)
+    }
+
+    // Returns true once the session is over
+    async fn handle_debug_event(&self, debugger_id: &str, client: &DapClient, event: DapEvent) -> bool {
+        match event.event.as_str() {
+            "stopped" => {
+                let thread_id = event.body["threadId"].as_i64();
+                if let Some(connection) = self.debug_connections.lock().unwrap().get_mut(debugger_id) {
+                    connection.thread_id = thread_id.or(connection.thread_id);
+                }
+                let (call_stack, variables) = match thread_id {
+                    Some(thread_id) => Self::fetch_stack(client, thread_id).await.unwrap_or_else(|e| {
+                        log::warn!("Failed to read stack for debugger {}: {}", debugger_id, e);
+                        (Vec::new(), HashMap::new())
+                    }),
+                    None => (Vec::new(), HashMap::new()),
+                };
+                let top = call_stack.first().cloned();
+                let _ = self.update_debugger(debugger_id, |d| {
+                    d.status = DebuggerStatus::Paused;
+                    d.call_stack = call_stack;
+                    d.variables = variables;
+                });
+
+                let mut details: HashMap<String, serde_json::Value> = [
+                    ("debugger_id".to_string(), serde_json::Value::String(debugger_id.to_string())),
+                    ("reason".to_string(), event.body["reason"].clone()),
+                    ("thread_id".to_string(), event.body["threadId"].clone()),
+                    ("hit_breakpoint_ids".to_string(), event.body["hitBreakpointIds"].clone()),
+                ].into_iter().collect();
+                if let Some(frame) = top {
+                    details.insert("file_path".to_string(), serde_json::Value::String(frame.file_path));
+                    details.insert("line".to_string(), serde_json::Value::from(frame.line));
+                }
+                self.emit_event(DevToolsEvent {
+                    event_type: DevToolsEventType::BreakpointHit,
+                    timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+                    details,
+                });
+                false
+            }
+            "continued" => {
+                let _ = self.update_debugger(debugger_id, |d| {
+                    d.status = DebuggerStatus::Running;
+                    d.call_stack.clear();
+                    d.variables.clear();
+                });
+                false
+            }
+            "breakpoint" => {
+                let breakpoint = &event.body["breakpoint"];
+                if let (Some(path), Some(line)) = (breakpoint["source"]["path"].as_str(), breakpoint["line"].as_u64()) {
+                    let verified = breakpoint["verified"].as_bool().unwrap_or(false);
+                    let _ = self.update_debugger(debugger_id, |d| {
+                        for b in d.breakpoints.iter_mut().filter(|b| b.file_path == path && b.line as u64 == line) {
+                            b.verified = verified;
+                        }
+                    });
+                }
+                false
+            }
+            "terminated" => true,
+            _ => false,
+        }
+    }
+
+    // Frames of `thread_id` plus the variables visible in the innermost one
+    async fn fetch_stack(client: &DapClient, thread_id: i64) -> Result<(Vec<StackFrame>, HashMap<String, DebugVariable>), String> {
+        let body = client.request("stackTrace", serde_json::json!({ "threadId": thread_id, "startFrame": 0, "levels": 50 })).await?;
+        let call_stack: Vec<StackFrame> = body["stackFrames"].as_array().cloned().unwrap_or_default().iter().map(|frame| StackFrame {
+            id: frame["id"].as_u64().unwrap_or_default() as usize,
+            name: frame["name"].as_str().unwrap_or_default().to_string(),
+            file_path: frame["source"]["path"].as_str().unwrap_or_default().to_string(),
+            line: frame["line"].as_u64().unwrap_or_default() as usize,
+            column: frame["column"].as_u64().unwrap_or_default() as usize,
+        }).collect();
+
+        let mut variables = HashMap::new();
+        if let Some(frame) = call_stack.first() {
+            let scopes = client.request("scopes", serde_json::json!({ "frameId": frame.id })).await?;
+            for scope in scopes["scopes"].as_array().cloned().unwrap_or_default() {
+                if scope["expensive"].as_bool().unwrap_or(false) {
+                    continue;
+                }
+                let name = scope["name"].as_str().unwrap_or_default().to_lowercase();
+                let variable_scope = if name.contains("global") {
+                    VariableScope::Global
+                } else if name.contains("arg") || name.contains("param") {
+                    VariableScope::Parameter
+                } else if name.contains("return") {
+                    VariableScope::Return
+                } else {
+                    VariableScope::Local
+                };
+                let reference = scope["variablesReference"].as_i64().unwrap_or_default();
+                let body = client.request("variables", serde_json::json!({ "variablesReference": reference })).await?;
+                for variable in body["variables"].as_array().cloned().unwrap_or_default() {
+                    let name = variable["name"].as_str().unwrap_or_default().to_string();
+                    // Inner scopes come first, so they shadow outer ones
+                    variables.entry(name.clone()).or_insert(DebugVariable {
+                        name,
+                        value: variable["value"].as_str().unwrap_or_default().to_string(),
+                        variable_type: variable["type"].as_str().unwrap_or_default().to_string(),
+                        scope: variable_scope.clone(),
+                        children: Vec::new(),
+                    });
+                }
+            }
+        }
+        Ok((call_stack, variables))
+    }
+
+    fn end_debug_session(&self, debugger_id: &str) {
+        if self.debug_connections.lock().unwrap().remove(debugger_id).is_none() {
+            return;
+        }
+        let session_id = self.update_debugger(debugger_id, |d| {
+            d.status = DebuggerStatus::Stopped;
+            d.call_stack.clear();
+            d.variables.clear();
+            d.current_session.take().map(|s| s.id)
+        }).ok().flatten();
+
+        self.emit_event(DevToolsEvent {
+            event_type: DevToolsEventType::DebugSessionStopped,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            details: [
+                ("debugger_id".to_string(), serde_json::Value::String(debugger_id.to_string())),
+                ("session_id".to_string(), session_id.map(serde_json::Value::String).unwrap_or_default()),
+            ].into_iter().collect(),
+        });
+    }
+
+    // DAP replaces a file's breakpoints wholesale, so always send the full set
+    async fn sync_breakpoints(&self, debugger_id: &str, client: &DapClient, file_path: &str) -> Result<(), String> {
+        let breakpoints: Vec<Breakpoint> = self.update_debugger(debugger_id, |d| {
+            d.breakpoints.iter().filter(|b| b.enabled && b.file_path == file_path).cloned().collect()
+        })?;
+        let requested: Vec<serde_json::Value> = breakpoints.iter().map(|b| serde_json::json!({
+            "line": b.line,
+            "condition": b.condition,
+            "hitCondition": b.hit_condition,
+            "logMessage": b.log_message,
+        })).collect();
+        let body = client.request("setBreakpoints", serde_json::json!({
+            "source": { "path": file_path },
+            "breakpoints": requested,
+        })).await?;
+
+        let results = body["breakpoints"].as_array().cloned().unwrap_or_default();
+        self.update_debugger(debugger_id, |d| {
+            for (requested, result) in breakpoints.iter().zip(results.iter()) {
+                if let Some(b) = d.breakpoints.iter_mut().find(|b| b.id == requested.id) {
+                    b.verified = result["verified"].as_bool().unwrap_or(false);
+                }
+            }
+        })
+    }
+
+    /// Add (or update) a breakpoint, sending it to the adapter when a session is live.
+    pub async fn debug_set_breakpoint(&self, debugger_id: &str, file_path: &str, line: usize, condition: Option<String>) -> Result<Breakpoint, String> {
+        let id = self.update_debugger(debugger_id, |d| {
+            if let Some(existing) = d.breakpoints.iter_mut().find(|b| b.file_path == file_path && b.line == line) {
+                existing.condition = condition;
+                existing.enabled = true;
+                return existing.id.clone();
+            }
+            let id = uuid::Uuid::new_v4().to_string();
+            d.breakpoints.push(Breakpoint {
+                id: id.clone(),
+                file_path: file_path.to_string(),
+                line,
+                condition,
+                hit_condition: None,
+                log_message: None,
+                enabled: true,
+                verified: false,
+            });
+            id
+        })?;
+
+        if let Ok(connection) = self.debug_connection(debugger_id) {
+            self.sync_breakpoints(debugger_id, &connection.client, file_path).await?;
+        }
+
+        self.update_debugger(debugger_id, |d| d.breakpoints.iter().find(|b| b.id == id).cloned())?
+            .ok_or_else(|| "Breakpoint was removed".to_string())
+    }
+
+    async fn resume(&self, debugger_id: &str, command: &str) -> Result<(), String> {
+        let connection = self.debug_connection(debugger_id)?;
+        let thread_id = connection.thread_id.ok_or_else(|| format!("Debugger {} is not paused", debugger_id))?;
+        connection.client.request(command, serde_json::json!({ "threadId": thread_id })).await?;
+        // Not every adapter sends `continued` for requests it was asked to run
+        self.update_debugger(debugger_id, |d| {
+            d.status = DebuggerStatus::Running;
+            d.call_stack.clear();
+            d.variables.clear();
+        })
+    }
+
+    pub async fn debug_continue(&self, debugger_id: &str) -> Result<(), String> {
+        self.resume(debugger_id, "continue").await
+    }
+
+    pub async fn debug_step_over(&self, debugger_id: &str) -> Result<(), String> {
+        self.resume(debugger_id, "next").await
+    }
+
+    /// Evaluate `expression` in `frame_id`, or the innermost frame when paused.
+    pub async fn debug_evaluate(&self, debugger_id: &str, expression: &str, frame_id: Option<usize>) -> Result<String, String> {
+        let connection = self.debug_connection(debugger_id)?;
+        let frame_id = match frame_id {
+            Some(frame_id) => Some(frame_id),
+            None => self.update_debugger(debugger_id, |d| d.call_stack.first().map(|f| f.id))?,
+        };
+        let mut arguments = serde_json::json!({ "expression": expression, "context": "repl" });
+        if let Some(frame_id) = frame_id {
+            arguments["frameId"] = serde_json::Value::from(frame_id);
+        }
+        let body = connection.client.request("evaluate", arguments).await?;
+        Ok(body["result"].as_str().unwrap_or_default().to_string())
+    }
+
+    pub async fn debug_stop(&self, debugger_id: &str) -> Result<(), String> {
+        let connection = self.debug_connection(debugger_id)?;
+        connection.client.shutdown().await;
+        self.end_debug_session(debugger_id);
+        Ok(())
+    }
+
+    pub fn get_debugger(&self, debugger_id: &str) -> Option<Debugger> {
+        let debuggers = self.debuggers.lock().unwrap();
+        debuggers.get(debugger_id).cloned()
+    }
+
     // Getters
     pub fn get_git_repositories(&self) -> Vec<GitRepository> {
         let repos = self.git_repositories.lock().unwrap();
@@ -1262,3 +1975,476 @@ impl DevToolsManager {
         history.iter().cloned().collect()
     }
 }
+
+fn parse_hunk_range(range: &str) -> (usize, usize) {
+    let (start, count) = range.split_once(',').unwrap_or((range, "1"));
+    (start.parse().unwrap_or_default(), count.parse().unwrap_or_default())
+}
+
+fn parse_unified_diff(output: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    let mut in_hunk = false;
+
+    // Split on '\n' alone: a CRLF file's lines keep their '\r', so a hunk
+    // rebuilt into a patch still matches the file
+    for line in output.split_inclusive('\n').map(|line| line.strip_suffix('\n').unwrap_or(line)) {
+        if let Some(paths) = line.strip_prefix("diff --git ") {
+            // Fallback path for diffs without ---/+++ lines (mode changes, binaries)
+            let path = paths.rsplit_once(" b/").map(|(_, b)| b.to_string()).unwrap_or_default();
+            files.push(FileDiff { path, old_path: None, header: vec![line.to_string()], binary: false, hunks: Vec::new() });
+            in_hunk = false;
+            continue;
+        }
+        let Some(file) = files.last_mut() else { continue };
+
+        if line.starts_with("@@") {
+            let mut ranges = line.trim_start_matches('@').split_whitespace();
+            let (old_start, old_lines) = parse_hunk_range(ranges.next().unwrap_or_default().trim_start_matches('-'));
+            let (new_start, new_lines) = parse_hunk_range(ranges.next().unwrap_or_default().trim_start_matches('+'));
+            let index = file.hunks.len();
+            file.hunks.push(DiffHunk { index, header: line.to_string(), old_start, old_lines, new_start, new_lines, lines: Vec::new() });
+            in_hunk = true;
+        } else if in_hunk {
+            if let Some(hunk) = file.hunks.last_mut() {
+                hunk.lines.push(line.to_string());
+            }
+        } else {
+            if let Some(old) = line.strip_prefix("--- a/") {
+                file.old_path = Some(old.to_string());
+            } else if let Some(new) = line.strip_prefix("+++ b/") {
+                file.path = new.to_string();
+            } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+                file.binary = true;
+            }
+            file.header.push(line.to_string());
+        }
+    }
+
+    // Only keep old_path where it differs from the new path
+    for file in &mut files {
+        if file.old_path.as_deref() == Some(file.path.as_str()) {
+            file.old_path = None;
+        }
+    }
+    files
+}
+
+/// Prefix of the error returned when applying or popping a stash hits conflicts.
+pub const STASH_CONFLICT_ERROR: &str = "Stash conflict";
+
+// "stash@{N}\0<unix time>\0WIP on <branch>: <hash> <subject>" (or "On <branch>: <message>")
+fn parse_stash_line(line: &str) -> Option<GitStash> {
+    let mut fields = line.splitn(3, '\0');
+    let reference = fields.next()?;
+    let timestamp = fields.next()?.parse().unwrap_or_default();
+    let subject = fields.next().unwrap_or_default();
+    let index = reference.strip_prefix("stash@{")?.strip_suffix('}')?.parse().ok()?;
+
+    let described = subject.strip_prefix("WIP on ").or_else(|| subject.strip_prefix("On ")).unwrap_or(subject);
+    let (branch, message) = match described.split_once(": ") {
+        Some((branch, message)) => (branch.to_string(), message.to_string()),
+        None => (String::new(), described.to_string()),
+    };
+    Some(GitStash { index, branch, message, timestamp })
+}
+
+// `git blame --porcelain`: each line starts with "<hash> <orig> <final> [<count>]",
+// followed by the commit's headers the first time that commit appears, then the
+// line content prefixed with a tab.
+fn parse_blame_porcelain(output: &str) -> Vec<BlameLine> {
+    let mut commits: HashMap<String, (String, String, u64, String)> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut current: Option<(String, usize)> = None;
+
+    for line in output.lines() {
+        if let Some(content) = line.strip_prefix('\t') {
+            let Some((hash, line_number)) = current.take() else { continue };
+            let (author, email, timestamp, summary) = commits.get(&hash).cloned().unwrap_or_default();
+            lines.push(BlameLine {
+                line_number,
+                short_hash: hash.chars().take(7).collect(),
+                committed: hash.chars().any(|c| c != '0'),
+                commit_hash: hash,
+                author,
+                email,
+                timestamp,
+                summary,
+                content: content.to_string(),
+            });
+            continue;
+        }
+
+        let Some((hash, _)) = current.as_ref() else {
+            let mut fields = line.split_whitespace();
+            let (Some(hash), Some(_), Some(final_line)) = (fields.next(), fields.next(), fields.next()) else { continue };
+            if hash.len() >= 40 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                if let Ok(final_line) = final_line.parse() {
+                    commits.entry(hash.to_string()).or_default();
+                    current = Some((hash.to_string(), final_line));
+                }
+            }
+            continue;
+        };
+
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        if let Some(commit) = commits.get_mut(hash) {
+            match key {
+                "author" => commit.0 = value.to_string(),
+                "author-mail" => commit.1 = value.trim_matches(|c| c == '<' || c == '>').to_string(),
+                "author-time" => commit.2 = value.parse().unwrap_or_default(),
+                "summary" => commit.3 = value.to_string(),
+                _ => {}
+            }
+        }
+    }
+    lines
+}
+
+/// One ecosystem found in a project directory, keyed off the file that marks it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectedProject {
+    pub language: String,
+    pub framework: Option<String>,
+    pub marker: String, // e.g. "Cargo.toml"
+    pub tool: String,   // build tool the suggestions use
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectInfo {
+    pub path: PathBuf,
+    pub language: Option<String>, // the first detected project, by marker priority
+    pub framework: Option<String>,
+    pub projects: Vec<DetectedProject>,
+    pub build_configs: Vec<BuildConfiguration>,
+    pub test_configs: Vec<TestConfiguration>,
+}
+
+fn build_config(name: &str, dir: &std::path::Path, command: &[&str], pre: &[&str], watch: &[&str], ignore: &[&str]) -> BuildConfiguration {
+    BuildConfiguration {
+        name: name.to_string(),
+        command: command.iter().map(|s| s.to_string()).collect(),
+        working_directory: Some(dir.to_path_buf()),
+        environment: HashMap::new(),
+        pre_build_commands: pre.iter().map(|s| s.to_string()).collect(),
+        post_build_commands: Vec::new(),
+        watch_patterns: watch.iter().map(|s| s.to_string()).collect(),
+        ignore_patterns: ignore.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+fn test_config(name: &str, command: &[&str], pattern: Option<&str>, parallel: bool) -> TestConfiguration {
+    TestConfiguration {
+        name: name.to_string(),
+        command: command.iter().map(|s| s.to_string()).collect(),
+        test_pattern: pattern.map(str::to_string),
+        coverage_enabled: false,
+        parallel,
+        timeout: Some(600),
+        environment: HashMap::new(),
+    }
+}
+
+// First framework whose dependency name appears in the manifest text, quoted, as a
+// TOML key, at the start of a line, or as a whole word (go.mod `require` lines)
+fn find_framework(manifest: &str, candidates: &[(&str, &str)]) -> Option<String> {
+    candidates
+        .iter()
+        .find(|(dependency, _)| {
+            manifest.contains(&format!("\"{}\"", dependency))
+                || manifest.contains(&format!("{} =", dependency))
+                || manifest.contains(&format!("\n{}", dependency))
+                || manifest.split_whitespace().any(|word| word == *dependency)
+        })
+        .map(|(_, framework)| framework.to_string())
+}
+
+/// Look for well-known manifests in `path` and suggest build and test
+/// configurations for each ecosystem found. Config names are prefixed with the
+/// tool (`cargo-build`, `npm-test`, ...) so suggestions from several ecosystems
+/// can be added side by side. Nothing is registered; see `add_build_configuration`.
+pub async fn detect_project_type(path: &std::path::Path) -> Result<ProjectInfo, String> {
+    if !path.is_dir() {
+        return Err(format!("{} is not a directory", path.display()));
+    }
+    let exists = |name: &str| path.join(name).exists();
+    let read = |name: &str| {
+        let file = path.join(name);
+        async move { fs::read_to_string(file).await.unwrap_or_default() }
+    };
+
+    let mut projects = Vec::new();
+    let mut build_configs = Vec::new();
+    let mut test_configs = Vec::new();
+
+    if exists("Cargo.toml") {
+        let manifest = read("Cargo.toml").await;
+        let framework = find_framework(&manifest, &[("tauri", "Tauri"), ("actix-web", "Actix Web"), ("axum", "Axum"), ("rocket", "Rocket"), ("bevy", "Bevy")]);
+        projects.push(DetectedProject { language: "Rust".into(), framework, marker: "Cargo.toml".into(), tool: "cargo".into() });
+        build_configs.push(build_config("cargo-build", path, &["cargo", "build"], &[], &["src/**/*.rs", "Cargo.toml"], &["target/**"]));
+        test_configs.push(test_config("cargo-test", &["cargo", "test"], None, true));
+    }
+
+    if exists("package.json") {
+        let manifest = read("package.json").await;
+        let package: serde_json::Value = serde_json::from_str(&manifest).unwrap_or_default();
+        let language = if exists("tsconfig.json") { "TypeScript" } else { "JavaScript" };
+        let framework = find_framework(&manifest, &[
+            ("next", "Next.js"), ("nuxt", "Nuxt"), ("@angular/core", "Angular"), ("svelte", "Svelte"),
+            ("vue", "Vue"), ("react", "React"), ("express", "Express"), ("vite", "Vite"),
+        ]);
+        let tool = if exists("pnpm-lock.yaml") {
+            "pnpm"
+        } else if exists("yarn.lock") {
+            "yarn"
+        } else if exists("bun.lockb") {
+            "bun"
+        } else {
+            "npm"
+        };
+        projects.push(DetectedProject { language: language.into(), framework, marker: "package.json".into(), tool: tool.into() });
+        let scripts = &package["scripts"];
+        if scripts.get("build").is_some() {
+            build_configs.push(build_config(&format!("{}-build", tool), path, &[tool, "run", "build"], &[], &["src/**", "package.json"], &["node_modules/**", "dist/**", "build/**"]));
+        }
+        if scripts.get("test").is_some() {
+            test_configs.push(test_config(&format!("{}-test", tool), &[tool, "test"], None, false));
+        }
+    }
+
+    if exists("pyproject.toml") || exists("setup.py") || exists("requirements.txt") {
+        let manifest = format!("{}\n{}", read("pyproject.toml").await, read("requirements.txt").await).to_lowercase();
+        let framework = if exists("manage.py") {
+            Some("Django".to_string())
+        } else {
+            find_framework(&manifest, &[("django", "Django"), ("fastapi", "FastAPI"), ("flask", "Flask")])
+        };
+        let marker = ["pyproject.toml", "setup.py", "requirements.txt"].into_iter().find(|m| exists(m)).unwrap_or_default();
+        projects.push(DetectedProject { language: "Python".into(), framework, marker: marker.into(), tool: "python".into() });
+        if exists("pyproject.toml") || exists("setup.py") {
+            build_configs.push(build_config("python-build", path, &["python", "-m", "build"], &[], &["**/*.py", "pyproject.toml"], &[".venv/**", "dist/**", "build/**"]));
+        }
+        if manifest.contains("pytest") || exists("pytest.ini") || exists("conftest.py") {
+            test_configs.push(test_config("pytest", &["python", "-m", "pytest"], Some("test_*.py"), false));
+        } else {
+            test_configs.push(test_config("python-unittest", &["python", "-m", "unittest", "discover"], Some("test*.py"), false));
+        }
+    }
+
+    if exists("go.mod") {
+        let manifest = read("go.mod").await;
+        let framework = find_framework(&manifest, &[("github.com/gin-gonic/gin", "Gin"), ("github.com/labstack/echo", "Echo"), ("github.com/gofiber/fiber", "Fiber")]);
+        projects.push(DetectedProject { language: "Go".into(), framework, marker: "go.mod".into(), tool: "go".into() });
+        build_configs.push(build_config("go-build", path, &["go", "build", "./..."], &[], &["**/*.go", "go.mod"], &["vendor/**"]));
+        test_configs.push(test_config("go-test", &["go", "test", "./..."], Some("*_test.go"), true));
+    }
+
+    if exists("pom.xml") {
+        projects.push(DetectedProject { language: "Java".into(), framework: None, marker: "pom.xml".into(), tool: "maven".into() });
+        build_configs.push(build_config("maven-build", path, &["mvn", "package", "-DskipTests"], &[], &["src/**", "pom.xml"], &["target/**"]));
+        test_configs.push(test_config("maven-test", &["mvn", "test"], None, false));
+    }
+
+    if exists("build.gradle") || exists("build.gradle.kts") {
+        let gradle = if exists("gradlew") { "./gradlew" } else { "gradle" };
+        let marker = if exists("build.gradle.kts") { "build.gradle.kts" } else { "build.gradle" };
+        let language = if marker.ends_with(".kts") { "Kotlin" } else { "Java" };
+        projects.push(DetectedProject { language: language.into(), framework: None, marker: marker.into(), tool: "gradle".into() });
+        build_configs.push(build_config("gradle-build", path, &[gradle, "build", "-x", "test"], &[], &["src/**", marker], &["build/**", ".gradle/**"]));
+        test_configs.push(test_config("gradle-test", &[gradle, "test"], None, false));
+    }
+
+    if exists("CMakeLists.txt") {
+        projects.push(DetectedProject { language: "C/C++".into(), framework: None, marker: "CMakeLists.txt".into(), tool: "cmake".into() });
+        build_configs.push(build_config("cmake-build", path, &["cmake", "--build", "build"], &["cmake -S . -B build"], &["**/*.c", "**/*.cpp", "**/*.h", "CMakeLists.txt"], &["build/**"]));
+        test_configs.push(test_config("ctest", &["ctest", "--test-dir", "build"], None, true));
+    }
+
+    // A Makefile is often a wrapper around the tools above, so it only adds configs
+    if exists("Makefile") || exists("makefile") {
+        let makefile = format!("{}{}", read("Makefile").await, read("makefile").await);
+        projects.push(DetectedProject { language: projects.first().map(|p| p.language.clone()).unwrap_or_else(|| "Make".into()), framework: None, marker: "Makefile".into(), tool: "make".into() });
+        build_configs.push(build_config("make-build", path, &["make"], &[], &[], &[]));
+        if makefile.lines().any(|line| line.starts_with("test:")) {
+            test_configs.push(test_config("make-test", &["make", "test"], None, false));
+        }
+    }
+
+    Ok(ProjectInfo {
+        path: path.to_path_buf(),
+        language: projects.first().map(|p| p.language.clone()),
+        framework: projects.iter().find_map(|p| p.framework.clone()),
+        projects,
+        build_configs,
+        test_configs,
+    })
+}
+
+// How long an enclosing-project lookup is reused; suggestions ask on every keystroke
+const ENCLOSING_PROJECT_TTL: Duration = Duration::from_secs(10);
+// Past this many cached directories the cache starts over
+const MAX_ENCLOSING_PROJECT_CACHE: usize = 256;
+
+type EnclosingProjectCache = Mutex<HashMap<PathBuf, (std::time::Instant, Option<ProjectInfo>)>>;
+
+fn enclosing_project_cache() -> &'static EnclosingProjectCache {
+    static CACHE: std::sync::OnceLock<EnclosingProjectCache> = std::sync::OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The nearest project at or above `start`, not looking past the root of the
+/// git repository that contains it. Results are cached per directory for
+/// `ENCLOSING_PROJECT_TTL`, so new or removed markers show up shortly after.
+pub async fn detect_enclosing_project(start: &std::path::Path) -> Option<ProjectInfo> {
+    if let Some((checked_at, project)) = enclosing_project_cache().lock().unwrap().get(start) {
+        if checked_at.elapsed() < ENCLOSING_PROJECT_TTL {
+            return project.clone();
+        }
+    }
+    let project = find_enclosing_project(start).await;
+    let mut cache = enclosing_project_cache().lock().unwrap();
+    if cache.len() >= MAX_ENCLOSING_PROJECT_CACHE {
+        cache.clear();
+    }
+    cache.insert(start.to_path_buf(), (std::time::Instant::now(), project.clone()));
+    project
+}
+
+async fn find_enclosing_project(start: &std::path::Path) -> Option<ProjectInfo> {
+    for dir in start.ancestors() {
+        if let Ok(info) = detect_project_type(dir).await {
+            if !info.projects.is_empty() {
+                return Some(info);
+            }
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(args: &[&str], dir: &std::path::Path) -> std::process::Output {
+        let output = std::process::Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com", "-c", "core.autocrlf=false"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        output
+    }
+
+    #[test]
+    fn crlf_hunk_lines_keep_their_carriage_returns() {
+        let diff = "diff --git a/win.txt b/win.txt\nindex 1111111..2222222 100644\n--- a/win.txt\n+++ b/win.txt\n@@ -1,2 +1,2 @@\n one\r\n-two\r\n+TWO\r\n";
+        let files = parse_unified_diff(diff);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "win.txt");
+        assert_eq!(files[0].header.last().map(String::as_str), Some("+++ b/win.txt"));
+        assert_eq!(files[0].hunks[0].lines, vec![" one\r", "-two\r", "+TWO\r"]);
+        assert_eq!((files[0].hunks[0].old_start, files[0].hunks[0].new_lines), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn staging_one_hunk_of_a_crlf_file_keeps_its_line_endings() {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        let repo = std::env::temp_dir().join(format!("warp-hunk-test-{}-{}", std::process::id(), nanos));
+        std::fs::create_dir_all(&repo).unwrap();
+        git(&["init", "-q"], &repo);
+        let original: String = (1..=20).map(|n| format!("line {}\r\n", n)).collect();
+        std::fs::write(repo.join("win.txt"), &original).unwrap();
+        git(&["add", "win.txt"], &repo);
+        git(&["commit", "-q", "-m", "init"], &repo);
+        // Two changes far enough apart to be separate hunks
+        let edited = original.replace("line 2\r\n", "line two\r\n").replace("line 19\r\n", "line nineteen\r\n");
+        std::fs::write(repo.join("win.txt"), &edited).unwrap();
+
+        let dev_tools = DevToolsManager::new();
+        let name = dev_tools.load_git_repository(&repo).await.unwrap().name;
+        let diff = dev_tools.get_git_diff(&name, Some("win.txt"), false).await.unwrap();
+        assert_eq!(diff[0].hunks.len(), 2);
+        dev_tools.stage_hunk(&name, "win.txt", 0, Some(&diff[0].hunks[0].header)).await.unwrap();
+
+        let staged = git(&["show", ":win.txt"], &repo).stdout;
+        assert_eq!(String::from_utf8(staged).unwrap(), original.replace("line 2\r\n", "line two\r\n"));
+        let remaining = dev_tools.get_git_diff(&name, Some("win.txt"), false).await.unwrap();
+        assert_eq!(remaining[0].hunks.len(), 1);
+        assert!(remaining[0].hunks[0].lines.iter().any(|line| line == "+line nineteen\r"));
+
+        dev_tools.unstage_hunk(&name, "win.txt", 0, None).await.unwrap();
+        assert_eq!(String::from_utf8(git(&["show", ":win.txt"], &repo).stdout).unwrap(), original);
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    fn fixture(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        let dir = std::env::temp_dir().join(format!("warp-project-{}-{}-{}", name, std::process::id(), nanos));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            std::fs::write(dir.join(file), contents).unwrap();
+        }
+        dir
+    }
+
+    fn names<T>(configs: &[T], name: impl Fn(&T) -> &str) -> Vec<String> {
+        configs.iter().map(|c| name(c).to_string()).collect()
+    }
+
+    // Fixture name and files, then the expected language, framework, build and test config names
+    type EcosystemCase<'a> = (&'a str, &'a [(&'a str, &'a str)], &'a str, Option<&'a str>, &'a [&'a str], &'a [&'a str]);
+
+    #[tokio::test]
+    async fn each_ecosystem_is_detected_from_its_fixture() {
+        let cases: &[EcosystemCase] = &[
+            ("rust", &[("Cargo.toml", "[package]\nname = \"app\"\n\n[dependencies]\ntauri = \"2\"\n")], "Rust", Some("Tauri"), &["cargo-build"], &["cargo-test"]),
+            (
+                "node",
+                &[("package.json", r#"{"scripts": {"build": "vite build", "test": "vitest"}, "dependencies": {"react": "18"}}"#), ("tsconfig.json", "{}"), ("pnpm-lock.yaml", "")],
+                "TypeScript",
+                Some("React"),
+                &["pnpm-build"],
+                &["pnpm-test"],
+            ),
+            ("python", &[("pyproject.toml", "[project]\ndependencies = [\"FastAPI\", \"pytest\"]\n")], "Python", Some("FastAPI"), &["python-build"], &["pytest"]),
+            ("go", &[("go.mod", "module example.com/api\n\nrequire (\n\tgithub.com/gin-gonic/gin v1.9.1\n)\n")], "Go", Some("Gin"), &["go-build"], &["go-test"]),
+            ("make", &[("Makefile", "all:\n\tcc main.c\n\ntest:\n\t./run-tests\n")], "Make", None, &["make-build"], &["make-test"]),
+        ];
+
+        for (name, files, language, framework, builds, tests) in cases {
+            let dir = fixture(name, files);
+            let info = detect_project_type(&dir).await.unwrap();
+            assert_eq!(info.language.as_deref(), Some(*language), "{}", name);
+            assert_eq!(info.framework.as_deref(), *framework, "{}", name);
+            assert_eq!(names(&info.build_configs, |c| &c.name), *builds, "{}", name);
+            assert_eq!(names(&info.test_configs, |c| &c.name), *tests, "{}", name);
+            assert!(info.build_configs.iter().all(|c| c.working_directory.as_deref() == Some(dir.as_path())));
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn a_makefile_wrapping_cargo_adds_to_the_rust_suggestions() {
+        let dir = fixture("mixed", &[("Cargo.toml", "[package]\nname = \"cli\"\n"), ("Makefile", "release:\n\tcargo build --release\n")]);
+        let info = detect_project_type(&dir).await.unwrap();
+        let projects: Vec<_> = info.projects.iter().map(|p| (p.marker.as_str(), p.language.as_str(), p.tool.as_str())).collect();
+        assert_eq!(projects, [("Cargo.toml", "Rust", "cargo"), ("Makefile", "Rust", "make")]);
+        assert_eq!(names(&info.build_configs, |c| &c.name), ["cargo-build", "make-build"]);
+        // No `test:` target, so only cargo's tests are offered
+        assert_eq!(names(&info.test_configs, |c| &c.name), ["cargo-test"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn folders_without_markers_suggest_nothing() {
+        let dir = fixture("empty", &[("notes.txt", "hello")]);
+        let info = detect_project_type(&dir).await.unwrap();
+        assert!(info.projects.is_empty() && info.build_configs.is_empty() && info.test_configs.is_empty());
+        assert_eq!(info.language, None);
+        assert!(detect_project_type(&dir.join("notes.txt")).await.is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -21,6 +21,134 @@ pub struct DirectoryBookmark {
     pub path: String,
     pub created_at: DateTime<Utc>,
     pub tags: Vec<String>,
+    #[serde(default)]
+    pub visit_count: u32,
+    #[serde(default)]
+    pub last_visited: Option<DateTime<Utc>>,
+}
+
+impl DirectoryBookmark {
+    /// zoxide-style frecency: visits weighted by how recently the bookmark was used.
+    pub fn frecency(&self, now: DateTime<Utc>) -> f64 {
+        let weight = match self.last_visited {
+            Some(visited) => recency_weight(visited, now),
+            None => 0.1,
+        };
+        (self.visit_count.max(1)) as f64 * weight
+    }
+
+    /// Best fuzzy score of `query` against the name, path, final path component and tags.
+    pub fn match_score(&self, query: &str) -> Option<i64> {
+        let leaf = self.path.rsplit(['/', '\\']).find(|s| !s.is_empty()).unwrap_or(&self.path);
+        [self.name.as_str(), leaf]
+            .into_iter()
+            .chain(self.tags.iter().map(|t| t.as_str()))
+            .filter_map(|candidate| fuzzy_score(query, candidate))
+            .chain(fuzzy_score(query, &self.path).map(|score| score / 2))
+            .max()
+    }
+}
+
+/// Multiplier applied to a visit count based on how long ago the entry was last used.
+pub fn recency_weight(last_accessed: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    let age = now.signed_duration_since(last_accessed);
+    if age.num_hours() < 1 { 4.0 }
+    else if age.num_days() < 1 { 2.0 }
+    else if age.num_weeks() < 1 { 0.5 }
+    else { 0.25 }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrecentDirectory {
+    pub path: String,
+    pub rank: f64,
+    pub last_accessed: DateTime<Utc>,
+}
+
+impl FrecentDirectory {
+    pub fn score(&self, now: DateTime<Utc>) -> f64 {
+        self.rank * recency_weight(self.last_accessed, now)
+    }
+
+    /// zoxide matching rules: every keyword appears in the path in order (case-insensitive)
+    /// and the last keyword matches inside the final path component.
+    pub fn matches(&self, keywords: &[String]) -> bool {
+        let path = self.path.to_lowercase();
+        let mut rest = path.as_str();
+        for keyword in keywords {
+            match rest.find(keyword.as_str()) {
+                Some(idx) => rest = &rest[idx + keyword.len()..],
+                None => return false,
+            }
+        }
+        match keywords.last() {
+            Some(last) => {
+                let leaf = path.rsplit(['/', '\\']).find(|s| !s.is_empty()).unwrap_or(&path);
+                leaf.contains(last.as_str())
+            }
+            None => true,
+        }
+    }
+}
+
+/// One entry of the merged recent-directories jump list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentDirectory {
+    pub path: String,
+    pub score: f64,
+    pub last_accessed: Option<DateTime<Utc>>,
+    pub sources: Vec<String>, // "frecency", "session", "file_browser"
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookmarkSuggestion {
+    pub bookmark: DirectoryBookmark,
+    pub score: i64,
+    pub frecency: f64,
+}
+
+/// Case-insensitive subsequence match. Consecutive characters, word-boundary hits and
+/// prefix/substring matches score higher; `None` means `query` is not a subsequence.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let query: Vec<char> = query.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let lower = candidate.to_lowercase();
+    let chars: Vec<char> = lower.chars().collect();
+    let mut score = 0i64;
+    let mut qi = 0;
+    let mut previous_match: Option<usize> = None;
+    for (i, c) in chars.iter().enumerate() {
+        if qi == query.len() {
+            break;
+        }
+        if *c != query[qi] {
+            continue;
+        }
+        score += 1;
+        if previous_match == Some(i.wrapping_sub(1)) {
+            score += 5;
+        }
+        if i == 0 || matches!(chars[i - 1], '/' | '\\' | '-' | '_' | '.' | ' ') {
+            score += 8;
+        }
+        previous_match = Some(i);
+        qi += 1;
+    }
+    if qi < query.len() {
+        return None;
+    }
+    let needle: String = query.iter().collect();
+    if lower == needle {
+        score += 100;
+    } else if lower.starts_with(&needle) {
+        score += 50;
+    } else if lower.contains(&needle) {
+        score += 25;
+    }
+    // Prefer tighter candidates when everything else is equal
+    Some(score * 4 - (chars.len() as i64 - query.len() as i64).max(0) / 4)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -101,6 +229,72 @@ pub struct ExecutionContext {
     pub selected_text: Vec<String>,
     pub active_processes: Vec<ProcessInfo>,
     pub network_status: NetworkStatus,
+    #[serde(default)]
+    pub frequent_commands: Vec<DirectoryCommandStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryCommandStats {
+    pub command: String,
+    pub count: u32,
+    pub last_used: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvironmentSnapshot {
+    pub id: String,
+    pub captured_at: DateTime<Utc>,
+    pub variables: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvironmentChange {
+    pub name: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub redacted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvironmentDiff {
+    pub snapshot_id: String,
+    pub added: Vec<EnvironmentChange>,
+    pub removed: Vec<EnvironmentChange>,
+    pub changed: Vec<EnvironmentChange>,
+}
+
+impl EnvironmentDiff {
+    /// Blank out values of every variable `is_secret` flags, keeping the name visible.
+    pub fn redact(&mut self, is_secret: impl Fn(&str, &str) -> bool) {
+        for change in self.added.iter_mut().chain(self.removed.iter_mut()).chain(self.changed.iter_mut()) {
+            let flagged = [&change.old_value, &change.new_value]
+                .iter()
+                .filter_map(|value| value.as_deref())
+                .any(|value| is_secret(&change.name, value));
+            if flagged {
+                change.old_value = change.old_value.as_ref().map(|_| "[REDACTED]".to_string());
+                change.new_value = change.new_value.as_ref().map(|_| "[REDACTED]".to_string());
+                change.redacted = true;
+            }
+        }
+    }
+}
+
+/// Parse the output of `env -0` (NUL separated) or plain `env` (one `NAME=value` per line)
+/// as captured from the shell by shell integration.
+pub fn parse_env_output(output: &str) -> HashMap<String, String> {
+    let separator = if output.contains('\0') { '\0' } else { '\n' };
+    output
+        .split(separator)
+        .filter_map(|entry| {
+            let entry = entry.trim_end_matches('\r');
+            let (name, value) = entry.split_once('=')?;
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return None;
+            }
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -151,9 +345,49 @@ pub struct ProxySettings {
 
 pub type ExecutionContextManager = Arc<Mutex<ExecutionContextState>>;
 
+const MAX_COMMANDS_PER_DIRECTORY: usize = 200;
+const FREQUENT_COMMANDS_IN_CONTEXT: usize = 10;
+const MAX_ENVIRONMENT_SNAPSHOTS: usize = 20;
+const DEFAULT_BOOKMARK_SUGGESTIONS: usize = 10;
+const DEFAULT_RECENT_DIRECTORIES: usize = 20;
+/// Total rank above which all directory ranks are scaled down, as zoxide's `_ZO_MAXAGE`.
+const FRECENCY_MAX_AGE: f64 = 10_000.0;
+/// How often batched directory-command counts are written to disk.
+pub const PERSIST_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+
+fn config_dir() -> PathBuf {
+    let home = env::var("HOME")
+        .or_else(|_| env::var("USERPROFILE"))
+        .unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(home).join(".warp-terminal")
+}
+
+fn directory_commands_path() -> PathBuf { config_dir().join("directory-commands.json") }
+
+fn bookmarks_path() -> PathBuf { config_dir().join("bookmarks.json") }
+
+fn frecency_path() -> PathBuf { config_dir().join("directory-frecency.json") }
+
+/// Directory keys are compared without trailing separators so `~/proj` and `~/proj/` share history.
+fn normalize_directory(path: &str) -> String {
+    let trimmed = path.trim_end_matches(['/', '\\']);
+    if trimmed.is_empty() { path.to_string() } else { trimmed.to_string() }
+}
+
 pub struct ExecutionContextState {
     pub contexts: HashMap<String, ExecutionContext>,
     pub active_session: Option<String>,
+    /// directory -> command -> usage stats, persisted to `directory-commands.json`
+    pub directory_commands: HashMap<String, HashMap<String, DirectoryCommandStats>>,
+    /// Set when `directory_commands` changed since the last `flush_pending_writes`
+    directory_commands_dirty: bool,
+    pub env_snapshots: HashMap<String, EnvironmentSnapshot>,
+    /// Bookmarks are shared by all sessions and persisted to `bookmarks.json`
+    pub bookmarks: Vec<DirectoryBookmark>,
+    /// Every visited directory keyed by normalized path, persisted to `directory-frecency.json`
+    pub frecent_directories: HashMap<String, FrecentDirectory>,
+    /// Set when `frecent_directories` changed since the last `flush_pending_writes`
+    frecent_directories_dirty: bool,
 }
 
 impl ExecutionContextState {
@@ -161,7 +395,432 @@ impl ExecutionContextState {
         Self {
             contexts: HashMap::new(),
             active_session: None,
+            directory_commands: Self::load_directory_commands(),
+            directory_commands_dirty: false,
+            env_snapshots: HashMap::new(),
+            bookmarks: Self::load_bookmarks(),
+            frecent_directories: Self::load_frecent_directories(),
+            frecent_directories_dirty: false,
+        }
+    }
+
+    fn load_frecent_directories() -> HashMap<String, FrecentDirectory> {
+        std::fs::read_to_string(frecency_path())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_frecent_directories(&self) -> Result<(), String> {
+        let dir = config_dir();
+        if !dir.exists() { std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?; }
+        let data = serde_json::to_string(&self.frecent_directories).map_err(|e| e.to_string())?;
+        std::fs::write(frecency_path(), data).map_err(|e| e.to_string())
+    }
+
+    /// Bump the rank of `path` and age the database once the total rank exceeds
+    /// the limit. Written out by the next `flush_pending_writes`, so a shell
+    /// hopping between directories doesn't cost a write per prompt.
+    pub fn record_directory_visit(&mut self, path: &str) {
+        if path.is_empty() {
+            return;
+        }
+        let key = normalize_directory(path);
+        let now = Utc::now();
+        let entry = self.frecent_directories.entry(key.clone()).or_insert_with(|| FrecentDirectory {
+            path: key,
+            rank: 0.0,
+            last_accessed: now,
+        });
+        entry.rank += 1.0;
+        entry.last_accessed = now;
+        self.age_frecent_directories();
+        self.frecent_directories_dirty = true;
+    }
+
+    /// Scale ranks so they sum to 90% of the limit and forget directories that fall below 1.
+    fn age_frecent_directories(&mut self) {
+        let total: f64 = self.frecent_directories.values().map(|d| d.rank).sum();
+        if total <= FRECENCY_MAX_AGE {
+            return;
+        }
+        let factor = 0.9 * FRECENCY_MAX_AGE / total;
+        self.frecent_directories.retain(|_, dir| {
+            dir.rank *= factor;
+            dir.rank >= 1.0
+        });
+    }
+
+    /// Existing directories matching `query`, highest frecency first.
+    pub fn frecent_matches(&self, query: &str) -> Vec<FrecentDirectory> {
+        let keywords: Vec<String> = query.split_whitespace().map(|k| k.to_lowercase()).collect();
+        let now = Utc::now();
+        let mut matches: Vec<FrecentDirectory> = self
+            .frecent_directories
+            .values()
+            .filter(|dir| dir.matches(&keywords) && std::path::Path::new(&dir.path).is_dir())
+            .cloned()
+            .collect();
+        matches.sort_by(|a, b| b.score(now).partial_cmp(&a.score(now)).unwrap_or(std::cmp::Ordering::Equal));
+        matches
+    }
+
+    pub fn jump_frecent(&self, query: &str) -> Option<PathBuf> {
+        self.frecent_matches(query).into_iter().next().map(|dir| PathBuf::from(dir.path))
+    }
+
+    /// The jump list: the frecency database merged with every session's recent
+    /// directories and `browsed` (the file browser's recent paths, most recent
+    /// first). List positions count as a fading bonus on top of frecency, so a
+    /// directory opened a moment ago shows up even before it has built up rank.
+    /// Directories that no longer exist are dropped, and pruned from the database.
+    pub fn get_recent_directories(&mut self, browsed: &[String], limit: usize) -> Vec<RecentDirectory> {
+        let now = Utc::now();
+        let mut merged: HashMap<String, RecentDirectory> = HashMap::new();
+        let mut add = |path: &str, score: f64, last_accessed: Option<DateTime<Utc>>, source: &str| {
+            let key = normalize_directory(path);
+            let entry = merged.entry(key.clone()).or_insert_with(|| RecentDirectory {
+                path: key,
+                score: 0.0,
+                last_accessed: None,
+                sources: Vec::new(),
+            });
+            entry.score += score;
+            entry.last_accessed = entry.last_accessed.max(last_accessed);
+            if !entry.sources.iter().any(|s| s == source) {
+                entry.sources.push(source.to_string());
+            }
+        };
+
+        for dir in self.frecent_directories.values() {
+            add(&dir.path, dir.score(now), Some(dir.last_accessed), "frecency");
+        }
+        for context in self.contexts.values() {
+            for (index, path) in context.directory_state.recent_directories.iter().enumerate() {
+                add(path, 1.0 / (index + 1) as f64, None, "session");
+            }
+        }
+        for (index, path) in browsed.iter().enumerate() {
+            // The browser also remembers files; jump to the folder holding them
+            let path = std::path::Path::new(path);
+            let directory = if path.is_file() { path.parent().unwrap_or(path) } else { path };
+            add(&directory.to_string_lossy(), 1.0 / (index + 1) as f64, None, "file_browser");
+        }
+
+        let missing: Vec<String> = merged.keys().filter(|path| !std::path::Path::new(path).is_dir()).cloned().collect();
+        let pruned = missing.iter().filter(|path| self.frecent_directories.remove(*path).is_some()).count();
+        if pruned > 0 {
+            self.frecent_directories_dirty = true;
+        }
+        for path in &missing {
+            merged.remove(path);
+        }
+
+        let mut directories: Vec<RecentDirectory> = merged.into_values().collect();
+        directories.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.path.cmp(&b.path)));
+        directories.truncate(limit);
+        directories
+    }
+
+    /// Merge the output of `zoxide query --list --score` (`<score> <path>` per line).
+    /// Imported scores are added to existing ranks. Returns the number of directories imported.
+    pub fn import_zoxide_scores(&mut self, listing: &str) -> Result<usize, String> {
+        let now = Utc::now();
+        let mut imported = 0;
+        for line in listing.lines() {
+            let line = line.trim();
+            let Some((score, path)) = line.split_once(char::is_whitespace) else { continue };
+            let Ok(score) = score.parse::<f64>() else { continue };
+            let key = normalize_directory(path.trim());
+            if key.is_empty() || score <= 0.0 {
+                continue;
+            }
+            let entry = self.frecent_directories.entry(key.clone()).or_insert_with(|| FrecentDirectory {
+                path: key,
+                rank: 0.0,
+                last_accessed: now,
+            });
+            entry.rank += score;
+            imported += 1;
+        }
+        self.age_frecent_directories();
+        self.save_frecent_directories()?;
+        self.frecent_directories_dirty = false;
+        Ok(imported)
+    }
+
+    fn load_bookmarks() -> Vec<DirectoryBookmark> {
+        std::fs::read_to_string(bookmarks_path())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_bookmarks(&self) -> Result<(), String> {
+        let dir = config_dir();
+        if !dir.exists() { std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?; }
+        let data = serde_json::to_string_pretty(&self.bookmarks).map_err(|e| e.to_string())?;
+        std::fs::write(bookmarks_path(), data).map_err(|e| e.to_string())
+    }
+
+    /// Copy the shared bookmark list into every session context.
+    fn sync_bookmarks(&mut self) {
+        for context in self.contexts.values_mut() {
+            context.directory_state.bookmarks = self.bookmarks.clone();
+        }
+    }
+
+    /// Add a bookmark, replacing any existing one with the same name while keeping its visit history.
+    /// `path` made absolute against the session's working directory, so "." or
+    /// "../lib" bookmark a directory relative to where the session is.
+    pub fn resolve_session_path(&self, session_id: &str, path: &str) -> String {
+        let Some(context) = self.contexts.get(session_id) else {
+            return path.to_string();
+        };
+        if !std::path::Path::new(path).is_relative() {
+            return path.to_string();
+        }
+        let resolved = path.split(['/', '\\']).fold(PathBuf::from(&context.directory_state.pwd), |mut dir, part| {
+            match part {
+                "" | "." => {}
+                ".." => {
+                    dir.pop();
+                }
+                part => dir.push(part),
+            }
+            dir
+        });
+        resolved.to_string_lossy().into_owned()
+    }
+
+    pub fn add_bookmark(&mut self, name: String, path: String, tags: Vec<String>) -> Result<(), String> {
+        let mut bookmark = DirectoryBookmark {
+            name,
+            path,
+            created_at: Utc::now(),
+            tags,
+            visit_count: 0,
+            last_visited: None,
+        };
+        if let Some(pos) = self.bookmarks.iter().position(|b| b.name == bookmark.name) {
+            let existing = self.bookmarks.remove(pos);
+            bookmark.created_at = existing.created_at;
+            if existing.path == bookmark.path {
+                bookmark.visit_count = existing.visit_count;
+                bookmark.last_visited = existing.last_visited;
+            }
+        }
+        self.bookmarks.push(bookmark);
+        self.sync_bookmarks();
+        self.save_bookmarks()
+    }
+
+    /// Restore bookmarks by name (incoming wins). Without `merge`, existing bookmarks are dropped first.
+    pub fn restore_bookmarks(&mut self, restored: Vec<DirectoryBookmark>, merge: bool) -> Result<usize, String> {
+        if !merge {
+            self.bookmarks.clear();
+        }
+        let count = restored.len();
+        for bookmark in restored {
+            self.bookmarks.retain(|existing| existing.name != bookmark.name);
+            self.bookmarks.push(bookmark);
+        }
+        self.sync_bookmarks();
+        self.save_bookmarks()?;
+        Ok(count)
+    }
+
+    /// Bookmarks matching `query`, best match first with frecency breaking ties.
+    pub fn get_bookmark_suggestions(&self, query: &str, limit: usize) -> Vec<BookmarkSuggestion> {
+        let now = Utc::now();
+        let mut suggestions: Vec<BookmarkSuggestion> = self
+            .bookmarks
+            .iter()
+            .filter_map(|bookmark| {
+                bookmark.match_score(query).map(|score| BookmarkSuggestion {
+                    frecency: bookmark.frecency(now),
+                    bookmark: bookmark.clone(),
+                    score,
+                })
+            })
+            .collect();
+        suggestions.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then(b.frecency.partial_cmp(&a.frecency).unwrap_or(std::cmp::Ordering::Equal))
+        });
+        suggestions.truncate(limit);
+        suggestions
+    }
+
+    /// Record a visit to any bookmark pointing at `path`.
+    pub fn record_bookmark_visit(&mut self, path: &str) -> Result<(), String> {
+        let target = normalize_directory(path);
+        let now = Utc::now();
+        let mut visited = false;
+        for bookmark in self.bookmarks.iter_mut().filter(|b| normalize_directory(&b.path) == target) {
+            bookmark.visit_count = bookmark.visit_count.saturating_add(1);
+            bookmark.last_visited = Some(now);
+            visited = true;
+        }
+        if !visited {
+            return Ok(());
+        }
+        self.sync_bookmarks();
+        self.save_bookmarks()
+    }
+
+    /// Resolve `query` to the best bookmark and count the jump as a visit.
+    pub fn jump_to_bookmark(&mut self, query: &str) -> Result<Option<DirectoryBookmark>, String> {
+        let best = match self.get_bookmark_suggestions(query, 1).into_iter().next() {
+            Some(suggestion) => suggestion.bookmark,
+            None => return Ok(None),
+        };
+        self.record_bookmark_visit(&best.path)?;
+        Ok(self.bookmarks.iter().find(|b| b.name == best.name).cloned())
+    }
+
+    /// Record the shell environment, falling back to this process's environment when
+    /// shell integration did not supply one.
+    pub fn snapshot_environment(&mut self, variables: Option<HashMap<String, String>>) -> EnvironmentSnapshot {
+        let snapshot = EnvironmentSnapshot {
+            id: uuid::Uuid::new_v4().to_string(),
+            captured_at: Utc::now(),
+            variables: variables.unwrap_or_else(|| env::vars().collect()),
+        };
+        if self.env_snapshots.len() >= MAX_ENVIRONMENT_SNAPSHOTS {
+            if let Some(oldest) = self
+                .env_snapshots
+                .values()
+                .min_by_key(|s| s.captured_at)
+                .map(|s| s.id.clone())
+            {
+                self.env_snapshots.remove(&oldest);
+            }
+        }
+        self.env_snapshots.insert(snapshot.id.clone(), snapshot.clone());
+        snapshot
+    }
+
+    /// Compare a stored snapshot against `current` (or this process's environment).
+    /// Values are returned unredacted; callers apply `EnvironmentDiff::redact`.
+    pub fn diff_environment(
+        &self,
+        snapshot_id: &str,
+        current: Option<HashMap<String, String>>,
+    ) -> Result<EnvironmentDiff, String> {
+        let snapshot = self
+            .env_snapshots
+            .get(snapshot_id)
+            .ok_or_else(|| format!("Environment snapshot not found: {}", snapshot_id))?;
+        let current = current.unwrap_or_else(|| env::vars().collect());
+
+        let mut diff = EnvironmentDiff {
+            snapshot_id: snapshot_id.to_string(),
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        };
+        for (name, value) in &current {
+            match snapshot.variables.get(name) {
+                None => diff.added.push(EnvironmentChange {
+                    name: name.clone(),
+                    old_value: None,
+                    new_value: Some(value.clone()),
+                    redacted: false,
+                }),
+                Some(old) if old != value => diff.changed.push(EnvironmentChange {
+                    name: name.clone(),
+                    old_value: Some(old.clone()),
+                    new_value: Some(value.clone()),
+                    redacted: false,
+                }),
+                Some(_) => {}
+            }
+        }
+        for (name, value) in &snapshot.variables {
+            if !current.contains_key(name) {
+                diff.removed.push(EnvironmentChange {
+                    name: name.clone(),
+                    old_value: Some(value.clone()),
+                    new_value: None,
+                    redacted: false,
+                });
+            }
+        }
+        for changes in [&mut diff.added, &mut diff.removed, &mut diff.changed] {
+            changes.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        Ok(diff)
+    }
+
+    fn load_directory_commands() -> HashMap<String, HashMap<String, DirectoryCommandStats>> {
+        std::fs::read_to_string(directory_commands_path())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_directory_commands(&self) -> Result<(), String> {
+        let dir = config_dir();
+        if !dir.exists() { std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?; }
+        let data = serde_json::to_string(&self.directory_commands).map_err(|e| e.to_string())?;
+        std::fs::write(directory_commands_path(), data).map_err(|e| e.to_string())
+    }
+
+    /// Count one run of `command` in `directory`. The map is written out by the
+    /// next `flush_pending_writes`, so a burst of commands costs one write.
+    pub fn record_directory_command(&mut self, directory: &str, command: &str) {
+        let command = command.trim();
+        if command.is_empty() || directory.is_empty() {
+            return;
+        }
+        let entries = self.directory_commands.entry(normalize_directory(directory)).or_default();
+        let now = Utc::now();
+        let stats = entries.entry(command.to_string()).or_insert_with(|| DirectoryCommandStats {
+            command: command.to_string(),
+            count: 0,
+            last_used: now,
+        });
+        stats.count = stats.count.saturating_add(1);
+        stats.last_used = now;
+
+        if entries.len() > MAX_COMMANDS_PER_DIRECTORY {
+            // Drop the least useful entry: lowest count, oldest first on ties
+            if let Some(victim) = entries
+                .values()
+                .min_by(|a, b| a.count.cmp(&b.count).then(a.last_used.cmp(&b.last_used)))
+                .map(|s| s.command.clone())
+            {
+                entries.remove(&victim);
+            }
+        }
+        self.directory_commands_dirty = true;
+    }
+
+    /// Persist whatever changed since the last flush. Called every `PERSIST_INTERVAL`.
+    pub fn flush_pending_writes(&mut self) -> Result<(), String> {
+        if self.directory_commands_dirty {
+            self.save_directory_commands()?;
+            self.directory_commands_dirty = false;
         }
+        if self.frecent_directories_dirty {
+            self.save_frecent_directories()?;
+            self.frecent_directories_dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Commands run in `path`, most frequent first, most recent breaking ties.
+    pub fn get_directory_commands(&self, path: &str, limit: usize) -> Vec<DirectoryCommandStats> {
+        let mut commands: Vec<DirectoryCommandStats> = self
+            .directory_commands
+            .get(&normalize_directory(path))
+            .map(|entries| entries.values().cloned().collect())
+            .unwrap_or_default();
+        commands.sort_by(|a, b| b.count.cmp(&a.count).then(b.last_used.cmp(&a.last_used)));
+        commands.truncate(limit);
+        commands
     }
 
     pub fn create_context(&mut self, session_id: String) -> tauri::Result<()> {
@@ -184,7 +843,6 @@ impl ExecutionContextState {
             let mut updated = self.build_execution_context()?;
             // Preserve user-specific data
             updated.selected_text = existing.selected_text.clone();
-            updated.directory_state.bookmarks = existing.directory_state.bookmarks.clone();
             updated.directory_state.recent_directories = existing.directory_state.recent_directories.clone();
             
             self.contexts.insert(session_id.to_string(), updated);
@@ -202,6 +860,7 @@ impl ExecutionContextState {
             selected_text: Vec::new(),
             active_processes: self.get_active_processes()?,
             network_status: self.get_network_status()?,
+            frequent_commands: Vec::new(),
         })
     }
 
@@ -219,7 +878,7 @@ impl ExecutionContextState {
             pwd,
             home,
             previous: None,
-            bookmarks: Vec::new(),
+            bookmarks: self.bookmarks.clone(),
             recent_directories: Vec::new(),
         })
     }
@@ -397,7 +1056,47 @@ pub async fn get_execution_context(
     context_manager: State<'_, ExecutionContextManager>,
 ) -> Result<Option<ExecutionContext>, String> {
     let manager = context_manager.lock().map_err(|e| e.to_string())?;
-    Ok(manager.get_context(&session_id).cloned())
+    Ok(manager.get_context(&session_id).cloned().map(|mut context| {
+        context.frequent_commands =
+            manager.get_directory_commands(&context.directory_state.pwd, FREQUENT_COMMANDS_IN_CONTEXT);
+        context
+    }))
+}
+
+#[tauri::command]
+pub async fn get_directory_commands(
+    path: String,
+    limit: Option<usize>,
+    context_manager: State<'_, ExecutionContextManager>,
+) -> Result<Vec<DirectoryCommandStats>, String> {
+    let manager = context_manager.lock().map_err(|e| e.to_string())?;
+    Ok(manager.get_directory_commands(&path, limit.unwrap_or(FREQUENT_COMMANDS_IN_CONTEXT)))
+}
+
+#[tauri::command]
+pub async fn snapshot_environment(
+    env_output: Option<String>,
+    context_manager: State<'_, ExecutionContextManager>,
+) -> Result<String, String> {
+    let mut manager = context_manager.lock().map_err(|e| e.to_string())?;
+    let snapshot = manager.snapshot_environment(env_output.as_deref().map(parse_env_output));
+    Ok(snapshot.id)
+}
+
+#[tauri::command]
+pub async fn diff_environment(
+    snapshot_id: String,
+    env_output: Option<String>,
+    context_manager: State<'_, ExecutionContextManager>,
+    security_manager: State<'_, Arc<tokio::sync::Mutex<crate::security::SecurityManager>>>,
+) -> Result<EnvironmentDiff, String> {
+    let mut diff = {
+        let manager = context_manager.lock().map_err(|e| e.to_string())?;
+        manager.diff_environment(&snapshot_id, env_output.as_deref().map(parse_env_output))?
+    };
+    let security = security_manager.lock().await;
+    diff.redact(|name, value| security.is_secret_variable(name, value));
+    Ok(diff)
 }
 
 #[tauri::command]
@@ -440,29 +1139,35 @@ pub async fn add_directory_bookmark(
     context_manager: State<'_, ExecutionContextManager>,
 ) -> Result<(), String> {
     let mut manager = context_manager.lock().map_err(|e| e.to_string())?;
-    if let Some(context) = manager.contexts.get_mut(&session_id) {
-        let bookmark = DirectoryBookmark {
-            name,
-            path,
-            created_at: Utc::now(),
-            tags,
-        };
-        context.directory_state.bookmarks.push(bookmark);
-    }
-    Ok(())
+    let path = manager.resolve_session_path(&session_id, &path);
+    manager.add_bookmark(name, path, tags)
 }
 
 #[tauri::command]
 pub async fn get_directory_bookmarks(
-    session_id: String,
     context_manager: State<'_, ExecutionContextManager>,
 ) -> Result<Vec<DirectoryBookmark>, String> {
     let manager = context_manager.lock().map_err(|e| e.to_string())?;
-    if let Some(context) = manager.get_context(&session_id) {
-        Ok(context.directory_state.bookmarks.clone())
-    } else {
-        Ok(Vec::new())
-    }
+    Ok(manager.bookmarks.clone())
+}
+
+#[tauri::command]
+pub async fn jump_to_bookmark(
+    query: String,
+    context_manager: State<'_, ExecutionContextManager>,
+) -> Result<Option<DirectoryBookmark>, String> {
+    let mut manager = context_manager.lock().map_err(|e| e.to_string())?;
+    manager.jump_to_bookmark(&query)
+}
+
+#[tauri::command]
+pub async fn get_bookmark_suggestions(
+    query: String,
+    limit: Option<usize>,
+    context_manager: State<'_, ExecutionContextManager>,
+) -> Result<Vec<BookmarkSuggestion>, String> {
+    let manager = context_manager.lock().map_err(|e| e.to_string())?;
+    Ok(manager.get_bookmark_suggestions(&query, limit.unwrap_or(DEFAULT_BOOKMARK_SUGGESTIONS)))
 }
 
 #[tauri::command]
@@ -478,9 +1183,206 @@ pub async fn update_current_directory(
         
         // Add to recent directories
         if !context.directory_state.recent_directories.contains(&new_path) {
-            context.directory_state.recent_directories.insert(0, new_path);
+            context.directory_state.recent_directories.insert(0, new_path.clone());
             context.directory_state.recent_directories.truncate(20); // Keep last 20
         }
     }
-    Ok(())
+    manager.record_directory_visit(&new_path);
+    manager.record_bookmark_visit(&new_path)
+}
+
+#[tauri::command]
+pub async fn jump_frecent(
+    query: String,
+    context_manager: State<'_, ExecutionContextManager>,
+) -> Result<Option<String>, String> {
+    let manager = context_manager.lock().map_err(|e| e.to_string())?;
+    Ok(manager.jump_frecent(&query).map(|path| path.to_string_lossy().to_string()))
+}
+
+#[tauri::command]
+pub async fn get_frecent_directories(
+    query: String,
+    limit: Option<usize>,
+    context_manager: State<'_, ExecutionContextManager>,
+) -> Result<Vec<FrecentDirectory>, String> {
+    let manager = context_manager.lock().map_err(|e| e.to_string())?;
+    let mut matches = manager.frecent_matches(&query);
+    matches.truncate(limit.unwrap_or(DEFAULT_BOOKMARK_SUGGESTIONS));
+    Ok(matches)
+}
+
+#[tauri::command]
+pub async fn get_recent_directories(
+    limit: Option<usize>,
+    context_manager: State<'_, ExecutionContextManager>,
+    fs_manager: State<'_, crate::filesystem_manager::FileSystemManager>,
+) -> Result<Vec<RecentDirectory>, String> {
+    let browsed = fs_manager.lock().map_err(|e| e.to_string())?.recent_paths.clone();
+    let mut manager = context_manager.lock().map_err(|e| e.to_string())?;
+    Ok(manager.get_recent_directories(&browsed, limit.unwrap_or(DEFAULT_RECENT_DIRECTORIES)))
+}
+
+/// Quick jump: the `index`-th entry (default first) of the jump list, optionally
+/// narrowed by a fuzzy `query`. The jump counts as a visit.
+#[tauri::command]
+pub async fn jump_to_recent_directory(
+    query: Option<String>,
+    index: Option<usize>,
+    context_manager: State<'_, ExecutionContextManager>,
+    fs_manager: State<'_, crate::filesystem_manager::FileSystemManager>,
+) -> Result<Option<String>, String> {
+    let browsed = fs_manager.lock().map_err(|e| e.to_string())?.recent_paths.clone();
+    let mut manager = context_manager.lock().map_err(|e| e.to_string())?;
+    let directories = manager.get_recent_directories(&browsed, usize::MAX);
+    let query = query.unwrap_or_default();
+    let mut candidates: Vec<(i64, RecentDirectory)> = directories
+        .into_iter()
+        .filter_map(|dir| {
+            if query.trim().is_empty() {
+                return Some((0, dir));
+            }
+            let leaf = dir.path.rsplit(['/', '\\']).find(|s| !s.is_empty()).unwrap_or(&dir.path).to_string();
+            fuzzy_score(&query, &leaf).or_else(|| fuzzy_score(&query, &dir.path).map(|score| score / 2)).map(|score| (score, dir))
+        })
+        .collect();
+    // Stable, so equal fuzzy scores keep their jump-list order
+    candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.0));
+    let Some((_, target)) = candidates.into_iter().nth(index.unwrap_or(0)) else {
+        return Ok(None);
+    };
+    manager.record_directory_visit(&target.path);
+    Ok(Some(target.path))
+}
+
+/// Import an existing zoxide database by asking the `zoxide` binary for its scores.
+#[tauri::command]
+pub async fn import_zoxide_database(
+    context_manager: State<'_, ExecutionContextManager>,
+) -> Result<usize, String> {
+    let output = std::process::Command::new("zoxide")
+        .args(["query", "--list", "--score"])
+        .output()
+        .map_err(|e| format!("Failed to run zoxide: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    let listing = String::from_utf8_lossy(&output.stdout).to_string();
+    let mut manager = context_manager.lock().map_err(|e| e.to_string())?;
+    manager.import_zoxide_scores(&listing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn relative_bookmark_paths_resolve_against_the_session_directory() {
+        let mut state = ExecutionContextState::new();
+        state.create_context("s1".to_string()).unwrap();
+        state.contexts.get_mut("s1").unwrap().directory_state.pwd = "/home/user/src/app".to_string();
+
+        assert_eq!(state.resolve_session_path("s1", "."), "/home/user/src/app");
+        assert_eq!(state.resolve_session_path("s1", "../lib/"), "/home/user/src/lib");
+        assert_eq!(state.resolve_session_path("s1", "/etc"), "/etc");
+        assert_eq!(state.resolve_session_path("unknown", "docs"), "docs");
+    }
+
+    #[test]
+    fn directory_visits_are_ranked_and_left_for_the_next_flush() {
+        let mut state = ExecutionContextState::new();
+        state.frecent_directories.clear();
+        state.frecent_directories_dirty = false;
+
+        state.record_directory_visit("/srv/app/");
+        state.record_directory_visit("/srv/app");
+        state.record_directory_visit("");
+        assert!(state.frecent_directories_dirty);
+        let key = normalize_directory("/srv/app");
+        assert_eq!(state.frecent_directories.len(), 1);
+        assert_eq!(state.frecent_directories[&key].rank, 2.0);
+    }
+
+    fn frecent(path: &str, rank: f64, last_accessed: DateTime<Utc>) -> FrecentDirectory {
+        FrecentDirectory { path: path.to_string(), rank, last_accessed }
+    }
+
+    #[test]
+    fn frecency_scores_decay_with_time_since_the_last_visit() {
+        let now = Utc::now();
+        let at = |age: chrono::Duration| frecent("/srv/app", 3.0, now - age).score(now);
+
+        assert_eq!(at(chrono::Duration::minutes(30)), 12.0);
+        assert_eq!(at(chrono::Duration::hours(5)), 6.0);
+        assert_eq!(at(chrono::Duration::days(3)), 1.5);
+        assert_eq!(at(chrono::Duration::weeks(3)), 0.75);
+
+        // A fresh single visit outranks a stale habit
+        assert!(frecent("/a", 1.0, now).score(now) > frecent("/b", 3.0, now - chrono::Duration::days(2)).score(now));
+    }
+
+    #[test]
+    fn aging_scales_ranks_below_the_limit_and_forgets_rare_directories() {
+        let mut state = ExecutionContextState::new();
+        let now = Utc::now();
+        state.frecent_directories = [
+            ("/srv/busy".to_string(), frecent("/srv/busy", 9_000.0, now)),
+            ("/srv/steady".to_string(), frecent("/srv/steady", 999.0, now)),
+            ("/srv/rare".to_string(), frecent("/srv/rare", 1.05, now)),
+        ]
+        .into_iter()
+        .collect();
+
+        // 9000 + 1000 + 1.05 tips the total over 10,000
+        state.record_directory_visit("/srv/steady");
+        let factor = 0.9 * FRECENCY_MAX_AGE / 10_001.05;
+        assert!(!state.frecent_directories.contains_key("/srv/rare"));
+        assert!((state.frecent_directories["/srv/busy"].rank - 9_000.0 * factor).abs() < 1e-9);
+        assert!((state.frecent_directories["/srv/steady"].rank - 1_000.0 * factor).abs() < 1e-9);
+        let total: f64 = state.frecent_directories.values().map(|d| d.rank).sum();
+        assert!(total < 0.9 * FRECENCY_MAX_AGE);
+
+        // Below the limit nothing is rescaled
+        state.record_directory_visit("/srv/busy");
+        assert!((state.frecent_directories["/srv/busy"].rank - (9_000.0 * factor + 1.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn keywords_match_in_order_with_the_last_in_the_final_component() {
+        let dir = frecent("/home/user/Projects/terminal-app", 1.0, Utc::now());
+        let keywords = |query: &str| query.split_whitespace().map(|k| k.to_lowercase()).collect::<Vec<_>>();
+
+        assert!(dir.matches(&keywords("")));
+        assert!(dir.matches(&keywords("proj term")));
+        assert!(dir.matches(&keywords("APP")));
+        assert!(!dir.matches(&keywords("term proj")));
+        assert!(!dir.matches(&keywords("projects")));
+    }
+
+    #[test]
+    fn jumping_picks_the_highest_scoring_existing_match() {
+        let root = std::env::temp_dir().join(format!("frecency-jump-{}", std::process::id()));
+        let (old, recent) = (root.join("old/api"), root.join("recent/api"));
+        std::fs::create_dir_all(&old).unwrap();
+        std::fs::create_dir_all(&recent).unwrap();
+        let key = |path: &std::path::Path| normalize_directory(&path.to_string_lossy());
+        let now = Utc::now();
+
+        let mut state = ExecutionContextState::new();
+        state.frecent_directories = [
+            frecent(&key(&old), 20.0, now - chrono::Duration::weeks(2)),
+            frecent(&key(&recent), 2.0, now),
+            frecent(&key(&root.join("gone/api")), 100.0, now),
+        ]
+        .into_iter()
+        .map(|dir| (dir.path.clone(), dir))
+        .collect();
+
+        // 20 * 0.25 loses to 2 * 4, and the missing directory is never offered
+        assert_eq!(state.jump_frecent("api"), Some(recent.clone()));
+        assert_eq!(state.jump_frecent("old api"), Some(old.clone()));
+        assert_eq!(state.jump_frecent("nothing-here"), None);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
 }
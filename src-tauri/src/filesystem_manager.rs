@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::path::{Path, PathBuf};
 use std::fs::{self, Metadata};
 use std::time::SystemTime;
@@ -109,6 +109,23 @@ pub struct FileOperation {
     pub completed_at: Option<DateTime<Utc>>,
     pub error: Option<String>,
     pub can_resume: bool,
+    #[serde(default)]
+    pub delete_mode: DeleteMode,
+    /// Paths moved to the OS trash by a `Trash` delete, kept so the UI can offer undo
+    #[serde(default)]
+    pub trashed_paths: Vec<String>,
+    /// Paths a Copy or Move created at the destination, in `source` order
+    #[serde(default)]
+    pub created_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum DeleteMode {
+    /// Move to the OS trash / recycle bin so the files can be recovered
+    #[default]
+    Trash,
+    /// Remove for good
+    Permanent,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -141,6 +158,25 @@ pub struct FileWatcher {
     pub events: Vec<WatchEventType>,
     pub created_at: DateTime<Utc>,
     pub active: bool,
+    #[serde(default)]
+    pub confirmation: ChangeConfirmation,
+    #[serde(default)]
+    pub debounce_ms: u64,
+}
+
+/// How a watcher decides that a `Modified` notification is a real change.
+/// Editors often touch or rewrite files without changing them, and notify
+/// reports attribute-only and duplicate events; confirmed watchers drop those.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum ChangeConfirmation {
+    /// Emit every notification as-is
+    #[default]
+    None,
+    /// Require the size or modification time to change
+    Metadata,
+    /// Compare a content hash for files up to `max_bytes`, metadata beyond that
+    ContentHash { max_bytes: u64 },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -222,6 +258,9 @@ pub type FileSystemManager = Arc<Mutex<FileSystemState>>;
 pub struct FileSystemState {
     pub operations: HashMap<String, FileOperation>,
     pub watchers: HashMap<String, FileWatcher>,
+    active_watchers: HashMap<String, notify::RecommendedWatcher>, // dropping one stops it
+    undo_history: VecDeque<String>, // ids of completed, reversible operations, oldest first
+    running_operations: HashMap<String, Arc<Mutex<FileOperation>>>, // live records of background operations
     pub watch_tx: Option<broadcast::Sender<FileWatchEvent>>,
     pub recent_paths: Vec<String>,
     pub bookmarks: Vec<PathBookmark>,
@@ -253,6 +292,9 @@ impl FileSystemState {
         Self {
             operations: HashMap::new(),
             watchers: HashMap::new(),
+            active_watchers: HashMap::new(),
+            undo_history: VecDeque::new(),
+            running_operations: HashMap::new(),
             watch_tx: Some(watch_tx),
             recent_paths: Vec::new(),
             bookmarks: Vec::new(),
@@ -348,6 +390,7 @@ impl FileSystemState {
         operation_type: OperationType,
         source: Vec<String>,
         destination: Option<String>,
+        delete_mode: DeleteMode,
     ) -> String {
         let operation_id = uuid::Uuid::new_v4().to_string();
         
@@ -369,6 +412,9 @@ impl FileSystemState {
             completed_at: None,
             error: None,
             can_resume: false,
+            delete_mode,
+            trashed_paths: Vec::new(),
+            created_paths: Vec::new(),
         };
 
         self.operations.insert(operation_id.clone(), operation);
@@ -379,19 +425,120 @@ impl FileSystemState {
         if let Some(operation) = self.operations.get_mut(operation_id) {
             operation.status = OperationStatus::Running;
             operation.started_at = Utc::now();
-            // In a real implementation, this would spawn an async task
-            Ok(())
+            let result = match operation.operation_type {
+                OperationType::Delete => run_delete(operation),
+                OperationType::Copy | OperationType::Move => run_transfer(operation),
+                OperationType::Archive | OperationType::Compress | OperationType::Extract => {
+                    // Archives can be large, so they run in the background and report
+                    // progress through a shared record picked up by sync_running_operations
+                    let live = Arc::new(Mutex::new(operation.clone()));
+                    self.running_operations.insert(operation_id.to_string(), live.clone());
+                    std::thread::spawn(move || run_archive_operation(&live));
+                    return Ok(());
+                }
+                // In a real implementation, this would spawn an async task
+                _ => return Ok(()),
+            };
+            let reversible = !(matches!(operation.operation_type, OperationType::Delete) && operation.delete_mode == DeleteMode::Permanent);
+            if result.is_ok() && reversible {
+                self.undo_history.push_back(operation_id.to_string());
+                if self.undo_history.len() > MAX_UNDO_HISTORY {
+                    self.undo_history.pop_front();
+                }
+            }
+            result
         } else {
             Err("Operation not found".to_string())
         }
     }
 
+    /// Copy the latest state of background operations into `operations`,
+    /// dropping the live records of those that have finished.
+    pub fn sync_running_operations(&mut self) {
+        let mut finished = Vec::new();
+        for (id, live) in &self.running_operations {
+            let snapshot = live.lock().unwrap().clone();
+            if !matches!(snapshot.status, OperationStatus::Running) {
+                finished.push(id.clone());
+            }
+            self.operations.insert(id.clone(), snapshot);
+        }
+        for id in finished {
+            self.running_operations.remove(&id);
+        }
+    }
+
+    pub fn get_undo_history(&self) -> Vec<FileOperation> {
+        self.undo_history.iter().rev().filter_map(|id| self.operations.get(id).cloned()).collect()
+    }
+
+    pub fn undo_last_operation(&mut self) -> Result<FileOperation, String> {
+        let operation_id = self.undo_history.back().cloned().ok_or("Nothing to undo")?;
+        self.undo_operation(&operation_id)
+    }
+
+    /// Reverse a completed operation: moves go back, copies are removed and
+    /// trashed files are restored. Checked up front so a reverse that can't be
+    /// done cleanly changes nothing.
+    pub fn undo_operation(&mut self, operation_id: &str) -> Result<FileOperation, String> {
+        let position = self.undo_history.iter().position(|id| id == operation_id)
+            .ok_or("Operation is not in the undo history")?;
+        let operation = self.operations.get(operation_id).cloned()
+            .ok_or("Operation not found")?;
+
+        match operation.operation_type {
+            OperationType::Move => {
+                for (source, created) in operation.source.iter().zip(&operation.created_paths) {
+                    if Path::new(source).exists() {
+                        return Err(format!("Cannot move {} back: {} is occupied", created, source));
+                    }
+                    if !Path::new(created).exists() {
+                        return Err(format!("Cannot move back: {} no longer exists", created));
+                    }
+                }
+                for (source, created) in operation.source.iter().zip(&operation.created_paths) {
+                    move_path(Path::new(created), Path::new(source))?;
+                }
+            }
+            OperationType::Copy => {
+                for created in &operation.created_paths {
+                    let target = Path::new(created);
+                    let removed = if target.is_dir() && !target.is_symlink() {
+                        fs::remove_dir_all(target)
+                    } else {
+                        fs::remove_file(target)
+                    };
+                    match removed {
+                        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                            return Err(format!("Failed to remove copy {}: {}", created, e));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            OperationType::Delete => {
+                if let Some(occupied) = operation.trashed_paths.iter().find(|path| Path::new(path).exists()) {
+                    return Err(format!("Cannot restore {}: the path is occupied", occupied));
+                }
+                restore_from_trash(&operation.trashed_paths)?;
+            }
+            _ => return Err("This operation can't be undone".to_string()),
+        }
+
+        self.undo_history.remove(position);
+        Ok(operation)
+    }
+
     pub fn create_watcher(
         &mut self,
         path: String,
         recursive: bool,
         events: Vec<WatchEventType>,
+        confirmation: ChangeConfirmation,
+        debounce_ms: u64,
     ) -> Result<String, String> {
+        use notify::Watcher;
+
         let watcher_id = uuid::Uuid::new_v4().to_string();
         
         let watcher = FileWatcher {
@@ -401,16 +548,37 @@ impl FileSystemState {
             events,
             created_at: Utc::now(),
             active: true,
+            confirmation,
+            debounce_ms,
         };
 
+        let watch_tx = self.watch_tx.clone().ok_or("File watching is unavailable")?;
+        let (raw_tx, raw_rx) = std::sync::mpsc::channel();
+        let mut notify_watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if let Ok(event) = event {
+                let _ = raw_tx.send(event);
+            }
+        }).map_err(|e| format!("Failed to create watcher: {}", e))?;
+        let mode = if recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+        notify_watcher.watch(Path::new(&path), mode)
+            .map_err(|e| format!("Failed to watch {}: {}", path, e))?;
+
+        let config = watcher.clone();
+        std::thread::spawn(move || run_watch_loop(config, raw_rx, watch_tx));
+
+        self.active_watchers.insert(watcher_id.clone(), notify_watcher);
         self.watchers.insert(watcher_id.clone(), watcher);
         
-        // In a real implementation, this would create an actual file watcher
-        // using the notify crate and send events to the broadcast channel
-        
         Ok(watcher_id)
     }
 
+    pub fn remove_watcher(&mut self, watcher_id: &str) -> Result<(), String> {
+        self.active_watchers.remove(watcher_id);
+        self.watchers.remove(watcher_id)
+            .map(|_| ())
+            .ok_or_else(|| "Watcher not found".to_string())
+    }
+
     pub fn get_path_completions(&self, partial_path: &str, limit: usize) -> Vec<PathCompletion> {
         let mut completions = Vec::new();
         
@@ -860,6 +1028,483 @@ impl FileSystemState {
 }
 
 // Implementation for EntryType Display trait for sorting
+const MAX_UNDO_HISTORY: usize = 50;
+
+// Archive and Compress pack `source` into the archive at `destination`; Extract
+// unpacks each source archive into `destination` (default: beside the archive).
+fn run_archive_operation(live: &Mutex<FileOperation>) {
+    let operation = live.lock().unwrap().clone();
+    let update = |progress: crate::archive::ArchiveProgress| {
+        let mut operation = live.lock().unwrap();
+        operation.bytes_processed = progress.bytes;
+        operation.total_bytes = progress.total_bytes;
+        operation.files_processed = progress.files;
+        operation.total_files = progress.total_files.max(progress.files);
+        if progress.total_bytes > 0 {
+            operation.progress = (progress.bytes as f64 / progress.total_bytes as f64).min(1.0);
+        }
+    };
+
+    let result = match operation.operation_type {
+        OperationType::Extract => operation.source.iter().try_for_each(|source| {
+            let archive = Path::new(source);
+            let format = crate::archive::detect_format(archive)
+                .ok_or_else(|| format!("{} is not a .zip, .tar.gz or .gz archive", source))?;
+            let destination = match &operation.destination {
+                Some(destination) => PathBuf::from(destination),
+                None => archive.parent().map(Path::to_path_buf).unwrap_or_default(),
+            };
+            crate::archive::extract(archive, &destination, format, &mut |progress| update(progress))
+        }),
+        _ => operation.destination.as_deref()
+            .ok_or_else(|| "Creating an archive needs a destination file".to_string())
+            .and_then(|destination| {
+                let destination = Path::new(destination);
+                let format = crate::archive::detect_format(destination)
+                    .ok_or_else(|| format!("{} should end in .zip, .tar.gz or .gz", destination.display()))?;
+                crate::archive::create(&operation.source, destination, format, &mut |progress| update(progress))
+            }),
+    };
+
+    let mut operation = live.lock().unwrap();
+    operation.completed_at = Some(Utc::now());
+    match result {
+        Ok(()) => {
+            operation.status = OperationStatus::Completed;
+            operation.progress = 1.0;
+        }
+        Err(e) => {
+            operation.status = OperationStatus::Failed;
+            operation.error = Some(e);
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+fn move_to_trash(path: &str) -> Result<(), String> {
+    trash::delete(path).map_err(|e| format!("Could not move {} to the trash: {}", path, e))
+}
+
+#[cfg(any(target_os = "android", target_os = "ios"))]
+fn move_to_trash(path: &str) -> Result<(), String> {
+    Err(format!("Could not move {} to the trash: this platform has no trash", path))
+}
+
+// Listing and restoring trash items isn't available on macOS in the trash crate
+#[cfg(all(not(target_os = "macos"), not(any(target_os = "android", target_os = "ios"))))]
+fn restore_from_trash(paths: &[String]) -> Result<(), String> {
+    let items = trash::os_limited::list().map_err(|e| format!("Failed to read the trash: {}", e))?;
+    let mut restore = Vec::new();
+    for path in paths {
+        // The most recent deletion of a path is the one this operation made
+        let item = items.iter()
+            .filter(|item| item.original_path() == Path::new(path))
+            .max_by_key(|item| item.time_deleted)
+            .ok_or_else(|| format!("{} is no longer in the trash", path))?;
+        restore.push(item.clone());
+    }
+    trash::os_limited::restore_all(restore).map_err(|e| format!("Failed to restore from the trash: {}", e))
+}
+
+#[cfg(any(target_os = "macos", target_os = "android", target_os = "ios"))]
+fn restore_from_trash(_paths: &[String]) -> Result<(), String> {
+    Err("Restoring from the trash isn't supported on this platform; use the system trash to put the files back".to_string())
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> std::io::Result<()> {
+    if from.is_dir() && !from.is_symlink() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+// rename, or copy-then-delete when the destination is on another filesystem
+fn move_path(from: &Path, to: &Path) -> Result<(), String> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_recursive(from, to).map_err(|e| format!("Failed to move {}: {}", from.display(), e))?;
+    let removed = if from.is_dir() && !from.is_symlink() { fs::remove_dir_all(from) } else { fs::remove_file(from) };
+    removed.map_err(|e| format!("Moved {} but failed to remove the original: {}", from.display(), e))
+}
+
+// Copy or move each source into the destination directory, or onto the
+// destination path itself for a single source that isn't going into a directory.
+fn run_transfer(operation: &mut FileOperation) -> Result<(), String> {
+    let destination = operation.destination.clone().ok_or("Copy and move need a destination")?;
+    let destination = Path::new(&destination);
+    let into_directory = operation.source.len() > 1 || destination.is_dir();
+    let is_move = matches!(operation.operation_type, OperationType::Move);
+
+    let sources = operation.source.clone();
+    for (index, source) in sources.iter().enumerate() {
+        let source_path = Path::new(source);
+        let target = if into_directory {
+            destination.join(source_path.file_name().unwrap_or_default())
+        } else {
+            destination.to_path_buf()
+        };
+
+        let result = if target.exists() {
+            Err(format!("{} already exists", target.display()))
+        } else if is_move {
+            move_path(source_path, &target)
+        } else {
+            copy_recursive(source_path, &target).map_err(|e| format!("Failed to copy {}: {}", source, e))
+        };
+
+        if let Err(e) = result {
+            operation.status = OperationStatus::Failed;
+            operation.error = Some(e.clone());
+            operation.completed_at = Some(Utc::now());
+            return Err(e);
+        }
+        operation.created_paths.push(target.to_string_lossy().to_string());
+        operation.files_processed = index + 1;
+        operation.progress = (index + 1) as f64 / sources.len() as f64;
+    }
+
+    operation.bytes_processed = operation.total_bytes;
+    operation.progress = 1.0;
+    operation.status = OperationStatus::Completed;
+    operation.completed_at = Some(Utc::now());
+    Ok(())
+}
+
+// Deletes each source in turn, stopping at the first failure. A failed trash
+// never falls back to a permanent delete; the caller has to ask for that.
+/// Makes a path absolute without resolving the last component, so a symlink
+/// stays the link rather than becoming whatever it points at.
+fn absolute_path(path: &str) -> String {
+    let target = Path::new(path);
+    let parent = match target.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    match (fs::canonicalize(parent), target.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name).to_string_lossy().to_string(),
+        _ => path.to_string(),
+    }
+}
+
+fn run_delete(operation: &mut FileOperation) -> Result<(), String> {
+    let sources = operation.source.clone();
+    // The trash remembers absolute original paths, which is what undo looks up
+    let absolute_paths: Vec<String> = sources.iter().map(|path| absolute_path(path)).collect();
+    for (index, path) in sources.iter().enumerate() {
+        let result = match operation.delete_mode {
+            DeleteMode::Trash => move_to_trash(path)
+                .map_err(|e| format!("{}. Choose a permanent delete to remove it without the trash.", e)),
+            DeleteMode::Permanent => {
+                let target = Path::new(path);
+                let removed = if target.is_dir() && !target.is_symlink() {
+                    fs::remove_dir_all(target)
+                } else {
+                    fs::remove_file(target)
+                };
+                removed.map_err(|e| format!("Failed to delete {}: {}", path, e))
+            }
+        };
+
+        if let Err(e) = result {
+            operation.status = OperationStatus::Failed;
+            operation.error = Some(e.clone());
+            operation.completed_at = Some(Utc::now());
+            return Err(e);
+        }
+        if operation.delete_mode == DeleteMode::Trash {
+            operation.trashed_paths.push(absolute_paths[index].clone());
+        }
+        operation.files_processed = index + 1;
+        operation.progress = (index + 1) as f64 / sources.len() as f64;
+    }
+
+    operation.bytes_processed = operation.total_bytes;
+    operation.progress = 1.0;
+    operation.status = OperationStatus::Completed;
+    operation.completed_at = Some(Utc::now());
+    Ok(())
+}
+
+/// SHA-256 of a file's content, streamed so large files aren't read into memory.
+fn content_digest(path: &Path) -> std::io::Result<String> {
+    use sha2::{Digest, Sha256};
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub size: u64,
+    pub hash: String,
+    pub paths: Vec<String>,
+    pub wasted_bytes: u64, // size * (copies - 1)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateScanProgress {
+    pub phase: String, // "scanning", then "hashing"
+    pub files_scanned: usize,
+    pub candidates: usize,
+    pub files_hashed: usize,
+    pub bytes_hashed: u64,
+    pub bytes_to_hash: u64,
+}
+
+/// Group files under `base_path` with identical content, largest waste first.
+/// Files are bucketed by size and only same-size files are hashed. Symlinks
+/// are skipped, as are extra hard links to an already-seen file.
+pub fn scan_duplicates(
+    base_path: &str,
+    min_size: u64,
+    max_depth: Option<usize>,
+    respect_gitignore: bool,
+    progress: &mut dyn FnMut(&DuplicateScanProgress),
+) -> Result<Vec<DuplicateGroup>, String> {
+    if !Path::new(base_path).is_dir() {
+        return Err(format!("{} is not a directory", base_path));
+    }
+    let mut state = DuplicateScanProgress {
+        phase: "scanning".to_string(),
+        files_scanned: 0,
+        candidates: 0,
+        files_hashed: 0,
+        bytes_hashed: 0,
+        bytes_to_hash: 0,
+    };
+
+    let walker = ignore::WalkBuilder::new(base_path)
+        .standard_filters(false)
+        .git_ignore(respect_gitignore)
+        .git_exclude(respect_gitignore)
+        .git_global(respect_gitignore)
+        .parents(respect_gitignore)
+        .max_depth(max_depth)
+        .build();
+
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    #[cfg(unix)]
+    let mut seen_inodes = std::collections::HashSet::new();
+    for entry in walker.flatten() {
+        if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else { continue };
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if !seen_inodes.insert((metadata.dev(), metadata.ino())) {
+                continue;
+            }
+        }
+        state.files_scanned += 1;
+        if state.files_scanned.is_multiple_of(500) {
+            progress(&state);
+        }
+        if metadata.len() >= min_size.max(1) {
+            by_size.entry(metadata.len()).or_default().push(entry.into_path());
+        }
+    }
+
+    let candidates: Vec<(u64, Vec<PathBuf>)> = by_size.into_iter().filter(|(_, paths)| paths.len() > 1).collect();
+    state.phase = "hashing".to_string();
+    state.candidates = candidates.iter().map(|(_, paths)| paths.len()).sum();
+    state.bytes_to_hash = candidates.iter().map(|(size, paths)| size * paths.len() as u64).sum();
+    progress(&state);
+
+    let mut groups = Vec::new();
+    for (size, paths) in candidates {
+        let mut by_hash: HashMap<String, Vec<String>> = HashMap::new();
+        for path in paths {
+            // Files that vanish or can't be read mid-scan just drop out
+            if let Ok(hash) = content_digest(&path) {
+                by_hash.entry(hash).or_default().push(path.to_string_lossy().to_string());
+            }
+            state.files_hashed += 1;
+            state.bytes_hashed += size;
+            progress(&state);
+        }
+        for (hash, mut paths) in by_hash {
+            if paths.len() > 1 {
+                paths.sort();
+                let wasted_bytes = size * (paths.len() as u64 - 1);
+                groups.push(DuplicateGroup { size, hash, paths, wasted_bytes });
+            }
+        }
+    }
+
+    groups.sort_by(|a, b| b.wasted_bytes.cmp(&a.wasted_bytes).then_with(|| a.paths.cmp(&b.paths)));
+    Ok(groups)
+}
+
+// Cap on files fingerprinted up front, so watching a huge tree stays cheap
+const MAX_BASELINE_FILES: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq)]
+struct FileFingerprint {
+    size: u64,
+    modified: Option<SystemTime>,
+    hash: Option<String>,
+}
+
+fn fingerprint(path: &Path, confirmation: &ChangeConfirmation) -> Option<FileFingerprint> {
+    let metadata = fs::metadata(path).ok().filter(|m| m.is_file())?;
+    let hash = match confirmation {
+        ChangeConfirmation::ContentHash { max_bytes } if metadata.len() <= *max_bytes => Some(content_digest(path).ok()?),
+        _ => None,
+    };
+    Some(FileFingerprint { size: metadata.len(), modified: metadata.modified().ok(), hash })
+}
+
+impl FileFingerprint {
+    // With a content hash on both sides only the content matters; otherwise size+mtime
+    fn changed_from(&self, previous: &FileFingerprint) -> bool {
+        match (&self.hash, &previous.hash) {
+            (Some(current), Some(previous)) => current != previous,
+            _ => self.size != previous.size || self.modified != previous.modified,
+        }
+    }
+}
+
+fn collect_baseline(root: &Path, recursive: bool, confirmation: &ChangeConfirmation, baseline: &mut HashMap<PathBuf, FileFingerprint>) {
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            if baseline.len() >= MAX_BASELINE_FILES {
+                return;
+            }
+            let path = entry.path();
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => {
+                    if recursive {
+                        pending.push(path);
+                    }
+                }
+                Ok(_) => {
+                    if let Some(print) = fingerprint(&path, confirmation) {
+                        baseline.insert(path, print);
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+fn watch_event_type(kind: &EventKind) -> Option<WatchEventType> {
+    match kind {
+        EventKind::Create(_) => Some(WatchEventType::Created),
+        EventKind::Modify(notify::event::ModifyKind::Name(_)) => Some(WatchEventType::Moved),
+        EventKind::Modify(_) => Some(WatchEventType::Modified),
+        EventKind::Remove(_) => Some(WatchEventType::Deleted),
+        _ => None,
+    }
+}
+
+fn wants_event(watcher: &FileWatcher, event_type: &WatchEventType) -> bool {
+    watcher.events.is_empty() || watcher.events.iter().any(|wanted| {
+        matches!(wanted, WatchEventType::All) || std::mem::discriminant(wanted) == std::mem::discriminant(event_type)
+    })
+}
+
+// Coalesce notify events per path for `debounce_ms`, then confirm modifications
+// against the last fingerprint seen before broadcasting. Ends when the notify
+// watcher is dropped.
+fn run_watch_loop(watcher: FileWatcher, raw_rx: std::sync::mpsc::Receiver<Event>, watch_tx: broadcast::Sender<FileWatchEvent>) {
+    use std::sync::mpsc::RecvTimeoutError;
+    use std::time::{Duration, Instant};
+
+    let mut baseline = HashMap::new();
+    if watcher.confirmation != ChangeConfirmation::None {
+        collect_baseline(Path::new(&watcher.path), watcher.recursive, &watcher.confirmation, &mut baseline);
+    }
+    let debounce = Duration::from_millis(watcher.debounce_ms);
+    let mut pending: HashMap<PathBuf, (WatchEventType, Option<String>, Instant)> = HashMap::new();
+    let mut connected = true;
+
+    while connected || !pending.is_empty() {
+        let next_due = pending.values().map(|(_, _, at)| *at + debounce).min();
+        let received = match next_due {
+            _ if !connected => Err(RecvTimeoutError::Timeout),
+            Some(due) => raw_rx.recv_timeout(due.saturating_duration_since(Instant::now())),
+            None => raw_rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match received {
+            Ok(event) => {
+                let Some(event_type) = watch_event_type(&event.kind) else { continue };
+                let now = Instant::now();
+                if matches!(event_type, WatchEventType::Moved) && event.paths.len() == 2 {
+                    // Supersedes the separate from/to notifications some backends also send
+                    let old_path = event.paths[0].to_string_lossy().to_string();
+                    pending.remove(&event.paths[0]);
+                    baseline.remove(&event.paths[0]);
+                    pending.insert(event.paths[1].clone(), (WatchEventType::Moved, Some(old_path), now));
+                    continue;
+                }
+                for path in event.paths {
+                    let merged = match (pending.remove(&path), event_type.clone()) {
+                        // A file created and then written within the window is still just created
+                        (Some((WatchEventType::Created, _, _)), WatchEventType::Modified) => (WatchEventType::Created, None),
+                        (Some((WatchEventType::Moved, old_path, _)), WatchEventType::Modified) => (WatchEventType::Moved, old_path),
+                        (_, event_type) => (event_type, None),
+                    };
+                    pending.insert(path, (merged.0, merged.1, now));
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => connected = false,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+
+        let now = Instant::now();
+        let due: Vec<PathBuf> = pending.iter()
+            .filter(|(_, (_, _, at))| !connected || *at + debounce <= now)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in due {
+            let Some((event_type, old_path, _)) = pending.remove(&path) else { continue };
+            if watcher.confirmation != ChangeConfirmation::None {
+                match event_type {
+                    WatchEventType::Deleted => {
+                        baseline.remove(&path);
+                    }
+                    _ => {
+                        let current = fingerprint(&path, &watcher.confirmation);
+                        let unchanged = matches!(event_type, WatchEventType::Modified)
+                            && matches!((&current, baseline.get(&path)), (Some(current), Some(previous)) if !current.changed_from(previous));
+                        match current {
+                            Some(current) => { baseline.insert(path.clone(), current); }
+                            None => { baseline.remove(&path); }
+                        }
+                        if unchanged {
+                            continue;
+                        }
+                    }
+                }
+            }
+            if !wants_event(&watcher, &event_type) {
+                continue;
+            }
+            let _ = watch_tx.send(FileWatchEvent {
+                watcher_id: watcher.id.clone(),
+                event_type,
+                path: path.to_string_lossy().to_string(),
+                old_path,
+                timestamp: Utc::now(),
+            });
+        }
+    }
+}
+
 impl std::fmt::Display for EntryType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -922,10 +1567,11 @@ pub async fn create_file_operation(
     operation_type: OperationType,
     source: Vec<String>,
     destination: Option<String>,
+    delete_mode: Option<DeleteMode>,
     fs_manager: State<'_, FileSystemManager>,
 ) -> Result<String, String> {
     let mut manager = fs_manager.lock().map_err(|e| e.to_string())?;
-    Ok(manager.create_file_operation(operation_type, source, destination))
+    Ok(manager.create_file_operation(operation_type, source, destination, delete_mode.unwrap_or_default()))
 }
 
 #[tauri::command]
@@ -938,10 +1584,36 @@ pub async fn start_file_operation(
 }
 
 #[tauri::command]
-pub async fn get_file_operations(
+pub async fn undo_last_operation(
+    fs_manager: State<'_, FileSystemManager>,
+) -> Result<FileOperation, String> {
+    let mut manager = fs_manager.lock().map_err(|e| e.to_string())?;
+    manager.undo_last_operation()
+}
+
+#[tauri::command]
+pub async fn undo_operation(
+    operation_id: String,
+    fs_manager: State<'_, FileSystemManager>,
+) -> Result<FileOperation, String> {
+    let mut manager = fs_manager.lock().map_err(|e| e.to_string())?;
+    manager.undo_operation(&operation_id)
+}
+
+#[tauri::command]
+pub async fn get_undo_history(
     fs_manager: State<'_, FileSystemManager>,
 ) -> Result<Vec<FileOperation>, String> {
     let manager = fs_manager.lock().map_err(|e| e.to_string())?;
+    Ok(manager.get_undo_history())
+}
+
+#[tauri::command]
+pub async fn get_file_operations(
+    fs_manager: State<'_, FileSystemManager>,
+) -> Result<Vec<FileOperation>, String> {
+    let mut manager = fs_manager.lock().map_err(|e| e.to_string())?;
+    manager.sync_running_operations();
     Ok(manager.operations.values().cloned().collect())
 }
 
@@ -950,10 +1622,48 @@ pub async fn create_file_watcher(
     path: String,
     recursive: bool,
     events: Vec<WatchEventType>,
+    confirmation: Option<ChangeConfirmation>,
+    debounce_ms: Option<u64>,
     fs_manager: State<'_, FileSystemManager>,
 ) -> Result<String, String> {
     let mut manager = fs_manager.lock().map_err(|e| e.to_string())?;
-    manager.create_watcher(path, recursive, events)
+    manager.create_watcher(path, recursive, events, confirmation.unwrap_or_default(), debounce_ms.unwrap_or(0))
+}
+
+#[tauri::command]
+pub async fn remove_file_watcher(
+    watcher_id: String,
+    fs_manager: State<'_, FileSystemManager>,
+) -> Result<(), String> {
+    let mut manager = fs_manager.lock().map_err(|e| e.to_string())?;
+    manager.remove_watcher(&watcher_id)
+}
+
+/// Find duplicate files under `base_path`. Progress arrives as
+/// "duplicate-scan-progress" events while the scan runs.
+#[tauri::command]
+pub async fn find_duplicates(
+    app: tauri::AppHandle,
+    base_path: String,
+    min_size: Option<u64>,
+    max_depth: Option<usize>,
+    respect_gitignore: Option<bool>,
+) -> Result<Vec<DuplicateGroup>, String> {
+    use tauri::Emitter;
+    tokio::task::spawn_blocking(move || {
+        let mut last_emit = std::time::Instant::now();
+        let mut phase = String::new();
+        scan_duplicates(&base_path, min_size.unwrap_or(1), max_depth, respect_gitignore.unwrap_or(true), &mut |progress| {
+            // Throttle to a few events a second, but always report a phase change
+            if progress.phase != phase || last_emit.elapsed() >= std::time::Duration::from_millis(200) {
+                phase = progress.phase.clone();
+                last_emit = std::time::Instant::now();
+                let _ = app.emit("duplicate-scan-progress", progress);
+            }
+        })
+    })
+    .await
+    .map_err(|e| format!("Duplicate scan failed: {}", e))?
 }
 
 #[tauri::command]
@@ -990,3 +1700,26 @@ pub async fn get_path_bookmarks(
     let manager = fs_manager.lock().map_err(|e| e.to_string())?;
     Ok(manager.bookmarks.clone())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn a_deleted_symlink_is_remembered_by_its_own_path() {
+        let dir = std::env::temp_dir().join(format!("warp-delete-link-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("target.txt");
+        let link = dir.join("link.txt");
+        fs::write(&target, "content").unwrap();
+        let _ = fs::remove_file(&link);
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let remembered = absolute_path(&link.to_string_lossy());
+        let canonical_dir = fs::canonicalize(&dir).unwrap();
+        assert_eq!(remembered, canonical_dir.join("link.txt").to_string_lossy());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -4,6 +4,7 @@ mod terminal;
 mod terminal_types;
 mod commands;
 mod shell_hooks;
+mod completions;
 mod search;
 mod ai;
 mod workflows;
@@ -17,13 +18,20 @@ mod execution_context;
 mod shell_integration;
 mod clipboard_manager;
 mod filesystem_manager;
+mod archive;
 mod process_manager;
 mod theme_manager;
 mod network_manager;
 mod dev_tools;
+mod dap;
 mod accessibility;
 mod advanced_terminal;
 mod advanced_commands;
+mod config_bundle;
+mod structured_output;
+mod transcript;
+mod recording;
+mod notifications;
 
 use commands::*;
 use advanced_commands::*;
@@ -35,6 +43,7 @@ use execution_context::*;
 use shell_integration::*;
 use clipboard_manager::*;
 use filesystem_manager::*;
+use config_bundle::*;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tauri::{Manager, Emitter};
@@ -52,58 +61,345 @@ pub fn run() {
             .build(),
         )?;
       }
+      app.handle().plugin(tauri_plugin_notification::init())?;
+      app.handle().plugin(tauri_plugin_opener::init())?;
 
       // Initialize terminal manager
-      let (terminal_manager, output_receiver) = TerminalManager::new();
+      let (terminal_manager, output_receiver, command_event_receiver) = TerminalManager::new();
+      let startup_settings = settings::load_settings().unwrap_or_default();
+      terminal_manager.set_output_guard_limits(pty::OutputGuardLimits::from_settings(&startup_settings));
+      terminal_manager.set_bell_policy(startup_settings.bell_policy);
+      terminal_manager.set_reset_on_binary_output(startup_settings.reset_on_binary_output);
+      terminal_manager.set_scrollback_limits(startup_settings.scrollback_lines, startup_settings.scrollback_max_bytes);
+      telemetry::set_enabled(startup_settings.telemetry_enabled);
+      let output_throttle = terminal_manager.output_throttle();
       let terminal_manager_state = Arc::new(Mutex::new(terminal_manager));
       
       // Initialize additional managers
       let session_manager = Arc::new(Mutex::new(session_manager::SessionManager::new(terminal_manager_state.clone())));
       let (performance_monitor_instance, _alert_receiver) = performance_monitor::PerformanceMonitor::new();
       let performance_monitor = Arc::new(Mutex::new(performance_monitor_instance));
-      let security_manager = Arc::new(Mutex::new(security::SecurityManager::new()));
-      let execution_context_manager = Arc::new(Mutex::new(execution_context::ExecutionContextState::new()));
+      let security_manager_instance = security::SecurityManager::new();
+      security_manager_instance.configure(&startup_settings);
+      let security_manager = Arc::new(Mutex::new(security_manager_instance));
+      let execution_context_manager: ExecutionContextManager = Arc::new(std::sync::Mutex::new(execution_context::ExecutionContextState::new()));
       let shell_integration_manager = Arc::new(Mutex::new(shell_integration::ShellIntegrationState::new()));
       let clipboard_manager = Arc::new(Mutex::new(clipboard_manager::ClipboardState::new()));
-      let filesystem_manager = Arc::new(Mutex::new(filesystem_manager::FileSystemState::new()));
+      let filesystem_manager: FileSystemManager = Arc::new(std::sync::Mutex::new(filesystem_manager::FileSystemState::new()));
       let process_manager = Arc::new(Mutex::new(process_manager::ProcessManager::new()));
       let theme_manager = Arc::new(Mutex::new(theme_manager::ThemeManager::new("themes".to_string())));
-      let network_manager = Arc::new(Mutex::new(network_manager::NetworkManager::new()));
+      let network_manager_instance = network_manager::NetworkManager::new();
+      let mut ssh_event_receiver = network_manager_instance.subscribe_ssh_events();
+      let network_manager = Arc::new(Mutex::new(network_manager_instance));
       let dev_tools_manager = Arc::new(Mutex::new(dev_tools::DevToolsManager::new()));
-      let accessibility_manager = Arc::new(Mutex::new(accessibility::AccessibilityManager::new()));
+      let accessibility_manager_instance = accessibility::AccessibilityManager::new();
+      accessibility_manager_instance.configure(&startup_settings);
+      let accessibility_manager = Arc::new(Mutex::new(accessibility_manager_instance));
       let i18n_manager = Arc::new(Mutex::new(accessibility::I18nManager::new()));
-      let advanced_terminal_manager = Arc::new(Mutex::new(advanced_terminal::AdvancedTerminalManager::new()));
+      let advanced_terminal_manager = advanced_terminal::AdvancedTerminalManager::new();
+      advanced_terminal_manager.set_alert_on_bell_only(startup_settings.alert_on_bell_only);
+      let advanced_terminal_manager = Arc::new(Mutex::new(advanced_terminal_manager));
+      let mut plugin_host_instance = plugins::PluginHost::new();
+      plugin_host_instance.start_enabled();
+      let plugin_host = Arc::new(Mutex::new(plugin_host_instance));
+      let command_notifier = Arc::new(Mutex::new(notifications::CommandNotifier::new(&startup_settings)));
       
       // Store managers in app state
       app.manage(terminal_manager_state.clone());
       app.manage(session_manager);
-      app.manage(performance_monitor);
-      app.manage(security_manager);
-      app.manage(execution_context_manager);
+      app.manage(performance_monitor.clone());
+      app.manage(security_manager.clone());
+      app.manage(execution_context_manager.clone());
       app.manage(shell_integration_manager);
       app.manage(clipboard_manager);
-      app.manage(filesystem_manager);
+      app.manage(filesystem_manager.clone());
       app.manage(process_manager);
-      app.manage(theme_manager);
+      app.manage(theme_manager.clone());
       app.manage(network_manager);
-      app.manage(dev_tools_manager);
-      app.manage(accessibility_manager);
+      app.manage(dev_tools_manager.clone());
+      app.manage(accessibility_manager.clone());
       app.manage(i18n_manager);
-      app.manage(advanced_terminal_manager);
+      app.manage(advanced_terminal_manager.clone());
+      app.manage(plugin_host.clone());
+      app.manage(command_notifier.clone());
+      app.manage(Arc::new(Mutex::new(search::HistoryEmbeddingIndex::load())));
 
       // Spawn task to handle terminal output using tauri async runtime
       let app_handle = app.handle().clone();
       let terminal_manager_clone = terminal_manager_state.clone();
+      let plugin_host_output = plugin_host.clone();
+      let advanced_terminal_output = advanced_terminal_manager.clone();
+      let execution_context_output = execution_context_manager.clone();
+      let accessibility_output = accessibility_manager.clone();
       
+      let batch_settings = settings::load_settings().unwrap_or_default();
+      let mut output_coalescer = pty::OutputCoalescer::new(
+        output_receiver,
+        batch_settings.output_batch_window_ms,
+        batch_settings.output_batch_max_bytes,
+      );
+      output_throttle.set_max_bytes_per_sec(batch_settings.max_output_bytes_per_sec);
+      
+      let output_throttle_consumer = output_throttle.clone();
+      tauri::async_runtime::spawn(async move {
+        while let Some(batch) = output_coalescer.next_batch().await {
+          for output in batch {
+            let output_len = output.data.len();
+            let session_id = output.session_id.clone();
+
+            // Feed the backend model (grid, blocks, shell hooks, search index) every
+            // chunk, including output the guard keeps off the screen
+            let (new_cwd, binary_output, bell, (output, warning)) = {
+              let manager = terminal_manager_clone.lock().await;
+              let new_cwd = manager.process_output(output.clone());
+              // Hold back runaway output (say, a binary file) until the user truncates or shows it
+              let guarded = manager.guard_output(output);
+              (new_cwd, manager.take_binary_output_warning(&session_id), manager.take_bell(&session_id), guarded)
+            };
+            output_throttle_consumer.consumed(&session_id, output_len);
+
+            if let Some(warning) = warning {
+              let _ = app_handle.emit("large-output-warning", &warning);
+            }
+            if let Some(output) = output {
+              // Emit terminal output to frontend
+              let _ = app_handle.emit("terminal-output", &output);
+
+              // Plugins receive chunks through their own queues and never block output
+              plugin_host_output.lock().await.dispatch(
+                plugins::PluginEvent::OnOutputChunk,
+                serde_json::json!({ "session_id": output.session_id, "data": output.data }),
+              );
+            }
+
+            // Background panes get activity and bell indicators until they're focused
+            for alert in advanced_terminal_output.lock().await.note_terminal_output(&session_id, bell) {
+              let event = match alert.kind {
+                advanced_terminal::PaneAlertKind::Activity => "session-activity",
+                advanced_terminal::PaneAlertKind::Bell => "session-bell",
+              };
+              let _ = app_handle.emit(event, &alert);
+            }
+
+            // Ring the bell the way the user asked: sound, a pane flash, and/or a notification
+            if bell {
+              let reduced_motion = accessibility_output.lock().await.get_config().reduced_motion;
+              let actions = terminal_manager_clone.lock().await.bell_actions(&session_id, reduced_motion);
+              let payload = serde_json::json!({ "terminal_id": session_id });
+              if actions.sound {
+                let _ = app_handle.emit("terminal-bell", &payload);
+              }
+              if actions.flash {
+                let _ = app_handle.emit("terminal-flash", &payload);
+              }
+              if actions.notify {
+                let _ = app_handle.emit("terminal-notification", &payload);
+              }
+            }
+
+            // Raw binary can leave the terminal broken: offer a reset, or reset right away
+            if binary_output {
+              let reset = terminal_manager_clone.lock().await.reset_on_binary_output();
+              if reset {
+                if let Ok(output) = terminal_manager_clone.lock().await.reset_terminal(&session_id) {
+                  let _ = app_handle.emit("terminal-output", &output);
+                  let _ = app_handle.emit("terminal-reset", serde_json::json!({ "terminal_id": session_id }));
+                }
+              }
+              let _ = app_handle.emit("binary-output-detected", serde_json::json!({ "terminal_id": session_id, "reset": reset }));
+            }
+
+            // Keep the pane's working directory in step with the shell (OSC 7)
+            if let Some(cwd) = new_cwd {
+              let remote_host = if cwd.remote { cwd.host.clone() } else { None };
+              advanced_terminal_output.lock().await.update_terminal_working_directory(&session_id, std::path::PathBuf::from(&cwd.path), remote_host);
+              let _ = app_handle.emit("terminal-cwd-changed", serde_json::json!({ "terminal_id": session_id, "cwd": cwd }));
+              // Local directories feed the frecency-ranked jump list
+              if !cwd.remote {
+                match execution_context_output.lock() {
+                  Ok(mut context) => context.record_directory_visit(&cwd.path),
+                  Err(e) => log::warn!("Failed to record directory visit: {}", e),
+                }
+              }
+            }
+          }
+        }
+      });
+
+      // Forward file watcher events to the frontend
+      let file_watch_app_handle = app.handle().clone();
+      let file_watch_receiver = filesystem_manager.lock().ok().and_then(|fs| fs.watch_tx.as_ref().map(|tx| tx.subscribe()));
+      tauri::async_runtime::spawn(async move {
+        if let Some(mut receiver) = file_watch_receiver {
+          loop {
+            match receiver.recv().await {
+              Ok(event) => { let _ = file_watch_app_handle.emit("file-watch-event", &event); }
+              Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+              Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+          }
+        }
+      });
+
+      // Forward SSH reconnect progress to the frontend
+      let ssh_events_app_handle = app.handle().clone();
       tauri::async_runtime::spawn(async move {
-        let mut output_receiver = output_receiver;
-        while let Some(output) = output_receiver.recv().await {
-          // Emit terminal output to frontend
-          let _ = app_handle.emit("terminal-output", &output);
-          
-          // Process output in terminal manager
-          // For now, skip processing output since we need to handle async properly
-          // TODO: Refactor output processing to be async-compatible
+        loop {
+          match ssh_event_receiver.recv().await {
+            Ok(event) => {
+              let name = match event.kind {
+                network_manager::SshSessionEventKind::Reconnecting => "ssh-reconnecting",
+                network_manager::SshSessionEventKind::Reconnected => "ssh-reconnected",
+                network_manager::SshSessionEventKind::ReconnectFailed => "ssh-reconnect-failed",
+              };
+              let _ = ssh_events_app_handle.emit(name, &event);
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+          }
+        }
+      });
+
+      // Forward dev tools events (build/test progress, debugger stops) to the frontend
+      let dev_tools_app_handle = app.handle().clone();
+      tauri::async_runtime::spawn(async move {
+        let receiver = dev_tools_manager.lock().await.start_event_monitoring().await;
+        if let Ok(mut receiver) = receiver {
+          while let Some(event) = receiver.recv().await {
+            let _ = dev_tools_app_handle.emit("devtools-event", &event);
+          }
+        }
+      });
+
+      // Apply saved settings live and forward them to the frontend
+      let settings_app_handle = app.handle().clone();
+      let settings_throttle = output_throttle.clone();
+      let settings_theme_manager = theme_manager.clone();
+      let settings_terminal_manager = terminal_manager_state.clone();
+      let settings_advanced_terminal = advanced_terminal_manager.clone();
+      let settings_command_notifier = command_notifier.clone();
+      let settings_accessibility = accessibility_manager.clone();
+      let settings_security = security_manager.clone();
+      tauri::async_runtime::spawn(async move {
+        let mut settings_receiver = settings::subscribe();
+        loop {
+          let change = match settings_receiver.recv().await {
+            Ok(change) => change,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+          };
+          for key in &change.keys {
+            match key.as_str() {
+              "max_output_bytes_per_sec" => settings_throttle.set_max_bytes_per_sec(change.settings.max_output_bytes_per_sec),
+              "large_output_max_lines" | "large_output_max_bytes" | "large_output_window_ms" | "large_output_allowlist" => {
+                settings_terminal_manager.lock().await.set_output_guard_limits(pty::OutputGuardLimits::from_settings(&change.settings));
+              }
+              "bell_policy" => settings_terminal_manager.lock().await.set_bell_policy(change.settings.bell_policy),
+              "reset_on_binary_output" => settings_terminal_manager.lock().await.set_reset_on_binary_output(change.settings.reset_on_binary_output),
+              "scrollback_lines" | "scrollback_max_bytes" => {
+                settings_terminal_manager.lock().await.set_scrollback_limits(change.settings.scrollback_lines, change.settings.scrollback_max_bytes);
+              }
+              "screen_reader_support" | "high_contrast_mode" | "reduced_motion" => settings_accessibility.lock().await.configure(&change.settings),
+              "mask_sensitive_data" | "audit_logging" => settings_security.lock().await.configure(&change.settings),
+              "notify_long_commands" | "long_command_notification_secs" => settings_command_notifier.lock().await.configure(&change.settings),
+              "alert_on_bell_only" => settings_advanced_terminal.lock().await.set_alert_on_bell_only(change.settings.alert_on_bell_only),
+              "telemetry_enabled" => telemetry::set_enabled(change.settings.telemetry_enabled),
+              "theme" => {
+                if let Err(e) = settings_theme_manager.lock().await.apply_appearance(&change.settings.theme) {
+                  log::warn!("Failed to apply theme setting: {}", e);
+                }
+              }
+              _ => {}
+            }
+          }
+          let _ = settings_app_handle.emit("settings-changed", &change);
+        }
+      });
+
+      // Write batched execution-context changes to disk
+      let execution_context_persist = execution_context_manager.clone();
+      tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(execution_context::PERSIST_INTERVAL);
+        loop {
+          ticker.tick().await;
+          if let Err(e) = execution_context_persist.lock().map_err(|e| e.to_string()).and_then(|mut context| context.flush_pending_writes()) {
+            log::warn!("Failed to save execution context: {}", e);
+          }
+        }
+      });
+
+      // Write buffered usage metrics to disk
+      tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(telemetry::METRICS_FLUSH_INTERVAL);
+        loop {
+          ticker.tick().await;
+          if let Err(e) = telemetry::flush_metrics() {
+            log::warn!("Failed to save usage metrics: {}", e);
+          }
+        }
+      });
+
+      // Surface throttle state changes so the UI can show an "output throttled" indicator
+      let throttle_app_handle = app.handle().clone();
+      tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_millis(250));
+        loop {
+          ticker.tick().await;
+          for event in output_throttle.take_events() {
+            let _ = throttle_app_handle.emit("terminal-output-throttled", &event);
+          }
+        }
+      });
+
+      // Feed shell-integration command events into the performance monitor, plugins, session history and screen-reader announcements
+      let terminal_manager_events = terminal_manager_state.clone();
+      let performance_monitor_events = performance_monitor.clone();
+      let plugin_host_events = plugin_host.clone();
+      let accessibility_events = accessibility_manager.clone();
+      let notifier_events = command_notifier.clone();
+      let advanced_terminal_events = advanced_terminal_manager.clone();
+      let notification_app_handle = app.handle().clone();
+      tauri::async_runtime::spawn(async move {
+        let mut command_event_receiver = command_event_receiver;
+        while let Some(event) = command_event_receiver.recv().await {
+          let plugin_event = match event.event_type {
+            shell_hooks::CommandEventType::Started => plugins::PluginEvent::OnCommandStart,
+            shell_hooks::CommandEventType::Finished => plugins::PluginEvent::OnCommandEnd,
+            shell_hooks::CommandEventType::Prompt => plugins::PluginEvent::OnPrompt,
+          };
+          if let Ok(payload) = serde_json::to_value(&event) {
+            plugin_host_events.lock().await.dispatch(plugin_event, payload);
+          }
+
+          if let shell_hooks::CommandEventType::Finished = event.event_type {
+            accessibility_events.lock().await.announce_command_finished(&event.command, event.exit_code, event.duration_ms);
+            advanced_terminal_events.lock().await.record_command(&event.session_id, &event);
+
+            // Long commands finishing while the user is elsewhere get a desktop notification
+            let focused = notification_app_handle
+              .get_webview_window("main")
+              .and_then(|window| window.is_focused().ok())
+              .unwrap_or(true);
+            if !focused && notifier_events.lock().await.queue(&event) {
+              let notifier = notifier_events.clone();
+              let app_handle = notification_app_handle.clone();
+              tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(notifications::COALESCE_WINDOW).await;
+                let batch = notifier.lock().await.take_batch();
+                if batch.is_empty() {
+                  return;
+                }
+                let (title, body) = notifications::notification_text(&batch);
+                use tauri_plugin_notification::NotificationExt;
+                if let Err(e) = app_handle.notification().builder().title(title).body(body).show() {
+                  log::warn!("Failed to show command notification: {}", e);
+                }
+              });
+            }
+          }
+
+          let shell_pid = terminal_manager_events.lock().await.get_shell_pid(&event.session_id);
+          performance_monitor_events.lock().await.handle_command_event(event, shell_pid);
         }
       });
 
@@ -112,28 +408,80 @@ pub fn run() {
     .invoke_handler(tauri::generate_handler![
       // Core terminal endpoints
       create_terminal,
+      run_captured_command,
       write_to_terminal,
+      paste_to_terminal,
+      send_mouse_event,
       resize_terminal,
       close_terminal,
       get_terminal_state,
+      start_selection,
+      update_selection,
+      clear_selection,
+      select_word_at,
+      select_line_at,
+      select_semantic_at,
+      get_selection_text,
+      detect_clickable_regions,
+      open_clickable,
+      copy_terminal_selection,
+      set_scrollback_limits,
+      set_output_rate_limit,
+      resolve_large_output,
+      reset_terminal,
+      set_command_notifications,
+      set_session_encoding,
+      get_grid_diff,
+      take_dirty_rows,
+      get_scrollback_len,
+      get_scrollback_rows,
+      get_command_blocks,
+      get_last_command_duration,
+      get_block_output,
+      copy_block_output,
+      copy_last_output,
+      copy_last_command,
+      parse_command_output,
+      detect_output_format,
+      get_block,
+      rerun_block,
+      fold_block,
+      unfold_block,
+      get_visible_scrollback,
+      get_accessible_terminal_snapshot,
       // Shell integration endpoints
       get_command_history,
+      semantic_search_history,
       get_command_suggestions,
       handle_tab_completion,
+      get_completion_items,
       is_at_prompt,
       get_current_prompt,
+      record_exit_code,
+      get_last_exit_code,
       search_history,
       search_scrollback,
+      search_scrollback_with_context,
+      export_scrollback,
+      start_recording,
+      stop_recording,
+      play_recording,
+      stop_playback,
       get_scrollback_context,
       // AI endpoints
       ai_generate_command,
+      ai_generate_command_checked,
       ai_explain_error,
       ai_suggest_next,
+      ai_explain_output,
       // Workflow endpoints
       list_workflows,
       save_workflow,
       delete_workflow,
+      import_workflow_yaml,
+      import_workflows_from_directory,
       preview_workflow_command,
+      preview_workflow,
       run_workflow,
       // Session management endpoints
       create_session,
@@ -144,6 +492,7 @@ pub fn run() {
       // Performance monitoring endpoints
       get_performance_metrics,
       get_command_performance_history,
+      export_command_timeline,
       get_system_resources,
       get_performance_alerts,
       set_performance_thresholds,
@@ -160,22 +509,47 @@ pub fn run() {
       get_settings,
       save_user_settings,
       list_plugins,
+      enable_plugin,
+      disable_plugin,
+      get_plugin_statuses,
+      get_plugin_commands,
+      invoke_plugin_command,
       record_event,
+      get_telemetry_summary,
+      upload_telemetry,
+      list_crash_reports,
+      export_crash_report,
       // Execution context commands
       get_execution_context,
+      get_directory_commands,
+      snapshot_environment,
+      diff_environment,
       create_execution_context,
       refresh_execution_context,
       update_selected_text,
       add_directory_bookmark,
       get_directory_bookmarks,
+      jump_to_bookmark,
+      get_bookmark_suggestions,
+      jump_frecent,
+      get_recent_directories,
+      jump_to_recent_directory,
+      get_frecent_directories,
+      import_zoxide_database,
       update_current_directory,
       // Shell integration commands
       get_shell_completions,
+      load_completion_spec,
+      reload_completion_specs,
+      list_completion_specs,
       add_command_to_history,
       search_command_history,
+      get_autosuggestion,
       add_shell_alias,
       get_shell_aliases,
+      expand_aliases,
       get_git_status,
+      get_shell_integration_script,
       create_shell_script,
       get_shell_scripts,
       generate_custom_prompt,
@@ -200,7 +574,12 @@ pub fn run() {
       create_file_operation,
       start_file_operation,
       get_file_operations,
+      undo_last_operation,
+      undo_operation,
+      get_undo_history,
       create_file_watcher,
+      find_duplicates,
+      remove_file_watcher,
       get_recent_paths,
       add_path_bookmark,
       get_path_bookmarks,
@@ -220,20 +599,52 @@ pub fn run() {
       get_css_variables,
       export_theme,
       import_theme,
+      adjust_theme_for_colorblindness,
       // Network management commands
       add_ssh_connection,
       get_ssh_connections,
       connect_ssh,
+      trust_ssh_host_key,
       disconnect_ssh,
+      create_remote_port_forward,
+      list_port_forwards,
+      close_port_forward,
+      sftp_list_directory,
+      sftp_download,
+      sftp_upload,
       scan_ports,
+      ping_host,
+      discover_hosts,
       get_network_stats,
+      get_network_connections,
       // Developer tools commands
       discover_git_repositories,
       load_git_repository,
       git_commit,
       git_push,
       git_pull,
+      git_blame,
+      get_git_diff,
+      stage_hunk,
+      unstage_hunk,
+      stash_save,
+      list_stashes,
+      stash_apply,
+      stash_pop,
+      stash_drop,
       run_build,
+      detect_project_type,
+      suggest_configs,
+      add_build_configuration,
+      add_test_configuration,
+      register_debugger,
+      get_debugger,
+      debug_start,
+      debug_continue,
+      debug_step_over,
+      debug_set_breakpoint,
+      debug_evaluate,
+      debug_stop,
       run_tests,
       // Accessibility commands
       get_accessibility_config,
@@ -241,7 +652,11 @@ pub fn run() {
       toggle_high_contrast,
       set_magnification,
       announce,
+      run_accessibility_audit,
       get_keyboard_shortcuts,
+      find_shortcut_conflicts,
+      export_shortcuts,
+      import_shortcuts,
       // Internationalization commands
       get_i18n_config,
       set_locale,
@@ -251,8 +666,16 @@ pub fn run() {
       create_terminal_session,
       get_terminal_session,
       get_all_terminal_sessions,
+      get_rich_history,
+      get_session_command_history,
       split_pane,
       close_pane,
+      apply_layout,
+      attach_pane_terminal,
+      get_pane_cwd,
+      apply_session_template,
+      set_input_broadcast,
+      clear_input_broadcast,
       create_terminal_tab,
       close_terminal_tab,
       switch_terminal_tab,
@@ -260,7 +683,10 @@ pub fn run() {
       restore_session,
       get_session_templates,
       export_session,
-      import_session
+      import_session,
+      // Configuration bundle commands
+      export_config_bundle,
+      import_config_bundle
     ])
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
@@ -1,11 +1,12 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::net::{IpAddr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::process::Command;
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc, oneshot};
 use tokio::time::{interval, timeout};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +28,190 @@ pub struct SshConnection {
     pub tags: Vec<String>,
     pub last_connected: Option<u64>,
     pub connection_count: u32,
+    /// Re-establish the session when the connection drops
+    #[serde(default)]
+    pub auto_reconnect: bool,
+    /// Unanswered keepalives before the connection counts as dead
+    #[serde(default = "default_keepalive_count_max")]
+    pub keepalive_count_max: u32,
+    #[serde(default = "default_max_reconnect_attempts")]
+    pub max_reconnect_attempts: u32,
+    /// Delay before the first reconnect attempt, doubled after each failed one
+    #[serde(default = "default_reconnect_backoff_ms")]
+    pub reconnect_backoff_ms: u64,
+    #[serde(default = "default_max_reconnect_backoff_ms")]
+    pub max_reconnect_backoff_ms: u64,
+    /// Host keys are checked against this file, `~/.ssh/known_hosts` when unset
+    #[serde(default)]
+    pub known_hosts_path: Option<String>,
+    /// `proxy_jump` resolved hop by hop when a session starts
+    #[serde(skip)]
+    resolved_jumps: Vec<JumpHop>,
+}
+
+/// One hop of a ProxyJump chain. Hops that name a stored connection use its address and
+/// credentials; others are passed to ssh as written, so `~/.ssh/config` applies to them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JumpHop {
+    pub host: String,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub identity_file: Option<String>,
+    pub known_hosts_path: Option<String>,
+}
+
+impl JumpHop {
+    /// Parse `[user@]host[:port]`, with IPv6 addresses in brackets when a port is given.
+    fn parse(spec: &str) -> Option<Self> {
+        let (username, address) = match spec.rsplit_once('@') {
+            Some((user, address)) => (Some(user.to_string()), address),
+            None => (None, spec),
+        };
+        let (host, port) = if let Some(rest) = address.strip_prefix('[') {
+            let (host, after) = rest.split_once(']')?;
+            match after.strip_prefix(':') {
+                Some(port) => (host, Some(port.parse().ok()?)),
+                None if after.is_empty() => (host, None),
+                None => return None,
+            }
+        } else {
+            match address.split_once(':') {
+                Some((host, port)) if !port.contains(':') => (host, Some(port.parse().ok()?)),
+                _ => (address, None),
+            }
+        };
+        if host.is_empty() || username.as_deref() == Some("") {
+            return None;
+        }
+        Some(Self {
+            host: host.to_string(),
+            port,
+            username,
+            identity_file: None,
+            known_hosts_path: None,
+        })
+    }
+
+    fn from_connection(connection: &SshConnection) -> Self {
+        Self {
+            host: connection.host.clone(),
+            port: Some(connection.port),
+            username: Some(connection.username.clone()),
+            identity_file: connection.identity_file.clone(),
+            known_hosts_path: connection.known_hosts_path.clone(),
+        }
+    }
+
+    /// Options and destination for reaching this hop on behalf of `connection`.
+    fn ssh_args(&self, connection: &SshConnection) -> Vec<String> {
+        let known_hosts = self.known_hosts_path.as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| connection.known_hosts_file());
+        let mut args = host_key_options(&known_hosts).to_vec();
+        args.extend(["-o".to_string(), format!("ConnectTimeout={}", connection.connection_timeout)]);
+        if let Some(ref identity_file) = self.identity_file {
+            args.extend(["-i".to_string(), identity_file.clone()]);
+        }
+        if let Some(port) = self.port {
+            args.extend(["-p".to_string(), port.to_string()]);
+        }
+        args.push(match self.username {
+            Some(ref user) => format!("{}@{}", user, self.host),
+            None => self.host.clone(),
+        });
+        args
+    }
+}
+
+/// Quote an argument for the shell ssh runs a ProxyCommand with.
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:@%=,+".contains(c)) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// ProxyCommand tunnelling through `hops` in order, each hop's ssh using the chain before it
+/// as its own ProxyCommand. Every ssh expands `%` tokens once, so inner commands are escaped
+/// once per level of nesting.
+fn proxy_command(hops: &[JumpHop], connection: &SshConnection) -> Option<String> {
+    let mut command: Option<String> = None;
+    for hop in hops {
+        let mut args = vec!["ssh".to_string()];
+        if let Some(inner) = command {
+            args.extend(["-o".to_string(), format!("ProxyCommand={}", inner.replace('%', "%%"))]);
+        }
+        args.extend(["-W".to_string(), "[%h]:%p".to_string()]);
+        args.extend(hop.ssh_args(connection));
+        command = Some(args.iter().map(|arg| shell_quote(arg)).collect::<Vec<_>>().join(" "));
+    }
+    command
+}
+
+fn host_key_options(known_hosts: &Path) -> [String; 4] {
+    [
+        "-o".to_string(), "StrictHostKeyChecking=yes".to_string(),
+        "-o".to_string(), format!("UserKnownHostsFile=\"{}\"", known_hosts.display()),
+    ]
+}
+
+impl SshConnection {
+    pub fn known_hosts_file(&self) -> PathBuf {
+        match self.known_hosts_path {
+            Some(ref path) => PathBuf::from(path),
+            None => {
+                let home = if cfg!(windows) {
+                    std::env::var("USERPROFILE").unwrap_or_else(|_| ".".into())
+                } else {
+                    std::env::var("HOME").unwrap_or_else(|_| ".".into())
+                };
+                PathBuf::from(home).join(".ssh").join("known_hosts")
+            }
+        }
+    }
+
+    /// How the host is named in known_hosts: `host`, or `[host]:port` off the default port.
+    fn known_hosts_name(&self) -> String {
+        if self.port == 22 {
+            self.host.clone()
+        } else {
+            format!("[{}]:{}", self.host, self.port)
+        }
+    }
+
+    /// Options making ssh refuse unknown or changed host keys instead of prompting.
+    fn host_key_options(&self) -> [String; 4] {
+        host_key_options(&self.known_hosts_file())
+    }
+
+    /// How to reach the host through `proxy_jump`: a ProxyCommand chain once resolved,
+    /// otherwise ssh's own `-J`.
+    fn jump_options(&self) -> Vec<String> {
+        if let Some(command) = proxy_command(&self.resolved_jumps, self) {
+            vec!["-o".to_string(), format!("ProxyCommand={}", command)]
+        } else if let Some(ref proxy_jump) = self.proxy_jump {
+            vec!["-J".to_string(), proxy_jump.clone()]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+fn default_keepalive_count_max() -> u32 {
+    3
+}
+
+fn default_max_reconnect_attempts() -> u32 {
+    5
+}
+
+fn default_reconnect_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_max_reconnect_backoff_ms() -> u64 {
+    30_000
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -34,6 +219,7 @@ pub enum SshConnectionStatus {
     Disconnected,
     Connecting,
     Connected,
+    Reconnecting,
     Failed,
     Timeout,
 }
@@ -50,6 +236,32 @@ pub struct ActiveSshSession {
     pub last_activity: u64,
     pub local_port_forwards: Vec<PortForward>,
     pub remote_port_forwards: Vec<PortForward>,
+    /// Attempts made so far while `Reconnecting`
+    #[serde(default)]
+    pub reconnect_attempts: u32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SshSessionEventKind {
+    Reconnecting,
+    Reconnected,
+    ReconnectFailed,
+}
+
+/// Progress of an automatic reconnect, forwarded to the frontend as `ssh-reconnecting`,
+/// `ssh-reconnected` and `ssh-reconnect-failed`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SshSessionEvent {
+    pub kind: SshSessionEventKind,
+    pub session_id: String,
+    pub connection_id: String,
+    pub terminal_id: Option<String>,
+    pub attempt: u32,
+    pub max_attempts: u32,
+    /// Wait before this attempt, for `Reconnecting`
+    pub delay_ms: Option<u64>,
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -61,6 +273,27 @@ pub struct PortForward {
     pub is_active: bool,
     pub created_at: u64,
     pub bytes_transferred: u64,
+    #[serde(default)]
+    pub direction: ForwardDirection,
+    /// Target of a remote (reverse) forward on this machine
+    #[serde(default)]
+    pub local_host: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum ForwardDirection {
+    /// Local listener forwarded to the remote side (`ssh -L`)
+    #[default]
+    Local,
+    /// Remote listener forwarded back to this machine (`ssh -R`)
+    Remote,
+}
+
+/// Running pieces of a reverse tunnel: the `ssh -R` process and the local relay that counts bytes.
+pub struct ForwardHandle {
+    ssh: tokio::process::Child,
+    relay: tokio::task::JoinHandle<()>,
+    bytes: Arc<std::sync::atomic::AtomicU64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -137,6 +370,322 @@ pub struct HostDiscoveryResult {
     pub is_reachable: bool,
     pub response_time: Option<Duration>,
     pub open_ports: Vec<u16>,
+    #[serde(default)]
+    pub ping: Option<PingStats>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum PingMethod {
+    /// Unprivileged ICMP datagram socket
+    IcmpSocket,
+    /// Output of the system `ping` binary
+    SystemPing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PingStats {
+    pub host: String,
+    pub address: Option<IpAddr>,
+    pub transmitted: u32,
+    pub received: u32,
+    /// Percentage of echoes without a reply
+    pub packet_loss: f64,
+    pub min_rtt_ms: Option<f64>,
+    pub avg_rtt_ms: Option<f64>,
+    pub max_rtt_ms: Option<f64>,
+    pub stddev_rtt_ms: Option<f64>,
+    pub rtts_ms: Vec<f64>,
+    pub method: PingMethod,
+}
+
+impl PingStats {
+    fn from_rtts(host: &str, address: Option<IpAddr>, transmitted: u32, rtts_ms: Vec<f64>, method: PingMethod) -> Self {
+        let received = rtts_ms.len() as u32;
+        let transmitted = transmitted.max(received);
+        let packet_loss = if transmitted == 0 {
+            100.0
+        } else {
+            (transmitted - received) as f64 / transmitted as f64 * 100.0
+        };
+        let (min, avg, max, stddev) = if rtts_ms.is_empty() {
+            (None, None, None, None)
+        } else {
+            let n = rtts_ms.len() as f64;
+            let avg = rtts_ms.iter().sum::<f64>() / n;
+            let variance = rtts_ms.iter().map(|rtt| (rtt - avg).powi(2)).sum::<f64>() / n;
+            (
+                rtts_ms.iter().cloned().reduce(f64::min),
+                Some(avg),
+                rtts_ms.iter().cloned().reduce(f64::max),
+                Some(variance.sqrt()),
+            )
+        };
+        Self {
+            host: host.to_string(),
+            address,
+            transmitted,
+            received,
+            packet_loss,
+            min_rtt_ms: min,
+            avg_rtt_ms: avg,
+            max_rtt_ms: max,
+            stddev_rtt_ms: stddev,
+            rtts_ms,
+            method,
+        }
+    }
+}
+
+fn icmp_checksum(data: &[u8]) -> u16 {
+    let mut sum = 0u32;
+    for chunk in data.chunks(2) {
+        let word = if chunk.len() == 2 { u16::from_be_bytes([chunk[0], chunk[1]]) } else { (chunk[0] as u16) << 8 };
+        sum = sum.wrapping_add(word as u32);
+    }
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Send echoes over an unprivileged ICMP datagram socket. Fails when the OS does not allow
+/// such sockets for this user (e.g. `net.ipv4.ping_group_range` excludes us) so callers can
+/// fall back to the system `ping`.
+#[cfg(unix)]
+fn icmp_socket_ping(address: Ipv4Addr, count: u32, interval: Duration, reply_timeout: Duration) -> Result<Vec<f64>, String> {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, libc::IPPROTO_ICMP) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+    struct Socket(i32);
+    impl Drop for Socket {
+        fn drop(&mut self) {
+            unsafe { libc::close(self.0) };
+        }
+    }
+    let socket = Socket(fd);
+
+    let tv = libc::timeval {
+        tv_sec: reply_timeout.as_secs() as libc::time_t,
+        tv_usec: reply_timeout.subsec_micros() as libc::suseconds_t,
+    };
+    unsafe {
+        libc::setsockopt(
+            socket.0,
+            libc::SOL_SOCKET,
+            libc::SO_RCVTIMEO,
+            &tv as *const libc::timeval as *const libc::c_void,
+            std::mem::size_of::<libc::timeval>() as libc::socklen_t,
+        );
+    }
+
+    let mut dest: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+    dest.sin_family = libc::AF_INET as libc::sa_family_t;
+    dest.sin_addr = libc::in_addr { s_addr: u32::from(address).to_be() };
+
+    let mut rtts = Vec::new();
+    for seq in 0..count {
+        if seq > 0 {
+            std::thread::sleep(interval);
+        }
+        let sequence = (seq & 0xffff) as u16;
+        let mut packet = [0u8; 64];
+        packet[0] = 8; // echo request
+        packet[6..8].copy_from_slice(&sequence.to_be_bytes());
+        let checksum = icmp_checksum(&packet);
+        packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+
+        let sent_at = std::time::Instant::now();
+        let sent = unsafe {
+            libc::sendto(
+                socket.0,
+                packet.as_ptr() as *const libc::c_void,
+                packet.len(),
+                0,
+                &dest as *const libc::sockaddr_in as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+            )
+        };
+        if sent < 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+
+        let mut buf = [0u8; 1024];
+        while sent_at.elapsed() < reply_timeout {
+            let n = unsafe { libc::recv(socket.0, buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+            if n < 0 {
+                break; // timed out
+            }
+            let mut reply = &buf[..n as usize];
+            // Some platforms (macOS) hand back the IP header as well
+            if reply.first().map(|b| b >> 4) == Some(4) {
+                let header_len = ((reply[0] & 0x0f) as usize) * 4;
+                if reply.len() < header_len {
+                    continue;
+                }
+                reply = &reply[header_len..];
+            }
+            if reply.len() >= 8 && reply[0] == 0 && u16::from_be_bytes([reply[6], reply[7]]) == sequence {
+                rtts.push(sent_at.elapsed().as_secs_f64() * 1000.0);
+                break;
+            }
+        }
+    }
+    Ok(rtts)
+}
+
+/// Per-reply round-trip times from system `ping` output (`time=12.3 ms`, `time<1ms`).
+fn parse_ping_rtts(output: &str) -> Vec<f64> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let idx = line.find("time=").map(|i| i + 5).or_else(|| line.find("time<").map(|i| i + 5))?;
+            let value: String = line[idx..].chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+            value.parse().ok()
+        })
+        .collect()
+}
+
+/// Embedded OUI prefixes for vendors commonly seen on home and office networks.
+/// Not exhaustive; unknown prefixes simply leave `vendor` empty.
+const OUI_VENDORS: &[(&str, &str)] = &[
+    ("00:00:0C", "Cisco Systems"),
+    ("00:03:93", "Apple"),
+    ("00:04:4B", "NVIDIA"),
+    ("00:05:5D", "D-Link"),
+    ("00:05:69", "VMware"),
+    ("00:08:74", "Dell"),
+    ("00:0A:95", "Apple"),
+    ("00:0C:29", "VMware"),
+    ("00:0C:42", "MikroTik"),
+    ("00:0D:3A", "Microsoft"),
+    ("00:0D:93", "Apple"),
+    ("00:0D:B9", "PC Engines"),
+    ("00:0F:B5", "Netgear"),
+    ("00:10:18", "Broadcom"),
+    ("00:11:24", "Apple"),
+    ("00:11:32", "Synology"),
+    ("00:13:10", "Linksys"),
+    ("00:14:22", "Dell"),
+    ("00:14:6C", "Netgear"),
+    ("00:15:17", "Intel"),
+    ("00:15:5D", "Microsoft Hyper-V"),
+    ("00:16:3E", "Xen"),
+    ("00:17:88", "Philips Lighting"),
+    ("00:17:A4", "Hewlett-Packard"),
+    ("00:18:0A", "Cisco Meraki"),
+    ("00:1A:11", "Google"),
+    ("00:1B:21", "Intel"),
+    ("00:1B:63", "Apple"),
+    ("00:1C:42", "Parallels"),
+    ("00:1E:58", "D-Link"),
+    ("00:21:6A", "Intel"),
+    ("00:25:90", "Super Micro Computer"),
+    ("00:26:BB", "Apple"),
+    ("00:27:22", "Ubiquiti"),
+    ("00:50:56", "VMware"),
+    ("00:50:F2", "Microsoft"),
+    ("00:90:A9", "Western Digital"),
+    ("00:A0:C9", "Intel"),
+    ("00:E0:4C", "Realtek"),
+    ("04:18:D6", "Ubiquiti"),
+    ("08:00:27", "Oracle VirtualBox"),
+    ("18:03:73", "Dell"),
+    ("24:0A:C4", "Espressif"),
+    ("24:A4:3C", "Ubiquiti"),
+    ("28:CD:C1", "Raspberry Pi"),
+    ("30:AE:A4", "Espressif"),
+    ("3C:D9:2B", "Hewlett-Packard"),
+    ("4C:5E:0C", "MikroTik"),
+    ("52:54:00", "QEMU/KVM"),
+    ("B8:27:EB", "Raspberry Pi"),
+    ("D8:3A:DD", "Raspberry Pi"),
+    ("DC:A6:32", "Raspberry Pi"),
+    ("E4:5F:01", "Raspberry Pi"),
+    ("F0:9F:C2", "Ubiquiti"),
+];
+
+fn oui_table() -> &'static HashMap<&'static str, &'static str> {
+    static TABLE: std::sync::OnceLock<HashMap<&'static str, &'static str>> = std::sync::OnceLock::new();
+    TABLE.get_or_init(|| OUI_VENDORS.iter().cloned().collect())
+}
+
+/// Canonical `AA:BB:CC:DD:EE:FF` form of a MAC written with `:` or `-` and optional
+/// unpadded octets (macOS prints `0:1c:42:...`).
+fn normalize_mac(raw: &str) -> Option<String> {
+    let octets: Vec<String> = raw
+        .split([':', '-'])
+        .map(|octet| format!("{:0>2}", octet.to_uppercase()))
+        .collect();
+    if octets.len() != 6 || octets.iter().any(|o| o.len() != 2 || !o.chars().all(|c| c.is_ascii_hexdigit())) {
+        return None;
+    }
+    let mac = octets.join(":");
+    (mac != "00:00:00:00:00:00" && mac != "FF:FF:FF:FF:FF:FF").then_some(mac)
+}
+
+pub fn lookup_mac_vendor(mac: &str) -> Option<String> {
+    let mac = normalize_mac(mac)?;
+    oui_table().get(&mac[..8]).map(|vendor| vendor.to_string())
+}
+
+/// Parse `/proc/net/arp`: `IP address  HW type  Flags  HW address  Mask  Device`.
+fn parse_proc_arp(table: &str) -> HashMap<IpAddr, String> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let ip = fields.first()?.parse().ok()?;
+            let mac = normalize_mac(fields.get(3)?)?;
+            Some((ip, mac))
+        })
+        .collect()
+}
+
+/// Parse `arp -a` output from macOS/BSD (`? (10.0.0.1) at 0:1c:42:0:0:18 on en0`)
+/// and Windows (`  10.0.0.1   00-1c-42-00-00-18   dynamic`).
+fn parse_arp_a(output: &str) -> HashMap<IpAddr, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if let Some(at) = fields.iter().position(|f| *f == "at") {
+                let ip = fields.get(at.checked_sub(1)?)?.trim_matches(|c| c == '(' || c == ')').parse().ok()?;
+                let mac = normalize_mac(fields.get(at + 1)?)?;
+                Some((ip, mac))
+            } else {
+                let ip = fields.first()?.parse().ok()?;
+                let mac = normalize_mac(fields.get(1)?)?;
+                Some((ip, mac))
+            }
+        })
+        .collect()
+}
+
+async fn read_arp_table() -> HashMap<IpAddr, String> {
+    if cfg!(target_os = "linux") {
+        if let Ok(table) = tokio::fs::read_to_string("/proc/net/arp").await {
+            return parse_proc_arp(&table);
+        }
+    }
+    match Command::new("arp").arg("-a").output().await {
+        Ok(output) => parse_arp_a(&String::from_utf8_lossy(&output.stdout)),
+        Err(_) => HashMap::new(),
+    }
+}
+
+/// The transmitted count from a `ping` summary line (`5 packets transmitted` / `Sent = 5`).
+fn parse_ping_transmitted(output: &str) -> Option<u32> {
+    output.lines().find_map(|line| {
+        if let Some(idx) = line.find(" packets transmitted") {
+            line[..idx].split_whitespace().last()?.parse().ok()
+        } else if let Some(idx) = line.find("Sent = ") {
+            line[idx + 7..].chars().take_while(|c| c.is_ascii_digit()).collect::<String>().parse().ok()
+        } else {
+            None
+        }
+    })
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -196,6 +745,316 @@ pub enum AlertSeverity {
     Critical,
 }
 
+/// Failures surfaced when opening an SSH session. The host key variants carry the key the
+/// server presented so the UI can show its fingerprint and offer to trust it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "message")]
+pub enum SshError {
+    HostKeyUnknown { host: String, key_type: String, fingerprint: String },
+    HostKeyChanged { host: String, key_type: String, fingerprint: String },
+    /// Hop `hop` (1-based) of the ProxyJump chain couldn't be reached
+    JumpHostFailed { hop: usize, host: String, message: String },
+    Failed(String),
+}
+
+impl std::fmt::Display for SshError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SshError::HostKeyUnknown { host, key_type, fingerprint } => {
+                write!(f, "The authenticity of host {} can't be established ({} key {})", host, key_type, fingerprint)
+            }
+            SshError::HostKeyChanged { host, key_type, fingerprint } => write!(
+                f,
+                "WARNING: the host key for {} has changed (now {} key {}). Someone could be intercepting the connection; refusing to connect",
+                host, key_type, fingerprint
+            ),
+            SshError::JumpHostFailed { hop, host, message } => write!(f, "Jump host {} ({}) failed: {}", hop, host, message),
+            SshError::Failed(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<String> for SshError {
+    fn from(msg: String) -> Self {
+        SshError::Failed(msg)
+    }
+}
+
+/// A host key as listed by ssh-keyscan or in known_hosts.
+#[derive(Debug, Clone, PartialEq)]
+struct HostKey {
+    key_type: String,
+    key: String,
+}
+
+impl HostKey {
+    /// Parse a `host keytype key [comment]` line. Marked lines (`@revoked`, `@cert-authority`)
+    /// don't describe a trusted key for the host, so they are skipped; ssh still enforces them.
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('@') {
+            return None;
+        }
+        let mut fields = line.split_whitespace().skip(1);
+        Some(Self {
+            key_type: fields.next()?.to_string(),
+            key: fields.next()?.to_string(),
+        })
+    }
+
+    /// `SHA256:...` fingerprint, as printed by `ssh-keygen -l`.
+    fn fingerprint(&self) -> String {
+        use base64::{engine::general_purpose, Engine as _};
+        use sha2::{Digest, Sha256};
+        let blob = general_purpose::STANDARD.decode(&self.key).unwrap_or_default();
+        format!("SHA256:{}", general_purpose::STANDARD_NO_PAD.encode(Sha256::digest(&blob)))
+    }
+}
+
+/// Compare the keys a server offers against the ones known for it.
+fn check_host_keys(host: &str, offered: &[HostKey], known: &[HostKey]) -> Result<(), SshError> {
+    if offered.iter().any(|key| known.contains(key)) {
+        return Ok(());
+    }
+    if known.is_empty() {
+        let key = offered.iter()
+            .find(|key| key.key_type == "ssh-ed25519")
+            .or_else(|| offered.first())
+            .ok_or_else(|| SshError::Failed(format!("{} offered no host keys", host)))?;
+        return Err(SshError::HostKeyUnknown {
+            host: host.to_string(),
+            key_type: key.key_type.clone(),
+            fingerprint: key.fingerprint(),
+        });
+    }
+    let key = offered.iter()
+        .find(|key| known.iter().any(|known| known.key_type == key.key_type))
+        .or_else(|| offered.first())
+        .ok_or_else(|| SshError::Failed(format!("{} offered no host keys", host)))?;
+    Err(SshError::HostKeyChanged {
+        host: host.to_string(),
+        key_type: key.key_type.clone(),
+        fingerprint: key.fingerprint(),
+    })
+}
+
+/// Failures surfaced by the SFTP commands, distinguishing the cases the file UI reacts to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "message")]
+pub enum SftpError {
+    SessionNotFound(String),
+    NotFound(String),
+    PermissionDenied(String),
+    Failed(String),
+}
+
+impl std::fmt::Display for SftpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SftpError::SessionNotFound(msg) => write!(f, "SSH session not found: {}", msg),
+            SftpError::NotFound(msg) => write!(f, "No such file or directory: {}", msg),
+            SftpError::PermissionDenied(msg) => write!(f, "Permission denied: {}", msg),
+            SftpError::Failed(msg) => write!(f, "SFTP failed: {}", msg),
+        }
+    }
+}
+
+impl SftpError {
+    /// Classify sftp's stderr. Authentication failures also say "Permission denied"
+    /// but name the auth method in parentheses, so they are reported as plain failures.
+    fn from_stderr(path: &str, stderr: &str) -> Self {
+        let message = stderr.trim().to_string();
+        if message.contains("No such file") || message.contains("not found") {
+            SftpError::NotFound(path.to_string())
+        } else if message.contains("Permission denied") && !message.contains("Permission denied (") {
+            SftpError::PermissionDenied(path.to_string())
+        } else {
+            SftpError::Failed(message)
+        }
+    }
+}
+
+/// Parse one line of `ss -tulnp` output:
+/// `tcp LISTEN 0 128 127.0.0.1:5432 0.0.0.0:* users:(("postgres",pid=812,fd=5))`.
+#[cfg(unix)]
+fn parse_ss_line(line: &str) -> Option<NetworkConnection> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 5 {
+        return None;
+    }
+    let local_address = parts[4].parse::<SocketAddr>().ok()?;
+    let protocol = match parts[0] {
+        "tcp" => NetworkProtocol::Tcp,
+        "udp" => NetworkProtocol::Udp,
+        _ => return None,
+    };
+    let state = match parts[1] {
+        "LISTEN" => ConnectionState::Listen,
+        "ESTAB" => ConnectionState::Established,
+        _ => ConnectionState::Closed,
+    };
+    let (process_id, process_name) = match parse_ss_process(&parts[5..].join(" ")) {
+        Some((pid, name)) => (Some(pid), Some(name)),
+        None => (None, None),
+    };
+    Some(NetworkConnection {
+        local_address,
+        remote_address: None,
+        protocol,
+        state,
+        process_id,
+        process_name,
+    })
+}
+
+/// First owner listed in a `users:(("name",pid=123,fd=4),...)` column.
+#[cfg(unix)]
+fn parse_ss_process(columns: &str) -> Option<(u32, String)> {
+    let users = &columns[columns.find("users:((")? + "users:((".len()..];
+    let name_start = users.find('"')? + 1;
+    let name_len = users[name_start..].find('"')?;
+    let name = &users[name_start..name_start + name_len];
+    let rest = &users[name_start + name_len..];
+    let pid_start = rest.find("pid=")? + "pid=".len();
+    let pid = rest[pid_start..].split(|c: char| !c.is_ascii_digit()).next()?.parse().ok()?;
+    Some((pid, name.to_string()))
+}
+
+/// Quote a path for an sftp batch command.
+/// Share of a transfer done, on the 0.0..=1.0 scale local `FileOperation`s use.
+fn transfer_progress(processed: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    processed.min(total) as f64 / total as f64
+}
+
+fn sftp_quote(path: &str) -> String {
+    format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Parse the `ls -l` style date columns sftp prints: `Jan  1 12:00` (this year) or `Jan  1  2023`.
+fn parse_sftp_date(month: &str, day: &str, time_or_year: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    use chrono::{Datelike, NaiveDate, TimeZone};
+    let month = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"]
+        .iter()
+        .position(|m| *m == month)? as u32
+        + 1;
+    let day: u32 = day.parse().ok()?;
+    let now = chrono::Utc::now();
+    let (year, hour, minute) = match time_or_year.split_once(':') {
+        Some((h, m)) => {
+            // Entries without a year are within the last six months, possibly last year
+            let year = if month > now.month() { now.year() - 1 } else { now.year() };
+            (year, h.parse().ok()?, m.parse().ok()?)
+        }
+        None => (time_or_year.parse().ok()?, 0, 0),
+    };
+    let naive = NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(hour, minute, 0)?;
+    Some(chrono::Utc.from_utc_datetime(&naive))
+}
+
+/// Turn one line of sftp `ls -la` output into a `FileSystemEntry`.
+fn parse_sftp_listing_line(directory: &str, line: &str) -> Option<crate::filesystem_manager::FileSystemEntry> {
+    use crate::filesystem_manager::{EntryType, FileMetadata, FilePermissions, FileSystemEntry};
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 9 {
+        return None;
+    }
+    let mode = fields[0];
+    let file_type = match mode.chars().next()? {
+        '-' => EntryType::File,
+        'd' => EntryType::Directory,
+        'l' => EntryType::Symlink,
+        'p' => EntryType::Pipe,
+        's' => EntryType::Socket,
+        'b' => EntryType::BlockDevice,
+        'c' => EntryType::CharDevice,
+        _ => return None,
+    };
+    // The name is everything after the date columns; it may contain spaces
+    let name_start = line
+        .match_indices(fields[7])
+        .map(|(idx, _)| idx + fields[7].len())
+        .find(|idx| line[..*idx].split_whitespace().count() == 8)?;
+    let raw_name = line[name_start..].trim_start();
+    let (name, symlink_target) = match raw_name.split_once(" -> ") {
+        Some((name, target)) if file_type == EntryType::Symlink => (name.to_string(), Some(target.to_string())),
+        _ => (raw_name.to_string(), None),
+    };
+    if name == "." || name == ".." {
+        return None;
+    }
+    let modified = parse_sftp_date(fields[5], fields[6], fields[7]).unwrap_or_else(chrono::Utc::now);
+    let extension = std::path::Path::new(&name).extension().map(|e| e.to_string_lossy().to_string());
+    let is_executable = mode.contains('x') && file_type == EntryType::File;
+    let path = format!("{}/{}", directory.trim_end_matches('/'), name);
+
+    Some(FileSystemEntry {
+        path,
+        is_hidden: name.starts_with('.'),
+        name,
+        is_symlink: file_type == EntryType::Symlink,
+        file_type,
+        size: fields[4].parse().unwrap_or(0),
+        permissions: FilePermissions {
+            readable: mode.contains('r'),
+            writable: mode.contains('w'),
+            executable: mode.contains('x'),
+            owner: fields[2].to_string(),
+            group: fields[3].to_string(),
+            mode: mode.to_string(),
+        },
+        created: modified,
+        modified,
+        accessed: modified,
+        symlink_target,
+        mime_type: None,
+        extension,
+        metadata: FileMetadata {
+            line_count: None,
+            encoding: None,
+            language: None,
+            is_binary: false,
+            is_executable,
+            is_archive: false,
+            is_image: false,
+            is_video: false,
+            is_audio: false,
+            checksum: None,
+        },
+    })
+}
+
+/// Pump bytes both ways between two sockets, adding every chunk to `bytes` as it passes.
+async fn relay_connection(inbound: TcpStream, outbound: TcpStream, bytes: Arc<std::sync::atomic::AtomicU64>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let (mut in_read, mut in_write) = inbound.into_split();
+    let (mut out_read, mut out_write) = outbound.into_split();
+    let upstream_bytes = bytes.clone();
+    let upstream = async move {
+        let mut buf = [0u8; 16 * 1024];
+        while let Ok(n) = in_read.read(&mut buf).await {
+            if n == 0 || out_write.write_all(&buf[..n]).await.is_err() {
+                break;
+            }
+            upstream_bytes.fetch_add(n as u64, std::sync::atomic::Ordering::Relaxed);
+        }
+        let _ = out_write.shutdown().await;
+    };
+    let downstream = async move {
+        let mut buf = [0u8; 16 * 1024];
+        while let Ok(n) = out_read.read(&mut buf).await {
+            if n == 0 || in_write.write_all(&buf[..n]).await.is_err() {
+                break;
+            }
+            bytes.fetch_add(n as u64, std::sync::atomic::Ordering::Relaxed);
+        }
+        let _ = in_write.shutdown().await;
+    };
+    tokio::join!(upstream, downstream);
+}
+
 pub struct NetworkManager {
     ssh_connections: Arc<Mutex<HashMap<String, SshConnection>>>,
     active_sessions: Arc<Mutex<HashMap<String, ActiveSshSession>>>,
@@ -205,6 +1064,9 @@ pub struct NetworkManager {
     monitoring_config: Arc<Mutex<NetworkMonitorConfig>>,
     alerts: Arc<Mutex<Vec<NetworkAlert>>>,
     monitoring_enabled: Arc<Mutex<bool>>,
+    forward_handles: Arc<Mutex<HashMap<String, ForwardHandle>>>,
+    ssh_monitors: Arc<Mutex<HashMap<String, oneshot::Sender<()>>>>,
+    ssh_events: broadcast::Sender<SshSessionEvent>,
 }
 
 impl NetworkManager {
@@ -232,9 +1094,16 @@ impl NetworkManager {
             monitoring_config: Arc::new(Mutex::new(default_config)),
             alerts: Arc::new(Mutex::new(Vec::new())),
             monitoring_enabled: Arc::new(Mutex::new(false)),
+            forward_handles: Arc::new(Mutex::new(HashMap::new())),
+            ssh_monitors: Arc::new(Mutex::new(HashMap::new())),
+            ssh_events: broadcast::channel(64).0,
         }
     }
 
+    pub fn subscribe_ssh_events(&self) -> broadcast::Receiver<SshSessionEvent> {
+        self.ssh_events.subscribe()
+    }
+
     // SSH Connection Management
     pub fn add_ssh_connection(&self, connection: SshConnection) -> Result<String, String> {
         let mut connections = self.ssh_connections.lock().unwrap();
@@ -271,74 +1140,36 @@ impl NetworkManager {
         Ok(())
     }
 
-    pub async fn connect_ssh(&self, connection_id: &str, terminal_id: Option<String>) -> Result<String, String> {
-        let connection = self.get_ssh_connection(connection_id)
-            .ok_or_else(|| format!("SSH connection {} not found", connection_id))?;
+    pub async fn connect_ssh(&self, connection_id: &str, terminal_id: Option<String>) -> Result<String, SshError> {
+        let connection = self.resolved_connection(connection_id)?;
+        if !connection.resolved_jumps.is_empty() {
+            Self::probe_jump_chain(&connection).await?;
+        }
+        Self::verify_host_key(&connection).await?;
 
         let session_id = format!("{}-{}", connection_id, SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs());
 
-        // Build SSH command
-        let connect_timeout = format!("ConnectTimeout={}", connection.connection_timeout);
-        let keepalive_interval = format!("ServerAliveInterval={}", connection.keepalive_interval);
-        let port_str = connection.port.to_string();
-        let user_host = format!("{}@{}", connection.username, connection.host);
-        
-        let mut ssh_args = vec![
-            "-o", "StrictHostKeyChecking=no",
-            "-o", &connect_timeout,
-            "-o", &keepalive_interval,
-        ];
-
-        if connection.compression {
-            ssh_args.push("-C");
-        }
-
-        if connection.forward_agent {
-            ssh_args.push("-A");
-        }
-
-        if connection.forward_x11 {
-            ssh_args.push("-X");
-        }
-
-        if let Some(ref identity_file) = connection.identity_file {
-            ssh_args.extend_from_slice(&["-i", identity_file]);
-        }
-
-        if let Some(ref proxy_jump) = connection.proxy_jump {
-            ssh_args.extend_from_slice(&["-J", proxy_jump]);
-        }
-
-        ssh_args.push("-p");
-        ssh_args.push(&port_str);
-        ssh_args.push(&user_host);
-
-        // Start SSH process
-        let mut ssh_command = Command::new("ssh");
-        ssh_command.args(&ssh_args);
-
-        match ssh_command.spawn() {
-            Ok(_child) => {
+        match Self::ssh_session_command(&connection).spawn() {
+            Ok(child) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs();
                 let session = ActiveSshSession {
                     connection_id: connection_id.to_string(),
                     session_id: session_id.clone(),
                     terminal_id,
                     status: SshConnectionStatus::Connected,
-                    connected_at: SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs(),
+                    connected_at: now,
                     bytes_sent: 0,
                     bytes_received: 0,
-                    last_activity: SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs(),
+                    last_activity: now,
                     local_port_forwards: Vec::new(),
                     remote_port_forwards: Vec::new(),
+                    reconnect_attempts: 0,
                 };
 
                 {
@@ -350,21 +1181,384 @@ impl NetworkManager {
                 {
                     let mut connections = self.ssh_connections.lock().unwrap();
                     if let Some(conn) = connections.get_mut(connection_id) {
-                        conn.last_connected = Some(SystemTime::now()
-                            .duration_since(UNIX_EPOCH)
-                            .unwrap()
-                            .as_secs());
+                        conn.last_connected = Some(now);
                         conn.connection_count += 1;
                     }
                 }
 
+                let (cancel_tx, cancel_rx) = oneshot::channel();
+                self.ssh_monitors.lock().unwrap().insert(session_id.clone(), cancel_tx);
+                let (monitors, monitored_session) = (self.ssh_monitors.clone(), session_id.clone());
+                let monitor = Self::monitor_ssh_session(
+                    connection,
+                    session_id.clone(),
+                    child,
+                    cancel_rx,
+                    self.active_sessions.clone(),
+                    self.ssh_events.clone(),
+                );
+                tokio::spawn(async move {
+                    monitor.await;
+                    // A monitor that ended on its own leaves its cancel sender behind; a
+                    // closed sender under this id can only be that one
+                    let mut monitors = monitors.lock().unwrap();
+                    if monitors.get(&monitored_session).is_some_and(|cancel| cancel.is_closed()) {
+                        monitors.remove(&monitored_session);
+                    }
+                });
+
                 Ok(session_id)
             }
-            Err(e) => Err(format!("Failed to start SSH connection: {}", e)),
+            Err(e) => Err(SshError::Failed(format!("Failed to start SSH connection: {}", e))),
         }
     }
 
+    /// A stored connection with its `proxy_jump` chain resolved. Hops naming another stored
+    /// connection, by id or name, take its address and credentials but not its own jump hosts.
+    fn resolved_connection(&self, connection_id: &str) -> Result<SshConnection, String> {
+        let connections = self.ssh_connections.lock().unwrap();
+        let mut connection = connections.get(connection_id)
+            .cloned()
+            .ok_or_else(|| format!("SSH connection {} not found", connection_id))?;
+        if let Some(ref proxy_jump) = connection.proxy_jump {
+            connection.resolved_jumps = proxy_jump.split(',')
+                .map(str::trim)
+                .filter(|spec| !spec.is_empty() && *spec != "none")
+                .map(|spec| {
+                    connections.values()
+                        .find(|stored| stored.id == spec || stored.name == spec)
+                        .map(JumpHop::from_connection)
+                        .or_else(|| JumpHop::parse(spec))
+                        .ok_or_else(|| format!("Invalid jump host: {}", spec))
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        Ok(connection)
+    }
+
+    /// Log in to each jump host in turn through the hops before it, so a broken chain reports
+    /// the hop that failed rather than a bare ssh exit status.
+    async fn probe_jump_chain(connection: &SshConnection) -> Result<(), SshError> {
+        for (index, hop) in connection.resolved_jumps.iter().enumerate() {
+            let mut command = Command::new("ssh");
+            command.args(["-o", "BatchMode=yes"]);
+            if let Some(proxy) = proxy_command(&connection.resolved_jumps[..index], connection) {
+                command.arg("-o").arg(format!("ProxyCommand={}", proxy));
+            }
+            let output = command
+                .args(hop.ssh_args(connection))
+                .arg("exit")
+                .stdin(std::process::Stdio::null())
+                .output()
+                .await
+                .map_err(|e| SshError::Failed(format!("Failed to run ssh: {}", e)))?;
+            if output.status.success() {
+                continue;
+            }
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            // The hop is up but wants interactive authentication, which only the real
+            // connection can do, so the hops behind it can't be checked ahead of time
+            if stderr.contains("Permission denied") {
+                break;
+            }
+            let message = stderr.lines()
+                .map(str::trim)
+                .rfind(|line| !line.is_empty())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("ssh exited with {}", output.status));
+            return Err(SshError::JumpHostFailed { hop: index + 1, host: hop.host.clone(), message });
+        }
+        Ok(())
+    }
+
+    /// Keys the server currently offers, via ssh-keyscan, or through the jump chain when there is one.
+    async fn scan_host_keys(connection: &SshConnection) -> Result<Vec<HostKey>, SshError> {
+        if !connection.resolved_jumps.is_empty() {
+            return Self::scan_host_keys_through_jumps(connection).await;
+        }
+        let output = Command::new("ssh-keyscan")
+            .args(["-T", &connection.connection_timeout.max(1).to_string()])
+            .args(["-p", &connection.port.to_string()])
+            .arg(&connection.host)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .await
+            .map_err(|e| SshError::Failed(format!("Failed to run ssh-keyscan: {}", e)))?;
+        let keys: Vec<HostKey> = String::from_utf8_lossy(&output.stdout).lines().filter_map(HostKey::parse).collect();
+        if keys.is_empty() {
+            return Err(SshError::Failed(format!("Could not read host keys from {}", connection.known_hosts_name())));
+        }
+        Ok(keys)
+    }
+
+    /// ssh-keyscan can't tunnel, so ask ssh itself: through the chain, with `accept-new` and an
+    /// empty scratch known_hosts, it records the key the target presents before authenticating.
+    /// Authentication is expected to fail; only the recorded key matters.
+    async fn scan_host_keys_through_jumps(connection: &SshConnection) -> Result<Vec<HostKey>, SshError> {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        let scratch = std::env::temp_dir().join(format!("warp-keyscan-{}-{}", std::process::id(), nanos));
+        let known_hosts = format!("\"{}\"", scratch.display());
+        let output = Command::new("ssh")
+            .args(["-o", "BatchMode=yes", "-o", "StrictHostKeyChecking=accept-new", "-o", "HashKnownHosts=no"])
+            .arg("-o").arg(format!("UserKnownHostsFile={}", known_hosts))
+            .arg("-o").arg(format!("GlobalKnownHostsFile={}", known_hosts))
+            .arg("-o").arg(format!("ConnectTimeout={}", connection.connection_timeout))
+            .args(connection.jump_options())
+            .args(["-p".to_string(), connection.port.to_string()])
+            .arg(format!("{}@{}", connection.username, connection.host))
+            .arg("exit")
+            .stdin(std::process::Stdio::null())
+            .output()
+            .await
+            .map_err(|e| SshError::Failed(format!("Failed to run ssh: {}", e)))?;
+        let keys: Vec<HostKey> = std::fs::read_to_string(&scratch)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(HostKey::parse)
+            .collect();
+        let _ = std::fs::remove_file(&scratch);
+        if keys.is_empty() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = stderr.lines().map(str::trim).rfind(|line| !line.is_empty()).unwrap_or("no host key received");
+            return Err(SshError::Failed(format!("Could not read host keys from {}: {}", connection.known_hosts_name(), reason)));
+        }
+        Ok(keys)
+    }
+
+    /// Keys recorded for the host in its known_hosts file. `ssh-keygen -F` also matches hashed entries.
+    async fn known_host_keys(connection: &SshConnection) -> Result<Vec<HostKey>, SshError> {
+        let known_hosts = connection.known_hosts_file();
+        if !known_hosts.exists() {
+            return Ok(Vec::new());
+        }
+        let output = Command::new("ssh-keygen")
+            .args(["-F", &connection.known_hosts_name()])
+            .arg("-f")
+            .arg(&known_hosts)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .await
+            .map_err(|e| SshError::Failed(format!("Failed to run ssh-keygen: {}", e)))?;
+        Ok(String::from_utf8_lossy(&output.stdout).lines().filter_map(HostKey::parse).collect())
+    }
+
+    /// Check the server's host key before connecting: known keys pass, unknown hosts come back as
+    /// `HostKeyUnknown` for the UI to offer trust-on-first-use, and changed keys are refused.
+    pub async fn verify_host_key(connection: &SshConnection) -> Result<(), SshError> {
+        let offered = Self::scan_host_keys(connection).await?;
+        let known = Self::known_host_keys(connection).await?;
+        check_host_keys(&connection.known_hosts_name(), &offered, &known)
+    }
+
+    /// Trust-on-first-use: record the host key the user accepted in the connection's known_hosts.
+    /// The server is scanned again, so a key that changed since the prompt is not saved.
+    pub async fn trust_host_key(&self, connection_id: &str, fingerprint: &str) -> Result<(), SshError> {
+        let connection = self.resolved_connection(connection_id)?;
+        let host = connection.known_hosts_name();
+        if !Self::known_host_keys(&connection).await?.is_empty() {
+            return Err(SshError::Failed(format!("{} already has a known host key; remove it before trusting a new one", host)));
+        }
+        let key = Self::scan_host_keys(&connection).await?
+            .into_iter()
+            .find(|key| key.fingerprint() == fingerprint)
+            .ok_or_else(|| SshError::Failed(format!("{} no longer offers a host key with fingerprint {}", host, fingerprint)))?;
+
+        let known_hosts = connection.known_hosts_file();
+        if let Some(parent) = known_hosts.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&known_hosts)
+            .map_err(|e| format!("Failed to open {}: {}", known_hosts.display(), e))?;
+        use std::io::Write;
+        writeln!(file, "{} {} {}", host, key.key_type, key.key)
+            .map_err(|e| format!("Failed to write {}: {}", known_hosts.display(), e))?;
+        Ok(())
+    }
+
+    fn ssh_session_command(connection: &SshConnection) -> Command {
+        let mut command = Command::new("ssh");
+        command.args(connection.host_key_options());
+        command.args([
+            "-o".to_string(), format!("ConnectTimeout={}", connection.connection_timeout),
+            "-o".to_string(), format!("ServerAliveInterval={}", connection.keepalive_interval),
+            "-o".to_string(), format!("ServerAliveCountMax={}", connection.keepalive_count_max),
+        ]);
+        if connection.compression {
+            command.arg("-C");
+        }
+        if connection.forward_agent {
+            command.arg("-A");
+        }
+        if connection.forward_x11 {
+            command.arg("-X");
+        }
+        if let Some(ref identity_file) = connection.identity_file {
+            command.args(["-i", identity_file]);
+        }
+        command.args(connection.jump_options());
+        command.args(["-p".to_string(), connection.port.to_string()]);
+        command.arg(format!("{}@{}", connection.username, connection.host));
+        command.kill_on_drop(true);
+        command
+    }
+
+    /// Apply `update` to a session, returning false once it has been disconnected.
+    fn update_ssh_session(
+        sessions: &Mutex<HashMap<String, ActiveSshSession>>,
+        session_id: &str,
+        update: impl FnOnce(&mut ActiveSshSession),
+    ) -> bool {
+        match sessions.lock().unwrap().get_mut(session_id) {
+            Some(session) => {
+                update(session);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Watch the ssh process behind a session until it is disconnected. ssh exits with 255 when
+    /// the connection fails, including when `keepalive_count_max` keepalives go unanswered.
+    async fn monitor_ssh_session(
+        connection: SshConnection,
+        session_id: String,
+        mut child: tokio::process::Child,
+        mut cancel: oneshot::Receiver<()>,
+        sessions: Arc<Mutex<HashMap<String, ActiveSshSession>>>,
+        events: broadcast::Sender<SshSessionEvent>,
+    ) {
+        loop {
+            let status = tokio::select! {
+                status = child.wait() => status.ok(),
+                _ = &mut cancel => {
+                    let _ = child.kill().await;
+                    return;
+                }
+            };
+            let dropped = status.is_none_or(|status| status.code() == Some(255));
+            if !dropped {
+                Self::update_ssh_session(&sessions, &session_id, |session| {
+                    session.status = SshConnectionStatus::Disconnected;
+                });
+                return;
+            }
+            if !connection.auto_reconnect {
+                Self::update_ssh_session(&sessions, &session_id, |session| {
+                    session.status = SshConnectionStatus::Timeout;
+                });
+                return;
+            }
+            match Self::reconnect_ssh(&connection, &session_id, &mut cancel, &sessions, &events).await {
+                Some(reconnected) => child = reconnected,
+                None => return,
+            }
+        }
+    }
+
+    /// Respawn ssh with exponential backoff. Returns the new process once it has stayed up past
+    /// the connect timeout, or None after the last failed attempt or a disconnect.
+    async fn reconnect_ssh(
+        connection: &SshConnection,
+        session_id: &str,
+        cancel: &mut oneshot::Receiver<()>,
+        sessions: &Mutex<HashMap<String, ActiveSshSession>>,
+        events: &broadcast::Sender<SshSessionEvent>,
+    ) -> Option<tokio::process::Child> {
+        let terminal_id = sessions.lock().unwrap().get(session_id)?.terminal_id.clone();
+        let event = |kind, attempt, delay_ms, error| SshSessionEvent {
+            kind,
+            session_id: session_id.to_string(),
+            connection_id: connection.id.clone(),
+            terminal_id: terminal_id.clone(),
+            attempt,
+            max_attempts: connection.max_reconnect_attempts,
+            delay_ms,
+            error,
+        };
+
+        let mut delay_ms = connection.reconnect_backoff_ms;
+        let mut last_error = "connection lost".to_string();
+        for attempt in 1..=connection.max_reconnect_attempts {
+            let still_open = Self::update_ssh_session(sessions, session_id, |session| {
+                session.status = SshConnectionStatus::Reconnecting;
+                session.reconnect_attempts = attempt;
+            });
+            if !still_open {
+                return None;
+            }
+            let _ = events.send(event(SshSessionEventKind::Reconnecting, attempt, Some(delay_ms), None));
+
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(delay_ms)) => {}
+                _ = &mut *cancel => return None,
+            }
+            delay_ms = delay_ms.saturating_mul(2).min(connection.max_reconnect_backoff_ms);
+
+            let mut child = match Self::ssh_session_command(connection).spawn() {
+                Ok(child) => child,
+                Err(e) => {
+                    last_error = e.to_string();
+                    continue;
+                }
+            };
+            // A connection that can't be made fails within ConnectTimeout
+            let settle = Duration::from_secs(connection.connection_timeout + 1);
+            tokio::select! {
+                status = child.wait() => match status {
+                    Ok(status) if status.code() != Some(255) => {
+                        // Connected, then the remote side ended the session
+                        Self::update_ssh_session(sessions, session_id, |session| {
+                            session.status = SshConnectionStatus::Disconnected;
+                            session.reconnect_attempts = 0;
+                        });
+                        return None;
+                    }
+                    Ok(status) => last_error = format!("ssh exited with {}", status),
+                    Err(e) => last_error = e.to_string(),
+                },
+                _ = tokio::time::sleep(settle) => {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs();
+                    let still_open = Self::update_ssh_session(sessions, session_id, |session| {
+                        session.status = SshConnectionStatus::Connected;
+                        session.reconnect_attempts = 0;
+                        session.connected_at = now;
+                        session.last_activity = now;
+                    });
+                    if !still_open {
+                        return None;
+                    }
+                    let _ = events.send(event(SshSessionEventKind::Reconnected, attempt, None, None));
+                    return Some(child);
+                }
+                _ = &mut *cancel => {
+                    let _ = child.kill().await;
+                    return None;
+                }
+            }
+        }
+
+        Self::update_ssh_session(sessions, session_id, |session| {
+            session.status = SshConnectionStatus::Failed;
+        });
+        let _ = events.send(event(
+            SshSessionEventKind::ReconnectFailed,
+            connection.max_reconnect_attempts,
+            None,
+            Some(last_error),
+        ));
+        None
+    }
+
     pub fn disconnect_ssh(&self, session_id: &str) -> Result<(), String> {
+        if let Some(cancel) = self.ssh_monitors.lock().unwrap().remove(session_id) {
+            let _ = cancel.send(());
+        }
         let mut sessions = self.active_sessions.lock().unwrap();
         if let Some(session) = sessions.get_mut(session_id) {
             session.status = SshConnectionStatus::Disconnected;
@@ -380,6 +1574,216 @@ impl NetworkManager {
         sessions.values().cloned().collect()
     }
 
+    // SFTP
+    /// Connection settings behind a connected session, for running SFTP without holding the manager.
+    pub fn sftp_connection(&self, session_id: &str) -> Result<SshConnection, SftpError> {
+        let connection_id = {
+            let sessions = self.active_sessions.lock().unwrap();
+            match sessions.get(session_id) {
+                Some(session) if session.status == SshConnectionStatus::Connected => session.connection_id.clone(),
+                _ => return Err(SftpError::SessionNotFound(session_id.to_string())),
+            }
+        };
+        self.resolved_connection(&connection_id)
+            .map_err(|_| SftpError::SessionNotFound(session_id.to_string()))
+    }
+
+    fn sftp_args(connection: &SshConnection) -> Vec<String> {
+        let mut args = connection.host_key_options().to_vec();
+        args.extend([
+            "-o".to_string(), format!("ConnectTimeout={}", connection.connection_timeout),
+            "-o".to_string(), "BatchMode=yes".to_string(),
+        ]);
+        if connection.compression {
+            args.push("-C".to_string());
+        }
+        if let Some(ref identity_file) = connection.identity_file {
+            args.extend(["-i".to_string(), identity_file.clone()]);
+        }
+        args.extend(connection.jump_options());
+        args.extend(["-P".to_string(), connection.port.to_string()]);
+        args.extend(["-b".to_string(), "-".to_string()]);
+        args.push(format!("{}@{}", connection.username, connection.host));
+        args
+    }
+
+    fn spawn_sftp(connection: &SshConnection) -> Result<tokio::process::Child, SftpError> {
+        Command::new("sftp")
+            .args(Self::sftp_args(connection))
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| SftpError::Failed(format!("Failed to start sftp: {}", e)))
+    }
+
+    /// Run a batch of sftp commands to completion and return stdout.
+    async fn run_sftp_batch(connection: &SshConnection, path: &str, batch: String) -> Result<String, SftpError> {
+        use tokio::io::AsyncWriteExt;
+        let mut child = Self::spawn_sftp(connection)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(batch.as_bytes()).await
+                .map_err(|e| SftpError::Failed(e.to_string()))?;
+        }
+        let output = child.wait_with_output().await
+            .map_err(|e| SftpError::Failed(e.to_string()))?;
+        if !output.status.success() {
+            return Err(SftpError::from_stderr(path, &String::from_utf8_lossy(&output.stderr)));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    pub async fn sftp_list_directory(
+        connection: &SshConnection,
+        path: &str,
+    ) -> Result<Vec<crate::filesystem_manager::FileSystemEntry>, SftpError> {
+        let stdout = Self::run_sftp_batch(connection, path, format!("ls -la {}\n", sftp_quote(path))).await?;
+        Ok(stdout
+            .lines()
+            .filter(|line| !line.starts_with("sftp>"))
+            .filter_map(|line| parse_sftp_listing_line(path, line))
+            .collect())
+    }
+
+    async fn sftp_remote_size(connection: &SshConnection, remote: &str) -> Result<u64, SftpError> {
+        let stdout = Self::run_sftp_batch(connection, remote, format!("ls -ln {}\n", sftp_quote(remote))).await?;
+        Ok(stdout
+            .lines()
+            .filter(|line| !line.starts_with("sftp>"))
+            .find_map(|line| line.split_whitespace().nth(4).and_then(|size| size.parse().ok()))
+            .unwrap_or(0))
+    }
+
+    /// Run a single get/put, reporting progress as a `FileOperation` like local copies.
+    /// `observe` reads how many bytes have landed so far; it is polled while sftp runs,
+    /// and a slow poll delays the next one rather than bunching them up.
+    async fn sftp_transfer<F: std::future::Future<Output = u64>>(
+        connection: &SshConnection,
+        mut operation: crate::filesystem_manager::FileOperation,
+        batch: String,
+        error_path: &str,
+        observe: impl Fn() -> F,
+        on_progress: impl Fn(&crate::filesystem_manager::FileOperation),
+    ) -> Result<crate::filesystem_manager::FileOperation, SftpError> {
+        use crate::filesystem_manager::OperationStatus;
+        use tokio::io::AsyncWriteExt;
+
+        operation.status = OperationStatus::Running;
+        on_progress(&operation);
+
+        let mut child = Self::spawn_sftp(connection)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(batch.as_bytes()).await
+                .map_err(|e| SftpError::Failed(e.to_string()))?;
+        }
+        let mut ticker = interval(Duration::from_millis(250));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let status = loop {
+            tokio::select! {
+                status = child.wait() => break status.map_err(|e| SftpError::Failed(e.to_string()))?,
+                _ = ticker.tick() => {
+                    let processed = observe().await.min(operation.total_bytes);
+                    if processed != operation.bytes_processed {
+                        operation.bytes_processed = processed;
+                        operation.progress = transfer_progress(processed, operation.total_bytes);
+                        on_progress(&operation);
+                    }
+                }
+            }
+        };
+
+        if !status.success() {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = child.stderr.take() {
+                use tokio::io::AsyncReadExt;
+                let _ = pipe.read_to_string(&mut stderr).await;
+            }
+            let error = SftpError::from_stderr(error_path, &stderr);
+            operation.status = OperationStatus::Failed;
+            operation.error = Some(error.to_string());
+            operation.completed_at = Some(chrono::Utc::now());
+            on_progress(&operation);
+            return Err(error);
+        }
+
+        operation.status = OperationStatus::Completed;
+        operation.bytes_processed = operation.total_bytes;
+        operation.files_processed = 1;
+        operation.progress = 1.0;
+        operation.completed_at = Some(chrono::Utc::now());
+        on_progress(&operation);
+        Ok(operation)
+    }
+
+    fn new_transfer(source: &str, destination: &str, total_bytes: u64) -> crate::filesystem_manager::FileOperation {
+        use crate::filesystem_manager::{FileOperation, OperationStatus, OperationType};
+        FileOperation {
+            id: uuid::Uuid::new_v4().to_string(),
+            operation_type: OperationType::Copy,
+            source: vec![source.to_string()],
+            destination: Some(destination.to_string()),
+            status: OperationStatus::Pending,
+            progress: 0.0,
+            bytes_processed: 0,
+            total_bytes,
+            files_processed: 0,
+            total_files: 1,
+            started_at: chrono::Utc::now(),
+            completed_at: None,
+            error: None,
+            can_resume: false,
+            delete_mode: Default::default(),
+            trashed_paths: Vec::new(),
+            created_paths: Vec::new(),
+        }
+    }
+
+    pub async fn sftp_download(
+        connection: &SshConnection,
+        remote: &str,
+        local: &str,
+        on_progress: impl Fn(&crate::filesystem_manager::FileOperation),
+    ) -> Result<crate::filesystem_manager::FileOperation, SftpError> {
+        let total_bytes = Self::sftp_remote_size(connection, remote).await?;
+        let operation = Self::new_transfer(remote, local, total_bytes);
+        let local_path = std::path::PathBuf::from(local);
+        Self::sftp_transfer(
+            connection,
+            operation,
+            format!("get {} {}\n", sftp_quote(remote), sftp_quote(local)),
+            remote,
+            || std::future::ready(std::fs::metadata(&local_path).map(|m| m.len()).unwrap_or(0)),
+            on_progress,
+        )
+        .await
+    }
+
+    /// Upload progress comes from the size of the remote file, read over its own
+    /// sftp connection on each poll.
+    pub async fn sftp_upload(
+        connection: &SshConnection,
+        local: &str,
+        remote: &str,
+        on_progress: impl Fn(&crate::filesystem_manager::FileOperation),
+    ) -> Result<crate::filesystem_manager::FileOperation, SftpError> {
+        let metadata = std::fs::metadata(local).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => SftpError::NotFound(local.to_string()),
+            std::io::ErrorKind::PermissionDenied => SftpError::PermissionDenied(local.to_string()),
+            _ => SftpError::Failed(e.to_string()),
+        })?;
+        let operation = Self::new_transfer(local, remote, metadata.len());
+        Self::sftp_transfer(
+            connection,
+            operation,
+            format!("put {} {}\n", sftp_quote(local), sftp_quote(remote)),
+            remote,
+            || async { Self::sftp_remote_size(connection, remote).await.unwrap_or(0) },
+            on_progress,
+        )
+        .await
+    }
+
     // Port Forwarding
     pub async fn create_port_forward(
         &self,
@@ -401,6 +1805,8 @@ impl NetworkManager {
                 .unwrap()
                 .as_secs(),
             bytes_transferred: 0,
+            direction: ForwardDirection::Local,
+            local_host: None,
         };
 
         {
@@ -430,7 +1836,174 @@ impl NetworkManager {
 
     pub fn get_port_forwards(&self) -> Vec<PortForward> {
         let forwards = self.port_forwards.lock().unwrap();
-        forwards.values().cloned().collect()
+        forwards.values().cloned().map(|forward| self.with_live_bytes(forward)).collect()
+    }
+
+    /// Fill in the running byte count for forwards that have a relay.
+    fn with_live_bytes(&self, mut forward: PortForward) -> PortForward {
+        if let Some(handle) = self.forward_handles.lock().unwrap().get(&forward.id) {
+            forward.bytes_transferred = handle.bytes.load(std::sync::atomic::Ordering::Relaxed);
+        }
+        forward
+    }
+
+    /// The connection behind a connected session, for opening a reverse tunnel on it.
+    pub fn remote_forward_connection(&self, session_id: &str) -> Result<SshConnection, String> {
+        let connection_id = {
+            let sessions = self.active_sessions.lock().unwrap();
+            sessions.get(session_id)
+                .filter(|session| session.status == SshConnectionStatus::Connected)
+                .map(|session| session.connection_id.clone())
+                .ok_or_else(|| format!("SSH session {} not found", session_id))?
+        };
+        self.resolved_connection(&connection_id)
+    }
+
+    /// Open `remote_port` on the SSH server and route its connections to `local_host:local_port`.
+    /// Connections arrive through a local relay so traffic can be counted. Takes a couple of
+    /// seconds to confirm the server bound the port, so it runs without the manager borrowed;
+    /// hand the result to `add_remote_port_forward`.
+    pub async fn open_remote_tunnel(
+        connection: &SshConnection,
+        remote_port: u16,
+        local_host: &str,
+        local_port: u16,
+    ) -> Result<ForwardHandle, String> {
+        let relay_listener = TcpListener::bind("127.0.0.1:0").await
+            .map_err(|e| format!("Failed to bind relay listener: {}", e))?;
+        let relay_port = relay_listener.local_addr().map_err(|e| e.to_string())?.port();
+
+        let mut ssh_args = vec!["-N".to_string()];
+        ssh_args.extend(connection.host_key_options());
+        ssh_args.extend([
+            "-o".to_string(), "ExitOnForwardFailure=yes".to_string(),
+            "-o".to_string(), "BatchMode=yes".to_string(),
+            "-o".to_string(), format!("ConnectTimeout={}", connection.connection_timeout),
+            "-o".to_string(), format!("ServerAliveInterval={}", connection.keepalive_interval),
+            "-R".to_string(), format!("{}:127.0.0.1:{}", remote_port, relay_port),
+        ]);
+        if let Some(ref identity_file) = connection.identity_file {
+            ssh_args.extend(["-i".to_string(), identity_file.clone()]);
+        }
+        ssh_args.extend(connection.jump_options());
+        ssh_args.extend(["-p".to_string(), connection.port.to_string()]);
+        ssh_args.push(format!("{}@{}", connection.username, connection.host));
+
+        let mut ssh = Command::new("ssh")
+            .args(&ssh_args)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to start SSH tunnel: {}", e))?;
+
+        // With ExitOnForwardFailure ssh exits promptly when the remote port cannot be bound
+        if let Ok(status) = timeout(Duration::from_secs(2), ssh.wait()).await {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = ssh.stderr.take() {
+                use tokio::io::AsyncReadExt;
+                let _ = pipe.read_to_string(&mut stderr).await;
+            }
+            return Err(format!(
+                "Remote port forward failed ({}): {}",
+                status.map(|s| s.to_string()).unwrap_or_else(|e| e.to_string()),
+                stderr.trim()
+            ));
+        }
+
+        let bytes = Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let relay_bytes = bytes.clone();
+        let target = format!("{}:{}", local_host, local_port);
+        let relay = tokio::spawn(async move {
+            // Aborting the relay drops this set, which aborts every open connection with it
+            let mut connections = tokio::task::JoinSet::new();
+            loop {
+                tokio::select! {
+                    accepted = relay_listener.accept() => {
+                        let Ok((inbound, _)) = accepted else { break };
+                        let target = target.clone();
+                        let bytes = relay_bytes.clone();
+                        connections.spawn(async move {
+                            if let Ok(outbound) = TcpStream::connect(&target).await {
+                                relay_connection(inbound, outbound, bytes).await;
+                            }
+                        });
+                    }
+                    Some(_) = connections.join_next(), if !connections.is_empty() => {}
+                }
+            }
+        });
+
+        Ok(ForwardHandle { ssh, relay, bytes })
+    }
+
+    /// Record a tunnel opened by `open_remote_tunnel` against its session.
+    pub fn add_remote_port_forward(
+        &self,
+        session_id: &str,
+        connection: &SshConnection,
+        remote_port: u16,
+        local_host: String,
+        local_port: u16,
+        mut handle: ForwardHandle,
+    ) -> Result<String, String> {
+        let mut sessions = self.active_sessions.lock().unwrap();
+        // The session may have been disconnected while the tunnel was coming up
+        let Some(session) = sessions.get_mut(session_id) else {
+            let _ = handle.ssh.start_kill();
+            handle.relay.abort();
+            return Err(format!("SSH session {} not found", session_id));
+        };
+        let forward_id = format!("rpf-{}-{}-{}", session_id, remote_port, local_port);
+        let port_forward = PortForward {
+            id: forward_id.clone(),
+            local_port,
+            remote_host: connection.host.clone(),
+            remote_port,
+            is_active: true,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            bytes_transferred: 0,
+            direction: ForwardDirection::Remote,
+            local_host: Some(local_host),
+        };
+
+        self.forward_handles.lock().unwrap().insert(forward_id.clone(), handle);
+        self.port_forwards.lock().unwrap().insert(forward_id.clone(), port_forward.clone());
+        session.remote_port_forwards.push(port_forward);
+        Ok(forward_id)
+    }
+
+    /// Both local and remote forwards of a session, with current byte counts.
+    pub fn list_port_forwards(&self, session_id: &str) -> Result<Vec<PortForward>, String> {
+        let sessions = self.active_sessions.lock().unwrap();
+        let session = sessions.get(session_id)
+            .ok_or_else(|| format!("SSH session {} not found", session_id))?;
+        Ok(session.local_port_forwards.iter()
+            .chain(session.remote_port_forwards.iter())
+            .cloned()
+            .map(|forward| self.with_live_bytes(forward))
+            .collect())
+    }
+
+    /// Tear down a forward of either direction, stopping its tunnel and relay if running.
+    pub fn close_port_forward(&self, forward_id: &str) -> Result<(), String> {
+        let removed = self.port_forwards.lock().unwrap().remove(forward_id);
+        if removed.is_none() {
+            return Err(format!("Port forward {} not found", forward_id));
+        }
+        if let Some(mut handle) = self.forward_handles.lock().unwrap().remove(forward_id) {
+            let _ = handle.ssh.start_kill();
+            handle.relay.abort();
+        }
+        for session in self.active_sessions.lock().unwrap().values_mut() {
+            session.local_port_forwards.retain(|f| f.id != forward_id);
+            session.remote_port_forwards.retain(|f| f.id != forward_id);
+        }
+        Ok(())
     }
 
     // Network Monitoring
@@ -535,46 +2108,18 @@ impl NetworkManager {
         Ok(Vec::new())
     }
 
+    /// Sockets from `ss -tulnp`. Without privileges ss only names the processes owned by the
+    /// current user, so other sockets keep `process_id`/`process_name` as None.
     #[cfg(unix)]
     async fn get_network_connections() -> Result<Vec<NetworkConnection>, String> {
         let output = Command::new("ss")
-            .args(&["-tuln"])
+            .args(["-tulnp"])
             .output()
             .await
             .map_err(|e| format!("Failed to execute ss command: {}", e))?;
 
         let output_str = String::from_utf8_lossy(&output.stdout);
-        let mut connections = Vec::new();
-
-        for line in output_str.lines().skip(1) { // Skip header
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 5 {
-                if let Ok(local_addr) = parts[4].parse::<SocketAddr>() {
-                    let protocol = match parts[0] {
-                        "tcp" => NetworkProtocol::Tcp,
-                        "udp" => NetworkProtocol::Udp,
-                        _ => continue,
-                    };
-
-                    let state = match parts[1] {
-                        "LISTEN" => ConnectionState::Listen,
-                        "ESTAB" => ConnectionState::Established,
-                        _ => ConnectionState::Closed,
-                    };
-
-                    connections.push(NetworkConnection {
-                        local_address: local_addr,
-                        remote_address: None,
-                        protocol,
-                        state,
-                        process_id: None,
-                        process_name: None,
-                    });
-                }
-            }
-        }
-
-        Ok(connections)
+        Ok(output_str.lines().skip(1).filter_map(parse_ss_line).collect()) // Skip header
     }
 
     #[cfg(windows)]
@@ -583,6 +2128,14 @@ impl NetworkManager {
         Ok(Vec::new())
     }
 
+    /// Read the sockets now instead of waiting for the monitor's next pass, and
+    /// keep them for `get_network_stats`.
+    pub async fn refresh_network_connections(&self) -> Result<Vec<NetworkConnection>, String> {
+        let connections = Self::get_network_connections().await?;
+        *self.network_connections.lock().unwrap() = connections.clone();
+        Ok(connections)
+    }
+
     pub fn get_network_stats(&self) -> NetworkStats {
         let interfaces = self.network_interfaces.lock().unwrap().clone();
         let connections = self.network_connections.lock().unwrap().clone();
@@ -663,6 +2216,68 @@ impl NetworkManager {
     }
 
     // Host Discovery
+    /// Ping `host` `count` times, preferring an ICMP socket and falling back to the system `ping`.
+    pub async fn ping(&self, host: &str, count: u32, interval: Duration, reply_timeout: Duration) -> Result<PingStats, String> {
+        let count = count.max(1);
+        let address = tokio::net::lookup_host((host, 0)).await
+            .map_err(|e| format!("Failed to resolve {}: {}", host, e))?
+            .map(|addr| addr.ip())
+            .find(|ip| ip.is_ipv4())
+            .or_else(|| host.parse::<IpAddr>().ok());
+
+        #[cfg(unix)]
+        if let Some(IpAddr::V4(v4)) = address {
+            let attempt = tokio::task::spawn_blocking(move || icmp_socket_ping(v4, count, interval, reply_timeout))
+                .await
+                .map_err(|e| e.to_string())?;
+            if let Ok(rtts) = attempt {
+                return Ok(PingStats::from_rtts(host, address, count, rtts, PingMethod::IcmpSocket));
+            }
+        }
+
+        self.system_ping(host, address, count, interval, reply_timeout).await
+    }
+
+    async fn system_ping(
+        &self,
+        host: &str,
+        address: Option<IpAddr>,
+        count: u32,
+        interval: Duration,
+        reply_timeout: Duration,
+    ) -> Result<PingStats, String> {
+        let count_str = count.to_string();
+
+        #[cfg(windows)]
+        let args = vec!["-n".to_string(), count_str, "-w".to_string(), reply_timeout.as_millis().to_string()];
+
+        #[cfg(target_os = "macos")]
+        let args = vec![
+            "-c".to_string(), count_str,
+            "-i".to_string(), format!("{:.1}", interval.as_secs_f64().max(0.1)),
+            "-W".to_string(), reply_timeout.as_millis().to_string(),
+        ];
+
+        // Linux ping refuses intervals below 0.2s for unprivileged users and takes -W in seconds
+        #[cfg(all(unix, not(target_os = "macos")))]
+        let args = vec![
+            "-c".to_string(), count_str,
+            "-i".to_string(), format!("{:.1}", interval.as_secs_f64().max(0.2)),
+            "-W".to_string(), reply_timeout.as_secs().max(1).to_string(),
+        ];
+
+        let output = Command::new("ping")
+            .args(&args)
+            .arg(host)
+            .output()
+            .await
+            .map_err(|e| format!("Failed to run ping: {}", e))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let rtts = parse_ping_rtts(&stdout);
+        let transmitted = parse_ping_transmitted(&stdout).unwrap_or(count);
+        Ok(PingStats::from_rtts(host, address, transmitted, rtts, PingMethod::SystemPing))
+    }
+
     pub async fn discover_hosts(&self, network: &str) -> Vec<HostDiscoveryResult> {
         let mut results = Vec::new();
 
@@ -671,40 +2286,33 @@ impl NetworkManager {
         for i in 1..255 {
             let ip_str = format!("{}.{}", network_base, i);
             if let Ok(ip) = ip_str.parse::<IpAddr>() {
-                let start_time = std::time::Instant::now();
-
-                #[cfg(unix)]
-                let ping_result = Command::new("ping")
-                    .args(&["-c", "1", "-W", "1000", &ip_str])
-                    .output()
-                    .await;
-
-                #[cfg(windows)]
-                let ping_result = Command::new("ping")
-                    .args(&["-n", "1", "-w", "1000", &ip_str])
-                    .output()
-                    .await;
-
-                let is_reachable = ping_result
-                    .map(|output| output.status.success())
-                    .unwrap_or(false);
-
-                if is_reachable {
-                    let response_time = Some(start_time.elapsed());
-                    
-                    // Try to resolve hostname
-                    let hostname = self.resolve_hostname(&ip).await;
-
-                    results.push(HostDiscoveryResult {
-                        ip_address: ip,
-                        hostname,
-                        mac_address: None, // Could be implemented with ARP lookup
-                        vendor: None,
-                        is_reachable: true,
-                        response_time,
-                        open_ports: Vec::new(), // Could scan common ports
-                    });
-                }
+                let stats = match self.ping(&ip_str, 1, Duration::from_millis(200), Duration::from_secs(1)).await {
+                    Ok(stats) if stats.received > 0 => stats,
+                    _ => continue,
+                };
+
+                // Try to resolve hostname
+                let hostname = self.resolve_hostname(&ip).await;
+
+                results.push(HostDiscoveryResult {
+                    ip_address: ip,
+                    hostname,
+                    mac_address: None,
+                    vendor: None,
+                    is_reachable: true,
+                    response_time: stats.avg_rtt_ms.map(|ms| Duration::from_secs_f64(ms / 1000.0)),
+                    open_ports: Vec::new(), // Could scan common ports
+                    ping: Some(stats),
+                });
+            }
+        }
+
+        // Replies populate the ARP cache, so read it once after the sweep
+        let arp_table = read_arp_table().await;
+        for result in &mut results {
+            if let Some(mac) = arp_table.get(&result.ip_address) {
+                result.vendor = lookup_mac_vendor(mac);
+                result.mac_address = Some(mac.clone());
             }
         }
 
@@ -794,6 +2402,17 @@ impl NetworkManager {
             .map_err(|e| format!("Failed to serialize connections: {}", e))
     }
 
+    /// Restore saved connections by id. Without `merge`, existing connections are dropped first.
+    pub fn restore_ssh_connections(&self, restored: Vec<SshConnection>, merge: bool) -> usize {
+        let mut connections = self.ssh_connections.lock().unwrap();
+        if !merge {
+            connections.clear();
+        }
+        let count = restored.len();
+        connections.extend(restored.into_iter().map(|connection| (connection.id.clone(), connection)));
+        count
+    }
+
     pub fn import_ssh_connections(&self, json_data: &str) -> Result<usize, String> {
         let connections: HashMap<String, SshConnection> = serde_json::from_str(json_data)
             .map_err(|e| format!("Failed to parse connections JSON: {}", e))?;
@@ -808,3 +2427,153 @@ impl NetworkManager {
         Ok(count)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(id: &str, host: &str, port: u16, username: &str, proxy_jump: Option<&str>) -> SshConnection {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "name": id, "host": host, "port": port, "username": username,
+            "connection_timeout": 10, "keepalive_interval": 30,
+            "compression": false, "forward_agent": false, "forward_x11": false,
+            "proxy_jump": proxy_jump, "tags": [], "connection_count": 0,
+            "known_hosts_path": "/kh",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn jump_hops_parse_user_host_and_port() {
+        let hop = JumpHop::parse("alice@bastion:2222").unwrap();
+        assert_eq!((hop.username.as_deref(), hop.host.as_str(), hop.port), (Some("alice"), "bastion", Some(2222)));
+        let hop = JumpHop::parse("[fe80::1]:22").unwrap();
+        assert_eq!((hop.host.as_str(), hop.port), ("fe80::1", Some(22)));
+        // A bare IPv6 address has no port
+        assert_eq!(JumpHop::parse("fe80::1").unwrap().port, None);
+        assert_eq!(JumpHop::parse("bastion").unwrap().username, None);
+        assert!(JumpHop::parse("alice@").is_none());
+        assert!(JumpHop::parse("@bastion").is_none());
+        assert!(JumpHop::parse("bastion:ssh").is_none());
+        assert!(JumpHop::parse("[fe80::1]x").is_none());
+    }
+
+    #[test]
+    fn a_two_hop_chain_tunnels_each_hop_through_the_one_before() {
+        let manager = NetworkManager::new();
+        let mut bastion = connection("bastion", "bastion.example.com", 2222, "alice", None);
+        bastion.identity_file = Some("/keys/bastion key".into());
+        manager.add_ssh_connection(bastion).unwrap();
+        manager.add_ssh_connection(connection("target", "db.internal", 22, "carol", Some("bastion, bob@inner:2200"))).unwrap();
+
+        let target = manager.resolved_connection("target").unwrap();
+        assert_eq!(target.resolved_jumps.len(), 2);
+        assert_eq!(target.resolved_jumps[0].identity_file.as_deref(), Some("/keys/bastion key"));
+        assert_eq!(target.resolved_jumps[1], JumpHop::parse("bob@inner:2200").unwrap());
+
+        let first = "ssh -W '[%h]:%p' -o StrictHostKeyChecking=yes -o 'UserKnownHostsFile=\"/kh\"' -o ConnectTimeout=10 \
+                     -i '/keys/bastion key' -p 2222 alice@bastion.example.com";
+        assert_eq!(proxy_command(&target.resolved_jumps[..1], &target).unwrap(), first);
+
+        // The first hop's command is nested as the second's ProxyCommand, its `%` escaped once
+        let chain = proxy_command(&target.resolved_jumps, &target).unwrap();
+        let nested = format!("ProxyCommand={}", first.replace('%', "%%"));
+        assert_eq!(
+            chain,
+            format!(
+                "ssh -o {} -W '[%h]:%p' -o StrictHostKeyChecking=yes -o 'UserKnownHostsFile=\"/kh\"' -o ConnectTimeout=10 -p 2200 bob@inner",
+                shell_quote(&nested)
+            )
+        );
+        assert_eq!(target.jump_options(), vec!["-o".to_string(), format!("ProxyCommand={}", chain)]);
+    }
+
+    #[test]
+    fn invalid_jump_specs_are_rejected() {
+        let manager = NetworkManager::new();
+        manager.add_ssh_connection(connection("target", "db.internal", 22, "carol", Some("bastion:notaport"))).unwrap();
+        assert!(manager.resolved_connection("target").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ss_lines_carry_their_owning_process_when_listed() {
+        let owned = parse_ss_line(r#"tcp   LISTEN 0      128        127.0.0.1:5432      0.0.0.0:*    users:(("postgres",pid=812,fd=5),("postgres",pid=813,fd=5))"#).unwrap();
+        assert!(matches!(owned.protocol, NetworkProtocol::Tcp));
+        assert!(matches!(owned.state, ConnectionState::Listen));
+        assert_eq!(owned.local_address, "127.0.0.1:5432".parse::<SocketAddr>().unwrap());
+        assert_eq!((owned.process_id, owned.process_name.as_deref()), (Some(812), Some("postgres")));
+
+        // Without privileges ss leaves the process column off
+        let unowned = parse_ss_line("udp   UNCONN 0      0          [::1]:323           [::]:*").unwrap();
+        assert!(matches!(unowned.protocol, NetworkProtocol::Udp));
+        assert!(matches!(unowned.state, ConnectionState::Closed));
+        assert_eq!(unowned.local_address, "[::1]:323".parse::<SocketAddr>().unwrap());
+        assert_eq!((unowned.process_id, unowned.process_name), (None, None));
+
+        assert!(parse_ss_line("Netid State  Recv-Q Send-Q Local Address:Port Peer Address:Port Process").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn the_first_listed_owner_is_the_process() {
+        assert_eq!(parse_ss_process(r#"users:(("node",pid=4242,fd=21))"#), Some((4242, "node".to_string())));
+        assert_eq!(parse_ss_process(r#"10.0.0.5:443 users:(("my app",pid=7,fd=3),("sh",pid=8,fd=3))"#), Some((7, "my app".to_string())));
+        assert_eq!(parse_ss_process("0.0.0.0:*"), None);
+        assert_eq!(parse_ss_process(r#"users:(("broken",fd=3))"#), None);
+    }
+
+    #[test]
+    fn the_linux_arp_table_maps_hosts_to_macs_and_vendors() {
+        let table = "\
+IP address       HW type     Flags       HW address            Mask     Device
+192.168.1.1      0x1         0x2         b8:27:eb:12:34:56     *        eth0
+192.168.1.20     0x1         0x2         00:0c:29:ab:cd:ef     *        eth0
+192.168.1.30     0x1         0x0         00:00:00:00:00:00     *        eth0
+192.168.1.40     0x1         0x2         aa:bb:cc:dd:ee:ff     *        eth0
+";
+        let arp = parse_proc_arp(table);
+        let mac = |ip: &str| arp.get(&ip.parse::<IpAddr>().unwrap()).cloned();
+
+        // Incomplete entries (all-zero MAC) are skipped
+        assert_eq!(arp.len(), 3);
+        assert_eq!(mac("192.168.1.1").as_deref(), Some("B8:27:EB:12:34:56"));
+        assert_eq!(mac("192.168.1.30"), None);
+        assert_eq!(lookup_mac_vendor(&mac("192.168.1.1").unwrap()).as_deref(), Some("Raspberry Pi"));
+        assert_eq!(lookup_mac_vendor(&mac("192.168.1.20").unwrap()).as_deref(), Some("VMware"));
+        assert_eq!(lookup_mac_vendor(&mac("192.168.1.40").unwrap()), None);
+    }
+
+    #[test]
+    fn arp_a_output_from_bsd_and_windows_is_understood() {
+        let bsd = "\
+? (10.0.0.1) at 0:1c:42:0:0:18 on en0 ifscope [ethernet]
+? (10.0.0.9) at (incomplete) on en0 ifscope [ethernet]
+";
+        let arp = parse_arp_a(bsd);
+        assert_eq!(arp.len(), 1);
+        let mac = &arp[&"10.0.0.1".parse::<IpAddr>().unwrap()];
+        assert_eq!(mac, "00:1C:42:00:00:18");
+        assert_eq!(lookup_mac_vendor(mac).as_deref(), Some("Parallels"));
+
+        let windows = "\
+Interface: 10.0.0.5 --- 0x4
+  Internet Address      Physical Address      Type
+  10.0.0.1              dc-a6-32-01-02-03     dynamic
+  10.0.0.255            ff-ff-ff-ff-ff-ff     static
+";
+        let arp = parse_arp_a(windows);
+        assert_eq!(arp.len(), 1);
+        assert_eq!(arp[&"10.0.0.1".parse::<IpAddr>().unwrap()], "DC:A6:32:01:02:03");
+        assert_eq!(lookup_mac_vendor("dc-a6-32-01-02-03").as_deref(), Some("Raspberry Pi"));
+    }
+
+    #[test]
+    fn transfer_progress_uses_the_local_operation_scale() {
+        assert_eq!(transfer_progress(0, 0), 0.0);
+        assert_eq!(transfer_progress(256, 1024), 0.25);
+        assert_eq!(transfer_progress(1024, 1024), 1.0);
+        // A remote file briefly larger than expected (say, an earlier upload) caps at done
+        assert_eq!(transfer_progress(4096, 1024), 1.0);
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::settings::Settings;
+use crate::shell_hooks::{CommandEvent, CommandEventType};
+use crate::terminal::format_duration;
+
+// Completions this close together share one notification
+pub const COALESCE_WINDOW: Duration = Duration::from_secs(2);
+const MAX_LISTED_COMMANDS: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct CompletedCommand {
+    pub session_id: String,
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+}
+
+/// Decides which finished commands are worth a desktop notification and
+/// batches the ones that finish close together.
+pub struct CommandNotifier {
+    enabled: bool,
+    min_duration: Duration,
+    muted_sessions: HashSet<String>,
+    pending: Vec<CompletedCommand>,
+}
+
+impl CommandNotifier {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            enabled: settings.notify_long_commands,
+            min_duration: Duration::from_secs(settings.long_command_notification_secs),
+            muted_sessions: HashSet::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn configure(&mut self, settings: &Settings) {
+        self.enabled = settings.notify_long_commands;
+        self.min_duration = Duration::from_secs(settings.long_command_notification_secs);
+    }
+
+    /// Muting a session also drops its completions still waiting in the
+    /// current batch, so they don't show up a moment later.
+    pub fn set_session_enabled(&mut self, session_id: &str, enabled: bool) {
+        if enabled {
+            self.muted_sessions.remove(session_id);
+        } else {
+            self.muted_sessions.insert(session_id.to_string());
+            self.pending.retain(|command| command.session_id != session_id);
+        }
+    }
+
+    pub fn session_enabled(&self, session_id: &str) -> bool {
+        !self.muted_sessions.contains(session_id)
+    }
+
+    /// Queue `event` if it's a long enough command finishing in a session that
+    /// wants notifications. Returns true when it starts a new batch, which the
+    /// caller should flush with `take_batch` after `COALESCE_WINDOW`.
+    pub fn queue(&mut self, event: &CommandEvent) -> bool {
+        if !self.enabled || !matches!(event.event_type, CommandEventType::Finished) || !self.session_enabled(&event.session_id) {
+            return false;
+        }
+        let Some(duration_ms) = event.duration_ms else {
+            return false;
+        };
+        if Duration::from_millis(duration_ms) < self.min_duration {
+            return false;
+        }
+        self.pending.push(CompletedCommand {
+            session_id: event.session_id.clone(),
+            command: event.command.clone(),
+            exit_code: event.exit_code,
+            duration_ms,
+        });
+        self.pending.len() == 1
+    }
+
+    pub fn take_batch(&mut self) -> Vec<CompletedCommand> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+fn describe(command: &CompletedCommand) -> String {
+    let status = match command.exit_code {
+        Some(0) => "succeeded".to_string(),
+        Some(code) => format!("failed (exit {})", code),
+        None => "finished".to_string(),
+    };
+    format!("{} {} after {}", command.command.trim(), status, format_duration(command.duration_ms))
+}
+
+/// Title and body for a notification covering `batch`.
+pub fn notification_text(batch: &[CompletedCommand]) -> (String, String) {
+    if let [command] = batch {
+        let title = if command.exit_code.is_some_and(|code| code != 0) { "Command failed" } else { "Command finished" };
+        return (title.to_string(), describe(command));
+    }
+    let failed = batch.iter().filter(|c| c.exit_code.is_some_and(|code| code != 0)).count();
+    let title = match failed {
+        0 => format!("{} commands finished", batch.len()),
+        _ => format!("{} commands finished, {} failed", batch.len(), failed),
+    };
+    let mut lines: Vec<String> = batch.iter().take(MAX_LISTED_COMMANDS).map(describe).collect();
+    if batch.len() > MAX_LISTED_COMMANDS {
+        lines.push(format!("and {} more", batch.len() - MAX_LISTED_COMMANDS));
+    }
+    (title, lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finished(session_id: &str, command: &str) -> CommandEvent {
+        CommandEvent {
+            event_type: CommandEventType::Finished,
+            session_id: session_id.to_string(),
+            command_id: command.to_string(),
+            command: command.to_string(),
+            timestamp: 0,
+            exit_code: Some(0),
+            duration_ms: Some(60_000),
+            output_size: 0,
+            working_dir: String::new(),
+        }
+    }
+
+    #[test]
+    fn muting_a_session_drops_its_queued_completions() {
+        let settings = Settings { notify_long_commands: true, long_command_notification_secs: 10, ..Settings::default() };
+        let mut notifier = CommandNotifier::new(&settings);
+
+        assert!(notifier.queue(&finished("a", "make")));
+        assert!(!notifier.queue(&finished("b", "cargo test")));
+        notifier.set_session_enabled("a", false);
+        assert!(!notifier.queue(&finished("a", "make install")));
+
+        let batch: Vec<String> = notifier.take_batch().into_iter().map(|c| c.command).collect();
+        assert_eq!(batch, vec!["cargo test"]);
+    }
+}
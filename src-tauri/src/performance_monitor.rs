@@ -4,6 +4,10 @@ use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 use uuid::Uuid;
+use tauri::State;
+use crate::shell_hooks::{CommandEvent, CommandEventType};
+
+pub type PerformanceMonitorState = Arc<tokio::sync::Mutex<PerformanceMonitor>>;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PerformanceMetrics {
@@ -23,16 +27,44 @@ pub struct PerformanceMetrics {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandPerformance {
+    pub command_id: String,
+    pub terminal_id: String,
     pub command: String,
     pub start_time: u64,
     pub end_time: Option<u64>,
     pub duration_ms: Option<u64>,
     pub exit_code: Option<i32>,
     pub memory_peak: u64,
+    pub memory_avg: u64,
     pub cpu_peak: f64,
+    pub cpu_avg: f64,
+    pub sample_count: u32,
     pub output_size: u64,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommandSortKey {
+    StartTime,
+    Duration,
+    PeakMemory,
+    PeakCpu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimelineFormat {
+    ChromeTrace,
+    FoldedStacks,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandTimelineExport {
+    pub terminal_id: String,
+    pub format: TimelineFormat,
+    pub schema: serde_json::Value,
+    pub command_count: usize,
+    pub content: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemResources {
     pub total_memory: u64,
@@ -88,6 +120,7 @@ pub struct PerformanceMonitor {
     alert_sender: mpsc::UnboundedSender<PerformanceAlert>,
     monitoring_enabled: Arc<Mutex<bool>>,
     thresholds: Arc<Mutex<PerformanceThresholds>>,
+    max_command_history: Arc<Mutex<usize>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -125,6 +158,7 @@ impl PerformanceMonitor {
             alert_sender,
             monitoring_enabled: Arc::new(Mutex::new(true)),
             thresholds: Arc::new(Mutex::new(PerformanceThresholds::default())),
+            max_command_history: Arc::new(Mutex::new(1000)),
         };
 
         (monitor, alert_receiver)
@@ -165,23 +199,46 @@ impl PerformanceMonitor {
         });
     }
 
-    pub fn start_command_monitoring(&self, terminal_id: String, command: String) -> String {
-        let command_id = Uuid::new_v4().to_string();
+    /// Routes a shell-integration command event to start/end monitoring.
+    pub fn handle_command_event(&self, event: CommandEvent, shell_pid: Option<u32>) {
+        match event.event_type {
+            CommandEventType::Started => {
+                self.start_command_monitoring(event.session_id, event.command_id, event.command, shell_pid);
+            }
+            CommandEventType::Finished => {
+                self.end_command_monitoring(&event.command_id, event.exit_code, event.output_size);
+            }
+            CommandEventType::Prompt => {}
+        }
+    }
+
+    pub fn start_command_monitoring(&self, terminal_id: String, command_id: String, command: String, shell_pid: Option<u32>) -> String {
         let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
 
         let command_perf = CommandPerformance {
+            command_id: command_id.clone(),
+            terminal_id: terminal_id.clone(),
             command: command.clone(),
             start_time: now,
             end_time: None,
             duration_ms: None,
             exit_code: None,
             memory_peak: 0,
+            memory_avg: 0,
             cpu_peak: 0.0,
+            cpu_avg: 0.0,
+            sample_count: 0,
             output_size: 0,
         };
 
         self.active_commands.lock().unwrap().insert(command_id.clone(), command_perf);
 
+        // Without the shell's pid there is no process tree to sample
+        let shell_pid = match shell_pid {
+            Some(pid) => pid,
+            None => return command_id,
+        };
+
         // Monitor command resources
         let active_commands = self.active_commands.clone();
         let alert_sender = self.alert_sender.clone();
@@ -189,23 +246,42 @@ impl PerformanceMonitor {
         let cmd_id = command_id.clone();
 
         tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_millis(100));
+            let mut interval = tokio::time::interval(Duration::from_millis(200));
+            let mut last_sample: Option<(u64, Instant)> = None;
+            let mut memory_total: u64 = 0;
+            let mut cpu_total: f64 = 0.0;
+            let mut slow_alert_sent = false;
             
             while active_commands.lock().unwrap().contains_key(&cmd_id) {
                 interval.tick().await;
                 
-                // Collect command-specific metrics (simplified)
-                if let Ok(resources) = Self::get_process_resources(&command).await {
+                // Sample the processes spawned by the shell for this command
+                if let Ok(resources) = Self::get_process_resources(shell_pid) {
+                    let sampled_at = Instant::now();
+                    let cpu = match last_sample {
+                        Some((last_cpu_ms, last_at)) => {
+                            let elapsed_ms = sampled_at.duration_since(last_at).as_millis().max(1) as f64;
+                            resources.cpu_time_ms.saturating_sub(last_cpu_ms) as f64 / elapsed_ms * 100.0
+                        }
+                        None => 0.0,
+                    };
+                    last_sample = Some((resources.cpu_time_ms, sampled_at));
+
                     let mut commands = active_commands.lock().unwrap();
                     if let Some(cmd_perf) = commands.get_mut(&cmd_id) {
+                        cmd_perf.sample_count += 1;
+                        memory_total += resources.memory;
+                        cpu_total += cpu;
                         cmd_perf.memory_peak = cmd_perf.memory_peak.max(resources.memory);
-                        cmd_perf.cpu_peak = cmd_perf.cpu_peak.max(resources.cpu);
+                        cmd_perf.cpu_peak = cmd_perf.cpu_peak.max(cpu);
+                        cmd_perf.memory_avg = memory_total / cmd_perf.sample_count as u64;
+                        cmd_perf.cpu_avg = cpu_total / cmd_perf.sample_count as f64;
                         
                         // Check for slow command alerts
                         let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64 - cmd_perf.start_time;
                         let threshold_ms = thresholds.lock().unwrap().command_timeout;
                         
-                        if elapsed > threshold_ms {
+                        if elapsed > threshold_ms && !slow_alert_sent {
                             let alert = PerformanceAlert {
                                 id: Uuid::new_v4().to_string(),
                                 terminal_id: terminal_id.clone(),
@@ -216,11 +292,10 @@ impl PerformanceMonitor {
                                 current_value: elapsed as f64,
                             };
                             let _ = alert_sender.send(alert);
+                            slow_alert_sent = true;
                         }
                     }
                 }
-                
-                tokio::time::sleep(Duration::from_millis(100)).await;
             }
         });
 
@@ -233,18 +308,29 @@ impl PerformanceMonitor {
         if let Some(mut command_perf) = active_commands.remove(command_id) {
             let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
             command_perf.end_time = Some(now);
-            command_perf.duration_ms = Some(now - command_perf.start_time);
+            command_perf.duration_ms = Some(now.saturating_sub(command_perf.start_time));
             command_perf.exit_code = exit_code;
             command_perf.output_size = output_size;
 
-            // Store in command history
-            let terminal_id = "default".to_string(); // This should be properly tracked
+            // Store in the owning terminal's command history
+            let max_history = *self.max_command_history.lock().unwrap();
             let mut command_history = self.command_history.lock().unwrap();
-            let history = command_history.entry(terminal_id).or_insert_with(VecDeque::new);
+            let history = command_history.entry(command_perf.terminal_id.clone()).or_default();
             history.push_back(command_perf);
             
-            // Keep only last 1000 commands
-            if history.len() > 1000 {
+            // Evict the oldest commands once the cap is exceeded
+            while history.len() > max_history {
+                history.pop_front();
+            }
+        }
+    }
+
+    pub fn set_command_history_limit(&self, limit: usize) {
+        let limit = limit.max(1);
+        *self.max_command_history.lock().unwrap() = limit;
+
+        for history in self.command_history.lock().unwrap().values_mut() {
+            while history.len() > limit {
                 history.pop_front();
             }
         }
@@ -270,14 +356,158 @@ impl PerformanceMonitor {
         }
     }
 
-    pub fn get_command_history(&self, terminal_id: &str) -> Vec<CommandPerformance> {
+    pub fn get_command_history(&self, terminal_id: &str, sort_by: Option<CommandSortKey>, limit: Option<usize>) -> Vec<CommandPerformance> {
         let history = self.command_history.lock().unwrap();
         
-        if let Some(terminal_history) = history.get(terminal_id) {
+        let mut commands: Vec<CommandPerformance> = if let Some(terminal_history) = history.get(terminal_id) {
             terminal_history.iter().cloned().collect()
         } else {
             Vec::new()
+        };
+
+        // Heaviest/slowest first; chronological order is kept for StartTime
+        match sort_by.unwrap_or(CommandSortKey::StartTime) {
+            CommandSortKey::StartTime => {}
+            CommandSortKey::Duration => commands.sort_by_key(|c| std::cmp::Reverse(c.duration_ms)),
+            CommandSortKey::PeakMemory => commands.sort_by_key(|c| std::cmp::Reverse(c.memory_peak)),
+            CommandSortKey::PeakCpu => commands.sort_by(|a, b| b.cpu_peak.total_cmp(&a.cpu_peak)),
         }
+
+        if let Some(limit) = limit {
+            commands.truncate(limit);
+        }
+        commands
+    }
+
+    /// Exports the recorded commands of a terminal as a profiling timeline.
+    /// Commands whose run fully falls inside another one (e.g. commands run
+    /// from a subshell) are nested under it.
+    pub fn export_command_timeline(&self, terminal_id: &str, format: TimelineFormat) -> Result<CommandTimelineExport, String> {
+        let mut commands: Vec<CommandPerformance> = self
+            .get_command_history(terminal_id, Some(CommandSortKey::StartTime), None)
+            .into_iter()
+            .filter(|cmd| cmd.end_time.is_some())
+            .collect();
+        if commands.is_empty() {
+            return Err(format!("No completed commands recorded for terminal {}", terminal_id));
+        }
+        commands.sort_by(|a, b| a.start_time.cmp(&b.start_time).then(b.end_time.cmp(&a.end_time)));
+
+        // Resolve nesting: parents[i] is the index of the enclosing command, if any
+        let mut parents: Vec<Option<usize>> = Vec::with_capacity(commands.len());
+        let mut stack: Vec<usize> = Vec::new();
+        for (i, cmd) in commands.iter().enumerate() {
+            let end = cmd.end_time.unwrap_or(cmd.start_time);
+            while let Some(&top) = stack.last() {
+                if end <= commands[top].end_time.unwrap_or(commands[top].start_time) {
+                    break;
+                }
+                stack.pop();
+            }
+            parents.push(stack.last().copied());
+            stack.push(i);
+        }
+
+        let (schema, content) = match format {
+            TimelineFormat::ChromeTrace => {
+                let events: Vec<serde_json::Value> = commands
+                    .iter()
+                    .enumerate()
+                    .map(|(i, cmd)| {
+                        let mut depth = 0;
+                        let mut parent = parents[i];
+                        while let Some(p) = parent {
+                            depth += 1;
+                            parent = parents[p];
+                        }
+                        serde_json::json!({
+                            "name": cmd.command,
+                            "cat": "command",
+                            "ph": "X",
+                            "ts": cmd.start_time * 1000,
+                            "dur": cmd.duration_ms.unwrap_or(0) * 1000,
+                            "pid": 1,
+                            "tid": 1,
+                            "args": {
+                                "command_id": cmd.command_id,
+                                "exit_code": cmd.exit_code,
+                                "depth": depth,
+                                "memory_peak": cmd.memory_peak,
+                                "memory_avg": cmd.memory_avg,
+                                "cpu_peak": cmd.cpu_peak,
+                                "cpu_avg": cmd.cpu_avg,
+                                "output_size": cmd.output_size,
+                            },
+                        })
+                    })
+                    .collect();
+                let trace = serde_json::json!({
+                    "traceEvents": events,
+                    "displayTimeUnit": "ms",
+                    "otherData": { "terminal_id": terminal_id },
+                });
+                let schema = serde_json::json!({
+                    "description": "Chrome trace event format (chrome://tracing, Perfetto, speedscope). One complete event per command; nested commands share a track and nest by time containment.",
+                    "traceEvents[]": {
+                        "name": "command line as typed",
+                        "cat": "always \"command\"",
+                        "ph": "event phase, always \"X\" (complete event)",
+                        "ts": "start time, microseconds since the Unix epoch",
+                        "dur": "wall-clock duration in microseconds",
+                        "pid": "always 1",
+                        "tid": "always 1",
+                        "args.command_id": "shell-integration command id",
+                        "args.exit_code": "exit code or null when unknown",
+                        "args.depth": "nesting depth, 0 for top-level commands",
+                        "args.memory_peak": "peak RSS of the command's processes in bytes",
+                        "args.memory_avg": "average RSS in bytes",
+                        "args.cpu_peak": "peak CPU usage in percent of one core",
+                        "args.cpu_avg": "average CPU usage in percent of one core",
+                        "args.output_size": "bytes of output produced",
+                    },
+                });
+                (schema, serde_json::to_string_pretty(&trace).map_err(|e| e.to_string())?)
+            }
+            TimelineFormat::FoldedStacks => {
+                // Self time per command: its duration minus the time spent in nested commands
+                let mut self_time: Vec<u64> = commands.iter().map(|cmd| cmd.duration_ms.unwrap_or(0)).collect();
+                for (i, parent) in parents.iter().enumerate() {
+                    if let Some(p) = parent {
+                        self_time[*p] = self_time[*p].saturating_sub(commands[i].duration_ms.unwrap_or(0));
+                    }
+                }
+
+                let frame = |cmd: &CommandPerformance| -> String {
+                    cmd.command.replace(';', ",").replace(['\n', '\r'], " ").trim().to_string()
+                };
+                let mut lines = Vec::with_capacity(commands.len());
+                for (i, cmd) in commands.iter().enumerate() {
+                    let mut frames = vec![frame(cmd)];
+                    let mut parent = parents[i];
+                    while let Some(p) = parent {
+                        frames.push(frame(&commands[p]));
+                        parent = parents[p];
+                    }
+                    frames.reverse();
+                    lines.push(format!("{} {}", frames.join(";"), self_time[i]));
+                }
+                let schema = serde_json::json!({
+                    "description": "Folded stack format for flamegraph.pl / inferno / speedscope. One line per command.",
+                    "line": "<outer command>;<nested command>;... <self time>",
+                    "frames": "command lines, outermost first; ';' inside a command is replaced with ','",
+                    "value": "self time in milliseconds (duration minus nested commands)",
+                });
+                (schema, lines.join("\n"))
+            }
+        };
+
+        Ok(CommandTimelineExport {
+            terminal_id: terminal_id.to_string(),
+            format,
+            schema,
+            command_count: commands.len(),
+            content,
+        })
     }
 
     pub fn get_recent_alerts(&self, limit: Option<usize>) -> Vec<PerformanceAlert> {
@@ -338,12 +568,61 @@ impl PerformanceMonitor {
         })
     }
 
-    async fn get_process_resources(_command: &str) -> Result<ProcessResources, String> {
-        // Simplified process resource collection
-        Ok(ProcessResources {
-            memory: 100 * 1024 * 1024, // 100MB
-            cpu: 10.0, // 10% CPU
-        })
+    /// Sums RSS and CPU time over every descendant of the shell process,
+    /// i.e. the processes started for the command currently running in it.
+    #[cfg(unix)]
+    fn get_process_resources(shell_pid: u32) -> Result<ProcessResources, String> {
+        use std::fs;
+
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        let mut stats: HashMap<u32, (u64, u64)> = HashMap::new();
+
+        let entries = fs::read_dir("/proc").map_err(|e| format!("Failed to read /proc: {}", e))?;
+        for entry in entries.flatten() {
+            let pid = match entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) {
+                Some(pid) => pid,
+                None => continue,
+            };
+            if let Ok(stat) = fs::read_to_string(format!("/proc/{}/stat", pid)) {
+                // Fields after the parenthesised command name (which may contain spaces)
+                let fields: Vec<&str> = match stat.rfind(')') {
+                    Some(pos) => stat[pos + 1..].split_whitespace().collect(),
+                    None => continue,
+                };
+                if fields.len() < 22 {
+                    continue;
+                }
+                let ppid = fields[1].parse::<u32>().unwrap_or(0);
+                let utime = fields[11].parse::<u64>().unwrap_or(0);
+                let stime = fields[12].parse::<u64>().unwrap_or(0);
+                let rss_pages = fields[21].parse::<u64>().unwrap_or(0);
+                children.entry(ppid).or_default().push(pid);
+                stats.insert(pid, (utime + stime, rss_pages));
+            }
+        }
+
+        let ticks_per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as u64;
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as u64;
+
+        let mut resources = ProcessResources { memory: 0, cpu_time_ms: 0 };
+        let mut pending = children.get(&shell_pid).cloned().unwrap_or_default();
+        while let Some(pid) = pending.pop() {
+            if let Some((ticks, rss_pages)) = stats.get(&pid) {
+                resources.memory += rss_pages * page_size;
+                resources.cpu_time_ms += ticks * 1000 / ticks_per_sec;
+            }
+            if let Some(grandchildren) = children.get(&pid) {
+                pending.extend(grandchildren.iter().copied());
+            }
+        }
+
+        Ok(resources)
+    }
+
+    #[cfg(windows)]
+    fn get_process_resources(_shell_pid: u32) -> Result<ProcessResources, String> {
+        // Windows implementation would walk the job/process tree via the Windows API
+        Err("Per-command process sampling is not supported on this platform".to_string())
     }
 
     fn check_thresholds(
@@ -399,7 +678,7 @@ impl PerformanceMonitor {
 
 struct ProcessResources {
     memory: u64,
-    cpu: f64,
+    cpu_time_ms: u64,
 }
 
 // Tauri commands for performance monitoring
@@ -411,9 +690,28 @@ pub async fn get_performance_metrics(terminal_id: String, duration_seconds: Opti
 }
 
 #[tauri::command]
-pub async fn get_command_performance_history(terminal_id: String) -> Result<Vec<CommandPerformance>, String> {
-    // This would access the global performance monitor instance
-    Ok(vec![])
+pub async fn get_command_performance_history(
+    terminal_id: String,
+    sort_by: Option<CommandSortKey>,
+    limit: Option<usize>,
+    performance_monitor: State<'_, PerformanceMonitorState>,
+) -> Result<Vec<CommandPerformance>, String> {
+    Ok(performance_monitor
+        .lock()
+        .await
+        .get_command_history(&terminal_id, sort_by, limit))
+}
+
+#[tauri::command]
+pub async fn export_command_timeline(
+    session_id: String,
+    format: TimelineFormat,
+    performance_monitor: State<'_, PerformanceMonitorState>,
+) -> Result<CommandTimelineExport, String> {
+    performance_monitor
+        .lock()
+        .await
+        .export_command_timeline(&session_id, format)
 }
 
 #[tauri::command]
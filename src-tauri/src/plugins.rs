@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use std::{collections::{HashMap, HashSet}, fs, path::PathBuf, process::Stdio, sync::{Arc, Mutex}, time::Duration};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::{mpsc, oneshot};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PluginManifest {
@@ -7,6 +10,99 @@ pub struct PluginManifest {
     pub version: String,
     pub description: Option<String>,
     pub workflows: Option<Vec<crate::workflows::Workflow>>, // optional bundled workflows
+    pub events: Option<Vec<PluginEvent>>, // lifecycle events the handler subscribes to
+    pub handler: Option<PluginHandler>,
+    pub commands: Option<Vec<PluginCommand>>, // command palette entries
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PluginCommand {
+    pub id: String,
+    pub title: String,
+    pub keybinding: Option<String>,
+    pub category: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PaletteCommand {
+    pub plugin_id: String,
+    pub id: String,
+    pub title: String,
+    pub keybinding: Option<String>,
+    pub category: Option<String>,
+    pub keybinding_conflicts: Vec<String>, // "plugin_id:command_id" of commands bound to the same keys
+}
+
+/// What the handler answers to an `invoke_command` message.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PluginCommandResult {
+    pub insert_text: Option<String>,
+    pub notification: Option<PluginNotification>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PluginNotification {
+    pub title: String,
+    pub body: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginEvent {
+    OnCommandStart,
+    OnCommandEnd,
+    OnOutputChunk,
+    OnPrompt,
+    InvokeCommand,
+}
+
+/// Subprocess speaking newline-delimited JSON over stdio. Each event is sent
+/// as `{"event": ..., "payload": ...}` and must be acknowledged with one line
+/// (e.g. `{"ok": true}`) within `timeout_ms`. For `invoke_command` the line is
+/// read as a `PluginCommandResult`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PluginHandler {
+    pub command: String,
+    pub args: Option<Vec<String>>,
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PluginStatus {
+    pub name: String,
+    pub enabled: bool,
+    pub running: bool,
+    pub failures: u32,
+    pub dropped_events: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct PluginWireMessage<'a> {
+    event: &'a PluginEvent,
+    payload: &'a serde_json::Value,
+}
+
+struct PluginMessage {
+    event: PluginEvent,
+    payload: serde_json::Value,
+    reply: Option<oneshot::Sender<Result<PluginCommandResult, String>>>,
+}
+
+const PLUGIN_QUEUE_SIZE: usize = 256;
+const DEFAULT_PLUGIN_TIMEOUT_MS: u64 = 2000;
+const MAX_PLUGIN_FAILURES: u32 = 3;
+
+struct RunningPlugin {
+    events: HashSet<PluginEvent>,
+    commands: Vec<PluginCommand>,
+    sender: mpsc::Sender<PluginMessage>,
+    status: Arc<Mutex<PluginStatus>>,
+}
+
+pub struct PluginHost {
+    enabled: HashSet<String>,
+    running: HashMap<String, RunningPlugin>,
 }
 
 fn plugins_dir() -> PathBuf {
@@ -18,6 +114,10 @@ fn plugins_dir() -> PathBuf {
     PathBuf::from(home).join(".warp-terminal").join("plugins")
 }
 
+fn enabled_plugins_path() -> PathBuf {
+    plugins_dir().with_file_name("plugins-enabled.json")
+}
+
 pub fn list_plugins() -> Vec<PluginManifest> {
     let dir = plugins_dir();
     let mut out = Vec::new();
@@ -30,3 +130,353 @@ pub fn list_plugins() -> Vec<PluginManifest> {
     }
     out
 }
+
+impl PluginHost {
+    pub fn new() -> Self {
+        let enabled = fs::read_to_string(enabled_plugins_path())
+            .ok()
+            .and_then(|s| serde_json::from_str::<HashSet<String>>(&s).ok())
+            .unwrap_or_default();
+        PluginHost { enabled, running: HashMap::new() }
+    }
+
+    /// Starts the handlers of all plugins that were enabled in a previous run.
+    pub fn start_enabled(&mut self) {
+        for manifest in list_plugins() {
+            if self.enabled.contains(&manifest.name) {
+                if let Err(e) = self.start(manifest) {
+                    log::warn!("Failed to start plugin: {}", e);
+                }
+            }
+        }
+    }
+
+    pub fn enable_plugin(&mut self, name: &str) -> Result<(), String> {
+        let manifest = list_plugins()
+            .into_iter()
+            .find(|m| m.name == name)
+            .ok_or_else(|| format!("Plugin not found: {}", name))?;
+        self.enabled.insert(name.to_string());
+        self.save_enabled()?;
+        self.start_or_reset(manifest)
+    }
+
+    // Enabling gives a plugin a clean slate: a running handler has its failure
+    // count cleared, and one stopped after repeated failures is started afresh
+    fn start_or_reset(&mut self, manifest: PluginManifest) -> Result<(), String> {
+        if let Some(plugin) = self.running.get(&manifest.name) {
+            let mut status = plugin.status.lock().unwrap();
+            if status.running {
+                status.failures = 0;
+                status.last_error = None;
+                return Ok(());
+            }
+        }
+        self.start(manifest)
+    }
+
+    pub fn disable_plugin(&mut self, name: &str) -> Result<(), String> {
+        self.enabled.remove(name);
+        // Dropping the sender ends the worker, which kills the handler process
+        self.running.remove(name);
+        self.save_enabled()
+    }
+
+    pub fn get_statuses(&self) -> Vec<PluginStatus> {
+        list_plugins()
+            .into_iter()
+            .map(|m| match self.running.get(&m.name) {
+                Some(plugin) => plugin.status.lock().unwrap().clone(),
+                None => PluginStatus {
+                    enabled: self.enabled.contains(&m.name),
+                    name: m.name,
+                    running: false,
+                    failures: 0,
+                    dropped_events: 0,
+                    last_error: None,
+                },
+            })
+            .collect()
+    }
+
+    /// Hands an event to every subscribed plugin without waiting on it. A plugin
+    /// whose queue is full has the event dropped so terminal output never blocks.
+    pub fn dispatch(&self, event: PluginEvent, payload: serde_json::Value) {
+        for plugin in self.running.values() {
+            if !plugin.events.contains(&event) {
+                continue;
+            }
+            let message = PluginMessage { event: event.clone(), payload: payload.clone(), reply: None };
+            if plugin.sender.try_send(message).is_err() {
+                plugin.status.lock().unwrap().dropped_events += 1;
+            }
+        }
+    }
+
+    /// Palette entries declared by enabled plugins, with keybinding clashes across plugins flagged.
+    pub fn get_plugin_commands(&self) -> Vec<PaletteCommand> {
+        let mut commands: Vec<PaletteCommand> = self
+            .running
+            .iter()
+            .flat_map(|(plugin_id, plugin)| {
+                plugin.commands.iter().map(move |c| PaletteCommand {
+                    plugin_id: plugin_id.clone(),
+                    id: c.id.clone(),
+                    title: c.title.clone(),
+                    keybinding: c.keybinding.clone(),
+                    category: c.category.clone(),
+                    keybinding_conflicts: Vec::new(),
+                })
+            })
+            .collect();
+
+        let mut by_keys: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, command) in commands.iter().enumerate() {
+            if let Some(keys) = command.keybinding.as_deref().and_then(normalize_keybinding) {
+                by_keys.entry(keys).or_default().push(i);
+            }
+        }
+        for indices in by_keys.values().filter(|indices| indices.len() > 1) {
+            for &i in indices {
+                let conflicts = indices
+                    .iter()
+                    .filter(|&&j| j != i && commands[j].plugin_id != commands[i].plugin_id)
+                    .map(|&j| format!("{}:{}", commands[j].plugin_id, commands[j].id))
+                    .collect();
+                commands[i].keybinding_conflicts = conflicts;
+            }
+        }
+
+        commands.sort_by(|a, b| a.category.cmp(&b.category).then(a.title.cmp(&b.title)));
+        commands
+    }
+
+    /// Queues a palette command on the plugin's handler; await the returned receiver
+    /// after releasing the host lock.
+    pub fn invoke_plugin_command(
+        &self,
+        plugin_id: &str,
+        command_id: &str,
+        args: serde_json::Value,
+    ) -> Result<oneshot::Receiver<Result<PluginCommandResult, String>>, String> {
+        let plugin = self
+            .running
+            .get(plugin_id)
+            .ok_or_else(|| format!("Plugin {} is not enabled", plugin_id))?;
+        if !plugin.commands.iter().any(|c| c.id == command_id) {
+            return Err(format!("Plugin {} has no command {}", plugin_id, command_id));
+        }
+
+        let (reply, receiver) = oneshot::channel();
+        let message = PluginMessage {
+            event: PluginEvent::InvokeCommand,
+            payload: serde_json::json!({ "command_id": command_id, "args": args }),
+            reply: Some(reply),
+        };
+        plugin
+            .sender
+            .try_send(message)
+            .map_err(|_| format!("Plugin {} is busy or not running", plugin_id))?;
+        Ok(receiver)
+    }
+
+    fn save_enabled(&self) -> Result<(), String> {
+        let path = enabled_plugins_path();
+        if let Some(parent) = path.parent() { fs::create_dir_all(parent).map_err(|e| e.to_string())?; }
+        let json = serde_json::to_string_pretty(&self.enabled).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    fn start(&mut self, manifest: PluginManifest) -> Result<(), String> {
+        let handler = manifest
+            .handler
+            .clone()
+            .ok_or_else(|| format!("Plugin {} has no handler", manifest.name))?;
+        let events: HashSet<PluginEvent> = manifest.events.clone().unwrap_or_default().into_iter().collect();
+        let commands = manifest.commands.clone().unwrap_or_default();
+        let status = Arc::new(Mutex::new(PluginStatus {
+            name: manifest.name.clone(),
+            enabled: true,
+            running: true,
+            failures: 0,
+            dropped_events: 0,
+            last_error: None,
+        }));
+        let (sender, receiver) = mpsc::channel(PLUGIN_QUEUE_SIZE);
+
+        tauri::async_runtime::spawn(run_plugin_worker(handler, receiver, status.clone()));
+
+        self.running.insert(manifest.name.clone(), RunningPlugin { events, commands, sender, status });
+        Ok(())
+    }
+}
+
+async fn run_plugin_worker(
+    handler: PluginHandler,
+    mut receiver: mpsc::Receiver<PluginMessage>,
+    status: Arc<Mutex<PluginStatus>>,
+) {
+    let timeout = Duration::from_millis(handler.timeout_ms.unwrap_or(DEFAULT_PLUGIN_TIMEOUT_MS));
+    let plugins_dir = plugins_dir();
+    let spawned = Command::new(&handler.command)
+        .args(handler.args.clone().unwrap_or_default())
+        .current_dir(if plugins_dir.exists() { plugins_dir } else { PathBuf::from(".") })
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn();
+
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            let mut status = status.lock().unwrap();
+            status.running = false;
+            status.last_error = Some(format!("Failed to spawn handler: {}", e));
+            return;
+        }
+    };
+    let (mut stdin, stdout) = match (child.stdin.take(), child.stdout.take()) {
+        (Some(stdin), Some(stdout)) => (stdin, stdout),
+        _ => {
+            status.lock().unwrap().running = false;
+            return;
+        }
+    };
+    let mut lines = BufReader::new(stdout).lines();
+
+    while let Some(message) = receiver.recv().await {
+        let wire = PluginWireMessage { event: &message.event, payload: &message.payload };
+        let line = match serde_json::to_string(&wire) {
+            Ok(line) => line + "\n",
+            Err(_) => continue,
+        };
+
+        let exchange = async {
+            stdin.write_all(line.as_bytes()).await.map_err(|e| e.to_string())?;
+            stdin.flush().await.map_err(|e| e.to_string())?;
+            match lines.next_line().await {
+                Ok(Some(response)) => Ok(response),
+                Ok(None) => Err("Handler closed its output".to_string()),
+                Err(e) => Err(e.to_string()),
+            }
+        };
+
+        let result = match tokio::time::timeout(timeout, exchange).await {
+            Ok(result) => result,
+            Err(_) => Err(format!("Handler did not respond within {}ms", timeout.as_millis())),
+        };
+
+        if let Some(reply) = message.reply {
+            let command_result = result.clone().and_then(|response| {
+                serde_json::from_str::<PluginCommandResult>(&response)
+                    .map_err(|e| format!("Invalid command response: {}", e))
+            });
+            let _ = reply.send(command_result);
+        }
+
+        if let Err(error) = result {
+            let mut status = status.lock().unwrap();
+            status.failures += 1;
+            status.last_error = Some(error);
+            if status.failures >= MAX_PLUGIN_FAILURES {
+                status.running = false;
+                break;
+            }
+        }
+    }
+
+    // Isolate a misbehaving or disabled plugin by terminating its process
+    let _ = child.kill().await;
+    status.lock().unwrap().running = false;
+}
+
+/// Canonical form of a keybinding so "Shift+Ctrl+P" and "ctrl+shift+p" compare equal.
+fn normalize_keybinding(keybinding: &str) -> Option<String> {
+    let mut parts: Vec<String> = keybinding
+        .split('+')
+        .map(|p| p.trim().to_lowercase())
+        .filter(|p| !p.is_empty())
+        .collect();
+    let key = parts.pop()?;
+    parts.sort();
+    parts.dedup();
+    parts.push(key);
+    Some(parts.join("+"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(script: &str, timeout_ms: u64) -> PluginManifest {
+        PluginManifest {
+            name: "flaky".to_string(),
+            version: "1.0.0".to_string(),
+            description: None,
+            workflows: None,
+            events: Some(vec![PluginEvent::OnPrompt]),
+            handler: Some(PluginHandler {
+                command: "sh".to_string(),
+                args: Some(vec!["-c".to_string(), script.to_string()]),
+                timeout_ms: Some(timeout_ms),
+            }),
+            commands: None,
+        }
+    }
+
+    fn status(host: &PluginHost) -> PluginStatus {
+        host.running["flaky"].status.lock().unwrap().clone()
+    }
+
+    async fn wait_for(host: &PluginHost, done: impl Fn(&PluginStatus) -> bool) -> PluginStatus {
+        for _ in 0..200 {
+            let current = status(host);
+            if done(&current) {
+                return current;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("plugin never reached the expected state: {:?}", status(host));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn re_enabling_a_plugin_stopped_for_failures_starts_it_with_a_clean_count() {
+        let mut host = PluginHost { enabled: HashSet::new(), running: HashMap::new() };
+        // Exits straight away, so every event fails
+        let broken = manifest("exit 0", 1000);
+        host.start(broken.clone()).unwrap();
+        for _ in 0..MAX_PLUGIN_FAILURES {
+            host.dispatch(PluginEvent::OnPrompt, serde_json::json!({}));
+        }
+        let stopped = wait_for(&host, |s| !s.running).await;
+        assert_eq!(stopped.failures, MAX_PLUGIN_FAILURES);
+        assert!(stopped.last_error.is_some());
+
+        host.start_or_reset(broken).unwrap();
+        let restarted = status(&host);
+        assert!(restarted.running);
+        assert_eq!((restarted.failures, restarted.last_error), (0, None));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn re_enabling_a_running_plugin_clears_its_failures() {
+        let mut host = PluginHost { enabled: HashSet::new(), running: HashMap::new() };
+        // Reads events but never answers, so each one times out
+        let silent = manifest("while read line; do :; done", 50);
+        host.start(silent.clone()).unwrap();
+        host.dispatch(PluginEvent::OnPrompt, serde_json::json!({}));
+        let failing = wait_for(&host, |s| s.failures == 1).await;
+        assert!(failing.running);
+
+        host.start_or_reset(silent).unwrap();
+        let reset = status(&host);
+        assert!(reset.running);
+        assert_eq!((reset.failures, reset.last_error), (0, None));
+        // The same handler keeps going, and failures count from zero again
+        host.dispatch(PluginEvent::OnPrompt, serde_json::json!({}));
+        assert_eq!(wait_for(&host, |s| s.failures == 1).await.failures, 1);
+    }
+}
@@ -1,10 +1,13 @@
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::collections::HashMap;
 use std::io::{Read, Write};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
-use portable_pty::{native_pty_system, CommandBuilder, PtySize, MasterPty};
+use portable_pty::{native_pty_system, Child, CommandBuilder, PtySize, MasterPty};
+use encoding_rs::{Decoder, Encoding};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TerminalSize {
@@ -20,23 +23,560 @@ pub struct TerminalOutput {
     pub data: String,
 }
 
+/// Coalesces PTY chunks so bulk output crosses the IPC boundary as fewer,
+/// larger events. A lone chunk (typing echo) is released immediately; once
+/// more data is queued, chunks are gathered until `window` elapses or
+/// `max_bytes` is reached.
+pub struct OutputCoalescer {
+    receiver: mpsc::UnboundedReceiver<TerminalOutput>,
+    window: std::time::Duration,
+    max_bytes: usize,
+}
+
+impl OutputCoalescer {
+    pub fn new(receiver: mpsc::UnboundedReceiver<TerminalOutput>, window_ms: u64, max_bytes: usize) -> Self {
+        OutputCoalescer {
+            receiver,
+            window: std::time::Duration::from_millis(window_ms),
+            max_bytes: max_bytes.max(1),
+        }
+    }
+
+    /// Next batch with at most one merged chunk per session, or `None` once the PTY side is gone.
+    pub async fn next_batch(&mut self) -> Option<Vec<TerminalOutput>> {
+        let first = self.receiver.recv().await?;
+        let mut total = first.data.len();
+        let mut batch = vec![first];
+
+        let mut queued = false;
+        while total < self.max_bytes {
+            match self.receiver.try_recv() {
+                Ok(output) => {
+                    total += output.data.len();
+                    Self::merge(&mut batch, output);
+                    queued = true;
+                }
+                Err(_) => break,
+            }
+        }
+
+        if queued && !self.window.is_zero() {
+            let deadline = tokio::time::Instant::now() + self.window;
+            while total < self.max_bytes {
+                match tokio::time::timeout_at(deadline, self.receiver.recv()).await {
+                    Ok(Some(output)) => {
+                        total += output.data.len();
+                        Self::merge(&mut batch, output);
+                    }
+                    // Closed channel or window elapsed: flush what we have
+                    _ => break,
+                }
+            }
+        }
+
+        Some(batch)
+    }
+
+    fn merge(batch: &mut Vec<TerminalOutput>, output: TerminalOutput) {
+        match batch.iter_mut().find(|o| o.session_id == output.session_id) {
+            Some(existing) => existing.data.push_str(&output.data),
+            None => batch.push(output),
+        }
+    }
+}
+
+// Unread output a session may have in the channel before its reader stops pulling from the PTY
+const DEFAULT_OUTPUT_BACKLOG_BYTES: usize = 4 * 1024 * 1024;
+// Resizes arriving within this window (e.g. while dragging the window edge)
+// reach the child as one SIGWINCH with the final size
+const RESIZE_DEBOUNCE: Duration = Duration::from_millis(40);
+// How long a closed session's shell has to exit on SIGHUP before it's killed
+const CLOSE_GRACE: Duration = Duration::from_millis(300);
+// A session counts as throttled until it has gone this long without waiting
+const THROTTLE_RELEASE: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputThrottleEvent {
+    pub session_id: String,
+    pub throttled: bool,
+}
+
+/// Flow control between the PTY reader threads and the output consumer. Rather
+/// than buffering without bound, a reader stops reading while the consumer is
+/// behind on its session's output, which blocks the child on its own writes, and
+/// paces itself to `max_bytes_per_sec` (0 disables the rate cap). Backlogs are
+/// per session so one flooding command never stalls the other sessions.
+pub struct OutputThrottle {
+    max_bytes_per_sec: AtomicU64,
+    max_backlog: usize,
+    backlog: Mutex<HashMap<String, usize>>, // session -> bytes sent but not yet consumed
+    drained: Condvar,
+    throttled: Mutex<HashMap<String, Instant>>, // session -> last time its reader had to wait
+    events: Mutex<Vec<OutputThrottleEvent>>,
+}
+
+impl OutputThrottle {
+    pub fn new(max_bytes_per_sec: u64, max_backlog: usize) -> Self {
+        OutputThrottle {
+            max_bytes_per_sec: AtomicU64::new(max_bytes_per_sec),
+            max_backlog: max_backlog.max(1),
+            backlog: Mutex::new(HashMap::new()),
+            drained: Condvar::new(),
+            throttled: Mutex::new(HashMap::new()),
+            events: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn max_bytes_per_sec(&self) -> u64 {
+        self.max_bytes_per_sec.load(Ordering::Relaxed)
+    }
+
+    pub fn set_max_bytes_per_sec(&self, max_bytes_per_sec: u64) {
+        self.max_bytes_per_sec.store(max_bytes_per_sec, Ordering::Relaxed);
+    }
+
+    fn produced(&self, session_id: &str, bytes: usize) {
+        *self.backlog.lock().unwrap().entry(session_id.to_string()).or_insert(0) += bytes;
+    }
+
+    /// Called by the consumer once output has been handed to the frontend and the terminal model.
+    pub fn consumed(&self, session_id: &str, bytes: usize) {
+        let mut backlog = self.backlog.lock().unwrap();
+        let Some(pending) = backlog.get_mut(session_id) else { return };
+        *pending = pending.saturating_sub(bytes);
+        let drained = *pending < self.max_backlog;
+        if *pending == 0 {
+            backlog.remove(session_id);
+        }
+        if drained {
+            self.drained.notify_all();
+        }
+    }
+
+    /// Blocks while the session's backlog is full; returns whether the caller had to wait.
+    fn wait_for_consumer(&self, session_id: &str) -> bool {
+        let mut backlog = self.backlog.lock().unwrap();
+        let mut waited = false;
+        while backlog.get(session_id).is_some_and(|pending| *pending >= self.max_backlog) {
+            waited = true;
+            // Timed wait so a reader never hangs if the consumer goes away
+            backlog = self.drained.wait_timeout(backlog, Duration::from_millis(100)).unwrap().0;
+        }
+        waited
+    }
+
+    fn mark_throttled(&self, session_id: &str) {
+        let newly = self
+            .throttled
+            .lock()
+            .unwrap()
+            .insert(session_id.to_string(), Instant::now())
+            .is_none();
+        if newly {
+            self.events.lock().unwrap().push(OutputThrottleEvent { session_id: session_id.to_string(), throttled: true });
+        }
+    }
+
+    /// Throttle state changes since the last call, including sessions that have recovered.
+    pub fn take_events(&self) -> Vec<OutputThrottleEvent> {
+        let mut events = std::mem::take(&mut *self.events.lock().unwrap());
+        self.throttled.lock().unwrap().retain(|session_id, last_wait| {
+            let active = last_wait.elapsed() < THROTTLE_RELEASE;
+            if !active {
+                events.push(OutputThrottleEvent { session_id: session_id.clone(), throttled: false });
+            }
+            active
+        });
+        events
+    }
+}
+
+// Output held back from a paused command beyond this is dropped
+const MAX_HELD_OUTPUT_BYTES: usize = 64 * 1024 * 1024;
+// A truncate or show-full decision lasts until the session has been quiet this long
+const OUTPUT_GUARD_IDLE_RESET: Duration = Duration::from_secs(2);
+// Most of a truncated command's unfinished last line kept for showing the prompt
+const MAX_HELD_LAST_LINE_BYTES: usize = 4096;
+// OSC 133 prompt start, where a shell-integrated prompt begins
+const PROMPT_START_MARK: &str = "\x1b]133;A";
+
+#[derive(Debug, Clone)]
+pub struct OutputGuardLimits {
+    pub max_lines: usize, // 0 = no line limit
+    pub max_bytes: usize, // 0 = no byte limit
+    pub window: Duration,
+    pub allowlist: Vec<String>, // program names that are never paused
+}
+
+impl OutputGuardLimits {
+    pub fn from_settings(settings: &crate::settings::Settings) -> Self {
+        OutputGuardLimits {
+            max_lines: settings.large_output_max_lines,
+            max_bytes: settings.large_output_max_bytes,
+            window: Duration::from_millis(settings.large_output_window_ms),
+            allowlist: settings.large_output_allowlist.clone(),
+        }
+    }
+}
+
+/// Emitted as `large-output-warning` when a session's output is paused.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LargeOutputWarning {
+    pub session_id: String,
+    pub command: Option<String>,
+    pub lines: usize,
+    pub bytes: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LargeOutputAction {
+    Truncate, // keep hiding the command's output; it stays available for ShowFull
+    ShowFull, // reveal everything held back and let the rest through
+}
+
+pub enum GuardVerdict {
+    Pass,
+    Hold,
+    Pause(LargeOutputWarning),
+    Resume(String), // show this now; the truncated command has finished
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GuardState {
+    Watching,
+    Paused,
+    Truncated,
+    Released,
+}
+
+struct GuardedSession {
+    state: GuardState,
+    window_start: Instant,
+    last_output: Instant,
+    lines: usize,
+    bytes: usize,
+    held: String,
+    dropped: usize, // bytes over MAX_HELD_OUTPUT_BYTES
+    last_line: String, // held text after the last newline, likely the prompt once the command ends
+}
+
+impl GuardedSession {
+    // Start counting afresh for the next command
+    fn watch(&mut self, now: Instant) {
+        self.state = GuardState::Watching;
+        self.window_start = now;
+        self.lines = 0;
+        self.bytes = 0;
+        self.last_line.clear();
+    }
+
+    fn hold(&mut self, data: &str) {
+        if self.held.len() + data.len() <= MAX_HELD_OUTPUT_BYTES {
+            self.held.push_str(data);
+        } else {
+            self.dropped += data.len();
+        }
+    }
+
+    fn note_last_line(&mut self, data: &str) {
+        match data.rfind('\n') {
+            Some(newline) => {
+                self.last_line.clear();
+                self.last_line.push_str(&data[newline + 1..]);
+            }
+            None => self.last_line.push_str(data),
+        }
+        if self.last_line.len() > MAX_HELD_LAST_LINE_BYTES {
+            let mut cut = self.last_line.len() - MAX_HELD_LAST_LINE_BYTES;
+            while !self.last_line.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.last_line.drain(..cut);
+        }
+    }
+
+    fn take_held(&mut self) -> String {
+        let mut held = std::mem::take(&mut self.held);
+        if self.dropped > 0 {
+            held.push_str(&format!("\r\n[{} bytes of output were too large to keep]\r\n", self.dropped));
+            self.dropped = 0;
+        }
+        held
+    }
+}
+
+/// Pauses a command whose output floods the terminal (a binary file, a runaway
+/// loop) so the frontend can offer to truncate or show it. Held output is kept
+/// so "show full" can still reveal it later. Only what reaches the frontend is
+/// guarded: the terminal model sees every chunk before it gets here.
+pub struct OutputGuard {
+    limits: OutputGuardLimits,
+    sessions: HashMap<String, GuardedSession>,
+}
+
+impl OutputGuard {
+    pub fn new(limits: OutputGuardLimits) -> Self {
+        OutputGuard { limits, sessions: HashMap::new() }
+    }
+
+    pub fn set_limits(&mut self, limits: OutputGuardLimits) {
+        self.limits = limits;
+    }
+
+    /// Decide what the frontend sees of a chunk. `command` is the running command
+    /// line, if known; full-screen programs and allowlisted commands are never
+    /// paused. Once truncated, the command ends when the shell is back at a prompt
+    /// (`at_prompt`, judged after the model took this chunk) or falls quiet.
+    pub fn inspect(
+        &mut self,
+        session_id: &str,
+        data: &str,
+        command: Option<&str>,
+        full_screen: bool,
+        at_prompt: bool,
+    ) -> GuardVerdict {
+        let now = Instant::now();
+        let session = self.sessions.entry(session_id.to_string()).or_insert_with(|| GuardedSession {
+            state: GuardState::Watching,
+            window_start: now,
+            last_output: now,
+            lines: 0,
+            bytes: 0,
+            held: String::new(),
+            dropped: 0,
+            last_line: String::new(),
+        });
+        let idle = now.duration_since(session.last_output) >= OUTPUT_GUARD_IDLE_RESET;
+        session.last_output = now;
+        if idle && session.state == GuardState::Truncated {
+            // The prompt went unrecognized; show the held last line along with the new output
+            let last_line = std::mem::take(&mut session.last_line);
+            session.watch(now);
+            return GuardVerdict::Resume(format!("\r\n{}{}", last_line, data));
+        }
+        if idle && session.state == GuardState::Released {
+            session.watch(now);
+        }
+
+        match session.state {
+            GuardState::Released => return GuardVerdict::Pass,
+            GuardState::Paused => {
+                session.hold(data);
+                return GuardVerdict::Hold;
+            }
+            GuardState::Truncated if at_prompt => {
+                // Show from the prompt mark, else from the last line, which may have started in an earlier chunk
+                let (split, continues_last_line) = match data.rfind(PROMPT_START_MARK) {
+                    Some(mark) => (mark, false),
+                    None => data.rfind('\n').map_or((0, true), |newline| (newline + 1, false)),
+                };
+                let (output, tail) = data.split_at(split);
+                let prompt = if continues_last_line { format!("{}{}", session.last_line, tail) } else { tail.to_string() };
+                session.hold(output);
+                session.watch(now);
+                return GuardVerdict::Resume(format!("\r\n{}", prompt));
+            }
+            GuardState::Truncated => {
+                session.hold(data);
+                session.note_last_line(data);
+                return GuardVerdict::Hold;
+            }
+            GuardState::Watching => {}
+        }
+
+        let program = command
+            .and_then(|command| command.split_whitespace().find(|word| !word.contains('=') && *word != "sudo"))
+            .map(|word| word.rsplit(['/', '\\']).next().unwrap_or(word));
+        let allowed = program.is_some_and(|program| self.limits.allowlist.iter().any(|allowed| allowed == program));
+        if full_screen || allowed {
+            return GuardVerdict::Pass;
+        }
+
+        if now.duration_since(session.window_start) > self.limits.window {
+            session.window_start = now;
+            session.lines = 0;
+            session.bytes = 0;
+        }
+        session.lines += data.matches('\n').count();
+        session.bytes += data.len();
+        let too_many_lines = self.limits.max_lines > 0 && session.lines > self.limits.max_lines;
+        let too_many_bytes = self.limits.max_bytes > 0 && session.bytes > self.limits.max_bytes;
+        if !too_many_lines && !too_many_bytes {
+            return GuardVerdict::Pass;
+        }
+
+        session.state = GuardState::Paused;
+        session.held.clear();
+        session.dropped = 0;
+        session.hold(data);
+        GuardVerdict::Pause(LargeOutputWarning {
+            session_id: session_id.to_string(),
+            command: command.map(str::to_string),
+            lines: session.lines,
+            bytes: session.bytes,
+        })
+    }
+
+    /// Apply the user's answer to a warning. Returns output to show now: the
+    /// held output for `ShowFull`, a marker line for `Truncate`.
+    pub fn resolve(&mut self, session_id: &str, action: LargeOutputAction) -> Result<String, String> {
+        let session = self
+            .sessions
+            .get_mut(session_id)
+            .filter(|session| session.state != GuardState::Watching || !session.held.is_empty())
+            .ok_or_else(|| format!("No held output for terminal {}", session_id))?;
+        match action {
+            LargeOutputAction::Truncate => {
+                if session.state != GuardState::Paused {
+                    return Ok(String::new());
+                }
+                session.state = GuardState::Truncated;
+                Ok("\r\n\x1b[2m[output truncated]\x1b[0m\r\n".to_string())
+            }
+            LargeOutputAction::ShowFull => {
+                if session.state != GuardState::Watching {
+                    session.state = GuardState::Released;
+                }
+                Ok(session.take_held())
+            }
+        }
+    }
+
+    pub fn remove_session(&mut self, session_id: &str) {
+        self.sessions.remove(session_id);
+    }
+}
+
+/// How to launch a session's process. The parent environment is inherited and
+/// `env` is overlaid on top of it.
+///
+/// The working directory is the first of: `cwd` (an error if it isn't a
+/// directory), the app's current directory, then the home directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShellOptions {
+    pub shell: Option<String>, // program name on PATH or a path; defaults to $SHELL
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub cwd: Option<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub login_shell: bool, // prepends -l (ignored for cmd.exe and Windows PowerShell)
+    #[serde(default)]
+    pub encoding: Option<String>, // label of the shell's output encoding; UTF-8 when unset
+    #[serde(default)]
+    pub no_shell_integration: bool, // don't inject the OSC 133/OSC 7 integration script
+}
+
+impl ShellOptions {
+    /// The shell to launch, checked to exist either as a path or on PATH.
+    pub fn resolve_shell(&self) -> Result<String, String> {
+        let shell = match self.shell.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            Some(shell) => shell.to_string(),
+            None if cfg!(windows) => "powershell.exe".to_string(), // $SHELL may be an MSYS path ConPTY cannot spawn
+            None => std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string()),
+        };
+
+        let path = std::path::Path::new(&shell);
+        let found = if path.components().count() > 1 {
+            path.is_file()
+        } else {
+            find_on_path(&shell).is_some()
+        };
+        if found {
+            Ok(shell)
+        } else {
+            Err(format!("Shell not found: {}", shell))
+        }
+    }
+
+    pub fn resolve_working_dir(&self) -> Result<String, String> {
+        if let Some(cwd) = &self.cwd {
+            return if std::path::Path::new(cwd).is_dir() {
+                Ok(cwd.clone())
+            } else {
+                Err(format!("Working directory does not exist: {}", cwd))
+            };
+        }
+        let dir = std::env::current_dir().ok().or_else(|| {
+            std::env::var(if cfg!(windows) { "USERPROFILE" } else { "HOME" }).ok().map(Into::into)
+        });
+        Ok(dir.unwrap_or_default().to_string_lossy().to_string())
+    }
+
+    fn command_args(&self, shell: &str) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.login_shell {
+            let program = std::path::Path::new(shell)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            if program != "cmd" && program != "powershell" {
+                args.push("-l".to_string());
+            }
+        }
+        args.extend(self.args.iter().cloned());
+        args
+    }
+}
+
+fn find_on_path(program: &str) -> Option<std::path::PathBuf> {
+    let extensions: Vec<String> = if cfg!(windows) {
+        std::env::var("PATHEXT")
+            .unwrap_or_else(|_| ".EXE;.CMD;.BAT".to_string())
+            .split(';')
+            .map(|ext| ext.to_string())
+            .chain(std::iter::once(String::new()))
+            .collect()
+    } else {
+        vec![String::new()]
+    };
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).find_map(|dir| {
+        extensions.iter().find_map(|ext| {
+            let candidate = dir.join(format!("{}{}", program, ext));
+            candidate.is_file().then_some(candidate)
+        })
+    })
+}
+
 #[derive(Debug, Clone)]
 pub struct PtySession {
     pub id: String,
     pub size: TerminalSize,
     pub shell: String,
     pub working_dir: String,
+    pub pid: Option<u32>,
+}
+
+/// The encoding for a label such as `latin1` or `shift_jis` (WHATWG labels).
+pub fn lookup_encoding(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(label.trim().as_bytes()).ok_or_else(|| format!("Unknown encoding: {}", label))
+}
+
+// Transcode a chunk of PTY output to UTF-8. The decoder keeps a multibyte
+// sequence split across reads until the rest of it arrives.
+pub(crate) fn decode_output(decoder: &mut Decoder, bytes: &[u8], last: bool) -> String {
+    let capacity = decoder.max_utf8_buffer_length(bytes.len()).unwrap_or(bytes.len() * 3);
+    let mut data = String::with_capacity(capacity);
+    let _ = decoder.decode_to_string(bytes, &mut data, last);
+    data
 }
 
 pub struct PtyProcess {
     pub session: PtySession,
     pub writer: Arc<tokio::sync::Mutex<Option<Box<dyn std::io::Write + Send>>>>,
     pub master: Arc<std::sync::Mutex<Option<Box<dyn MasterPty + Send>>>>,
+    pending_resize: Arc<Mutex<Option<PtySize>>>, // latest size not yet applied to the PTY
+    encoding: Arc<Mutex<&'static Encoding>>, // read by the session's reader thread
+    child: Box<dyn Child + Send + Sync>,
 }
 
 pub struct PtyManager {
     processes: Arc<Mutex<HashMap<String, PtyProcess>>>,
     output_sender: mpsc::UnboundedSender<TerminalOutput>,
+    output_throttle: Arc<OutputThrottle>,
 }
 
 impl PtyManager {
@@ -45,48 +585,45 @@ impl PtyManager {
         let manager = PtyManager {
             processes: Arc::new(Mutex::new(HashMap::new())),
             output_sender,
+            output_throttle: Arc::new(OutputThrottle::new(0, DEFAULT_OUTPUT_BACKLOG_BYTES)),
         };
         (manager, output_receiver)
     }
 
+    pub fn output_throttle(&self) -> Arc<OutputThrottle> {
+        self.output_throttle.clone()
+    }
+
     pub fn create_session(
         &self,
         size: TerminalSize,
-        shell: Option<String>,
-        working_dir: Option<String>,
+        options: &ShellOptions,
     ) -> Result<String, Box<dyn std::error::Error>> {
         let session_id = Uuid::new_v4().to_string();
-        
-        let shell = shell.unwrap_or_else(|| {
-            if cfg!(windows) {
-                std::env::var("SHELL").unwrap_or_else(|_| "powershell.exe".to_string())
-            } else {
-                std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string())
-            }
-        });
+        let shell = options.resolve_shell()?;
+        let working_dir = options.resolve_working_dir()?;
+        let encoding = Arc::new(Mutex::new(lookup_encoding(options.encoding.as_deref().unwrap_or("utf-8"))?));
 
-        let working_dir = working_dir.unwrap_or_else(|| {
-            std::env::current_dir()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string()
-        });
+        // Start the shell process and get a handle to stdin
+        let (writer_handle, master_handle, child) =
+            self.start_shell_process(&session_id, &shell, options, &working_dir, size.clone(), encoding.clone())?;
 
         let session = PtySession {
             id: session_id.clone(),
             size: size.clone(),
             shell: shell.clone(),
             working_dir: working_dir.clone(),
+            pid: child.process_id(),
         };
 
-        // Start the shell process and get a handle to stdin
-        let (writer_handle, master_handle) = self.start_shell_process(&session_id, &shell, &working_dir, size.clone())?;
-
         // Track the process so we can write to it later
         let process = PtyProcess {
             session,
             writer: writer_handle,
             master: master_handle,
+            pending_resize: Arc::new(Mutex::new(None)),
+            encoding,
+            child,
         };
 
         self.processes.lock().unwrap().insert(session_id.clone(), process);
@@ -94,48 +631,69 @@ impl PtyManager {
         Ok(session_id)
     }
 
+    /// `MasterPty::resize` sets the window size with TIOCSWINSZ on Unix (the kernel
+    /// then signals SIGWINCH to the foreground process group) and with
+    /// ResizePseudoConsole on Windows. Only the latest size within
+    /// `RESIZE_DEBOUNCE` is applied, and a single task per session applies it so
+    /// sizes can't land out of order.
     pub fn resize_session(
         &self,
         session_id: &str,
         size: TerminalSize,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(proc) = self.processes.lock().unwrap().get_mut(session_id) {
-            proc.session.size = size.clone();
-            let cols = size.cols;
-            let rows = size.rows;
-            let pxw = size.pixel_width;
-            let pxh = size.pixel_height;
-            let master = proc.master.clone();
-            tauri::async_runtime::spawn_blocking(move || {
-                if let Some(ref mut master_opt) = *master.lock().unwrap() {
-                    let _ = master_opt.resize(PtySize {
-                        rows,
-                        cols,
-                        pixel_width: pxw,
-                        pixel_height: pxh,
-                    });
-                }
-            });
-            Ok(())
-        } else {
-            Err("Session not found".into())
+        let mut processes = self.processes.lock().unwrap();
+        let proc = processes.get_mut(session_id).ok_or("Session not found")?;
+        proc.session.size = size.clone();
+
+        let pty_size = PtySize {
+            rows: size.rows,
+            cols: size.cols,
+            pixel_width: size.pixel_width,
+            pixel_height: size.pixel_height,
+        };
+        let already_scheduled = proc.pending_resize.lock().unwrap().replace(pty_size).is_some();
+        if already_scheduled {
+            return Ok(());
         }
+
+        let pending = proc.pending_resize.clone();
+        let master = proc.master.clone();
+        let session_id = session_id.to_string();
+        tauri::async_runtime::spawn_blocking(move || {
+            std::thread::sleep(RESIZE_DEBOUNCE);
+            let Some(pty_size) = pending.lock().unwrap().take() else { return };
+            if let Some(ref mut master_opt) = *master.lock().unwrap() {
+                if let Err(e) = master_opt.resize(pty_size) {
+                    log::error!("Failed to resize PTY {}: {}", session_id, e);
+                }
+            }
+        });
+        Ok(())
     }
 
     pub fn write_to_session(
         &self,
         session_id: &str,
         data: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.write_bytes_to_session(session_id, data.as_bytes())
+    }
+
+    // Some input (e.g. legacy mouse reports) isn't valid UTF-8
+    pub fn write_bytes_to_session(
+        &self,
+        session_id: &str,
+        data: &[u8],
     ) -> Result<(), Box<dyn std::error::Error>> {
         let processes = self.processes.lock().unwrap();
         let proc = processes.get(session_id).ok_or("Session not found")?;
         let writer_arc = proc.writer.clone();
-        let data = data.to_string();
+        let data = data.to_vec();
 
         tauri::async_runtime::spawn(async move {
             let mut guard = writer_arc.lock().await;
             if let Some(writer) = guard.as_mut() {
-                if let Err(e) = writer.write_all(data.as_bytes()) {
+                if let Err(e) = writer.write_all(&data) {
                     log::error!("Failed to write to PTY: {}", e);
                 } else {
                     let _ = writer.flush();
@@ -146,14 +704,57 @@ impl PtyManager {
         Ok(())
     }
 
+    /// Decode the session's output as `label` from the next read on; returns
+    /// the encoding's canonical name.
+    pub fn set_session_encoding(&self, session_id: &str, label: &str) -> Result<&'static str, String> {
+        let encoding = lookup_encoding(label)?;
+        let processes = self.processes.lock().unwrap();
+        let proc = processes.get(session_id).ok_or("Session not found")?;
+        *proc.encoding.lock().unwrap() = encoding;
+        Ok(encoding.name())
+    }
+
+    pub fn get_session_pid(&self, session_id: &str) -> Option<u32> {
+        self.processes
+            .lock()
+            .unwrap()
+            .get(session_id)
+            .and_then(|proc| proc.session.pid)
+    }
+
     pub fn close_session(&self, session_id: &str) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(proc) = self.processes.lock().unwrap().remove(session_id) {
-            // Drop writer to signal EOF and close master to send SIGHUP on Unix
-            tauri::async_runtime::block_on(async {
-                let mut w = proc.writer.lock().await;
-                *w = None;
-            });
+        if let Some(mut proc) = self.processes.lock().unwrap().remove(session_id) {
+            // Drop writer to signal EOF and close master to send SIGHUP on Unix;
+            // on Windows dropping the master closes the pseudoconsole and its conhost.
+            // This runs from async commands, so a write in progress is waited out
+            // on a task instead of blocking here.
+            match proc.writer.try_lock() {
+                Ok(mut w) => *w = None,
+                Err(_) => {
+                    let writer = proc.writer.clone();
+                    tauri::async_runtime::spawn(async move {
+                        *writer.lock().await = None;
+                    });
+                }
+            }
             if let Ok(mut m) = proc.master.lock() { *m = None; }
+
+            // Shells get `CLOSE_GRACE` to exit on the hangup before they're killed,
+            // then are reaped off-thread
+            let session_id = session_id.to_string();
+            std::thread::spawn(move || {
+                let deadline = Instant::now() + CLOSE_GRACE;
+                while matches!(proc.child.try_wait(), Ok(None)) {
+                    if Instant::now() >= deadline {
+                        if let Err(e) = proc.child.kill() {
+                            log::warn!("Failed to kill shell for session {}: {}", session_id, e);
+                        }
+                        break;
+                    }
+                    std::thread::sleep(Duration::from_millis(20));
+                }
+                let _ = proc.child.wait();
+            });
         }
         Ok(())
     }
@@ -162,16 +763,19 @@ impl PtyManager {
         &self,
         session_id: &str,
         shell: &str,
+        options: &ShellOptions,
         working_dir: &str,
         size: TerminalSize,
+        encoding: Arc<Mutex<&'static Encoding>>,
     ) -> Result<(
         Arc<tokio::sync::Mutex<Option<Box<dyn std::io::Write + Send>>>>,
-        Arc<std::sync::Mutex<Option<Box<dyn MasterPty + Send>>>>
+        Arc<std::sync::Mutex<Option<Box<dyn MasterPty + Send>>>>,
+        Box<dyn Child + Send + Sync>
     ), Box<dyn std::error::Error>> {
         let output_sender = self.output_sender.clone();
         let session_id_str = session_id.to_string();
 
-        // Create native PTY system
+        // Native PTY system: openpty on Unix, ConPTY on Windows
         let pty_system = native_pty_system();
 
         // Open pty pair with initial size
@@ -182,16 +786,19 @@ impl PtyManager {
             pixel_height: size.pixel_height,
         })?;
 
-        // Build shell command
-        let shell_prog = if cfg!(windows) { "powershell.exe" } else { shell };
-        let mut cmd = CommandBuilder::new(shell_prog);
+        // Build shell command; CommandBuilder starts from the parent environment
+        let mut cmd = CommandBuilder::new(shell);
+        cmd.args(options.command_args(shell));
         cmd.cwd(working_dir);
         if cfg!(not(windows)) {
             cmd.env("TERM", "xterm-256color");
         }
+        for (key, value) in &options.env {
+            cmd.env(key, value);
+        }
 
         // Spawn child attached to the slave end
-        let _child = pair.slave.spawn_command(cmd)?;
+        let child = pair.slave.spawn_command(cmd)?;
         drop(pair.slave);
 
         // Writer and master handles
@@ -205,6 +812,7 @@ impl PtyManager {
 
         // Create a separate blocking thread for reading from the PTY
         let (read_master_arc, output_sender2, sid2) = (master_arc.clone(), output_sender.clone(), session_id_str.clone());
+        let throttle = self.output_throttle.clone();
         std::thread::spawn(move || {
             // Lock master and create a reader
             // Note: portable-pty provides a try_clone_reader() API on MasterPty
@@ -216,12 +824,52 @@ impl PtyManager {
             };
             if let Some(mut reader) = maybe_reader {
                 let mut buf = [0u8; 8192];
+                let mut active_encoding = *encoding.lock().unwrap();
+                let mut decoder = active_encoding.new_decoder_without_bom_handling();
+                let mut window_start = Instant::now();
+                let mut window_bytes = 0u64;
                 loop {
+                    // Not reading is the backpressure: the child blocks once the PTY buffer fills
+                    if throttle.wait_for_consumer(&sid2) {
+                        throttle.mark_throttled(&sid2);
+                    }
                     match reader.read(&mut buf) {
-                        Ok(0) => break,
+                        Ok(0) => {
+                            // A sequence cut short by the exit shows as a replacement character
+                            let data = decode_output(&mut decoder, &[], true);
+                            if !data.is_empty() {
+                                throttle.produced(&sid2, data.len());
+                                let _ = output_sender2.send(TerminalOutput { session_id: sid2.clone(), data });
+                            }
+                            break;
+                        }
                         Ok(n) => {
-                            let data = String::from_utf8_lossy(&buf[..n]).to_string();
-                            let _ = output_sender2.send(TerminalOutput { session_id: sid2.clone(), data });
+                            let wanted_encoding = *encoding.lock().unwrap();
+                            if wanted_encoding != active_encoding {
+                                active_encoding = wanted_encoding;
+                                decoder = active_encoding.new_decoder_without_bom_handling();
+                            }
+                            let data = decode_output(&mut decoder, &buf[..n], false);
+                            if !data.is_empty() {
+                                throttle.produced(&sid2, data.len());
+                                let _ = output_sender2.send(TerminalOutput { session_id: sid2.clone(), data });
+                            }
+
+                            let limit = throttle.max_bytes_per_sec();
+                            if limit == 0 {
+                                continue;
+                            }
+                            if window_start.elapsed() >= Duration::from_secs(1) {
+                                window_start = Instant::now();
+                                window_bytes = 0;
+                            }
+                            window_bytes += n as u64;
+                            if window_bytes >= limit {
+                                throttle.mark_throttled(&sid2);
+                                std::thread::sleep(Duration::from_secs(1).saturating_sub(window_start.elapsed()));
+                                window_start = Instant::now();
+                                window_bytes = 0;
+                            }
                         }
                         Err(_) => break,
                     }
@@ -231,8 +879,176 @@ impl PtyManager {
 
         // Initial message
         let welcome_msg = format!("Welcome to Warp Terminal\r\nWorking directory: {}\r\n", working_dir);
+        self.output_throttle.produced(&session_id_str, welcome_msg.len());
         let _ = output_sender.send(TerminalOutput { session_id: session_id_str, data: welcome_msg });
 
-        Ok((writer_arc, master_arc))
+        Ok((writer_arc, master_arc, child))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard() -> OutputGuard {
+        OutputGuard::new(OutputGuardLimits { max_lines: 3, max_bytes: 0, window: Duration::from_secs(10), allowlist: vec!["less".into()] })
+    }
+
+    #[test]
+    fn a_full_backlog_only_stalls_its_own_session() {
+        let throttle = OutputThrottle::new(0, 100);
+        throttle.produced("busy", 150);
+        throttle.produced("quiet", 10);
+        assert!(!throttle.wait_for_consumer("quiet"));
+
+        let start = Instant::now();
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                std::thread::sleep(Duration::from_millis(50));
+                throttle.consumed("busy", 100);
+            });
+            assert!(throttle.wait_for_consumer("busy"));
+        });
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        throttle.consumed("busy", 50);
+        assert!(throttle.backlog.lock().unwrap().get("busy").is_none());
+    }
+
+    #[test]
+    fn floods_pause_and_allowlisted_programs_pass() {
+        let mut guard = guard();
+        assert!(matches!(guard.inspect("s", "a\nb\n", Some("cat big.bin"), false, false), GuardVerdict::Pass));
+        assert!(matches!(guard.inspect("s", "c\nd\n", Some("cat big.bin"), false, false), GuardVerdict::Pause(_)));
+        assert!(matches!(guard.inspect("s", "e\n", Some("cat big.bin"), false, false), GuardVerdict::Hold));
+        assert_eq!(guard.resolve("s", LargeOutputAction::ShowFull).unwrap(), "c\nd\ne\n");
+
+        let mut guard = self::guard();
+        assert!(matches!(guard.inspect("t", "1\n2\n3\n4\n5\n", Some("less log"), false, false), GuardVerdict::Pass));
+        assert!(matches!(guard.inspect("t", "1\n2\n3\n4\n5\n", None, true, false), GuardVerdict::Pass));
+    }
+
+    #[test]
+    fn truncated_output_resumes_at_the_prompt() {
+        let mut guard = guard();
+        guard.inspect("s", "1\n2\n3\n4\n", Some("yes"), false, false);
+        guard.resolve("s", LargeOutputAction::Truncate).unwrap();
+        assert!(matches!(guard.inspect("s", "y\ny\n", Some("yes"), false, false), GuardVerdict::Hold));
+
+        // With shell integration the prompt starts at its OSC 133 A mark
+        match guard.inspect("s", "y\n\x1b]133;D;0\x07\x1b]133;A\x07$ ", None, false, true) {
+            GuardVerdict::Resume(data) => assert_eq!(data, "\r\n\x1b]133;A\x07$ "),
+            _ => panic!("expected the prompt to be shown"),
+        }
+        assert!(matches!(guard.inspect("s", "ls\n", None, false, false), GuardVerdict::Pass));
+    }
+
+    #[test]
+    fn a_prompt_split_across_chunks_is_shown_whole() {
+        let mut guard = guard();
+        guard.inspect("s", "1\n2\n3\n4\n", Some("yes"), false, false);
+        guard.resolve("s", LargeOutputAction::Truncate).unwrap();
+        assert!(matches!(guard.inspect("s", "y\nuser@host", Some("yes"), false, false), GuardVerdict::Hold));
+        match guard.inspect("s", ":~$ ", None, false, true) {
+            GuardVerdict::Resume(data) => assert_eq!(data, "\r\nuser@host:~$ "),
+            _ => panic!("expected the prompt to be shown"),
+        }
+    }
+
+    /// Collects session output until `done` matches it or `timeout` passes.
+    async fn read_until(
+        receiver: &mut mpsc::UnboundedReceiver<TerminalOutput>,
+        seen: &mut String,
+        timeout: Duration,
+        done: impl Fn(&str) -> bool,
+    ) -> bool {
+        let deadline = tokio::time::Instant::now() + timeout;
+        while !done(seen) {
+            match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(output)) => seen.push_str(&output.data),
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn the_child_sees_only_the_final_size_of_a_burst_of_resizes() {
+        let (manager, mut receiver) = PtyManager::new();
+        let size = |rows, cols| TerminalSize { rows, cols, pixel_width: 0, pixel_height: 0 };
+        // The helper prints the tty's rows and columns on every SIGWINCH
+        let script = r#"trap 'echo "winch $(stty size)"' WINCH; echo ready; while :; do sleep 0.02; done"#;
+        let options = ShellOptions {
+            shell: Some("sh".into()),
+            args: vec!["-c".into(), script.into()],
+            no_shell_integration: true,
+            ..Default::default()
+        };
+        let id = manager.create_session(size(24, 80), &options).unwrap();
+
+        let mut seen = String::new();
+        assert!(read_until(&mut receiver, &mut seen, Duration::from_secs(5), |s| s.contains("ready")).await);
+
+        // A window drag: every step lands inside one debounce window
+        for (rows, cols) in [(25, 90), (28, 95), (30, 100)] {
+            manager.resize_session(&id, size(rows, cols)).unwrap();
+        }
+        assert!(read_until(&mut receiver, &mut seen, Duration::from_secs(5), |s| s.contains("winch 30 100")).await, "{}", seen);
+
+        // Give any straggling SIGWINCH time to show up
+        read_until(&mut receiver, &mut seen, RESIZE_DEBOUNCE * 5, |_| false).await;
+        assert_eq!(seen.matches("winch").count(), 1, "{}", seen);
+        assert_eq!(manager.processes.lock().unwrap()[&id].session.size.cols, 100);
+
+        manager.close_session(&id).unwrap();
+    }
+
+    fn shell_session(manager: &PtyManager, script: &str) -> String {
+        let options = ShellOptions {
+            shell: Some("sh".into()),
+            args: vec!["-c".into(), script.into()],
+            no_shell_integration: true,
+            ..Default::default()
+        };
+        manager.create_session(TerminalSize { rows: 24, cols: 80, pixel_width: 0, pixel_height: 0 }, &options).unwrap()
+    }
+
+    fn is_alive(pid: u32) -> bool {
+        std::process::Command::new("kill").args(["-0", &pid.to_string()]).status().unwrap().success()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn closing_hangs_up_on_the_shell_before_killing_it() {
+        let (manager, mut receiver) = PtyManager::new();
+        let marker = std::env::temp_dir().join(format!("pty-hangup-{}", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+        // Called from async code, as the close_terminal command does
+        let polite = shell_session(&manager, &format!("trap 'echo hup > {}; exit 0' HUP; echo ready; while :; do sleep 0.02; done", marker.display()));
+        let mut seen = String::new();
+        assert!(read_until(&mut receiver, &mut seen, Duration::from_secs(5), |s| s.contains("ready")).await);
+        manager.close_session(&polite).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !marker.exists() && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(std::fs::read_to_string(&marker).unwrap_or_default().trim(), "hup");
+        let _ = std::fs::remove_file(&marker);
+
+        // A shell that ignores the hangup is killed once the grace period is up
+        let stubborn = shell_session(&manager, "trap '' HUP; echo ready; while :; do sleep 0.02; done");
+        let mut seen = String::new();
+        assert!(read_until(&mut receiver, &mut seen, Duration::from_secs(5), |s| s.contains("ready")).await);
+        let pid = manager.get_session_pid(&stubborn).unwrap();
+        manager.close_session(&stubborn).unwrap();
+        assert!(manager.get_session_pid(&stubborn).is_none());
+        tokio::time::sleep(CLOSE_GRACE / 2).await;
+        assert!(is_alive(pid));
+        let deadline = Instant::now() + CLOSE_GRACE * 5;
+        while is_alive(pid) && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(!is_alive(pid));
     }
 }
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::time::Instant;
+
+use crate::pty::TerminalSize;
+
+/// asciinema v2 header, the first line of a `.cast` file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CastHeader {
+    pub version: u32,
+    pub width: u16,
+    pub height: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_time_limit: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<HashMap<String, String>>,
+}
+
+/// One `[time, code, data]` line: "o" output, "i" input, "r" resize ("COLSxROWS"), "m" marker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CastEvent(pub f64, pub String, pub String);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingSummary {
+    pub terminal_id: String,
+    pub path: String,
+    pub events: usize,
+    pub duration_secs: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackInfo {
+    pub terminal_id: String,
+    pub width: u16,
+    pub height: u16,
+    pub events: usize,
+    pub duration_secs: f64, // at the requested speed
+}
+
+pub struct CastRecorder {
+    terminal_id: String,
+    path: String,
+    writer: BufWriter<File>,
+    started: Instant,
+    events: usize,
+}
+
+impl CastRecorder {
+    pub fn create(terminal_id: &str, path: &str, size: &TerminalSize) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+        let mut env = HashMap::new();
+        for key in ["SHELL", "TERM"] {
+            if let Ok(value) = std::env::var(key) {
+                env.insert(key.to_string(), value);
+            }
+        }
+        let header = CastHeader {
+            version: 2,
+            width: size.cols,
+            height: size.rows,
+            timestamp: Some(chrono::Utc::now().timestamp()),
+            idle_time_limit: None,
+            title: None,
+            env: Some(env),
+        };
+        let mut writer = BufWriter::new(file);
+        let line = serde_json::to_string(&header).map_err(|e| e.to_string())?;
+        writeln!(writer, "{}", line).map_err(|e| e.to_string())?;
+        Ok(Self { terminal_id: terminal_id.to_string(), path: path.to_string(), writer, started: Instant::now(), events: 0 })
+    }
+
+    fn write_event(&mut self, code: &str, data: String) {
+        let event = CastEvent(self.started.elapsed().as_secs_f64(), code.to_string(), data);
+        match serde_json::to_string(&event) {
+            Ok(line) => {
+                if let Err(e) = writeln!(self.writer, "{}", line) {
+                    log::warn!("Failed to write recording {}: {}", self.path, e);
+                }
+                self.events += 1;
+            }
+            Err(e) => log::warn!("Failed to encode recording event: {}", e),
+        }
+    }
+
+    pub fn output(&mut self, data: &str) {
+        self.write_event("o", data.to_string());
+    }
+
+    pub fn resize(&mut self, size: &TerminalSize) {
+        self.write_event("r", format!("{}x{}", size.cols, size.rows));
+    }
+
+    pub fn finish(mut self) -> RecordingSummary {
+        if let Err(e) = self.writer.flush() {
+            log::warn!("Failed to flush recording {}: {}", self.path, e);
+        }
+        RecordingSummary {
+            terminal_id: self.terminal_id,
+            path: self.path,
+            events: self.events,
+            duration_secs: self.started.elapsed().as_secs_f64(),
+        }
+    }
+}
+
+/// Parse a `.cast` file. Malformed event lines are skipped rather than failing the
+/// whole file, since recordings cut short by a crash often end mid-line.
+pub fn read_cast(path: &str) -> Result<(CastHeader, Vec<CastEvent>), String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let mut lines = BufReader::new(file).lines();
+    let header_line = lines
+        .next()
+        .ok_or_else(|| format!("{} is empty", path))?
+        .map_err(|e| e.to_string())?;
+    let header: CastHeader = serde_json::from_str(&header_line).map_err(|e| format!("Invalid cast header: {}", e))?;
+    if header.version != 2 {
+        return Err(format!("Unsupported asciicast version {}", header.version));
+    }
+    let events = lines
+        .map_while(Result::ok)
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str::<CastEvent>(&line).ok())
+        .collect();
+    Ok((header, events))
+}
+
+/// Parse a resize event's "COLSxROWS" payload.
+pub fn parse_resize(data: &str) -> Option<TerminalSize> {
+    let (cols, rows) = data.split_once('x')?;
+    Some(TerminalSize { cols: cols.trim().parse().ok()?, rows: rows.trim().parse().ok()?, pixel_width: 0, pixel_height: 0 })
+}
+
+/// Delays between consecutive events at `speed`, with idle gaps capped by the
+/// header's `idle_time_limit` the way asciinema's player does.
+pub fn playback_delays(header: &CastHeader, events: &[CastEvent], speed: f64) -> Vec<f64> {
+    let mut previous = 0.0;
+    events
+        .iter()
+        .map(|CastEvent(time, _, _)| {
+            let mut gap = (time - previous).max(0.0);
+            previous = *time;
+            if let Some(limit) = header.idle_time_limit {
+                gap = gap.min(limit);
+            }
+            gap / speed
+        })
+        .collect()
+}
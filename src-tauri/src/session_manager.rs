@@ -98,7 +98,7 @@ impl SessionManager {
         let terminal_id = self.terminal_manager
             .lock()
             .await
-            .create_terminal(default_size, Some(shell.clone()), Some(working_dir.clone()))
+            .create_terminal(default_size, &crate::pty::ShellOptions { shell: Some(shell.clone()), cwd: Some(working_dir.clone()), ..Default::default() })
             .map_err(|e| e.to_string())?;
 
         let pane_info = PaneInfo {
@@ -266,7 +266,7 @@ impl SessionManager {
                 let _terminal_id = self.terminal_manager
                     .lock()
                     .await
-                    .create_terminal(default_size, None, Some(pane.working_dir.clone()))
+                    .create_terminal(default_size, &crate::pty::ShellOptions { cwd: Some(pane.working_dir.clone()), ..Default::default() })
                     .map_err(|e| e.to_string())?;
                 
                 // Restore scrollback if available
@@ -296,7 +296,7 @@ impl SessionManager {
             let terminal_id = self.terminal_manager
                 .lock()
                 .await
-                .create_terminal(default_size, Some(session.shell.clone()), Some(session.working_dir.clone()))
+                .create_terminal(default_size, &crate::pty::ShellOptions { shell: Some(session.shell.clone()), cwd: Some(session.working_dir.clone()), ..Default::default() })
                 .map_err(|e| e.to_string())?;
 
             let pane_info = PaneInfo {
@@ -339,7 +339,7 @@ impl SessionManager {
                     let terminal_id = self.terminal_manager
                         .lock()
                         .await
-                        .create_terminal(default_size, Some(session.shell.clone()), Some(tab.working_dir.clone()))
+                        .create_terminal(default_size, &crate::pty::ShellOptions { shell: Some(session.shell.clone()), cwd: Some(tab.working_dir.clone()), ..Default::default() })
                         .map_err(|e| e.to_string())?;
 
                     let new_pane = PaneInfo {
@@ -400,7 +400,7 @@ impl SessionManager {
                 let _new_terminal_id = self.terminal_manager
                     .lock()
                     .await
-                    .create_terminal(default_size, Some(session.shell.clone()), Some(pane.working_dir.clone()))
+                    .create_terminal(default_size, &crate::pty::ShellOptions { shell: Some(session.shell.clone()), cwd: Some(pane.working_dir.clone()), ..Default::default() })
                     .map_err(|e| e.to_string())?;
             }
         }
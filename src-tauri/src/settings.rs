@@ -1,7 +1,15 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::{Mutex, OnceLock};
 use std::{fs, path::PathBuf};
+use tokio::sync::broadcast;
+
+/// Bumped whenever a stored settings file needs rewriting to load correctly;
+/// `migrate` upgrades older files step by step.
+pub const CURRENT_SETTINGS_VERSION: u32 = 2;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Keybindings {
     pub open_ai_panel: String,
     pub open_workflows: String,
@@ -22,23 +30,187 @@ impl Default for Keybindings {
     }
 }
 
+/// What a terminal bell does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BellPolicy {
+    None,
+    #[default]
+    Sound,
+    Visual, // flash the pane, unless reduced motion is on
+    Both,
+    Notification, // desktop notification
+}
+
+// Missing keys take their value from `Settings::default()`
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
+    pub version: u32,
     pub theme: String,          // "dark" or "light"
     pub font_size: u16,         // terminal font size hint
     pub telemetry_enabled: bool,
     pub analytics_endpoint: Option<String>,
     pub keybindings: Keybindings,
+    pub output_batch_window_ms: u64, // how long to gather PTY output before emitting
+    pub output_batch_max_bytes: usize, // emit early once this much is buffered
+    pub max_output_bytes_per_sec: u64, // per-session PTY read cap, 0 = unlimited
+    pub strip_trailing_newlines_on_paste: bool, // only used when bracketed paste is off
+    pub selection_word_chars: String, // besides letters and digits, what a double click keeps in a word
+    // Opens clicked file references; `{path}`, `{line}` and `{column}` are filled
+    // in (line and column default to 1). Empty uses the system's default app.
+    pub editor_command: String,
+    // Regex for clickable file references, with a `path` group and optional
+    // `line`/`column` groups. Empty uses the built-in pattern.
+    pub file_reference_pattern: String,
+    // A command printing more than this many lines or bytes within
+    // `large_output_window_ms` is paused until the user truncates or shows it; 0 turns a limit off
+    pub large_output_max_lines: usize,
+    pub large_output_max_bytes: usize,
+    pub large_output_window_ms: u64,
+    pub large_output_allowlist: Vec<String>, // programs that are never paused (pagers, editors, monitors)
+    pub reset_on_binary_output: bool, // reset the terminal right away instead of offering to
+    pub alert_on_bell_only: bool, // background panes are flagged for the bell, not for plain output
+    pub bell_policy: BellPolicy,
+    // Notify when a command running at least this long finishes while the window is in the background
+    pub notify_long_commands: bool,
+    pub long_command_notification_secs: u64,
+    // Source the OSC 133/OSC 7 integration script into new bash, zsh, fish and
+    // pwsh sessions; turn off if you source it from your own rc files or not at all
+    pub shell_integration_injection: bool,
+    // Let semantic history search send (masked) commands to the embeddings API
+    // when AI_API_KEY is set; off keeps history on this machine
+    pub remote_history_embeddings: bool,
+    // History kept per terminal, applied to open terminals as well
+    pub scrollback_lines: usize,
+    pub scrollback_max_bytes: usize,
+    // Mirrored into the accessibility manager
+    pub screen_reader_support: bool,
+    pub high_contrast_mode: bool,
+    pub reduced_motion: bool,
+    // Mirrored into the security policy
+    pub mask_sensitive_data: bool,
+    pub audit_logging: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsFieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl SettingsFieldError {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        SettingsFieldError { field: field.to_string(), message: message.into() }
+    }
+}
+
+/// Published after settings are saved. Saves landing within
+/// `SETTINGS_CHANGE_DEBOUNCE` of each other arrive as one event carrying the
+/// union of their changed top-level keys and the latest settings.
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsChanged {
+    pub keys: Vec<String>,
+    pub settings: Settings,
+}
+
+const SETTINGS_CHANGE_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(150);
+
+struct SettingsNotifier {
+    sender: broadcast::Sender<SettingsChanged>,
+    pending: Mutex<Option<(BTreeSet<String>, Settings)>>,
+}
+
+fn notifier() -> &'static SettingsNotifier {
+    static NOTIFIER: OnceLock<SettingsNotifier> = OnceLock::new();
+    NOTIFIER.get_or_init(|| SettingsNotifier {
+        sender: broadcast::channel(16).0,
+        pending: Mutex::new(None),
+    })
+}
+
+pub fn subscribe() -> broadcast::Receiver<SettingsChanged> {
+    notifier().sender.subscribe()
+}
+
+fn publish_change(keys: Vec<String>, settings: Settings) {
+    if keys.is_empty() {
+        return;
+    }
+    let mut pending = notifier().pending.lock().unwrap();
+    match pending.as_mut() {
+        Some((pending_keys, latest)) => {
+            pending_keys.extend(keys);
+            *latest = settings;
+        }
+        None => {
+            *pending = Some((keys.into_iter().collect(), settings));
+            tauri::async_runtime::spawn(async {
+                tokio::time::sleep(SETTINGS_CHANGE_DEBOUNCE).await;
+                if let Some((keys, settings)) = notifier().pending.lock().unwrap().take() {
+                    // No subscribers is fine
+                    let _ = notifier().sender.send(SettingsChanged { keys: keys.into_iter().collect(), settings });
+                }
+            });
+        }
+    }
+}
+
+fn changed_keys(old: &Settings, new: &Settings) -> Vec<String> {
+    let (old, new) = match (serde_json::to_value(old), serde_json::to_value(new)) {
+        (Ok(serde_json::Value::Object(old)), Ok(serde_json::Value::Object(new))) => (old, new),
+        _ => return Vec::new(),
+    };
+    new.iter()
+        .filter(|(key, value)| key.as_str() != "version" && old.get(key.as_str()) != Some(value))
+        .map(|(key, _)| key.clone())
+        .collect()
+}
+
+fn default_output_batch_window_ms() -> u64 { 8 }
+fn default_output_batch_max_bytes() -> usize { 64 * 1024 }
+fn default_max_output_bytes_per_sec() -> u64 { 8 * 1024 * 1024 }
+fn default_large_output_allowlist() -> Vec<String> {
+    ["less", "more", "man", "vim", "nvim", "vi", "nano", "emacs", "top", "htop", "btop", "watch", "tail", "tmux", "screen", "ssh"]
+        .iter()
+        .map(|program| program.to_string())
+        .collect()
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Settings {
+            version: CURRENT_SETTINGS_VERSION,
             theme: "dark".into(),
             font_size: 14,
             telemetry_enabled: false,
             analytics_endpoint: None,
             keybindings: Keybindings::default(),
+            output_batch_window_ms: default_output_batch_window_ms(),
+            output_batch_max_bytes: default_output_batch_max_bytes(),
+            max_output_bytes_per_sec: default_max_output_bytes_per_sec(),
+            strip_trailing_newlines_on_paste: true,
+            selection_word_chars: "_-.~".into(),
+            editor_command: String::new(),
+            file_reference_pattern: String::new(),
+            large_output_max_lines: 10_000,
+            large_output_max_bytes: 8 * 1024 * 1024,
+            large_output_window_ms: 1000,
+            large_output_allowlist: default_large_output_allowlist(),
+            reset_on_binary_output: false,
+            alert_on_bell_only: false,
+            bell_policy: BellPolicy::default(),
+            notify_long_commands: true,
+            long_command_notification_secs: 10,
+            shell_integration_injection: true,
+            remote_history_embeddings: false,
+            scrollback_lines: crate::terminal::DEFAULT_SCROLLBACK_LINES,
+            scrollback_max_bytes: crate::terminal::DEFAULT_SCROLLBACK_BYTES,
+            screen_reader_support: false,
+            high_contrast_mode: false,
+            reduced_motion: false,
+            mask_sensitive_data: true,
+            audit_logging: true,
         }
     }
 }
@@ -54,6 +226,127 @@ fn config_dir() -> PathBuf {
 
 fn settings_path() -> PathBuf { config_dir().join("settings.json") }
 
+impl Settings {
+    /// Builds settings from untrusted JSON (e.g. the settings UI), reporting every
+    /// bad field instead of stopping at the first one. Unknown keys are rejected.
+    pub fn from_json(value: serde_json::Value) -> Result<Settings, Vec<SettingsFieldError>> {
+        let object = match value {
+            serde_json::Value::Object(object) => object,
+            _ => return Err(vec![SettingsFieldError::new("", "settings must be a JSON object")]),
+        };
+        let defaults = serde_json::to_value(Settings::default()).unwrap_or_default();
+
+        // Deserialize each key on its own against the defaults so type errors name their field
+        let mut errors = Vec::new();
+        let mut well_typed = serde_json::Map::new();
+        for (key, field_value) in object {
+            if defaults.get(&key).is_none() {
+                errors.push(SettingsFieldError::new(&key, "unknown setting"));
+                continue;
+            }
+            let mut probe = defaults.clone();
+            probe[key.as_str()] = field_value.clone();
+            match serde_json::from_value::<Settings>(probe) {
+                Ok(_) => { well_typed.insert(key, field_value); }
+                Err(e) => errors.push(SettingsFieldError::new(&key, e.to_string())),
+            }
+        }
+
+        // Range checks still run on the fields that parsed, so one pass reports everything
+        let settings: Settings = serde_json::from_value(serde_json::Value::Object(well_typed))
+            .map_err(|e| vec![SettingsFieldError::new("", e.to_string())])?;
+        if let Err(invalid) = settings.validate() {
+            errors.extend(invalid);
+        }
+        if errors.is_empty() { Ok(settings) } else { Err(errors) }
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<SettingsFieldError>> {
+        let mut errors = Vec::new();
+        if self.version > CURRENT_SETTINGS_VERSION {
+            errors.push(SettingsFieldError::new("version", format!("newer than supported version {}", CURRENT_SETTINGS_VERSION)));
+        }
+        if self.theme != "dark" && self.theme != "light" {
+            errors.push(SettingsFieldError::new("theme", "must be \"dark\" or \"light\""));
+        }
+        if !(6..=72).contains(&self.font_size) {
+            errors.push(SettingsFieldError::new("font_size", "must be between 6 and 72"));
+        }
+        if let Some(endpoint) = &self.analytics_endpoint {
+            if !endpoint.starts_with("https://") && !endpoint.starts_with("http://") {
+                errors.push(SettingsFieldError::new("analytics_endpoint", "must be an http(s) URL"));
+            }
+        }
+        if self.output_batch_window_ms > 1000 {
+            errors.push(SettingsFieldError::new("output_batch_window_ms", "must be at most 1000"));
+        }
+        if self.output_batch_max_bytes == 0 {
+            errors.push(SettingsFieldError::new("output_batch_max_bytes", "must be greater than zero"));
+        }
+        if !(1..=60_000).contains(&self.large_output_window_ms) {
+            errors.push(SettingsFieldError::new("large_output_window_ms", "must be between 1 and 60000"));
+        }
+        if self.scrollback_lines == 0 {
+            errors.push(SettingsFieldError::new("scrollback_lines", "must be greater than zero"));
+        }
+        if self.scrollback_max_bytes == 0 {
+            errors.push(SettingsFieldError::new("scrollback_max_bytes", "must be greater than zero"));
+        }
+        if self.long_command_notification_secs == 0 {
+            errors.push(SettingsFieldError::new("long_command_notification_secs", "must be greater than zero"));
+        }
+        if !self.file_reference_pattern.is_empty() {
+            match regex::Regex::new(&self.file_reference_pattern) {
+                Ok(pattern) if pattern.capture_names().any(|name| name == Some("path")) => {}
+                Ok(_) => errors.push(SettingsFieldError::new("file_reference_pattern", "must have a `path` group")),
+                Err(e) => errors.push(SettingsFieldError::new("file_reference_pattern", e.to_string())),
+            }
+        }
+        if !self.editor_command.is_empty() && !self.editor_command.contains("{path}") {
+            errors.push(SettingsFieldError::new("editor_command", "must contain {path}"));
+        }
+        let keybindings = [
+            ("keybindings.open_ai_panel", &self.keybindings.open_ai_panel),
+            ("keybindings.open_workflows", &self.keybindings.open_workflows),
+            ("keybindings.split_vertical", &self.keybindings.split_vertical),
+            ("keybindings.split_horizontal", &self.keybindings.split_horizontal),
+            ("keybindings.close_pane", &self.keybindings.close_pane),
+        ];
+        for (field, keys) in keybindings {
+            if keys.trim().is_empty() {
+                errors.push(SettingsFieldError::new(field, "must not be empty"));
+            }
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
+
+/// Upgrades a stored settings document to `CURRENT_SETTINGS_VERSION`. Files
+/// without a version (or with version 0) predate the field and are treated as
+/// version 1.
+pub fn migrate(mut value: serde_json::Value) -> Result<serde_json::Value, String> {
+    let object = value.as_object_mut().ok_or("settings file is not a JSON object")?;
+    let mut version = match object.get("version") {
+        None | Some(serde_json::Value::Null) => 1,
+        Some(v) => v.as_u64().ok_or("settings version is not a number")?.clamp(1, u32::MAX as u64) as u32,
+    };
+    if version > CURRENT_SETTINGS_VERSION {
+        return Err(format!("settings version {} is newer than this build supports", version));
+    }
+
+    while version < CURRENT_SETTINGS_VERSION {
+        match version {
+            // v1 -> v2: the version field was introduced; keys added since v1 are
+            // left missing and pick up their defaults on deserialization
+            1 => {}
+            _ => return Err(format!("no migration from settings version {}", version)),
+        }
+        version += 1;
+    }
+    object.insert("version".to_string(), version.into());
+    Ok(value)
+}
+
 pub fn load_settings() -> Result<Settings, String> {
     let dir = config_dir();
     if !dir.exists() { fs::create_dir_all(&dir).map_err(|e| e.to_string())?; }
@@ -63,13 +356,58 @@ pub fn load_settings() -> Result<Settings, String> {
         fs::write(&path, serde_json::to_string_pretty(&defaults).unwrap()).map_err(|e| e.to_string())?;
         return Ok(defaults)
     }
-    let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
-    serde_json::from_str(&data).map_err(|e| e.to_string())
+    let data = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let stored: serde_json::Value = serde_json::from_str(&data).map_err(|e| e.to_string())?;
+    let stored_version = stored.get("version").and_then(|v| v.as_u64());
+    let settings: Settings = serde_json::from_value(migrate(stored)?).map_err(|e| e.to_string())?;
+    if stored_version != Some(CURRENT_SETTINGS_VERSION as u64) {
+        // Persist the upgrade so the migration only runs once
+        fs::write(&path, serde_json::to_string_pretty(&settings).unwrap()).map_err(|e| e.to_string())?;
+    }
+    Ok(settings)
 }
 
 pub fn save_settings(s: &Settings) -> Result<(), String> {
     let dir = config_dir();
     if !dir.exists() { fs::create_dir_all(&dir).map_err(|e| e.to_string())?; }
+    let previous = load_settings().unwrap_or_default();
     let path = settings_path();
-    fs::write(path, serde_json::to_string_pretty(s).unwrap()).map_err(|e| e.to_string())
+    let s = Settings { version: CURRENT_SETTINGS_VERSION, ..s.clone() };
+    fs::write(path, serde_json::to_string_pretty(&s).unwrap()).map_err(|e| e.to_string())?;
+    publish_change(changed_keys(&previous, &s), s);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_v1_blob_migrates_to_the_current_version() {
+        let v1 = serde_json::json!({ "theme": "light", "font_size": 16, "telemetry_enabled": true });
+        let migrated = migrate(v1).unwrap();
+        assert_eq!(migrated["version"], CURRENT_SETTINGS_VERSION);
+
+        let settings: Settings = serde_json::from_value(migrated).unwrap();
+        assert_eq!(settings.version, CURRENT_SETTINGS_VERSION);
+        assert_eq!(settings.theme, "light");
+        assert_eq!(settings.font_size, 16);
+        assert!(settings.telemetry_enabled);
+        // Keys added after v1 take their defaults
+        assert_eq!(settings.keybindings.open_ai_panel, Keybindings::default().open_ai_panel);
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn version_zero_is_the_oldest_version_and_bad_versions_are_errors() {
+        let migrated = migrate(serde_json::json!({ "version": 0, "theme": "dark" })).unwrap();
+        assert_eq!(migrated["version"], CURRENT_SETTINGS_VERSION);
+
+        let current = serde_json::json!({ "version": CURRENT_SETTINGS_VERSION, "font_size": 12 });
+        assert_eq!(migrate(current.clone()).unwrap(), current);
+
+        assert!(migrate(serde_json::json!({ "version": CURRENT_SETTINGS_VERSION + 1 })).is_err());
+        assert!(migrate(serde_json::json!({ "version": "two" })).is_err());
+        assert!(migrate(serde_json::json!([1, 2])).is_err());
+    }
 }
@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 use regex::Regex;
 use uuid::Uuid;
+use crate::completions::{self, CompletionItem, CompletionKind};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Command {
@@ -32,6 +34,39 @@ pub struct PromptInfo {
     pub user: String,
     pub hostname: String,
     pub prompt_text: String,
+    #[serde(default)]
+    pub last_exit_code: Option<i32>, // of the command that ran before this prompt
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommandEventType {
+    Started,
+    Finished,
+    Prompt, // `command` carries the prompt text
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandEvent {
+    pub event_type: CommandEventType,
+    pub session_id: String,
+    pub command_id: String,
+    pub command: String,
+    pub timestamp: u64,
+    pub exit_code: Option<i32>,
+    pub duration_ms: Option<u64>,
+    pub output_size: u64,
+    #[serde(default)]
+    pub working_dir: String, // where the command ran, as the shell last reported it
+}
+
+/// An OSC 133 mark from shell integration, with the text the terminal
+/// captured since the previous mark.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrationMark {
+    PromptStart,
+    CommandStart { prompt: String },
+    OutputStart { command: String },
+    CommandEnd { exit_code: Option<i32> },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,8 +75,14 @@ pub struct CommandSuggestion {
     pub description: String,
     pub frequency: u32,
     pub last_used: u64,
+    /// Where the suggestion came from: `history`, `common`, `git`, `cargo`,
+    /// the package manager running a script (`npm`, `pnpm`, ...) or `project`
+    #[serde(default)]
+    pub source: String,
 }
 
+const MAX_SUGGESTIONS: usize = 10;
+
 pub struct ShellHooks {
     pub session_id: String,
     command_history: VecDeque<Command>,
@@ -52,6 +93,12 @@ pub struct ShellHooks {
     working_dir: String,
     max_history_size: usize,
     output_buffer: String,
+    current_output_size: u64,
+    pending_events: Vec<CommandEvent>,
+    prompt_reported: bool,
+    pending_exit_code: Option<i32>, // reported by a shell hook for the running command
+    last_exit_code: Option<i32>,
+    marks_seen: bool, // the shell sends OSC 133 marks, so the prompt patterns are only a fallback
 }
 
 impl ShellHooks {
@@ -66,6 +113,12 @@ impl ShellHooks {
             working_dir,
             max_history_size: 1000,
             output_buffer: String::new(),
+            current_output_size: 0,
+            pending_events: Vec::new(),
+            prompt_reported: false,
+            pending_exit_code: None,
+            last_exit_code: None,
+            marks_seen: false,
         };
 
         hooks.init_prompt_patterns();
@@ -119,6 +172,12 @@ impl ShellHooks {
     }
 
     pub fn process_output(&mut self, data: &str) {
+        if self.current_command.is_some() {
+            self.current_output_size += data.len() as u64;
+        }
+        if self.marks_seen {
+            return;
+        }
         self.output_buffer.push_str(data);
         
         // Process complete lines
@@ -132,7 +191,9 @@ impl ShellHooks {
         // Also check the current buffer for prompts (in case prompt doesn't end with newline)
         if !self.output_buffer.trim().is_empty() {
             let buffer_copy = self.output_buffer.clone();
-            self.check_for_prompt(&buffer_copy);
+            if self.check_for_prompt(&buffer_copy) {
+                self.handle_prompt(&buffer_copy);
+            }
         }
     }
 
@@ -144,40 +205,165 @@ impl ShellHooks {
 
         // Check if this line contains a prompt
         if self.check_for_prompt(line) {
-            // If we have a current command, it just finished
-            if let Some(mut cmd) = self.current_command.take() {
-                cmd.duration_ms = Some(
-                    SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_millis() as u64 - cmd.timestamp
-                );
-                self.add_to_history(cmd);
-            }
+            self.handle_prompt(line);
             return;
         }
 
-        // Check if this looks like a command being executed
-        if self.current_prompt.is_some() && !line.starts_with(' ') {
+        // The first command-like line after a prompt starts a new command
+        if self.current_prompt.is_some() && self.current_command.is_none() && !line.starts_with(' ') {
             // This might be a command
             if self.looks_like_command(line) {
-                let cmd = Command {
-                    id: Uuid::new_v4().to_string(),
-                    text: line.to_string(),
-                    timestamp: SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_millis() as u64,
-                    working_dir: self.working_dir.clone(),
-                    exit_code: None,
-                    duration_ms: None,
+                self.start_command(line.to_string());
+            }
+        }
+    }
+
+    fn handle_prompt(&mut self, prompt_line: &str) {
+        // If we have a current command, it just finished
+        self.finish_command(None);
+        if let Some(prompt) = self.current_prompt.as_mut() {
+            prompt.last_exit_code = self.last_exit_code;
+        }
+        self.report_prompt(prompt_line);
+    }
+
+    fn start_command(&mut self, text: String) {
+        let cmd = Command {
+            id: Uuid::new_v4().to_string(),
+            text,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+            working_dir: self.working_dir.clone(),
+            exit_code: None,
+            duration_ms: None,
+            shell_type: self.shell_type.clone(),
+        };
+        self.current_output_size = 0;
+        self.prompt_reported = false;
+        self.pending_exit_code = None;
+        self.push_event(CommandEventType::Started, &cmd);
+        self.current_command = Some(cmd);
+    }
+
+    // `exit_code` from the shell wins over one a hook recorded
+    fn finish_command(&mut self, exit_code: Option<i32>) {
+        let pending_exit_code = self.pending_exit_code.take();
+        if let Some(mut cmd) = self.current_command.take() {
+            cmd.duration_ms = Some(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_millis() as u64 - cmd.timestamp
+            );
+            cmd.exit_code = exit_code.or(pending_exit_code);
+            self.last_exit_code = cmd.exit_code;
+            self.push_event(CommandEventType::Finished, &cmd);
+            self.current_output_size = 0;
+            self.add_to_history(cmd);
+        }
+    }
+
+    /// Tracks commands from shell integration marks. Once a shell sends them,
+    /// they replace the prompt patterns for the rest of the session.
+    pub fn handle_mark(&mut self, mark: IntegrationMark) {
+        if !self.marks_seen {
+            self.marks_seen = true;
+            self.output_buffer.clear();
+        }
+        match mark {
+            // A prompt without a D before it still ends the running command
+            IntegrationMark::PromptStart => {
+                self.finish_command(None);
+                self.prompt_reported = false;
+            }
+            IntegrationMark::CommandStart { prompt } => {
+                let prompt_text = self.strip_ansi_codes(&prompt).trim().to_string();
+                // The marks track the directory themselves; a prompt that matches a
+                // pattern only adds the user and host
+                let (user, hostname) = self.parse_prompt(&prompt_text)
+                    .map(|parsed| (parsed.user, parsed.hostname))
+                    .unwrap_or_default();
+                self.current_prompt = Some(PromptInfo {
                     shell_type: self.shell_type.clone(),
-                };
-                self.current_command = Some(cmd);
+                    working_dir: self.working_dir.clone(),
+                    user,
+                    hostname,
+                    prompt_text: prompt_text.clone(),
+                    last_exit_code: self.last_exit_code,
+                });
+                self.report_prompt(&prompt_text);
+            }
+            IntegrationMark::OutputStart { command } => {
+                self.finish_command(None);
+                let command = command.trim();
+                if !command.is_empty() {
+                    self.start_command(command.to_string());
+                }
             }
+            IntegrationMark::CommandEnd { exit_code } => self.finish_command(exit_code),
+        }
+    }
+
+    fn report_prompt(&mut self, prompt_line: &str) {
+        // Report each prompt once, even though the trailing buffer is re-checked on every chunk
+        if !self.prompt_reported {
+            self.prompt_reported = true;
+            self.pending_events.push(CommandEvent {
+                event_type: CommandEventType::Prompt,
+                session_id: self.session_id.clone(),
+                command_id: String::new(),
+                command: self.strip_ansi_codes(prompt_line),
+                timestamp: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_millis() as u64,
+                exit_code: None,
+                duration_ms: None,
+                output_size: 0,
+                working_dir: self.working_dir.clone(),
+            });
         }
     }
 
+    fn push_event(&mut self, event_type: CommandEventType, cmd: &Command) {
+        let timestamp = match event_type {
+            CommandEventType::Finished => cmd.timestamp + cmd.duration_ms.unwrap_or(0),
+            _ => cmd.timestamp,
+        };
+        self.pending_events.push(CommandEvent {
+            event_type,
+            session_id: self.session_id.clone(),
+            command_id: cmd.id.clone(),
+            command: cmd.text.clone(),
+            timestamp,
+            exit_code: cmd.exit_code,
+            duration_ms: cmd.duration_ms,
+            output_size: self.current_output_size,
+            working_dir: cmd.working_dir.clone(),
+        });
+    }
+
+    /// Drains the command start/finish events detected since the last call.
+    pub fn take_events(&mut self) -> Vec<CommandEvent> {
+        std::mem::take(&mut self.pending_events)
+    }
+
+    pub fn current_command(&self) -> Option<&str> {
+        self.current_command.as_ref().map(|command| command.text.as_str())
+    }
+
+    /// Exit code of the running command from a shell hook; it's attached to
+    /// the command when it finishes, unless the shell reports one itself.
+    pub fn record_exit_code(&mut self, exit_code: i32) {
+        self.pending_exit_code = Some(exit_code);
+    }
+
+    pub fn last_exit_code(&self) -> Option<i32> {
+        self.last_exit_code
+    }
+
     fn check_for_prompt(&mut self, line: &str) -> bool {
         let clean_line = self.strip_ansi_codes(line);
         
@@ -207,43 +393,43 @@ impl ShellHooks {
     }
 
     fn parse_prompt_info(&mut self, prompt_line: &str) {
-        match self.shell_type {
+        if let Some(prompt) = self.parse_prompt(prompt_line) {
+            self.working_dir = prompt.working_dir.clone();
+            // A PowerShell prompt only tells us the directory
+            if !matches!(self.shell_type, ShellType::PowerShell) {
+                self.current_prompt = Some(prompt);
+            }
+        }
+    }
+
+    /// Reads the user, host and directory out of a prompt line, if it matches
+    /// the current shell's prompt format.
+    fn parse_prompt(&self, prompt_line: &str) -> Option<PromptInfo> {
+        let (user, hostname, working_dir) = match self.shell_type {
             ShellType::PowerShell => {
-                if let Some(caps) = Regex::new(r"^PS\s+([A-Za-z]:[\\\/][^>]*|[~\/][^>]*)>\s*$")
+                let caps = Regex::new(r"^PS\s+([A-Za-z]:[\\\/][^>]*|[~\/][^>]*)>\s*$")
                     .unwrap()
-                    .captures(prompt_line)
-                {
-                    if let Some(path) = caps.get(1) {
-                        self.working_dir = path.as_str().to_string();
-                    }
-                }
+                    .captures(prompt_line)?;
+                (String::new(), String::new(), caps.get(1)?.as_str().to_string())
             }
             ShellType::Bash | ShellType::Zsh => {
-                if let Some(caps) = Regex::new(r"^([^@\s]+)@([^:\s]+):([^$%]*)([$%])\s*$")
+                let caps = Regex::new(r"^([^@\s]+)@([^:\s]+):([^$%]*)([$%])\s*$")
                     .unwrap()
-                    .captures(prompt_line)
-                {
-                    let user = caps.get(1).map(|m| m.as_str().to_string()).unwrap_or_default();
-                    let hostname = caps.get(2).map(|m| m.as_str().to_string()).unwrap_or_default();
-                    let working_dir = caps.get(3).map(|m| m.as_str().to_string()).unwrap_or_default();
-                    
-                    self.working_dir = if working_dir.starts_with('~') {
-                        working_dir
-                    } else {
-                        working_dir
-                    };
-
-                    self.current_prompt = Some(PromptInfo {
-                        shell_type: self.shell_type.clone(),
-                        working_dir: self.working_dir.clone(),
-                        user,
-                        hostname,
-                        prompt_text: prompt_line.to_string(),
-                    });
-                }
+                    .captures(prompt_line)?;
+                let field = |index: usize| caps.get(index).map(|m| m.as_str().to_string()).unwrap_or_default();
+                (field(1), field(2), field(3))
             }
-            _ => {}
-        }
+            _ => return None,
+        };
+
+        Some(PromptInfo {
+            shell_type: self.shell_type.clone(),
+            working_dir,
+            user,
+            hostname,
+            prompt_text: prompt_line.to_string(),
+            last_exit_code: self.last_exit_code,
+        })
     }
 
     fn looks_like_command(&self, line: &str) -> bool {
@@ -298,7 +484,10 @@ impl ShellHooks {
             .collect()
     }
 
-    pub fn get_command_suggestions(&self, partial_command: &str) -> Vec<CommandSuggestion> {
+    /// History matches merged with `context` suggestions for the current
+    /// directory. History that the context also suggests ranks first, then the
+    /// rest of the history, then context suggestions, then common commands.
+    pub fn get_command_suggestions(&self, partial_command: &str, context: &[CommandSuggestion]) -> Vec<CommandSuggestion> {
         let mut suggestions = HashMap::new();
         
         // Analyze command history for suggestions
@@ -309,6 +498,7 @@ impl ShellHooks {
                     description: format!("Previously used in {}", cmd.working_dir),
                     frequency: 0,
                     last_used: cmd.timestamp,
+                    source: "history".to_string(),
                 });
                 entry.frequency += 1;
                 if cmd.timestamp > entry.last_used {
@@ -317,20 +507,40 @@ impl ShellHooks {
             }
         }
 
+        for suggestion in context {
+            if suggestion.command.starts_with(partial_command) {
+                suggestions.entry(suggestion.command.clone()).or_insert_with(|| suggestion.clone());
+            }
+        }
+
         // Add common commands based on shell type
         if partial_command.is_empty() || self.command_history.is_empty() {
             self.add_common_command_suggestions(partial_command, &mut suggestions);
         }
 
-        // Sort by frequency and recency
+        let tier = |suggestion: &CommandSuggestion| match suggestion.source.as_str() {
+            "history" if context.iter().any(|c| {
+                suggestion.command.strip_prefix(c.command.as_str()).is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+            }) => 0,
+            "history" => 1,
+            "common" => 3,
+            _ => 2,
+        };
+
+        // Context suggestions keep the order they were given in
+        let position = |suggestion: &CommandSuggestion| context.iter().position(|c| c.command == suggestion.command);
+
+        // Sort by tier, then frequency, then recency
         let mut result: Vec<CommandSuggestion> = suggestions.into_values().collect();
         result.sort_by(|a, b| {
-            // Sort by frequency first, then by recency
-            b.frequency.cmp(&a.frequency)
+            tier(a).cmp(&tier(b))
+                .then(b.frequency.cmp(&a.frequency))
                 .then(b.last_used.cmp(&a.last_used))
+                .then(position(a).cmp(&position(b)))
+                .then(a.command.cmp(&b.command))
         });
 
-        result.into_iter().take(10).collect() // Limit to 10 suggestions
+        result.into_iter().take(MAX_SUGGESTIONS).collect()
     }
 
     fn add_common_command_suggestions(
@@ -413,6 +623,7 @@ impl ShellHooks {
                     description: desc.to_string(),
                     frequency: 1,
                     last_used: 0,
+                    source: "common".to_string(),
                 });
             }
         }
@@ -471,44 +682,65 @@ impl ShellHooks {
     }
 
     pub fn complete_command(&self, partial: &str) -> Vec<String> {
-        let suggestions = self.get_command_suggestions(partial);
+        let suggestions = self.get_command_suggestions(partial, &[]);
         suggestions.into_iter().map(|s| s.command).collect()
     }
 
     // Hook for handling command completion from shell
     pub fn handle_tab_completion(&self, current_line: &str, cursor_pos: usize) -> Vec<String> {
-        // Extract the word at cursor position
-        let words: Vec<&str> = current_line[..cursor_pos].split_whitespace().collect();
-        
+        self.handle_tab_completion_items(current_line, cursor_pos)
+            .into_iter()
+            .map(|item| item.value)
+            .collect()
+    }
+
+    pub fn handle_tab_completion_items(&self, current_line: &str, cursor_pos: usize) -> Vec<CompletionItem> {
+        let before_cursor = current_line.get(..cursor_pos).unwrap_or(current_line);
+        let mut words: Vec<&str> = before_cursor.split_whitespace().collect();
+
+        // The word under the cursor is empty when the line ends in whitespace
+        let partial = if before_cursor.is_empty() || before_cursor.ends_with(char::is_whitespace) {
+            ""
+        } else {
+            words.pop().unwrap_or("")
+        };
+
         if words.is_empty() {
-            // Complete command names
-            self.complete_command("")
-        } else if words.len() == 1 {
-            // Complete command names
-            self.complete_command(words[0])
+            // Complete command names from history first, then known commands
+            let mut items: Vec<CompletionItem> = self
+                .complete_command(partial)
+                .into_iter()
+                .map(|command| CompletionItem {
+                    kind: CompletionKind::Command,
+                    label: command.clone(),
+                    value: command,
+                    description: Some("From history".to_string()),
+                })
+                .collect();
+            for item in completions::complete_command_name(partial) {
+                if !items.iter().any(|i| i.value == item.value) {
+                    items.push(item);
+                }
+            }
+            items
         } else {
-            // Complete file/directory names (simplified)
-            let last_word = words.last().map(|&s| s).unwrap_or("");
-            self.complete_filesystem(last_word)
+            completions::complete_arguments(&words, partial, &self.working_dir)
         }
     }
-
-    fn complete_filesystem(&self, _partial: &str) -> Vec<String> {
-        // Basic filesystem completion - in a real implementation this would
-        // interact with the filesystem and shell completion mechanisms
-        vec![]
-    }
 }
 
 // Helper struct for managing shell hooks across all terminal sessions
 pub struct ShellHooksManager {
     hooks: HashMap<String, ShellHooks>,
+    // package.json scripts per project directory, with the manifest's mtime
+    package_scripts: HashMap<PathBuf, (SystemTime, Vec<(String, String)>)>,
 }
 
 impl ShellHooksManager {
     pub fn new() -> Self {
         ShellHooksManager {
             hooks: HashMap::new(),
+            package_scripts: HashMap::new(),
         }
     }
 
@@ -523,9 +755,12 @@ impl ShellHooksManager {
         self.hooks.insert(session_id, hooks);
     }
 
-    pub fn process_output(&mut self, session_id: &str, data: &str) {
+    pub fn process_output(&mut self, session_id: &str, data: &str) -> Vec<CommandEvent> {
         if let Some(hooks) = self.hooks.get_mut(session_id) {
             hooks.process_output(data);
+            hooks.take_events()
+        } else {
+            Vec::new()
         }
     }
 
@@ -533,14 +768,118 @@ impl ShellHooksManager {
         self.hooks.get(session_id).map(|hooks| hooks.get_command_history(limit))
     }
 
+    pub fn get_working_directory(&self, session_id: &str) -> Option<String> {
+        self.hooks.get(session_id).map(|hooks| hooks.get_working_directory().to_string())
+    }
+
+    pub fn current_command(&self, session_id: &str) -> Option<String> {
+        self.hooks.get(session_id).and_then(|hooks| hooks.current_command()).map(str::to_string)
+    }
+
+    pub fn set_working_directory(&mut self, session_id: &str, working_dir: String) {
+        if let Some(hooks) = self.hooks.get_mut(session_id) {
+            hooks.set_working_directory(working_dir);
+        }
+    }
+
+    /// History from every live session, oldest first.
+    pub fn get_all_command_history(&self) -> Vec<Command> {
+        let mut commands: Vec<Command> = self
+            .hooks
+            .values()
+            .flat_map(|hooks| hooks.command_history.iter().cloned())
+            .collect();
+        commands.sort_by_key(|command| command.timestamp);
+        commands
+    }
+
+    /// Suggestions for `session_id`, including git subcommands when `cwd` is
+    /// inside a repository and the commands of `project`, if one was detected.
     pub fn get_command_suggestions(
-        &self,
+        &mut self,
         session_id: &str,
         partial_command: &str,
+        cwd: Option<&Path>,
+        project: Option<&crate::dev_tools::ProjectInfo>,
     ) -> Option<Vec<CommandSuggestion>> {
+        if !self.hooks.contains_key(session_id) {
+            return None;
+        }
+        let context = self.context_suggestions(cwd, project);
         self.hooks
             .get(session_id)
-            .map(|hooks| hooks.get_command_suggestions(partial_command))
+            .map(|hooks| hooks.get_command_suggestions(partial_command, &context))
+    }
+
+    fn context_suggestions(&mut self, cwd: Option<&Path>, project: Option<&crate::dev_tools::ProjectInfo>) -> Vec<CommandSuggestion> {
+        let suggestion = |command: String, description: &str, source: &str| CommandSuggestion {
+            command,
+            description: description.to_string(),
+            frequency: 0,
+            last_used: 0,
+            source: source.to_string(),
+        };
+        let mut context = Vec::new();
+
+        // Most specific first: the project's own scripts and build/test commands,
+        // then its tool's subcommands, then git
+        if let Some(project) = project {
+            for detected in &project.projects {
+                if matches!(detected.tool.as_str(), "npm" | "pnpm" | "yarn" | "bun") {
+                    for (name, script) in self.package_scripts(&project.path) {
+                        context.push(suggestion(format!("{} run {}", detected.tool, name), &script, &detected.tool));
+                    }
+                }
+            }
+            let configs = project.build_configs.iter().map(|config| (&config.name, &config.command))
+                .chain(project.test_configs.iter().map(|config| (&config.name, &config.command)));
+            for (name, command) in configs {
+                context.push(suggestion(command.join(" "), name, "project"));
+            }
+            if project.projects.iter().any(|detected| detected.tool == "cargo") {
+                for (name, description) in completions::subcommands("cargo") {
+                    context.push(suggestion(format!("cargo {}", name), description, "cargo"));
+                }
+            }
+        }
+
+        if cwd.and_then(completions::find_git_dir).is_some() {
+            for (name, description) in completions::subcommands("git") {
+                context.push(suggestion(format!("git {}", name), description, "git"));
+            }
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        context.retain(|suggestion| seen.insert(suggestion.command.clone()));
+        context
+    }
+
+    // Parsed once per manifest version; re-read when package.json changes
+    fn package_scripts(&mut self, dir: &Path) -> Vec<(String, String)> {
+        let manifest = dir.join("package.json");
+        let Ok(modified) = std::fs::metadata(&manifest).and_then(|metadata| metadata.modified()) else {
+            return Vec::new();
+        };
+        if let Some((cached_at, scripts)) = self.package_scripts.get(dir) {
+            if *cached_at == modified {
+                return scripts.clone();
+            }
+        }
+
+        let package: serde_json::Value = std::fs::read_to_string(&manifest)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        let scripts: Vec<(String, String)> = package["scripts"]
+            .as_object()
+            .map(|scripts| {
+                scripts.iter()
+                    .map(|(name, script)| (name.clone(), script.as_str().unwrap_or_default().to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.package_scripts.insert(dir.to_path_buf(), (modified, scripts.clone()));
+        scripts
     }
 
     pub fn handle_tab_completion(
@@ -554,6 +893,17 @@ impl ShellHooksManager {
             .map(|hooks| hooks.handle_tab_completion(current_line, cursor_pos))
     }
 
+    pub fn handle_tab_completion_items(
+        &self,
+        session_id: &str,
+        current_line: &str,
+        cursor_pos: usize,
+    ) -> Option<Vec<CompletionItem>> {
+        self.hooks
+            .get(session_id)
+            .map(|hooks| hooks.handle_tab_completion_items(current_line, cursor_pos))
+    }
+
     pub fn is_at_prompt(&self, session_id: &str) -> bool {
         self.hooks
             .get(session_id)
@@ -567,6 +917,26 @@ impl ShellHooksManager {
             .and_then(|hooks| hooks.get_current_prompt())
     }
 
+    pub fn handle_mark(&mut self, session_id: &str, mark: IntegrationMark) {
+        if let Some(hooks) = self.hooks.get_mut(session_id) {
+            hooks.handle_mark(mark);
+        }
+    }
+
+    pub fn record_exit_code(&mut self, session_id: &str, exit_code: i32) -> bool {
+        match self.hooks.get_mut(session_id) {
+            Some(hooks) => {
+                hooks.record_exit_code(exit_code);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn last_exit_code(&self, session_id: &str) -> Option<i32> {
+        self.hooks.get(session_id).and_then(|hooks| hooks.last_exit_code())
+    }
+
     pub fn remove_session(&mut self, session_id: &str) {
         self.hooks.remove(session_id);
     }
@@ -577,3 +947,72 @@ impl ShellHooksManager {
             .map(|hooks| hooks.search_history(query))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hooks() -> ShellHooks {
+        ShellHooks::new("s1".to_string(), ShellType::Bash, "/tmp".to_string())
+    }
+
+    fn event_summary(events: &[CommandEvent]) -> Vec<(CommandEventType, String, Option<i32>)> {
+        events.iter().map(|e| (e.event_type.clone(), e.command.clone(), e.exit_code)).collect()
+    }
+
+    #[test]
+    fn marks_drive_commands_whatever_the_prompt_looks_like() {
+        let mut hooks = hooks();
+        // A themed prompt that none of the patterns recognise
+        hooks.handle_mark(IntegrationMark::PromptStart);
+        hooks.handle_mark(IntegrationMark::CommandStart { prompt: "\x1b[32m❯\x1b[0m ".to_string() });
+        assert!(hooks.is_at_prompt());
+        assert_eq!(hooks.get_current_prompt().unwrap().prompt_text, "❯");
+
+        hooks.handle_mark(IntegrationMark::OutputStart { command: " cargo build ".to_string() });
+        assert!(!hooks.is_at_prompt());
+        // Output that looks like a prompt doesn't end the command
+        hooks.process_output("   Compiling app\r\nuser@host:~$ \r\n");
+        assert_eq!(hooks.current_command(), Some("cargo build"));
+
+        hooks.handle_mark(IntegrationMark::CommandEnd { exit_code: Some(101) });
+        hooks.handle_mark(IntegrationMark::PromptStart);
+        assert_eq!(hooks.current_command(), None);
+        assert_eq!(hooks.last_exit_code(), Some(101));
+        assert_eq!(
+            event_summary(&hooks.take_events()),
+            vec![
+                (CommandEventType::Prompt, "❯".to_string(), None),
+                (CommandEventType::Started, "cargo build".to_string(), None),
+                (CommandEventType::Finished, "cargo build".to_string(), Some(101)),
+            ]
+        );
+        assert_eq!(hooks.command_history.back().unwrap().exit_code, Some(101));
+    }
+
+    #[test]
+    fn a_marked_prompt_adds_user_and_host_but_keeps_the_tracked_directory() {
+        let mut hooks = hooks();
+        hooks.set_working_directory("/home/user/project".to_string());
+        hooks.handle_mark(IntegrationMark::CommandStart { prompt: "user@host:~/elsewhere$ ".to_string() });
+
+        let prompt = hooks.get_current_prompt().unwrap();
+        assert_eq!((prompt.user.as_str(), prompt.hostname.as_str()), ("user", "host"));
+        assert_eq!(prompt.working_dir, "/home/user/project");
+        assert_eq!(hooks.working_dir, "/home/user/project");
+    }
+
+    #[test]
+    fn without_marks_the_prompt_patterns_find_commands() {
+        let mut hooks = hooks();
+        hooks.process_output("user@host:~/src$ \r\n");
+        hooks.process_output("ls -la\r\ntotal 0\r\n");
+        assert_eq!(hooks.current_command(), Some("ls -la"));
+        hooks.process_output("user@host:~/src$ ");
+        assert!(hooks.is_at_prompt());
+
+        let events = event_summary(&hooks.take_events());
+        assert_eq!(events[1], (CommandEventType::Started, "ls -la".to_string(), None));
+        assert_eq!(events[2], (CommandEventType::Finished, "ls -la".to_string(), None));
+    }
+}
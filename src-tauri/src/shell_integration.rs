@@ -5,7 +5,7 @@ use std::io::{BufReader, Write};
 use std::path::{Path, PathBuf};
 use chrono::{DateTime, Utc};
 use tauri::State;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, LazyLock, Mutex};
 use regex::Regex;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +32,8 @@ pub enum CompletionType {
     Custom,
 }
 
+/// A prompt is either a `template` with `{segment}` placeholders, or (when the
+/// template is empty) the enabled `segments` joined by spaces.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PromptConfiguration {
     pub template: String,
@@ -42,8 +44,19 @@ pub struct PromptConfiguration {
     pub show_duration: bool,
     pub show_exit_code: bool,
     pub multiline: bool,
+    /// Trailing directory components the cwd segment keeps; 0 shows the whole path
+    #[serde(default = "default_truncation_length")]
+    pub truncation_length: usize,
 }
 
+fn default_truncation_length() -> usize {
+    3
+}
+
+/// One piece of the prompt: `cwd`, `git`, `exit_code`, `duration`, `time`,
+/// `user` or `hostname`. `format` wraps the value (`"on {value}"`), `condition`
+/// is one of `git`, `dirty`, `success`, `failed` or `duration>MS`, and colors
+/// are a `PromptColors` role, a hex color, a 256-color index or an ANSI name.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PromptSegment {
     pub name: String,
@@ -54,6 +67,21 @@ pub struct PromptSegment {
     pub background: Option<String>,
 }
 
+impl PromptSegment {
+    fn named(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            enabled: true,
+            format: String::new(),
+            condition: None,
+            color: None,
+            background: None,
+        }
+    }
+}
+
+const DEFAULT_PROMPT_SEGMENTS: &[&str] = &["cwd", "git", "duration", "exit_code"];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PromptColors {
     pub primary: String,
@@ -103,6 +131,16 @@ pub struct ShellAlias {
     pub usage_count: u64,
 }
 
+/// What a command line turns into once its aliases are expanded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AliasExpansion {
+    pub original: String,
+    pub expanded: String,
+    /// Aliases that were expanded, in the order they were applied
+    pub aliases: Vec<String>,
+    pub warning: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShellFunction {
     pub name: String,
@@ -162,8 +200,30 @@ pub struct GitStatus {
     pub is_detached: bool,
 }
 
+impl GitStatus {
+    /// Counts for the prompt from dev_tools' per-file status.
+    pub fn from_working_tree(branch: String, status: &crate::dev_tools::GitStatus) -> Self {
+        let mut git_status = GitStatus {
+            is_detached: branch == "HEAD",
+            branch: Some(branch),
+            ahead: 0,
+            behind: 0,
+            staged: status.staged.len() as u32,
+            modified: status.unstaged.len() as u32,
+            untracked: status.untracked.len() as u32,
+            conflicts: status.conflicted.len() as u32,
+            stashes: 0,
+            is_dirty: false,
+        };
+        git_status.is_dirty = git_status.staged > 0 || git_status.modified > 0 || git_status.untracked > 0;
+        git_status
+    }
+}
+
 pub type ShellIntegrationManager = Arc<Mutex<ShellIntegrationState>>;
 
+static PROMPT_PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{(\w+)\}").unwrap());
+
 pub struct ShellIntegrationState {
     pub completions_cache: HashMap<String, Vec<ShellCompletion>>,
     pub history: VecDeque<CommandHistory>,
@@ -208,6 +268,28 @@ impl ShellIntegrationState {
             .collect()
     }
 
+    /// Fish-style suggestion: the most recent successful command starting with
+    /// `prefix`, preferring ones run in `cwd`. Returns only the suffix to ghost in.
+    pub fn get_autosuggestion(&self, prefix: &str, cwd: &str) -> Option<String> {
+        if prefix.trim().is_empty() {
+            return None;
+        }
+        let cwd = cwd.trim_end_matches(['/', '\\']);
+        let is_candidate = |item: &&CommandHistory| {
+            item.command.len() > prefix.len()
+                && item.command.starts_with(prefix)
+                && item.exit_code.map(|code| code == 0).unwrap_or(true)
+        };
+
+        // History is kept newest first
+        self.history
+            .iter()
+            .filter(is_candidate)
+            .find(|item| item.directory.trim_end_matches(['/', '\\']) == cwd)
+            .or_else(|| self.history.iter().find(is_candidate))
+            .map(|item| item.command[prefix.len()..].to_string())
+    }
+
     pub fn get_completion_suggestions(
         &mut self,
         input: &str,
@@ -215,6 +297,11 @@ impl ShellIntegrationState {
         shell_type: &str,
         current_dir: &str,
     ) -> Vec<ShellCompletion> {
+        // Spec completions bypass the cache so newly loaded specs apply immediately
+        if let Some(spec_completions) = Self::get_spec_completions(input, current_dir) {
+            return spec_completions;
+        }
+
         let cache_key = format!("{}:{}:{}", shell_type, current_dir, input);
         
         if let Some(cached) = self.completions_cache.get(&cache_key) {
@@ -256,6 +343,33 @@ impl ShellIntegrationState {
         suggestions
     }
 
+    fn get_spec_completions(input: &str, current_dir: &str) -> Option<Vec<ShellCompletion>> {
+        let mut words: Vec<&str> = input.split_whitespace().collect();
+        let partial = if input.ends_with(char::is_whitespace) { "" } else { words.pop().unwrap_or("") };
+        if words.is_empty() {
+            return None;
+        }
+
+        let items = crate::completions::complete_from_user_spec(&words, partial, current_dir)?;
+        Some(items
+            .into_iter()
+            .map(|item| ShellCompletion {
+                completion_type: match item.kind {
+                    crate::completions::CompletionKind::File => CompletionType::File,
+                    crate::completions::CompletionKind::Directory => CompletionType::Directory,
+                    crate::completions::CompletionKind::Flag => CompletionType::Flag,
+                    crate::completions::CompletionKind::Argument => CompletionType::Argument,
+                    _ => CompletionType::Custom,
+                },
+                text: item.value,
+                display: item.label,
+                description: item.description,
+                priority: 90,
+                source: "spec".to_string(),
+            })
+            .collect())
+    }
+
     fn get_command_completions(&self, input: &str) -> Vec<ShellCompletion> {
         let mut completions = Vec::new();
         
@@ -389,6 +503,99 @@ impl ShellIntegrationState {
         Vec::new()
     }
 
+    /// Expand aliases in command position the way the shell would, so the UI can
+    /// show what will actually run. Quoted words are left alone, an alias is never
+    /// expanded inside itself, and a cycle stops expansion with a warning.
+    pub fn expand_aliases(&self, command_line: &str) -> AliasExpansion {
+        let mut expansion = AliasExpansion {
+            original: command_line.to_string(),
+            expanded: String::new(),
+            aliases: Vec::new(),
+            warning: None,
+        };
+        expansion.expanded = self.expand_alias_text(command_line, &mut Vec::new(), &mut expansion);
+        expansion
+    }
+
+    fn expand_alias_text(&self, text: &str, chain: &mut Vec<String>, expansion: &mut AliasExpansion) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut result = String::new();
+        let mut command_position = true;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            if c.is_whitespace() {
+                command_position |= c == '\n';
+                result.push(c);
+                i += 1;
+                continue;
+            }
+            if matches!(c, ';' | '&' | '|' | '(' | ')') {
+                // `2>&1` and `>&` redirect rather than start a new command
+                let redirect = c == '&' && i > 0 && matches!(chars[i - 1], '>' | '<');
+                command_position = c != ')' && !redirect;
+                result.push(c);
+                i += 1;
+                continue;
+            }
+
+            let start = i;
+            let mut quoted = false;
+            let mut quote: Option<char> = None;
+            while i < chars.len() {
+                let c = chars[i];
+                match quote {
+                    Some(q) if c == q => quote = None,
+                    Some('"') if c == '\\' => i += 1,
+                    Some(_) => {}
+                    None if c.is_whitespace() || matches!(c, ';' | '&' | '|' | '(' | ')') => break,
+                    None if c == '\'' || c == '"' => {
+                        quote = Some(c);
+                        quoted = true;
+                    }
+                    None if c == '\\' => {
+                        quoted = true;
+                        i += 1;
+                    }
+                    None => {}
+                }
+                i += 1;
+            }
+            let word: String = chars[start..i.min(chars.len())].iter().collect();
+            if !command_position || quoted {
+                command_position = false;
+                result.push_str(&word);
+                continue;
+            }
+            command_position = false;
+
+            match self.aliases.get(&word) {
+                Some(alias) if !chain.contains(&word) => {
+                    expansion.aliases.push(word.clone());
+                    chain.push(word.clone());
+                    result.push_str(&self.expand_alias_text(&alias.command, chain, expansion));
+                    chain.pop();
+                    // An alias ending in a blank makes the following word eligible too
+                    command_position = alias.command.ends_with(char::is_whitespace);
+                }
+                Some(_) => {
+                    // `alias ls='ls -la'` names the real command; reaching an alias
+                    // again through another one is a cycle
+                    if chain.last() != Some(&word) && expansion.warning.is_none() {
+                        let cycle_start = chain.iter().position(|name| *name == word).unwrap_or_default();
+                        let mut cycle = chain[cycle_start..].to_vec();
+                        cycle.push(word.clone());
+                        expansion.warning = Some(format!("Alias cycle detected: {}", cycle.join(" -> ")));
+                    }
+                    result.push_str(&word);
+                }
+                None => result.push_str(&word),
+            }
+        }
+        result
+    }
+
     pub fn get_git_status(&mut self, directory: &str) -> Option<GitStatus> {
         // Check cache first
         if let Some((status, timestamp)) = self.git_status_cache.get(directory) {
@@ -434,6 +641,8 @@ impl ShellIntegrationState {
             if line.starts_with("##") {
                 // Branch information
                 if let Some(branch_info) = line.strip_prefix("## ") {
+                    // A repository without commits reports "No commits yet on <branch>"
+                    let branch_info = branch_info.strip_prefix("No commits yet on ").unwrap_or(branch_info);
                     if let Some(branch) = branch_info.split("...").next() {
                         git_status.branch = Some(branch.to_string());
                     }
@@ -460,28 +669,94 @@ impl ShellIntegrationState {
         Ok(git_status)
     }
 
-    pub fn generate_prompt(&self, config: &PromptConfiguration, context: &crate::execution_context::ExecutionContext) -> String {
-        let mut prompt = config.template.clone();
-        
-        // Replace basic placeholders
-        prompt = prompt.replace("{pwd}", &context.directory_state.pwd);
-        prompt = prompt.replace("{user}", &context.operating_system.username);
-        prompt = prompt.replace("{hostname}", &context.operating_system.hostname);
-        prompt = prompt.replace("{time}", &context.current_time.format("%H:%M:%S").to_string());
-        
-        // Git information
-        if config.show_git {
-            if let Some((git_status, _)) = self.git_status_cache.get(&context.directory_state.pwd) {
-                let git_info = self.format_git_info(git_status, &config.colors, &config.icons);
-                prompt = prompt.replace("{git}", &git_info);
+    /// Render `config` for `context`. `git` is the status of the prompt's
+    /// directory, None outside a repository; it is ignored unless `show_git` is set.
+    pub fn generate_prompt(&self, config: &PromptConfiguration, context: &crate::execution_context::ExecutionContext, git: Option<GitStatus>) -> String {
+        let state = PromptState {
+            context,
+            git: git.filter(|status| config.show_git && status.branch.is_some()),
+            last_command: self.history.front(),
+        };
+
+        let mut prompt = if config.template.trim().is_empty() {
+            let defaults: Vec<PromptSegment>;
+            let segments = if config.segments.is_empty() {
+                defaults = DEFAULT_PROMPT_SEGMENTS.iter().map(|name| PromptSegment::named(name)).collect();
+                &defaults
             } else {
-                prompt = prompt.replace("{git}", "");
-            }
+                &config.segments
+            };
+            segments
+                .iter()
+                .filter_map(|segment| self.render_segment(segment, config, &state))
+                .collect::<Vec<_>>()
+                .join(" ")
+        } else {
+            PROMPT_PLACEHOLDER
+                .replace_all(&config.template, |caps: &regex::Captures| {
+                    let name = &caps[1];
+                    let segment = config.segments.iter()
+                        .find(|segment| segment.name == name)
+                        .cloned()
+                        .unwrap_or_else(|| PromptSegment::named(name));
+                    if !is_prompt_segment(&segment.name) {
+                        return caps[0].to_string();
+                    }
+                    self.render_segment(&segment, config, &state).unwrap_or_default()
+                })
+                .into_owned()
+        };
+
+        if config.multiline {
+            prompt.push('\n');
         }
-        
         prompt
     }
 
+    // None when the segment is disabled, its condition fails or it has nothing to show
+    fn render_segment(&self, segment: &PromptSegment, config: &PromptConfiguration, state: &PromptState) -> Option<String> {
+        if !segment.enabled || !segment.condition.as_deref().is_none_or(|condition| state.holds(condition)) {
+            return None;
+        }
+        let context = state.context;
+        let (value, role) = match segment.name.as_str() {
+            "cwd" | "pwd" => (
+                truncate_directory(&context.directory_state.pwd, config.truncation_length),
+                "directory",
+            ),
+            "git" => {
+                let info = self.format_git_info(state.git.as_ref()?, &config.colors, &config.icons);
+                let role = if state.git.as_ref()?.is_dirty { "warning" } else { "git" };
+                (info, role)
+            }
+            "exit_code" if config.show_exit_code => match state.last_command?.exit_code? {
+                0 => (config.icons.success.clone(), "success"),
+                code => (format!("{} {}", config.icons.error, code).trim().to_string(), "error"),
+            },
+            "duration" if config.show_duration => (format_duration(state.last_command?.duration?), "warning"),
+            "time" => (
+                context.current_time.with_timezone(&chrono::Local).format("%H:%M:%S").to_string(),
+                "secondary",
+            ),
+            "user" => (context.operating_system.username.clone(), "primary"),
+            "hostname" => (context.operating_system.hostname.clone(), "info"),
+            _ => return None,
+        };
+        if value.is_empty() {
+            return None;
+        }
+
+        let text = if segment.format.is_empty() { value } else { segment.format.replace("{value}", &value) };
+        let mut codes = Vec::new();
+        codes.extend(prompt_color(segment.color.as_deref().unwrap_or(role), &config.colors, false));
+        codes.extend(segment.background.as_deref().and_then(|spec| prompt_color(spec, &config.colors, true)));
+        if codes.is_empty() {
+            Some(text)
+        } else {
+            Some(format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text))
+        }
+    }
+
     fn format_git_info(&self, git_status: &GitStatus, _colors: &PromptColors, icons: &PromptIcons) -> String {
         if let Some(branch) = &git_status.branch {
             let mut git_info = format!("{} {}", icons.git_branch, branch);
@@ -505,6 +780,343 @@ impl ShellIntegrationState {
     }
 }
 
+struct PromptState<'a> {
+    context: &'a crate::execution_context::ExecutionContext,
+    git: Option<GitStatus>,
+    last_command: Option<&'a CommandHistory>,
+}
+
+impl PromptState<'_> {
+    fn holds(&self, condition: &str) -> bool {
+        let exit_code = self.last_command.and_then(|command| command.exit_code);
+        match condition.trim() {
+            "git" => self.git.is_some(),
+            "dirty" => self.git.as_ref().is_some_and(|status| status.is_dirty),
+            "success" => exit_code == Some(0),
+            "failed" => exit_code.is_some_and(|code| code != 0),
+            other => match other.strip_prefix("duration>").and_then(|ms| ms.trim().parse::<u64>().ok()) {
+                Some(threshold) => self.last_command.and_then(|command| command.duration).is_some_and(|d| d > threshold),
+                None => false,
+            },
+        }
+    }
+}
+
+fn is_prompt_segment(name: &str) -> bool {
+    matches!(name, "cwd" | "pwd" | "git" | "exit_code" | "duration" | "time" | "user" | "hostname")
+}
+
+// Abbreviate the home directory to ~ and keep only the last `keep` components
+fn truncate_directory(path: &str, keep: usize) -> String {
+    let separator = if path.contains('\\') && !path.contains('/') { '\\' } else { '/' };
+    let home = std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")).unwrap_or_default();
+    let path = match path.strip_prefix(home.as_str()) {
+        Some(rest) if !home.is_empty() && (rest.is_empty() || rest.starts_with(separator)) => format!("~{}", rest),
+        _ => path.to_string(),
+    };
+
+    let components: Vec<&str> = path.split(separator).filter(|part| !part.is_empty()).collect();
+    if keep == 0 || components.len() <= keep {
+        return path;
+    }
+    format!("…{}{}", separator, components[components.len() - keep..].join(&separator.to_string()))
+}
+
+// `duration` is in milliseconds, as recorded in CommandHistory
+fn format_duration(millis: u64) -> String {
+    let seconds = millis / 1000;
+    if millis < 1000 {
+        format!("{}ms", millis)
+    } else if seconds < 60 {
+        format!("{:.1}s", millis as f64 / 1000.0)
+    } else if seconds < 3600 {
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}h {:02}m", seconds / 3600, (seconds % 3600) / 60)
+    }
+}
+
+// SGR parameters for a role name, #rrggbb, 256-color index or basic color name
+fn prompt_color(spec: &str, colors: &PromptColors, background: bool) -> Option<String> {
+    let spec = match spec.trim() {
+        "primary" => colors.primary.as_str(),
+        "secondary" => colors.secondary.as_str(),
+        "success" => colors.success.as_str(),
+        "warning" => colors.warning.as_str(),
+        "error" => colors.error.as_str(),
+        "info" => colors.info.as_str(),
+        "directory" => colors.directory.as_str(),
+        "git" => colors.git.as_str(),
+        other => other,
+    }
+    .trim()
+    .to_lowercase();
+    let base = if background { 40 } else { 30 };
+
+    if spec.starts_with('#') {
+        let color = crate::theme_manager::Color::from_hex(&spec).ok()?;
+        return Some(format!("{};2;{};{};{}", base + 8, color.r, color.g, color.b));
+    }
+    if let Ok(index) = spec.parse::<u8>() {
+        return Some(format!("{};5;{}", base + 8, index));
+    }
+    let (name, bright) = match spec.strip_prefix("bright_") {
+        Some(name) => (name, true),
+        None => (spec.as_str(), false),
+    };
+    let offset = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"]
+        .iter()
+        .position(|candidate| *candidate == name)?;
+    Some((base + offset + if bright { 60 } else { 0 }).to_string())
+}
+
+// Shell integration: each script emits OSC 133 marks (A prompt start, B command
+// start, C output start, D;<exit code> command end) and OSC 7 with the working
+// directory. They guard against being sourced twice and only act in interactive
+// shells.
+
+pub const BASH_INTEGRATION: &str = r##"# Shell integration for bash: OSC 133 prompt marks and OSC 7 working directory
+if [[ $- == *i* && -z "$__warp_terminal_integration" ]]; then
+    __warp_terminal_integration=1
+    __warp_terminal_prompted=""
+    __warp_terminal_precmd() {
+        local exit_status=$?
+        if [[ -n "$__warp_terminal_prompted" ]]; then
+            printf '\e]133;D;%s\a' "$exit_status"
+        fi
+        __warp_terminal_prompted=1
+        printf '\e]7;file://%s%s\a' "$HOSTNAME" "$PWD"
+        printf '\e]133;A\a'
+        return $exit_status
+    }
+    PROMPT_COMMAND="__warp_terminal_precmd${PROMPT_COMMAND:+; $PROMPT_COMMAND}"
+    PS1="$PS1\[\e]133;B\a\]"
+    # PS0 (bash 4.4+) is printed after a command line is read, before it runs
+    PS0="\e]133;C\a$PS0"
+fi
+"##;
+
+pub const ZSH_INTEGRATION: &str = r##"# Shell integration for zsh: OSC 133 prompt marks and OSC 7 working directory
+if [[ -o interactive && -z "$__warp_terminal_integration" ]]; then
+    typeset -g __warp_terminal_integration=1
+    typeset -g __warp_terminal_ran=""
+    __warp_terminal_precmd() {
+        local exit_status=$?
+        if [[ -n "$__warp_terminal_ran" ]]; then
+            printf '\e]133;D;%s\a' "$exit_status"
+        fi
+        __warp_terminal_ran=""
+        printf '\e]7;file://%s%s\a' "$HOST" "$PWD"
+        printf '\e]133;A\a'
+    }
+    __warp_terminal_preexec() {
+        __warp_terminal_ran=1
+        printf '\e]133;C\a'
+    }
+    # First in line, so $? is still the command's status
+    precmd_functions=(__warp_terminal_precmd $precmd_functions)
+    preexec_functions+=(__warp_terminal_preexec)
+    PS1="$PS1%{$(printf '\e]133;B\a')%}"
+fi
+"##;
+
+pub const FISH_INTEGRATION: &str = r##"# Shell integration for fish: OSC 133 prompt marks and OSC 7 working directory
+if status is-interactive; and not set -q __warp_terminal_integration
+    set -g __warp_terminal_integration 1
+    function __warp_terminal_prompt_start --on-event fish_prompt
+        printf '\e]7;file://%s%s\a' $hostname "$PWD"
+        printf '\e]133;A\a'
+    end
+    function __warp_terminal_preexec --on-event fish_preexec
+        printf '\e]133;C\a'
+    end
+    function __warp_terminal_postexec --on-event fish_postexec
+        printf '\e]133;D;%s\a' $status
+    end
+    functions -c fish_prompt __warp_terminal_original_prompt
+    function fish_prompt
+        __warp_terminal_original_prompt
+        printf '\e]133;B\a'
+    end
+end
+"##;
+
+pub const PWSH_INTEGRATION: &str = r##"# Shell integration for PowerShell: OSC 133 prompt marks and OSC 7 working directory
+if (-not $Global:__WarpTerminalIntegration) {
+    $Global:__WarpTerminalIntegration = $true
+    $Global:__WarpTerminalOriginalPrompt = $function:prompt
+    $Global:__WarpTerminalLastHistoryId = -1
+    function Global:prompt {
+        $success = $?
+        $exitCode = if ($success) { 0 } elseif ($Global:LASTEXITCODE) { $Global:LASTEXITCODE } else { 1 }
+        $esc = [char]27
+        $bel = [char]7
+        $marks = ''
+        # PowerShell has no preexec hook: a new history entry means a command ran
+        $lastId = (Get-History -Count 1).Id
+        if ($Global:__WarpTerminalLastHistoryId -ne -1 -and $lastId -ne $Global:__WarpTerminalLastHistoryId) {
+            $marks += "$esc]133;D;$exitCode$bel"
+        }
+        $Global:__WarpTerminalLastHistoryId = if ($lastId) { $lastId } else { 0 }
+        if ($PWD.Provider.Name -eq 'FileSystem') {
+            $path = $PWD.ProviderPath -replace '\\', '/'
+            if (-not $path.StartsWith('/')) { $path = "/$path" }
+            $marks += "$esc]7;file://$([System.Net.Dns]::GetHostName())$path$bel"
+        }
+        "$marks$esc]133;A$bel$(& $Global:__WarpTerminalOriginalPrompt)$esc]133;B$bel"
+    }
+    # PSReadLine returns the line just before it runs, which marks the command start
+    if (Get-Command PSConsoleHostReadLine -ErrorAction SilentlyContinue) {
+        $Global:__WarpTerminalOriginalReadLine = $function:PSConsoleHostReadLine
+        function Global:PSConsoleHostReadLine {
+            $line = & $Global:__WarpTerminalOriginalReadLine
+            [Console]::Write("$([char]27)]133;C$([char]7)")
+            $line
+        }
+    }
+}
+"##;
+
+// Loaded through --rcfile in place of ~/.bashrc, so it loads the user's own
+// startup files first (the profile files for a login shell)
+const BASH_RC_PREAMBLE: &str = r##"if [ -n "$WARP_TERMINAL_BASH_LOGIN" ]; then
+    unset WARP_TERMINAL_BASH_LOGIN
+    [ -r /etc/profile ] && . /etc/profile
+    for __warp_terminal_profile in ~/.bash_profile ~/.bash_login ~/.profile; do
+        if [ -r "$__warp_terminal_profile" ]; then
+            . "$__warp_terminal_profile"
+            break
+        fi
+    done
+    unset __warp_terminal_profile
+else
+    [ -r /etc/bash.bashrc ] && . /etc/bash.bashrc
+    [ -r ~/.bashrc ] && . ~/.bashrc
+fi
+"##;
+
+// zsh reads its startup files from $ZDOTDIR, which points here: each file
+// loads the user's own from their real ZDOTDIR, and .zshrc hands ZDOTDIR back
+const ZSH_STARTUP_FILE: &str = r##"__warp_terminal_zdotdir=$ZDOTDIR
+ZDOTDIR=${WARP_TERMINAL_USER_ZDOTDIR:-$HOME}
+[[ -r "$ZDOTDIR/{file}" ]] && source "$ZDOTDIR/{file}"
+WARP_TERMINAL_USER_ZDOTDIR=$ZDOTDIR
+ZDOTDIR=$__warp_terminal_zdotdir
+unset __warp_terminal_zdotdir
+"##;
+
+const ZSH_RC_PREAMBLE: &str = r##"ZDOTDIR=${WARP_TERMINAL_USER_ZDOTDIR:-$HOME}
+unset WARP_TERMINAL_USER_ZDOTDIR
+[[ -r "$ZDOTDIR/.zshrc" ]] && source "$ZDOTDIR/.zshrc"
+"##;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IntegrationShell {
+    Bash,
+    Zsh,
+    Fish,
+    Pwsh,
+}
+
+impl IntegrationShell {
+    /// The shell behind a program name or path (`/bin/zsh`, `pwsh.exe`, ...).
+    pub fn detect(program: &str) -> Option<Self> {
+        let name = Path::new(program.trim())
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match name.as_str() {
+            "bash" => Some(Self::Bash),
+            "zsh" => Some(Self::Zsh),
+            "fish" => Some(Self::Fish),
+            "pwsh" | "powershell" => Some(Self::Pwsh),
+            _ => None,
+        }
+    }
+
+    pub fn script(self) -> &'static str {
+        match self {
+            Self::Bash => BASH_INTEGRATION,
+            Self::Zsh => ZSH_INTEGRATION,
+            Self::Fish => FISH_INTEGRATION,
+            Self::Pwsh => PWSH_INTEGRATION,
+        }
+    }
+}
+
+fn integration_dir() -> PathBuf {
+    let home = std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(home).join(".warp-terminal").join("shell-integration")
+}
+
+fn write_integration_file(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    std::fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Options that launch `shell` with its integration script sourced after the
+/// user's own startup files, or `None` when `shell` isn't supported or is
+/// being run with its own arguments (a script or `-c`).
+///
+/// Users opt out with the `shell_integration_injection` setting, or per
+/// session with `ShellOptions::no_shell_integration`.
+pub fn inject_shell_integration(options: &crate::pty::ShellOptions, shell: &str) -> Result<Option<crate::pty::ShellOptions>, String> {
+    inject_shell_integration_in(&integration_dir(), options, shell)
+}
+
+fn inject_shell_integration_in(dir: &Path, options: &crate::pty::ShellOptions, shell: &str) -> Result<Option<crate::pty::ShellOptions>, String> {
+    let Some(integration) = IntegrationShell::detect(shell) else {
+        return Ok(None);
+    };
+    if !options.args.is_empty() {
+        return Ok(None);
+    }
+    let mut injected = options.clone();
+    match integration {
+        IntegrationShell::Bash => {
+            let rc_file = dir.join("bash").join("rc.bash");
+            write_integration_file(&rc_file, &format!("{}{}", BASH_RC_PREAMBLE, BASH_INTEGRATION))?;
+            // bash ignores --rcfile in login shells, so the rc file loads the profile itself
+            if injected.login_shell {
+                injected.login_shell = false;
+                injected.env.insert("WARP_TERMINAL_BASH_LOGIN".to_string(), "1".to_string());
+            }
+            injected.args = vec!["--rcfile".to_string(), rc_file.to_string_lossy().into_owned()];
+        }
+        IntegrationShell::Zsh => {
+            let zdotdir = dir.join("zsh");
+            for file in [".zshenv", ".zprofile"] {
+                write_integration_file(&zdotdir.join(file), &ZSH_STARTUP_FILE.replace("{file}", file))?;
+            }
+            write_integration_file(&zdotdir.join(".zshrc"), &format!("{}{}", ZSH_RC_PREAMBLE, ZSH_INTEGRATION))?;
+            let user_zdotdir = options.env.get("ZDOTDIR").cloned()
+                .or_else(|| std::env::var("ZDOTDIR").ok())
+                .or_else(|| std::env::var("HOME").ok())
+                .unwrap_or_default();
+            injected.env.insert("WARP_TERMINAL_USER_ZDOTDIR".to_string(), user_zdotdir);
+            injected.env.insert("ZDOTDIR".to_string(), zdotdir.to_string_lossy().into_owned());
+        }
+        IntegrationShell::Fish => {
+            let script = dir.join("integration.fish");
+            write_integration_file(&script, FISH_INTEGRATION)?;
+            let path = script.to_string_lossy().replace('\\', "\\\\").replace('\'', "\\'");
+            injected.args = vec!["--init-command".to_string(), format!("source '{}'", path)];
+        }
+        IntegrationShell::Pwsh => {
+            let script = dir.join("integration.ps1");
+            write_integration_file(&script, PWSH_INTEGRATION)?;
+            let path = script.to_string_lossy().replace('\'', "''");
+            injected.args = vec!["-NoExit".to_string(), "-Command".to_string(), format!(". '{}'", path)];
+        }
+    }
+    Ok(Some(injected))
+}
+
 // Tauri commands
 #[tauri::command]
 pub async fn get_shell_completions(
@@ -518,6 +1130,22 @@ pub async fn get_shell_completions(
     Ok(manager.get_completion_suggestions(&input, cursor_position, &shell_type, &current_dir))
 }
 
+#[tauri::command]
+pub async fn load_completion_spec(path: String) -> Result<String, String> {
+    crate::completions::load_completion_spec(&path)
+        .map_err(|e| format!("Invalid completion spec {}: {}", e.path, e.errors.join("; ")))
+}
+
+#[tauri::command]
+pub async fn reload_completion_specs() -> Result<crate::completions::SpecLoadReport, String> {
+    Ok(crate::completions::reload_completion_specs())
+}
+
+#[tauri::command]
+pub async fn list_completion_specs() -> Result<Vec<crate::completions::CompletionSpec>, String> {
+    Ok(crate::completions::list_completion_specs())
+}
+
 #[tauri::command]
 pub async fn add_command_to_history(
     command: String,
@@ -526,7 +1154,13 @@ pub async fn add_command_to_history(
     exit_code: Option<i32>,
     duration: Option<u64>,
     integration_manager: State<'_, ShellIntegrationManager>,
+    context_manager: State<'_, crate::execution_context::ExecutionContextManager>,
 ) -> Result<(), String> {
+    // Per-directory stats are a nicety; never lose the history entry over them
+    match context_manager.lock() {
+        Ok(mut context) => context.record_directory_command(&directory, &command),
+        Err(e) => log::warn!("Failed to record directory command: {}", e),
+    }
     let mut manager = integration_manager.lock().map_err(|e| e.to_string())?;
     let history_item = CommandHistory {
         id: uuid::Uuid::new_v4().to_string(),
@@ -553,6 +1187,16 @@ pub async fn search_command_history(
     Ok(manager.search_history(&query, limit))
 }
 
+#[tauri::command]
+pub async fn get_autosuggestion(
+    prefix: String,
+    cwd: String,
+    integration_manager: State<'_, ShellIntegrationManager>,
+) -> Result<Option<String>, String> {
+    let manager = integration_manager.lock().map_err(|e| e.to_string())?;
+    Ok(manager.get_autosuggestion(&prefix, &cwd))
+}
+
 #[tauri::command]
 pub async fn add_shell_alias(
     name: String,
@@ -582,6 +1226,15 @@ pub async fn get_shell_aliases(
     Ok(manager.aliases.values().cloned().collect())
 }
 
+#[tauri::command]
+pub async fn expand_aliases(
+    command_line: String,
+    integration_manager: State<'_, ShellIntegrationManager>,
+) -> Result<AliasExpansion, String> {
+    let manager = integration_manager.lock().map_err(|e| e.to_string())?;
+    Ok(manager.expand_aliases(&command_line))
+}
+
 #[tauri::command]
 pub async fn get_git_status(
     directory: String,
@@ -631,7 +1284,229 @@ pub async fn generate_custom_prompt(
     config: PromptConfiguration,
     context: crate::execution_context::ExecutionContext,
     integration_manager: State<'_, ShellIntegrationManager>,
+    dev_tools_manager: State<'_, Arc<tokio::sync::Mutex<crate::dev_tools::DevToolsManager>>>,
 ) -> Result<String, String> {
+    let git = if config.show_git {
+        let directory = PathBuf::from(&context.directory_state.pwd);
+        dev_tools_manager
+            .lock()
+            .await
+            .git_working_tree(&directory)
+            .await
+            .ok()
+            .map(|(branch, status)| GitStatus::from_working_tree(branch, &status))
+    } else {
+        None
+    };
     let manager = integration_manager.lock().map_err(|e| e.to_string())?;
-    Ok(manager.generate_prompt(&config, &context))
+    Ok(manager.generate_prompt(&config, &context, git))
+}
+
+/// The OSC 133/OSC 7 integration script for `shell` (a name or path), for
+/// users who'd rather source it from their own rc files.
+#[tauri::command]
+pub async fn get_shell_integration_script(shell: String) -> Result<String, String> {
+    IntegrationShell::detect(&shell)
+        .map(|integration| integration.script().to_string())
+        .ok_or_else(|| format!("No shell integration for {}; bash, zsh, fish and pwsh are supported", shell))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> PromptConfiguration {
+        let colors = PromptColors {
+            primary: String::new(),
+            secondary: String::new(),
+            success: "green".into(),
+            warning: "yellow".into(),
+            error: "red".into(),
+            info: String::new(),
+            directory: "blue".into(),
+            git: "magenta".into(),
+        };
+        let icons = PromptIcons {
+            directory: String::new(),
+            git_branch: "@".into(),
+            git_modified: "!".into(),
+            git_staged: "+".into(),
+            git_untracked: "?".into(),
+            success: String::new(),
+            error: "x".into(),
+            lock: String::new(),
+            user: String::new(),
+            host: String::new(),
+        };
+        let git = PromptSegment { format: "on {value}".into(), condition: Some("git".into()), ..PromptSegment::named("git") };
+        PromptConfiguration {
+            template: String::new(),
+            segments: vec![PromptSegment::named("cwd"), git],
+            colors,
+            icons,
+            show_git: true,
+            show_duration: false,
+            show_exit_code: false,
+            multiline: false,
+            truncation_length: 0,
+        }
+    }
+
+    fn context(pwd: &str) -> crate::execution_context::ExecutionContext {
+        let mut state = crate::execution_context::ExecutionContextState::new();
+        state.create_context("test".into()).unwrap();
+        let mut context = state.get_context("test").unwrap().clone();
+        context.directory_state.pwd = pwd.to_string();
+        context
+    }
+
+    fn git(args: &[&str], dir: &Path) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn the_git_segment_only_shows_in_a_repository() {
+        let manager = ShellIntegrationState::new();
+        let context = context("/srv/project");
+        assert_eq!(manager.generate_prompt(&config(), &context, None), "\x1b[34m/srv/project\x1b[0m");
+
+        let clean = GitStatus { branch: Some("main".into()), ahead: 0, behind: 0, staged: 0, modified: 0, untracked: 0, conflicts: 0, stashes: 0, is_dirty: false, is_detached: false };
+        assert_eq!(
+            manager.generate_prompt(&config(), &context, Some(clean.clone())),
+            "\x1b[34m/srv/project\x1b[0m \x1b[35mon @ main\x1b[0m"
+        );
+        let dirty = GitStatus { modified: 2, untracked: 1, is_dirty: true, ..clean.clone() };
+        assert_eq!(
+            manager.generate_prompt(&config(), &context, Some(dirty)),
+            "\x1b[34m/srv/project\x1b[0m \x1b[33mon @ main !2 ?1\x1b[0m"
+        );
+
+        let hidden = PromptConfiguration { show_git: false, ..config() };
+        assert_eq!(manager.generate_prompt(&hidden, &context, Some(clean)), "\x1b[34m/srv/project\x1b[0m");
+    }
+
+    #[tokio::test]
+    async fn working_tree_status_comes_from_dev_tools() {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        let root = std::env::temp_dir().join(format!("warp-prompt-test-{}-{}", std::process::id(), nanos));
+        let repo = root.join("repo");
+        let plain = root.join("plain");
+        std::fs::create_dir_all(&repo).unwrap();
+        std::fs::create_dir_all(&plain).unwrap();
+        git(&["init", "-q", "-b", "main"], &repo);
+        std::fs::write(repo.join("tracked.txt"), "one").unwrap();
+        git(&["add", "tracked.txt"], &repo);
+        git(&["commit", "-q", "-m", "init"], &repo);
+        std::fs::write(repo.join("new.txt"), "two").unwrap();
+
+        let dev_tools = crate::dev_tools::DevToolsManager::new();
+        let (branch, status) = dev_tools.git_working_tree(&repo).await.unwrap();
+        let status = GitStatus::from_working_tree(branch, &status);
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!((status.untracked, status.modified, status.staged), (1, 0, 0));
+        assert!(status.is_dirty);
+        // A directory outside any repository has no git status, so no git segment
+        assert!(dev_tools.git_working_tree(&plain).await.is_err());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn shells_are_detected_from_the_program() {
+        assert_eq!(IntegrationShell::detect("bash"), Some(IntegrationShell::Bash));
+        assert_eq!(IntegrationShell::detect("/usr/bin/zsh"), Some(IntegrationShell::Zsh));
+        assert_eq!(IntegrationShell::detect(" /opt/homebrew/bin/fish "), Some(IntegrationShell::Fish));
+        assert_eq!(IntegrationShell::detect("pwsh.exe"), Some(IntegrationShell::Pwsh));
+        assert_eq!(IntegrationShell::detect("PowerShell.EXE"), Some(IntegrationShell::Pwsh));
+        assert_eq!(IntegrationShell::detect("/bin/sh"), None);
+        assert_eq!(IntegrationShell::detect("cmd.exe"), None);
+        assert_eq!(IntegrationShell::detect(""), None);
+    }
+
+    #[test]
+    fn every_script_emits_the_prompt_command_and_cwd_marks() {
+        for (shell, esc, bel) in [
+            (IntegrationShell::Bash, "\\e]", "\\a"),
+            (IntegrationShell::Zsh, "\\e]", "\\a"),
+            (IntegrationShell::Fish, "\\e]", "\\a"),
+            (IntegrationShell::Pwsh, "$esc]", "$bel"),
+        ] {
+            let script = shell.script();
+            for mark in ["133;A", "133;B", "133;D;", "7;file://"] {
+                assert!(script.contains(&format!("{}{}", esc, mark)), "{:?} script is missing OSC {}", shell, mark);
+            }
+            let command_start = if shell == IntegrationShell::Pwsh { "$([char]27)]133;C$([char]7)".to_string() } else { format!("{}133;C{}", esc, bel) };
+            assert!(script.contains(&command_start), "{:?} script is missing OSC 133;C", shell);
+        }
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        std::env::temp_dir().join(format!("warp-{}-{}-{}", name, std::process::id(), nanos))
+    }
+
+    #[test]
+    fn bash_loads_the_rc_file_and_a_login_shell_loads_its_profile_from_it() {
+        let dir = scratch_dir("bash-integration");
+        let rc_file = dir.join("bash").join("rc.bash");
+
+        let plain = inject_shell_integration_in(&dir, &crate::pty::ShellOptions::default(), "/bin/bash").unwrap().unwrap();
+        assert_eq!(plain.args, vec!["--rcfile".to_string(), rc_file.to_string_lossy().into_owned()]);
+        assert!(!plain.login_shell);
+        assert!(!plain.env.contains_key("WARP_TERMINAL_BASH_LOGIN"));
+        let rc = std::fs::read_to_string(&rc_file).unwrap();
+        assert!(rc.starts_with(BASH_RC_PREAMBLE) && rc.ends_with(BASH_INTEGRATION));
+
+        // bash skips --rcfile for login shells, so the login is handed to the rc file
+        let login = crate::pty::ShellOptions { login_shell: true, ..Default::default() };
+        let injected = inject_shell_integration_in(&dir, &login, "bash").unwrap().unwrap();
+        assert!(!injected.login_shell);
+        assert_eq!(injected.env.get("WARP_TERMINAL_BASH_LOGIN").map(String::as_str), Some("1"));
+        assert_eq!(injected.args[0], "--rcfile");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn zsh_startup_goes_through_a_zdotdir_that_hands_back_the_users_own() {
+        let dir = scratch_dir("zsh-integration");
+        let zdotdir = dir.join("zsh");
+        let mut options = crate::pty::ShellOptions::default();
+        options.env.insert("ZDOTDIR".to_string(), "/home/user/.config/zsh".to_string());
+
+        let injected = inject_shell_integration_in(&dir, &options, "zsh").unwrap().unwrap();
+        assert!(injected.args.is_empty());
+        assert_eq!(injected.env.get("ZDOTDIR").map(String::as_str), Some(zdotdir.to_string_lossy().as_ref()));
+        assert_eq!(injected.env.get("WARP_TERMINAL_USER_ZDOTDIR").map(String::as_str), Some("/home/user/.config/zsh"));
+        for file in [".zshenv", ".zprofile"] {
+            let startup = std::fs::read_to_string(zdotdir.join(file)).unwrap();
+            assert!(startup.contains(&format!("source \"$ZDOTDIR/{}\"", file)));
+            assert!(startup.contains("ZDOTDIR=$__warp_terminal_zdotdir"));
+        }
+        let rc = std::fs::read_to_string(zdotdir.join(".zshrc")).unwrap();
+        assert!(rc.starts_with(ZSH_RC_PREAMBLE) && rc.ends_with(ZSH_INTEGRATION));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn fish_and_pwsh_source_their_script_and_explicit_args_are_left_alone() {
+        let dir = scratch_dir("other-integration");
+        let fish = inject_shell_integration_in(&dir, &crate::pty::ShellOptions::default(), "fish").unwrap().unwrap();
+        assert_eq!(fish.args, vec!["--init-command".to_string(), format!("source '{}'", dir.join("integration.fish").display())]);
+        let pwsh = inject_shell_integration_in(&dir, &crate::pty::ShellOptions::default(), "pwsh").unwrap().unwrap();
+        assert_eq!(pwsh.args, vec!["-NoExit".to_string(), "-Command".to_string(), format!(". '{}'", dir.join("integration.ps1").display())]);
+        assert_eq!(std::fs::read_to_string(dir.join("integration.ps1")).unwrap(), PWSH_INTEGRATION);
+
+        let script = crate::pty::ShellOptions { args: vec!["-c".into(), "ls".into()], ..Default::default() };
+        assert!(inject_shell_integration_in(&dir, &script, "bash").unwrap().is_none());
+        assert!(inject_shell_integration_in(&dir, &crate::pty::ShellOptions::default(), "sh").unwrap().is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+// Tables need a header plus this many rows before we trust the alignment
+const MIN_TABLE_ROWS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputFormat {
+    Json,
+    JsonLines,
+    Table,
+    Raw,
+}
+
+/// A command block's output in a form the UI can render interactively.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "format")]
+pub enum StructuredOutput {
+    Json { value: serde_json::Value, pretty: String },
+    JsonLines { values: Vec<serde_json::Value> },
+    Table { columns: Vec<String>, rows: Vec<Vec<String>> },
+    Raw { text: String },
+}
+
+pub fn detect_format(output: &str) -> OutputFormat {
+    match parse(output) {
+        StructuredOutput::Json { .. } => OutputFormat::Json,
+        StructuredOutput::JsonLines { .. } => OutputFormat::JsonLines,
+        StructuredOutput::Table { .. } => OutputFormat::Table,
+        StructuredOutput::Raw { .. } => OutputFormat::Raw,
+    }
+}
+
+pub fn parse(output: &str) -> StructuredOutput {
+    let trimmed = output.trim();
+    if let Some(structured) = parse_json(trimmed).or_else(|| parse_json_lines(trimmed)) {
+        return structured;
+    }
+    let lines: Vec<&str> = trimmed.lines().map(str::trim_end).filter(|line| !line.trim().is_empty()).collect();
+    if let Some(table) = parse_tab_separated(&lines).or_else(|| parse_aligned_table(&lines)) {
+        return table;
+    }
+    StructuredOutput::Raw { text: output.to_string() }
+}
+
+fn parse_json(text: &str) -> Option<StructuredOutput> {
+    if !(text.starts_with('{') || text.starts_with('[')) {
+        return None;
+    }
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    let pretty = serde_json::to_string_pretty(&value).ok()?;
+    Some(StructuredOutput::Json { value, pretty })
+}
+
+// One JSON object per line, as emitted by loggers and `--format json` flags
+fn parse_json_lines(text: &str) -> Option<StructuredOutput> {
+    let values: Vec<serde_json::Value> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str::<serde_json::Value>(line.trim()).ok().filter(|v| v.is_object()))
+        .collect::<Option<_>>()?;
+    (values.len() >= 2).then_some(StructuredOutput::JsonLines { values })
+}
+
+fn parse_tab_separated(lines: &[&str]) -> Option<StructuredOutput> {
+    let width = lines.first()?.split('\t').count();
+    if width < 2 || lines.len() < MIN_TABLE_ROWS + 1 || lines.iter().any(|line| line.split('\t').count() != width) {
+        return None;
+    }
+    let mut rows = lines.iter().map(|line| line.split('\t').map(|cell| cell.trim().to_string()).collect::<Vec<_>>());
+    let columns = rows.next()?;
+    Some(StructuredOutput::Table { columns, rows: rows.collect() })
+}
+
+/// Column-aligned output like `docker ps` or `kubectl get`: columns are split at
+/// character positions that are blank on every line, and every header cell must
+/// be non-empty. The last column may contain spaces.
+fn parse_aligned_table(lines: &[&str]) -> Option<StructuredOutput> {
+    if lines.len() < MIN_TABLE_ROWS + 1 {
+        return None;
+    }
+    let grid: Vec<Vec<char>> = lines.iter().map(|line| line.chars().collect()).collect();
+    if grid.iter().flatten().any(|c| *c == '\t') {
+        return None;
+    }
+    let width = grid.iter().map(Vec::len).max()?;
+    let blank_everywhere = |col: usize| grid.iter().all(|row| row.get(col).is_none_or(|c| *c == ' '));
+
+    // Padding is the tell for alignment: some row must have 2+ blanks before a
+    // column's text, which rules out ordinary single-spaced text
+    let padded = |start: usize| {
+        start >= 2 && grid.iter().any(|row| row.get(start).is_some_and(|c| *c != ' ') && row[start - 1] == ' ' && row[start - 2] == ' ')
+    };
+    let filled = |start: usize, end: usize| {
+        grid[1..].iter().all(|row| row.iter().skip(start).take(end - start).any(|c| *c != ' '))
+    };
+
+    // Candidate starts: a non-blank position after a blank-everywhere one. The
+    // header defines the columns, so anything past its end belongs to the last one.
+    let header_len = grid[0].len();
+    let candidates: Vec<usize> = (1..width.min(header_len))
+        .filter(|&col| blank_everywhere(col - 1) && !blank_everywhere(col))
+        .collect();
+    // A single-spaced header gap ("%MEM VSZ" in `ps aux`) is still a column when
+    // every row has data under it; otherwise it's a two-word label like "CONTAINER ID"
+    let mut starts = vec![0];
+    for (i, &col) in candidates.iter().enumerate() {
+        let end = candidates.get(i + 1).copied().unwrap_or(width);
+        if padded(col) || filled(col, end) {
+            starts.push(col);
+        }
+    }
+    if starts.len() < 2 || !starts[1..].iter().any(|&start| padded(start)) {
+        return None;
+    }
+
+    let split = |row: &[char]| -> Vec<String> {
+        starts
+            .iter()
+            .enumerate()
+            .map(|(i, &start)| {
+                let end = starts.get(i + 1).copied().unwrap_or(row.len()).min(row.len());
+                if start >= end { String::new() } else { row[start..end].iter().collect::<String>().trim().to_string() }
+            })
+            .collect()
+    };
+
+    let columns = split(&grid[0]);
+    // Each header cell must be a label, so an unaligned prose line can't pass as a header
+    if columns.iter().any(|column| column.is_empty()) {
+        return None;
+    }
+    let rows: Vec<Vec<String>> = grid[1..].iter().map(|row| split(row)).collect();
+    Some(StructuredOutput::Table { columns, rows })
+}
@@ -1,25 +1,134 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 use std::{fs, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
 
-#[derive(Serialize)]
-pub struct TelemetryEvent<'a> {
-    pub ts: u64,
-    pub kind: &'a str,
-    pub data: serde_json::Value,
-}
+const UPLOAD_BATCH_SIZE: usize = 100; // event kinds per request
+const UPLOAD_ATTEMPTS: u32 = 4;
+const MAX_EVENT_KIND_LEN: usize = 64;
+const CRASH_LOG_TAIL_LINES: usize = 50;
+// Keys whose values never go into a crash report, matched as substrings
+const OMITTED_KEYS: &[&str] = &["command", "history", "password", "secret", "token"];
 
-fn telemetry_path() -> PathBuf {
+fn config_dir() -> PathBuf {
     let home = if cfg!(windows) {
         std::env::var("USERPROFILE").unwrap_or_else(|_| ".".into())
     } else {
         std::env::var("HOME").unwrap_or_else(|_| ".".into())
     };
-    PathBuf::from(home).join(".warp-terminal").join("telemetry.log")
+    PathBuf::from(home).join(".warp-terminal")
 }
 
-pub fn record(kind: &str, data: serde_json::Value) {
-    let ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-    let ev = TelemetryEvent { ts, kind, data };
+fn telemetry_path() -> PathBuf { config_dir().join("telemetry.log") }
+
+fn crash_reports_dir() -> PathBuf { config_dir().join("crash-reports") }
+
+/// Everything needed to file a bug about a panic. Secrets are masked with the
+/// security module's patterns and command text/history is dropped entirely.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub id: String,
+    pub created_at: u64, // unix seconds
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub message: String,
+    pub location: Option<String>,
+    pub backtrace: String,
+    pub log_tail: Vec<serde_json::Value>,
+    pub settings: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReportSummary {
+    pub id: String,
+    pub created_at: u64,
+    pub message: String,
+}
+
+fn metrics_path() -> PathBuf { config_dir().join("metrics.json") }
+
+/// How often buffered metrics are written to disk.
+pub const METRICS_FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+// The opt-in flag, kept in step with settings by `set_enabled` so `record`
+// never touches the settings file
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+// In-memory buffer, loaded from disk on first use and written back by `flush_metrics`
+static METRICS: Mutex<Option<Metrics>> = Mutex::new(None);
+
+struct Metrics {
+    buffer: MetricsBuffer,
+    dirty: bool,
+}
+
+#[derive(Serialize)]
+struct TelemetryEvent<'a> {
+    ts: u64,
+    kind: &'a str,
+    data: serde_json::Value,
+}
+
+/// Locally buffered usage metrics: how often each feature was used, nothing else.
+/// Event payloads are never stored.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricsBuffer {
+    pub counts: BTreeMap<String, u64>,
+    pub period_start: Option<u64>, // unix seconds of the first buffered event
+    pub period_end: Option<u64>,
+    pub last_upload: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetrySummary {
+    pub enabled: bool,
+    pub endpoint: Option<String>,
+    pub total_events: u64,
+    /// Exactly what an upload would send
+    pub pending: MetricsBuffer,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryUploadResult {
+    pub batches: usize,
+    pub events: u64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Turn recording on or off; called at startup and whenever the setting changes.
+/// Opting out drops everything buffered.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+    if !enabled {
+        clear_metrics();
+    }
+}
+
+fn load_metrics() -> MetricsBuffer {
+    fs::read_to_string(metrics_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_metrics(buffer: &MetricsBuffer) -> Result<(), String> {
+    fs::create_dir_all(config_dir()).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(buffer).map_err(|e| e.to_string())?;
+    fs::write(metrics_path(), json).map_err(|e| e.to_string())
+}
+
+fn with_metrics<T>(f: impl FnOnce(&mut Metrics) -> T) -> T {
+    let mut metrics = METRICS.lock().unwrap_or_else(|e| e.into_inner());
+    f(metrics.get_or_insert_with(|| Metrics { buffer: load_metrics(), dirty: false }))
+}
+
+fn append_log(kind: &str, data: serde_json::Value) {
+    let ev = TelemetryEvent { ts: now_secs(), kind, data };
     if let Ok(line) = serde_json::to_string(&ev) {
         if let Some(parent) = telemetry_path().parent() { let _ = fs::create_dir_all(parent); }
         let _ = fs::OpenOptions::new().create(true).append(true).open(telemetry_path()).and_then(|mut f| {
@@ -29,6 +138,129 @@ pub fn record(kind: &str, data: serde_json::Value) {
     }
 }
 
+// Kinds come from the frontend; anything that isn't a short identifier might
+// carry user content and is dropped
+fn is_valid_event_kind(kind: &str) -> bool {
+    !kind.is_empty()
+        && kind.len() <= MAX_EVENT_KIND_LEN
+        && kind.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ':' | '-'))
+}
+
+/// Count one use of `kind` and append the event to the local log that crash
+/// reports quote from. Only the count is ever uploaded. Does nothing at all
+/// unless the user opted in.
+pub fn record(kind: &str, data: serde_json::Value) {
+    if !ENABLED.load(Ordering::SeqCst) || !is_valid_event_kind(kind) {
+        return;
+    }
+    append_log(kind, data);
+    let now = now_secs();
+    with_metrics(|metrics| {
+        *metrics.buffer.counts.entry(kind.to_string()).or_insert(0) += 1;
+        metrics.buffer.period_start.get_or_insert(now);
+        metrics.buffer.period_end = Some(now);
+        metrics.dirty = true;
+    });
+}
+
+/// Write buffered counts to disk if they changed since the last flush.
+pub fn flush_metrics() -> Result<(), String> {
+    with_metrics(|metrics| {
+        if metrics.dirty {
+            save_metrics(&metrics.buffer)?;
+            metrics.dirty = false;
+        }
+        Ok(())
+    })
+}
+
+pub fn get_summary() -> TelemetrySummary {
+    let settings = crate::settings::load_settings().unwrap_or_default();
+    let pending = with_metrics(|metrics| metrics.buffer.clone());
+    TelemetrySummary {
+        enabled: settings.telemetry_enabled,
+        endpoint: settings.analytics_endpoint,
+        total_events: pending.counts.values().sum(),
+        pending,
+    }
+}
+
+/// Delete everything buffered, e.g. after opting out.
+pub fn clear_metrics() {
+    *METRICS.lock().unwrap_or_else(|e| e.into_inner()) = Some(Metrics { buffer: MetricsBuffer::default(), dirty: false });
+    let _ = fs::remove_file(metrics_path());
+}
+
+async fn post_with_backoff(client: &reqwest::Client, endpoint: &str, body: &serde_json::Value) -> Result<(), String> {
+    let mut delay = std::time::Duration::from_millis(500);
+    let mut last_error = String::new();
+    for attempt in 1..=UPLOAD_ATTEMPTS {
+        match client.post(endpoint).json(body).send().await {
+            Ok(resp) if resp.status().is_success() => return Ok(()),
+            // Other 4xx won't succeed on retry
+            Ok(resp) if resp.status().is_client_error() && resp.status().as_u16() != 429 => {
+                return Err(format!("Telemetry endpoint rejected upload: {}", resp.status()));
+            }
+            Ok(resp) => last_error = format!("Telemetry endpoint returned {}", resp.status()),
+            Err(e) => last_error = e.to_string(),
+        }
+        if attempt < UPLOAD_ATTEMPTS {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+    Err(format!("Telemetry upload failed after {} attempts: {}", UPLOAD_ATTEMPTS, last_error))
+}
+
+/// Send buffered counts to the configured endpoint. Only runs when asked; the
+/// buffer is cleared once every batch has been accepted.
+pub async fn upload_metrics() -> Result<TelemetryUploadResult, String> {
+    let settings = crate::settings::load_settings()?;
+    if !settings.telemetry_enabled {
+        return Err("Telemetry is disabled".to_string());
+    }
+    let endpoint = settings.analytics_endpoint.ok_or("No analytics endpoint configured")?;
+    let buffer = with_metrics(|metrics| metrics.buffer.clone());
+    let counts: Vec<(&String, &u64)> = buffer.counts.iter().collect();
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(15))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut batches = 0;
+    for chunk in counts.chunks(UPLOAD_BATCH_SIZE) {
+        let body = serde_json::json!({
+            "app_version": env!("CARGO_PKG_VERSION"),
+            "os": std::env::consts::OS,
+            "period_start": buffer.period_start,
+            "period_end": buffer.period_end,
+            "counts": chunk.iter().map(|(kind, count)| ((*kind).clone(), **count)).collect::<BTreeMap<_, _>>(),
+        });
+        post_with_backoff(&client, &endpoint, &body).await?;
+        batches += 1;
+    }
+
+    // Keep anything recorded while the upload was in flight
+    with_metrics(|metrics| {
+        let current = &mut metrics.buffer;
+        for (kind, sent) in &buffer.counts {
+            if let Some(count) = current.counts.get_mut(kind) {
+                *count = count.saturating_sub(*sent);
+            }
+        }
+        current.counts.retain(|_, count| *count > 0);
+        if current.counts.is_empty() {
+            current.period_start = None;
+            current.period_end = None;
+        }
+        current.last_upload = Some(now_secs());
+        metrics.dirty = true;
+    });
+    flush_metrics()?;
+
+    Ok(TelemetryUploadResult { batches, events: buffer.counts.values().sum() })
+}
+
 pub fn install_panic_hook() {
     let path = telemetry_path();
     std::panic::set_hook(Box::new(move |info| {
@@ -40,5 +272,108 @@ pub fn install_panic_hook() {
             let line = serde_json::json!({"ts": ts, "kind":"panic","data":data}).to_string();
             use std::io::Write; writeln!(f, "{}", line)
         });
+        let message = match info.payload().downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => info.payload().downcast_ref::<String>().cloned().unwrap_or(msg),
+        };
+        let location = info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+        write_crash_report(&message, location);
     }));
 }
+
+fn scrub(value: serde_json::Value, redactor: &crate::security::SecurityManager) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(text) => serde_json::Value::String(redactor.mask_sensitive_data(&text)),
+        serde_json::Value::Array(items) => items.into_iter().map(|item| scrub(item, redactor)).collect(),
+        serde_json::Value::Object(map) => map
+            .into_iter()
+            .map(|(key, value)| {
+                let lower = key.to_lowercase();
+                let value = if OMITTED_KEYS.iter().any(|omitted| lower.contains(omitted)) {
+                    serde_json::Value::String("[omitted]".into())
+                } else {
+                    scrub(value, redactor)
+                };
+                (key, value)
+            })
+            .collect(),
+        other => other,
+    }
+}
+
+fn log_tail(redactor: &crate::security::SecurityManager) -> Vec<serde_json::Value> {
+    let data = fs::read_to_string(telemetry_path()).unwrap_or_default();
+    let lines: Vec<&str> = data.lines().collect();
+    lines[lines.len().saturating_sub(CRASH_LOG_TAIL_LINES)..]
+        .iter()
+        .map(|line| {
+            let value = serde_json::from_str(line).unwrap_or_else(|_| serde_json::Value::String(line.to_string()));
+            scrub(value, redactor)
+        })
+        .collect()
+}
+
+// Runs inside the panic hook, so every step is best effort
+fn write_crash_report(message: &str, location: Option<String>) {
+    let redactor = crate::security::SecurityManager::new();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let settings = crate::settings::load_settings()
+        .ok()
+        .and_then(|settings| serde_json::to_value(settings).ok())
+        .unwrap_or_default();
+
+    let report = CrashReport {
+        id: format!("crash-{}", now.as_millis()),
+        created_at: now.as_secs(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        message: redactor.mask_sensitive_data(message),
+        location,
+        backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+        log_tail: log_tail(&redactor),
+        settings: scrub(settings, &redactor),
+    };
+
+    let dir = crash_reports_dir();
+    if fs::create_dir_all(&dir).is_ok() {
+        if let Ok(json) = serde_json::to_string_pretty(&report) {
+            let _ = fs::write(dir.join(format!("{}.json", report.id)), json);
+        }
+    }
+}
+
+fn read_crash_report(id: &str) -> Result<CrashReport, String> {
+    // Ids become file names; refuse anything that could escape the directory
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid crash report id: {}", id));
+    }
+    let data = fs::read_to_string(crash_reports_dir().join(format!("{}.json", id)))
+        .map_err(|_| format!("Crash report {} not found", id))?;
+    serde_json::from_str(&data).map_err(|e| e.to_string())
+}
+
+/// Crash reports on disk, newest first.
+pub fn list_crash_reports() -> Vec<CrashReportSummary> {
+    let mut reports: Vec<CrashReportSummary> = fs::read_dir(crash_reports_dir())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let id = entry.path().file_stem()?.to_string_lossy().to_string();
+                    let report = read_crash_report(&id).ok()?;
+                    Some(CrashReportSummary { id: report.id, created_at: report.created_at, message: report.message })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    reports.sort_by_key(|report| std::cmp::Reverse(report.created_at));
+    reports
+}
+
+/// Copy a crash report to `destination` for attaching to a bug report.
+pub fn export_crash_report(id: &str, destination: &str) -> Result<(), String> {
+    let report = read_crash_report(id)?;
+    let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+    fs::write(destination, json).map_err(|e| format!("Failed to write {}: {}", destination, e))
+}
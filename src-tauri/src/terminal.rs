@@ -1,17 +1,38 @@
-use crate::ansi::{AnsiParser, AnsiCommand, CharAttributes, CursorPosition};
-use crate::pty::{PtyManager, TerminalSize, TerminalOutput};
-use crate::shell_hooks::ShellHooksManager;
-use crate::search::{SearchIndexManager, ScrollMatch, ContextLine};
+use crate::ansi::{AnsiParser, AnsiCommand, CharAttributes, CursorPosition, CursorStyle, MouseReportMode, PromptMark};
+use crate::pty::{GuardVerdict, LargeOutputAction, LargeOutputWarning, OutputGuard, OutputGuardLimits, OutputThrottle, PtyManager, ShellOptions, TerminalSize, TerminalOutput};
+use crate::shell_hooks::{ShellHooksManager, CommandEvent, IntegrationMark};
+use crate::search::{SearchIndexManager, ScrollMatch, ScrollGrepResult, GrepOptions, ContextLine};
 use crate::ai::AiContext;
+use crate::recording::{CastRecorder, RecordingSummary};
+use crate::settings::BellPolicy;
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+const MAX_COMMAND_BLOCKS: usize = 1000;
+// Commands running at least this long get a "took 12.3s" badge
+const DURATION_BADGE_MIN_MS: u64 = 2000;
+
+#[derive(Debug, Clone, Hash, Serialize, Deserialize)]
 pub struct TerminalChar {
     pub character: char,
     pub attributes: CharAttributes,
+    // Cells taken: 2 for wide (CJK, emoji) characters, 0 for the spacer cell
+    // that follows them
+    #[serde(default = "default_char_width")]
+    pub width: u8,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub combining: String, // the rest of the grapheme cluster: marks, modifiers, ZWJ sequences
+}
+
+fn default_char_width() -> u8 {
+    1
 }
 
 impl Default for TerminalChar {
@@ -19,6 +40,8 @@ impl Default for TerminalChar {
         TerminalChar {
             character: ' ',
             attributes: CharAttributes::default(),
+            width: 1,
+            combining: String::new(),
         }
     }
 }
@@ -29,6 +52,19 @@ pub struct TerminalGrid {
     pub cols: usize,
     pub cursor: CursorPosition,
     pub saved_cursor: Option<CursorPosition>,
+    pub scrollback_usage: ScrollbackUsage,
+    pub scroll_top: usize,    // scroll region margins (DECSTBM), 0-based and inclusive
+    pub scroll_bottom: usize,
+    #[serde(skip)]
+    pub tab_stops: Vec<bool>, // per column
+    // Damage tracking: each write bumps `damage_count` and stamps the row with
+    // it, so any reader can ask which rows changed since the count it last saw
+    #[serde(skip)]
+    damage: Vec<u64>,
+    #[serde(skip)]
+    damage_count: u64,
+    #[serde(skip)]
+    dirty_taken: u64, // damage count at the last `take_dirty_rows`
 }
 
 impl TerminalGrid {
@@ -42,13 +78,58 @@ impl TerminalGrid {
             cols,
             cursor: CursorPosition { row: 0, col: 0 },
             saved_cursor: None,
+            scrollback_usage: ScrollbackUsage::default(),
+            scroll_top: 0,
+            scroll_bottom: rows.saturating_sub(1),
+            tab_stops: (0..cols).map(default_tab_stop).collect(),
+            damage: vec![1; rows],
+            damage_count: 1,
+            dirty_taken: 0,
+        }
+    }
+
+    fn mark_dirty(&mut self, row: usize) {
+        self.damage_count += 1;
+        if let Some(damage) = self.damage.get_mut(row) {
+            *damage = self.damage_count;
+        }
+    }
+
+    fn mark_rows_dirty(&mut self, rows: std::ops::RangeInclusive<usize>) {
+        self.damage_count += 1;
+        let count = self.damage_count;
+        for damage in self.damage.iter_mut().take(rows.end() + 1).skip(*rows.start()) {
+            *damage = count;
         }
     }
 
+    pub fn mark_all_dirty(&mut self) {
+        self.mark_rows_dirty(0..=self.rows.len().saturating_sub(1));
+    }
+
+    pub fn damage_count(&self) -> u64 {
+        self.damage_count
+    }
+
+    /// Rows written since the damage count was `since`.
+    pub fn rows_damaged_since(&self, since: u64) -> impl Iterator<Item = usize> + '_ {
+        self.damage.iter().enumerate().filter(move |(_, &damage)| damage > since).map(|(row, _)| row)
+    }
+
+    /// Rows written since the last call, which clears them.
+    pub fn take_dirty_rows(&mut self) -> Vec<usize> {
+        let dirty = self.rows_damaged_since(self.dirty_taken).collect();
+        self.dirty_taken = self.damage_count;
+        dirty
+    }
+
     pub fn resize(&mut self, new_cols: usize, new_rows: usize) {
-        // Resize existing rows
+        // Resize existing rows, blanking a wide character the new edge cuts in half
         for row in &mut self.rows {
             row.resize(new_cols, TerminalChar::default());
+            if row.last().is_some_and(|cell| cell.width == 2) {
+                row[new_cols - 1] = TerminalChar::default();
+            }
         }
 
         // Add or remove rows
@@ -60,7 +141,15 @@ impl TerminalGrid {
             self.rows.truncate(new_rows);
         }
 
+        // Stops the program set survive; new columns get the defaults
+        let kept = self.tab_stops.len().min(new_cols);
+        self.tab_stops.truncate(kept);
+        self.tab_stops.extend((kept..new_cols).map(default_tab_stop));
         self.cols = new_cols;
+        self.scroll_top = 0;
+        self.scroll_bottom = new_rows.saturating_sub(1);
+        self.damage.resize(new_rows, 0);
+        self.mark_all_dirty();
 
         // Ensure cursor is within bounds
         self.cursor.row = self.cursor.row.min(new_rows as u16 - 1);
@@ -71,26 +160,273 @@ impl TerminalGrid {
         if self.cursor.row as usize >= self.rows.len() {
             return;
         }
+        // No printable ASCII character continues a cluster, so plain text skips the check
+        if !ch.is_ascii() && self.extend_cluster(ch) {
+            return;
+        }
+        let width = match ch.width() {
+            Some(width) if width > 0 && width <= self.cols => width,
+            _ => return, // control and stray zero-width characters, or wider than the screen
+        };
+
+        // A wide character doesn't fit in the last column; it wraps whole
+        if self.cursor.col as usize + width > self.cols {
+            self.cursor.col = 0;
+            self.line_feed();
+        }
 
-        let row = &mut self.rows[self.cursor.row as usize];
-        if (self.cursor.col as usize) < row.len() {
-            row[self.cursor.col as usize] = TerminalChar {
-                character: ch,
+        let row = self.cursor.row as usize;
+        let col = self.cursor.col as usize;
+        for cell in col..col + width {
+            self.break_wide_char(row, cell);
+        }
+        self.mark_dirty(row);
+        let cells = &mut self.rows[row];
+        cells[col] = TerminalChar {
+            character: ch,
+            attributes: attributes.clone(),
+            width: width as u8,
+            combining: String::new(),
+        };
+        if width == 2 {
+            cells[col + 1] = TerminalChar {
+                character: ' ',
                 attributes: attributes.clone(),
+                width: 0,
+                combining: String::new(),
             };
-            self.cursor.col += 1;
+        }
+        self.cursor.col += width as u16;
+
+        // Wrap to next line if needed
+        if self.cursor.col as usize >= self.cols {
+            self.cursor.col = 0;
+            self.line_feed();
+        }
+    }
+
+    // The cell holding the cluster written just before the cursor, which is on
+    // the previous row right after a wrap
+    fn previous_cell(&self) -> Option<(usize, usize)> {
+        let (mut row, mut col) = (self.cursor.row as usize, self.cursor.col as usize);
+        if col == 0 {
+            row = row.checked_sub(1)?;
+            col = self.cols;
+        }
+        col -= 1;
+        if self.rows[row][col].width == 0 && col > 0 {
+            col -= 1;
+        }
+        Some((row, col))
+    }
+
+    // Join `ch` to the previous cell when they form one grapheme cluster
+    // (combining marks, skin-tone modifiers, ZWJ sequences, flag pairs). A
+    // cluster that becomes wide, like a flag, takes the next cell as its spacer.
+    fn extend_cluster(&mut self, ch: char) -> bool {
+        let Some((row, col)) = self.previous_cell() else {
+            return false;
+        };
+        let cell = &self.rows[row][col];
+        let mut cluster = String::from(cell.character);
+        cluster.push_str(&cell.combining);
+        cluster.push(ch);
+        if cluster.graphemes(true).count() != 1 {
+            return false;
+        }
 
-            // Wrap to next line if needed
+        let widened = cell.width == 1 && cluster.width() >= 2;
+        self.mark_dirty(row);
+        let cell = &mut self.rows[row][col];
+        cell.combining.push(ch);
+        let cursor_follows = row == self.cursor.row as usize && col + 1 == self.cursor.col as usize;
+        if widened && cursor_follows && col + 1 < self.cols {
+            self.break_wide_char(row, col + 1);
+            let attributes = self.rows[row][col].attributes.clone();
+            self.rows[row][col].width = 2;
+            self.rows[row][col + 1] = TerminalChar { character: ' ', attributes, width: 0, combining: String::new() };
+            self.cursor.col += 1;
             if self.cursor.col as usize >= self.cols {
                 self.cursor.col = 0;
-                if (self.cursor.row as usize) < self.rows.len() - 1 {
-                    self.cursor.row += 1;
-                } else {
-                    // Scroll up
-                    self.scroll_up(1);
+                self.line_feed();
+            }
+        }
+        true
+    }
+
+    // Overwriting either half of a wide character blanks the other half
+    fn break_wide_char(&mut self, row: usize, col: usize) {
+        let cells = &mut self.rows[row];
+        match cells[col].width {
+            0 if col > 0 => cells[col - 1] = TerminalChar::default(),
+            2 if col + 1 < cells.len() => cells[col + 1] = TerminalChar::default(),
+            _ => {}
+        }
+    }
+
+    // Cells `first..=last` of a row as text. A wide character counts once if
+    // either of its cells is covered, and keeps its combining marks.
+    fn row_text(&self, row: usize, first: usize, last: usize) -> String {
+        let cells = &self.rows[row];
+        let last = last.min(cells.len().saturating_sub(1));
+        // Starting on a spacer takes in the wide character it belongs to
+        let first = if first > 0 && cells.get(first).is_some_and(|cell| cell.width == 0) { first - 1 } else { first };
+        let mut line = String::new();
+        for cell in cells.iter().take(last + 1).skip(first).filter(|cell| cell.width > 0) {
+            line.push(cell.character);
+            line.push_str(&cell.combining);
+        }
+        line.trim_end().to_string()
+    }
+
+    // A row as text, with the byte offset and first/last cell of each cluster
+    fn row_text_with_offsets(&self, row: usize) -> (String, Vec<(usize, usize, usize)>) {
+        let mut text = String::new();
+        let mut offsets = Vec::new();
+        for (first, last, cluster) in self.row_clusters(row) {
+            offsets.push((text.len(), first, last));
+            text.push_str(&cluster);
+        }
+        (text, offsets)
+    }
+
+    /// URLs and file references (`src/foo.rs:42:10`) on a row. File references
+    /// come from `file_pattern`, which needs a `path` group and may have `line`
+    /// and `column` groups; ones overlapping a URL are left out.
+    pub fn clickable_regions(&self, row: usize, file_pattern: &regex::Regex) -> Vec<Clickable> {
+        let (text, offsets) = self.row_text_with_offsets(row);
+        let cells = |start: usize, end: usize| {
+            let first = offsets.iter().find(|(offset, _, _)| *offset >= start)?.1;
+            let last = offsets.iter().rev().find(|(offset, _, _)| *offset < end)?.2;
+            Some((first, last))
+        };
+        let mut regions = Vec::new();
+        let mut url_spans = Vec::new();
+        if let Ok(url_regex) = regex::Regex::new(URL_PATTERN) {
+            for found in url_regex.find_iter(&text) {
+                let end = trim_url_end(&text, found.start(), found.end());
+                let Some((start_col, end_col)) = cells(found.start(), end) else { continue };
+                let url = &text[found.start()..end];
+                let url = if url.starts_with("www.") { format!("https://{}", url) } else { url.to_string() };
+                url_spans.push(found.start()..end);
+                regions.push(Clickable { row, start_col, end_col, text: text[found.start()..end].to_string(), target: ClickTarget::Url { url } });
+            }
+        }
+        for captures in file_pattern.captures_iter(&text) {
+            let (Some(whole), Some(path)) = (captures.get(0), captures.name("path")) else { continue };
+            if url_spans.iter().any(|span| span.start < whole.end() && whole.start() < span.end) {
+                continue;
+            }
+            let Some((start_col, end_col)) = cells(whole.start(), whole.end()) else { continue };
+            let number = |name: &str| captures.name(name).and_then(|m| m.as_str().parse().ok());
+            regions.push(Clickable {
+                row,
+                start_col,
+                end_col,
+                text: whole.as_str().to_string(),
+                target: ClickTarget::File { path: path.as_str().to_string(), line: number("line"), column: number("column") },
+            });
+        }
+        regions.sort_by_key(|region| region.start_col);
+        regions
+    }
+
+    // Each cluster on a row with its first and last cell, spacers skipped
+    fn row_clusters(&self, row: usize) -> Vec<(usize, usize, String)> {
+        self.rows.get(row).map_or_else(Vec::new, |cells| {
+            cells.iter()
+                .enumerate()
+                .filter(|(_, cell)| cell.width > 0)
+                .map(|(col, cell)| {
+                    let last = (col + cell.width as usize - 1).min(cells.len() - 1);
+                    (col, last, format!("{}{}", cell.character, cell.combining))
+                })
+                .collect()
+        })
+    }
+
+    /// First and last cell of the word under `col`: letters, digits and
+    /// `word_chars` group together, as do runs of blanks; anything else is
+    /// selected on its own.
+    pub fn word_range_at(&self, row: usize, col: usize, word_chars: &str) -> Option<(usize, usize)> {
+        let clusters = self.row_clusters(row);
+        let index = clusters.iter().position(|(first, last, _)| (*first..=*last).contains(&col))?;
+        let class = |text: &str| {
+            let ch = text.chars().next().unwrap_or(' ');
+            if ch.is_alphanumeric() || word_chars.contains(ch) { 1 } else if ch.is_whitespace() { 2 } else { 0 }
+        };
+        let target = class(&clusters[index].2);
+        if target == 0 {
+            return Some((clusters[index].0, clusters[index].1));
+        }
+        let mut start = index;
+        while start > 0 && class(&clusters[start - 1].2) == target {
+            start -= 1;
+        }
+        let mut end = index;
+        while end + 1 < clusters.len() && class(&clusters[end + 1].2) == target {
+            end += 1;
+        }
+        Some((clusters[start].0, clusters[end].1))
+    }
+
+    /// First and last cell of the URL, quoted string (without its quotes) or
+    /// file path under `col`, tried in that order; falls back to the word.
+    pub fn semantic_range_at(&self, row: usize, col: usize, word_chars: &str) -> Option<((usize, usize), SemanticKind)> {
+        let (text, offsets) = self.row_text_with_offsets(row);
+        let offset = offsets.iter().find(|(_, first, last)| (*first..=*last).contains(&col))?.0;
+
+        let patterns = [
+            (SemanticKind::Url, URL_PATTERN),
+            (SemanticKind::Quoted, r#""[^"]+"|'[^']+'|`[^`]+`"#),
+            (SemanticKind::Path, r"(?:[A-Za-z]:\\|~|\.{1,2})?[\\/]?[\w.@+~-]+(?:[\\/][\w.@+~-]+)+[\\/]?(?::\d+){0,2}|(?:~|\.{1,2})?/[\w.@+~-]+(?::\d+){0,2}"),
+        ];
+        for (kind, pattern) in patterns {
+            let Ok(regex) = regex::Regex::new(pattern) else { continue };
+            let Some(found) = regex.find_iter(&text).find(|m| m.start() <= offset && offset < m.end()) else { continue };
+            let (mut start, mut end) = (found.start(), found.end());
+            match kind {
+                SemanticKind::Quoted => {
+                    start += 1;
+                    end -= 1;
                 }
+                SemanticKind::Url => end = trim_url_end(&text, start, end),
+                _ => {}
             }
+            if start >= end || offset < start || offset >= end {
+                continue;
+            }
+            let first = offsets.iter().find(|(o, _, _)| *o >= start)?.1;
+            let last = offsets.iter().rev().find(|(o, _, _)| *o < end)?.2;
+            return Some(((first, last), kind));
         }
+        self.word_range_at(row, col, word_chars).map(|range| (range, SemanticKind::Word))
+    }
+
+    /// Text between two cell positions (inclusive, row-major), as a linear
+    /// selection copies it; rows are joined with newlines and lose trailing blanks.
+    pub fn linear_text(&self, start: &CursorPosition, end: &CursorPosition) -> String {
+        let (start, end) = if (start.row, start.col) <= (end.row, end.col) { (start, end) } else { (end, start) };
+        let last_row = (end.row as usize).min(self.rows.len().saturating_sub(1));
+        (start.row as usize..=last_row)
+            .map(|row| {
+                let first = if row == start.row as usize { start.col as usize } else { 0 };
+                let last = if row == end.row as usize { end.col as usize } else { self.cols };
+                self.row_text(row, first, last)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The rectangle with corners `a` and `b`, one line per row. Wide
+    /// characters straddling either edge are included whole, as in `linear_text`.
+    pub fn block_text(&self, a: &CursorPosition, b: &CursorPosition) -> String {
+        let (left, right) = (a.col.min(b.col) as usize, a.col.max(b.col) as usize);
+        let last_row = (a.row.max(b.row) as usize).min(self.rows.len().saturating_sub(1));
+        (a.row.min(b.row) as usize..=last_row)
+            .map(|row| self.row_text(row, left, right))
+            .collect::<Vec<_>>()
+            .join("\n")
     }
 
     pub fn move_cursor(&mut self, row: u16, col: u16) {
@@ -102,10 +438,23 @@ impl TerminalGrid {
         let new_row = (self.cursor.row as i16 + delta_row)
             .max(0)
             .min(self.rows.len() as i16 - 1) as u16;
-        let new_col = (self.cursor.col as i16 + delta_col)
+        let mut new_col = (self.cursor.col as i16 + delta_col)
             .max(0)
             .min(self.cols as i16 - 1) as u16;
-        
+
+        // Never stop inside a wide cluster: backspacing over one lands on its
+        // first cell, moving right over one skips it whole
+        let on_spacer = self.rows.get(new_row as usize)
+            .and_then(|cells| cells.get(new_col as usize))
+            .is_some_and(|cell| cell.width == 0);
+        if on_spacer && new_col > 0 {
+            if delta_col < 0 || (new_col + 1) as usize >= self.cols {
+                new_col -= 1;
+            } else {
+                new_col += 1;
+            }
+        }
+
         self.cursor.row = new_row;
         self.cursor.col = new_col;
     }
@@ -116,6 +465,7 @@ impl TerminalGrid {
                 *cell = TerminalChar::default();
             }
         }
+        self.mark_all_dirty();
         self.cursor = CursorPosition { row: 0, col: 0 };
     }
 
@@ -125,266 +475,2445 @@ impl TerminalGrid {
                 *cell = TerminalChar::default();
             }
         }
+        self.mark_dirty(self.cursor.row as usize);
     }
 
-    pub fn scroll_up(&mut self, lines: usize) {
-        if lines >= self.rows.len() {
-            self.clear_screen();
+    /// EL 0: the cursor cell through the end of its row. Shells echo a
+    /// backspace as one, so it must leave the prompt before the cursor alone.
+    pub fn clear_to_end_of_line(&mut self) {
+        let col = (self.cursor.col as usize).min(self.cols.saturating_sub(1));
+        self.clear_cells(col, self.cols.saturating_sub(1));
+    }
+
+    /// EL 1: the start of the cursor's row through the cursor cell.
+    pub fn clear_to_beginning_of_line(&mut self) {
+        self.clear_cells(0, self.cursor.col as usize);
+    }
+
+    // Blank cells `first..=last` of the cursor row, along with the outside
+    // half of a wide character the range cuts through
+    fn clear_cells(&mut self, first: usize, last: usize) {
+        let row = self.cursor.row as usize;
+        if row >= self.rows.len() || self.cols == 0 {
             return;
         }
-
-        // Remove lines from the top
-        for _ in 0..lines {
-            self.rows.remove(0);
-            // Add empty line at the bottom
-            self.rows.push(vec![TerminalChar::default(); self.cols]);
+        let last = last.min(self.cols - 1);
+        self.break_wide_char(row, first);
+        self.break_wide_char(row, last);
+        for cell in &mut self.rows[row][first..=last] {
+            *cell = TerminalChar::default();
         }
+        self.mark_dirty(row);
     }
-}
-
-#[derive(Debug)]
-pub struct Terminal {
-    pub id: String,
-    pub grid: TerminalGrid,
-    pub parser: AnsiParser,
-    pub size: TerminalSize,
-}
-
-impl Terminal {
-    pub fn new(id: String, size: TerminalSize) -> Self {
-        let grid = TerminalGrid::new(size.cols as usize, size.rows as usize);
-        let parser = AnsiParser::new();
 
-        Terminal {
-            id,
-            grid,
-            parser,
-            size,
+    /// Advance to the `count`-th next tab stop, or the last column when there
+    /// are no more stops.
+    pub fn tab(&mut self, count: usize) {
+        let last = self.cols.saturating_sub(1);
+        let mut col = self.cursor.col as usize;
+        for _ in 0..count {
+            col = (col + 1..self.cols).find(|&c| self.tab_stops[c]).unwrap_or(last);
         }
+        self.cursor.col = col as u16;
     }
 
-    pub fn process_output(&mut self, data: &str) {
-        let commands = self.parser.parse(data);
-        
-        for command in commands {
-            self.execute_command(command);
+    pub fn set_tab_stop(&mut self) {
+        if let Some(stop) = self.tab_stops.get_mut(self.cursor.col as usize) {
+            *stop = true;
         }
     }
 
-    fn execute_command(&mut self, command: AnsiCommand) {
-        match command {
-            AnsiCommand::PrintText(text) => {
-                for ch in text.chars() {
-                    self.grid.write_char(ch, self.parser.current_attributes());
-                }
-            }
-            AnsiCommand::CursorUp(n) => {
-                self.grid.move_cursor_relative(-(n as i16), 0);
-            }
-            AnsiCommand::CursorDown(n) => {
-                self.grid.move_cursor_relative(n as i16, 0);
-            }
-            AnsiCommand::CursorLeft(n) => {
-                self.grid.move_cursor_relative(0, -(n as i16));
-            }
-            AnsiCommand::CursorRight(n) => {
-                self.grid.move_cursor_relative(0, n as i16);
-            }
-            AnsiCommand::CursorPosition(row, col) => {
-                self.grid.move_cursor(row.saturating_sub(1), col.saturating_sub(1));
-            }
-            AnsiCommand::CursorHome => {
-                self.grid.move_cursor(0, 0);
-            }
-            AnsiCommand::ClearScreen => {
-                self.grid.clear_screen();
-            }
-            AnsiCommand::ClearLine => {
-                self.grid.clear_line();
-            }
-            AnsiCommand::ClearToEndOfLine => {
-                // TODO: Implement partial line clearing
-                self.grid.clear_line();
-            }
-            AnsiCommand::ClearToBeginningOfLine => {
-                // TODO: Implement partial line clearing
-                self.grid.clear_line();
-            }
-            AnsiCommand::ScrollUp(n) => {
-                self.grid.scroll_up(n as usize);
-            }
-            AnsiCommand::ScrollDown(_n) => {
-                // TODO: Implement scroll down
-            }
-            AnsiCommand::SetGraphicsMode(params) => {
-                self.parser.apply_graphics_mode(&params);
-            }
-            AnsiCommand::Bell => {
-                // TODO: Handle bell (audio/visual notification)
-                log::info!("Terminal bell");
-            }
-            AnsiCommand::Unknown(seq) => {
-                log::warn!("Unknown escape sequence: {}", seq);
-            },
-            _ => {
-                // Handle other ANSI commands
-            }
+    pub fn clear_tab_stop(&mut self, all: bool) {
+        if all {
+            self.tab_stops.fill(false);
+        } else if let Some(stop) = self.tab_stops.get_mut(self.cursor.col as usize) {
+            *stop = false;
         }
     }
 
-    pub fn resize(&mut self, new_size: TerminalSize) {
-        self.size = new_size.clone();
-        self.grid.resize(new_size.cols as usize, new_size.rows as usize);
+    pub fn reset_tab_stops(&mut self) {
+        for (col, stop) in self.tab_stops.iter_mut().enumerate() {
+            *stop = default_tab_stop(col);
+        }
     }
-}
-
-pub struct TerminalManager {
-    terminals: Arc<Mutex<HashMap<String, Terminal>>>,
-    pty_manager: Arc<Mutex<PtyManager>>,
-    shell_hooks: Arc<Mutex<ShellHooksManager>>,
-    search_index: Arc<Mutex<SearchIndexManager>>,
-}
 
-impl TerminalManager {
-    pub fn gather_context(&self, terminal_id: &str) -> Option<AiContext> {
-        let (working_dir, prompt) = if let Some(p) = self.get_current_prompt(terminal_id) {
-            (Some(p.working_dir.clone()), Some(p.prompt_text.clone()))
-        } else { (None, None) };
-        let recent_commands = self
-            .get_command_history(terminal_id, Some(20))
-            .unwrap_or_default()
-            .into_iter()
-            .map(|c| c.text)
-            .collect::<Vec<_>>();
-        let tail_output = self
-            .search_index
-            .lock()
-            .unwrap()
-            .tail(terminal_id, 200)
-            .unwrap_or_default();
-        Some(AiContext { working_dir, prompt, recent_commands, tail_output })
+    /// Set the scroll region from 1-based margins, where 0 means the screen
+    /// edge. Invalid regions are ignored; a valid one homes the cursor.
+    pub fn set_scroll_region(&mut self, top: u16, bottom: u16) {
+        let last = self.rows.len().saturating_sub(1);
+        let top = (top as usize).saturating_sub(1);
+        let bottom = if bottom == 0 { last } else { (bottom as usize - 1).min(last) };
+        if top >= bottom {
+            return;
+        }
+        self.scroll_top = top;
+        self.scroll_bottom = bottom;
+        self.cursor = CursorPosition { row: 0, col: 0 };
     }
-    pub fn new() -> (Self, mpsc::UnboundedReceiver<TerminalOutput>) {
-        let (pty_manager, output_receiver) = PtyManager::new();
-        
-        let manager = TerminalManager {
-            terminals: Arc::new(Mutex::new(HashMap::new())),
-            pty_manager: Arc::new(Mutex::new(pty_manager)),
-            shell_hooks: Arc::new(Mutex::new(ShellHooksManager::new())),
-            search_index: Arc::new(Mutex::new(SearchIndexManager::new())),
-        };
 
-        (manager, output_receiver)
+    /// Margins back to the full screen. Unlike DECSTBM this leaves the cursor
+    /// where it is, as DECSTR does.
+    pub fn reset_scroll_region(&mut self) {
+        self.scroll_top = 0;
+        self.scroll_bottom = self.rows.len().saturating_sub(1);
     }
 
-    pub fn create_terminal(
-        &self,
-        size: TerminalSize,
-        shell: Option<String>,
-        working_dir: Option<String>,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        let shell_path = shell.clone().unwrap_or_else(|| {
-            if cfg!(windows) {
-                std::env::var("SHELL").unwrap_or_else(|_| "powershell.exe".to_string())
-            } else {
-                std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string())
-            }
-        });
+    fn in_scroll_region(&self) -> bool {
+        (self.scroll_top..=self.scroll_bottom).contains(&(self.cursor.row as usize))
+    }
 
-        let work_dir = working_dir.clone().unwrap_or_else(|| {
-            std::env::current_dir()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string()
-        });
+    /// Move down a line; at the bottom margin the region scrolls instead.
+    pub fn line_feed(&mut self) {
+        let row = self.cursor.row as usize;
+        if row == self.scroll_bottom {
+            self.scroll_up(1);
+        } else if row + 1 < self.rows.len() {
+            self.cursor.row += 1;
+        }
+    }
 
-        let session_id = self.pty_manager
-            .lock()
-            .unwrap()
-            .create_session(size.clone(), shell, working_dir)?;
+    /// Move up a line; at the top margin the region scrolls down instead.
+    pub fn reverse_index(&mut self) {
+        let row = self.cursor.row as usize;
+        if row == self.scroll_top {
+            self.scroll_down(1);
+        } else if row > 0 {
+            self.cursor.row -= 1;
+        }
+    }
 
-        // Initialize shell hooks for this session
-        self.shell_hooks
-            .lock()
-            .unwrap()
-            .create_session_hooks(session_id.clone(), &shell_path, work_dir);
-        // Initialize search index
-        self.search_index.lock().unwrap().create_session(session_id.clone());
+    // Remove `lines` rows at `at` and open as many blank rows at `insert_at`,
+    // both within the scroll region, so rows outside it never move.
+    fn shift_rows(&mut self, at: usize, insert_at: usize, lines: usize) {
+        for _ in 0..lines {
+            self.rows.remove(at);
+            self.rows.insert(insert_at, vec![TerminalChar::default(); self.cols]);
+        }
+        if lines > 0 {
+            self.mark_rows_dirty(at.min(insert_at)..=at.max(insert_at));
+        }
+    }
 
-        let terminal = Terminal::new(session_id.clone(), size);
-        self.terminals
-            .lock()
-            .unwrap()
-            .insert(session_id.clone(), terminal);
+    /// Scroll the region's content up, blank rows entering at the bottom margin.
+    pub fn scroll_up(&mut self, lines: usize) {
+        let height = self.scroll_bottom + 1 - self.scroll_top;
+        self.shift_rows(self.scroll_top, self.scroll_bottom, lines.min(height));
+    }
 
-        Ok(session_id)
+    /// Scroll the region's content down, blank rows entering at the top margin.
+    pub fn scroll_down(&mut self, lines: usize) {
+        let height = self.scroll_bottom + 1 - self.scroll_top;
+        self.shift_rows(self.scroll_bottom, self.scroll_top, lines.min(height));
     }
 
-    pub fn write_to_terminal(
-        &self,
-        terminal_id: &str,
-        data: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        self.pty_manager
-            .lock()
-            .unwrap()
-            .write_to_session(terminal_id, data)
+    /// Insert blank lines at the cursor, pushing the rows below it toward the
+    /// bottom margin. Does nothing with the cursor outside the region.
+    pub fn insert_lines(&mut self, lines: usize) {
+        if !self.in_scroll_region() {
+            return;
+        }
+        let row = self.cursor.row as usize;
+        self.shift_rows(self.scroll_bottom, row, lines.min(self.scroll_bottom + 1 - row));
+        self.cursor.col = 0;
     }
 
-    pub fn resize_terminal(
-        &self,
-        terminal_id: &str,
-        size: TerminalSize,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(terminal) = self.terminals.lock().unwrap().get_mut(terminal_id) {
-            terminal.resize(size.clone());
+    /// Delete lines at the cursor, pulling the rows below it up and opening
+    /// blank lines at the bottom margin. Does nothing outside the region.
+    pub fn delete_lines(&mut self, lines: usize) {
+        if !self.in_scroll_region() {
+            return;
         }
+        let row = self.cursor.row as usize;
+        self.shift_rows(row, self.scroll_bottom, lines.min(self.scroll_bottom + 1 - row));
+        self.cursor.col = 0;
+    }
+}
 
+fn default_tab_stop(col: usize) -> bool {
+    col > 0 && col.is_multiple_of(8)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockPhase {
+    Prompt,
+    Command,
+    Output,
+    Finished,
+}
+
+// One prompt/command/output cycle delimited by OSC 133 marks. Line fields are
+// absolute scrollback indices, so they stay valid as older lines are evicted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandBlock {
+    pub id: String,
+    pub terminal_id: String,
+    pub phase: BlockPhase,
+    pub prompt: String,
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub prompt_started_at: u64,
+    pub command_started_at: Option<u64>,
+    pub output_started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    pub prompt_line: u64,
+    pub output_start_line: Option<u64>,
+    pub output_end_line: Option<u64>, // exclusive
+    pub output_lines: usize,
+    pub truncated: bool, // the start of the output was evicted from scrollback
+    pub folded: bool,
+    // Wall-clock time from the command starting to run until it finished, or
+    // until now while it's still running
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    #[serde(default)]
+    pub duration_badge: Option<String>, // "took 12.3s", for commands over the badge threshold
+}
+
+impl CommandBlock {
+    // C (output start) marks the moment the command was run; without it, fall
+    // back to B, which also counts the time spent typing. Before C or D the
+    // command is still being typed, not run.
+    fn execution_started_at(&self) -> Option<u64> {
+        match self.phase {
+            BlockPhase::Prompt | BlockPhase::Command => None,
+            BlockPhase::Output | BlockPhase::Finished => self.output_started_at.or(self.command_started_at),
+        }
+    }
+
+    fn update_duration(&mut self, now: u64) {
+        let Some(started) = self.execution_started_at() else {
+            return;
+        };
+        let duration_ms = self.finished_at.unwrap_or(now).saturating_sub(started);
+        self.duration_ms = Some(duration_ms);
+        self.duration_badge = (duration_ms >= DURATION_BADGE_MIN_MS).then(|| format!("took {}", format_duration(duration_ms)));
+    }
+}
+
+/// Short human duration: "4.2s", "3m 12s", "1h 5m".
+pub fn format_duration(ms: u64) -> String {
+    let secs = ms / 1000;
+    match secs {
+        0..=59 => format!("{:.1}s", ms as f64 / 1000.0),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RerunStatus {
+    Sent,
+    Queued, // a command is running; it goes out at the next prompt
+}
+
+#[derive(Debug, Clone)]
+struct QueuedRerun {
+    command: String,
+    execute: bool,
+}
+
+/// How long the most recent command took, or has been running so far.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandDuration {
+    pub block_id: String,
+    pub command: String,
+    pub duration_ms: u64,
+    pub running: bool,
+    pub badge: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VisibleLineKind {
+    Text,
+    Output,
+    Image,
+    FoldSummary,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisibleLine {
+    pub kind: VisibleLineKind,
+    pub line_index: u64,
+    pub block_id: Option<String>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AccessibleRegionKind {
+    Prompt,
+    Command,
+    Output,
+}
+
+// Line fields index into `AccessibleSnapshot::lines`; `end_line` is exclusive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessibleRegion {
+    pub kind: AccessibleRegionKind,
+    pub block_id: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: Option<String>, // prompt or command text; output is read from `lines`
+    pub exit_code: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessibleSnapshot {
+    pub terminal_id: String,
+    pub lines: Vec<String>,
+    pub cursor: CursorPosition,
+    pub regions: Vec<AccessibleRegion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HyperlinkSpan {
+    pub line_index: u64,
+    pub url: String,
+}
+
+/// Characters `start..start + len` of a scrollback line (in chars) drawn with
+/// non-default attributes. Unstyled text has no runs.
+#[derive(Debug, Clone)]
+pub struct StyleRun {
+    pub start: usize,
+    pub len: usize,
+    pub attributes: CharAttributes,
+}
+
+/// Text drawn with one set of attributes; `None` means the defaults.
+#[derive(Debug, Clone, Serialize)]
+pub struct TextRun {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<CharAttributes>,
+}
+
+/// A scrollback line in render-ready form, for drawing only the rows in view.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScrollbackRow {
+    pub line_index: u64,
+    pub runs: Vec<TextRun>, // an image is a single run describing it
+    pub image: bool,
+}
+
+// Split a line into runs, filling the gaps between its style runs with
+// default-attribute text
+fn text_runs(text: &str, styles: &[StyleRun]) -> Vec<TextRun> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut runs = Vec::new();
+    let mut at = 0;
+    for style in styles {
+        let start = style.start.min(chars.len());
+        let end = (style.start + style.len).min(chars.len());
+        if start > at {
+            runs.push(TextRun { text: chars[at..start].iter().collect(), attributes: None });
+        }
+        if end > start {
+            runs.push(TextRun { text: chars[start..end].iter().collect(), attributes: Some(style.attributes.clone()) });
+        }
+        at = at.max(end);
+    }
+    if at < chars.len() {
+        runs.push(TextRun { text: chars[at..].iter().collect(), attributes: None });
+    }
+    runs
+}
+
+#[derive(Debug, Clone)]
+pub enum ScrollbackEntry {
+    Line(String, Vec<StyleRun>),
+    Image {
+        format: String,
+        decoded_bytes: usize,
+        #[allow(dead_code)]
+        data: Vec<u8>, // the encoded image, for redrawing it when scrolled back into view
+        width: Option<u32>,
+        height: Option<u32>,
+    },
+}
+
+impl ScrollbackEntry {
+    fn cost(&self) -> usize {
+        match self {
+            ScrollbackEntry::Line(text, runs) => text.len() + runs.len() * std::mem::size_of::<StyleRun>(),
+            ScrollbackEntry::Image { decoded_bytes, .. } => *decoded_bytes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScrollbackUsage {
+    pub lines: usize,
+    pub bytes: usize,
+    pub image_bytes: usize,
+    pub max_lines: usize,
+    pub max_bytes: usize,
+    pub evicted_lines: u64,
+}
+
+pub const DEFAULT_SCROLLBACK_LINES: usize = 10_000;
+pub const DEFAULT_SCROLLBACK_BYTES: usize = 32 * 1024 * 1024;
+
+// Text and images in arrival order, capped by entry count and total bytes.
+// Images count by decoded size since that's what the frontend keeps around.
+#[derive(Debug)]
+pub struct Scrollback {
+    entries: std::collections::VecDeque<ScrollbackEntry>,
+    first_index: u64, // absolute index of entries[0]
+    current_line: String,
+    current_runs: Vec<StyleRun>,
+    current_chars: usize,
+    bytes: usize,
+    image_bytes: usize,
+    max_lines: usize,
+    max_bytes: usize,
+}
+
+impl Scrollback {
+    pub fn new(max_lines: usize, max_bytes: usize) -> Self {
+        Scrollback {
+            entries: std::collections::VecDeque::new(),
+            first_index: 0,
+            current_line: String::new(),
+            current_runs: Vec::new(),
+            current_chars: 0,
+            bytes: 0,
+            image_bytes: 0,
+            max_lines,
+            max_bytes,
+        }
+    }
+
+    /// Absolute index the line currently being written will get.
+    pub fn next_index(&self) -> u64 {
+        self.first_index + self.entries.len() as u64
+    }
+
+    pub fn first_index(&self) -> u64 {
+        self.first_index
+    }
+
+    fn push_text(&mut self, text: &str, attributes: &CharAttributes) {
+        let len = text.chars().count();
+        if *attributes != CharAttributes::default() {
+            match self.current_runs.last_mut() {
+                Some(run) if run.start + run.len == self.current_chars && run.attributes == *attributes => run.len += len,
+                _ => self.current_runs.push(StyleRun { start: self.current_chars, len, attributes: attributes.clone() }),
+            }
+        }
+        self.current_line.push_str(text);
+        self.current_chars += len;
+    }
+
+    fn push_entry(&mut self, entry: ScrollbackEntry) {
+        self.bytes += entry.cost();
+        if let ScrollbackEntry::Image { decoded_bytes, .. } = &entry {
+            self.image_bytes += decoded_bytes;
+        }
+        self.entries.push_back(entry);
+    }
+
+    fn end_line(&mut self) {
+        let line = std::mem::take(&mut self.current_line);
+        let runs = std::mem::take(&mut self.current_runs);
+        self.current_chars = 0;
+        self.push_entry(ScrollbackEntry::Line(line, runs));
+    }
+
+    fn push_image(&mut self, format: &str, decoded_bytes: usize, data: Vec<u8>, dimensions: Option<(u32, u32)>) {
+        if !self.current_line.is_empty() {
+            self.end_line();
+        }
+        self.push_entry(ScrollbackEntry::Image {
+            format: format.to_string(),
+            decoded_bytes,
+            data,
+            width: dimensions.map(|(w, _)| w),
+            height: dimensions.map(|(_, h)| h),
+        });
+    }
+
+    /// Drops the oldest entries until both limits hold. Returns true if anything was evicted.
+    fn enforce_limits(&mut self) -> bool {
+        let mut evicted = false;
+        while self.entries.len() > self.max_lines || (self.bytes > self.max_bytes && !self.entries.is_empty()) {
+            if let Some(entry) = self.entries.pop_front() {
+                self.bytes -= entry.cost();
+                if let ScrollbackEntry::Image { decoded_bytes, .. } = entry {
+                    self.image_bytes -= decoded_bytes;
+                }
+                self.first_index += 1;
+                evicted = true;
+            }
+        }
+        evicted
+    }
+
+    /// Entries in `[start, end)` that are still retained, with their absolute index.
+    pub fn range(&self, start: u64, end: u64) -> impl Iterator<Item = (u64, &ScrollbackEntry)> {
+        let first = self.first_index;
+        let from = start.max(first);
+        let to = end.min(self.next_index()).max(from);
+        self.entries
+            .range((from - first) as usize..(to - first) as usize)
+            .enumerate()
+            .map(move |(i, entry)| (from + i as u64, entry))
+    }
+
+    /// Every retained entry plus the line still being written, with absolute indices.
+    pub fn entries_with_current(&self) -> Vec<(u64, ScrollbackEntry)> {
+        let mut entries: Vec<(u64, ScrollbackEntry)> = self
+            .range(self.first_index, self.next_index())
+            .map(|(index, entry)| (index, entry.clone()))
+            .collect();
+        if !self.current_line.is_empty() {
+            entries.push((self.next_index(), ScrollbackEntry::Line(self.current_line.clone(), self.current_runs.clone())));
+        }
+        entries
+    }
+
+    /// Retained lines plus the line still being written.
+    pub fn row_count(&self) -> usize {
+        self.entries.len() + usize::from(!self.current_line.is_empty())
+    }
+
+    /// Up to `count` rows from position `start`, where 0 is the oldest retained
+    /// line. Parts of the range past the end are left out.
+    pub fn rows(&self, start: usize, count: usize) -> Vec<ScrollbackRow> {
+        let end = start.saturating_add(count).min(self.row_count());
+        (start.min(end)..end)
+            .map(|position| {
+                let line_index = self.first_index + position as u64;
+                match self.entries.get(position) {
+                    Some(ScrollbackEntry::Line(text, styles)) => ScrollbackRow { line_index, runs: text_runs(text, styles), image: false },
+                    Some(entry) => ScrollbackRow {
+                        line_index,
+                        runs: vec![TextRun { text: entry_text(entry), attributes: None }],
+                        image: true,
+                    },
+                    None => ScrollbackRow { line_index, runs: text_runs(&self.current_line, &self.current_runs), image: false },
+                }
+            })
+            .collect()
+    }
+
+    pub fn usage(&self) -> ScrollbackUsage {
+        ScrollbackUsage {
+            lines: self.entries.len(),
+            bytes: self.bytes,
+            image_bytes: self.image_bytes,
+            max_lines: self.max_lines,
+            max_bytes: self.max_bytes,
+            evicted_lines: self.first_index,
+        }
+    }
+}
+
+pub fn entry_text(entry: &ScrollbackEntry) -> String {
+    match entry {
+        ScrollbackEntry::Line(text, _) => text.clone(),
+        ScrollbackEntry::Image { format, decoded_bytes, .. } => format!("[{} image, {} bytes]", format, decoded_bytes),
+    }
+}
+
+// Sixel raster attributes ("Pan;Pad;Ph;Pv) give the pixel size.
+fn sixel_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let text = String::from_utf8_lossy(data);
+    let start = text.find('"')?;
+    let dims: Vec<u32> = text[start + 1..]
+        .split(|c: char| !c.is_ascii_digit() && c != ';')
+        .next()
+        .unwrap_or("")
+        .split(';')
+        .filter_map(|n| n.parse().ok())
+        .collect();
+    (dims.len() >= 4).then(|| (dims[2], dims[3]))
+}
+
+// Fall back to six pixels per data byte when the raster attributes are missing.
+fn sixel_decoded_size(data: &[u8]) -> usize {
+    match sixel_dimensions(data) {
+        Some((width, height)) => width as usize * height as usize * 4,
+        None => data.len() * 6 * 4,
+    }
+}
+
+/// Plain-text description of a retained entry for assistive technology.
+fn accessible_entry_text(entry: &ScrollbackEntry) -> String {
+    match entry {
+        ScrollbackEntry::Line(text, _) => text.chars().filter(|c| !c.is_control() || *c == '\t').collect::<String>().trim_end().to_string(),
+        ScrollbackEntry::Image { width: Some(width), height: Some(height), .. } => format!("[image: {}x{}]", width, height),
+        ScrollbackEntry::Image { .. } => "[image]".to_string(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    WheelUp,
+    WheelDown,
+    None, // plain motion
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MouseAction {
+    Press,
+    Release,
+    Move,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MouseModifiers {
+    #[serde(default)]
+    pub shift: bool,
+    #[serde(default)]
+    pub alt: bool,
+    #[serde(default)]
+    pub ctrl: bool,
+}
+
+// Tracking mode (which events to report) and encoding are set independently by programs
+#[derive(Debug, Clone, Default)]
+pub struct MouseState {
+    pub tracking: Option<MouseReportMode>, // X10, Normal, Button or Any
+    pub sgr: bool,
+    pub urxvt: bool,
+}
+
+impl MouseState {
+    fn apply(&mut self, mode: MouseReportMode, enabled: bool) {
+        match mode {
+            MouseReportMode::SGR => self.sgr = enabled,
+            MouseReportMode::URXVT => self.urxvt = enabled,
+            tracking => {
+                if enabled {
+                    self.tracking = Some(tracking);
+                } else if self.tracking.as_ref() == Some(&tracking) {
+                    self.tracking = None;
+                }
+            }
+        }
+    }
+
+    /// Bytes to send for a mouse event at 0-based `row`/`col`, or `None` when the
+    /// active mode doesn't report it.
+    pub fn encode(&self, button: MouseButton, action: MouseAction, row: u16, col: u16, modifiers: &MouseModifiers) -> Option<Vec<u8>> {
+        let tracking = self.tracking.as_ref()?;
+        let wheel = matches!(button, MouseButton::WheelUp | MouseButton::WheelDown);
+
+        let reported = match (tracking, action) {
+            (MouseReportMode::X10, MouseAction::Press) => true,
+            (MouseReportMode::X10, _) => false,
+            (_, MouseAction::Press) => true,
+            (_, MouseAction::Release) => !wheel,
+            (MouseReportMode::Button, MouseAction::Move) => button != MouseButton::None,
+            (MouseReportMode::Any, MouseAction::Move) => true,
+            (_, MouseAction::Move) => false,
+        };
+        if !reported {
+            return None;
+        }
+
+        let mut code: u32 = match button {
+            MouseButton::Left => 0,
+            MouseButton::Middle => 1,
+            MouseButton::Right => 2,
+            MouseButton::None => 3,
+            MouseButton::WheelUp => 64,
+            MouseButton::WheelDown => 65,
+        };
+        // Legacy encodings can't say which button was released
+        if action == MouseAction::Release && !self.sgr {
+            code = 3;
+        }
+        if action == MouseAction::Move {
+            code += 32;
+        }
+        if *tracking != MouseReportMode::X10 {
+            if modifiers.shift { code += 4; }
+            if modifiers.alt { code += 8; }
+            if modifiers.ctrl { code += 16; }
+        }
+
+        let (x, y) = (col as u32 + 1, row as u32 + 1);
+        if self.sgr {
+            let suffix = if action == MouseAction::Release { 'm' } else { 'M' };
+            Some(format!("\x1b[<{};{};{}{}", code, x, y, suffix).into_bytes())
+        } else if self.urxvt {
+            Some(format!("\x1b[{};{};{}M", code + 32, x, y).into_bytes())
+        } else {
+            // Single-byte coordinates stop at 223
+            if x > 223 || y > 223 {
+                return None;
+            }
+            Some(vec![0x1b, b'[', b'M', (code + 32) as u8, (x + 32) as u8, (y + 32) as u8])
+        }
+    }
+}
+
+/// A cwd reported via OSC 7. `remote` is set when the host names another machine,
+/// e.g. a shell reached over ssh, so the path can't be used locally.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkingDirectory {
+    pub path: String,
+    pub host: Option<String>,
+    pub remote: bool,
+}
+
+impl WorkingDirectory {
+    pub fn new(path: String, host: Option<String>) -> Self {
+        let remote = host.as_deref().map(|host| !is_local_host(host)).unwrap_or(false);
+        WorkingDirectory { path, host, remote }
+    }
+}
+
+fn is_local_host(host: &str) -> bool {
+    let local = gethostname::gethostname().to_string_lossy().to_lowercase();
+    let host = host.to_lowercase();
+    let short = |name: &str| name.split('.').next().unwrap_or("").to_string();
+    host == "localhost" || host == local || short(&host) == short(&local)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionMode {
+    Linear, // row-major, wrapping from the end of one row to the start of the next
+    Block,  // the rectangle between the two corners
+}
+
+const URL_PATTERN: &str = r#"(?:(?:https?|ftp|file|ssh)://|www\.)[^\s<>"'`]+"#;
+
+/// Default for the `file_reference_pattern` setting: a path with a slash or an
+/// extension, then an optional `:line` and `:column`.
+// After warning about binary output, stay quiet while it keeps coming this often
+const BINARY_WARNING_COOLDOWN: Duration = Duration::from_secs(10);
+
+// A bell storm (say, from printing a binary file) rings at most this often...
+const BELL_RATE_LIMIT: Duration = Duration::from_millis(200);
+// ...and raises a desktop notification at most this often
+const BELL_NOTIFICATION_RATE_LIMIT: Duration = Duration::from_secs(10);
+
+/// What the frontend should do about a bell.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BellActions {
+    pub sound: bool,
+    pub flash: bool,
+    pub notify: bool,
+}
+
+impl BellActions {
+    pub fn for_policy(policy: BellPolicy, reduced_motion: bool) -> Self {
+        let flash = !reduced_motion;
+        match policy {
+            BellPolicy::None => BellActions::default(),
+            BellPolicy::Sound => BellActions { sound: true, ..Default::default() },
+            BellPolicy::Visual => BellActions { flash, ..Default::default() },
+            BellPolicy::Both => BellActions { sound: true, flash, ..Default::default() },
+            BellPolicy::Notification => BellActions { notify: true, ..Default::default() },
+        }
+    }
+}
+
+/// What `reset` sends, minus the screen clear: default tab stops, soft reset,
+/// ASCII charset, primary screen, visible default cursor, and no mouse,
+/// focus or bracketed-paste reporting.
+pub fn terminal_reset_sequence(cols: usize) -> String {
+    let mut sequence = String::from("\x1b7\x1b[3g");
+    for col in (1..cols).filter(|&col| default_tab_stop(col)) {
+        sequence.push_str(&format!("\x1b[{}G\x1bH", col + 1));
+    }
+    sequence.push_str("\x1b8\x1b[!p\x1b(B\x0f\x1b[?1049l\x1b[?25h\x1b[0 q");
+    sequence.push_str("\x1b[?9l\x1b[?1000l\x1b[?1002l\x1b[?1003l\x1b[?1006l\x1b[?1015l\x1b[?1004l\x1b[?2004l\x1b[?2026l\x1b[0m");
+    sequence
+}
+
+pub const DEFAULT_FILE_REFERENCE_PATTERN: &str = r"(?P<path>(?:[A-Za-z]:[\\/]|~|\.{1,2})?[\\/]?[\w.@+~-]*(?:[\\/][\w.@+~-]+)+|[\w@+~-][\w.@+~-]*\.[A-Za-z][A-Za-z0-9]*)(?::(?P<line>\d+)(?::(?P<column>\d+))?)?";
+
+// Trailing punctuation usually ends the sentence, not the URL; a closing
+// paren stays when the URL opened one
+fn trim_url_end(text: &str, start: usize, mut end: usize) -> usize {
+    while let Some(last) = text[start..end].chars().last() {
+        let unbalanced_paren = last == ')' && text[start..end].matches('(').count() < text[start..end].matches(')').count();
+        if !(".,;:!?'\"".contains(last) || unbalanced_paren) {
+            break;
+        }
+        end -= last.len_utf8();
+    }
+    end
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClickTarget {
+    Url { url: String },
+    File { path: String, line: Option<u32>, column: Option<u32> },
+}
+
+/// A clickable span of a screen row, from `start_col` to `end_col` inclusive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Clickable {
+    pub row: usize,
+    pub start_col: usize,
+    pub end_col: usize,
+    pub text: String,
+    pub target: ClickTarget,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SemanticKind {
+    Word,
+    Line,
+    Url,
+    Path,
+    Quoted,
+}
+
+/// What a double/triple click selected; also becomes the terminal's selection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectedRange {
+    pub start: CursorPosition,
+    pub end: CursorPosition,
+    pub text: String,
+    pub kind: SemanticKind,
+}
+
+/// The screen rows that changed since the client's `since_version`, for
+/// patching a rendered view instead of fetching the whole grid.
+#[derive(Debug, Clone, Serialize)]
+pub struct GridDiff {
+    pub version: u64, // pass back as `since_version` next time
+    pub full: bool,   // every row is included and the view should be rebuilt
+    pub rows: usize,
+    pub cols: usize,
+    pub changed_rows: Vec<GridRowUpdate>,
+    pub cursor: CursorPosition,
+    pub modes: Option<TerminalModes>, // None when unchanged since `since_version`
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GridRowUpdate {
+    pub index: usize,
+    pub cells: Vec<TerminalChar>,
+}
+
+/// Modes that change how the view is drawn or what input it reports.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TerminalModes {
+    pub cursor_visible: bool,
+    pub cursor_style: Option<CursorStyle>,
+    pub alternate_screen: bool,
+    pub bracketed_paste: bool,
+    pub mouse_reporting: bool,
+    pub focus_events: bool,
+    pub synchronized_update: bool,
+}
+
+// Change tracking behind `grid_diff`: the version that last touched each row,
+// the cursor and the modes
+#[derive(Debug, Default)]
+struct GridVersions {
+    version: u64,
+    layout: (usize, usize, bool), // rows, columns and whether the alternate screen is up
+    layout_version: u64,          // diffs from before a layout change need everything
+    row_hashes: Vec<u64>,
+    row_versions: Vec<u64>,
+    damage_seen: u64, // the grid's damage count when rows were last hashed
+    cursor: (u16, u16),
+    modes: Option<TerminalModes>,
+    modes_version: u64,
+}
+
+fn row_hash(row: &[TerminalChar]) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    row.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Screen cells from where the drag started (`anchor`) to where it is now (`head`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Selection {
+    pub mode: SelectionMode,
+    pub anchor: CursorPosition,
+    pub head: CursorPosition,
+}
+
+#[derive(Debug)]
+pub struct Terminal {
+    pub id: String,
+    pub grid: TerminalGrid,
+    pub parser: AnsiParser,
+    pub size: TerminalSize,
+    pub blocks: Vec<CommandBlock>,
+    pub scrollback: Scrollback,
+    pub hyperlinks: Vec<HyperlinkSpan>,
+    pub bracketed_paste: bool, // set by the running program via \e[?2004h
+    pub mouse: MouseState,
+    pub working_directory: Option<WorkingDirectory>, // last reported by the shell via OSC 7
+    pub primary_grid: Option<TerminalGrid>, // saved while the alternate screen is shown
+    pub cursor_visible: bool,
+    pub cursor_style: Option<CursorStyle>, // None until a program picks one with DECSCUSR
+    pub focus_events: bool,
+    pub synchronized_update: bool,
+    pub selection: Option<Selection>,
+    binary_output_at: Option<Instant>, // last time the parser flagged binary output
+    binary_warning: bool,
+    bell: bool, // rang since the last `take_bell`
+    integration_marks: Vec<IntegrationMark>, // OSC 133 marks seen, until `take_integration_marks`
+    queued_rerun: Option<QueuedRerun>,
+    bell_at: Option<Instant>, // last bell acted on
+    bell_notified_at: Option<Instant>,
+    versions: GridVersions,
+}
+
+impl Terminal {
+    pub fn new(id: String, size: TerminalSize, max_lines: usize, max_bytes: usize) -> Self {
+        let grid = TerminalGrid::new(size.cols as usize, size.rows as usize);
+        let parser = AnsiParser::new();
+
+        Terminal {
+            id,
+            grid,
+            parser,
+            size,
+            blocks: Vec::new(),
+            scrollback: Scrollback::new(max_lines, max_bytes),
+            hyperlinks: Vec::new(),
+            bracketed_paste: false,
+            mouse: MouseState::default(),
+            working_directory: None,
+            primary_grid: None,
+            cursor_visible: true,
+            cursor_style: None,
+            focus_events: false,
+            synchronized_update: false,
+            selection: None,
+            binary_output_at: None,
+            binary_warning: false,
+            bell: false,
+            integration_marks: Vec::new(),
+            queued_rerun: None,
+            bell_at: None,
+            bell_notified_at: None,
+            versions: GridVersions::default(),
+        }
+    }
+
+    pub fn process_output(&mut self, data: &str) {
+        let commands = self.parser.parse(data);
+        
+        for command in commands {
+            self.execute_command(command);
+        }
+
+        if self.parser.take_binary_detected() {
+            let now = Instant::now();
+            if self.binary_output_at.is_none_or(|at| now.duration_since(at) > BINARY_WARNING_COOLDOWN) {
+                self.binary_warning = true;
+            }
+            self.binary_output_at = Some(now);
+        }
+
+        if self.scrollback.enforce_limits() {
+            self.drop_evicted_references();
+        }
+        self.note_changes();
+    }
+
+    fn modes(&self) -> TerminalModes {
+        TerminalModes {
+            cursor_visible: self.cursor_visible,
+            cursor_style: self.cursor_style.clone(),
+            alternate_screen: self.in_alternate_screen(),
+            bracketed_paste: self.bracketed_paste,
+            mouse_reporting: self.mouse.tracking.is_some(),
+            focus_events: self.focus_events,
+            synchronized_update: self.synchronized_update,
+        }
+    }
+
+    // Bump the version if anything `grid_diff` reports has changed. Only rows
+    // written since the last check are rehashed; rewriting a row with the same
+    // content doesn't count as a change.
+    fn note_changes(&mut self) {
+        let next = self.versions.version + 1;
+        let layout = (self.grid.rows.len(), self.grid.cols, self.in_alternate_screen());
+        let cursor = (self.grid.cursor.row, self.grid.cursor.col);
+        let modes = self.modes();
+        let grid = &self.grid;
+        let versions = &mut self.versions;
+
+        let mut changed = false;
+        if layout != versions.layout {
+            versions.layout = layout;
+            versions.layout_version = next;
+            versions.row_hashes = grid.rows.iter().map(|row| row_hash(row)).collect();
+            versions.row_versions = vec![next; grid.rows.len()];
+            changed = true;
+        } else {
+            for row in grid.rows_damaged_since(versions.damage_seen) {
+                let hash = row_hash(&grid.rows[row]);
+                if versions.row_hashes[row] != hash {
+                    versions.row_hashes[row] = hash;
+                    versions.row_versions[row] = next;
+                    changed = true;
+                }
+            }
+        }
+        versions.damage_seen = grid.damage_count();
+        if cursor != versions.cursor {
+            versions.cursor = cursor;
+            changed = true;
+        }
+        if versions.modes.as_ref() != Some(&modes) {
+            versions.modes = Some(modes);
+            versions.modes_version = next;
+            changed = true;
+        }
+        if changed {
+            versions.version = next;
+        }
+    }
+
+    /// What changed on screen since `since_version`. Version 0, a version from
+    /// before a resize or screen switch, or one this terminal never issued gets
+    /// a full snapshot.
+    pub fn grid_diff(&self, since_version: u64) -> GridDiff {
+        let versions = &self.versions;
+        let full = since_version == 0 || since_version < versions.layout_version || since_version > versions.version;
+        let changed_rows = self.grid.rows.iter().enumerate()
+            .filter(|(row, _)| full || versions.row_versions.get(*row).is_none_or(|&version| version > since_version))
+            .map(|(index, cells)| GridRowUpdate { index, cells: cells.clone() })
+            .collect();
+        GridDiff {
+            version: versions.version,
+            full,
+            rows: self.grid.rows.len(),
+            cols: self.grid.cols,
+            changed_rows,
+            cursor: self.grid.cursor.clone(),
+            modes: (full || versions.modes_version > since_version).then(|| self.modes()),
+        }
+    }
+
+    pub fn set_scrollback_limits(&mut self, max_lines: usize, max_bytes: usize) {
+        self.scrollback.max_lines = max_lines;
+        self.scrollback.max_bytes = max_bytes;
+        if self.scrollback.enforce_limits() {
+            self.drop_evicted_references();
+        }
+    }
+
+    // Blocks whose output is entirely gone are removed; partially evicted ones are flagged
+    fn drop_evicted_references(&mut self) {
+        let first = self.scrollback.first_index();
+        self.hyperlinks.retain(|link| link.line_index >= first);
+        self.blocks.retain(|block| match block.output_end_line {
+            Some(end) => end > first,
+            None => true,
+        });
+        for block in &mut self.blocks {
+            if block.output_start_line.map(|start| start < first).unwrap_or(block.prompt_line < first) {
+                block.truncated = true;
+            }
+        }
+    }
+
+    fn execute_command(&mut self, command: AnsiCommand) {
+        match command {
+            AnsiCommand::PrintText(text) => {
+                // Full-screen programs redraw the alternate screen; only the primary keeps history
+                if !self.in_alternate_screen() {
+                    self.capture_block_text(&text);
+                    self.scrollback.push_text(&text, self.parser.current_attributes());
+                }
+                for ch in text.chars() {
+                    self.grid.write_char(ch, self.parser.current_attributes());
+                }
+            }
+            AnsiCommand::CursorUp(n) => {
+                self.grid.move_cursor_relative(-(n as i16), 0);
+            }
+            AnsiCommand::CursorDown(n) => {
+                self.grid.move_cursor_relative(n as i16, 0);
+            }
+            AnsiCommand::LineFeed => {
+                if !self.in_alternate_screen() {
+                    self.scrollback.end_line();
+                }
+                self.grid.line_feed();
+            }
+            AnsiCommand::ReverseIndex => {
+                self.grid.reverse_index();
+            }
+            AnsiCommand::SemanticPromptMark(mark) => {
+                self.handle_prompt_mark(mark);
+            }
+            AnsiCommand::SetWorkingDirectory(path, host) => {
+                self.working_directory = Some(WorkingDirectory::new(path, host));
+            }
+            AnsiCommand::EnableBracketedPaste => {
+                self.bracketed_paste = true;
+            }
+            AnsiCommand::DisableBracketedPaste => {
+                self.bracketed_paste = false;
+            }
+            AnsiCommand::EnableMouseReporting(mode) => {
+                self.mouse.apply(mode, true);
+            }
+            AnsiCommand::DisableMouseReporting(mode) => {
+                self.mouse.apply(mode, false);
+            }
+            AnsiCommand::DisplayImage(_) | AnsiCommand::DisplaySixel(_) | AnsiCommand::SetHyperlink(..) if self.in_alternate_screen() => {}
+            AnsiCommand::DisplayImage(image) => {
+                let size = image.data.len();
+                let dimensions = image.width.zip(image.height);
+                self.scrollback.push_image(&image.format, size, image.data, dimensions);
+            }
+            AnsiCommand::DisplaySixel(data) => {
+                let size = sixel_decoded_size(&data);
+                let dimensions = sixel_dimensions(&data);
+                self.scrollback.push_image("sixel", size, data, dimensions);
+            }
+            AnsiCommand::SetHyperlink(url, _) if !url.is_empty() => {
+                self.hyperlinks.push(HyperlinkSpan { line_index: self.scrollback.next_index(), url });
+            }
+            AnsiCommand::CursorLeft(n) => {
+                self.grid.move_cursor_relative(0, -(n as i16));
+            }
+            AnsiCommand::CursorRight(n) => {
+                self.grid.move_cursor_relative(0, n as i16);
+            }
+            AnsiCommand::CursorPosition(row, col) => {
+                self.grid.move_cursor(row.saturating_sub(1), col.saturating_sub(1));
+            }
+            AnsiCommand::Tab(n) => {
+                self.grid.tab(n as usize);
+            }
+            AnsiCommand::SetTabStop => {
+                self.grid.set_tab_stop();
+            }
+            AnsiCommand::ClearTabStop(all) => {
+                self.grid.clear_tab_stop(all);
+            }
+            AnsiCommand::CursorColumn(col) => {
+                // Also how the parser reports carriage returns
+                let row = self.grid.cursor.row;
+                self.grid.move_cursor(row, col.saturating_sub(1));
+            }
+            AnsiCommand::CursorHome => {
+                self.grid.move_cursor(0, 0);
+            }
+            AnsiCommand::ClearScreen => {
+                self.grid.clear_screen();
+            }
+            AnsiCommand::ClearLine => {
+                self.grid.clear_line();
+            }
+            AnsiCommand::ClearToEndOfLine => {
+                self.grid.clear_to_end_of_line();
+            }
+            AnsiCommand::ClearToBeginningOfLine => {
+                self.grid.clear_to_beginning_of_line();
+            }
+            AnsiCommand::ScrollUp(n) => {
+                self.grid.scroll_up(n as usize);
+            }
+            AnsiCommand::ScrollDown(n) => {
+                self.grid.scroll_down(n as usize);
+            }
+            AnsiCommand::SetScrollRegion(top, bottom) => {
+                self.grid.set_scroll_region(top, bottom);
+            }
+            AnsiCommand::InsertLines(n) => {
+                self.grid.insert_lines(n as usize);
+            }
+            AnsiCommand::DeleteLines(n) => {
+                self.grid.delete_lines(n as usize);
+            }
+            AnsiCommand::SetGraphicsMode(params) => {
+                self.parser.apply_graphics_mode(&params);
+            }
+            AnsiCommand::EnterAlternateScreen => self.enter_alternate_screen(),
+            AnsiCommand::ExitAlternateScreen => self.exit_alternate_screen(),
+            AnsiCommand::ShowCursor => self.cursor_visible = true,
+            AnsiCommand::HideCursor => self.cursor_visible = false,
+            AnsiCommand::SetCursorStyle(style) => self.cursor_style = Some(style),
+            AnsiCommand::EnableFocusEvents => self.focus_events = true,
+            AnsiCommand::DisableFocusEvents => self.focus_events = false,
+            AnsiCommand::BeginSynchronizedUpdate => self.synchronized_update = true,
+            AnsiCommand::EndSynchronizedUpdate => self.synchronized_update = false,
+            AnsiCommand::DeviceStatusReport => {
+                self.parser.respond("\x1b[0n");
+            }
+            AnsiCommand::CursorPositionReport => {
+                let cursor = &self.grid.cursor;
+                let report = format!("\x1b[{};{}R", cursor.row + 1, cursor.col + 1);
+                self.parser.respond(&report);
+            }
+            AnsiCommand::PrimaryDeviceAttributes => {
+                let attributes = self.parser.device_attributes();
+                self.parser.respond(&attributes);
+            }
+            AnsiCommand::SecondaryDeviceAttributes => {
+                // Terminal type 0 (VT100-compatible), version 10, no ROM cartridge
+                self.parser.respond("\x1b[>0;10;0c");
+            }
+            AnsiCommand::RequestMode(mode, private) => {
+                let report = format!("\x1b[{}{};{}$y", if private { "?" } else { "" }, mode, self.mode_state(mode, private));
+                self.parser.respond(&report);
+            }
+            AnsiCommand::SoftReset => self.soft_reset(),
+            AnsiCommand::FullReset => {
+                self.soft_reset();
+                self.exit_alternate_screen();
+                self.grid.clear_screen();
+                self.grid.move_cursor(0, 0);
+            }
+            AnsiCommand::Bell | AnsiCommand::VisualBell => {
+                self.bell = true;
+            }
+            AnsiCommand::Unknown(seq) => {
+                log::warn!("Unknown escape sequence: {}", seq);
+            },
+            _ => {
+                // Handle other ANSI commands
+            }
+        }
+    }
+
+    pub fn start_selection(&mut self, row: u16, col: u16, mode: SelectionMode) {
+        let anchor = CursorPosition { row, col };
+        self.selection = Some(Selection { mode, anchor: anchor.clone(), head: anchor });
+    }
+
+    /// Move the selection's free end. Returns false when nothing is being selected.
+    pub fn update_selection(&mut self, row: u16, col: u16) -> bool {
+        match self.selection.as_mut() {
+            Some(selection) => {
+                selection.head = CursorPosition { row, col };
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Make cells `first..=last` of `row` the selection
+    fn select_cells(&mut self, row: u16, (first, last): (usize, usize), kind: SemanticKind) -> SelectedRange {
+        let start = CursorPosition { row, col: first as u16 };
+        let end = CursorPosition { row, col: last as u16 };
+        self.selection = Some(Selection { mode: SelectionMode::Linear, anchor: start.clone(), head: end.clone() });
+        let text = self.grid.linear_text(&start, &end);
+        SelectedRange { start, end, text, kind }
+    }
+
+    /// Double click: the word under the cell.
+    pub fn select_word_at(&mut self, row: u16, col: u16, word_chars: &str) -> Option<SelectedRange> {
+        let range = self.grid.word_range_at(row as usize, col as usize, word_chars)?;
+        Some(self.select_cells(row, range, SemanticKind::Word))
+    }
+
+    /// Triple click: the row up to its last non-blank cell.
+    pub fn select_line_at(&mut self, row: u16) -> Option<SelectedRange> {
+        let cells = self.grid.rows.get(row as usize)?;
+        let last = cells.iter().rposition(|cell| cell.character != ' ' || !cell.combining.is_empty()).unwrap_or(0);
+        Some(self.select_cells(row, (0, last), SemanticKind::Line))
+    }
+
+    /// Double click that understands URLs, paths and quoted strings.
+    pub fn select_semantic_at(&mut self, row: u16, col: u16, word_chars: &str) -> Option<SelectedRange> {
+        let (range, kind) = self.grid.semantic_range_at(row as usize, col as usize, word_chars)?;
+        Some(self.select_cells(row, range, kind))
+    }
+
+    pub fn get_selection_text(&self) -> Option<String> {
+        let selection = self.selection.as_ref()?;
+        Some(match selection.mode {
+            SelectionMode::Linear => self.grid.linear_text(&selection.anchor, &selection.head),
+            SelectionMode::Block => self.grid.block_text(&selection.anchor, &selection.head),
+        })
+    }
+
+    pub fn in_alternate_screen(&self) -> bool {
+        self.primary_grid.is_some()
+    }
+
+    // The primary grid, cursor included, is set aside untouched and the program
+    // gets a blank screen of the same size
+    fn enter_alternate_screen(&mut self) {
+        if self.in_alternate_screen() {
+            return;
+        }
+        let alternate = TerminalGrid::new(self.size.cols as usize, self.size.rows as usize);
+        self.primary_grid = Some(std::mem::replace(&mut self.grid, alternate));
+        self.selection = None;
+    }
+
+    fn exit_alternate_screen(&mut self) {
+        if let Some(primary) = self.primary_grid.take() {
+            self.grid = primary;
+            self.grid.mark_all_dirty();
+            self.selection = None;
+        }
+    }
+
+    /// Whether binary output was seen since the last call; repeats are held
+    /// back while the same burst continues.
+    pub fn take_binary_warning(&mut self) -> bool {
+        std::mem::take(&mut self.binary_warning)
+    }
+
+    pub fn take_integration_marks(&mut self) -> Vec<IntegrationMark> {
+        std::mem::take(&mut self.integration_marks)
+    }
+
+    /// Exit code of the last command shell integration saw finish.
+    pub fn last_exit_code(&self) -> Option<Option<i32>> {
+        self.blocks.iter().rev().find(|b| b.phase == BlockPhase::Finished).map(|b| b.exit_code)
+    }
+
+    pub fn take_bell(&mut self) -> bool {
+        std::mem::take(&mut self.bell)
+    }
+
+    /// Apply `policy` to a bell that just rang; bells coming faster than the
+    /// rate limits allow do nothing.
+    pub fn bell_actions(&mut self, policy: BellPolicy, reduced_motion: bool) -> BellActions {
+        let now = Instant::now();
+        if self.bell_at.is_some_and(|at| now.duration_since(at) < BELL_RATE_LIMIT) {
+            return BellActions::default();
+        }
+        self.bell_at = Some(now);
+        let mut actions = BellActions::for_policy(policy, reduced_motion);
+        if actions.notify {
+            if self.bell_notified_at.is_some_and(|at| now.duration_since(at) < BELL_NOTIFICATION_RATE_LIMIT) {
+                actions.notify = false;
+            } else {
+                self.bell_notified_at = Some(now);
+            }
+        }
+        actions
+    }
+
+    // DECSTR: the screen contents and cursor position survive
+    fn soft_reset(&mut self) {
+        self.parser.reset_state();
+        self.grid.reset_scroll_region();
+        self.cursor_visible = true;
+        self.synchronized_update = false;
+    }
+
+    /// Put the terminal back in a known-good state, as `reset` would, while
+    /// keeping the screen contents, scrollback and the shell itself.
+    pub fn reset(&mut self) {
+        self.exit_alternate_screen();
+        self.soft_reset();
+        self.grid.reset_tab_stops();
+        self.cursor_style = None;
+        self.mouse = MouseState::default();
+        self.focus_events = false;
+        self.bracketed_paste = false;
+        self.binary_warning = false;
+        self.note_changes();
+    }
+
+    // DECRPM state: 1 set, 2 reset, 0 for modes we don't track
+    fn mode_state(&self, mode: u16, private: bool) -> u8 {
+        let set = |on: bool| if on { 1 } else { 2 };
+        if !private {
+            return 0;
+        }
+        match mode {
+            25 => set(self.cursor_visible),
+            47 | 1047 | 1049 => set(self.in_alternate_screen()),
+            9 => set(self.mouse.tracking == Some(MouseReportMode::X10)),
+            1000 => set(self.mouse.tracking == Some(MouseReportMode::Normal)),
+            1002 => set(self.mouse.tracking == Some(MouseReportMode::Button)),
+            1003 => set(self.mouse.tracking == Some(MouseReportMode::Any)),
+            1004 => set(self.focus_events),
+            1006 => set(self.mouse.sgr),
+            1015 => set(self.mouse.urxvt),
+            2004 => set(self.bracketed_paste),
+            2026 => set(self.synchronized_update),
+            _ => 0,
+        }
+    }
+
+    fn handle_prompt_mark(&mut self, mark: PromptMark) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        self.apply_prompt_mark(mark, now);
+    }
+
+    fn apply_prompt_mark(&mut self, mark: PromptMark, now: u64) {
+        match mark {
+            PromptMark::PromptStart => {
+                // A new prompt closes a block whose shell never sent D
+                if self.blocks.last().map(|b| b.phase != BlockPhase::Finished).unwrap_or(false) {
+                    self.finish_block(None, now);
+                }
+                self.blocks.push(CommandBlock {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    terminal_id: self.id.clone(),
+                    phase: BlockPhase::Prompt,
+                    prompt: String::new(),
+                    command: String::new(),
+                    exit_code: None,
+                    prompt_started_at: now,
+                    command_started_at: None,
+                    output_started_at: None,
+                    finished_at: None,
+                    prompt_line: self.scrollback.next_index(),
+                    output_start_line: None,
+                    output_end_line: None,
+                    output_lines: 0,
+                    truncated: false,
+                    folded: false,
+                    duration_ms: None,
+                    duration_badge: None,
+                });
+                if self.blocks.len() > MAX_COMMAND_BLOCKS {
+                    let overflow = self.blocks.len() - MAX_COMMAND_BLOCKS;
+                    self.blocks.drain(0..overflow);
+                }
+                self.integration_marks.push(IntegrationMark::PromptStart);
+            }
+            PromptMark::CommandStart => {
+                if let Some(block) = self.blocks.last_mut().filter(|b| b.phase == BlockPhase::Prompt) {
+                    block.phase = BlockPhase::Command;
+                    block.command_started_at = Some(now);
+                    self.integration_marks.push(IntegrationMark::CommandStart { prompt: block.prompt.clone() });
+                }
+            }
+            PromptMark::OutputStart => {
+                let line = self.scrollback.next_index();
+                if let Some(block) = self.blocks.last_mut().filter(|b| b.phase != BlockPhase::Finished) {
+                    block.phase = BlockPhase::Output;
+                    block.command = block.command.trim().to_string();
+                    block.output_started_at = Some(now);
+                    block.output_start_line = Some(line);
+                    self.integration_marks.push(IntegrationMark::OutputStart { command: block.command.clone() });
+                }
+            }
+            PromptMark::CommandEnd(exit_code) => {
+                if self.blocks.last().map(|b| b.phase != BlockPhase::Finished).unwrap_or(false) {
+                    self.finish_block(exit_code, now);
+                }
+                self.integration_marks.push(IntegrationMark::CommandEnd { exit_code });
+            }
+        }
+    }
+
+    fn finish_block(&mut self, exit_code: Option<i32>, now: u64) {
+        // A partially written line still belongs to this block's output
+        let end = self.scrollback.next_index() + if self.scrollback.current_line.is_empty() { 0 } else { 1 };
+        if let Some(block) = self.blocks.last_mut() {
+            block.phase = BlockPhase::Finished;
+            block.exit_code = exit_code;
+            block.finished_at = Some(now);
+            block.update_duration(now);
+            if let Some(start) = block.output_start_line {
+                block.output_end_line = Some(end);
+                block.output_lines = end.saturating_sub(start) as usize;
+            }
+        }
+    }
+
+    fn capture_block_text(&mut self, text: &str) {
+        if let Some(block) = self.blocks.last_mut() {
+            match block.phase {
+                BlockPhase::Prompt => block.prompt.push_str(text),
+                BlockPhase::Command => block.command.push_str(text),
+                BlockPhase::Output | BlockPhase::Finished => {}
+            }
+        }
+    }
+
+    /// Blocks with the running one's duration brought up to date.
+    pub fn command_blocks(&self) -> Vec<CommandBlock> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        let mut blocks = self.blocks.clone();
+        if let Some(block) = blocks.last_mut().filter(|b| b.phase != BlockPhase::Finished) {
+            block.update_duration(now);
+        }
+        blocks
+    }
+
+    /// The last command that was run; a fresh prompt with nothing run yet doesn't count.
+    pub fn last_command_duration(&self) -> Option<CommandDuration> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        self.last_command_duration_at(now)
+    }
+
+    fn last_command_duration_at(&self, now: u64) -> Option<CommandDuration> {
+        let mut block = self.blocks.iter().rev().find(|b| b.execution_started_at().is_some())?.clone();
+        block.update_duration(now);
+        Some(CommandDuration {
+            block_id: block.id,
+            command: block.command.trim().to_string(),
+            duration_ms: block.duration_ms.unwrap_or_default(),
+            running: block.phase != BlockPhase::Finished,
+            badge: block.duration_badge,
+        })
+    }
+
+    /// Retained output of a block as text; images show as placeholders.
+    pub fn block_output(&self, block: &CommandBlock) -> String {
+        let start = match block.output_start_line {
+            Some(start) => start,
+            None => return String::new(),
+        };
+        let running = block.output_end_line.is_none();
+        let end = block.output_end_line.unwrap_or_else(|| self.scrollback.next_index());
+        let mut lines: Vec<String> = self
+            .scrollback
+            .range(start, end)
+            .map(|(_, entry)| entry_text(entry))
+            .collect();
+        if running && !self.scrollback.current_line.is_empty() {
+            lines.push(self.scrollback.current_line.clone());
+        }
+        lines.join("\n")
+    }
+
+    /// Block output as it should land on the clipboard: when the output didn't end
+    /// in a newline the next prompt shares its last line, so that's cut off, along
+    /// with trailing blank lines.
+    pub fn copyable_output(&self, index: usize) -> String {
+        let block = &self.blocks[index];
+        let mut output = self.block_output(block);
+        if let Some(next) = self.blocks.get(index + 1) {
+            let prompt = next.prompt.trim_end();
+            if !prompt.is_empty() {
+                if let Some(stripped) = output.trim_end().strip_suffix(prompt) {
+                    output = stripped.to_string();
+                }
+            }
+        }
+        output.trim_end().to_string()
+    }
+
+    /// Index of the most recent block that ran a command.
+    pub fn last_command_block(&self) -> Option<usize> {
+        self.blocks.iter().rposition(|block| !block.command.trim().is_empty() && block.output_start_line.is_some())
+    }
+
+    /// A copy of the block, with its duration brought up to date if it's running.
+    pub fn block(&self, block_id: &str) -> Option<CommandBlock> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        let mut block = self.blocks.iter().find(|b| b.id == block_id)?.clone();
+        if block.phase != BlockPhase::Finished {
+            block.update_duration(now);
+        }
+        Some(block)
+    }
+
+    pub fn set_block_folded(&mut self, block_id: &str, folded: bool) -> bool {
+        match self.blocks.iter_mut().find(|b| b.id == block_id) {
+            Some(block) => {
+                block.folded = folded;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Retained scrollback where each folded block's output collapses into a
+    /// single summary line. Folds are keyed by block, so they survive resizes.
+    pub fn visible_scrollback(&self) -> Vec<VisibleLine> {
+        let mut lines = Vec::new();
+        let mut blocks = self.blocks.iter().filter(|b| b.output_start_line.is_some()).peekable();
+        let mut index = self.scrollback.first_index();
+        let end = self.scrollback.next_index();
+
+        while index < end {
+            // Skip blocks that ended before this line
+            while blocks.peek().map(|b| b.output_end_line.map(|e| e <= index).unwrap_or(false)).unwrap_or(false) {
+                blocks.next();
+            }
+            let block = blocks.peek().filter(|b| b.output_start_line.unwrap() <= index);
+
+            if let Some(block) = block.filter(|b| b.folded) {
+                let block_end = block.output_end_line.unwrap_or(end).min(end);
+                let hidden = block_end - index;
+                lines.push(VisibleLine {
+                    kind: VisibleLineKind::FoldSummary,
+                    line_index: index,
+                    block_id: Some(block.id.clone()),
+                    text: format!("{} line{} hidden", hidden, if hidden == 1 { "" } else { "s" }),
+                });
+                index = block_end;
+                continue;
+            }
+
+            if let Some((_, entry)) = self.scrollback.range(index, index + 1).next() {
+                lines.push(VisibleLine {
+                    kind: match (entry, block.is_some()) {
+                        (ScrollbackEntry::Image { .. }, _) => VisibleLineKind::Image,
+                        (_, true) => VisibleLineKind::Output,
+                        (_, false) => VisibleLineKind::Text,
+                    },
+                    line_index: index,
+                    block_id: block.map(|b| b.id.clone()),
+                    text: entry_text(entry),
+                });
+            }
+            index += 1;
+        }
+
+        if !self.scrollback.current_line.is_empty() {
+            let running = self.blocks.last().filter(|b| b.output_start_line.is_some() && b.output_end_line.is_none());
+            if !running.map(|b| b.folded).unwrap_or(false) {
+                lines.push(VisibleLine {
+                    kind: if running.is_some() { VisibleLineKind::Output } else { VisibleLineKind::Text },
+                    line_index: end,
+                    block_id: running.map(|b| b.id.clone()),
+                    text: self.scrollback.current_line.clone(),
+                });
+            }
+        }
+        lines
+    }
+
+    /// The last screenful of output as plain text for screen readers: styling and
+    /// control characters are gone, blank-line runs collapse to one, images are
+    /// described by size, and prompt/command/output blocks are mapped onto the lines.
+    pub fn accessible_snapshot(&self) -> AccessibleSnapshot {
+        let end = self.scrollback.next_index();
+        let mut window: Vec<(u64, String)> = self
+            .scrollback
+            .range(end.saturating_sub(self.size.rows as u64), end)
+            .map(|(index, entry)| (index, accessible_entry_text(entry)))
+            .collect();
+        if !self.scrollback.current_line.is_empty() {
+            window.push((end, accessible_entry_text(&ScrollbackEntry::Line(self.scrollback.current_line.clone(), Vec::new()))));
+        }
+        let overflow = window.len().saturating_sub(self.size.rows as usize);
+        window.drain(..overflow);
+
+        // Keep one blank line out of each run, remembering which absolute line each row came from
+        let mut lines = Vec::new();
+        let mut sources = Vec::new();
+        for (index, text) in window {
+            let blank = text.trim().is_empty();
+            if blank && lines.last().is_none_or(|last: &String| last.is_empty()) {
+                continue;
+            }
+            lines.push(if blank { String::new() } else { text });
+            sources.push(index);
+        }
+        while lines.last().is_some_and(|last| last.is_empty()) {
+            lines.pop();
+            sources.pop();
+        }
+
+        // Absolute range [start, end) -> row range in `lines`
+        let rows_for = |start: u64, end: u64| -> Option<(usize, usize)> {
+            let first = sources.partition_point(|&index| index < start);
+            let last = sources.partition_point(|&index| index < end);
+            (first < last).then_some((first, last))
+        };
+
+        let mut regions = Vec::new();
+        let live_end = end + 1;
+        for block in &self.blocks {
+            let prompt_end = block.output_start_line.unwrap_or(live_end).max(block.prompt_line + 1);
+            if let Some((start_line, end_line)) = rows_for(block.prompt_line, prompt_end) {
+                regions.push(AccessibleRegion {
+                    kind: AccessibleRegionKind::Prompt,
+                    block_id: block.id.clone(),
+                    start_line,
+                    end_line,
+                    text: Some(block.prompt.trim().to_string()),
+                    exit_code: None,
+                });
+                if !block.command.trim().is_empty() {
+                    regions.push(AccessibleRegion {
+                        kind: AccessibleRegionKind::Command,
+                        block_id: block.id.clone(),
+                        start_line,
+                        end_line,
+                        text: Some(block.command.trim().to_string()),
+                        exit_code: None,
+                    });
+                }
+            }
+            if let Some(output_start) = block.output_start_line {
+                if let Some((start_line, end_line)) = rows_for(output_start, block.output_end_line.unwrap_or(live_end)) {
+                    regions.push(AccessibleRegion {
+                        kind: AccessibleRegionKind::Output,
+                        block_id: block.id.clone(),
+                        start_line,
+                        end_line,
+                        text: None,
+                        exit_code: block.exit_code,
+                    });
+                }
+            }
+        }
+
+        AccessibleSnapshot {
+            terminal_id: self.id.clone(),
+            lines,
+            cursor: self.grid.cursor.clone(),
+            regions,
+        }
+    }
+
+    pub fn resize(&mut self, new_size: TerminalSize) {
+        self.size = new_size.clone();
+        self.grid.resize(new_size.cols as usize, new_size.rows as usize);
+        if let Some(primary) = self.primary_grid.as_mut() {
+            primary.resize(new_size.cols as usize, new_size.rows as usize);
+        }
+        self.note_changes();
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i32>, // None when the process was ended by a signal
+    pub duration_ms: u64,
+}
+
+pub struct TerminalManager {
+    terminals: Arc<Mutex<HashMap<String, Terminal>>>,
+    pty_manager: Arc<Mutex<PtyManager>>,
+    shell_hooks: Arc<Mutex<ShellHooksManager>>,
+    search_index: Arc<Mutex<SearchIndexManager>>,
+    command_event_sender: mpsc::UnboundedSender<CommandEvent>,
+    scrollback_limits: Arc<Mutex<(usize, usize)>>, // (max_lines, max_bytes) for new terminals
+    recordings: Arc<Mutex<HashMap<String, CastRecorder>>>,
+    playbacks: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>, // playback terminal -> cancel flag
+    output_guard: Arc<Mutex<OutputGuard>>,
+    bell_policy: Arc<Mutex<BellPolicy>>,
+    reset_on_binary_output: Arc<AtomicBool>, // the setting, kept live by the settings subscriber
+}
+
+impl TerminalManager {
+    pub fn gather_context(&self, terminal_id: &str) -> Option<AiContext> {
+        let (working_dir, prompt) = if let Some(p) = self.get_current_prompt(terminal_id) {
+            (Some(p.working_dir.clone()), Some(p.prompt_text.clone()))
+        } else { (None, None) };
+        let recent_commands = self
+            .get_command_history(terminal_id, Some(20))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|c| c.text)
+            .collect::<Vec<_>>();
+        let tail_output = self
+            .search_index
+            .lock()
+            .unwrap()
+            .tail(terminal_id, 200)
+            .unwrap_or_default();
+        Some(AiContext { working_dir, prompt, recent_commands, tail_output })
+    }
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<TerminalOutput>, mpsc::UnboundedReceiver<CommandEvent>) {
+        let (pty_manager, output_receiver) = PtyManager::new();
+        let (command_event_sender, command_event_receiver) = mpsc::unbounded_channel();
+        
+        let manager = TerminalManager {
+            terminals: Arc::new(Mutex::new(HashMap::new())),
+            pty_manager: Arc::new(Mutex::new(pty_manager)),
+            shell_hooks: Arc::new(Mutex::new(ShellHooksManager::new())),
+            search_index: Arc::new(Mutex::new(SearchIndexManager::new())),
+            command_event_sender,
+            scrollback_limits: Arc::new(Mutex::new((DEFAULT_SCROLLBACK_LINES, DEFAULT_SCROLLBACK_BYTES))),
+            recordings: Arc::new(Mutex::new(HashMap::new())),
+            playbacks: Arc::new(Mutex::new(HashMap::new())),
+            output_guard: Arc::new(Mutex::new(OutputGuard::new(OutputGuardLimits::from_settings(&crate::settings::Settings::default())))),
+            bell_policy: Arc::new(Mutex::new(BellPolicy::default())),
+            reset_on_binary_output: Arc::new(AtomicBool::new(false)),
+        };
+
+        (manager, output_receiver, command_event_receiver)
+    }
+
+    pub fn create_terminal(
+        &self,
+        size: TerminalSize,
+        options: &ShellOptions,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let shell_path = options.resolve_shell()?;
+        let work_dir = options.resolve_working_dir()?;
+
+        let injected = if !options.no_shell_integration && crate::settings::load_settings().unwrap_or_default().shell_integration_injection {
+            crate::shell_integration::inject_shell_integration(options, &shell_path).unwrap_or_else(|e| {
+                log::warn!("Failed to set up shell integration: {}", e);
+                None
+            })
+        } else {
+            None
+        };
+        let options = injected.as_ref().unwrap_or(options);
+
+        let session_id = self.pty_manager
+            .lock()
+            .unwrap()
+            .create_session(size.clone(), options)?;
+
+        // Initialize shell hooks for this session
+        self.shell_hooks
+            .lock()
+            .unwrap()
+            .create_session_hooks(session_id.clone(), &shell_path, work_dir);
+        // Initialize search index
+        self.search_index.lock().unwrap().create_session(session_id.clone());
+
+        let (max_lines, max_bytes) = *self.scrollback_limits.lock().unwrap();
+        let terminal = Terminal::new(session_id.clone(), size, max_lines, max_bytes);
+        self.terminals
+            .lock()
+            .unwrap()
+            .insert(session_id.clone(), terminal);
+
+        Ok(session_id)
+    }
+
+    pub fn write_to_terminal(
+        &self,
+        terminal_id: &str,
+        data: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.pty_manager
+            .lock()
+            .unwrap()
+            .write_to_session(terminal_id, data)
+    }
+
+    /// Screen rows written since the last call for this terminal.
+    pub fn take_dirty_rows(&self, terminal_id: &str) -> Result<Vec<usize>, String> {
+        self.terminals
+            .lock()
+            .unwrap()
+            .get_mut(terminal_id)
+            .map(|terminal| terminal.grid.take_dirty_rows())
+            .ok_or_else(|| format!("Terminal {} not found", terminal_id))
+    }
+
+    pub fn get_grid_diff(&self, terminal_id: &str, since_version: u64) -> Result<GridDiff, String> {
+        self.terminals
+            .lock()
+            .unwrap()
+            .get(terminal_id)
+            .map(|terminal| terminal.grid_diff(since_version))
+            .ok_or_else(|| format!("Terminal {} not found", terminal_id))
+    }
+
+    pub fn set_session_encoding(&self, terminal_id: &str, encoding: &str) -> Result<&'static str, String> {
+        self.pty_manager.lock().unwrap().set_session_encoding(terminal_id, encoding)
+    }
+
+    /// Writes pasted text so embedded newlines can't run commands early: wrapped in
+    /// paste brackets when the program asked for them, otherwise optionally with
+    /// trailing newlines removed.
+    pub fn paste_to_terminal(
+        &self,
+        terminal_id: &str,
+        text: &str,
+        strip_trailing_newlines: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let bracketed = self
+            .terminals
+            .lock()
+            .unwrap()
+            .get(terminal_id)
+            .map(|terminal| terminal.bracketed_paste)
+            .unwrap_or(false);
+
+        let data = if bracketed {
+            // A pasted end marker would let the rest of the text escape the bracket
+            let sanitized = text.replace("\x1b[200~", "").replace("\x1b[201~", "");
+            format!("\x1b[200~{}\x1b[201~", sanitized)
+        } else if strip_trailing_newlines {
+            text.trim_end_matches(['\r', '\n']).to_string()
+        } else {
+            text.to_string()
+        };
+
+        self.write_to_terminal(terminal_id, &data)
+    }
+
+    /// Encodes a mouse event for the program's active reporting mode. Returns
+    /// false when reporting is off and the event was dropped.
+    pub fn send_mouse_event(
+        &self,
+        terminal_id: &str,
+        button: MouseButton,
+        action: MouseAction,
+        row: u16,
+        col: u16,
+        modifiers: &MouseModifiers,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let encoded = self
+            .terminals
+            .lock()
+            .unwrap()
+            .get(terminal_id)
+            .ok_or("Terminal not found")?
+            .mouse
+            .encode(button, action, row, col, modifiers);
+
+        match encoded {
+            Some(bytes) => {
+                self.pty_manager.lock().unwrap().write_bytes_to_session(terminal_id, &bytes)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn resize_terminal(
+        &self,
+        terminal_id: &str,
+        size: TerminalSize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(terminal) = self.terminals.lock().unwrap().get_mut(terminal_id) {
+            terminal.resize(size.clone());
+        }
+        // Index lines are logical (unwrapped), so a reflow only needs evicted space reclaimed
+        self.search_index.lock().unwrap().compact(terminal_id);
+        if let Some(recorder) = self.recordings.lock().unwrap().get_mut(terminal_id) {
+            recorder.resize(&size);
+        }
+
+        self.pty_manager
+            .lock()
+            .unwrap()
+            .resize_session(terminal_id, size)
+    }
+
+    pub fn close_terminal(&self, terminal_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.stop_recording(terminal_id);
+        self.stop_playback(terminal_id);
+        self.terminals.lock().unwrap().remove(terminal_id);
+        self.shell_hooks.lock().unwrap().remove_session(terminal_id);
+        self.search_index.lock().unwrap().remove_session(terminal_id);
+        self.output_guard.lock().unwrap().remove_session(terminal_id);
+        self.pty_manager
+            .lock()
+            .unwrap()
+            .close_session(terminal_id)
+    }
+
+    pub fn set_output_guard_limits(&self, limits: OutputGuardLimits) {
+        self.output_guard.lock().unwrap().set_limits(limits);
+    }
+
+    /// Pass PTY output through the large-output guard, after `process_output`
+    /// has fed it to the model. Returns the output to emit to the frontend now,
+    /// if any, and a warning when the session was just paused.
+    pub fn guard_output(&self, output: TerminalOutput) -> (Option<TerminalOutput>, Option<LargeOutputWarning>) {
+        let full_screen = self.terminals
+            .lock()
+            .unwrap()
+            .get(&output.session_id)
+            .is_some_and(|terminal| terminal.in_alternate_screen());
+        let at_prompt = self.is_ready_for_input(&output.session_id);
+        let command = self.shell_hooks.lock().unwrap().current_command(&output.session_id);
+        let verdict = self.output_guard.lock().unwrap().inspect(
+            &output.session_id,
+            &output.data,
+            command.as_deref(),
+            full_screen,
+            at_prompt,
+        );
+        match verdict {
+            GuardVerdict::Pass => (Some(output), None),
+            GuardVerdict::Hold => (None, None),
+            GuardVerdict::Pause(warning) => (None, Some(warning)),
+            GuardVerdict::Resume(data) => (Some(TerminalOutput { session_id: output.session_id, data }), None),
+        }
+    }
+
+    pub fn take_binary_output_warning(&self, terminal_id: &str) -> bool {
+        self.terminals
+            .lock()
+            .unwrap()
+            .get_mut(terminal_id)
+            .is_some_and(|terminal| terminal.take_binary_warning())
+    }
+
+    pub fn set_reset_on_binary_output(&self, reset: bool) {
+        self.reset_on_binary_output.store(reset, Ordering::Relaxed);
+    }
+
+    pub fn reset_on_binary_output(&self) -> bool {
+        self.reset_on_binary_output.load(Ordering::Relaxed)
+    }
+
+    pub fn take_bell(&self, terminal_id: &str) -> bool {
+        self.terminals
+            .lock()
+            .unwrap()
+            .get_mut(terminal_id)
+            .is_some_and(|terminal| terminal.take_bell())
+    }
+
+    pub fn set_bell_policy(&self, policy: BellPolicy) {
+        *self.bell_policy.lock().unwrap() = policy;
+    }
+
+    /// Route a bell `terminal_id` rang through the bell policy.
+    pub fn bell_actions(&self, terminal_id: &str, reduced_motion: bool) -> BellActions {
+        let policy = *self.bell_policy.lock().unwrap();
+        self.terminals
+            .lock()
+            .unwrap()
+            .get_mut(terminal_id)
+            .map(|terminal| terminal.bell_actions(policy, reduced_motion))
+            .unwrap_or_default()
+    }
+
+    /// Reset a terminal left in a bad state by binary output or a crashed TUI.
+    /// The model is reset here; the caller emits the returned output so the
+    /// frontend resets too.
+    pub fn reset_terminal(&self, terminal_id: &str) -> Result<TerminalOutput, String> {
+        let mut terminals = self.terminals.lock().unwrap();
+        let terminal = terminals.get_mut(terminal_id).ok_or_else(|| format!("Terminal {} not found", terminal_id))?;
+        terminal.reset();
+        let data = terminal_reset_sequence(terminal.size.cols as usize);
+        Ok(TerminalOutput { session_id: terminal_id.to_string(), data })
+    }
+
+    /// The user's answer to a `large-output-warning`; returns output to show now.
+    pub fn resolve_large_output(&self, terminal_id: &str, action: LargeOutputAction) -> Result<Option<TerminalOutput>, String> {
+        let data = self.output_guard.lock().unwrap().resolve(terminal_id, action)?;
+        Ok((!data.is_empty()).then(|| TerminalOutput { session_id: terminal_id.to_string(), data }))
+    }
+
+    /// Returns the terminal's new working directory when this output changed it.
+    pub fn process_output(&self, output: TerminalOutput) -> Option<WorkingDirectory> {
+        if let Some(recorder) = self.recordings.lock().unwrap().get_mut(&output.session_id) {
+            recorder.output(&output.data);
+        }
+
+        // Append to search index
+        self.search_index
+            .lock()
+            .unwrap()
+            .append_output(&output.session_id, &output.data);
+
+        // Process output for terminal display
+        let mut new_cwd = None;
+        let mut responses = Vec::new();
+        let mut marks = Vec::new();
+        if let Some(terminal) = self.terminals
+            .lock()
+            .unwrap()
+            .get_mut(&output.session_id)
+        {
+            let previous = terminal.working_directory.clone();
+            terminal.process_output(&output.data);
+            if terminal.working_directory != previous {
+                new_cwd = terminal.working_directory.clone();
+            }
+            responses = terminal.parser.take_responses();
+            marks = terminal.take_integration_marks();
+        }
+
+        // Process output with shell hooks for command tracking. This runs after
+        // the grid so commands follow the shell's OSC 133 marks when it sends them.
+        let command_events = {
+            let mut shell_hooks = self.shell_hooks.lock().unwrap();
+            for mark in marks {
+                shell_hooks.handle_mark(&output.session_id, mark);
+            }
+            shell_hooks.process_output(&output.session_id, &output.data)
+        };
+        for event in command_events {
+            let _ = self.command_event_sender.send(event);
+        }
+        // Answer device status and mode queries from the program
+        if !responses.is_empty() {
+            if let Err(e) = self.pty_manager.lock().unwrap().write_bytes_to_session(&output.session_id, &responses) {
+                log::warn!("Failed to answer terminal query: {}", e);
+            }
+        }
+        if let Some(cwd) = new_cwd.as_ref().filter(|cwd| !cwd.remote) {
+            self.shell_hooks.lock().unwrap().set_working_directory(&output.session_id, cwd.path.clone());
+        }
+
+        // A rerun queued while a command was running goes out at the next prompt
+        if self.is_ready_for_input(&output.session_id) {
+            let rerun = self.terminals.lock().unwrap().get_mut(&output.session_id).and_then(|terminal| terminal.queued_rerun.take());
+            if let Some(rerun) = rerun {
+                if let Err(e) = self.send_rerun(&output.session_id, &rerun) {
+                    log::warn!("Failed to send queued rerun to terminal {}: {}", output.session_id, e);
+                }
+            }
+        }
+        new_cwd
+    }
+
+    pub fn get_reported_working_directory(&self, terminal_id: &str) -> Option<WorkingDirectory> {
+        self.terminals.lock().unwrap().get(terminal_id).and_then(|t| t.working_directory.clone())
+    }
+
+    /// The shell's live local cwd: OSC 7 when the shell reports it, otherwise what
+    /// the prompt parser last saw (with `~` expanded). None while the shell is remote.
+    pub fn get_working_directory(&self, terminal_id: &str) -> Option<String> {
+        if let Some(cwd) = self.get_reported_working_directory(terminal_id) {
+            return (!cwd.remote).then_some(cwd.path);
+        }
+        let cwd = self.shell_hooks.lock().unwrap().get_working_directory(terminal_id)?;
+        match cwd.strip_prefix('~') {
+            Some(rest) => std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")).ok().map(|home| format!("{}{}", home, rest)),
+            None if std::path::Path::new(&cwd).is_absolute() => Some(cwd),
+            None => None,
+        }
+    }
+
+    pub fn get_shell_pid(&self, terminal_id: &str) -> Option<u32> {
         self.pty_manager
             .lock()
             .unwrap()
-            .resize_session(terminal_id, size)
+            .get_session_pid(terminal_id)
+    }
+
+    pub fn get_command_blocks(&self, terminal_id: &str) -> Option<Vec<CommandBlock>> {
+        self.terminals
+            .lock()
+            .unwrap()
+            .get(terminal_id)
+            .map(|terminal| terminal.command_blocks())
+    }
+
+    pub fn get_last_command_duration(&self, terminal_id: &str) -> Result<Option<CommandDuration>, String> {
+        self.terminals
+            .lock()
+            .unwrap()
+            .get(terminal_id)
+            .map(|terminal| terminal.last_command_duration())
+            .ok_or_else(|| format!("Terminal {} not found", terminal_id))
+    }
+
+    pub fn get_block_output(&self, block_id: &str) -> Option<String> {
+        self.terminals
+            .lock()
+            .unwrap()
+            .values()
+            .find_map(|terminal| {
+                terminal
+                    .blocks
+                    .iter()
+                    .find(|block| block.id == block_id)
+                    .map(|block| terminal.block_output(block))
+            })
+    }
+
+    pub fn get_block_with_output(&self, block_id: &str) -> Option<(CommandBlock, String)> {
+        self.terminals
+            .lock()
+            .unwrap()
+            .values()
+            .find_map(|terminal| {
+                terminal
+                    .blocks
+                    .iter()
+                    .find(|block| block.id == block_id)
+                    .map(|block| (block.clone(), terminal.block_output(block)))
+            })
     }
 
-    pub fn close_terminal(&self, terminal_id: &str) -> Result<(), Box<dyn std::error::Error>> {
-        self.terminals.lock().unwrap().remove(terminal_id);
-        self.shell_hooks.lock().unwrap().remove_session(terminal_id);
-        self.search_index.lock().unwrap().remove_session(terminal_id);
-        self.pty_manager
+    pub fn get_copyable_block_output(&self, block_id: &str) -> Option<String> {
+        self.terminals
             .lock()
             .unwrap()
-            .close_session(terminal_id)
+            .values()
+            .find_map(|terminal| {
+                let index = terminal.blocks.iter().position(|block| block.id == block_id)?;
+                Some(terminal.copyable_output(index))
+            })
     }
 
-    pub fn process_output(&self, output: TerminalOutput) {
-        // Process output with shell hooks for command tracking
-        self.shell_hooks
+    /// The last command run in a terminal and its copyable output.
+    pub fn get_last_command_output(&self, terminal_id: &str) -> Option<(String, String)> {
+        let terminals = self.terminals.lock().unwrap();
+        let terminal = terminals.get(terminal_id)?;
+        let index = terminal.last_command_block()?;
+        Some((terminal.blocks[index].command.trim().to_string(), terminal.copyable_output(index)))
+    }
+
+    /// Record the terminal's output (and resizes) to an asciinema v2 file until stopped.
+    pub fn start_recording(&self, terminal_id: &str, path: &str) -> Result<(), String> {
+        let size = self
+            .terminals
             .lock()
             .unwrap()
-            .process_output(&output.session_id, &output.data);
+            .get(terminal_id)
+            .map(|terminal| terminal.size.clone())
+            .ok_or_else(|| format!("Terminal {} not found", terminal_id))?;
+        let mut recordings = self.recordings.lock().unwrap();
+        if recordings.contains_key(terminal_id) {
+            return Err(format!("Terminal {} is already being recorded", terminal_id));
+        }
+        recordings.insert(terminal_id.to_string(), CastRecorder::create(terminal_id, path, &size)?);
+        Ok(())
+    }
 
-        // Append to search index
-        self.search_index
+    pub fn stop_recording(&self, terminal_id: &str) -> Option<RecordingSummary> {
+        self.recordings.lock().unwrap().remove(terminal_id).map(CastRecorder::finish)
+    }
+
+    /// A display-only terminal (no PTY or shell hooks) for replaying a recording.
+    /// Returns its id and the flag that cancels the playback.
+    pub fn create_playback_terminal(&self, size: TerminalSize) -> (String, Arc<AtomicBool>) {
+        let terminal_id = format!("playback-{}", uuid::Uuid::new_v4());
+        let (max_lines, max_bytes) = *self.scrollback_limits.lock().unwrap();
+        self.search_index.lock().unwrap().create_session(terminal_id.clone());
+        self.terminals
             .lock()
             .unwrap()
-            .append_output(&output.session_id, &output.data);
+            .insert(terminal_id.clone(), Terminal::new(terminal_id.clone(), size, max_lines, max_bytes));
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.playbacks.lock().unwrap().insert(terminal_id.clone(), cancel.clone());
+        (terminal_id, cancel)
+    }
 
-        // Process output for terminal display
-        if let Some(terminal) = self.terminals
+    pub fn feed_playback(&self, terminal_id: &str, data: &str) {
+        self.search_index.lock().unwrap().append_output(terminal_id, data);
+        if let Some(terminal) = self.terminals.lock().unwrap().get_mut(terminal_id) {
+            terminal.process_output(data);
+            // A recording has no program left to answer
+            terminal.parser.take_responses();
+        }
+    }
+
+    pub fn resize_playback(&self, terminal_id: &str, size: TerminalSize) {
+        if let Some(terminal) = self.terminals.lock().unwrap().get_mut(terminal_id) {
+            terminal.resize(size);
+        }
+    }
+
+    pub fn finish_playback(&self, terminal_id: &str) {
+        self.playbacks.lock().unwrap().remove(terminal_id);
+    }
+
+    pub fn stop_playback(&self, terminal_id: &str) -> bool {
+        match self.playbacks.lock().unwrap().remove(terminal_id) {
+            Some(cancel) => {
+                cancel.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the rendered transcript and how many lines it covers.
+    pub fn render_transcript(&self, terminal_id: &str, format: crate::transcript::TranscriptFormat, scheme: Option<&crate::theme_manager::ColorScheme>) -> Option<(String, usize)> {
+        self.terminals
             .lock()
             .unwrap()
-            .get_mut(&output.session_id)
-        {
-            terminal.process_output(&output.data);
+            .get(terminal_id)
+            .map(|terminal| crate::transcript::render(terminal, format, scheme))
+    }
+
+    pub fn get_block(&self, block_id: &str) -> Option<CommandBlock> {
+        self.terminals
+            .lock()
+            .unwrap()
+            .values()
+            .find_map(|terminal| terminal.block(block_id))
+    }
+
+    /// Send a previous block's command line to `terminal_id`'s prompt, pressing
+    /// Enter when `execute` is set; otherwise it's left on the prompt to edit.
+    /// While a command is running it's queued for the next prompt instead,
+    /// replacing any rerun already queued there.
+    pub fn rerun_block(&self, terminal_id: &str, block_id: &str, execute: bool) -> Result<RerunStatus, String> {
+        let rerun = {
+            let terminals = self.terminals.lock().unwrap();
+            let terminal = terminals.get(terminal_id).ok_or_else(|| format!("Terminal {} not found", terminal_id))?;
+            let block = terminal
+                .blocks
+                .iter()
+                .find(|b| b.id == block_id)
+                .ok_or_else(|| format!("Command block {} not found in terminal {}", block_id, terminal_id))?;
+            let command = block.command.trim().to_string();
+            if command.is_empty() {
+                return Err(format!("Command block {} has no command", block_id));
+            }
+            QueuedRerun { command, execute }
+        };
+        if self.is_ready_for_input(terminal_id) {
+            self.send_rerun(terminal_id, &rerun)?;
+            return Ok(RerunStatus::Sent);
+        }
+        if let Some(terminal) = self.terminals.lock().unwrap().get_mut(terminal_id) {
+            terminal.queued_rerun = Some(rerun);
+        }
+        Ok(RerunStatus::Queued)
+    }
+
+    fn send_rerun(&self, terminal_id: &str, rerun: &QueuedRerun) -> Result<(), String> {
+        self.paste_to_terminal(terminal_id, &rerun.command, true).map_err(|e| e.to_string())?;
+        if rerun.execute {
+            self.write_to_terminal(terminal_id, "\r").map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    pub fn set_block_folded(&self, block_id: &str, folded: bool) -> bool {
+        self.terminals
+            .lock()
+            .unwrap()
+            .values_mut()
+            .any(|terminal| terminal.set_block_folded(block_id, folded))
+    }
+
+    pub fn get_visible_scrollback(&self, terminal_id: &str, limit: Option<usize>) -> Option<Vec<VisibleLine>> {
+        self.terminals
+            .lock()
+            .unwrap()
+            .get(terminal_id)
+            .map(|terminal| {
+                let lines = terminal.visible_scrollback();
+                let start = limit.map(|l| lines.len().saturating_sub(l)).unwrap_or(0);
+                lines[start..].to_vec()
+            })
+    }
+
+    pub fn get_scrollback_len(&self, terminal_id: &str) -> Result<usize, String> {
+        self.terminals
+            .lock()
+            .unwrap()
+            .get(terminal_id)
+            .map(|terminal| terminal.scrollback.row_count())
+            .ok_or_else(|| format!("Terminal {} not found", terminal_id))
+    }
+
+    pub fn get_scrollback_rows(&self, terminal_id: &str, start: usize, count: usize) -> Result<Vec<ScrollbackRow>, String> {
+        self.terminals
+            .lock()
+            .unwrap()
+            .get(terminal_id)
+            .map(|terminal| terminal.scrollback.rows(start, count))
+            .ok_or_else(|| format!("Terminal {} not found", terminal_id))
+    }
+
+    pub fn get_accessible_snapshot(&self, terminal_id: &str) -> Option<AccessibleSnapshot> {
+        self.terminals
+            .lock()
+            .unwrap()
+            .get(terminal_id)
+            .map(|terminal| terminal.accessible_snapshot())
+    }
+
+    pub fn start_selection(&self, terminal_id: &str, row: u16, col: u16, mode: SelectionMode) -> Result<(), String> {
+        self.terminals
+            .lock()
+            .unwrap()
+            .get_mut(terminal_id)
+            .ok_or_else(|| format!("Terminal {} not found", terminal_id))?
+            .start_selection(row, col, mode);
+        Ok(())
+    }
+
+    pub fn update_selection(&self, terminal_id: &str, row: u16, col: u16) -> Result<(), String> {
+        let updated = self.terminals
+            .lock()
+            .unwrap()
+            .get_mut(terminal_id)
+            .ok_or_else(|| format!("Terminal {} not found", terminal_id))?
+            .update_selection(row, col);
+        if updated { Ok(()) } else { Err("No selection in progress".to_string()) }
+    }
+
+    /// Run one of the click-selection functions on a terminal.
+    pub fn select_at(&self, terminal_id: &str, select: impl FnOnce(&mut Terminal) -> Option<SelectedRange>) -> Result<Option<SelectedRange>, String> {
+        let mut terminals = self.terminals.lock().unwrap();
+        let terminal = terminals.get_mut(terminal_id).ok_or_else(|| format!("Terminal {} not found", terminal_id))?;
+        Ok(select(terminal))
+    }
+
+    pub fn detect_clickable_regions(&self, terminal_id: &str, row: u16, file_pattern: &regex::Regex) -> Result<Vec<Clickable>, String> {
+        self.terminals
+            .lock()
+            .unwrap()
+            .get(terminal_id)
+            .map(|terminal| terminal.grid.clickable_regions(row as usize, file_pattern))
+            .ok_or_else(|| format!("Terminal {} not found", terminal_id))
+    }
+
+    pub fn clear_selection(&self, terminal_id: &str) {
+        if let Some(terminal) = self.terminals.lock().unwrap().get_mut(terminal_id) {
+            terminal.selection = None;
         }
     }
 
+    pub fn get_selection_text(&self, terminal_id: &str) -> Option<String> {
+        self.terminals
+            .lock()
+            .unwrap()
+            .get(terminal_id)
+            .and_then(|terminal| terminal.get_selection_text())
+    }
+
     pub fn get_terminal_state(&self, terminal_id: &str) -> Option<TerminalGrid> {
         self.terminals
             .lock()
             .unwrap()
             .get(terminal_id)
-            .map(|terminal| terminal.grid.clone())
+            .map(|terminal| {
+                let mut grid = terminal.grid.clone();
+                grid.scrollback_usage = terminal.scrollback.usage();
+                grid
+            })
+    }
+
+    /// Runs a program to completion outside any terminal session. Pipes are used
+    /// instead of a PTY so stdout and stderr stay separate, and nothing is
+    /// registered with the manager. The child is killed if `timeout` elapses.
+    pub async fn run_captured(
+        command: &str,
+        args: &[String],
+        cwd: Option<String>,
+        env: &HashMap<String, String>,
+        timeout: std::time::Duration,
+    ) -> Result<CapturedOutput, String> {
+        let working_dir = ShellOptions { cwd, ..Default::default() }.resolve_working_dir()?;
+        let child = tokio::process::Command::new(command)
+            .args(args)
+            .current_dir(working_dir)
+            .envs(env)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to start {}: {}", command, e))?;
+
+        let started = std::time::Instant::now();
+        // Dropping the wait future on timeout drops the child, which kills it
+        let output = tokio::time::timeout(timeout, child.wait_with_output())
+            .await
+            .map_err(|_| format!("{} timed out after {}ms and was killed", command, timeout.as_millis()))?
+            .map_err(|e| e.to_string())?;
+
+        Ok(CapturedOutput {
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            exit_code: output.status.code(),
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    pub fn output_throttle(&self) -> Arc<OutputThrottle> {
+        self.pty_manager.lock().unwrap().output_throttle()
+    }
+
+    pub fn set_scrollback_limits(&self, max_lines: usize, max_bytes: usize) {
+        *self.scrollback_limits.lock().unwrap() = (max_lines, max_bytes);
+        for terminal in self.terminals.lock().unwrap().values_mut() {
+            terminal.set_scrollback_limits(max_lines, max_bytes);
+        }
     }
 
     // Shell hooks integration methods
@@ -395,11 +2924,21 @@ impl TerminalManager {
             .get_command_history(terminal_id, limit)
     }
 
-    pub fn get_command_suggestions(&self, terminal_id: &str, partial_command: &str) -> Option<Vec<crate::shell_hooks::CommandSuggestion>> {
+    pub fn get_all_command_history(&self) -> Vec<crate::shell_hooks::Command> {
+        self.shell_hooks.lock().unwrap().get_all_command_history()
+    }
+
+    pub fn get_command_suggestions(
+        &self,
+        terminal_id: &str,
+        partial_command: &str,
+        cwd: Option<&std::path::Path>,
+        project: Option<&crate::dev_tools::ProjectInfo>,
+    ) -> Option<Vec<crate::shell_hooks::CommandSuggestion>> {
         self.shell_hooks
             .lock()
             .unwrap()
-            .get_command_suggestions(terminal_id, partial_command)
+            .get_command_suggestions(terminal_id, partial_command, cwd, project)
     }
 
     pub fn handle_tab_completion(&self, terminal_id: &str, current_line: &str, cursor_pos: usize) -> Option<Vec<String>> {
@@ -409,6 +2948,32 @@ impl TerminalManager {
             .handle_tab_completion(terminal_id, current_line, cursor_pos)
     }
 
+    pub fn handle_tab_completion_items(&self, terminal_id: &str, current_line: &str, cursor_pos: usize) -> Option<Vec<crate::completions::CompletionItem>> {
+        self.shell_hooks
+            .lock()
+            .unwrap()
+            .handle_tab_completion_items(terminal_id, current_line, cursor_pos)
+    }
+
+    pub fn last_block_id(&self, terminal_id: &str) -> Option<String> {
+        self.terminals.lock().unwrap().get(terminal_id)?.blocks.last().map(|block| block.id.clone())
+    }
+
+    /// Whether the shell is waiting for input. Shell integration (OSC 133) is
+    /// authoritative when present; otherwise fall back to prompt-line parsing.
+    pub fn is_ready_for_input(&self, terminal_id: &str) -> bool {
+        let phase = self
+            .terminals
+            .lock()
+            .unwrap()
+            .get(terminal_id)
+            .and_then(|terminal| terminal.blocks.last().map(|block| block.phase.clone()));
+        match phase {
+            Some(phase) => matches!(phase, BlockPhase::Prompt | BlockPhase::Command),
+            None => self.is_at_prompt(terminal_id),
+        }
+    }
+
     pub fn is_at_prompt(&self, terminal_id: &str) -> bool {
         self.shell_hooks
             .lock()
@@ -417,11 +2982,40 @@ impl TerminalManager {
     }
 
     pub fn get_current_prompt(&self, terminal_id: &str) -> Option<crate::shell_hooks::PromptInfo> {
-        self.shell_hooks
+        let mut prompt = self.shell_hooks
             .lock()
             .unwrap()
             .get_current_prompt(terminal_id)
-            .cloned()
+            .cloned()?;
+        prompt.last_exit_code = self.get_last_exit_code(terminal_id).ok().flatten();
+        Some(prompt)
+    }
+
+    /// Records the running command's exit code for a shell that reports it
+    /// through a hook instead of an OSC 133 D mark.
+    pub fn record_exit_code(&self, terminal_id: &str, exit_code: i32) -> Result<(), String> {
+        if self.shell_hooks.lock().unwrap().record_exit_code(terminal_id, exit_code) {
+            Ok(())
+        } else {
+            Err(format!("Terminal {} not found", terminal_id))
+        }
+    }
+
+    /// Exit code of the session's last finished command. Shell integration
+    /// (OSC 133 D) is authoritative when it reported one; otherwise the shell
+    /// hooks' record is used, and `None` means it's unknown.
+    pub fn get_last_exit_code(&self, terminal_id: &str) -> Result<Option<i32>, String> {
+        let from_blocks = self
+            .terminals
+            .lock()
+            .unwrap()
+            .get(terminal_id)
+            .ok_or_else(|| format!("Terminal {} not found", terminal_id))?
+            .last_exit_code();
+        Ok(match from_blocks {
+            Some(Some(exit_code)) => Some(exit_code),
+            _ => self.shell_hooks.lock().unwrap().last_exit_code(terminal_id),
+        })
     }
 
     pub fn search_history(&self, terminal_id: &str, query: &str) -> Option<Vec<crate::shell_hooks::Command>> {
@@ -438,6 +3032,13 @@ impl TerminalManager {
             .search(terminal_id, query, case_sensitive, use_regex, limit)
     }
 
+    pub fn grep_scrollback(&self, terminal_id: &str, pattern: &str, options: &GrepOptions) -> Option<ScrollGrepResult> {
+        self.search_index
+            .lock()
+            .unwrap()
+            .grep(terminal_id, pattern, options)
+    }
+
     pub fn get_scrollback_context(&self, terminal_id: &str, line_index: usize, before: usize, after: usize) -> Option<Vec<ContextLine>> {
         self.search_index
             .lock()
@@ -445,3 +3046,472 @@ impl TerminalManager {
             .context(terminal_id, line_index, before, after)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terminal(rows: u16, cols: u16) -> Terminal {
+        Terminal::new("test".into(), TerminalSize { rows, cols, pixel_width: 0, pixel_height: 0 }, 1000, 1 << 20)
+    }
+
+    #[test]
+    fn soft_reset_keeps_the_cursor_and_frees_the_margins() {
+        let mut term = terminal(24, 80);
+        term.process_output("\x1b[5;20r\x1b[10;30H");
+        term.process_output("\x1b[!p");
+        assert_eq!((term.grid.cursor.row, term.grid.cursor.col), (9, 29));
+        assert_eq!((term.grid.scroll_top, term.grid.scroll_bottom), (0, 23));
+    }
+
+    #[test]
+    fn reset_restores_every_tracked_mode() {
+        let mut term = terminal(24, 80);
+        term.process_output("\x1b[12;40H");
+        term.process_output("\x1b[?1049h\x1b[?25l\x1b[?2004h\x1b[?1000h\x1b[?1004h\x1b[5 q\x1b[?2026h");
+        term.process_output("\x1b[3;10r\x1b[3g\x1b(0\x1b[1;31m");
+        term.reset();
+
+        let modes = term.modes();
+        assert!(modes.cursor_visible);
+        assert_eq!(modes.cursor_style, None);
+        assert!(!modes.alternate_screen);
+        assert!(!modes.bracketed_paste);
+        assert!(!modes.mouse_reporting);
+        assert!(!modes.focus_events);
+        assert!(!modes.synchronized_update);
+        assert_eq!((term.grid.scroll_top, term.grid.scroll_bottom), (0, 23));
+        assert!(term.grid.tab_stops[8] && term.grid.tab_stops[16]);
+        // Back on the primary screen with the cursor where the shell left it
+        assert_eq!((term.grid.cursor.row, term.grid.cursor.col), (11, 39));
+
+        // ASCII charset and plain attributes again
+        term.process_output("\x1b[1;1Hq");
+        let cell = &term.grid.rows[0][0];
+        assert_eq!(cell.character, 'q');
+        assert!(!cell.attributes.bold);
+        assert_eq!(cell.attributes.fg_color, None);
+    }
+
+    fn screen(term: &Terminal) -> Vec<String> {
+        (0..term.grid.rows.len()).map(|row| term.grid.row_text(row, 0, usize::MAX)).collect()
+    }
+
+    #[test]
+    fn line_insert_delete_and_scrolling_stay_inside_the_scroll_region() {
+        let mut term = terminal(6, 10);
+        term.process_output("1\r\n2\r\n3\r\n4\r\n5\r\n6");
+        term.process_output("\x1b[2;5r");
+
+        // Inserting at row 3 pushes 5 out of the region; 6 below it stays put
+        term.process_output("\x1b[3;1H\x1b[L");
+        assert_eq!(screen(&term), ["1", "2", "", "3", "4", "6"]);
+        term.process_output("\x1b[M");
+        assert_eq!(screen(&term), ["1", "2", "3", "4", "", "6"]);
+        term.process_output("\x1b[2L");
+        assert_eq!(screen(&term), ["1", "2", "", "", "3", "6"]);
+        term.process_output("\x1b[9M");
+        assert_eq!(screen(&term), ["1", "2", "", "", "", "6"]);
+
+        term.process_output("\x1b[2;1Ha\r\nb\r\nc\r\nd");
+        term.process_output("\x1b[S");
+        assert_eq!(screen(&term), ["1", "b", "c", "d", "", "6"]);
+        term.process_output("\x1b[2T");
+        assert_eq!(screen(&term), ["1", "", "", "b", "c", "6"]);
+
+        // A line feed at the bottom margin scrolls only the region
+        term.process_output("\x1b[5;1H\n");
+        assert_eq!(screen(&term), ["1", "", "b", "c", "", "6"]);
+        assert_eq!(term.grid.cursor.row, 4);
+
+        // Outside the margins insert and delete do nothing
+        term.process_output("\x1b[6;1H\x1b[L\x1b[1;1H\x1b[M");
+        assert_eq!(screen(&term), ["1", "", "b", "c", "", "6"]);
+    }
+
+    #[test]
+    fn a_tab_advances_to_the_next_custom_stop() {
+        let mut term = terminal(24, 80);
+        term.process_output("\x1b[3g\x1b[1;21H\x1bH");
+        term.process_output("\x1b[1;6H\t");
+        assert_eq!(term.grid.cursor.col, 20);
+        // With no stop further right a tab goes to the last column
+        term.process_output("\t");
+        assert_eq!(term.grid.cursor.col, 79);
+    }
+
+    #[test]
+    fn stops_default_to_every_eight_columns_and_clear_one_at_a_time() {
+        let mut term = terminal(24, 80);
+        term.process_output("\x1b[1;6H\t");
+        assert_eq!(term.grid.cursor.col, 8);
+        term.process_output("\x1b[2I");
+        assert_eq!(term.grid.cursor.col, 24);
+
+        // TBC 0 clears only the stop under the cursor
+        term.process_output("\x1b[1;17H\x1b[g\x1b[1;1H\t\t");
+        assert_eq!(term.grid.cursor.col, 24);
+        term.process_output("ab\tc");
+        assert_eq!(term.grid.row_text(0, 0, usize::MAX), format!("{}ab{}c", " ".repeat(24), " ".repeat(6)));
+    }
+
+    #[test]
+    fn the_alternate_screen_leaves_the_primary_screen_and_scrollback_intact() {
+        let mut term = terminal(4, 20);
+        term.process_output("$ ls\r\nCargo.toml\r\n$ vim");
+        let history = term.scrollback.next_index();
+        let (row, col) = (term.grid.cursor.row, term.grid.cursor.col);
+
+        term.process_output("\x1b[?1049h");
+        assert!(term.in_alternate_screen());
+        assert_eq!(screen(&term), ["", "", "", ""]);
+        // Enough lines to scroll the alternate screen several times over
+        term.process_output("\x1b[H~\r\n~\r\n~\r\n~\r\n~\r\n~\r\n~\r\n\x1b[1;1Hfn main() {}");
+        assert_eq!(term.grid.row_text(0, 0, usize::MAX), "fn main() {}");
+
+        term.process_output("\x1b[?1049l");
+        assert!(!term.in_alternate_screen());
+        assert_eq!(screen(&term), ["$ ls", "Cargo.toml", "$ vim", ""]);
+        assert_eq!((term.grid.cursor.row, term.grid.cursor.col), (row, col));
+        assert_eq!(term.scrollback.next_index(), history);
+    }
+
+    #[test]
+    fn wide_characters_take_two_columns_and_combining_marks_none() {
+        let mut term = terminal(3, 8);
+        term.process_output("a中b");
+        let widths: Vec<(char, u8)> = term.grid.rows[0][..4].iter().map(|cell| (cell.character, cell.width)).collect();
+        assert_eq!(widths, [('a', 1), ('中', 2), (' ', 0), ('b', 1)]);
+        assert_eq!(term.grid.cursor.col, 4);
+
+        // The accent joins the e before it without moving the cursor
+        term.process_output("e\u{301}");
+        assert_eq!((term.grid.rows[0][4].character, term.grid.rows[0][4].combining.as_str()), ('e', "\u{301}"));
+        assert_eq!(term.grid.cursor.col, 5);
+
+        // 日 fits in columns 5-6; 本 doesn't fit in column 7 and wraps whole
+        term.process_output("日本");
+        assert_eq!(screen(&term), ["a中be\u{301}日", "本", ""]);
+        assert_eq!(term.grid.rows[0][7].width, 1);
+        assert_eq!((term.grid.cursor.row, term.grid.cursor.col), (1, 2));
+
+        // Writing over the spacer half of 中 blanks the other half
+        term.process_output("\x1b[1;3Hx");
+        assert_eq!(term.grid.row_text(0, 0, usize::MAX), "a xbe\u{301}日");
+        assert_eq!(term.grid.rows[0][1].width, 1);
+    }
+
+    #[test]
+    fn one_backspace_steps_over_a_whole_emoji_cluster() {
+        let mut term = terminal(3, 20);
+        // A flag is two regional indicators; 👍🏽 is a thumb and a skin-tone modifier
+        term.process_output("$ 🇯🇵👍🏽");
+        assert_eq!((term.grid.rows[0][2].character, term.grid.rows[0][2].combining.as_str(), term.grid.rows[0][2].width), ('🇯', "🇵", 2));
+        assert_eq!((term.grid.rows[0][4].character, term.grid.rows[0][4].combining.as_str(), term.grid.rows[0][4].width), ('👍', "🏽", 2));
+        assert_eq!(term.grid.cursor.col, 6);
+
+        // How line editors echo a backspace: step left, then erase to the end of the line
+        term.process_output("\x08\x1b[K");
+        assert_eq!(term.grid.cursor.col, 4);
+        assert_eq!(term.grid.row_text(0, 0, usize::MAX), "$ 🇯🇵");
+        term.process_output("\x08\x1b[K");
+        assert_eq!(term.grid.cursor.col, 2);
+        assert_eq!(term.grid.row_text(0, 0, usize::MAX), "$");
+
+        // Moving right over a cluster skips its spacer cell too
+        term.process_output("🇯🇵x\x1b[1;3H\x1b[C");
+        assert_eq!(term.grid.cursor.col, 4);
+    }
+
+    #[test]
+    fn partial_line_erases_keep_the_other_side_of_the_cursor() {
+        let mut term = terminal(2, 10);
+        term.process_output("abcdefgh\x1b[1;4H\x1b[K");
+        assert_eq!(term.grid.row_text(0, 0, usize::MAX), "abc");
+        term.process_output("\x1b[2;1Habcdefgh\x1b[2;4H\x1b[1K");
+        assert_eq!(term.grid.row_text(1, 0, usize::MAX), "    efgh");
+
+        // An erase that starts on the second half of a wide character takes all of it
+        term.process_output("\x1b[1;1Hx中y\x1b[1;3H\x1b[K");
+        assert_eq!(term.grid.row_text(0, 0, usize::MAX), "x");
+    }
+
+    #[test]
+    fn linear_selection_runs_row_major_from_either_end() {
+        let mut term = terminal(4, 20);
+        term.process_output("ab中cd\r\nefghij\r\nklmnop");
+        assert_eq!(term.get_selection_text(), None);
+        assert!(!term.update_selection(1, 1));
+
+        term.start_selection(0, 4, SelectionMode::Linear);
+        assert!(term.update_selection(1, 2));
+        assert_eq!(term.get_selection_text().as_deref(), Some("cd\nefg"));
+        // Dragging back past the anchor selects the other way
+        term.update_selection(0, 1);
+        assert_eq!(term.get_selection_text().as_deref(), Some("b中c"));
+    }
+
+    #[test]
+    fn block_selection_takes_the_same_columns_from_every_row() {
+        let mut term = terminal(4, 20);
+        term.process_output("ab中cd\r\nefghij\r\nklmnop");
+
+        // Corners given bottom-right first; column 3 is the second half of 中, which comes whole
+        term.start_selection(2, 4, SelectionMode::Block);
+        term.update_selection(0, 3);
+        assert_eq!(term.get_selection_text().as_deref(), Some("中c\nhi\nno"));
+        term.update_selection(1, 0);
+        assert_eq!(term.get_selection_text().as_deref(), Some("efghi\nklmno"));
+    }
+
+    #[test]
+    fn writes_dirty_only_their_row_and_clears_dirty_every_row() {
+        let mut term = terminal(5, 20);
+        term.grid.take_dirty_rows();
+
+        term.process_output("\x1b[3;5Hx");
+        assert_eq!(term.grid.take_dirty_rows(), [2]);
+        // Taking the rows clears them, and cursor moves alone damage nothing
+        term.process_output("\x1b[1;1H");
+        assert!(term.grid.take_dirty_rows().is_empty());
+
+        term.process_output("\x1b[2J");
+        assert_eq!(term.grid.take_dirty_rows(), [0, 1, 2, 3, 4]);
+
+        // Scrolling a region damages exactly the rows inside it
+        term.process_output("\x1b[2;4r\x1b[S");
+        assert_eq!(term.grid.take_dirty_rows(), [1, 2, 3]);
+        term.process_output("\x1b[4;1H\x1b[K");
+        assert_eq!(term.grid.take_dirty_rows(), [3]);
+    }
+
+    #[test]
+    fn the_manager_hands_out_each_dirty_row_once() {
+        let manager = manager_with_session("s1");
+        manager.take_dirty_rows("s1").unwrap();
+        feed(&manager, "s1", "hello\r\nworld");
+        assert_eq!(manager.take_dirty_rows("s1").unwrap(), [0, 1]);
+        assert!(manager.take_dirty_rows("s1").unwrap().is_empty());
+        assert!(manager.take_dirty_rows("missing").is_err());
+    }
+
+    #[test]
+    fn a_full_screen_redraw_is_not_mistaken_for_binary_output() {
+        let mut term = terminal(24, 80);
+        term.process_output("\x1b[?1049h\x1b[?25l");
+        // A few hundred htop-style frames: cursor moves, colors, DEC box drawing via SO/SI
+        for frame in 0..300 {
+            let mut redraw = String::from("\x1b[H\x1b)0");
+            for row in 1..=24 {
+                redraw.push_str(&format!("\x1b[{};1H\x1b[1;3{}m\x0elqqqqk\x0f\x1b[0m {:>3}% \x1b[7m{}\x1b[27m\x1b[K", row, row % 8, (frame * row) % 100, "|".repeat(row % 20)));
+            }
+            term.process_output(&redraw);
+        }
+        assert!(!term.take_binary_warning());
+
+        // The same screen with raw binary in it is flagged
+        let garbage: String = (0..2048u32).map(|i| char::from_u32(i % 32).unwrap()).collect();
+        term.process_output(&garbage);
+        assert!(term.take_binary_warning());
+    }
+
+    #[test]
+    fn sgr_mouse_reports_a_left_click_press_and_release() {
+        let mut term = terminal(24, 80);
+        let plain = MouseModifiers::default();
+        // Nothing is reported until a program asks for it
+        assert_eq!(term.mouse.encode(MouseButton::Left, MouseAction::Press, 4, 9, &plain), None);
+
+        term.process_output("\x1b[?1000h\x1b[?1006h");
+        assert_eq!(term.mouse.encode(MouseButton::Left, MouseAction::Press, 4, 9, &plain), Some(b"\x1b[<0;10;5M".to_vec()));
+        assert_eq!(term.mouse.encode(MouseButton::Left, MouseAction::Release, 4, 9, &plain), Some(b"\x1b[<0;10;5m".to_vec()));
+        let ctrl = MouseModifiers { ctrl: true, ..Default::default() };
+        assert_eq!(term.mouse.encode(MouseButton::Right, MouseAction::Press, 0, 0, &ctrl), Some(b"\x1b[<18;1;1M".to_vec()));
+        // Normal tracking doesn't report plain motion
+        assert_eq!(term.mouse.encode(MouseButton::None, MouseAction::Move, 0, 0, &plain), None);
+
+        term.process_output("\x1b[?1000l");
+        assert_eq!(term.mouse.encode(MouseButton::Left, MouseAction::Press, 4, 9, &plain), None);
+    }
+
+    #[test]
+    fn legacy_mouse_encodings_follow_the_tracking_mode() {
+        let mut term = terminal(24, 80);
+        let plain = MouseModifiers::default();
+        term.process_output("\x1b[?1000h");
+        assert_eq!(term.mouse.encode(MouseButton::Left, MouseAction::Press, 4, 9, &plain), Some(vec![0x1b, b'[', b'M', 32, 42, 37]));
+        // Releases can't name the button, and wheel releases aren't sent
+        assert_eq!(term.mouse.encode(MouseButton::Left, MouseAction::Release, 4, 9, &plain), Some(vec![0x1b, b'[', b'M', 35, 42, 37]));
+        assert_eq!(term.mouse.encode(MouseButton::WheelUp, MouseAction::Release, 4, 9, &plain), None);
+        assert_eq!(term.mouse.encode(MouseButton::Left, MouseAction::Press, 0, 230, &plain), None);
+
+        term.process_output("\x1b[?1015h");
+        assert_eq!(term.mouse.encode(MouseButton::Left, MouseAction::Press, 0, 230, &plain), Some(b"\x1b[32;231;1M".to_vec()));
+
+        term.process_output("\x1b[?1015l\x1b[?9h");
+        assert_eq!(term.mouse.encode(MouseButton::Middle, MouseAction::Press, 1, 1, &plain), Some(vec![0x1b, b'[', b'M', 33, 34, 34]));
+        assert_eq!(term.mouse.encode(MouseButton::Middle, MouseAction::Release, 1, 1, &plain), None);
+    }
+
+    #[test]
+    fn status_and_mode_queries_get_exact_replies_in_order() {
+        let mut term = terminal(24, 80);
+        term.process_output("\x1b[5;10H\x1b[6n\x1b[5n\x1b[c\x1b[>c");
+        assert_eq!(term.parser.take_responses(), b"\x1b[5;10R\x1b[0n\x1b[?62;22c\x1b[>0;10;0c".to_vec());
+        assert!(term.parser.take_responses().is_empty());
+
+        // DECRQM: set, reset, and a mode we don't track
+        term.process_output("\x1b[?2004h\x1b[?2004$p\x1b[?1049$p\x1b[4$p");
+        assert_eq!(term.parser.take_responses(), b"\x1b[?2004;1$y\x1b[?1049;2$y\x1b[4;0$y".to_vec());
+
+        // The reported position follows the cursor, 1-based
+        term.process_output("\x1b[Hab\x1b[6n");
+        assert_eq!(term.parser.take_responses(), b"\x1b[1;3R".to_vec());
+    }
+
+    #[test]
+    fn a_command_is_timed_from_output_start_to_its_end() {
+        let mut term = terminal(24, 80);
+        term.apply_prompt_mark(PromptMark::PromptStart, 1_000);
+        term.apply_prompt_mark(PromptMark::CommandStart, 1_500);
+        term.process_output("cargo build");
+        // Typing time before C doesn't count
+        assert!(term.last_command_duration_at(2_500).is_none());
+        term.apply_prompt_mark(PromptMark::OutputStart, 3_000);
+
+        let running = term.last_command_duration_at(9_000).unwrap();
+        assert_eq!((running.duration_ms, running.running), (6_000, true));
+        assert_eq!(running.command, "cargo build");
+        assert_eq!(running.badge.as_deref(), Some("took 6.0s"));
+
+        term.apply_prompt_mark(PromptMark::CommandEnd(Some(0)), 5_500);
+        let finished = term.last_command_duration_at(60_000).unwrap();
+        assert_eq!((finished.duration_ms, finished.running), (2_500, false));
+        assert_eq!(finished.badge.as_deref(), Some("took 2.5s"));
+        assert_eq!(term.blocks[0].duration_ms, Some(2_500));
+    }
+
+    #[test]
+    fn without_an_output_mark_timing_starts_at_the_command_and_short_runs_get_no_badge() {
+        let mut term = terminal(24, 80);
+        term.apply_prompt_mark(PromptMark::PromptStart, 0);
+        term.apply_prompt_mark(PromptMark::CommandStart, 1_000);
+        term.apply_prompt_mark(PromptMark::CommandEnd(Some(1)), 1_400);
+        let duration = term.last_command_duration_at(10_000).unwrap();
+        assert_eq!((duration.duration_ms, duration.running, duration.badge), (400, false, None));
+
+        // A new prompt that's only being typed at leaves the last run in place
+        term.apply_prompt_mark(PromptMark::PromptStart, 2_000);
+        term.apply_prompt_mark(PromptMark::CommandStart, 2_100);
+        assert_eq!(term.last_command_duration_at(10_000).unwrap().block_id, term.blocks[0].id);
+    }
+
+    #[test]
+    fn durations_read_naturally_at_every_scale() {
+        assert_eq!(format_duration(1_500), "1.5s");
+        assert_eq!(format_duration(59_940), "59.9s");
+        assert_eq!(format_duration(125_000), "2m 5s");
+        assert_eq!(format_duration(7_260_000), "2h 1m");
+    }
+
+    fn manager_with_session(id: &str) -> TerminalManager {
+        let (manager, _, _) = TerminalManager::new();
+        manager.shell_hooks.lock().unwrap().create_session_hooks(id.to_string(), "/bin/bash", "/tmp".to_string());
+        manager.search_index.lock().unwrap().create_session(id.to_string());
+        manager.terminals.lock().unwrap().insert(id.to_string(), Terminal::new(id.to_string(), TerminalSize { rows: 24, cols: 80, pixel_width: 0, pixel_height: 0 }, 1000, 1 << 20));
+        manager
+    }
+
+    fn feed(manager: &TerminalManager, id: &str, data: &str) {
+        manager.process_output(TerminalOutput { session_id: id.to_string(), data: data.to_string() });
+    }
+
+    fn block_exit_codes(manager: &TerminalManager, id: &str) -> Vec<(BlockPhase, Option<i32>)> {
+        manager.terminals.lock().unwrap()[id].blocks.iter().map(|b| (b.phase.clone(), b.exit_code)).collect()
+    }
+
+    #[test]
+    fn live_output_reaches_scrollback_search_and_keeps_image_data() {
+        let manager = manager_with_session("s1");
+        feed(&manager, "s1", "compiling\r\nwarning: unused ");
+        feed(&manager, "s1", "variable `needle`\r\ndone\r\n");
+
+        let hits = manager.search_scrollback("s1", "needle", false, false, 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].line_content, "warning: unused variable `needle`");
+        assert!(manager.search_scrollback("s1", "haystack", false, false, 10).unwrap().is_empty());
+
+        let sixel = "\x1bPq#0;2;0;0;0#0~~-\x1b\\";
+        feed(&manager, "s1", sixel);
+        let terminals = manager.terminals.lock().unwrap();
+        match terminals["s1"].scrollback.entries.back() {
+            Some(ScrollbackEntry::Image { format, data, .. }) => {
+                assert_eq!(format, "sixel");
+                assert_eq!(data, &b"q#0;2;0;0;0#0~~-".to_vec());
+            }
+            other => panic!("expected an image entry, got {:?}", other),
+        }
+    }
+
+    const PROMPT: &str = "\x1b]133;A\x07user@host:~$ \x1b]133;B\x07";
+
+    #[test]
+    fn the_accessible_snapshot_is_plain_text_with_blocks_mapped_onto_it() {
+        let mut term = terminal(24, 80);
+        term.process_output(PROMPT);
+        term.process_output("ls --color\r\n\x1b]133;C\x07");
+        term.process_output("\x1b[1;34msrc\x1b[0m  \x1b[4;32mbuild.sh\x1b[0m\r\n\r\n\r\n  \r\n\x1b[7mREADME\x1b[27m\r\n");
+        term.process_output("\x1bPq\"1;1;64;32#0~~\x1b\\");
+        term.process_output("\x1b]133;D;0\x07");
+        term.process_output(PROMPT);
+
+        let snapshot = term.accessible_snapshot();
+        // Styling is gone, the blank run is one line and the sixel is described by its raster size
+        assert_eq!(snapshot.lines, ["user@host:~$ ls --color", "src  build.sh", "", "README", "[image: 64x32]", "user@host:~$"]);
+        assert_eq!((snapshot.cursor.row, snapshot.cursor.col), (6, 13));
+
+        let regions: Vec<_> = snapshot
+            .regions
+            .iter()
+            .map(|r| (r.kind, r.start_line, r.end_line, r.text.as_deref(), r.exit_code))
+            .collect();
+        assert_eq!(regions, [
+            (AccessibleRegionKind::Prompt, 0, 1, Some("user@host:~$"), None),
+            (AccessibleRegionKind::Command, 0, 1, Some("ls --color"), None),
+            (AccessibleRegionKind::Output, 1, 5, None, Some(0)),
+            (AccessibleRegionKind::Prompt, 5, 6, Some("user@host:~$"), None),
+        ]);
+    }
+
+    #[test]
+    fn an_exit_code_mark_lands_on_the_command_that_finished() {
+        let manager = manager_with_session("s1");
+        feed(&manager, "s1", PROMPT);
+        feed(&manager, "s1", "false\r\n\x1b]133;C\x07");
+        feed(&manager, "s1", "\x1b]133;D;1\x07");
+        feed(&manager, "s1", PROMPT);
+        feed(&manager, "s1", "sleep 5\r\n\x1b]133;C\x07");
+
+        let codes = block_exit_codes(&manager, "s1");
+        assert_eq!(codes[0], (BlockPhase::Finished, Some(1)));
+        // The command still running has no exit code yet
+        assert_eq!(codes.last().unwrap().1, None);
+        assert_ne!(codes.last().unwrap().0, BlockPhase::Finished);
+        assert_eq!(manager.get_last_exit_code("s1").unwrap(), Some(1));
+        assert!(manager.get_last_exit_code("missing").is_err());
+    }
+
+    #[test]
+    fn a_finished_block_without_a_code_falls_back_to_the_shell_hooks() {
+        let manager = manager_with_session("s1");
+        feed(&manager, "s1", PROMPT);
+        feed(&manager, "s1", "make\r\n\x1b]133;C\x07");
+        manager.record_exit_code("s1", 2).unwrap();
+        // The shell ended the command without reporting its status
+        feed(&manager, "s1", "\x1b]133;D\x07");
+        feed(&manager, "s1", PROMPT);
+
+        assert_eq!(block_exit_codes(&manager, "s1")[0], (BlockPhase::Finished, None));
+        assert_eq!(manager.get_last_exit_code("s1").unwrap(), Some(2));
+    }
+}
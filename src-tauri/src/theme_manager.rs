@@ -4,6 +4,7 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::fs;
+use crate::accessibility::ColorBlindType;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Color {
@@ -57,6 +58,11 @@ impl Color {
     pub fn to_rgba(&self) -> String {
         format!("rgba({}, {}, {}, {})", self.r, self.g, self.b, self.a)
     }
+
+    pub fn daltonized(&self, color_blind_type: &ColorBlindType) -> Self {
+        let (r, g, b) = crate::accessibility::daltonize_color((self.r, self.g, self.b), color_blind_type);
+        Self::new(r, g, b, self.a)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -121,6 +127,39 @@ pub struct ColorScheme {
 }
 
 impl ColorScheme {
+    pub fn daltonized(&self, color_blind_type: &ColorBlindType) -> Self {
+        let adjust = |color: &Color| color.daltonized(color_blind_type);
+        Self {
+            name: self.name.clone(),
+            is_dark: self.is_dark,
+            foreground: adjust(&self.foreground),
+            background: adjust(&self.background),
+            cursor: adjust(&self.cursor),
+            selection: adjust(&self.selection),
+            black: adjust(&self.black),
+            red: adjust(&self.red),
+            green: adjust(&self.green),
+            yellow: adjust(&self.yellow),
+            blue: adjust(&self.blue),
+            magenta: adjust(&self.magenta),
+            cyan: adjust(&self.cyan),
+            white: adjust(&self.white),
+            bright_black: adjust(&self.bright_black),
+            bright_red: adjust(&self.bright_red),
+            bright_green: adjust(&self.bright_green),
+            bright_yellow: adjust(&self.bright_yellow),
+            bright_blue: adjust(&self.bright_blue),
+            bright_magenta: adjust(&self.bright_magenta),
+            bright_cyan: adjust(&self.bright_cyan),
+            bright_white: adjust(&self.bright_white),
+            accent: adjust(&self.accent),
+            warning: adjust(&self.warning),
+            error: adjust(&self.error),
+            success: adjust(&self.success),
+            info: adjust(&self.info),
+        }
+    }
+
     pub fn default_dark() -> Self {
         Self {
             name: "Default Dark".to_string(),
@@ -487,6 +526,19 @@ impl ThemeManager {
         Ok(())
     }
 
+    /// Switches to the preferred light or dark theme for the app-wide appearance setting.
+    pub fn apply_appearance(&self, appearance: &str) -> Result<(), String> {
+        let theme_id = {
+            let preferences = self.preferences.lock().unwrap();
+            match appearance {
+                "light" => preferences.light_theme_id.clone(),
+                "dark" => preferences.dark_theme_id.clone(),
+                other => return Err(format!("Unknown appearance {}", other)),
+            }
+        };
+        self.set_current_theme(theme_id)
+    }
+
     pub fn add_theme(&self, mut theme: Theme) -> Result<String, String> {
         // Ensure unique ID
         let mut counter = 1;
@@ -612,6 +664,37 @@ impl ThemeManager {
         Ok(theme)
     }
 
+    /// Create a daltonized copy of a theme for `color_blind_type` and register it.
+    pub fn adjust_theme_for_colorblindness(&self, theme_id: &str, color_blind_type: ColorBlindType) -> Result<Theme, String> {
+        if matches!(color_blind_type, ColorBlindType::None) {
+            return Err("No color blindness type selected".to_string());
+        }
+        let base = self.get_theme(theme_id)
+            .ok_or_else(|| format!("Theme {} not found", theme_id))?;
+
+        let label = format!("{:?}", color_blind_type);
+        let mut theme = base.clone();
+        theme.id = format!("{}_{}", base.id, label.to_lowercase());
+        theme.name = format!("{} ({})", base.name, label);
+        theme.description = format!("{} adjusted for {}", base.name, label.to_lowercase());
+        theme.color_scheme = base.color_scheme.daltonized(&color_blind_type);
+        theme.color_scheme.name = format!("{} ({})", base.color_scheme.name, label);
+        for color in theme.ui_colors.values_mut() {
+            *color = color.daltonized(&color_blind_type);
+        }
+        for border in theme.ui_borders.values_mut() {
+            border.color = border.color.daltonized(&color_blind_type);
+        }
+        for shadow in theme.ui_shadows.values_mut() {
+            shadow.color = shadow.color.daltonized(&color_blind_type);
+        }
+
+        // Regenerating replaces the earlier adjusted copy instead of piling up suffixed ids
+        self.themes.lock().unwrap().remove(&theme.id);
+        let new_id = self.add_theme(theme)?;
+        self.get_theme(&new_id).ok_or_else(|| format!("Theme {} not found", new_id))
+    }
+
     pub fn get_preferences(&self) -> ThemePreferences {
         let preferences = self.preferences.lock().unwrap();
         preferences.clone()
@@ -649,6 +732,41 @@ impl ThemeManager {
         }
     }
 
+    /// Themes added by the user; the built-in defaults are always present and never exported.
+    pub fn get_custom_themes(&self) -> Vec<Theme> {
+        let themes = self.themes.lock().unwrap();
+        themes.values().filter(|theme| !Self::is_builtin_theme(&theme.id)).cloned().collect()
+    }
+
+    /// Restore custom themes by id. Without `merge`, existing custom themes are dropped first.
+    pub fn restore_custom_themes(&self, custom_themes: Vec<Theme>, merge: bool) -> usize {
+        let mut count = 0;
+        {
+            let mut themes = self.themes.lock().unwrap();
+            if !merge {
+                themes.retain(|id, _| Self::is_builtin_theme(id));
+            }
+            for theme in custom_themes {
+                if Self::is_builtin_theme(&theme.id) {
+                    continue;
+                }
+                themes.insert(theme.id.clone(), theme);
+                count += 1;
+            }
+        }
+
+        let themes = self.themes.lock().unwrap();
+        let mut preferences = self.preferences.lock().unwrap();
+        if !themes.contains_key(&preferences.current_theme_id) {
+            preferences.current_theme_id = "default_dark".to_string();
+        }
+        count
+    }
+
+    fn is_builtin_theme(theme_id: &str) -> bool {
+        theme_id == "default_dark" || theme_id == "default_light"
+    }
+
     pub fn export_theme(&self, theme_id: &str) -> Result<String, String> {
         let theme = self.get_theme(theme_id)
             .ok_or_else(|| format!("Theme {} not found", theme_id))?;
@@ -0,0 +1,292 @@
+use serde::{Deserialize, Serialize};
+
+use crate::ansi::{CharAttributes, Color, UnderlineStyle};
+use crate::terminal::{entry_text, CommandBlock, ScrollbackEntry, StyleRun, Terminal};
+use crate::theme_manager::ColorScheme;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TranscriptFormat {
+    Plain,
+    Html,
+    AnsiSgr,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptExport {
+    pub path: String,
+    pub format: TranscriptFormat,
+    pub lines: usize,
+    pub bytes: usize,
+}
+
+/// Render a terminal's retained scrollback. Styling comes from the SGR state
+/// recorded with each line; cursor movement isn't retained, so `AnsiSgr` replays
+/// colors and attributes line by line rather than the original byte stream.
+pub fn render(terminal: &Terminal, format: TranscriptFormat, scheme: Option<&ColorScheme>) -> (String, usize) {
+    let entries = terminal.scrollback.entries_with_current();
+    let text = match format {
+        TranscriptFormat::Plain => entries.iter().map(|(_, entry)| plain_text(entry)).collect::<Vec<_>>().join("\n"),
+        TranscriptFormat::AnsiSgr => entries.iter().map(|(_, entry)| sgr_line(entry)).collect::<Vec<_>>().join("\n"),
+        TranscriptFormat::Html => html_document(&entries, &terminal.blocks, scheme),
+    };
+    (text, entries.len())
+}
+
+fn plain_text(entry: &ScrollbackEntry) -> String {
+    entry_text(entry).chars().filter(|c| !c.is_control() || *c == '\t').collect()
+}
+
+// Split a line into (text, attributes) segments, unstyled gaps included. Run
+// offsets count every stored character, so control characters are only dropped
+// after each character has been matched to its run.
+fn segments<'a>(text: &str, runs: &'a [StyleRun]) -> Vec<(String, Option<&'a CharAttributes>)> {
+    let mut segments: Vec<(String, Option<&'a CharAttributes>)> = Vec::new();
+    let mut last_run: Option<Option<usize>> = None;
+    let mut run = 0;
+    for (index, c) in text.chars().enumerate() {
+        while runs.get(run).is_some_and(|r| index >= r.start + r.len) {
+            run += 1;
+        }
+        if c.is_control() && c != '\t' {
+            continue;
+        }
+        let current = runs.get(run).filter(|r| index >= r.start).map(|_| run);
+        match segments.last_mut() {
+            Some((segment, _)) if last_run == Some(current) => segment.push(c),
+            _ => segments.push((c.to_string(), current.map(|i| &runs[i].attributes))),
+        }
+        last_run = Some(current);
+    }
+    segments
+}
+
+// The parser stores the eight basic SGR colors as pure RGB values
+fn basic_color_index(color: &Color) -> Option<u8> {
+    match (color.r, color.g, color.b) {
+        (0, 0, 0) => Some(0),
+        (255, 0, 0) => Some(1),
+        (0, 255, 0) => Some(2),
+        (255, 255, 0) => Some(3),
+        (0, 0, 255) => Some(4),
+        (255, 0, 255) => Some(5),
+        (0, 255, 255) => Some(6),
+        (255, 255, 255) => Some(7),
+        _ => None,
+    }
+}
+
+fn sgr_codes(attributes: &CharAttributes) -> String {
+    let mut codes: Vec<String> = Vec::new();
+    if attributes.bold { codes.push("1".into()); }
+    if attributes.dim { codes.push("2".into()); }
+    if attributes.italic { codes.push("3".into()); }
+    match attributes.underline {
+        UnderlineStyle::None => {}
+        UnderlineStyle::Single => codes.push("4".into()),
+        style => codes.push(format!("4:{}", underline_code(style))),
+    }
+    if attributes.blink { codes.push("5".into()); }
+    if attributes.reverse { codes.push("7".into()); }
+    if attributes.hidden { codes.push("8".into()); }
+    if attributes.strikethrough { codes.push("9".into()); }
+    for (color, base) in [(&attributes.fg_color, 30u8), (&attributes.bg_color, 40u8)] {
+        if let Some(color) = color {
+            codes.push(match basic_color_index(color) {
+                Some(index) => (base + index).to_string(),
+                None => format!("{};2;{};{};{}", base + 8, color.r, color.g, color.b),
+            });
+        }
+    }
+    if let Some(color) = &attributes.underline_color {
+        codes.push(format!("58;2;{};{};{}", color.r, color.g, color.b));
+    }
+    codes.join(";")
+}
+
+fn underline_code(style: UnderlineStyle) -> u8 {
+    match style {
+        UnderlineStyle::None => 0,
+        UnderlineStyle::Single => 1,
+        UnderlineStyle::Double => 2,
+        UnderlineStyle::Curly => 3,
+        UnderlineStyle::Dotted => 4,
+        UnderlineStyle::Dashed => 5,
+    }
+}
+
+fn sgr_line(entry: &ScrollbackEntry) -> String {
+    let ScrollbackEntry::Line(text, runs) = entry else {
+        return plain_text(entry);
+    };
+    segments(text, runs)
+        .into_iter()
+        .map(|(segment, attributes)| match attributes {
+            Some(attributes) => format!("\x1b[{}m{}\x1b[0m", sgr_codes(attributes), segment),
+            None => segment,
+        })
+        .collect()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn css_color(color: &Color, scheme: Option<&ColorScheme>) -> String {
+    let themed = scheme.zip(basic_color_index(color)).map(|(scheme, index)| {
+        [&scheme.black, &scheme.red, &scheme.green, &scheme.yellow, &scheme.blue, &scheme.magenta, &scheme.cyan, &scheme.white][index as usize].to_hex()
+    });
+    themed.unwrap_or_else(|| format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b))
+}
+
+fn html_style(attributes: &CharAttributes, scheme: Option<&ColorScheme>) -> String {
+    let (fg, bg) = if attributes.reverse {
+        (attributes.bg_color.as_ref(), attributes.fg_color.as_ref())
+    } else {
+        (attributes.fg_color.as_ref(), attributes.bg_color.as_ref())
+    };
+    let mut style = Vec::new();
+    match fg {
+        Some(color) => style.push(format!("color:{}", css_color(color, scheme))),
+        None if attributes.reverse => style.push("color:var(--bg)".to_string()),
+        None => {}
+    }
+    match bg {
+        Some(color) => style.push(format!("background:{}", css_color(color, scheme))),
+        None if attributes.reverse => style.push("background:var(--fg)".to_string()),
+        None => {}
+    }
+    if attributes.bold { style.push("font-weight:bold".to_string()); }
+    if attributes.italic { style.push("font-style:italic".to_string()); }
+    if attributes.dim { style.push("opacity:0.5".to_string()); }
+    if attributes.hidden { style.push("visibility:hidden".to_string()); }
+    let underlined = attributes.underline != UnderlineStyle::None;
+    let decorations: Vec<&str> = [(underlined, "underline"), (attributes.strikethrough, "line-through")]
+        .into_iter()
+        .filter_map(|(on, name)| on.then_some(name))
+        .collect();
+    if !decorations.is_empty() {
+        style.push(format!("text-decoration:{}", decorations.join(" ")));
+    }
+    let line_style = match attributes.underline {
+        UnderlineStyle::Double => Some("double"),
+        UnderlineStyle::Curly => Some("wavy"),
+        UnderlineStyle::Dotted => Some("dotted"),
+        UnderlineStyle::Dashed => Some("dashed"),
+        UnderlineStyle::None | UnderlineStyle::Single => None,
+    };
+    if let Some(line_style) = line_style {
+        style.push(format!("text-decoration-style:{}", line_style));
+    }
+    if let Some(color) = attributes.underline_color.as_ref().filter(|_| underlined) {
+        style.push(format!("text-decoration-color:{}", css_color(color, scheme)));
+    }
+    style.join(";")
+}
+
+fn html_line(entry: &ScrollbackEntry, scheme: Option<&ColorScheme>) -> String {
+    let ScrollbackEntry::Line(text, runs) = entry else {
+        return format!("<span class=\"image\">{}</span>", escape_html(&plain_text(entry)));
+    };
+    segments(text, runs)
+        .into_iter()
+        .map(|(segment, attributes)| match attributes {
+            Some(attributes) => format!("<span style=\"{}\">{}</span>", html_style(attributes, scheme), escape_html(&segment)),
+            None => escape_html(&segment),
+        })
+        .collect()
+}
+
+// Lines from a block's prompt up to the end of its output (or the next prompt)
+fn block_for_line(blocks: &[CommandBlock], index: u64) -> Option<usize> {
+    blocks.iter().enumerate().rposition(|(i, block)| {
+        let end = block.output_end_line.or_else(|| blocks.get(i + 1).map(|next| next.prompt_line)).unwrap_or(u64::MAX);
+        block.prompt_line <= index && index < end.max(block.prompt_line + 1)
+    })
+}
+
+/// A standalone page: each command block becomes a `<section>` whose prompt and
+/// command lines are set apart from the output, colored with the theme palette.
+fn html_document(entries: &[(u64, ScrollbackEntry)], blocks: &[CommandBlock], scheme: Option<&ColorScheme>) -> String {
+    let (fg, bg, accent, error) = match scheme {
+        Some(scheme) => (scheme.foreground.to_hex(), scheme.background.to_hex(), scheme.accent.to_hex(), scheme.error.to_hex()),
+        None => ("#d4d4d4".to_string(), "#1e1e1e".to_string(), "#569cd6".to_string(), "#f44747".to_string()),
+    };
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Terminal transcript</title>\n<style>\n\
+         :root {{ --fg: {fg}; --bg: {bg}; --accent: {accent}; --error: {error}; }}\n\
+         body {{ margin: 0; padding: 16px; color: var(--fg); background: var(--bg); font-family: monospace; }}\n\
+         .line {{ white-space: pre-wrap; min-height: 1.2em; }}\n\
+         .block {{ border-left: 3px solid var(--accent); padding-left: 8px; margin: 8px 0; }}\n\
+         .block.failed {{ border-left-color: var(--error); }}\n\
+         .command {{ font-weight: bold; }}\n\
+         .image {{ font-style: italic; opacity: 0.7; }}\n\
+         </style>\n</head>\n<body>\n<div class=\"transcript\">\n"
+    );
+
+    let mut open_block: Option<usize> = None;
+    for (index, entry) in entries {
+        let block = block_for_line(blocks, *index);
+        if block != open_block {
+            if open_block.is_some() {
+                html.push_str("</section>\n");
+            }
+            if let Some(i) = block {
+                let b = &blocks[i];
+                let failed = b.exit_code.map(|code| code != 0).unwrap_or(false);
+                html.push_str(&format!(
+                    "<section class=\"block{}\" data-command=\"{}\"{}>\n",
+                    if failed { " failed" } else { "" },
+                    escape_html(b.command.trim()),
+                    b.exit_code.map(|code| format!(" data-exit-code=\"{}\"", code)).unwrap_or_default(),
+                ));
+            }
+            open_block = block;
+        }
+        let class = match block.map(|i| &blocks[i]) {
+            Some(b) if b.output_start_line.map(|start| *index < start).unwrap_or(true) => "line command",
+            Some(_) => "line output",
+            None => "line",
+        };
+        html.push_str(&format!("<div class=\"{}\">{}</div>\n", class, html_line(entry, scheme)));
+    }
+    if open_block.is_some() {
+        html.push_str("</section>\n");
+    }
+    html.push_str("</div>\n</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pty::TerminalSize;
+
+    fn bold_red() -> CharAttributes {
+        CharAttributes { bold: true, fg_color: Some(Color { r: 255, g: 0, b: 0, a: 255 }), ..CharAttributes::default() }
+    }
+
+    #[test]
+    fn control_characters_do_not_shift_style_runs() {
+        let runs = vec![StyleRun { start: 3, len: 3, attributes: bold_red() }];
+        let entry = ScrollbackEntry::Line("a\u{7}\u{8}red.".to_string(), runs);
+        assert_eq!(html_line(&entry, None), "a<span style=\"color:#ff0000;font-weight:bold\">red</span>.");
+        assert_eq!(sgr_line(&entry), "a\x1b[1;31mred\x1b[0m.");
+    }
+
+    #[test]
+    fn html_export_of_a_small_styled_buffer() {
+        let mut terminal = Terminal::new("test".into(), TerminalSize { rows: 10, cols: 40, pixel_width: 0, pixel_height: 0 }, 1000, 1 << 20);
+        terminal.process_output("\x1b]133;A\x07$ \x1b]133;B\x07ls\r\n\x1b]133;C\x07\x1b[1;31mred\x1b[0m <file>\r\n\x1b]133;D;1\x07");
+        let (html, _) = render(&terminal, TranscriptFormat::Html, None);
+        let body = &html[html.find("<div class=\"transcript\">").unwrap()..];
+        assert_eq!(
+            body,
+            "<div class=\"transcript\">\n\
+             <section class=\"block failed\" data-command=\"ls\" data-exit-code=\"1\">\n\
+             <div class=\"line command\">$ ls</div>\n\
+             <div class=\"line output\"><span style=\"color:#ff0000;font-weight:bold\">red</span> &lt;file&gt;</div>\n\
+             </section>\n\
+             </div>\n</body>\n</html>\n"
+        );
+    }
+}
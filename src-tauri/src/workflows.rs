@@ -1,6 +1,18 @@
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
+use std::{collections::HashMap, fs, path::{Path, PathBuf}, time::{Instant, SystemTime, UNIX_EPOCH}};
 use regex::Regex;
+use tokio::process::Command;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkflowParamType {
+    #[default]
+    String,
+    Enum,
+    Number,
+    Path,
+    Boolean,
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WorkflowParam {
@@ -8,6 +20,12 @@ pub struct WorkflowParam {
     pub description: Option<String>,
     pub required: bool,
     pub default: Option<String>,
+    #[serde(default, rename = "type")]
+    pub param_type: WorkflowParamType,
+    #[serde(default)]
+    pub options: Option<Vec<String>>, // allowed values for enum params
+    #[serde(default)]
+    pub validation: Option<String>, // regex the value must match
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -20,6 +38,91 @@ pub struct Workflow {
     pub tags: Vec<String>,
     pub created_at: u64,
     pub updated_at: u64,
+    #[serde(default)]
+    pub steps: Vec<WorkflowStep>, // when empty, `command` is the only step
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorkflowStep {
+    pub name: Option<String>,
+    pub command: String,
+    #[serde(default)]
+    pub continue_on_error: bool,
+    // e.g. `previous.exit_code == 0`, `build.output contains "warning"`, `always`
+    pub when: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorkflowStepPreview {
+    pub index: usize,
+    pub name: Option<String>,
+    pub command: String,
+    pub when: Option<String>,
+    pub continue_on_error: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum StepStatus {
+    Succeeded,
+    Failed,
+    Skipped, // `when` evaluated to false
+    NotRun,  // an earlier step failed without continue_on_error
+    SentToTerminal,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorkflowStepResult {
+    pub index: usize,
+    pub name: Option<String>,
+    pub command: String,
+    pub status: StepStatus,
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorkflowRunReport {
+    pub workflow_id: String,
+    pub success: bool,
+    pub steps: Vec<WorkflowStepResult>,
+}
+
+const MAX_STEP_OUTPUT: usize = 64 * 1024;
+
+// Shared workflow files in the Warp `.yaml` format
+#[derive(Clone, Debug, Deserialize)]
+struct WarpWorkflow {
+    name: String,
+    command: String,
+    description: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    arguments: Vec<WarpArgument>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct WarpArgument {
+    name: String,
+    description: Option<String>,
+    default_value: Option<serde_yaml::Value>,
+    arg_type: Option<String>,
+    #[serde(default, alias = "enum_variants")]
+    options: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorkflowImportFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorkflowImportReport {
+    pub imported: Vec<Workflow>,
+    pub failed: Vec<WorkflowImportFailure>,
 }
 
 fn now_ms() -> u64 {
@@ -52,16 +155,26 @@ fn ensure_default_file() -> std::io::Result<()> {
                 tags: vec!["files".into()],
                 created_at: now_ms(),
                 updated_at: now_ms(),
+                steps: vec![],
             },
             Workflow {
                 id: uuid::Uuid::new_v4().to_string(),
                 name: "Search in files".into(),
                 description: Some("Search recursively for a pattern".into()),
                 command: "grep -R {{pattern}} .".into(),
-                params: vec![WorkflowParam { name: "pattern".into(), description: Some("Text to search".into()), required: true, default: None }],
+                params: vec![WorkflowParam {
+                    name: "pattern".into(),
+                    description: Some("Text to search".into()),
+                    required: true,
+                    default: None,
+                    param_type: WorkflowParamType::String,
+                    options: None,
+                    validation: None,
+                }],
                 tags: vec!["search".into()],
                 created_at: now_ms(),
                 updated_at: now_ms(),
+                steps: vec![],
             },
         ];
         let json = serde_json::to_string_pretty(&defaults).unwrap();
@@ -82,6 +195,27 @@ pub fn load_all() -> Result<Vec<Workflow>, String> {
     Ok(list)
 }
 
+/// Workflows from the user's file only, without the ones plugins contribute.
+pub fn load_user_workflows() -> Result<Vec<Workflow>, String> {
+    ensure_default_file().map_err(|e| e.to_string())?;
+    let data = fs::read_to_string(workflows_path()).map_err(|e| e.to_string())?;
+    serde_json::from_str(&data).map_err(|e| e.to_string())
+}
+
+/// Restore workflows, either replacing the user's file or merging by id (incoming wins).
+pub fn restore_user_workflows(items: Vec<Workflow>, merge: bool) -> Result<usize, String> {
+    let count = items.len();
+    let mut list = if merge { load_user_workflows()? } else { Vec::new() };
+    for wf in items {
+        match list.iter_mut().find(|existing| existing.id == wf.id) {
+            Some(existing) => *existing = wf,
+            None => list.push(wf),
+        }
+    }
+    save_all(&list)?;
+    Ok(count)
+}
+
 fn save_all(items: &Vec<Workflow>) -> Result<(), String> {
     let json = serde_json::to_string_pretty(items).map_err(|e| e.to_string())?;
     fs::write(workflows_path(), json).map_err(|e| e.to_string())
@@ -120,3 +254,344 @@ pub fn render_command(command: &str, params: &HashMap<String, String>) -> String
         params.get(key).cloned().unwrap_or_else(|| format!("{{{{{}}}}}", key))
     }).to_string()
 }
+
+/// Applies defaults and checks every declared param against its type and
+/// validation regex. On failure the error lists each offending param.
+pub fn resolve_params(wf: &Workflow, values: &HashMap<String, String>) -> Result<HashMap<String, String>, String> {
+    let mut resolved = values.clone();
+    let mut failures = Vec::new();
+
+    for param in &wf.params {
+        let value = values
+            .get(&param.name)
+            .filter(|v| !v.trim().is_empty())
+            .cloned()
+            .or_else(|| param.default.clone());
+
+        let value = match value {
+            Some(v) => v,
+            None => {
+                if param.required {
+                    failures.push(format!("{}: required", param.name));
+                }
+                resolved.remove(&param.name);
+                continue;
+            }
+        };
+
+        match validate_param(param, &value) {
+            Ok(normalized) => { resolved.insert(param.name.clone(), normalized); }
+            Err(e) => failures.push(format!("{}: {}", param.name, e)),
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(resolved)
+    } else {
+        Err(format!("Invalid workflow parameters: {}", failures.join("; ")))
+    }
+}
+
+fn validate_param(param: &WorkflowParam, value: &str) -> Result<String, String> {
+    let normalized = match param.param_type {
+        WorkflowParamType::String => value.to_string(),
+        WorkflowParamType::Enum => {
+            let options = param.options.as_deref().unwrap_or(&[]);
+            if !options.iter().any(|o| o == value) {
+                return Err(format!("expected one of [{}], got '{}'", options.join(", "), value));
+            }
+            value.to_string()
+        }
+        WorkflowParamType::Number => {
+            value.trim().parse::<f64>().map_err(|_| format!("expected a number, got '{}'", value))?;
+            value.trim().to_string()
+        }
+        WorkflowParamType::Path => {
+            if value.contains('\0') {
+                return Err("path contains a NUL byte".into());
+            }
+            value.to_string()
+        }
+        WorkflowParamType::Boolean => match value.trim().to_lowercase().as_str() {
+            "true" | "yes" | "1" | "on" => "true".to_string(),
+            "false" | "no" | "0" | "off" => "false".to_string(),
+            _ => return Err(format!("expected true or false, got '{}'", value)),
+        },
+    };
+
+    if let Some(pattern) = &param.validation {
+        let re = Regex::new(pattern).map_err(|e| format!("invalid validation regex: {}", e))?;
+        if !re.is_match(&normalized) {
+            return Err(format!("'{}' does not match {}", normalized, pattern));
+        }
+    }
+    Ok(normalized)
+}
+
+/// Renders every step of a workflow without running anything.
+pub fn preview_steps(wf: &Workflow, params: &HashMap<String, String>) -> Vec<WorkflowStepPreview> {
+    wf.steps
+        .iter()
+        .enumerate()
+        .map(|(index, step)| WorkflowStepPreview {
+            index,
+            name: step.name.clone(),
+            command: render_command(&step.command, params),
+            when: step.when.clone(),
+            continue_on_error: step.continue_on_error,
+        })
+        .collect()
+}
+
+/// Runs the steps one after another in a non-interactive shell, capturing
+/// their output so later `when` conditions can inspect it.
+pub async fn run_steps(wf: &Workflow, params: &HashMap<String, String>, working_dir: Option<String>) -> WorkflowRunReport {
+    let mut results: Vec<WorkflowStepResult> = Vec::new();
+    let mut stopped = false;
+
+    for (index, step) in wf.steps.iter().enumerate() {
+        let command = render_command(&step.command, params);
+        let mut result = WorkflowStepResult {
+            index,
+            name: step.name.clone(),
+            command: command.clone(),
+            status: StepStatus::NotRun,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            duration_ms: 0,
+        };
+
+        if stopped {
+            results.push(result);
+            continue;
+        }
+
+        match step.when.as_deref().map(|w| evaluate_condition(w, &wf.steps, &results)) {
+            Some(Ok(false)) => {
+                result.status = StepStatus::Skipped;
+                results.push(result);
+                continue;
+            }
+            Some(Err(e)) => {
+                result.status = StepStatus::Failed;
+                result.stderr = format!("Invalid condition: {}", e);
+            }
+            _ => {
+                let started = Instant::now();
+                match run_shell(&command, working_dir.as_deref()).await {
+                    Ok(output) => {
+                        result.exit_code = output.status.code();
+                        result.stdout = truncate_output(&output.stdout);
+                        result.stderr = truncate_output(&output.stderr);
+                        result.status = if output.status.success() { StepStatus::Succeeded } else { StepStatus::Failed };
+                    }
+                    Err(e) => {
+                        result.status = StepStatus::Failed;
+                        result.stderr = e.to_string();
+                    }
+                }
+                result.duration_ms = started.elapsed().as_millis() as u64;
+            }
+        }
+
+        if result.status == StepStatus::Failed && !step.continue_on_error {
+            stopped = true;
+        }
+        results.push(result);
+    }
+
+    WorkflowRunReport {
+        workflow_id: wf.id.clone(),
+        success: !stopped,
+        steps: results,
+    }
+}
+
+async fn run_shell(command: &str, working_dir: Option<&str>) -> std::io::Result<std::process::Output> {
+    let mut cmd = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C").arg(command);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c").arg(command);
+        c
+    };
+    if let Some(dir) = working_dir {
+        cmd.current_dir(dir);
+    }
+    cmd.output().await
+}
+
+fn truncate_output(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    if text.len() <= MAX_STEP_OUTPUT {
+        return text.into_owned();
+    }
+    let mut end = MAX_STEP_OUTPUT;
+    while !text.is_char_boundary(end) { end -= 1; }
+    text[..end].to_string()
+}
+
+/// Supported forms:
+///   always | success | failure             (relative to the previous step)
+///   <step>.exit_code (==|!=) <n>
+///   <step>.output (contains|matches) <text>
+/// where <step> is a step name, a zero-based index or `previous`.
+fn evaluate_condition(condition: &str, steps: &[WorkflowStep], results: &[WorkflowStepResult]) -> Result<bool, String> {
+    let condition = condition.trim();
+    let previous = results.iter().rev().find(|r| r.status == StepStatus::Succeeded || r.status == StepStatus::Failed);
+
+    match condition {
+        "always" => return Ok(true),
+        "success" => return Ok(previous.map(|r| r.status == StepStatus::Succeeded).unwrap_or(true)),
+        "failure" => return Ok(previous.map(|r| r.status == StepStatus::Failed).unwrap_or(false)),
+        _ => {}
+    }
+
+    let re = Regex::new(r#"^([A-Za-z0-9_\-]+)\.(exit_code|output)\s*(==|!=|contains|matches)\s*(.+)$"#).unwrap();
+    let caps = re.captures(condition).ok_or_else(|| format!("cannot parse '{}'", condition))?;
+    let target = &caps[1];
+    let field = &caps[2];
+    let op = &caps[3];
+    let operand = caps[4].trim().trim_matches('"');
+
+    let result = if target == "previous" {
+        previous
+    } else if let Ok(index) = target.parse::<usize>() {
+        results.iter().find(|r| r.index == index)
+    } else {
+        let index = steps
+            .iter()
+            .position(|s| s.name.as_deref() == Some(target))
+            .ok_or_else(|| format!("unknown step '{}'", target))?;
+        results.iter().find(|r| r.index == index)
+    };
+    // A step that never ran has no exit code or output to compare
+    let result = match result {
+        Some(r) if r.status == StepStatus::Succeeded || r.status == StepStatus::Failed => r,
+        _ => return Ok(false),
+    };
+
+    match (field, op) {
+        ("exit_code", "==" | "!=") => {
+            let expected: i32 = operand.parse().map_err(|_| format!("'{}' is not an exit code", operand))?;
+            let equal = result.exit_code == Some(expected);
+            Ok(if op == "==" { equal } else { !equal })
+        }
+        ("output", "contains") => Ok(result.stdout.contains(operand) || result.stderr.contains(operand)),
+        ("output", "matches") => {
+            let re = Regex::new(operand).map_err(|e| e.to_string())?;
+            Ok(re.is_match(&result.stdout) || re.is_match(&result.stderr))
+        }
+        _ => Err(format!("operator '{}' is not valid for {}", op, field)),
+    }
+}
+
+/// Parses a Warp-format workflow file (a single workflow or a list) and saves
+/// each entry. Re-importing a workflow with the same name updates it in place.
+pub fn import_workflow_yaml(yaml: &str) -> Result<Vec<Workflow>, String> {
+    let parsed: Vec<WarpWorkflow> = match serde_yaml::from_str::<WarpWorkflow>(yaml) {
+        Ok(w) => vec![w],
+        Err(single_err) => serde_yaml::from_str::<Vec<WarpWorkflow>>(yaml).map_err(|_| single_err.to_string())?,
+    };
+
+    let existing = load_all()?;
+    let mut imported = Vec::new();
+    for warp in parsed {
+        let mut wf = from_warp(warp)?;
+        if let Some(prev) = existing.iter().find(|w| w.name == wf.name) {
+            wf.id = prev.id.clone();
+            wf.created_at = prev.created_at;
+        }
+        imported.push(upsert(wf)?);
+    }
+    Ok(imported)
+}
+
+/// Imports every `.yaml`/`.yml` file under `path`. Files that fail to parse
+/// are reported instead of aborting the rest of the import.
+pub fn import_workflows_from_directory(path: &str) -> Result<WorkflowImportReport, String> {
+    let root = Path::new(path);
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", path));
+    }
+
+    let mut files = Vec::new();
+    collect_yaml_files(root, &mut files);
+    files.sort();
+
+    let mut report = WorkflowImportReport { imported: Vec::new(), failed: Vec::new() };
+    for file in files {
+        let result = fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|yaml| import_workflow_yaml(&yaml));
+        match result {
+            Ok(mut wfs) => report.imported.append(&mut wfs),
+            Err(error) => report.failed.push(WorkflowImportFailure { path: file.to_string_lossy().to_string(), error }),
+        }
+    }
+    Ok(report)
+}
+
+fn collect_yaml_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for e in entries.flatten() {
+            let path = e.path();
+            if path.is_dir() {
+                collect_yaml_files(&path, out);
+            } else if matches!(path.extension().and_then(|x| x.to_str()), Some("yaml") | Some("yml")) {
+                out.push(path);
+            }
+        }
+    }
+}
+
+fn from_warp(warp: WarpWorkflow) -> Result<Workflow, String> {
+    if warp.name.trim().is_empty() || warp.command.trim().is_empty() {
+        return Err("Workflow needs a name and a command".into());
+    }
+
+    let params = warp
+        .arguments
+        .into_iter()
+        .map(|arg| {
+            let param_type = match arg.arg_type.as_deref().map(|t| t.to_lowercase()) {
+                Some(t) if t == "enum" => WorkflowParamType::Enum,
+                Some(t) if t == "number" => WorkflowParamType::Number,
+                Some(t) if t == "path" => WorkflowParamType::Path,
+                Some(t) if t == "boolean" || t == "bool" => WorkflowParamType::Boolean,
+                _ if arg.options.is_some() => WorkflowParamType::Enum,
+                _ => WorkflowParamType::String,
+            };
+            // default_value may be written as a bare number or bool in YAML
+            let default = arg.default_value.and_then(|v| match v {
+                serde_yaml::Value::Null => None,
+                serde_yaml::Value::String(s) => Some(s),
+                other => serde_yaml::to_string(&other).ok().map(|s| s.trim().to_string()),
+            });
+            WorkflowParam {
+                name: arg.name,
+                description: arg.description,
+                required: default.is_none(),
+                default,
+                param_type,
+                options: arg.options,
+                validation: None,
+            }
+        })
+        .collect();
+
+    Ok(Workflow {
+        id: String::new(),
+        name: warp.name,
+        description: warp.description,
+        command: warp.command,
+        params,
+        tags: warp.tags,
+        created_at: 0,
+        updated_at: 0,
+        steps: vec![],
+    })
+}